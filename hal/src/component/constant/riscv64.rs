@@ -5,9 +5,15 @@ impl ConstantsHal for Constant {
 
     const KERNEL_ADDR_SPACE: core::ops::Range<usize> = 0xffff_ffc0_0000_0000..0xffff_ffff_ffff_ffff;
 
+    #[cfg(not(feature = "sv48"))]
     const USER_ADDR_SPACE: core::ops::Range<usize> = 0x0000_0000_0000_0000..0x0000_0040_0000_0000;
+    #[cfg(feature = "sv48")]
+    const USER_ADDR_SPACE: core::ops::Range<usize> = 0x0000_0000_0000_0000..0x0000_8000_0000_0000;
 
+    #[cfg(not(feature = "sv48"))]
     const VA_WIDTH: usize = 39;
+    #[cfg(feature = "sv48")]
+    const VA_WIDTH: usize = 48;
 
     const PA_WIDTH: usize = 56;
 
@@ -15,7 +21,10 @@ impl ConstantsHal for Constant {
 
     const PAGE_SIZE_BITS: usize = 12;
 
+    #[cfg(not(feature = "sv48"))]
     const PG_LEVEL: usize = 3;
+    #[cfg(feature = "sv48")]
+    const PG_LEVEL: usize = 4;
     
     const PTE_WIDTH: usize = 64;
     
@@ -37,11 +46,17 @@ impl ConstantsHal for Constant {
 
     // put the file mmap area under user stack
     const USER_FILE_END: usize = Self::USER_STACK_BOTTOM - Self::PAGE_SIZE;
+    #[cfg(not(feature = "sv48"))]
     const USER_FILE_SIZE: usize = 0x2_0000_0000;
+    #[cfg(feature = "sv48")]
+    const USER_FILE_SIZE: usize = 0x100_0000_0000;
 
     // put the share mmap area under file mmap area
     const USER_SHARE_END: usize = Self::USER_FILE_BEG  - Self::PAGE_SIZE;
+    #[cfg(not(feature = "sv48"))]
     const USER_SHARE_SIZE: usize = 0x2_0000_0000;
+    #[cfg(feature = "sv48")]
+    const USER_SHARE_SIZE: usize = 0x1000_0000_0000;
 
     const DL_INTERP_OFFSET: usize = 0x20_0000_0000;
 }
\ No newline at end of file
@@ -0,0 +1,97 @@
+//! loongarch64 implementation for cpu HAL
+//!
+//! Features are read from the `CPUCFG` configuration words.
+
+use alloc::string::String;
+
+use super::{Cpu, CpuHal, Feature};
+
+const CPUCFG2_FP_SP: usize = 1 << 1;
+const CPUCFG2_FP_DP: usize = 1 << 2;
+const CPUCFG2_LSX: usize = 1 << 6;
+const CPUCFG2_LAM: usize = 1 << 22;
+
+const PRID_COMP_LOONGSON: usize = 0x14;
+const PRID_SERIES_MASK: usize = 0xf000;
+
+const HWCAP_LOONGARCH_CPUCFG: usize = 1 << 0;
+const HWCAP_LOONGARCH_LAM: usize = 1 << 1;
+const HWCAP_LOONGARCH_FPU: usize = 1 << 3;
+const HWCAP_LOONGARCH_LSX: usize = 1 << 4;
+
+fn cpucfg(word: usize) -> usize {
+    let ret: usize;
+    unsafe {
+        core::arch::asm!(
+            "cpucfg {0}, {1}",
+            out(reg) ret,
+            in(reg) word,
+        );
+    }
+    ret
+}
+
+impl CpuHal for Cpu {
+    fn probe() -> usize {
+        let cfg2 = cpucfg(2);
+        let mut ret = Feature::M.bit();
+        if cfg2 & CPUCFG2_LAM != 0 {
+            ret |= Feature::A.bit();
+        }
+        if cfg2 & CPUCFG2_FP_SP != 0 {
+            ret |= Feature::F.bit();
+        }
+        if cfg2 & CPUCFG2_FP_DP != 0 {
+            ret |= Feature::D.bit();
+        }
+        if cfg2 & CPUCFG2_LSX != 0 {
+            ret |= Feature::V.bit();
+        }
+        ret
+    }
+
+    fn hwcap(features: usize) -> usize {
+        let mut ret = HWCAP_LOONGARCH_CPUCFG;
+        if features & Feature::A.bit() != 0 {
+            ret |= HWCAP_LOONGARCH_LAM;
+        }
+        if features & (Feature::F.bit() | Feature::D.bit()) != 0 {
+            ret |= HWCAP_LOONGARCH_FPU;
+        }
+        if features & Feature::V.bit() != 0 {
+            ret |= HWCAP_LOONGARCH_LSX;
+        }
+        ret
+    }
+
+    fn isa_string(features: usize) -> String {
+        let mut ret = String::from("loongarch64");
+        if features & Feature::D.bit() != 0 {
+            ret += " fpu";
+        }
+        if features & Feature::A.bit() != 0 {
+            ret += " lam";
+        }
+        if features & Feature::V.bit() != 0 {
+            ret += " lsx";
+        }
+        ret
+    }
+
+    /// the core series in the processor id of `CPUCFG` word 0
+    fn model_name() -> Option<String> {
+        let prid = cpucfg(0);
+        if (prid >> 16) & 0xff != PRID_COMP_LOONGSON {
+            return None;
+        }
+        let series = match prid & PRID_SERIES_MASK {
+            0x8000 => "LA132",
+            0xa000 => "LA264",
+            0xb000 => "LA364",
+            0xc000 => "LA464",
+            0xd000 => "LA664",
+            _ => return Some(String::from("Loongson-64bit")),
+        };
+        Some(String::from("Loongson-") + series)
+    }
+}
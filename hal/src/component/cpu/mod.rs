@@ -0,0 +1,91 @@
+//! CPU feature detection
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use alloc::string::String;
+
+/// CPU features the kernel cares about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// integer multiply/divide
+    M,
+    /// atomic instructions
+    A,
+    /// single-precision float
+    F,
+    /// double-precision float
+    D,
+    /// compressed instructions
+    C,
+    /// vector instructions (RVV on riscv64, LSX on loongarch64)
+    V,
+}
+
+impl Feature {
+    const fn bit(self) -> usize {
+        1 << (self as usize)
+    }
+}
+
+pub trait CpuHal {
+    /// probe the features of current cpu, return the feature bits
+    fn probe() -> usize;
+    /// translate feature bits into the value of `AT_HWCAP`
+    fn hwcap(features: usize) -> usize;
+    /// isa string shown in `/proc/cpuinfo`
+    fn isa_string(features: usize) -> String;
+    /// model of the cpu shown in `/proc/cpuinfo`, if the hardware tells it
+    fn model_name() -> Option<String>;
+}
+
+pub struct Cpu;
+
+static FEATURES: AtomicUsize = AtomicUsize::new(0);
+static PROBED: AtomicBool = AtomicBool::new(false);
+
+/// probe and cache cpu features, should be called by the boot processor
+pub fn init() {
+    FEATURES.store(Cpu::probe(), Ordering::Release);
+    PROBED.store(true, Ordering::Release);
+}
+
+fn features() -> usize {
+    if !PROBED.load(Ordering::Acquire) {
+        init();
+    }
+    FEATURES.load(Ordering::Acquire)
+}
+
+/// query whether the cpu has the given feature
+pub fn cpu_has(feature: Feature) -> bool {
+    features() & feature.bit() != 0
+}
+
+/// value of `AT_HWCAP` in the aux vector
+pub fn hwcap() -> usize {
+    Cpu::hwcap(features())
+}
+
+/// isa string of the cpu, e.g. `rv64imafdc`
+pub fn isa_string() -> String {
+    Cpu::isa_string(features())
+}
+
+/// model of the cpu, e.g. `sifive,u74-mc` or `Loongson-LA464`
+pub fn model_name() -> Option<String> {
+    Cpu::model_name()
+}
+
+#[cfg(target_arch = "riscv64")]
+mod riscv64;
+
+#[cfg(target_arch = "riscv64")]
+#[allow(unused)]
+pub use riscv64::*;
+
+#[cfg(target_arch = "loongarch64")]
+mod loongarch64;
+
+#[cfg(target_arch = "loongarch64")]
+#[allow(unused)]
+pub use loongarch64::*;
@@ -0,0 +1,122 @@
+//! riscv64 implementation for cpu HAL
+//!
+//! `misa` is only readable in M-mode, so the supervisor learns the
+//! extensions from the `riscv,isa` string that the firmware puts into
+//! the device tree. If the device tree does not tell, we fall back to
+//! what the kernel itself is built for (rv64gc).
+
+use alloc::string::String;
+
+use crate::board::get_device_tree_addr;
+
+use super::{Cpu, CpuHal, Feature};
+
+/// the kernel is built for rv64gc, so these must be present
+const BUILTIN: &[Feature] = &[Feature::M, Feature::A, Feature::F, Feature::D, Feature::C];
+
+fn letter_to_feature(c: u8) -> Option<Feature> {
+    match c.to_ascii_lowercase() {
+        b'm' => Some(Feature::M),
+        b'a' => Some(Feature::A),
+        b'f' => Some(Feature::F),
+        b'd' => Some(Feature::D),
+        b'c' => Some(Feature::C),
+        b'v' => Some(Feature::V),
+        _ => None,
+    }
+}
+
+fn parse_isa(isa: &str) -> usize {
+    let mut ret = 0;
+    let isa = isa.trim_end_matches('\0');
+    let Some(base) = isa.strip_prefix("rv64").or_else(|| isa.strip_prefix("rv32")) else {
+        return 0;
+    };
+    // single letter extensions end at the first multi-letter one
+    let singles = base.split('_').next().unwrap_or("");
+    for c in singles.bytes() {
+        if c == b'g' {
+            for f in [Feature::M, Feature::A, Feature::F, Feature::D] {
+                ret |= f.bit();
+            }
+        } else if let Some(f) = letter_to_feature(c) {
+            ret |= f.bit();
+        }
+    }
+    ret
+}
+
+fn probe_device_tree() -> usize {
+    let Ok(fdt) = (unsafe { fdt::Fdt::from_ptr(get_device_tree_addr() as *const u8) }) else {
+        return 0;
+    };
+    let Some(cpu) = fdt.cpus().next() else {
+        return 0;
+    };
+    let mut ret = 0;
+    if let Some(isa) = cpu.property("riscv,isa").and_then(|p| p.as_str()) {
+        ret |= parse_isa(isa);
+    }
+    // newer firmware lists the extensions one by one
+    if let Some(exts) = cpu.property("riscv,isa-extensions") {
+        for ext in exts.value.split(|&b| b == 0) {
+            if ext.len() == 1 {
+                if let Some(f) = letter_to_feature(ext[0]) {
+                    ret |= f.bit();
+                }
+            }
+        }
+    }
+    ret
+}
+
+impl CpuHal for Cpu {
+    fn probe() -> usize {
+        let mut ret = probe_device_tree();
+        for f in BUILTIN {
+            ret |= f.bit();
+        }
+        ret
+    }
+
+    fn hwcap(features: usize) -> usize {
+        // COMPAT_HWCAP_ISA_x = 1 << ('x' - 'a')
+        let mut ret = 1 << (b'i' - b'a');
+        for (f, c) in [
+            (Feature::M, b'm'), (Feature::A, b'a'), (Feature::F, b'f'),
+            (Feature::D, b'd'), (Feature::C, b'c'), (Feature::V, b'v'),
+        ] {
+            if features & f.bit() != 0 {
+                ret |= 1 << (c - b'a');
+            }
+        }
+        ret
+    }
+
+    fn isa_string(features: usize) -> String {
+        let mut ret = String::from("rv64i");
+        for (f, c) in [
+            (Feature::M, 'm'), (Feature::A, 'a'), (Feature::F, 'f'),
+            (Feature::D, 'd'), (Feature::C, 'c'), (Feature::V, 'v'),
+        ] {
+            if features & f.bit() != 0 {
+                ret.push(c);
+            }
+        }
+        ret
+    }
+
+    /// the micro architecture the device tree names the first cpu after,
+    /// the first of its `compatible` strings unless it is just "riscv"
+    fn model_name() -> Option<String> {
+        let fdt = unsafe { fdt::Fdt::from_ptr(get_device_tree_addr() as *const u8) }.ok()?;
+        let cpu = fdt.cpus().next()?;
+        let compatible = cpu.property("compatible")?;
+        let first = compatible.value.split(|&b| b == 0).next()?;
+        let first = core::str::from_utf8(first).ok()?;
+        if first.is_empty() || first == "riscv" {
+            return None;
+        }
+        Some(String::from(first))
+    }
+}
@@ -55,6 +55,7 @@ pub(crate) fn rust_main(id: usize) {
     if is_first {
         super::clear_bss();
         crate::console::init();
+        crate::cpu::init();
         print_info();
         let _ = unsafe { super::_main_for_arch(id, true) };
     } else {
@@ -1,11 +1,12 @@
 use core::{arch::global_asm, sync::atomic::Ordering};
-use crate::{constant::{Constant, ConstantsHal}, entry::BOOT_STACK, instruction::{Instruction, InstructionHal}, println, timer::{Timer, TimerHal}};
+use crate::{constant::{Constant, ConstantsHal}, entry::BOOT_STACK, pagetable::SATP_MODE, instruction::{Instruction, InstructionHal}, println, timer::{Timer, TimerHal}};
 
 use super::RUNNING_PROCESSOR;
 
 #[repr(C, align(4096))]
 pub struct BootPageTable([u64; Constant::PTES_PER_PAGE]);
 
+#[cfg(not(feature = "sv48"))]
 pub static mut BOOT_PAGE_TABLE: BootPageTable = {
     let mut arr: [u64; Constant::PTES_PER_PAGE] = [0; Constant::PTES_PER_PAGE];
     arr[2] = (0x80000 << 10) | 0xcf;
@@ -14,6 +15,47 @@ pub static mut BOOT_PAGE_TABLE: BootPageTable = {
     BootPageTable(arr)
 };
 
+/// sv48 root: a 512G leaf keeps the low memory identity mapped, the last entry
+/// is pointed at `BOOT_PAGE_TABLE_HIGH` by `_start` as its address is only known at link time
+#[cfg(feature = "sv48")]
+pub static mut BOOT_PAGE_TABLE: BootPageTable = {
+    let mut arr: [u64; Constant::PTES_PER_PAGE] = [0; Constant::PTES_PER_PAGE];
+    arr[0] = (0x00000 << 10) | 0xcf;
+    BootPageTable(arr)
+};
+
+/// sv48: 1G leaves of the kernel half, the same ones sv39 has in its root
+#[cfg(feature = "sv48")]
+#[unsafe(no_mangle)]
+pub static mut BOOT_PAGE_TABLE_HIGH: BootPageTable = {
+    let mut arr: [u64; Constant::PTES_PER_PAGE] = [0; Constant::PTES_PER_PAGE];
+    arr[256] = (0x00000 << 10) | 0xcf;
+    arr[258] = (0x80000 << 10) | 0xcf;
+    BootPageTable(arr)
+};
+
+/// hook `BOOT_PAGE_TABLE_HIGH` into the last root entry as a non-leaf entry
+#[cfg(feature = "sv48")]
+macro_rules! link_boot_page_table {
+    () => {
+        "
+            la      t0, BOOT_PAGE_TABLE_HIGH
+            srli    t0, t0, 12
+            slli    t0, t0, 10
+            ori     t0, t0, 1
+            la      t1, {page_table}
+            li      t2, 511 * 8
+            add     t1, t1, t2
+            sd      t0, 0(t1)
+        "
+    };
+}
+
+#[cfg(not(feature = "sv48"))]
+macro_rules! link_boot_page_table {
+    () => { "" };
+}
+
 const VIRT_RAM_OFFSET: usize = Constant::KERNEL_ADDR_SPACE.start;
 
 #[naked]
@@ -33,12 +75,13 @@ unsafe extern "C" fn _start(id: usize) -> ! {
             la      sp, {boot_stack}
             add     sp, sp, t0                // set boot stack
         ",
-        // 2. enable sv39 page table
-        // satp = (8 << 60) | PPN(page_table)
+        // 2. enable sv39 or sv48 page table
+        // satp = (SATP_MODE << 60) | PPN(page_table)
+        link_boot_page_table!(),
         "
             la      t0, {page_table}
             srli    t0, t0, 12
-            li      t1, 8 << 60
+            li      t1, {satp_mode} << 60
             or      t0, t0, t1
             csrw    satp, t0
             sfence.vma
@@ -62,6 +105,7 @@ unsafe extern "C" fn _start(id: usize) -> ! {
         page_table = sym BOOT_PAGE_TABLE,
         entry = sym rust_main,
         virt_ram_offset = const VIRT_RAM_OFFSET,
+        satp_mode = const SATP_MODE,
     )
 }
 
@@ -70,6 +114,7 @@ pub(crate) fn rust_main(id: usize) {
     if RUNNING_PROCESSOR.fetch_add(1, Ordering::AcqRel) == 0 {
         super::clear_bss();
         crate::console::init();
+        crate::cpu::init();
         print_info();
         let _ = unsafe { super::_main_for_arch(id, true) };
     } else {
@@ -86,7 +131,7 @@ pub(crate) fn rust_main(id: usize) {
 fn print_info() {
     println!("\u{1B}[36m\n{}\u{1B}[0m", super::BANNER);
     println!("[CINPHAL] PA_LEN: {}", 56);
-    println!("[CINPHAL] VA_LEN: {}", 39);
+    println!("[CINPHAL] VA_LEN: {}", Constant::VA_WIDTH);
     println!("[CINPHAL] Frequency: {} Hz", Timer::get_timer_freq());
     println!("[CINPHAL] start address: {:#x}", _start as usize);
     println!("");
@@ -1,26 +1,113 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
 use loongArch64::register::{self, ecfg::LineBasedInterrupt};
 
-use crate::{constant::{Constant, ConstantsHal}, println, trap::FP_REG_DIRTY};
+use crate::{board::MAX_PROCESSORS, constant::{Constant, ConstantsHal}, println, trap::FP_REG_DIRTY};
 
 const POWEROFF_REG_MMIO: usize = 0x8000_0000_100e_001c;
 const POWEROFF_VALUE: u8 = 0x34;
 
 
-use super::{Instruction, InstructionHal};
+use super::{Instruction, InstructionHal, TLB_FLUSH_STATS};
+
+/// CSR.ASID holds the current asid in its low bits and their width in bits 16..24
+const CSR_ASID_MASK: usize = 0x3ff;
+
+/// iocsr registers of the interprocessor interrupts
+const IOCSR_IPI_STATUS: usize = 0x1000;
+const IOCSR_IPI_EN: usize = 0x1004;
+const IOCSR_IPI_CLEAR: usize = 0x100c;
+/// the ipi vector asking for a TLB flush, vector 1 starts a hart
+const IPI_TLB_SHOOTDOWN: u32 = 2;
+/// the ipi vector asking for a memory barrier
+const IPI_FENCE: u32 = 3;
+
+/// what each hart is asked to flush: 0 for nothing, `asid + 1` for one address space,
+/// [`SHOOTDOWN_ALL`] for the whole TLB
+static SHOOTDOWN: [AtomicUsize; MAX_PROCESSORS] = [const { AtomicUsize::new(0) }; MAX_PROCESSORS];
+const SHOOTDOWN_ALL: usize = usize::MAX;
+/// requests made of each hart so far and requests it has flushed for, a requester
+/// waits until the second reaches the number of its own request
+static SHOOTDOWN_SEQ: [AtomicUsize; MAX_PROCESSORS] = [const { AtomicUsize::new(0) }; MAX_PROCESSORS];
+static SHOOTDOWN_DONE: [AtomicUsize; MAX_PROCESSORS] = [const { AtomicUsize::new(0) }; MAX_PROCESSORS];
+
+/// set while a hart is asked for a memory barrier, cleared by the hart once done
+static FENCE: [AtomicBool; MAX_PROCESSORS] = [const { AtomicBool::new(false) }; MAX_PROCESSORS];
+
+/// do the memory barrier asked of this hart, if any
+fn take_fence(hart: usize) {
+    if FENCE[hart].load(Ordering::Acquire) {
+        unsafe { core::arch::asm!("dbar 0", options(nostack)) };
+        FENCE[hart].store(false, Ordering::Release);
+    }
+}
+
+/// do the TLB flush asked of this hart, if any
+fn take_shootdown(hart: usize) {
+    // a request numbered up to `seq` is in the slot before it is swapped out
+    let seq = SHOOTDOWN_SEQ[hart].load(Ordering::Acquire);
+    match SHOOTDOWN[hart].swap(0, Ordering::AcqRel) {
+        0 => {}
+        SHOOTDOWN_ALL => unsafe { Instruction::tlb_flush_all() },
+        asid => unsafe { Instruction::tlb_flush_asid(asid - 1) },
+    }
+    SHOOTDOWN_DONE[hart].fetch_max(seq, Ordering::AcqRel);
+}
+
+unsafe fn iocsr_read_w(reg: usize) -> u32 {
+    let val: u32;
+    core::arch::asm!("iocsrrd.w {}, {}", out(reg) val, in(reg) reg, options(nostack));
+    val
+}
+
+unsafe fn iocsr_write_w(reg: usize, val: u32) {
+    core::arch::asm!("iocsrwr.w {}, {}", in(reg) val, in(reg) reg, options(nostack));
+}
+
+/// let this hart take interprocessor interrupts
+pub fn enable_ipi() {
+    unsafe { iocsr_write_w(IOCSR_IPI_EN, u32::MAX) };
+    let lie = register::ecfg::read().lie();
+    register::ecfg::set_lie(lie | LineBasedInterrupt::IPI);
+}
+
+/// acknowledge the interprocessor interrupts of this hart and do the flushes asked for
+pub fn handle_ipi() {
+    unsafe {
+        let status = iocsr_read_w(IOCSR_IPI_STATUS);
+        iocsr_write_w(IOCSR_IPI_CLEAR, status);
+    }
+    let hart = register::cpuid::read().core_id();
+    take_fence(hart);
+    take_shootdown(hart);
+}
+
+fn csr_asid() -> usize {
+    let asid: usize;
+    unsafe {
+        core::arch::asm!("csrrd {}, 0x18", out(reg) asid, options(nostack));
+    }
+    asid
+}
 
 impl InstructionHal for Instruction {
     unsafe fn tlb_flush_addr(vaddr: usize) {
+        TLB_FLUSH_STATS.addr.fetch_add(1, Ordering::Relaxed);
+        // entries of the current asid and global ones
+        let asid = csr_asid() & CSR_ASID_MASK;
         core::arch::asm!(
             r"
             dbar 0
-            invtlb 0x5, $zero, {0}
+            invtlb 0x6, {0}, {1}
             ", 
+            in(reg) asid,
             in(reg) vaddr, 
             options(nostack)
         );
     }
 
     unsafe fn tlb_flush_all() {
+        TLB_FLUSH_STATS.all.fetch_add(1, Ordering::Relaxed);
         core::arch::asm!(
             r"
             dbar 0
@@ -30,6 +117,77 @@ impl InstructionHal for Instruction {
         );
     }
 
+    unsafe fn tlb_flush_asid(asid: usize) {
+        TLB_FLUSH_STATS.asid.fetch_add(1, Ordering::Relaxed);
+        core::arch::asm!(
+            r"
+            dbar 0
+            invtlb 0x4, {0}, $zero
+            ",
+            in(reg) asid,
+            options(nostack)
+        );
+    }
+
+    unsafe fn tlb_shootdown(harts: usize, asid: usize, _start: usize, _len: usize) {
+        TLB_FLUSH_STATS.remote.fetch_add(1, Ordering::Relaxed);
+        // invtlb only reaches this hart, the others flush the whole address space
+        // when the interrupt arrives, or while they spin with interrupts off
+        // on a lock the caller may hold, see `handle_remote_requests`
+        let this = register::cpuid::read().core_id();
+        let request = if asid == 0 { SHOOTDOWN_ALL } else { asid + 1 };
+        let mut waits = [0usize; MAX_PROCESSORS];
+        for hart in (0..MAX_PROCESSORS).filter(|hart| harts & (1 << hart) != 0 && *hart != this) {
+            let _ = SHOOTDOWN[hart].fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                Some(if pending == 0 || pending == request { request } else { SHOOTDOWN_ALL })
+            });
+            waits[hart] = SHOOTDOWN_SEQ[hart].fetch_add(1, Ordering::AcqRel) + 1;
+            loongArch64::ipi::send_ipi_single(hart, IPI_TLB_SHOOTDOWN);
+        }
+        // the frames are freed once this returns, no hart may translate to them then;
+        // a hart shooting down at this one at the same time is served while waiting
+        while (0..MAX_PROCESSORS).any(|hart| waits[hart] > SHOOTDOWN_DONE[hart].load(Ordering::Acquire)) {
+            take_shootdown(this);
+            take_fence(this);
+            core::hint::spin_loop();
+        }
+    }
+
+    unsafe fn remote_fence(harts: usize) {
+        let this = register::cpuid::read().core_id();
+        let targets = || (0..MAX_PROCESSORS).filter(|hart| harts & (1 << hart) != 0 && *hart != this);
+        for hart in targets() {
+            FENCE[hart].store(true, Ordering::Release);
+            loongArch64::ipi::send_ipi_single(hart, IPI_FENCE);
+        }
+        // a hart asking this one at the same time waits for it as well
+        while targets().any(|hart| FENCE[hart].load(Ordering::Acquire)) {
+            take_fence(this);
+            take_shootdown(this);
+            core::hint::spin_loop();
+        }
+    }
+
+    fn max_asid() -> usize {
+        (1 << ((csr_asid() >> 16) & 0xff)) - 1
+    }
+
+    unsafe fn icache_flush(_all_harts: bool) {
+        // icache is kept coherent with dcache by hardware across cores,
+        // ibar only drops the instructions already fetched on this core
+        core::arch::asm!(
+            r"
+            dbar 0
+            ibar 0
+            ",
+            options(nostack)
+        );
+    }
+
+    unsafe fn dcache_flush() {
+        core::arch::asm!("dbar 0", options(nostack));
+    }
+
     unsafe fn enable_interrupt() {
         register::crmd::set_ie(true);
     }
@@ -107,4 +265,17 @@ impl InstructionHal for Instruction {
             | LineBasedInterrupt::HWI4 | LineBasedInterrupt::HWI5 | LineBasedInterrupt::HWI6 | LineBasedInterrupt::HWI7
         );
     }
+
+    fn handle_remote_requests() {
+        let hart = register::cpuid::read().core_id();
+        take_fence(hart);
+        take_shootdown(hart);
+    }
+
+    unsafe fn wait_for_interrupt() {
+        // an interrupt taken between enabling and idle is missed until the next
+        // one, which the timer bounds to a tick
+        register::crmd::set_ie(true);
+        core::arch::asm!("idle 0", options(nostack));
+    }
 }
\ No newline at end of file
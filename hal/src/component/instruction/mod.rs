@@ -1,11 +1,33 @@
+use core::sync::atomic::AtomicUsize;
+
 pub trait InstructionHal {
     unsafe fn tlb_flush_addr(vaddr: usize);
     unsafe fn tlb_flush_all();
+    /// drop the non-global translations tagged with `asid` from this hart's TLB
+    unsafe fn tlb_flush_asid(asid: usize);
+    /// drop the translations of `len` bytes from `start` tagged with `asid` on the harts
+    /// set in the `harts` mask, `len` of usize::MAX drops all of `asid`
+    unsafe fn tlb_shootdown(harts: usize, asid: usize, start: usize, len: usize);
+    /// run a full memory barrier on the harts set in the `harts` mask,
+    /// returning once all of them have
+    unsafe fn remote_fence(harts: usize);
+    /// do the TLB flushes and barriers other harts asked of this one,
+    /// for a hart spinning with interrupts off while they wait for it
+    fn handle_remote_requests();
+    /// the largest address space id the TLB tags entries with, 0 without ASID support
+    fn max_asid() -> usize;
+    /// make stores visible to instruction fetch, on this hart or on all harts
+    unsafe fn icache_flush(all_harts: bool);
+    /// make stores visible to other harts and devices
+    unsafe fn dcache_flush();
     unsafe fn enable_interrupt();
     unsafe fn disable_interrupt();
     unsafe fn is_interrupt_enabled() -> bool;
     unsafe fn enable_timer_interrupt();
     unsafe fn enable_external_interrupt();
+    /// park the hart until an interrupt is pending, interrupts are enabled
+    /// when it returns so that the pending one is taken
+    unsafe fn wait_for_interrupt();
     unsafe fn clear_sum();
     unsafe fn set_sum();
     /// shutdown is unsafe, because it will not trigger drop
@@ -18,6 +40,25 @@ pub trait InstructionHal {
 
 pub struct Instruction;
 
+/// TLB flushes done so far by all harts
+pub struct TlbFlushStats {
+    /// whole TLB
+    pub all: AtomicUsize,
+    /// one address space
+    pub asid: AtomicUsize,
+    /// one page
+    pub addr: AtomicUsize,
+    /// requests sent to other harts
+    pub remote: AtomicUsize,
+}
+
+pub static TLB_FLUSH_STATS: TlbFlushStats = TlbFlushStats {
+    all: AtomicUsize::new(0),
+    asid: AtomicUsize::new(0),
+    addr: AtomicUsize::new(0),
+    remote: AtomicUsize::new(0),
+};
+
 #[cfg(target_arch = "riscv64")]
 mod riscv64;
 
@@ -1,20 +1,80 @@
-use core::arch::asm;
+use core::{arch::asm, sync::atomic::Ordering};
 
 use riscv::register;
 
-use crate::{constant::{Constant, ConstantsHal}, println};
+use crate::{constant::{Constant, ConstantsHal}, pagetable::{SATP_ASID_MASK, SATP_ASID_SHIFT}, println};
 
-use super::{Instruction, InstructionHal};
+use super::{Instruction, InstructionHal, TLB_FLUSH_STATS};
 
 impl InstructionHal for Instruction {
     unsafe fn tlb_flush_addr(vaddr: usize) {
-        riscv::asm::sfence_vma(0, vaddr);
+        TLB_FLUSH_STATS.addr.fetch_add(1, Ordering::Relaxed);
+        // x0 as asid drops the page from every address space, global kernel
+        // entries included, which an asid operand would leave alone
+        asm!("sfence.vma {}, zero", in(reg) vaddr, options(nostack));
     }
 
     unsafe fn tlb_flush_all() {
+        TLB_FLUSH_STATS.all.fetch_add(1, Ordering::Relaxed);
         riscv::asm::sfence_vma_all();
     }
 
+    unsafe fn tlb_flush_asid(asid: usize) {
+        TLB_FLUSH_STATS.asid.fetch_add(1, Ordering::Relaxed);
+        asm!("sfence.vma zero, {}", in(reg) asid, options(nostack));
+    }
+
+    unsafe fn tlb_shootdown(harts: usize, asid: usize, start: usize, len: usize) {
+        TLB_FLUSH_STATS.remote.fetch_add(1, Ordering::Relaxed);
+        // the SBI returns once the remote harts have fenced
+        if asid == 0 {
+            sbi_rt::remote_sfence_vma(harts, 0, start, len);
+        } else {
+            sbi_rt::remote_sfence_vma_asid(harts, 0, start, len, asid);
+        }
+    }
+
+    unsafe fn remote_fence(harts: usize) {
+        if harts == 0 {
+            return;
+        }
+        // the remote harts trap into the SBI, which orders their memory
+        // accesses, and the call returns once they are done
+        sbi_rt::remote_fence_i(harts, 0);
+    }
+
+    fn max_asid() -> usize {
+        // the asid field keeps only the bits the hart implements
+        let probe: usize;
+        unsafe {
+            asm!(
+                "csrr {satp}, satp",
+                "or {probe}, {satp}, {mask}",
+                "csrw satp, {probe}",
+                "csrr {probe}, satp",
+                "csrw satp, {satp}",
+                satp = out(reg) _,
+                probe = out(reg) probe,
+                mask = in(reg) SATP_ASID_MASK << SATP_ASID_SHIFT,
+                options(nostack)
+            );
+        }
+        (probe >> SATP_ASID_SHIFT) & SATP_ASID_MASK
+    }
+
+    unsafe fn icache_flush(all_harts: bool) {
+        asm!("fence.i");
+        if all_harts {
+            // hart_mask_base = -1 means all harts
+            sbi_rt::remote_fence_i(0, usize::MAX);
+        }
+    }
+
+    unsafe fn dcache_flush() {
+        // data caches are coherent, only order the memory accesses
+        asm!("fence rw, rw");
+    }
+
     unsafe fn enable_interrupt() {
         register::sstatus::set_sie();
     }
@@ -33,6 +93,16 @@ impl InstructionHal for Instruction {
     unsafe fn enable_external_interrupt() {
         register::sie::set_sext();
     } 
+    fn handle_remote_requests() {
+        // the SBI serves the requests of other harts whatever this one does
+    }
+
+    unsafe fn wait_for_interrupt() {
+        // wfi wakes on an interrupt enabled in sie even with sstatus.SIE clear,
+        // so the caller may check for work with interrupts off before waiting
+        asm!("wfi", options(nomem, nostack));
+        register::sstatus::set_sie();
+    }
     unsafe fn clear_sum() {
         register::sstatus::clear_sum();
     }
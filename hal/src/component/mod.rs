@@ -1,6 +1,7 @@
 pub mod addr;
 pub mod console;
 pub mod constant;
+pub mod cpu;
 pub mod entry;
 pub mod instruction;
 pub mod pagetable;
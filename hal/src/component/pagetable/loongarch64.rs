@@ -1,9 +1,9 @@
-use core::ops::Range;
+use core::{ops::Range, sync::atomic::{AtomicUsize, Ordering}};
 
 use alloc::vec::Vec;
 use loongArch64::register;
 
-use crate::{addr::{PhysAddr, PhysAddrHal, PhysPageNum, PhysPageNumHal, RangePPNHal, VirtAddrHal, VirtPageNum, VirtPageNumHal}, allocator::{FrameAllocatorHal, FrameAllocatorTrackerExt, DynamicFrameAllocator}, common::FrameTracker, constant::{Constant, ConstantsHal}, println};
+use crate::{addr::{PhysAddr, PhysAddrHal, PhysPageNum, PhysPageNumHal, RangePPNHal, VirtAddrHal, VirtPageNum, VirtPageNumHal}, allocator::{FrameAllocatorHal, FrameAllocatorTrackerExt, DynamicFrameAllocator}, common::FrameTracker, constant::{Constant, ConstantsHal}, instruction::{Instruction, InstructionHal}, println};
 
 use super::{MapPerm, PageTableEntryHal, PageTableHal};
 
@@ -160,7 +160,7 @@ impl PageTableEntry {
     const FLAGS_MASK: usize = {
         PTEFlags::PLV_L.bits | PTEFlags::PLV_H.bits |
         PTEFlags::W.bits     | PTEFlags::NX.bits    |
-        PTEFlags::NR.bits
+        PTEFlags::NR.bits    | PTEFlags::GH.bits
     };
     const PTE_FLAGS_MASK: usize = 0xE000_0000_0000_0FFF;
     const PPN_MASK: usize = 0x1FFF_FFFF_FFFF_F000;
@@ -186,6 +186,11 @@ impl From<MapPerm> for PTEFlags {
         if !value.contains(MapPerm::X) {
             ret.insert(PTEFlags::NX);
         }
+        // GH is G for the basic pages `map` builds, a global user page
+        // would outlive the asid it was cached under
+        if value.contains(MapPerm::G) && !value.contains(MapPerm::U) {
+            ret.insert(PTEFlags::GH);
+        }
         ret
     }
 }
@@ -214,6 +219,9 @@ impl PageTableEntryHal for PageTableEntry {
         if !pte.contains(PTEFlags::NX) {
             ret.insert(MapPerm::X);
         }
+        if pte.contains(PTEFlags::GH) {
+            ret.insert(MapPerm::G);
+        }
         ret
     }
     
@@ -292,6 +300,12 @@ pub struct PageTable<A: FrameAllocatorHal + Clone = DynamicFrameAllocator> {
     pub root_ppn: PhysPageNum,
     frames: Vec<FrameTracker<A>>,
     alloc: A,
+    asid: AtomicUsize,
+    /// harts that may cache translations this table dropped
+    stale_harts: AtomicUsize,
+    /// vpns dropped since the last `take_flushed`, empty when start >= end
+    flushed_start: AtomicUsize,
+    flushed_end: AtomicUsize,
 }
 
 impl<A: FrameAllocatorHal + Clone> PageTable<A> {
@@ -326,7 +340,11 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
         Self { 
             root_ppn: PhysPageNum(token >> Constant::PAGE_SIZE_BITS), 
             frames: Vec::new(), 
-            alloc
+            alloc,
+            asid: AtomicUsize::new(0),
+            stale_harts: AtomicUsize::new(0),
+            flushed_start: AtomicUsize::new(usize::MAX),
+            flushed_end: AtomicUsize::new(0),
         }
     }
 
@@ -356,13 +374,17 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
         Some(PhysPageNum(ppn.0 + offset))
     }
  
-    fn new_in(_asid: usize, alloc: A) -> Self {
+    fn new_in(asid: usize, alloc: A) -> Self {
         let frame = alloc.alloc_tracker(1).unwrap();
         frame.range_ppn.get_slice_mut::<u8>().fill(0);
         Self {
             root_ppn: frame.range_ppn.start,
             frames: alloc::vec![frame],
-            alloc
+            alloc,
+            asid: AtomicUsize::new(asid),
+            stale_harts: AtomicUsize::new(0),
+            flushed_start: AtomicUsize::new(usize::MAX),
+            flushed_end: AtomicUsize::new(0),
         }
     }
 
@@ -409,12 +431,13 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
     }
 
     unsafe fn enable_high(&self) {
-        register::asid::set_asid(0);
+        // keep the asid: the low half still translates through pgdl, entries
+        // cached under asid 0 would outlive the user page table
         register::pgdh::set_base(self.get_token());
     }
 
     unsafe fn enable_low(&self) {
-        register::asid::set_asid(0);
+        register::asid::set_asid(self.asid() as _);
         register::pgdl::set_base(self.get_token());
     }
 
@@ -430,4 +453,40 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
         let token = self.get_token();
         token == pgdl || token == pgdh
     }
+
+    fn asid(&self) -> usize {
+        self.asid.load(Ordering::Relaxed)
+    }
+
+    fn set_asid(&self, asid: usize) {
+        self.asid.store(asid, Ordering::Relaxed);
+        self.stale_harts.store(usize::MAX, Ordering::Release);
+    }
+
+    fn flush_vpn(&self, vpn: VirtPageNum) {
+        unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0) };
+        let stale = if self.enabled() {
+            !(1 << Instruction::get_tp())
+        } else {
+            usize::MAX
+        };
+        self.stale_harts.fetch_or(stale, Ordering::Release);
+        self.flushed_start.fetch_min(vpn.0, Ordering::AcqRel);
+        self.flushed_end.fetch_max(vpn.0 + 1, Ordering::AcqRel);
+    }
+
+    fn take_flushed(&self) -> Option<Range<VirtPageNum>> {
+        let end = self.flushed_end.swap(0, Ordering::AcqRel);
+        let start = self.flushed_start.swap(usize::MAX, Ordering::AcqRel);
+        (start < end).then(|| VirtPageNum(start)..VirtPageNum(end))
+    }
+
+    fn take_stale(&self, hart: usize) -> bool {
+        let bit = 1 << hart;
+        self.stale_harts.fetch_and(!bit, Ordering::AcqRel) & bit != 0
+    }
+
+    fn split_leaf(&mut self, _vpn: VirtPageNum) {
+        // `find_pte_create` only builds leaves at the lowest level, there is nothing to split
+    }
 }
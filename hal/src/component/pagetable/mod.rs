@@ -1,3 +1,5 @@
+use core::ops::Range;
+
 use crate::allocator::FrameAllocatorHal;
 use crate::addr::{PhysPageNum, VirtPageNum, PhysAddr, VirtAddr};
 
@@ -13,6 +15,8 @@ bitflags! {
         const X = 1 << 2;
         /// User-mode accessible
         const U = 1 << 3;
+        /// Global, the same in every address space, never set together with U
+        const G = 1 << 4;
     }
 }
 
@@ -111,6 +115,21 @@ pub trait PageTableHal<PTE: PageTableEntryHal, A: FrameAllocatorHal> {
     unsafe fn enable_high(&self);
     unsafe fn enable_low(&self);
     fn enabled(&self) -> bool;
+    /// address space id the TLB entries of this table are tagged with, 0 for the kernel
+    fn asid(&self) -> usize;
+    /// retag the table, every hart has to drop what it cached under `asid` before using it
+    fn set_asid(&self, asid: usize);
+    /// drop the translation of `vpn` from this hart's TLB, other harts catch up
+    /// through `take_stale` before they next switch to the table
+    fn flush_vpn(&self, vpn: VirtPageNum);
+    /// whether `hart` may still cache translations dropped since it last asked
+    fn take_stale(&self, hart: usize) -> bool;
+    /// the span of the pages passed to `flush_vpn` since the last call, which harts
+    /// running the table right now still have to drop
+    fn take_flushed(&self) -> Option<Range<VirtPageNum>>;
+    /// replace the leaf above the lowest level mapping `vpn` by a table of base pages
+    /// with the same permission, nothing to do when `vpn` is not in such a leaf
+    fn split_leaf(&mut self, vpn: VirtPageNum);
 }
 
 #[cfg(target_arch = "riscv64")]
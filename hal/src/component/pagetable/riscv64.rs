@@ -1,23 +1,42 @@
-use core::{arch::asm, ops::Range};
+use core::{arch::asm, ops::Range, sync::atomic::{AtomicUsize, Ordering}};
 
 use alloc::vec::Vec;
 use bitflags::bitflags;
 use riscv::register;
 
-use crate::{addr::{PhysAddr, PhysAddrHal, PhysPageNum, PhysPageNumHal, RangePPNHal, VirtAddrHal, VirtPageNum, VirtPageNumHal}, allocator::{DynamicFrameAllocator, FrameAllocatorHal, FrameAllocatorTrackerExt}, common::FrameTracker, constant::{Constant, ConstantsHal}};
+use crate::{addr::{PhysAddr, PhysAddrHal, PhysPageNum, PhysPageNumHal, RangePPNHal, VirtAddrHal, VirtPageNum, VirtPageNumHal}, allocator::{DynamicFrameAllocator, FrameAllocatorHal, FrameAllocatorTrackerExt}, common::FrameTracker, constant::{Constant, ConstantsHal}, instruction::{Instruction, InstructionHal}};
 
 use super::{MapPerm, PageTableEntryHal, PageTableHal};
 
+/// position of the asid field in satp
+pub const SATP_ASID_SHIFT: usize = 44;
+/// width mask of the asid field in satp
+pub const SATP_ASID_MASK: usize = 0xffff;
+
+/// satp mode field: 8 for sv39, 9 for sv48
+#[cfg(not(feature = "sv48"))]
+pub const SATP_MODE: usize = 8;
+/// satp mode field: 8 for sv39, 9 for sv48
+#[cfg(feature = "sv48")]
+pub const SATP_MODE: usize = 9;
+
+/// level of a leaf, the value is its depth in the page table
+#[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PageLevel {
-    Huge = 0,
-    Big = 1,
-    Small = 2
+    /// 512G, only with sv48
+    #[cfg(feature = "sv48")]
+    Giant = 0,
+    Huge = Constant::PG_LEVEL - 3,
+    Big = Constant::PG_LEVEL - 2,
+    Small = Constant::PG_LEVEL - 1,
 }
 
 impl PageLevel {
     pub const fn page_count(self) -> usize {
         match self {
+            #[cfg(feature = "sv48")]
+            PageLevel::Giant => 512 * 512 * 512,
             PageLevel::Huge => 512 * 512,
             PageLevel::Big => 512,
             PageLevel::Small => 1,
@@ -26,6 +45,8 @@ impl PageLevel {
 
     pub const fn lower(self) -> Self {
         match self {
+            #[cfg(feature = "sv48")]
+            PageLevel::Giant => PageLevel::Huge,
             PageLevel::Huge => PageLevel::Big,
             PageLevel::Big => PageLevel::Small,
             PageLevel::Small => PageLevel::Small,
@@ -34,6 +55,11 @@ impl PageLevel {
 
     pub const fn higher(self) -> Self {
         match self {
+            #[cfg(feature = "sv48")]
+            PageLevel::Giant => PageLevel::Giant,
+            #[cfg(feature = "sv48")]
+            PageLevel::Huge => PageLevel::Giant,
+            #[cfg(not(feature = "sv48"))]
             PageLevel::Huge => PageLevel::Huge,
             PageLevel::Big => PageLevel::Huge,
             PageLevel::Small => PageLevel::Big,
@@ -48,10 +74,7 @@ impl PageLevel {
     }
 
     pub const fn highest(self) -> bool {
-        match self {
-            PageLevel::Huge => true,
-            _ => false
-        }
+        self as usize == 0
     }
 
     pub const fn from_count(count: usize) -> Option<Self> {
@@ -59,6 +82,8 @@ impl PageLevel {
             0x1 => Some(Self::Small),
             0x200 => Some(Self::Big),
             0x40000 => Some(Self::Huge),
+            #[cfg(feature = "sv48")]
+            0x800_0000 => Some(Self::Giant),
             _ => None
         }
     }
@@ -66,10 +91,12 @@ impl PageLevel {
 
 impl From<usize> for PageLevel {
     fn from(value: usize) -> Self {
-        match value {
-            0 => Self::Huge,
-            1 => Self::Big,
-            2 => Self::Small,
+        match value + 4 - Constant::PG_LEVEL {
+            #[cfg(feature = "sv48")]
+            0 => Self::Giant,
+            1 => Self::Huge,
+            2 => Self::Big,
+            3 => Self::Small,
             _ => panic!("unsupport Page Level")
         }
     }
@@ -143,7 +170,8 @@ impl PageTableEntry {
     const PTE_FLAGS_MASK: usize = 0x0000_0000_0000_03FF;
     const FLAGS_MASK: usize = {
         PTEFlags::U.bits | PTEFlags::R.bits |
-        PTEFlags::W.bits | PTEFlags::X.bits
+        PTEFlags::W.bits | PTEFlags::X.bits |
+        PTEFlags::G.bits
     } as usize;
 
     pub(crate) fn pteflags(&self) -> PTEFlags {
@@ -166,6 +194,10 @@ impl From<MapPerm> for PTEFlags {
         if value.contains(MapPerm::X) {
             ret.insert(PTEFlags::X);
         }
+        // a global user page would outlive the asid it was cached under
+        if value.contains(MapPerm::G) && !value.contains(MapPerm::U) {
+            ret.insert(PTEFlags::G);
+        }
         ret
     }
 }
@@ -193,6 +225,9 @@ impl PageTableEntryHal for PageTableEntry {
         if pte.contains(PTEFlags::X) {
             ret.insert(MapPerm::X);
         }
+        if pte.contains(PTEFlags::G) {
+            ret.insert(MapPerm::G);
+        }
         ret
     }
     
@@ -282,6 +317,12 @@ pub struct PageTable<A: FrameAllocatorHal + Clone = DynamicFrameAllocator> {
     pub root_ppn: PhysPageNum,
     frames: Vec<FrameTracker<A>>,
     alloc: A,
+    asid: AtomicUsize,
+    /// harts that may cache translations this table dropped
+    stale_harts: AtomicUsize,
+    /// vpns dropped since the last `take_flushed`, empty when start >= end
+    flushed_start: AtomicUsize,
+    flushed_end: AtomicUsize,
 }
 
 impl<A: FrameAllocatorHal + Clone> PageTable<A> {
@@ -316,21 +357,29 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
         Self {
             root_ppn: PhysPageNum(token & ((1 << Constant::PPN_WIDTH) - 1)), 
             frames: Vec::new(),
-            alloc
+            alloc,
+            asid: AtomicUsize::new((token >> SATP_ASID_SHIFT) & SATP_ASID_MASK),
+            stale_harts: AtomicUsize::new(0),
+            flushed_start: AtomicUsize::new(usize::MAX),
+            flushed_end: AtomicUsize::new(0),
         }
     }
 
     fn get_token(&self) -> usize {
-        (8usize << 60) | self.root_ppn.0
+        (SATP_MODE << 60) | (self.asid() << SATP_ASID_SHIFT) | self.root_ppn.0
     }
 
-    fn new_in(_: usize, alloc: A) -> Self {
+    fn new_in(asid: usize, alloc: A) -> Self {
         let frame = alloc.alloc_tracker(1).unwrap();
         frame.range_ppn.get_slice_mut::<u8>().fill(0);
         Self {
             root_ppn: frame.range_ppn.start,
             frames: alloc::vec![frame],
-            alloc
+            alloc,
+            asid: AtomicUsize::new(asid),
+            stale_harts: AtomicUsize::new(0),
+            flushed_start: AtomicUsize::new(usize::MAX),
+            flushed_end: AtomicUsize::new(0),
         }
     }
 
@@ -342,7 +391,7 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
             if !pte.is_valid() {
                 return None;
             }
-            if pte.is_leaf() || i == 2 {
+            if pte.is_leaf() || i == Constant::PG_LEVEL - 1 {
                 return Some((pte, i));
             }
             ppn = pte.ppn();
@@ -412,6 +461,59 @@ impl<A: FrameAllocatorHal + Clone> PageTableHal<PageTableEntry, A> for PageTable
     }
     
     fn enabled(&self) -> bool {
-        self.get_token() == register::satp::read().bits()
+        register::satp::read().ppn() == self.root_ppn.0
+    }
+
+    fn asid(&self) -> usize {
+        self.asid.load(Ordering::Relaxed)
+    }
+
+    fn set_asid(&self, asid: usize) {
+        self.asid.store(asid, Ordering::Relaxed);
+        self.stale_harts.store(usize::MAX, Ordering::Release);
+    }
+
+    fn flush_vpn(&self, vpn: VirtPageNum) {
+        unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0) };
+        let stale = if self.enabled() {
+            !(1 << Instruction::get_tp())
+        } else {
+            usize::MAX
+        };
+        self.stale_harts.fetch_or(stale, Ordering::Release);
+        self.flushed_start.fetch_min(vpn.0, Ordering::AcqRel);
+        self.flushed_end.fetch_max(vpn.0 + 1, Ordering::AcqRel);
+    }
+
+    fn take_flushed(&self) -> Option<Range<VirtPageNum>> {
+        let end = self.flushed_end.swap(0, Ordering::AcqRel);
+        let start = self.flushed_start.swap(usize::MAX, Ordering::AcqRel);
+        (start < end).then(|| VirtPageNum(start)..VirtPageNum(end))
+    }
+
+    fn take_stale(&self, hart: usize) -> bool {
+        let bit = 1 << hart;
+        self.stale_harts.fetch_and(!bit, Ordering::AcqRel) & bit != 0
+    }
+
+    fn split_leaf(&mut self, vpn: VirtPageNum) {
+        loop {
+            let (leaf, level) = match self.find_pte(vpn) {
+                Some((pte, level)) if pte.is_leaf() && !PageLevel::from(level).lowest() => (*pte, PageLevel::from(level)),
+                _ => return,
+            };
+            // one level down at a time, a huge leaf becomes big leaves first
+            let step = level.lower().page_count();
+            let frame = self.alloc.alloc(1).unwrap();
+            let children = frame.start.start_addr().get_mut::<[PageTableEntry; 512]>();
+            for (i, child) in children.iter_mut().enumerate() {
+                *child = leaf;
+                child.set_ppn(PhysPageNum(leaf.ppn().0 + i * step));
+            }
+            let (pte, _) = self.find_pte(vpn).unwrap();
+            *pte = PageTableEntry::new(frame.start, MapPerm::empty());
+            pte.set_valid(true);
+            self.frames.push(FrameTracker::new_in(frame, self.alloc.clone()));
+        }
     }
 }
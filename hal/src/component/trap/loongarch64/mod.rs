@@ -3,9 +3,9 @@ use core::{arch::asm, fmt::Debug};
 use log::{info, warn};
 use loongArch64::register::{self, estat::{Exception, Interrupt, Trap}};
 
-use crate::{addr::{VirtAddr, VirtAddrHal, VirtPageNum}, allocator::FakeFrameAllocator, board::MAX_PROCESSORS, constant::{Constant, ConstantsHal}, instruction::{Instruction, InstructionHal}, pagetable::{MapPerm, PTEFlags, PageTable, PageTableEntryHal, PageTableHal}, println};
+use crate::{addr::{VirtAddr, VirtAddrHal, VirtPageNum}, allocator::FakeFrameAllocator, board::MAX_PROCESSORS, constant::{Constant, ConstantsHal}, cpu::{cpu_has, Feature}, instruction::{Instruction, InstructionHal}, pagetable::{MapPerm, PTEFlags, PageTable, PageTableEntryHal, PageTableHal}, println};
 
-use super::{FloatContextHal, TrapContextHal, TrapType, TrapTypeHal};
+use super::{FloatContextHal, MisalignedAccess, TrapContextHal, TrapType, TrapTypeHal};
 
 core::arch::global_asm!(include_str!("trap.S"));
 
@@ -181,6 +181,63 @@ impl TrapContextHal for TrapContext {
         self.user_fx.restore();
     }
 
+    fn decode_misaligned(&mut self) -> Option<MisalignedAccess> {
+        let inst = unsafe { (self.era as *const u32).read_volatile() };
+        let rd = (inst & 0x1f) as usize;
+        // (store, size, signed)
+        let decoded = match inst >> 22 {
+            // ld.{b,h,w,d} st.{b,h,w,d} ld.{bu,hu,wu}
+            op @ 0x0a0..=0x0a3 => Some((false, 1 << (op - 0x0a0), op != 0x0a3)),
+            op @ 0x0a4..=0x0a7 => Some((true, 1 << (op - 0x0a4), false)),
+            op @ 0x0a8..=0x0aa => Some((false, 1 << (op - 0x0a8), false)),
+            _ => None,
+        }.or_else(|| match inst >> 24 {
+            // ldptr.w stptr.w ldptr.d stptr.d
+            0x24 => Some((false, 4, true)),
+            0x25 => Some((true, 4, false)),
+            0x26 => Some((false, 8, false)),
+            0x27 => Some((true, 8, false)),
+            _ => None,
+        }).or_else(|| match inst >> 15 {
+            // ldx.{b,h,w,d} stx.{b,h,w,d} ldx.{bu,hu,wu}
+            op @ (0x7000 | 0x7008 | 0x7010 | 0x7018) => Some((false, 1 << ((op - 0x7000) >> 3), op != 0x7018)),
+            op @ (0x7020 | 0x7028 | 0x7030 | 0x7038) => Some((true, 1 << ((op - 0x7020) >> 3), false)),
+            op @ (0x7040 | 0x7048 | 0x7050) => Some((false, 1 << ((op - 0x7040) >> 3), false)),
+            _ => None,
+        });
+        let (store, size, signed) = decoded?;
+        Some(MisalignedAccess {
+            store,
+            size,
+            signed,
+            reg: rd,
+            value: if store { self.r[rd] } else { 0 },
+            inst_len: 4,
+        })
+    }
+
+    fn complete_misaligned(&mut self, access: &MisalignedAccess, loaded: usize) {
+        if !access.store && access.reg != 0 {
+            let shift = 64 - access.size * 8;
+            self.r[access.reg] = if access.signed {
+                (((loaded << shift) as isize) >> shift) as usize
+            } else {
+                loaded
+            };
+        }
+        self.era += access.inst_len;
+    }
+
+    fn emulate_counter_read(&mut self) -> bool {
+        // rdtime{l,h}.w and rdtime.d never trap
+        false
+    }
+
+    fn fx_unavailable(&mut self) {
+        // FloatingPointUnavailable is processed in `get_trap_type` with FP_REG_DIRTY
+        self.user_fx.restore();
+    }
+
     // fn save_last_user_arg0(&mut self) {
     //     self.last_user_arg0 = self.r[4];
     // }
@@ -199,6 +256,11 @@ impl FloatContextHal for FloatContext {
         if self.need_save == 0 {
             return;
         }
+        if !cpu_has(Feature::F) {
+            // no fpu, nothing to save
+            self.need_save = 0;
+            return;
+        }
         self.need_save = 0;
         //warn!("FP save");
         let last_fpe = register::euen::read().fpe();
@@ -252,6 +314,10 @@ impl FloatContextHal for FloatContext {
         if self.need_restore == 0 {
             return;
         }
+        if !cpu_has(Feature::F) {
+            self.need_restore = 0;
+            return;
+        }
         self.need_restore = 0;
         //warn!("FP restore");
         let last_fpe = register::euen::read().fpe();
@@ -319,6 +385,7 @@ impl KernelContext {
 
 pub fn init() {
     set_kernel_trap_entry();
+    crate::instruction::enable_ipi();
 }
 
 
@@ -494,7 +561,12 @@ fn get_trap_type() -> TrapType {
         Trap::Exception(Exception::LoadPageFault) => TrapType::LoadPageFault(badv),
         Trap::Exception(Exception::StorePageFault) => TrapType::StorePageFault(badv),
         Trap::Exception(Exception::FetchPageFault) => TrapType::InstructionPageFault(badv),
+        Trap::Exception(Exception::AddressNotAligned) => TrapType::MisalignedAccess(badv),
         Trap::Interrupt(Interrupt::Timer) => TrapType::Timer,
+        Trap::Interrupt(Interrupt::IPI) => {
+            crate::instruction::handle_ipi();
+            TrapType::Processed
+        },
         Trap::Interrupt(Interrupt::HWI0) |
         Trap::Interrupt(Interrupt::HWI1) |
         Trap::Interrupt(Interrupt::HWI2) |
@@ -10,6 +10,27 @@ pub enum TrapType {
     LoadPageFault(usize),
     InstructionPageFault(usize),
     IllegalInstruction(usize),
+    /// the fpu is turned off and the task uses it
+    FloatUnavailable,
+    /// load or store on a misaligned address
+    MisalignedAccess(usize),
+}
+
+/// a load or store decoded from the instruction that caused [`TrapType::MisalignedAccess`]
+#[derive(Debug, Clone, Copy)]
+pub struct MisalignedAccess {
+    /// is it a store
+    pub store: bool,
+    /// access size in bytes
+    pub size: usize,
+    /// sign-extend the loaded value
+    pub signed: bool,
+    /// destination register of load
+    pub reg: usize,
+    /// value to store
+    pub value: usize,
+    /// length of the instruction
+    pub inst_len: usize,
 }
 
 pub trait TrapTypeHal: Sized {
@@ -53,6 +74,19 @@ pub trait TrapContextHal {
 
     fn fx_restore(&mut self);
 
+    /// reload the float registers on the first float instruction after a task switch
+    fn fx_unavailable(&mut self);
+
+    /// decode the load/store at the trapped pc, user memory must be accessible
+    fn decode_misaligned(&mut self) -> Option<MisalignedAccess>;
+
+    /// write back the loaded value and step over the emulated instruction
+    fn complete_misaligned(&mut self, access: &MisalignedAccess, loaded: usize);
+
+    /// emulate a user read of a counter csr that traps on this hardware,
+    /// user memory must be accessible, return false if the instruction is not emulated
+    fn emulate_counter_read(&mut self) -> bool;
+
     // fn save_last_user_arg0(&mut self);
 
     // fn restore_last_user_arg0(&mut self);
@@ -3,9 +3,9 @@ use core::arch::asm;
 use log::info;
 use riscv::register::{scause::{self, Exception, Interrupt, Trap}, sepc, sstatus::{self, Sstatus, FS, SPP}, stval, stvec::{self, TrapMode}};
 
-use crate::instruction::{Instruction, InstructionHal};
+use crate::{cpu::{cpu_has, Feature}, instruction::{Instruction, InstructionHal}};
 
-use super::{FloatContextHal, TrapContextHal, TrapType, TrapTypeHal};
+use super::{FloatContextHal, MisalignedAccess, TrapContextHal, TrapType, TrapTypeHal};
 
 core::arch::global_asm!(include_str!("trap.S"));
 
@@ -52,10 +52,38 @@ pub struct TrapContext {
     pub(crate) user_fx: FloatContext, 
     /// used in multi_core
     pub(crate) stored: usize,
+    /// vector registers
+    pub(crate) user_vx: VectorContext,
     // /// used for signal, when using SA_RESTART flag, need to restore last user arg0
     // pub(crate) last_user_arg0: usize,
 }
 
+impl TrapContext {
+    fn set_fs(&mut self, fs: FS) {
+        let fs = match fs {
+            FS::Off => 0,
+            FS::Initial => 1,
+            FS::Clean => 2,
+            FS::Dirty => 3,
+        };
+        let bits = (self.sstatus.bits() & !SSTATUS_FS_MASK) | (fs << 13);
+        // Sstatus is a plain wrapper of the csr bits
+        self.sstatus = unsafe { core::mem::transmute::<usize, Sstatus>(bits) };
+    }
+
+    /// sstatus.VS takes the same encoding as sstatus.FS
+    fn set_vs(&mut self, vs: FS) {
+        let vs = match vs {
+            FS::Off => 0,
+            FS::Initial => 1,
+            FS::Clean => 2,
+            FS::Dirty => 3,
+        };
+        let bits = (self.sstatus.bits() & !SSTATUS_VS_MASK) | (vs << 9);
+        self.sstatus = unsafe { core::mem::transmute::<usize, Sstatus>(bits) };
+    }
+}
+
 impl TrapContextHal for TrapContext {
     fn syscall_id(&self) -> usize {
         self.x[17]
@@ -101,6 +129,10 @@ impl TrapContextHal for TrapContext {
         unsafe {
             sstatus::set_spp(SPP::User);
             Instruction::disable_interrupt();
+            if cpu_has(Feature::V) {
+                // let user use the vector unit, the state starts as initial
+                asm!("csrs sstatus, {}", in(reg) SSTATUS_VS_INITIAL);
+            }
         }
         let mut cx = Self {
             x: [0; 32],
@@ -114,6 +146,7 @@ impl TrapContextHal for TrapContext {
             kernel_tp: 0,
             user_fx: FloatContext::new(),
             stored: 0,
+            user_vx: VectorContext::new(),
             // last_user_arg0: 0,
         };
         *cx.sp() = sp;
@@ -158,20 +191,139 @@ impl TrapContextHal for TrapContext {
     }
     
     fn mark_fx_save(&mut self) {
-        self.user_fx.need_save |= (self.sstatus.fs() == FS::Dirty) as u8;
-        self.user_fx.signal_dirty |= (self.sstatus.fs() == FS::Dirty) as u8;
+        if self.sstatus.fs() == FS::Dirty {
+            self.user_fx.need_save = 1;
+            self.user_fx.signal_dirty = 1;
+            // registers are still live in the fpu, only the next write makes it dirty again
+            self.set_fs(FS::Clean);
+        }
+        if self.sstatus.bits() & SSTATUS_VS_MASK == SSTATUS_VS_MASK {
+            self.user_vx.need_save = 1;
+            self.user_vx.signal_dirty = 1;
+            self.set_vs(FS::Clean);
+        }
     }
     
     fn fx_restore(&mut self) {
-        self.user_fx.restore();
+        // float registers are restored lazily in `fx_unavailable`
+        if self.sstatus.fs() != FS::Off {
+            self.user_fx.restore();
+        }
+        self.user_vx.restore();
     }
 
     fn fx_yield_task(&mut self) {
         self.user_fx.yield_task();
+        self.user_vx.yield_task();
+        // turn off the fpu, the registers are reloaded on the first float instruction
+        if cpu_has(Feature::F) {
+            self.set_fs(FS::Off);
+        }
+    }
+
+    fn decode_misaligned(&mut self) -> Option<MisalignedAccess> {
+        // instructions are only 2-byte aligned with the C extension
+        let pc = self.sepc as *const u16;
+        let low = unsafe { pc.read_volatile() } as u32;
+        let (store, size, signed, reg, inst_len) = if low & 0b11 != 0b11 {
+            // compressed, rd'/rs2' of C.LW/C.SW/C.LD/C.SD is 4:2, rd/rs2 of *SP forms is 11:7 / 6:2
+            let rs_c = 8 + ((low >> 2) & 0b111) as usize;
+            match (low & 0b11, low >> 13) {
+                (0b00, 0b010) => (false, 4, true, rs_c, 2),
+                (0b00, 0b011) => (false, 8, false, rs_c, 2),
+                (0b00, 0b110) => (true, 4, false, rs_c, 2),
+                (0b00, 0b111) => (true, 8, false, rs_c, 2),
+                (0b10, 0b010) => (false, 4, true, ((low >> 7) & 0x1f) as usize, 2),
+                (0b10, 0b011) => (false, 8, false, ((low >> 7) & 0x1f) as usize, 2),
+                (0b10, 0b110) => (true, 4, false, ((low >> 2) & 0x1f) as usize, 2),
+                (0b10, 0b111) => (true, 8, false, ((low >> 2) & 0x1f) as usize, 2),
+                _ => return None,
+            }
+        } else {
+            let high = unsafe { pc.add(1).read_volatile() } as u32;
+            let inst = low | (high << 16);
+            let funct3 = (inst >> 12) & 0b111;
+            match inst & 0x7f {
+                // LOAD: lb lh lw ld lbu lhu lwu
+                0x03 => {
+                    let size = 1 << (funct3 & 0b11);
+                    let signed = funct3 < 4 && size != 8;
+                    if funct3 == 7 {
+                        return None;
+                    }
+                    (false, size, signed, ((inst >> 7) & 0x1f) as usize, 4)
+                }
+                // STORE: sb sh sw sd
+                0x23 if funct3 < 4 => (true, 1 << funct3, false, ((inst >> 20) & 0x1f) as usize, 4),
+                _ => return None,
+            }
+        };
+        Some(MisalignedAccess {
+            store,
+            size,
+            signed,
+            reg,
+            value: if store { self.x[reg] } else { 0 },
+            inst_len,
+        })
+    }
+
+    fn complete_misaligned(&mut self, access: &MisalignedAccess, loaded: usize) {
+        if !access.store && access.reg != 0 {
+            let shift = 64 - access.size * 8;
+            self.x[access.reg] = if access.signed {
+                (((loaded << shift) as isize) >> shift) as usize
+            } else {
+                loaded
+            };
+        }
+        self.sepc += access.inst_len;
+    }
+
+    fn emulate_counter_read(&mut self) -> bool {
+        const CSR_CYCLE: u32 = 0xc00;
+        const CSR_TIME: u32 = 0xc01;
+        const CSR_INSTRET: u32 = 0xc02;
+        let pc = self.sepc as *const u16;
+        let low = unsafe { pc.read_volatile() } as u32;
+        if low & 0b11 != 0b11 {
+            return false;
+        }
+        let high = unsafe { pc.add(1).read_volatile() } as u32;
+        let inst = low | (high << 16);
+        // rdcycle/rdtime/rdinstret are `csrrs rd, csr, x0`
+        let (opcode, rd, funct3, rs1, csr) = (
+            inst & 0x7f,
+            ((inst >> 7) & 0x1f) as usize,
+            (inst >> 12) & 0b111,
+            (inst >> 15) & 0x1f,
+            inst >> 20,
+        );
+        if opcode != 0x73 || funct3 != 0b010 || rs1 != 0 {
+            return false;
+        }
+        let value = match csr {
+            CSR_CYCLE => riscv::register::cycle::read(),
+            CSR_TIME => riscv::register::time::read(),
+            CSR_INSTRET => riscv::register::instret::read(),
+            _ => return false,
+        };
+        if rd != 0 {
+            self.x[rd] = value;
+        }
+        self.sepc += 4;
+        true
+    }
+
+    fn fx_unavailable(&mut self) {
+        self.user_fx.need_restore = 1;
+        self.user_fx.restore();
+        self.set_fs(FS::Clean);
     }
 
     fn fx_encounter_signal(&mut self){
         self.user_fx.encounter_signal();
+        self.user_vx.encounter_signal();
     }
 
     // fn save_last_user_arg0(&mut self) {
@@ -201,9 +353,16 @@ impl FloatContextHal for FloatContext {
         if self.need_save == 0 {
             return;
         }
+        if !cpu_has(Feature::F) {
+            // no fpu, nothing to save
+            self.need_save = 0;
+            return;
+        }
         self.need_save = 0;
         //log::warn!("FP save");
         unsafe {
+            // the fpu may be off for the user, turn it on for kernel
+            sstatus::set_fs(FS::Clean);
             let mut _t: usize = 1; // as long as not x0
             asm!("
             fsd  f0,  0*8({0})
@@ -258,10 +417,15 @@ impl FloatContextHal for FloatContext {
         if self.need_restore == 0 {
             return;
         }
+        if !cpu_has(Feature::F) {
+            self.need_restore = 0;
+            return;
+        }
         self.need_restore = 0;
         //log::warn!("FP restore");
         //println!("{:#x}", self as *mut Self as usize);
         unsafe {
+            sstatus::set_fs(FS::Clean);
             let mut _t: usize = 1; // as long as not x0
             asm!("
             fld  f0,  0*8({0})
@@ -305,6 +469,149 @@ impl FloatContextHal for FloatContext {
     }
 }
 
+/// sstatus.FS, bits 13-14
+const SSTATUS_FS_MASK: usize = 0b11 << 13;
+
+/// sstatus.VS, bits 9-10
+const SSTATUS_VS_MASK: usize = 0b11 << 9;
+const SSTATUS_VS_INITIAL: usize = 0b01 << 9;
+
+/// max supported VLEN in bytes, i.e. VLEN = 512
+const MAX_VLENB: usize = 64;
+
+/// vector registers and csrs of RVV, saved lazily like [`FloatContext`]
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct VectorContext {
+    pub v: [u8; 32 * MAX_VLENB],
+    pub vstart: usize,
+    pub vcsr: usize,
+    pub vl: usize,
+    pub vtype: usize,
+    pub need_save: u8,
+    pub need_restore: u8,
+    pub signal_dirty: u8,
+}
+
+impl VectorContext {
+    fn vlenb() -> usize {
+        let vlenb: usize;
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +v",
+                "csrr {}, vlenb",
+                ".option pop",
+                out(reg) vlenb,
+            );
+        }
+        vlenb
+    }
+
+    /// the vector unit must be on before touching vector registers
+    fn enable_vector() {
+        unsafe { asm!("csrs sstatus, {}", in(reg) SSTATUS_VS_INITIAL); }
+    }
+
+    fn available() -> bool {
+        cpu_has(Feature::V) && Self::vlenb() <= MAX_VLENB
+    }
+
+    pub fn new() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+
+    pub fn save(&mut self) {
+        if self.need_save == 0 {
+            return;
+        }
+        self.need_save = 0;
+        if !cpu_has(Feature::V) {
+            return;
+        }
+        Self::enable_vector();
+        if !Self::available() {
+            log::warn!("[VectorContext] vlenb {} too large, vector state dropped", Self::vlenb());
+            return;
+        }
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +v",
+                "csrr {vstart}, vstart",
+                "csrr {vcsr}, vcsr",
+                "csrr {vl}, vl",
+                "csrr {vtype}, vtype",
+                "csrr {t}, vlenb",
+                "slli {t}, {t}, 3",
+                "vs8r.v v0, ({buf})",
+                "add {buf}, {buf}, {t}",
+                "vs8r.v v8, ({buf})",
+                "add {buf}, {buf}, {t}",
+                "vs8r.v v16, ({buf})",
+                "add {buf}, {buf}, {t}",
+                "vs8r.v v24, ({buf})",
+                ".option pop",
+                buf = inout(reg) self.v.as_mut_ptr() => _,
+                t = out(reg) _,
+                vstart = out(reg) self.vstart,
+                vcsr = out(reg) self.vcsr,
+                vl = out(reg) self.vl,
+                vtype = out(reg) self.vtype,
+            );
+        }
+    }
+
+    pub fn yield_task(&mut self) {
+        self.save();
+        self.need_restore = 1;
+    }
+
+    pub fn encounter_signal(&mut self) {
+        self.save();
+    }
+
+    pub fn restore(&mut self) {
+        if self.need_restore == 0 {
+            return;
+        }
+        self.need_restore = 0;
+        if !cpu_has(Feature::V) {
+            return;
+        }
+        Self::enable_vector();
+        if !Self::available() {
+            return;
+        }
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +v",
+                "csrr {t}, vlenb",
+                "slli {t}, {t}, 3",
+                "vl8re8.v v0, ({buf})",
+                "add {buf}, {buf}, {t}",
+                "vl8re8.v v8, ({buf})",
+                "add {buf}, {buf}, {t}",
+                "vl8re8.v v16, ({buf})",
+                "add {buf}, {buf}, {t}",
+                "vl8re8.v v24, ({buf})",
+                // vl and vtype can only be written by vsetvl
+                "vsetvl x0, {vl}, {vtype}",
+                "csrw vstart, {vstart}",
+                "csrw vcsr, {vcsr}",
+                ".option pop",
+                buf = inout(reg) self.v.as_ptr() => _,
+                t = out(reg) _,
+                vstart = in(reg) self.vstart,
+                vcsr = in(reg) self.vcsr,
+                vl = in(reg) self.vl,
+                vtype = in(reg) self.vtype,
+            );
+        }
+    }
+}
+
 pub fn init() {
     set_kernel_trap_entry();
 }
@@ -424,6 +731,10 @@ pub unsafe fn try_write_user(uaddr: *const u8) -> Result<(), TrapType> {
 fn get_trap_type() -> TrapType {
     let scause = scause::read();
     let stval = stval::read();
+    // sstatus still holds the FS of the trapped context here
+    let fs = sstatus::read().fs();
+    // kernel code may touch float registers, the trapped FS is restored by `__restore`
+    unsafe { sstatus::set_fs(FS::Clean); }
 
     match scause.cause() {
         Trap::Exception(Exception::Breakpoint) => TrapType::Breakpoint,
@@ -431,7 +742,15 @@ fn get_trap_type() -> TrapType {
         Trap::Exception(Exception::LoadPageFault) => TrapType::LoadPageFault(stval),
         Trap::Exception(Exception::StorePageFault) => TrapType::StorePageFault(stval),
         Trap::Exception(Exception::InstructionPageFault) => TrapType::InstructionPageFault(stval),
-        Trap::Exception(Exception::IllegalInstruction) => TrapType::IllegalInstruction(stval),
+        Trap::Exception(Exception::LoadMisaligned) |
+        Trap::Exception(Exception::StoreMisaligned) => TrapType::MisalignedAccess(stval),
+        Trap::Exception(Exception::IllegalInstruction) => {
+            if cpu_has(Feature::F) && fs == FS::Off {
+                TrapType::FloatUnavailable
+            } else {
+                TrapType::IllegalInstruction(stval)
+            }
+        }
         Trap::Interrupt(Interrupt::SupervisorTimer) => TrapType::Timer,
         Trap::Interrupt(Interrupt::SupervisorExternal) => TrapType::ExternalInterrupt,
         _ => {
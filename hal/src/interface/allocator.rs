@@ -3,6 +3,7 @@ use core::{cell::SyncUnsafeCell, ops::Range};
 use crate::{addr::PhysPageNum, common::FrameTracker};
 
 pub trait FrameAllocatorHal: Sync {
+    #[track_caller]
     fn alloc(&self, cnt: usize) -> Option<Range<PhysPageNum>> {
         self.alloc_with_align(cnt, 0)
     }
@@ -11,6 +12,7 @@ pub trait FrameAllocatorHal: Sync {
 }
 
 pub trait FrameAllocatorTrackerExt: FrameAllocatorHal + Clone {
+    #[track_caller]
     fn alloc_tracker(&self, cnt: usize) -> Option<FrameTracker<Self>> {
         self.alloc_with_align(cnt, 0).map(
             |range_ppn| FrameTracker::new_in(range_ppn, self.clone())
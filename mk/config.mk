@@ -6,6 +6,9 @@ ARCH := riscv64
 # run mode
 AUTOTEST :=
 
+# extra boot arguments, e.g. BOOTARGS=selftest
+export BOOTARGS ?=
+
 # smp
 export SMP := 
 
@@ -23,6 +26,10 @@ FS := ext4
 BOARD := qemu
 SBI ?= rustsbi
 VF2 :=
+# four-level page tables on riscv64, also needs a cpu with sv48 (QEMU default)
+SV48 :=
+# poison freed frames and track their call sites, FRAME_DEBUG=check also verifies the poison
+FRAME_DEBUG :=
 
 # Binutils
 OBJDUMP := rust-objdump --arch-name=${ARCH}
@@ -23,6 +23,18 @@ ifeq ($(VF2),y)
 KERNEL_FEATURES += vf2
 endif
 
+ifeq ($(SV48),y)
+KERNEL_FEATURES += sv48
+endif
+
+ifeq ($(FRAME_DEBUG),y)
+KERNEL_FEATURES += frame_debug
+endif
+
+ifeq ($(FRAME_DEBUG),check)
+KERNEL_FEATURES += frame_debug_check
+endif
+
 # kernel target
 ifeq ($(ARCH), riscv64)
 KERNEL_TARGET := riscv64gc-unknown-none-elf
@@ -146,4 +146,30 @@ impl dyn BlockDevice {
             self.direct_write_block(block_id, buf);
         }
     }
-}
\ No newline at end of file
+
+    /// read consecutive blocks starting at `start_id`,
+    /// in a single request when there is no buffer cache in between
+    pub fn read_blocks(&self, start_id: usize, buf: &mut [u8]) {
+        assert_eq!(buf.len() % BLOCK_SIZE, 0);
+        if self.buffer_cache().is_some() {
+            for (i, block) in buf.chunks_exact_mut(BLOCK_SIZE).enumerate() {
+                self.read_block(start_id + i, block);
+            }
+        } else {
+            self.direct_read_blocks(start_id, buf);
+        }
+    }
+
+    /// write consecutive blocks starting at `start_id`,
+    /// in a single request when there is no buffer cache in between
+    pub fn write_blocks(&self, start_id: usize, buf: &[u8]) {
+        assert_eq!(buf.len() % BLOCK_SIZE, 0);
+        if self.buffer_cache().is_some() {
+            for (i, block) in buf.chunks_exact(BLOCK_SIZE).enumerate() {
+                self.write_block(start_id + i, block);
+            }
+        } else {
+            self.direct_write_blocks(start_id, buf);
+        }
+    }
+}
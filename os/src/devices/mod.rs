@@ -40,6 +40,18 @@ pub enum DeviceMajor {
     Net = 9,
 }
 
+impl DeviceMajor {
+    /// the major of a device number, if any device has it
+    pub fn from_raw(major: u32) -> Option<Self> {
+        match major {
+            4 => Some(Self::Serial),
+            8 => Some(Self::Block),
+            9 => Some(Self::Net),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DevId {
     /// Major Device Number
@@ -155,6 +167,22 @@ pub trait BlockDevice: Send + Sync + Any {
 
     /// Write data from buffer to block
     fn direct_write_block(&self, block_id: usize, buf: &[u8]);
+
+    /// Read consecutive blocks starting at `start_id`, `buf` holds whole blocks
+    fn direct_read_blocks(&self, start_id: usize, buf: &mut [u8]) {
+        let block_size = self.block_size();
+        for (i, block) in buf.chunks_exact_mut(block_size).enumerate() {
+            self.direct_read_block(start_id + i, block);
+        }
+    }
+
+    /// Write consecutive blocks starting at `start_id`, `buf` holds whole blocks
+    fn direct_write_blocks(&self, start_id: usize, buf: &[u8]) {
+        let block_size = self.block_size();
+        for (i, block) in buf.chunks_exact(block_size).enumerate() {
+            self.direct_write_block(start_id + i, block);
+        }
+    }
 }
 
 pub trait NetDevice: Send + Sync + Any {
@@ -227,6 +255,24 @@ pub fn get_device_tree_addr() -> usize {
     hal::board::get_device_tree_addr()
 }
 
+/// boot arguments: `/chosen/bootargs` of the device tree followed by the ones given at build time
+static BOOT_ARGS: Once<String> = Once::new();
+
+/// whether `name` is among the boot arguments
+pub fn boot_arg(name: &str) -> bool {
+    BOOT_ARGS
+        .get()
+        .map_or(false, |args| args.split_whitespace().any(|arg| arg == name))
+}
+
+/// the value of the `name=value` boot argument, the last one if it is given again
+pub fn boot_arg_value(name: &str) -> Option<&'static str> {
+    BOOT_ARGS.get()?
+        .split_whitespace()
+        .filter_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+        .last()
+}
+
 lazy_static! {
     pub static ref DEVICE_MANAGER: SpinNoIrqLock<DeviceManager> = SpinNoIrqLock::new(DeviceManager::new());
 }
@@ -240,9 +286,15 @@ pub fn init() {
         fdt::Fdt::from_ptr(device_tree_addr as _).expect("parse DTB failed!")
     };
 
-    // if let Some(bootargs) = device_tree.chosen().bootargs() {
-    //     println!("Bootargs: {:?}", bootargs);
-    // }
+    BOOT_ARGS.call_once(|| {
+        let mut args = String::from(device_tree.chosen().bootargs().unwrap_or(""));
+        if let Some(extra) = option_env!("BOOTARGS") {
+            args.push(' ');
+            args.push_str(extra);
+        }
+        args
+    });
+    log::info!("bootargs: {:?}", BOOT_ARGS.get().unwrap());
 
     // find all devices
     DEVICE_MANAGER.lock().map_devices(&device_tree);
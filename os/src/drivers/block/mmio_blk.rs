@@ -57,6 +57,19 @@ impl BlockDevice for VirtIOMMIOBlock {
             .write_blocks(block_id, buf)
             .expect("Error when writing VirtIOBlk");
     }
+    fn direct_read_blocks(&self, start_id: usize, buf: &mut [u8]) {
+        // the device takes any number of sectors in one request
+        self.blk
+            .exclusive_access()
+            .read_blocks(start_id, buf)
+            .expect("Error when reading VirtIOBlk");
+    }
+    fn direct_write_blocks(&self, start_id: usize, buf: &[u8]) {
+        self.blk
+            .exclusive_access()
+            .write_blocks(start_id, buf)
+            .expect("Error when writing VirtIOBlk");
+    }
 }
 
 impl Device for VirtIOMMIOBlock {
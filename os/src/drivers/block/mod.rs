@@ -67,5 +67,16 @@ pub fn block_device_test() {
         block_device.read_block(i as usize, &mut read_buffer);
         assert_eq!(write_buffer, read_buffer);
     }
+    // a page worth of blocks per request
+    let mut write_buffer = [0u8; 4096];
+    let mut read_buffer = [0u8; 4096];
+    for i in (0..512).step_by(8) {
+        for (j, byte) in write_buffer.iter_mut().enumerate() {
+            *byte = (i + j / 512) as u8;
+        }
+        block_device.write_blocks(i as usize, &write_buffer);
+        block_device.read_blocks(i as usize, &mut read_buffer);
+        assert_eq!(write_buffer, read_buffer);
+    }
     println!("block device test passed!");
 }
\ No newline at end of file
@@ -15,34 +15,43 @@ use crate::syscall::process;
 use crate::task::manager::TASK_MANAGER;
 use crate::task::INITPROC_PID;
 use crate::task::{schedule::UserTaskFuture,task::TaskControlBlock};
+use crate::timer::get_current_time_duration;
 use crate::timer::timed_task::suspend_timeout;
+use hal::instruction::{Instruction, InstructionHal};
+use sched::{RunQueue, SchedBand, SchedPolicy, ANY_CPU};
+
+pub mod sched;
+
 #[cfg(not(feature = "smp"))]
 pub struct TaskQueue {
-    queue: SpinNoIrqLock<VecDeque<Runnable>>,
+    queue: SpinNoIrqLock<RunQueue>,
 }
 #[allow(dead_code)]
 #[cfg(not(feature = "smp"))]
 impl TaskQueue {
     pub const fn new() -> Self {
         Self {
-            queue: SpinNoIrqLock::new(VecDeque::new()),
+            queue: SpinNoIrqLock::new(RunQueue::new()),
         }
     }
     
     pub fn init(&self)  {
-        *self.queue.lock() = VecDeque::new();
+        *self.queue.lock() = RunQueue::new();
     }
-    pub fn push(&self, runnable: Runnable) {
-        self.queue.lock().push_back(runnable);
+    pub fn push(&self, runnable: Runnable, band: SchedBand) {
+        self.queue.lock().push_back(runnable, band, ANY_CPU);
     }
-    pub fn push_preempt(&self, runnable: Runnable) {
-        self.queue.lock().push_front(runnable);
+    pub fn push_preempt(&self, runnable: Runnable, band: SchedBand) {
+        self.queue.lock().push_front(runnable, band, ANY_CPU);
     }
     pub fn fetch(&self) -> Option<Runnable> {
         self.queue.lock().pop_front()
     }   
-    pub fn pop_back(&self) -> Option<Runnable> {
-        self.queue.lock().pop_back()
+    pub fn pop_back_for(&self, cpu: usize) -> Option<(Runnable, SchedBand, usize)> {
+        self.queue.lock().pop_back_for(cpu)
+    }
+    pub fn top_rt_priority(&self) -> Option<u32> {
+        self.queue.lock().top_rt_priority()
     }
     pub fn is_empty(&self) -> bool {
         self.queue.lock().is_empty()
@@ -65,22 +74,30 @@ pub fn spawn<F>(future: UserTaskFuture<F>) -> (Runnable, Task<F::Output>)
 {
     #[cfg(feature = "smp")]
     let cpu_mask_id = <Arc<TaskControlBlock> as Clone>::clone(&(&future.task.clone())).turn_cpu_mask_id();
+    #[cfg(feature = "smp")]
+    let cpus = if cpu_mask_id == 4 { ANY_CPU } else { 1 << cpu_mask_id };
+    // weak, the closure lives as long as the task allocation
+    let task = Arc::downgrade(&future.task);
     let schedule= move |runnable:Runnable, info: ScheduleInfo | {
+            let band = task.upgrade().map_or(SchedBand::Other, |task| task.sched_band());
             #[cfg(not(feature = "smp"))]
             if info.woken_while_running{
-                TASK_QUEUE.push(runnable);
+                TASK_QUEUE.push(runnable, band);
             }else {
-                TASK_QUEUE.push_preempt(runnable);
+                TASK_QUEUE.push_preempt(runnable, band);
             }
             #[cfg(feature = "smp")]
             if info.woken_while_running{
                 unsafe{
                     if cpu_mask_id == 4 {
-                        PROCESSORS[crate::processor::schedule::select_run_queue_index()]
-                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_back(runnable))
+                        // a yielding task waits behind the others of the processor it ran on
+                        // rather than landing on an idle one and running again at once,
+                        // idle processors pull work over themselves
+                        current_processor()
+                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_back(runnable, band, cpus))
                     } else {
                         PROCESSORS[cpu_mask_id]
-                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_back(runnable))
+                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_back(runnable, band, cpus))
                     }
                     
                 };
@@ -88,10 +105,10 @@ pub fn spawn<F>(future: UserTaskFuture<F>) -> (Runnable, Task<F::Output>)
                 unsafe{
                     if cpu_mask_id == 4 {
                         PROCESSORS[crate::processor::schedule::select_run_queue_index()]
-                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_front(runnable))
+                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_front(runnable, band, cpus))
                     } else {
                         PROCESSORS[cpu_mask_id]
-                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_front(runnable))
+                        .unwrap_with_mut_task_queue(|task_queue|task_queue.push_front(runnable, band, cpus))
                     }
                 }
             }
@@ -108,13 +125,33 @@ pub fn kernel_spawn<F>(future: F) -> (Runnable, Task<F::Output>)
     let schedule= move |runnable:Runnable, _info: ScheduleInfo | {
         // todo: judge push method by ScheduleInfo
         #[cfg(not(feature = "smp"))]
-        TASK_QUEUE.push(runnable);
+        TASK_QUEUE.push(runnable, SchedBand::Other);
         #[cfg(feature = "smp")]
-        current_processor().unwrap_with_mut_task_queue(|task_queue|task_queue.push_back(runnable));
+        current_processor().unwrap_with_mut_task_queue(|task_queue|task_queue.push_back(runnable, SchedBand::Other, ANY_CPU));
     };
     async_task::spawn(future, WithInfo(schedule))
 }
 
+/// priority of the most urgent realtime task waiting on this processor
+fn top_rt_priority() -> Option<u32> {
+    #[cfg(not(feature = "smp"))]
+    return TASK_QUEUE.top_rt_priority();
+    #[cfg(feature = "smp")]
+    return current_processor().unwrap_with_task_queue(|task_queue| task_queue.top_rt_priority());
+}
+
+/// whether the running task gives up the processor on a timer tick:
+/// time sharing tasks do at the end of their slice or when a realtime task waits,
+/// SCHED_RR tasks only to a task at least as urgent and SCHED_FIFO tasks only to a
+/// more urgent one
+pub fn should_preempt(task: &TaskControlBlock) -> bool {
+    match task.policy() {
+        SchedPolicy::Fifo => top_rt_priority().map_or(false, |top| top > task.rt_priority()),
+        SchedPolicy::Rr => top_rt_priority().map_or(false, |top| top >= task.rt_priority()),
+        _ => task.slice_expired() || top_rt_priority().is_some(),
+    }
+}
+
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SystemStatus {
@@ -152,7 +189,7 @@ pub fn do_shutdown() -> Result<(), ()> {
             if task.tid() == INITPROC_PID || !task.is_leader() {
                 return;
             }
-            task.recv_sigs(SigInfo { si_signo: SIGKILL, si_code: SigInfo::KERNEL, si_pid: None });
+            task.recv_sigs(SigInfo { si_signo: SIGKILL, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 });
         });
         Err(())
     } else {
@@ -176,8 +213,8 @@ pub fn run_until_idle() -> usize {
         let processor = current_processor();
         let migrate_id = processor.migrate_id();
         processor.set_need_migrate(processor.id());
-        if let Some(migrate_runnable) = processor.unwrap_with_mut_task_queue(|task_queue| task_queue.pop_back()){
-            unsafe{PROCESSORS[migrate_id].unwrap_with_mut_task_queue(|task_queue| task_queue.push_back(migrate_runnable))};
+        if let Some((migrate_runnable, band, cpus)) = processor.unwrap_with_mut_task_queue(|task_queue| task_queue.pop_back_for(migrate_id)){
+            unsafe{PROCESSORS[migrate_id].unwrap_with_mut_task_queue(|task_queue| task_queue.push_back(migrate_runnable, band, cpus))};
         }
     }
     #[cfg(feature = "smp")]
@@ -192,11 +229,42 @@ pub fn run_until_idle() -> usize {
     len
 }
 
+/// whether the run queue of this processor is empty
+fn queue_is_empty() -> bool {
+    #[cfg(not(feature = "smp"))]
+    return TASK_QUEUE.is_empty();
+    #[cfg(feature = "smp")]
+    return current_processor().unwrap_with_task_queue(|task_queue| task_queue.is_empty());
+}
+
+/// nothing is left to run on this processor: take a task over from a busier one,
+/// or wait for the interrupt that wakes a task up
+fn idle() {
+    #[cfg(feature = "smp")]
+    if crate::processor::schedule::idle_balance(current_processor().id()) {
+        return;
+    }
+    unsafe {
+        // with interrupts off no wake up slips in between the check and the wait
+        Instruction::disable_interrupt();
+        if !queue_is_empty() || os_is_shutting_down() {
+            Instruction::enable_interrupt();
+            return;
+        }
+        let start = get_current_time_duration();
+        Instruction::wait_for_interrupt();
+        current_processor().account_idle(get_current_time_duration() - start);
+    }
+}
+
 pub fn run_until_shutdown() {
     loop {
-        let _tasks = run_until_idle();
+        let tasks = run_until_idle();
         if os_is_shutting_down() {
             break;
         }
+        if tasks == 0 {
+            idle();
+        }
     }
 }
@@ -0,0 +1,169 @@
+//! scheduling policies and the run queue they are served from
+
+use alloc::collections::{BTreeMap, VecDeque};
+use async_task::Runnable;
+use strum::FromRepr;
+
+use crate::task::task::TaskControlBlock;
+
+/// scheduling policies, defined in <uapi/linux/sched.h>
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRepr)]
+pub enum SchedPolicy {
+    /// the default time sharing policy
+    Other = 0,
+    /// realtime, runs until it blocks or yields
+    Fifo = 1,
+    /// realtime, takes turns with tasks of the same priority on timer ticks
+    Rr = 2,
+    /// time sharing for cpu bound work
+    Batch = 3,
+    /// time sharing at the lowest priority
+    Idle = 5,
+}
+
+/// flag or'ed into the policy: children go back to SCHED_OTHER
+pub const SCHED_RESET_ON_FORK: u32 = 0x4000_0000;
+
+impl SchedPolicy {
+    /// whether tasks of the policy are served before time sharing ones
+    pub fn is_realtime(self) -> bool {
+        matches!(self, SchedPolicy::Fifo | SchedPolicy::Rr)
+    }
+    /// the static priorities the policy accepts, (min, max)
+    pub fn priority_range(self) -> (u32, u32) {
+        if self.is_realtime() {
+            (1, 99)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+/// the most favoured nice value
+pub const NICE_MIN: i32 = -20;
+/// the least favoured nice value
+pub const NICE_MAX: i32 = 19;
+
+/// timer ticks a time sharing task runs for before it is preempted, from 1 tick at
+/// nice 19 to 6 at nice -20: a weighted round robin where lower nice values get more
+/// consecutive runs than their peers
+pub fn nice_slice(nice: i32) -> u32 {
+    ((NICE_MAX + 1 - nice.clamp(NICE_MIN, NICE_MAX)) / 8 + 1) as u32
+}
+
+/// band of the run queue a task waits in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedBand {
+    /// SCHED_FIFO and SCHED_RR, by static priority
+    Rt(u32),
+    /// every other policy
+    Other,
+}
+
+impl TaskControlBlock {
+    /// get the scheduling policy
+    pub fn policy(&self) -> SchedPolicy {
+        SchedPolicy::from_repr(self.sched_policy()).unwrap_or(SchedPolicy::Other)
+    }
+    /// set the scheduling policy and its static priority,
+    /// it takes effect the next time the task is queued
+    pub fn set_policy(&self, policy: SchedPolicy, rt_priority: u32) {
+        self.set_rt_priority(rt_priority);
+        self.set_sched_policy(policy as u32);
+    }
+    /// count a timer tick against the time slice, true once the slice is used up
+    pub fn slice_expired(&self) -> bool {
+        let ticks = self.sched_ticks() + 1;
+        if ticks >= nice_slice(self.nice()) {
+            self.set_sched_ticks(0);
+            true
+        } else {
+            self.set_sched_ticks(ticks);
+            false
+        }
+    }
+    /// the band the task is queued in
+    pub fn sched_band(&self) -> SchedBand {
+        if self.policy().is_realtime() {
+            SchedBand::Rt(self.rt_priority())
+        } else {
+            SchedBand::Other
+        }
+    }
+}
+
+/// mask of the processors a task not pinned by sched_setaffinity may run on
+pub const ANY_CPU: usize = usize::MAX;
+
+/// run queue of a processor: a FIFO per realtime priority served from the
+/// highest priority down, then the SCHED_OTHER FIFO; each runnable is queued
+/// with the mask of the processors it may be moved to
+pub struct RunQueue {
+    /// realtime bands keyed by priority, never holds an empty band
+    rt: BTreeMap<u32, VecDeque<(Runnable, usize)>>,
+    /// the time sharing band
+    other: VecDeque<(Runnable, usize)>,
+}
+
+impl RunQueue {
+    /// an empty run queue
+    pub const fn new() -> Self {
+        Self {
+            rt: BTreeMap::new(),
+            other: VecDeque::new(),
+        }
+    }
+    fn band_mut(&mut self, band: SchedBand) -> &mut VecDeque<(Runnable, usize)> {
+        match band {
+            SchedBand::Rt(prio) => self.rt.entry(prio).or_default(),
+            SchedBand::Other => &mut self.other,
+        }
+    }
+    /// queue at the tail of the band
+    pub fn push_back(&mut self, runnable: Runnable, band: SchedBand, cpus: usize) {
+        self.band_mut(band).push_back((runnable, cpus));
+    }
+    /// queue at the head of the band
+    pub fn push_front(&mut self, runnable: Runnable, band: SchedBand, cpus: usize) {
+        self.band_mut(band).push_front((runnable, cpus));
+    }
+    /// take the next runnable to run: the head of the most urgent band
+    pub fn pop_front(&mut self) -> Option<Runnable> {
+        if let Some(mut band) = self.rt.last_entry() {
+            let runnable = band.get_mut().pop_front();
+            if band.get().is_empty() {
+                band.remove();
+            }
+            return runnable.map(|(runnable, _)| runnable);
+        }
+        self.other.pop_front().map(|(runnable, _)| runnable)
+    }
+    /// take the least urgent runnable allowed on processor `cpu`,
+    /// used to move work to that processor
+    pub fn pop_back_for(&mut self, cpu: usize) -> Option<(Runnable, SchedBand, usize)> {
+        let allowed = |&(_, cpus): &(Runnable, usize)| cpus & (1 << cpu) != 0;
+        if let Some(pos) = self.other.iter().rposition(allowed) {
+            let (runnable, cpus) = self.other.remove(pos).unwrap();
+            return Some((runnable, SchedBand::Other, cpus));
+        }
+        let (prio, pos) = self.rt.iter()
+            .find_map(|(&prio, band)| band.iter().rposition(allowed).map(|pos| (prio, pos)))?;
+        let band = self.rt.get_mut(&prio).unwrap();
+        let (runnable, cpus) = band.remove(pos).unwrap();
+        if band.is_empty() {
+            self.rt.remove(&prio);
+        }
+        Some((runnable, SchedBand::Rt(prio), cpus))
+    }
+    /// priority of the most urgent queued realtime task
+    pub fn top_rt_priority(&self) -> Option<u32> {
+        self.rt.last_key_value().map(|(&prio, _)| prio)
+    }
+    pub fn len(&self) -> usize {
+        self.other.len() + self.rt.values().map(|band| band.len()).sum::<usize>()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rt.is_empty() && self.other.is_empty()
+    }
+}
@@ -0,0 +1,143 @@
+//! the raw disk device, read only
+//! reads go straight to the block device, past every cache,
+//! so they show what is really on the disk
+
+use core::cmp;
+
+use alloc::{sync::{Arc, Weak}, vec};
+
+use crate::{config::BLOCK_SIZE, devices::BlockDevice, fs::{vfs::{inode::{encode_dev, InodeMode}, Inode, InodeInner}, Kstat, StatxTimestamp, SuperBlock, Xstat, XstatMask}, syscall::SysError};
+
+/// major of scsi disks, as the disks are named sdX
+const DISK_MAJOR: u32 = 8;
+
+/// read `buf` from the disk at byte `offset`, up to the end of the disk
+pub fn read_disk(device: &Arc<dyn BlockDevice>, offset: usize, buf: &mut [u8]) -> usize {
+    let end = cmp::min(offset + buf.len(), device.size() as usize);
+    if offset >= end {
+        return 0;
+    }
+    // whole blocks are read and the asked part copied out
+    let start_block = offset / BLOCK_SIZE;
+    let end_block = end.div_ceil(BLOCK_SIZE);
+    let mut blocks = vec![0u8; (end_block - start_block) * BLOCK_SIZE];
+    device.read_blocks(start_block, &mut blocks);
+    let skip = offset - start_block * BLOCK_SIZE;
+    let len = end - offset;
+    buf[..len].copy_from_slice(&blocks[skip..skip + len]);
+    len
+}
+
+pub struct BlkDevInode {
+    inner: InodeInner,
+    device: Arc<dyn BlockDevice>,
+}
+
+impl BlkDevInode {
+    pub fn new(super_block: Weak<dyn SuperBlock>, device: Arc<dyn BlockDevice>) -> Arc<Self> {
+        let size = device.size() as usize;
+        Arc::new(Self {
+            inner: InodeInner::new(Some(super_block), InodeMode::BLOCK, size),
+            device,
+        })
+    }
+}
+
+impl Inode for BlkDevInode {
+    fn inode_inner(&self) -> &InodeInner {
+        &self.inner
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, i32> {
+        Ok(read_disk(&self.device, offset, buf))
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, i32> {
+        // writing under a mounted file system would corrupt it
+        Err(SysError::EROFS as i32)
+    }
+
+    fn getattr(&self) -> Kstat {
+        let inner = self.inode_inner();
+        Kstat {
+            st_dev: inner.st_dev(),
+            st_ino: inner.ino as u64,
+            st_mode: inner.mode().bits() as _,
+            st_nlink: inner.nlink() as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: encode_dev(DISK_MAJOR, 0),
+            _pad0: 0,
+            st_size: inner.size() as _,
+            _pad1: 0,
+            st_blksize: BLOCK_SIZE as _,
+            st_blocks: 0,
+            st_atime_sec: inner.atime().tv_sec as _,
+            st_atime_nsec: inner.atime().tv_nsec as _,
+            st_mtime_sec: inner.mtime().tv_sec as _,
+            st_mtime_nsec: inner.mtime().tv_nsec as _,
+            st_ctime_sec: inner.ctime().tv_sec as _,
+            st_ctime_nsec: inner.ctime().tv_nsec as _,
+        }
+    }
+
+    fn getxattr(&self, mask: XstatMask) -> Xstat {
+        const SUPPORTED_MASK: XstatMask = XstatMask::from_bits_truncate({
+            XstatMask::STATX_BLOCKS.bits |
+            XstatMask::STATX_ATIME.bits |
+            XstatMask::STATX_CTIME.bits |
+            XstatMask::STATX_MTIME.bits |
+            XstatMask::STATX_NLINK.bits |
+            XstatMask::STATX_MODE.bits |
+            XstatMask::STATX_SIZE.bits |
+            XstatMask::STATX_INO.bits
+        });
+        let mask = mask & SUPPORTED_MASK;
+        let inner = self.inode_inner();
+        Xstat {
+            stx_mask: mask.bits,
+            stx_blksize: BLOCK_SIZE as _,
+            stx_attributes: 0,
+            stx_nlink: inner.nlink() as u32,
+            stx_uid: 0,
+            stx_gid: 0,
+            stx_mode: inner.mode().bits() as _,
+            stx_ino: inner.ino as u64,
+            stx_size: inner.size() as _,
+            stx_blocks: 0,
+            stx_attributes_mask: 0,
+            stx_atime: StatxTimestamp {
+                tv_sec: inner.atime().tv_sec as _,
+                tv_nsec: inner.atime().tv_nsec as _,
+            },
+            stx_btime: StatxTimestamp {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            stx_ctime: StatxTimestamp {
+                tv_sec: inner.ctime().tv_sec as _,
+                tv_nsec: inner.ctime().tv_nsec as _,
+            },
+            stx_mtime: StatxTimestamp {
+                tv_sec: inner.mtime().tv_sec as _,
+                tv_nsec: inner.mtime().tv_nsec as _,
+            },
+            stx_rdev_major: DISK_MAJOR,
+            stx_rdev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
+            stx_mnt_id: 0,
+            stx_dio_mem_align: 0,
+            std_dio_offset_align: 0,
+            stx_subvol: 0,
+            stx_atomic_write_unit_min: 0,
+            stx_atomic_write_unit_max: 0,
+            stx_atomic_write_segments_max: 0,
+            stx_dio_read_offset_align: 0,
+        }
+    }
+
+    fn support_splice(&self) -> Result<(), SysError> {
+        Err(SysError::EINVAL)
+    }
+}
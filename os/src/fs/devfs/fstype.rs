@@ -21,6 +21,14 @@ impl FSType for DevFsType {
         &self.inner
     }
 
+    fn type_name(&self) -> &str {
+        "devtmpfs"
+    }
+
+    fn magic(&self) -> u64 {
+        0x0102_1994
+    }
+
     fn mount(&'static self, name: &str, parent: Option<Arc<dyn Dentry>>, _flags: MountFlags, dev: Option<Arc<dyn BlockDevice>>) -> Option<Arc<dyn Dentry>> {
         // can be dangerous..
         let fs_type = unsafe {
@@ -10,8 +10,9 @@ use rtc::{RtcDentry, RtcInode};
 use tty::{TtyDentry, TtyFile, TtyInode, TTY};
 use urandom::UrandomInode;
 use zero::ZeroInode;
+use blk::BlkDevInode;
 
-use crate::{fs::{devfs::{cpu_dma_latency::CpuDmaLatencyInode, loop_dev::{LoopDevDentry, LoopDevInode}}, tmpfs::{dentry::TmpDentry, inode::TmpInode}}, sync::mutex::SpinNoIrqLock};
+use crate::{devices::BlockDevice, fs::{devfs::{cpu_dma_latency::CpuDmaLatencyInode, loop_dev::{LoopDevDentry, LoopDevInode}}, tmpfs::{dentry::TmpDentry, inode::TmpInode}}, sync::mutex::SpinNoIrqLock};
 
 use super::{vfs::{inode::InodeMode, Dentry, DentryInner, DentryState, Inode, InodeInner, DCACHE}, OpenFlags, SuperBlock};
 
@@ -24,9 +25,11 @@ pub mod urandom;
 pub mod zero;
 pub mod cpu_dma_latency;
 pub mod loop_dev;
+pub mod blk;
+pub mod node;
 
-/// init the whole /dev
-pub fn init_devfs(root_dentry: Arc<dyn Dentry>) {
+/// init the whole /dev, `disk` is the device of the root file system
+pub fn init_devfs(root_dentry: Arc<dyn Dentry>, disk: Arc<dyn BlockDevice>) {
     let sb = root_dentry.inode().unwrap().inode_inner().super_block.clone();
 
     // add /dev/tty
@@ -85,6 +88,14 @@ pub fn init_devfs(root_dentry: Arc<dyn Dentry>) {
     loop_dev.set_inode(loop_dev_inode);
     root_dentry.add_child(loop_dev);
 
+    // add /dev/vda
+    let vda_dentry = TmpDentry::new("vda", Some(root_dentry.clone()));
+    let vda_inode = BlkDevInode::new(sb.clone().unwrap(), disk);
+    vda_dentry.set_inode(vda_inode);
+    root_dentry.add_child(vda_dentry.clone());
+    log::debug!("dcache insert: {}", vda_dentry.path());
+    DCACHE.lock().insert(vda_dentry.path(), vda_dentry.clone());
+
     // add /dev/shm
     // TODO: now only implement by tmp file
     let shm_dentry = TmpDentry::new("shm", Some(root_dentry.clone()));
@@ -0,0 +1,100 @@
+//! device nodes made by mknod, anywhere in the tree
+//! a node only holds a device number, the device behind it
+//! is looked up in the device manager when the node is opened
+
+use alloc::{boxed::Box, sync::Arc};
+use async_trait::async_trait;
+
+use crate::{devices::{BlockDevice, CharDevice, DevId, DeviceMajor, DEVICE_MANAGER}, fs::{devfs::blk::read_disk, vfs::{file::PollEvents, inode::{decode_dev, InodeMode}, Dentry, File, FileInner}, OpenFlags}, sync::mutex::SpinNoIrqLock, syscall::SysError};
+
+/// the device a node was opened on
+enum NodeDevice {
+    Char(Arc<dyn CharDevice>),
+    Block(Arc<dyn BlockDevice>),
+}
+
+pub struct DevNodeFile {
+    inner: FileInner,
+    device: NodeDevice,
+}
+
+#[async_trait]
+impl File for DevNodeFile {
+    fn file_inner(&self) ->  &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        self.flags().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags().writable()
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
+        match &self.device {
+            NodeDevice::Char(device) => Ok(device.read(buf).await),
+            NodeDevice::Block(device) => {
+                let len = read_disk(device, self.pos(), buf);
+                self.set_pos(self.pos() + len);
+                Ok(len)
+            }
+        }
+    }
+
+    async fn write(&self, buf: &[u8]) -> Result<usize, SysError> {
+        match &self.device {
+            NodeDevice::Char(device) => Ok(device.write(buf).await),
+            // as for /dev/vda, writing under a mounted file system would corrupt it
+            NodeDevice::Block(_) => Err(SysError::EROFS),
+        }
+    }
+
+    async fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, SysError> {
+        match &self.device {
+            NodeDevice::Char(_) => Err(SysError::ESPIPE),
+            NodeDevice::Block(device) => Ok(read_disk(device, offset, buf)),
+        }
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let mut res = PollEvents::empty();
+        match &self.device {
+            NodeDevice::Char(device) => {
+                if events.contains(PollEvents::IN) && device.poll_in().await {
+                    res |= PollEvents::IN;
+                }
+                if events.contains(PollEvents::OUT) && device.poll_out().await {
+                    res |= PollEvents::OUT;
+                }
+            }
+            NodeDevice::Block(_) => res = events & (PollEvents::IN | PollEvents::OUT),
+        }
+        res
+    }
+}
+
+/// open the device node `dentry` refers to,
+/// ENXIO when there is no such device of the kind of the node
+pub fn open_dev_node(dentry: Arc<dyn Dentry>, flags: OpenFlags) -> Result<Arc<dyn File>, SysError> {
+    let inode = dentry.inode().ok_or(SysError::ENOENT)?;
+    let (major, minor) = decode_dev(inode.inode_inner().rdev());
+    let major = DeviceMajor::from_raw(major).ok_or(SysError::ENXIO)?;
+    let device = DEVICE_MANAGER.lock()
+        .devices
+        .get(&DevId { major, minor: minor as usize })
+        .cloned()
+        .ok_or(SysError::ENXIO)?;
+    let device = match inode.inode_type() {
+        InodeMode::CHAR => NodeDevice::Char(device.as_char().ok_or(SysError::ENXIO)?),
+        InodeMode::BLOCK => NodeDevice::Block(device.as_blk().ok_or(SysError::ENXIO)?),
+        _ => return Err(SysError::ENODEV),
+    };
+    let inner = FileInner {
+        offset: 0.into(),
+        dentry,
+        flags: SpinNoIrqLock::new(flags),
+    };
+    Ok(Arc::new(DevNodeFile { inner, device }))
+}
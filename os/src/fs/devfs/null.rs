@@ -114,7 +114,7 @@ impl Inode for NullInode {
         let inner = self.inode_inner();
         let rdev = ((1usize & 0xfff) << 8) | (3usize & 0xff);
         Kstat {
-            st_dev: 1,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
@@ -178,8 +178,8 @@ impl Inode for NullInode {
             },
             stx_rdev_major: 1,
             stx_rdev_minor: 3,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -2,7 +2,7 @@
 
 use alloc::sync::Arc;
 
-use crate::{devices::BlockDevice, fs::{vfs::Inode, SuperBlock, SuperBlockInner}};
+use crate::{devices::BlockDevice, fs::{tmpfs::superblock::mem_statfs, vfs::Inode, StatFs, SuperBlock, SuperBlockInner}};
 
 pub struct DevSuperBlock {
     inner: SuperBlockInner,
@@ -21,4 +21,8 @@ impl SuperBlock for DevSuperBlock {
     fn get_root_inode(&'static self, _name: &str) -> Arc<dyn Inode> {
         self.inner().root.get().unwrap().clone().inode().unwrap()
     }
+    fn statfs(&self) -> StatFs {
+        // devtmpfs is a tmpfs
+        mem_statfs(&self.inner)
+    }
 }
\ No newline at end of file
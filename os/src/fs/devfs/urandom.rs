@@ -7,67 +7,7 @@ use async_trait::async_trait;
 use alloc::boxed::Box;
 use hal::instruction::{Instruction, InstructionHal};
 
-use crate::{config::BLOCK_SIZE, fs::{vfs::{inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags, StatxTimestamp, SuperBlock, Xstat, XstatMask}, sync::mutex::SpinNoIrqLock, syscall::SysError};
-
-/// Linear congruence generator (LCG)
-pub struct SimpleRng {
-    state: u64,
-}
-
-impl SimpleRng {
-    // 使用时间初始化种子
-    pub const fn new() -> Self {
-        // let seed = get_time_duration();
-        let seed = 42;
-        Self { state: seed }
-    }
-
-    // 生成下一个随机数
-    pub fn next_u32(&mut self) -> u32 {
-        const A: u64 = 6364136223846793005;
-        const C: u64 = 1;
-        self.state = self.state.wrapping_mul(A).wrapping_add(C);
-        (self.state >> 32) as u32
-    }
-
-    #[allow(dead_code)]
-    pub fn next_u8(&mut self) -> u8 {
-        // LCG 参数：乘数、增量和模数
-        const A: u64 = 1664525;
-        const C: u64 = 1013904223;
-
-        // 更新内部状态
-        self.state = self.state.wrapping_mul(A).wrapping_add(C);
-
-        // 返回最低 8 位
-        (self.state >> 24) as u8
-    }
-
-    /// Generate a random number of u32 (4 bytes) at a time, and then split it
-    /// into bytes to fill in the buf
-    pub fn fill_buf(&mut self, buf: &mut [u8]) {
-        let mut remaining = buf.len();
-        let mut offset = 0;
-
-        while remaining > 0 {
-            // 生成一个随机的 u32 值
-            let rand = self.next_u32();
-            let rand_bytes = rand.to_le_bytes();
-
-            // 计算要复制的字节数
-            let chunk_size = remaining.min(4);
-
-            // 将 rand_bytes 中的字节填充到 buf 中
-            buf[offset..offset + chunk_size].copy_from_slice(&rand_bytes[..chunk_size]);
-
-            // 更新剩余字节数和偏移量
-            remaining -= chunk_size;
-            offset += chunk_size;
-        }
-    }
-}
-
-pub static RNG: SpinNoIrqLock<SimpleRng> = SpinNoIrqLock::new(SimpleRng::new());
+use crate::{config::BLOCK_SIZE, fs::{vfs::{inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags, StatxTimestamp, SuperBlock, Xstat, XstatMask}, syscall::SysError, utils::random::{fill_random, RNG}};
 
 pub struct UrandomInode {
     inner: InodeInner,
@@ -88,11 +28,13 @@ impl Inode for UrandomInode {
     }
 
     fn read_at(&self, _offset: usize, buf: &mut [u8]) -> Result<usize, i32> {
-        RNG.lock().fill_buf(buf);
+        fill_random(buf);
         Ok(buf.len())
     }
 
+    /// what is written is mixed into the generator
     fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, i32> {
+        RNG.lock().add_entropy(buf);
         Ok(buf.len())
     }
 
@@ -100,7 +42,7 @@ impl Inode for UrandomInode {
         let inner = self.inode_inner();
         let len = inner.size();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
@@ -164,8 +106,8 @@ impl Inode for UrandomInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
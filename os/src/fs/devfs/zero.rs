@@ -39,7 +39,7 @@ impl Inode for ZeroInode {
     fn getattr(&self) -> crate::fs::Kstat {
         let inner = self.inode_inner();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
@@ -103,8 +103,8 @@ impl Inode for ZeroInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
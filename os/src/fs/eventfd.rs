@@ -0,0 +1,312 @@
+//! eventfd: a file holding a 64-bit counter used for event notification
+
+use core::{future::Future, pin::Pin, task::{Context, Poll, Waker}};
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::boxed::Box;
+use async_trait::async_trait;
+
+use crate::{sync::mutex::SpinNoIrqLock, syscall::SysError, utils::get_waker};
+
+use super::{vfs::{file::PollEvents, inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags};
+
+bitflags! {
+    /// flags for eventfd2
+    pub struct EventFdFlags: u32 {
+        /// read returns 1 and decrements the counter
+        const EFD_SEMAPHORE = 1;
+        /// same as O_NONBLOCK
+        const EFD_NONBLOCK = 0o4000;
+        /// same as O_CLOEXEC
+        const EFD_CLOEXEC = 0o2000000;
+    }
+}
+
+impl From<EventFdFlags> for OpenFlags {
+    fn from(value: EventFdFlags) -> Self {
+        let mut flags = OpenFlags::O_RDWR;
+        if value.contains(EventFdFlags::EFD_NONBLOCK) {
+            flags |= OpenFlags::O_NONBLOCK;
+        }
+        if value.contains(EventFdFlags::EFD_CLOEXEC) {
+            flags |= OpenFlags::O_CLOEXEC;
+        }
+        flags
+    }
+}
+
+/// the largest value the counter can hold
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+pub struct EventFdInode {
+    inner: InodeInner,
+    meta: SpinNoIrqLock<EventFdMeta>,
+}
+
+pub struct EventFdMeta {
+    count: u64,
+    read_waker: VecDeque<Waker>,
+    write_waker: VecDeque<Waker>,
+}
+
+impl EventFdMeta {
+    fn readable(&self) -> bool {
+        self.count > 0
+    }
+
+    fn can_add(&self, val: u64) -> bool {
+        val <= EVENTFD_MAX - self.count
+    }
+
+    fn wake_all(wakers: &mut VecDeque<Waker>) {
+        while let Some(waker) = wakers.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+impl EventFdInode {
+    pub fn new(initval: u64) -> Arc<Self> {
+        let inner = InodeInner::new(
+            None,
+            InodeMode::FILE | InodeMode::OWNER_READ | InodeMode::OWNER_WRITE,
+            0,
+        );
+        let meta = SpinNoIrqLock::new(EventFdMeta {
+            count: initval,
+            read_waker: VecDeque::new(),
+            write_waker: VecDeque::new(),
+        });
+        Arc::new(Self { inner, meta })
+    }
+}
+
+impl Inode for EventFdInode {
+    fn inode_inner(&self) -> &InodeInner {
+        &self.inner
+    }
+
+    fn getattr(&self) -> Kstat {
+        let inner = self.inode_inner();
+        Kstat {
+            st_dev: inner.st_dev(),
+            st_ino: inner.ino as u64,
+            st_mode: inner.mode().bits() as _,
+            st_nlink: inner.nlink() as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            _pad0: 0,
+            st_size: 0,
+            _pad1: 0,
+            st_blksize: 0,
+            st_blocks: 0,
+            st_atime_sec: inner.atime().tv_sec as _,
+            st_atime_nsec: inner.atime().tv_nsec as _,
+            st_mtime_sec: inner.mtime().tv_sec as _,
+            st_mtime_nsec: inner.mtime().tv_nsec as _,
+            st_ctime_sec: inner.ctime().tv_sec as _,
+            st_ctime_nsec: inner.ctime().tv_nsec as _,
+        }
+    }
+}
+
+/// wait until the counter is nonzero
+pub struct EventFdReadFuture {
+    eventfd: Arc<EventFdInode>,
+}
+
+impl Future for EventFdReadFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut meta = self.eventfd.meta.lock();
+        if meta.readable() {
+            Poll::Ready(())
+        } else {
+            meta.read_waker.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// wait until `val` can be added without overflow
+pub struct EventFdWriteFuture {
+    eventfd: Arc<EventFdInode>,
+    val: u64,
+}
+
+impl Future for EventFdWriteFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut meta = self.eventfd.meta.lock();
+        if meta.can_add(self.val) {
+            Poll::Ready(())
+        } else {
+            meta.write_waker.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+pub struct EventFdFile {
+    eventfd: Arc<EventFdInode>,
+    semaphore: bool,
+    inner: FileInner,
+}
+
+impl EventFdFile {
+    fn new(dentry: Arc<dyn Dentry>, eventfd: Arc<EventFdInode>, flags: EventFdFlags) -> Arc<Self> {
+        let inner = FileInner {
+            offset: 0.into(),
+            dentry,
+            flags: SpinNoIrqLock::new(flags.into()),
+        };
+        Arc::new(Self {
+            eventfd,
+            semaphore: flags.contains(EventFdFlags::EFD_SEMAPHORE),
+            inner,
+        })
+    }
+
+    fn nonblock(&self) -> bool {
+        self.flags().contains(OpenFlags::O_NONBLOCK)
+    }
+}
+
+#[async_trait]
+impl File for EventFdFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
+        Ok(self.eventfd.clone())
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
+        if buf.len() < 8 {
+            return Err(SysError::EINVAL);
+        }
+        loop {
+            {
+                let mut meta = self.eventfd.meta.lock();
+                if meta.readable() {
+                    let val = if self.semaphore {
+                        meta.count -= 1;
+                        1
+                    } else {
+                        core::mem::take(&mut meta.count)
+                    };
+                    EventFdMeta::wake_all(&mut meta.write_waker);
+                    buf[..8].copy_from_slice(&val.to_ne_bytes());
+                    return Ok(8);
+                }
+            }
+            if self.nonblock() {
+                return Err(SysError::EAGAIN);
+            }
+            EventFdReadFuture { eventfd: self.eventfd.clone() }.await;
+        }
+    }
+
+    async fn write(&self, buf: &[u8]) -> Result<usize, SysError> {
+        if buf.len() < 8 {
+            return Err(SysError::EINVAL);
+        }
+        let val = u64::from_ne_bytes(buf[..8].try_into().unwrap());
+        if val == u64::MAX {
+            return Err(SysError::EINVAL);
+        }
+        loop {
+            {
+                let mut meta = self.eventfd.meta.lock();
+                if meta.can_add(val) {
+                    meta.count += val;
+                    if meta.readable() {
+                        EventFdMeta::wake_all(&mut meta.read_waker);
+                    }
+                    return Ok(8);
+                }
+            }
+            if self.nonblock() {
+                return Err(SysError::EAGAIN);
+            }
+            EventFdWriteFuture { eventfd: self.eventfd.clone(), val }.await;
+        }
+    }
+
+    async fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut meta = self.eventfd.meta.lock();
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if meta.readable() {
+                res |= PollEvents::IN;
+            } else {
+                meta.read_waker.push_back(waker.clone());
+            }
+        }
+        if events.contains(PollEvents::OUT) {
+            if meta.can_add(1) {
+                res |= PollEvents::OUT;
+            } else {
+                meta.write_waker.push_back(waker);
+            }
+        }
+        res
+    }
+}
+
+pub struct EventFdDentry {
+    inner: DentryInner,
+}
+
+impl EventFdDentry {
+    pub fn new() -> Arc<Self> {
+        let inner = DentryInner::new("[eventfd]", None);
+        Arc::new(Self { inner })
+    }
+}
+
+unsafe impl Sync for EventFdDentry {}
+unsafe impl Send for EventFdDentry {}
+
+impl Dentry for EventFdDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+
+    fn new(
+        &self,
+        _name: &str,
+        _parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        panic!("cannot create an eventfd in this way");
+    }
+}
+
+/// global function to create an eventfd file with the initial counter value
+pub fn make_eventfd(initval: u64, flags: EventFdFlags) -> Arc<dyn File> {
+    let eventfd = EventFdInode::new(initval);
+    let dentry = EventFdDentry::new();
+    dentry.set_inode(eventfd.clone());
+    EventFdFile::new(dentry, eventfd, flags)
+}
@@ -47,14 +47,15 @@ impl Disk {
         self.offset = pos as usize % BLOCK_SIZE;
     }
 
-    /// Read within one block, returns the number of bytes read.
+    /// Read within one block or as many whole blocks as fit, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> Result<usize, i32> {
         // info!("block id: {}", self.block_id);
         let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.read_block(self.block_id, &mut buf[0..BLOCK_SIZE]);
-            self.block_id += 1;
-            BLOCK_SIZE
+            // whole blocks, in one request
+            let len = buf.len() / BLOCK_SIZE * BLOCK_SIZE;
+            self.dev.read_blocks(self.block_id, &mut buf[..len]);
+            self.block_id += len / BLOCK_SIZE;
+            len
         } else {
             // partial block
             let mut data = [0u8; BLOCK_SIZE];
@@ -77,13 +78,14 @@ impl Disk {
         Ok(read_size)
     }
 
-    /// Write within one block, returns the number of bytes written.
+    /// Write within one block or as many whole blocks as fit, returns the number of bytes written.
     pub fn write_one(&mut self, buf: &[u8]) -> Result<usize, i32> {
         let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE]);
-            self.block_id += 1;
-            BLOCK_SIZE
+            // whole blocks, in one request
+            let len = buf.len() / BLOCK_SIZE * BLOCK_SIZE;
+            self.dev.write_blocks(self.block_id, &buf[..len]);
+            self.block_id += len / BLOCK_SIZE;
+            len
         } else {
             // partial block
             let mut data = [0u8; BLOCK_SIZE];
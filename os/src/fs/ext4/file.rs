@@ -8,6 +8,7 @@ use async_trait::async_trait;
 use hal::println;
 
 
+use crate::fs::inotify::{notify_dentry, InotifyMask};
 use crate::fs::page::page::PAGE_SIZE;
 use crate::fs::vfs::dentry::global_find_dentry;
 use crate::fs::vfs::file::SeekFrom;
@@ -99,31 +100,43 @@ impl File for Ext4File {
 
     async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
         let inode = self.dentry().unwrap().inode().unwrap();
-
+        if !self.flags().contains(OpenFlags::O_NOATIME) {
+            inode.access()?;
+        }
         let size = inode.cache_read_at(self.pos(), buf).unwrap();
         self.seek(SeekFrom::Current(size as i64)).expect("seek failed");
         Ok(size)
     }
     async fn write(&self, buf: &[u8]) -> Result<usize, SysError> {
+        let inode = self.dentry().unwrap().inode().unwrap();
+        inode.modified()?;
         if self.flags().contains(OpenFlags::O_APPEND) {
-            self.set_pos(self.size());
+            let (pos, size) = inode.append(buf)?;
+            self.set_pos(pos + size);
+            notify_dentry(&self.dentry().unwrap(), InotifyMask::IN_MODIFY);
+            return Ok(size);
         }
         let pos = self.pos();
-        let inode = self.dentry().unwrap().inode().unwrap();
         let size = inode.cache_write_at(pos, buf).unwrap();
         self.set_pos(pos + size);
+        notify_dentry(&self.dentry().unwrap(), InotifyMask::IN_MODIFY);
         Ok(size)
     }
 
     async fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, SysError> {
         let inode = self.dentry().unwrap().inode().unwrap();
+        if !self.flags().contains(OpenFlags::O_NOATIME) {
+            inode.access()?;
+        }
         let size = inode.cache_read_at(offset, buf).unwrap();
         Ok(size)
     }
     
     async fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, SysError> {
         let inode = self.dentry().unwrap().inode().unwrap();
+        inode.modified()?;
         let size = inode.cache_write_at(offset, buf).unwrap();
+        notify_dentry(&self.dentry().unwrap(), InotifyMask::IN_MODIFY);
         Ok(size)
     }
 }
@@ -44,6 +44,12 @@ impl FSType for Ext4FSType {
     fn inner(&self) -> &FSTypeInner {
         &self.inner
     }
+    fn type_name(&self) -> &str {
+        "ext4"
+    }
+    fn magic(&self) -> u64 {
+        0xEF53
+    }
     fn kill_sb(&self) -> isize {
         todo!()
     }
@@ -15,16 +15,18 @@ use alloc::{vec, vec::Vec};
 use log::*;
 use crate::fs::page::cache::PageCache;
 use crate::fs::page::page::{Page, PAGE_SIZE};
-use crate::fs::vfs::inode::InodeMode;
+use crate::fs::vfs::inode::{decode_dev, InodeMode};
 use crate::fs::vfs::{InodeInner, Inode};
-use crate::fs::{Kstat, StatxTimestamp, SuperBlock, Xstat, XstatMask};
+use crate::fs::{FallocFlags, Kstat, StatxTimestamp, SuperBlock, XattrFlags, Xstat, XstatMask, XATTR_LIST_MAX, XATTR_SIZE_MAX};
 use crate::sync::mutex::SpinNoIrqLock;
 use crate::sync::UPSafeCell;
 use crate::utils::rel_path_to_abs;
 use crate::syscall::{SysError, SysResult};
 
 use lwext4_rust::bindings::{
-    EXT4_DE_SYMLINK, O_APPEND, O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET
+    ext4_getxattr, ext4_listxattr, ext4_mknod, ext4_mode_get, ext4_mode_set, ext4_owner_get, ext4_owner_set,
+    ext4_removexattr, ext4_setxattr,
+    EXT4_DE_BLKDEV, EXT4_DE_CHRDEV, EXT4_DE_FIFO, EXT4_DE_SOCK, EXT4_DE_SYMLINK, O_APPEND, O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET
 };
 use lwext4_rust::{Ext4BlockWrapper, Ext4File, InodeTypes, KernelDevOp};
 
@@ -62,13 +64,32 @@ impl Ext4Inode {
             0
         };
 
+        let inner = InodeInner::new(Some(super_block.clone()), mode, size as usize);
+        // the permission bits and the owner on disk, left as above if they cannot be read
+        let cpath = file.get_path();
+        let (mut disk_mode, mut uid, mut gid) = (0u32, 0u32, 0u32);
+        if unsafe { ext4_mode_get(cpath.as_ptr(), &mut disk_mode) } == 0 {
+            inner.set_mode(mode.get_type() | InodeMode::from_bits_truncate(disk_mode & 0o7777));
+        }
+        if unsafe { ext4_owner_get(cpath.as_ptr(), &mut uid, &mut gid) } == 0 {
+            inner.set_uid(uid);
+            inner.set_gid(gid);
+        }
+
         Self {
-            inner: InodeInner::new(Some(super_block.clone()), mode, size as usize),
+            inner,
             file: SpinNoIrqLock::new(file),
             cache: Arc::new(PageCache::new()),
         }
     }
 
+    /// bytes allocated for a file of `size`,
+    /// including pages preallocated past it
+    fn allocated_size(&self, size: usize) -> usize {
+        let size_aligned = (size + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+        cmp::max(size_aligned, self.cache.allocated_end())
+    }
+
     #[allow(unused)]
     fn path_deal_with(&self, path: &str) -> String {
         if path.starts_with('/') {
@@ -120,7 +141,7 @@ impl Inode for Ext4Inode {
         } else {
             let mut page = Page::new(offset);
             let read_size = Arc::get_mut(&mut page).unwrap().read_from(self.clone(), offset);
-            page_cache.insert_page(offset, page.clone());
+            page_cache.insert_evictable(offset, page.clone(), Arc::downgrade(&self) as Weak<dyn Inode>);
             page_cache.update_end(offset + read_size);
             page
         };
@@ -152,6 +173,15 @@ impl Inode for Ext4Inode {
                 full_path.as_str(),
                 InodeTypes::EXT4_DE_SYMLINK)));
         }
+        // special files made by mknod
+        for types in [InodeTypes::EXT4_DE_FIFO, InodeTypes::EXT4_DE_CHRDEV, InodeTypes::EXT4_DE_BLKDEV, InodeTypes::EXT4_DE_SOCK] {
+            if file.check_inode_exist(full_path.as_str(), types.clone()) {
+                return Some(Arc::new(Ext4Inode::new(
+                    self.inode_inner().super_block.clone().unwrap(),
+                    full_path.as_str(),
+                    types)));
+            }
+        }
         //info!("lookup {} failed", name);
         None
     }
@@ -250,7 +280,7 @@ impl Inode for Ext4Inode {
                 let mut page = Page::new(page_offset);
                 let read_size = Arc::get_mut(&mut page).unwrap()
                     .read_from(self.clone(), page_offset);
-                cache.insert_page(page_offset, page.clone());
+                cache.insert_evictable(page_offset, page.clone(), Arc::downgrade(&self) as Weak<dyn Inode>);
                 cache.update_end(page_offset + read_size);
                 page
             };
@@ -300,7 +330,7 @@ impl Inode for Ext4Inode {
                     // write inside the file bound, should read out the data first
                     let _ = Arc::get_mut(&mut page).unwrap().read_from(self.clone(), page_offset);
                 }
-                cache.insert_page(page_offset, page.clone());
+                cache.insert_evictable(page_offset, page.clone(), Arc::downgrade(&self) as Weak<dyn Inode>);
                 page
             };
 
@@ -325,10 +355,52 @@ impl Inode for Ext4Inode {
         let path = file.get_path();
         let path = path.to_str().unwrap();
         file.file_open(path, O_RDWR).expect("file open failed");
-        let t = file.file_truncate(size as _).map_err(|e| SysError::from_i32(e))?;
+        // expanding only happens in page cache, the disk file follows at flush
+        if size < file.file_size() as usize {
+            file.file_truncate(size as _).map_err(|e| SysError::from_i32(e))?;
+        }
         let _ = file.file_close();
         self.cache.truncate(size);
-        Ok(t)
+        self.inner.set_size(size);
+        Ok(size)
+    }
+
+    fn fallocate(self: Arc<Self>, mode: FallocFlags, offset: usize, len: usize) -> Result<(), SysError> {
+        let end = offset + len;
+        let size = self.getattr().st_size as usize;
+        let file_size = {
+            let mut file = self.file.lock();
+            let cpath = file.get_path();
+            let path = cpath.to_str().unwrap();
+            file.file_open(path, O_RDWR).map_err(|e| SysError::from_i32(e))?;
+            let file_size = file.file_size() as usize;
+            let _ = file.file_close();
+            file_size
+        };
+        let range_end = if mode.contains(FallocFlags::FALLOC_FL_PUNCH_HOLE) {
+            cmp::min(end, size)
+        } else {
+            end
+        };
+        // bring the pages in, so that the cache owns the range
+        let cache = self.cache.clone();
+        for page_offset in (offset / PAGE_SIZE * PAGE_SIZE..range_end).step_by(PAGE_SIZE) {
+            if cache.get_page(page_offset).is_some() {
+                continue;
+            }
+            let mut page = Page::new(page_offset);
+            if page_offset < file_size {
+                let _ = Arc::get_mut(&mut page).unwrap().read_from(self.clone(), page_offset);
+            }
+            cache.insert_evictable(page_offset, page, Arc::downgrade(&self) as Weak<dyn Inode>);
+        }
+        if mode.contains(FallocFlags::FALLOC_FL_PUNCH_HOLE) {
+            // the data on disk is still there, keep zeroed pages over it
+            cache.zero_range(offset, range_end, false);
+        } else if !mode.contains(FallocFlags::FALLOC_FL_KEEP_SIZE) && end > size {
+            cache.update_end(end);
+        }
+        Ok(())
     }
 
     /// Create a new inode and return the inode
@@ -374,6 +446,34 @@ impl Inode for Ext4Inode {
         }
     }
 
+    fn mknod(&self, name: &str, mode: InodeMode, rdev: u64) -> Result<Arc<dyn Inode>, SysError> {
+        let filetype = match mode.get_type() {
+            InodeMode::FIFO => EXT4_DE_FIFO,
+            InodeMode::CHAR => EXT4_DE_CHRDEV,
+            InodeMode::BLOCK => EXT4_DE_BLKDEV,
+            InodeMode::SOCKET => EXT4_DE_SOCK,
+            _ => return self.create(name, mode),
+        };
+        let mut file = self.file.lock();
+        let parent_path = file.get_path().to_str().expect("cpath failed").to_string();
+        let fpath = rel_path_to_abs(&parent_path, name).unwrap();
+        info!("mknod {:?} on Ext4fs: {}", mode.get_type(), fpath);
+        let types: InodeTypes = mode.get_type().into();
+        if file.check_inode_exist(&fpath, types.clone()) {
+            return Err(SysError::EEXIST);
+        }
+        let cpath = CString::new(fpath.as_str()).map_err(|_| SysError::EINVAL)?;
+        // lwext4 keeps the device number on disk but has no call to read it back,
+        // so it only lives in the inode from here on
+        let ret = unsafe { ext4_mknod(cpath.as_ptr(), filetype as _, rdev as u32) };
+        if ret != 0 {
+            return Err(SysError::from_i32(ret));
+        }
+        let inode = Ext4Inode::new(self.inode_inner().super_block.clone().unwrap(), &fpath, types);
+        inode.inner.set_rdev(rdev);
+        Ok(Arc::new(inode))
+    }
+
     fn getattr(&self) -> Kstat {
         let inner = self.inode_inner();
         let file = self.file.lock();
@@ -394,18 +494,18 @@ impl Inode for Ext4Inode {
         };
         // log::info!("file size: {}", size);
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
-            st_uid: 0,
-            st_gid: 0,
-            st_rdev: 0,
+            st_uid: inner.uid(),
+            st_gid: inner.gid(),
+            st_rdev: inner.rdev(),
             _pad0: 0,
             st_size: size as _,
             _pad1: 0,
             st_blksize: BLOCK_SIZE as _,
-            st_blocks: (size / BLOCK_SIZE) as _,
+            st_blocks: (self.allocated_size(size) / BLOCK_SIZE) as _,
             st_atime_sec: inner.atime().tv_sec as _,
             st_atime_nsec: inner.atime().tv_nsec as _,
             st_mtime_sec: inner.mtime().tv_sec as _,
@@ -423,6 +523,8 @@ impl Inode for Ext4Inode {
             XstatMask::STATX_MTIME.bits |
             XstatMask::STATX_NLINK.bits |
             XstatMask::STATX_MODE.bits |
+            XstatMask::STATX_UID.bits |
+            XstatMask::STATX_GID.bits |
             XstatMask::STATX_SIZE.bits |
             XstatMask::STATX_INO.bits
         });
@@ -448,12 +550,12 @@ impl Inode for Ext4Inode {
             stx_blksize: BLOCK_SIZE as _,
             stx_attributes: 0,
             stx_nlink: inner.nlink() as u32,
-            stx_uid: 0,
-            stx_gid: 0,
+            stx_uid: inner.uid(),
+            stx_gid: inner.gid(),
             stx_mode: inner.mode().bits() as _,
             stx_ino: inner.ino as u64,
             stx_size: size as _,
-            stx_blocks: (size / BLOCK_SIZE) as _,
+            stx_blocks: (self.allocated_size(size) / BLOCK_SIZE) as _,
             stx_attributes_mask: 0,
             stx_atime: StatxTimestamp {
                 tv_sec: inner.atime().tv_sec as _,
@@ -471,10 +573,10 @@ impl Inode for Ext4Inode {
                 tv_sec: inner.mtime().tv_sec as _,
                 tv_nsec: inner.mtime().tv_nsec as _,
             },
-            stx_rdev_major: 0,
-            stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_rdev_major: decode_dev(inner.rdev()).0,
+            stx_rdev_minor: decode_dev(inner.rdev()).1,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -549,7 +651,9 @@ impl Inode for Ext4Inode {
         assert!(!fpath.is_empty()); // already check at `root.rs`
 
         match ty {
-            InodeTypes::EXT4_DE_REG_FILE | InodeTypes::EXT4_DE_SYMLINK => {
+            InodeTypes::EXT4_DE_REG_FILE | InodeTypes::EXT4_DE_SYMLINK
+            | InodeTypes::EXT4_DE_FIFO | InodeTypes::EXT4_DE_CHRDEV
+            | InodeTypes::EXT4_DE_BLKDEV | InodeTypes::EXT4_DE_SOCK => {
                 file.file_remove(fpath)
             }
             InodeTypes::EXT4_DE_DIR => {
@@ -566,29 +670,111 @@ impl Inode for Ext4Inode {
         let path = file.get_path();
         let old_path = path.to_str().expect("failed");
         let ty = file.get_type();
-        let old_mode = InodeMode::from_inode_type(ty).get_type();
+        let old_mode = InodeMode::from_inode_type(ty.clone()).get_type();
         log::debug!("old mode: {:x}", old_mode.bits());
         log::info!("[Ext4] rename {} -> {}", old_path, target);
         if let Some(new) = new_inode {
             let new_mode = new.inode_type();
-            if new_mode != old_mode {
-                return match (old_mode, new_mode) {
-                    (InodeMode::FILE, InodeMode::DIR) => Err(SysError::EISDIR),
-                    (InodeMode::DIR, InodeMode::FILE) => Err(SysError::ENOTDIR),
-                    _ => unimplemented!(),
-                };
+            match (old_mode == InodeMode::DIR, new_mode == InodeMode::DIR) {
+                (false, true) => return Err(SysError::EISDIR),
+                (true, false) => return Err(SysError::ENOTDIR),
+                _ => {}
             }
             let _ = match new_mode {
-                InodeMode::FILE => file.file_remove(target),
+                InodeMode::FILE | InodeMode::LINK => file.file_remove(target),
                 InodeMode::DIR => file.dir_rm(target),
                 _ => unimplemented!(),
             };
         }
-        let _ = match old_mode {
-            InodeMode::FILE => file.file_rename(old_path, target),
+        match old_mode {
+            InodeMode::FILE | InodeMode::LINK => file.file_rename(old_path, target),
             InodeMode::DIR => file.dir_mv(old_path, target),
             _ => unimplemented!(),
+        }.map_err(SysError::from_i32)?;
+        // lwext4 finds the file by its path from now on
+        *file = Ext4File::new(target, ty);
+        Ok(())
+    }
+
+    fn moved(&self, path: &str) {
+        let mut file = self.file.lock();
+        let ty = file.get_type();
+        *file = Ext4File::new(path, ty);
+    }
+
+    fn setattr(&self) -> Result<(), SysError> {
+        let inner = self.inode_inner();
+        let cpath = self.file.lock().get_path();
+        let mode = inner.mode().bits() & 0o7777;
+        if unsafe { ext4_mode_set(cpath.as_ptr(), mode) } != 0 {
+            return Err(SysError::EIO);
+        }
+        if unsafe { ext4_owner_set(cpath.as_ptr(), inner.uid(), inner.gid()) } != 0 {
+            return Err(SysError::EIO);
+        }
+        Ok(())
+    }
+
+    fn get_xattr(&self, name: &str) -> Result<Vec<u8>, SysError> {
+        let cpath = self.file.lock().get_path();
+        let mut buf = vec![0u8; XATTR_SIZE_MAX];
+        let mut size = 0;
+        let ret = unsafe {
+            ext4_getxattr(cpath.as_ptr(), name.as_ptr() as _, name.len(),
+                buf.as_mut_ptr() as _, buf.len(), &mut size)
+        };
+        if ret != 0 {
+            return Err(ext4_xattr_error(ret));
+        }
+        buf.truncate(size);
+        Ok(buf)
+    }
+
+    fn set_xattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> Result<(), SysError> {
+        // lwext4 always replaces, whether the attribute is there is asked first
+        if !flags.is_empty() {
+            match self.get_xattr(name) {
+                Ok(_) if flags.contains(XattrFlags::XATTR_CREATE) => return Err(SysError::EEXIST),
+                Err(SysError::ENODATA) if flags.contains(XattrFlags::XATTR_REPLACE) => return Err(SysError::ENODATA),
+                Err(e) if e != SysError::ENODATA => return Err(e),
+                _ => {}
+            }
+        }
+        let cpath = self.file.lock().get_path();
+        let ret = unsafe {
+            ext4_setxattr(cpath.as_ptr(), name.as_ptr() as _, name.len(),
+                value.as_ptr() as _, value.len())
         };
+        if ret != 0 {
+            return Err(ext4_xattr_error(ret));
+        }
+        Ok(())
+    }
+
+    fn list_xattr(&self) -> Result<Vec<String>, SysError> {
+        let cpath = self.file.lock().get_path();
+        let mut buf = vec![0u8; XATTR_LIST_MAX];
+        let mut size = 0;
+        let ret = unsafe {
+            ext4_listxattr(cpath.as_ptr(), buf.as_mut_ptr() as _, buf.len(), &mut size)
+        };
+        if ret != 0 {
+            return Err(ext4_xattr_error(ret));
+        }
+        // the names come out each ended by a NUL
+        Ok(buf[..size]
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect())
+    }
+
+    fn remove_xattr(&self, name: &str) -> Result<(), SysError> {
+        let cpath = self.file.lock().get_path();
+        let ret = unsafe { ext4_removexattr(cpath.as_ptr(), name.as_ptr() as _, name.len()) };
+        if ret != 0 {
+            return Err(ext4_xattr_error(ret));
+        }
         Ok(())
     }
 
@@ -599,6 +785,16 @@ impl Inode for Ext4Inode {
             page.set_clean();
         }
     }
+
+    fn sync(&self, _data_only: bool) -> Result<(), SysError> {
+        self.cache.flush(self).map_err(|_| SysError::EIO)?;
+        // even fdatasync needs the size and the block map of the file on disk,
+        // lwext4 caches them in the same blocks as the rest of the metadata
+        match self.inode_inner().super_block.as_ref().and_then(|sb| sb.upgrade()) {
+            Some(sb) => sb.sync(),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Drop for Ext4Inode {
@@ -623,6 +819,11 @@ impl Drop for Ext4Inode {
     }
 }
 
+/// the error of a failed lwext4 xattr call, which returns errnos
+fn ext4_xattr_error(ret: i32) -> SysError {
+    SysError::from_repr(ret).unwrap_or(SysError::EIO)
+}
+
 /// translate between InodeTypes and InodeMode
 impl InodeMode {
     pub fn from_inode_type(itype: InodeTypes) -> Self {
@@ -1,6 +1,11 @@
 //! ext4 file system implement for the VFS super block
+use crate::fs::StatFs;
 use crate::fs::vfs::{Dentry, DentryInner, DentryState, Inode, SuperBlock, SuperBlockInner, DCACHE};
+use crate::config::BLOCK_SIZE;
+use crate::syscall::SysError;
+use alloc::ffi::CString;
 use alloc::string::ToString;
+use lwext4_rust::bindings::{ext4_cache_flush, ext4_mount_point_stats, ext4_mount_stats};
 use lwext4_rust::{Ext4BlockWrapper, Ext4File, InodeTypes, KernelDevOp};
 use super::{disk::Disk, Ext4Dentry};
 use super::inode::Ext4Inode;
@@ -13,6 +18,8 @@ pub struct Ext4SuperBlock {
     inner: SuperBlockInner,
     /// lwext4 object to control file system
     block: Ext4BlockWrapper<Disk>,
+    /// mount point the file system is known by in lwext4
+    mount_point: &'static str,
 }
 
 unsafe impl Send for Ext4SuperBlock {}
@@ -26,7 +33,7 @@ impl Ext4SuperBlock {
         let block_device = inner.device.as_ref().unwrap().clone();
         let disk = Disk::new(block_device);
         let block = Ext4BlockWrapper::<Disk>::new(disk, mount_point, device_name).expect("failed to create ext4fs");
-        Arc::new(Self {inner, block})
+        Arc::new(Self {inner, block, mount_point})
     }
 }
 
@@ -37,4 +44,28 @@ impl SuperBlock for Ext4SuperBlock {
     fn get_root_inode(&'static self, _name: &str) -> Arc<dyn Inode> {
         self.inner().root.get().unwrap().clone().inode().unwrap()
     }
+    fn sync(&self) -> Result<(), SysError> {
+        // the blocks lwext4 holds on to: inodes, bitmaps, extent trees and directories
+        let mount_point = CString::new(self.mount_point).unwrap();
+        match unsafe { ext4_cache_flush(mount_point.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(SysError::EIO),
+        }
+    }
+    fn statfs(&self) -> StatFs {
+        // the counts lwext4 keeps in the ext4 super block and the group descriptors
+        let mount_point = CString::new(self.mount_point).unwrap();
+        let mut stats: ext4_mount_stats = unsafe { core::mem::zeroed() };
+        if unsafe { ext4_mount_point_stats(mount_point.as_ptr(), &mut stats) } != 0 {
+            log::warn!("[Ext4SuperBlock] no stats of {}", self.mount_point);
+            return self.inner.statfs(BLOCK_SIZE, 0, 0, 0, 0);
+        }
+        self.inner.statfs(
+            stats.block_size as usize,
+            stats.blocks_count,
+            stats.free_blocks_count,
+            stats.inodes_count as u64,
+            stats.free_inodes_count as u64,
+        )
+    }
 }
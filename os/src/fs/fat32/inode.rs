@@ -123,7 +123,7 @@ impl Inode for FatFileInode {
 
     fn getattr(&self) -> crate::fs::Kstat {
         Kstat {
-            st_ino: 1,
+            st_ino: self.inode_inner().ino as u64,
             st_mode: InodeMode::FILE.bits(),
             st_atime_sec: 0,
             st_atime_nsec: 0,
@@ -131,7 +131,7 @@ impl Inode for FatFileInode {
             st_ctime_sec: 0,
             st_ctime_nsec: 0,
             st_blocks: self.file.exclusive_access().size as i64 / 512,
-            st_dev: 0,
+            st_dev: self.inode_inner().st_dev(),
             st_gid: 0,
             st_mtime_sec: 0,
             st_mtime_nsec: 0,
@@ -161,7 +161,7 @@ impl Inode for FatFileInode {
             stx_uid: 0,
             stx_gid: 0,
             stx_mode: InodeMode::FILE.bits() as _,
-            stx_ino: 1,
+            stx_ino: self.inode_inner().ino as u64,
             stx_size: self.file.exclusive_access().size as u64,
             stx_blocks: self.file.exclusive_access().size as u64 / 512,
             stx_attributes_mask: 0,
@@ -183,8 +183,8 @@ impl Inode for FatFileInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: self.inode_inner().dev().0,
+            stx_dev_minor: self.inode_inner().dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -251,7 +251,7 @@ impl Inode for FatDirInode {
     }
     fn getattr(&self) -> crate::fs::Kstat {
         Kstat {
-            st_ino: 1,
+            st_ino: self.inode_inner().ino as u64,
             st_mode: InodeMode::DIR.bits(),
             st_atime_sec: 0,
             st_atime_nsec: 0,
@@ -259,7 +259,7 @@ impl Inode for FatDirInode {
             st_ctime_sec: 0,
             st_ctime_nsec: 0,
             st_blocks: self.dir.exclusive_access().size as i64 / 512,
-            st_dev: 0,
+            st_dev: self.inode_inner().st_dev(),
             st_gid: 0,
             st_mtime_sec: 0,
             st_mtime_nsec: 0,
@@ -288,7 +288,7 @@ impl Inode for FatDirInode {
             stx_uid: 0,
             stx_gid: 0,
             stx_mode: InodeMode::DIR.bits() as _,
-            stx_ino: 1,
+            stx_ino: self.inode_inner().ino as u64,
             stx_size: self.dir.exclusive_access().size as u64,
             stx_blocks: self.dir.exclusive_access().size as u64 / 512,
             stx_attributes_mask: 0,
@@ -310,8 +310,8 @@ impl Inode for FatDirInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: self.inode_inner().dev().0,
+            stx_dev_minor: self.inode_inner().dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
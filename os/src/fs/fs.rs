@@ -67,7 +67,8 @@ impl CNXFS {
     /// helper method to generate written file
     pub fn create_sys_file(contents: Arc<dyn InodeContent>, name: &str, parent: Arc<dyn Dentry>) -> Arc<dyn Dentry> {
         let dentry = TmpDentry::new(name, Some(parent.clone()));
-        let inode = TmpSysInode::new(InodeMode::FILE, contents);
+        let sb = parent.inode().and_then(|inode| inode.inode_inner().super_block.clone());
+        let inode = TmpSysInode::new(sb, InodeMode::FILE, contents);
         dentry.set_inode(inode);
         parent.add_child(dentry.clone());
         dentry
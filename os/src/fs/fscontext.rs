@@ -0,0 +1,360 @@
+//! the new mount api: fsopen and fsconfig describe a file system in a context,
+//! fsmount turns the context into a detached mount, move_mount attaches it
+
+use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc};
+use alloc::boxed::Box;
+use async_trait::async_trait;
+use strum::FromRepr;
+
+use crate::{sync::mutex::SpinNoIrqLock, syscall::SysError};
+
+use super::{get_filesystem, vfs::{fstype::{FSType, MountFlags}, inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags, FS_MANAGER};
+
+/// file systems fsopen can make a new instance of, those on a block device
+/// are mounted once at boot and devfs needs that device
+const CONTEXT_FS: [&str; 2] = ["tmpfs", "procfs"];
+
+bitflags! {
+    /// flags for fsopen
+    pub struct FsOpenFlags: u32 {
+        const FSOPEN_CLOEXEC = 1;
+    }
+}
+
+bitflags! {
+    /// flags for fsmount
+    pub struct FsMountFlags: u32 {
+        const FSMOUNT_CLOEXEC = 1;
+    }
+}
+
+bitflags! {
+    /// mount attributes given to fsmount, defined in <uapi/linux/mount.h>
+    pub struct MountAttr: u32 {
+        const MOUNT_ATTR_RDONLY = 0x1;
+        const MOUNT_ATTR_NOSUID = 0x2;
+        const MOUNT_ATTR_NODEV = 0x4;
+        const MOUNT_ATTR_NOEXEC = 0x8;
+        const MOUNT_ATTR_NOATIME = 0x10;
+        const MOUNT_ATTR_STRICTATIME = 0x20;
+        const MOUNT_ATTR_NODIRATIME = 0x80;
+        const MOUNT_ATTR_NOSYMFOLLOW = 0x200000;
+    }
+}
+
+impl From<MountAttr> for MountFlags {
+    fn from(value: MountAttr) -> Self {
+        let mut flags = MountFlags::empty();
+        let pairs = [
+            (MountAttr::MOUNT_ATTR_RDONLY, MountFlags::MS_RDONLY),
+            (MountAttr::MOUNT_ATTR_NOSUID, MountFlags::MS_NOSUID),
+            (MountAttr::MOUNT_ATTR_NODEV, MountFlags::MS_NODEV),
+            (MountAttr::MOUNT_ATTR_NOEXEC, MountFlags::MS_NOEXEC),
+            (MountAttr::MOUNT_ATTR_NOATIME, MountFlags::MS_NOATIME),
+            (MountAttr::MOUNT_ATTR_STRICTATIME, MountFlags::MS_STRICTATIME),
+            (MountAttr::MOUNT_ATTR_NODIRATIME, MountFlags::MS_NODEIRATIME),
+            (MountAttr::MOUNT_ATTR_NOSYMFOLLOW, MountFlags::MS_NOSYMFOLLOW),
+        ];
+        for (attr, flag) in pairs {
+            if value.contains(attr) {
+                flags |= flag;
+            }
+        }
+        flags
+    }
+}
+
+bitflags! {
+    /// flags for move_mount
+    pub struct MoveMountFlags: u32 {
+        /// follow symlinks on from path
+        const MOVE_MOUNT_F_SYMLINKS = 0x1;
+        /// follow automounts on from path
+        const MOVE_MOUNT_F_AUTOMOUNTS = 0x2;
+        /// empty from path permitted
+        const MOVE_MOUNT_F_EMPTY_PATH = 0x4;
+        /// follow symlinks on to path
+        const MOVE_MOUNT_T_SYMLINKS = 0x10;
+        /// follow automounts on to path
+        const MOVE_MOUNT_T_AUTOMOUNTS = 0x20;
+        /// empty to path permitted
+        const MOVE_MOUNT_T_EMPTY_PATH = 0x40;
+    }
+}
+
+/// commands of fsconfig
+#[derive(FromRepr, Debug, Eq, PartialEq, Clone, Copy)]
+#[allow(non_camel_case_types)]
+#[repr(u32)]
+pub enum FsConfigCmd {
+    /// set a parameter without a value
+    FSCONFIG_SET_FLAG = 0,
+    /// set a parameter to a string
+    FSCONFIG_SET_STRING = 1,
+    /// set a parameter to a blob
+    FSCONFIG_SET_BINARY = 2,
+    /// set a parameter to an object by path
+    FSCONFIG_SET_PATH = 3,
+    /// set a parameter to an object by path, the path may be empty
+    FSCONFIG_SET_PATH_EMPTY = 4,
+    /// set a parameter to an object by fd
+    FSCONFIG_SET_FD = 5,
+    /// create a new superblock
+    FSCONFIG_CMD_CREATE = 6,
+    /// reconfigure an existing superblock
+    FSCONFIG_CMD_RECONFIGURE = 7,
+    /// create a new superblock, never sharing one
+    FSCONFIG_CMD_CREATE_EXCL = 8,
+}
+
+/// what a file system context is ready for
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum FsContextPhase {
+    /// taking parameters
+    Config,
+    /// created, waiting for fsmount
+    Created,
+    /// turned into a mount already
+    Mounted,
+}
+
+/// the description of a file system instance being set up
+pub struct FsContext {
+    /// the file system type to instantiate
+    pub fs: &'static Arc<dyn FSType>,
+    pub phase: FsContextPhase,
+    /// the "source" parameter
+    pub source: Option<String>,
+    /// superblock flags from the "ro" and "rw" parameters
+    pub sb_flags: MountFlags,
+    /// parameters the file system type takes but does not act on
+    pub options: BTreeMap<String, Option<String>>,
+}
+
+impl FsContext {
+    /// set a parameter, only before the superblock is created
+    pub fn set_param(&mut self, key: &str, value: Option<&str>) -> Result<(), SysError> {
+        if self.phase != FsContextPhase::Config {
+            return Err(SysError::EBUSY);
+        }
+        match (key, value) {
+            ("source", Some(source)) => {
+                if self.source.is_some() {
+                    return Err(SysError::EINVAL);
+                }
+                self.source = Some(source.into());
+            }
+            ("ro", None) => self.sb_flags |= MountFlags::MS_RDONLY,
+            ("rw", None) => self.sb_flags.remove(MountFlags::MS_RDONLY),
+            ("silent", None) => self.sb_flags |= MountFlags::MS_SILENT,
+            ("source" | "ro" | "rw" | "silent", _) => return Err(SysError::EINVAL),
+            (key, value) => {
+                self.options.insert(key.into(), value.map(String::from));
+            }
+        }
+        Ok(())
+    }
+
+    /// FSCONFIG_CMD_CREATE: the parameters are complete
+    pub fn create(&mut self) -> Result<(), SysError> {
+        if self.phase != FsContextPhase::Config {
+            return Err(SysError::EBUSY);
+        }
+        self.phase = FsContextPhase::Created;
+        Ok(())
+    }
+}
+
+/// a mount made by fsmount and not yet attached anywhere; the superblock
+/// is made when it is attached, for a file system is mounted on a path
+pub struct DetachedMount {
+    pub fs: &'static Arc<dyn FSType>,
+    /// superblock flags and mount attributes together
+    pub flags: MountFlags,
+    pub source: Option<String>,
+    /// the path it was attached on by move_mount
+    pub attached: Option<String>,
+}
+
+pub struct MountApiInode {
+    inner: InodeInner,
+}
+
+impl MountApiInode {
+    pub fn new() -> Arc<Self> {
+        let inner = InodeInner::new(
+            None,
+            InodeMode::FILE | InodeMode::OWNER_READ | InodeMode::OWNER_WRITE,
+            0,
+        );
+        Arc::new(Self { inner })
+    }
+}
+
+impl Inode for MountApiInode {
+    fn inode_inner(&self) -> &InodeInner {
+        &self.inner
+    }
+
+    fn getattr(&self) -> Kstat {
+        let inner = self.inode_inner();
+        Kstat {
+            st_dev: inner.st_dev(),
+            st_ino: inner.ino as u64,
+            st_mode: inner.mode().bits() as _,
+            st_nlink: inner.nlink() as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            _pad0: 0,
+            st_size: 0,
+            _pad1: 0,
+            st_blksize: 0,
+            st_blocks: 0,
+            st_atime_sec: inner.atime().tv_sec as _,
+            st_atime_nsec: inner.atime().tv_nsec as _,
+            st_mtime_sec: inner.mtime().tv_sec as _,
+            st_mtime_nsec: inner.mtime().tv_nsec as _,
+            st_ctime_sec: inner.ctime().tv_sec as _,
+            st_ctime_nsec: inner.ctime().tv_nsec as _,
+        }
+    }
+}
+
+/// the file fsopen returns
+pub struct FsContextFile {
+    inode: Arc<MountApiInode>,
+    pub ctx: SpinNoIrqLock<FsContext>,
+    inner: FileInner,
+}
+
+/// the file fsmount returns
+pub struct MountFile {
+    inode: Arc<MountApiInode>,
+    pub mount: SpinNoIrqLock<DetachedMount>,
+    inner: FileInner,
+}
+
+#[async_trait]
+impl File for FsContextFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
+        Ok(self.inode.clone())
+    }
+
+    /// reads the error messages of the context, none are kept
+    async fn read(&self, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::ENODATA)
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+}
+
+#[async_trait]
+impl File for MountFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
+        Ok(self.inode.clone())
+    }
+
+    async fn read(&self, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::EBADF)
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EBADF)
+    }
+}
+
+pub struct MountApiDentry {
+    inner: DentryInner,
+}
+
+impl MountApiDentry {
+    pub fn new(name: &str) -> Arc<Self> {
+        let inner = DentryInner::new(name, None);
+        Arc::new(Self { inner })
+    }
+}
+
+unsafe impl Sync for MountApiDentry {}
+unsafe impl Send for MountApiDentry {}
+
+impl Dentry for MountApiDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+
+    fn new(
+        &self,
+        _name: &str,
+        _parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        panic!("cannot create a mount api file in this way");
+    }
+}
+
+fn file_inner(name: &str, inode: &Arc<MountApiInode>) -> FileInner {
+    let dentry = MountApiDentry::new(name);
+    dentry.set_inode(inode.clone());
+    FileInner {
+        offset: 0.into(),
+        dentry,
+        flags: SpinNoIrqLock::new(OpenFlags::O_RDWR),
+    }
+}
+
+/// find a file system type fsopen may instantiate by its user visible name
+pub fn find_context_fs(fs_type: &str) -> Option<&'static Arc<dyn FSType>> {
+    let name = FS_MANAGER
+        .lock()
+        .iter()
+        .find(|(name, fs)| CONTEXT_FS.contains(&name.as_str()) && fs.type_name() == fs_type)
+        .map(|(name, _)| name.clone())?;
+    Some(get_filesystem(&name))
+}
+
+/// global function to create a file system context of `fs`
+pub fn make_fscontext(fs: &'static Arc<dyn FSType>) -> Arc<dyn File> {
+    let inode = MountApiInode::new();
+    let inner = file_inner("fscontext", &inode);
+    let ctx = FsContext {
+        fs,
+        phase: FsContextPhase::Config,
+        source: None,
+        sb_flags: MountFlags::empty(),
+        options: BTreeMap::new(),
+    };
+    Arc::new(FsContextFile { inode, ctx: SpinNoIrqLock::new(ctx), inner })
+}
+
+/// global function to create the file of a detached mount
+pub fn make_mount_file(mount: DetachedMount) -> Arc<dyn File> {
+    let inode = MountApiInode::new();
+    let inner = file_inner("[mount]", &inode);
+    Arc::new(MountFile { inode, mount: SpinNoIrqLock::new(mount), inner })
+}
@@ -0,0 +1,505 @@
+//! inotify: a file reporting the changes made to the files it watches
+
+use core::{future::Future, pin::Pin, sync::atomic::{AtomicI32, AtomicU32, Ordering}, task::{Context, Poll, Waker}};
+
+use alloc::{boxed::Box, collections::{btree_map::BTreeMap, vec_deque::VecDeque}, string::{String, ToString}, sync::{Arc, Weak}, vec::Vec};
+use async_trait::async_trait;
+
+use crate::{sync::mutex::SpinNoIrqLock, syscall::SysError, utils::get_waker};
+
+use super::{vfs::{file::PollEvents, inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags};
+
+bitflags! {
+    /// flags for inotify_init1
+    pub struct InotifyFlags: u32 {
+        /// same as O_NONBLOCK
+        const IN_NONBLOCK = 0o4000;
+        /// same as O_CLOEXEC
+        const IN_CLOEXEC = 0o2000000;
+    }
+}
+
+bitflags! {
+    /// events of inotify_add_watch and of the events read,
+    /// define in <uapi/linux/inotify.h>
+    pub struct InotifyMask: u32 {
+        /// file was accessed
+        const IN_ACCESS = 0x1;
+        /// file was modified
+        const IN_MODIFY = 0x2;
+        /// metadata changed
+        const IN_ATTRIB = 0x4;
+        /// writable file was closed
+        const IN_CLOSE_WRITE = 0x8;
+        /// unwritable file closed
+        const IN_CLOSE_NOWRITE = 0x10;
+        /// file was opened
+        const IN_OPEN = 0x20;
+        /// file was moved from the name
+        const IN_MOVED_FROM = 0x40;
+        /// file was moved to the name
+        const IN_MOVED_TO = 0x80;
+        /// file was created
+        const IN_CREATE = 0x100;
+        /// file was deleted
+        const IN_DELETE = 0x200;
+        /// the watched file itself was deleted
+        const IN_DELETE_SELF = 0x400;
+        /// the watched file itself was moved
+        const IN_MOVE_SELF = 0x800;
+        /// file system holding the file was unmounted
+        const IN_UNMOUNT = 0x2000;
+        /// events were dropped, the queue was full
+        const IN_Q_OVERFLOW = 0x4000;
+        /// the watch was removed
+        const IN_IGNORED = 0x8000;
+        /// only watch the path if it is a directory
+        const IN_ONLYDIR = 0x01000000;
+        /// do not follow a symlink
+        const IN_DONT_FOLLOW = 0x02000000;
+        /// no events for the children once they are unlinked
+        const IN_EXCL_UNLINK = 0x04000000;
+        /// only create a watch, fail if there is one
+        const IN_MASK_CREATE = 0x10000000;
+        /// add to the mask of an existing watch
+        const IN_MASK_ADD = 0x20000000;
+        /// the event is about a directory
+        const IN_ISDIR = 0x40000000;
+        /// only send the event once
+        const IN_ONESHOT = 0x80000000;
+    }
+}
+
+impl InotifyMask {
+    /// the events a watch may ask for
+    pub const ALL_EVENTS: Self = Self::from_bits_truncate(0xfff);
+    /// the events a watch gets whatever it asked for
+    const ALWAYS: Self = Self::from_bits_truncate(0x2000 | 0x4000 | 0x8000);
+}
+
+impl From<InotifyFlags> for OpenFlags {
+    fn from(value: InotifyFlags) -> Self {
+        let mut flags = OpenFlags::O_RDONLY;
+        if value.contains(InotifyFlags::IN_NONBLOCK) {
+            flags |= OpenFlags::O_NONBLOCK;
+        }
+        if value.contains(InotifyFlags::IN_CLOEXEC) {
+            flags |= OpenFlags::O_CLOEXEC;
+        }
+        flags
+    }
+}
+
+/// events queued before the rest are dropped for an IN_Q_OVERFLOW,
+/// as /proc/sys/fs/inotify/max_queued_events
+const MAX_QUEUED_EVENTS: usize = 16384;
+/// size of `struct inotify_event` before the name
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// an event waiting to be read
+#[derive(PartialEq, Eq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: InotifyMask,
+    cookie: u32,
+    name: Option<String>,
+}
+
+impl InotifyEvent {
+    /// the length of the name field, the name ended by NULs up to a multiple of the header
+    fn name_len(&self) -> usize {
+        self.name.as_ref().map_or(0, |name| (name.len() + EVENT_HEADER_SIZE) / EVENT_HEADER_SIZE * EVENT_HEADER_SIZE)
+    }
+
+    fn size(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    /// write as `struct inotify_event` at the start of `buf`, which is large enough
+    fn write_to(&self, buf: &mut [u8]) {
+        let len = self.name_len();
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.bits().to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(len as u32).to_ne_bytes());
+        let name_buf = &mut buf[EVENT_HEADER_SIZE..EVENT_HEADER_SIZE + len];
+        name_buf.fill(0);
+        if let Some(name) = self.name.as_ref() {
+            name_buf[..name.len()].copy_from_slice(name.as_bytes());
+        }
+    }
+}
+
+/// a watch of an inotify instance, kept by the inode it watches
+pub struct InotifyWatch {
+    instance: Weak<InotifyInode>,
+    wd: i32,
+    mask: InotifyMask,
+}
+
+pub struct InotifyInode {
+    inner: InodeInner,
+    /// the instance itself, held by the watches
+    this: Weak<InotifyInode>,
+    next_wd: AtomicI32,
+    meta: SpinNoIrqLock<InotifyMeta>,
+}
+
+pub struct InotifyMeta {
+    events: VecDeque<InotifyEvent>,
+    /// the inodes watched, by watch descriptor
+    watches: BTreeMap<i32, Weak<dyn Inode>>,
+    read_waker: VecDeque<Waker>,
+}
+
+impl InotifyMeta {
+    /// queue the event, unless it is the same as the last one;
+    /// once the queue is full the events are dropped and an IN_Q_OVERFLOW is queued instead
+    fn push(&mut self, event: InotifyEvent) {
+        if self.events.back() == Some(&event) {
+            return;
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            let overflow = InotifyEvent { wd: -1, mask: InotifyMask::IN_Q_OVERFLOW, cookie: 0, name: None };
+            if self.events.back() != Some(&overflow) {
+                self.events.push_back(overflow);
+            }
+        } else {
+            self.events.push_back(event);
+        }
+        while let Some(waker) = self.read_waker.pop_front() {
+            waker.wake();
+        }
+    }
+
+    /// the watch is gone, say so with an IN_IGNORED
+    fn ignore(&mut self, wd: i32) {
+        if self.watches.remove(&wd).is_some() {
+            self.push(InotifyEvent { wd, mask: InotifyMask::IN_IGNORED, cookie: 0, name: None });
+        }
+    }
+}
+
+impl InotifyInode {
+    pub fn new() -> Arc<Self> {
+        let inner = InodeInner::new(
+            None,
+            InodeMode::FILE | InodeMode::OWNER_READ | InodeMode::OWNER_WRITE,
+            0,
+        );
+        let meta = SpinNoIrqLock::new(InotifyMeta {
+            events: VecDeque::new(),
+            watches: BTreeMap::new(),
+            read_waker: VecDeque::new(),
+        });
+        Arc::new_cyclic(|this| Self { inner, this: this.clone(), next_wd: AtomicI32::new(1), meta })
+    }
+
+    /// watch `inode` for the events in `mask`, return the watch descriptor;
+    /// a second watch of the inode replaces the mask of the first one,
+    /// or adds to it with IN_MASK_ADD
+    pub fn add_watch(&self, inode: &Arc<dyn Inode>, mask: InotifyMask) -> Result<i32, SysError> {
+        let mut watches = inode.inode_inner().watches.lock();
+        let events = mask & (InotifyMask::ALL_EVENTS | InotifyMask::IN_EXCL_UNLINK | InotifyMask::IN_ONESHOT);
+        if let Some(watch) = watches.iter_mut().find(|watch| watch.instance.ptr_eq(&self.this)) {
+            if mask.contains(InotifyMask::IN_MASK_CREATE) {
+                return Err(SysError::EEXIST);
+            }
+            if mask.contains(InotifyMask::IN_MASK_ADD) {
+                watch.mask |= events;
+            } else {
+                watch.mask = events;
+            }
+            return Ok(watch.wd);
+        }
+        let wd = self.next_wd.fetch_add(1, Ordering::Relaxed);
+        watches.push(InotifyWatch { instance: self.this.clone(), wd, mask: events });
+        drop(watches);
+        self.meta.lock().watches.insert(wd, Arc::downgrade(inode));
+        Ok(wd)
+    }
+
+    /// stop watching with `wd`
+    pub fn rm_watch(&self, wd: i32) -> Result<(), SysError> {
+        let inode = {
+            let mut meta = self.meta.lock();
+            let inode = meta.watches.get(&wd).cloned().ok_or(SysError::EINVAL)?;
+            meta.ignore(wd);
+            inode
+        };
+        if let Some(inode) = inode.upgrade() {
+            inode.inode_inner().watches.lock()
+                .retain(|watch| !(watch.wd == wd && watch.instance.ptr_eq(&self.this)));
+        }
+        Ok(())
+    }
+}
+
+impl Inode for InotifyInode {
+    fn inode_inner(&self) -> &InodeInner {
+        &self.inner
+    }
+
+    fn getattr(&self) -> Kstat {
+        let inner = self.inode_inner();
+        Kstat {
+            st_dev: inner.st_dev(),
+            st_ino: inner.ino as u64,
+            st_mode: inner.mode().bits() as _,
+            st_nlink: inner.nlink() as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            _pad0: 0,
+            st_size: 0,
+            _pad1: 0,
+            st_blksize: 0,
+            st_blocks: 0,
+            st_atime_sec: inner.atime().tv_sec as _,
+            st_atime_nsec: inner.atime().tv_nsec as _,
+            st_mtime_sec: inner.mtime().tv_sec as _,
+            st_mtime_nsec: inner.mtime().tv_nsec as _,
+            st_ctime_sec: inner.ctime().tv_sec as _,
+            st_ctime_nsec: inner.ctime().tv_nsec as _,
+        }
+    }
+}
+
+/// wait until an event is queued
+pub struct InotifyReadFuture {
+    inotify: Arc<InotifyInode>,
+}
+
+impl Future for InotifyReadFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut meta = self.inotify.meta.lock();
+        if !meta.events.is_empty() {
+            Poll::Ready(())
+        } else {
+            meta.read_waker.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+pub struct InotifyFile {
+    inotify: Arc<InotifyInode>,
+    inner: FileInner,
+}
+
+impl InotifyFile {
+    fn new(dentry: Arc<dyn Dentry>, inotify: Arc<InotifyInode>, flags: InotifyFlags) -> Arc<Self> {
+        let inner = FileInner {
+            offset: 0.into(),
+            dentry,
+            flags: SpinNoIrqLock::new(flags.into()),
+        };
+        Arc::new(Self { inotify, inner })
+    }
+
+    pub fn inotify(&self) -> &Arc<InotifyInode> {
+        &self.inotify
+    }
+
+    fn nonblock(&self) -> bool {
+        self.flags().contains(OpenFlags::O_NONBLOCK)
+    }
+}
+
+#[async_trait]
+impl File for InotifyFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
+        Ok(self.inotify.clone())
+    }
+
+    /// read as many whole events as `buf` holds, at least one
+    async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
+        loop {
+            {
+                let mut meta = self.inotify.meta.lock();
+                if let Some(first) = meta.events.front() {
+                    if first.size() > buf.len() {
+                        return Err(SysError::EINVAL);
+                    }
+                    let mut len = 0;
+                    while let Some(event) = meta.events.front() {
+                        if len + event.size() > buf.len() {
+                            break;
+                        }
+                        event.write_to(&mut buf[len..]);
+                        len += event.size();
+                        meta.events.pop_front();
+                    }
+                    return Ok(len);
+                }
+            }
+            if self.nonblock() {
+                return Err(SysError::EAGAIN);
+            }
+            InotifyReadFuture { inotify: self.inotify.clone() }.await;
+        }
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut meta = self.inotify.meta.lock();
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if !meta.events.is_empty() {
+                res |= PollEvents::IN;
+            } else {
+                meta.read_waker.push_back(waker);
+            }
+        }
+        res
+    }
+}
+
+pub struct InotifyDentry {
+    inner: DentryInner,
+}
+
+impl InotifyDentry {
+    pub fn new() -> Arc<Self> {
+        let inner = DentryInner::new("[inotify]", None);
+        Arc::new(Self { inner })
+    }
+}
+
+unsafe impl Sync for InotifyDentry {}
+unsafe impl Send for InotifyDentry {}
+
+impl Dentry for InotifyDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+
+    fn new(
+        &self,
+        _name: &str,
+        _parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        panic!("cannot create an inotify in this way");
+    }
+}
+
+/// global function to create an inotify file
+pub fn make_inotify(flags: InotifyFlags) -> Arc<dyn File> {
+    let inotify = InotifyInode::new();
+    let dentry = InotifyDentry::new();
+    dentry.set_inode(inotify.clone());
+    InotifyFile::new(dentry, inotify, flags)
+}
+
+/// report `mask` to the watches on `inode`, `name` is the child of the
+/// directory the event is about; IN_ONESHOT watches go with the first event
+pub fn fsnotify(inode: &Arc<dyn Inode>, mask: InotifyMask, name: Option<&str>, cookie: u32) {
+    let mut targets = Vec::new();
+    inode.inode_inner().watches.lock().retain(|watch| {
+        let Some(instance) = watch.instance.upgrade() else {
+            return false;
+        };
+        if !watch.mask.intersects(mask) && !mask.intersects(InotifyMask::ALWAYS) {
+            return true;
+        }
+        let oneshot = watch.mask.contains(InotifyMask::IN_ONESHOT);
+        targets.push((instance, watch.wd, oneshot));
+        !oneshot
+    });
+    for (instance, wd, oneshot) in targets {
+        let mut meta = instance.meta.lock();
+        meta.push(InotifyEvent { wd, mask, cookie, name: name.map(|name| name.to_string()) });
+        if oneshot {
+            meta.ignore(wd);
+        }
+    }
+}
+
+/// IN_ISDIR for the events about a directory
+fn isdir(inode: &Arc<dyn Inode>) -> InotifyMask {
+    if inode.inode_type() == InodeMode::DIR {
+        InotifyMask::IN_ISDIR
+    } else {
+        InotifyMask::empty()
+    }
+}
+
+/// report `mask` to the watches on `inode` only, as for its attributes
+pub fn notify_inode(inode: &Arc<dyn Inode>, mask: InotifyMask) {
+    fsnotify(inode, mask | isdir(inode), None, 0);
+}
+
+/// report `mask` about the child `name` of the directory `parent`
+pub fn notify_child(parent: &Arc<dyn Dentry>, name: &str, mask: InotifyMask, cookie: u32) {
+    if let Some(dir) = parent.inode() {
+        fsnotify(&dir, mask, Some(name), cookie);
+    }
+}
+
+/// report `mask` about the file of `dentry` to the watches on it and,
+/// with its name, to the watches on its directory
+pub fn notify_dentry(dentry: &Arc<dyn Dentry>, mask: InotifyMask) {
+    let Some(inode) = dentry.inode() else {
+        return;
+    };
+    let mask = mask | isdir(&inode);
+    fsnotify(&inode, mask, None, 0);
+    if let Some(parent) = dentry.parent() {
+        notify_child(&parent, dentry.name(), mask, 0);
+    }
+}
+
+/// a name of `inode` was removed: the inode is gone with its last one,
+/// which ends the watches on it, or else only its link count changed
+pub fn notify_unlinked(inode: &Arc<dyn Inode>) {
+    let is_dir = inode.inode_type() == InodeMode::DIR;
+    if !is_dir && inode.getattr().st_nlink > 1 {
+        fsnotify(inode, InotifyMask::IN_ATTRIB, None, 0);
+        return;
+    }
+    fsnotify(inode, InotifyMask::IN_DELETE_SELF | isdir(inode), None, 0);
+    let watches = core::mem::take(&mut *inode.inode_inner().watches.lock());
+    for watch in watches {
+        if let Some(instance) = watch.instance.upgrade() {
+            instance.meta.lock().ignore(watch.wd);
+        }
+    }
+}
+
+/// the cookie tying an IN_MOVED_FROM to its IN_MOVED_TO
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// `inode` was moved from `old_name` in `old_parent` to `new_name` in `new_parent`
+pub fn notify_move(old_parent: &Arc<dyn Dentry>, old_name: &str, new_parent: &Arc<dyn Dentry>, new_name: &str, inode: &Arc<dyn Inode>) {
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    let dir = isdir(inode);
+    notify_child(old_parent, old_name, InotifyMask::IN_MOVED_FROM | dir, cookie);
+    notify_child(new_parent, new_name, InotifyMask::IN_MOVED_TO | dir, cookie);
+    fsnotify(inode, InotifyMask::IN_MOVE_SELF | dir, None, 0);
+}
@@ -7,6 +7,12 @@ pub mod stdio;
 pub mod ext4;
 pub mod vfs;
 pub mod pipefs;
+pub mod eventfd;
+pub mod inotify;
+pub mod timerfd;
+pub mod signalfd;
+pub mod pidfd;
+pub mod fscontext;
 pub mod page;
 pub mod devfs;
 pub mod utils;
@@ -14,6 +20,8 @@ pub mod fs;
 // pub mod simplefs;
 pub mod procfs;
 pub mod tmpfs;
+pub mod mount;
+pub mod secretmem;
 
 use devfs::{fstype::DevFsType, init_devfs};
 use ext4::Ext4FSType;
@@ -22,7 +30,7 @@ use log::*;
 use procfs::{fstype::ProcFSType, init_procfs};
 pub use stdio::{Stdin, Stdout};
 
-use alloc::{boxed::Box, collections::btree_map::BTreeMap, string::{String, ToString}, sync::Arc};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, string::{String, ToString}, sync::Arc, vec::Vec};
 use tmpfs::{fstype::TmpFSType, init_tmpfs};
 use vfs::{fstype::{FSType, MountFlags}, DCACHE};
 
@@ -74,6 +82,21 @@ pub fn get_filesystem(name: &str) -> &'static Arc<dyn FSType> {
     Box::leak(Box::new(arc))
 }
 
+/// write every dirty cached page and the metadata of every mounted file system
+/// to their devices, keep going past a failure and return the first error
+pub fn sync_all() -> Result<(), SysError> {
+    let mut ret = page::lru::sync().map_err(|_| SysError::EIO);
+    let supers: Vec<Arc<dyn SuperBlock>> = FS_MANAGER.lock()
+        .values()
+        .flat_map(|fs| fs.inner().supers.lock().values().cloned().collect::<Vec<_>>())
+        .collect();
+    // the locks are not held while writing to the devices
+    for sb in supers {
+        ret = ret.and(sb.sync());
+    }
+    ret
+}
+
 
 /// init the file system
 pub fn init() {
@@ -104,7 +127,7 @@ pub fn init() {
 
     // create the ext4 file system using the block device
     let diskfs = get_filesystem(DISK_FS_NAME);
-    let diskfs_root = diskfs.mount("/", None, MountFlags::empty(), Some(disk_device)).unwrap();
+    let diskfs_root = diskfs.mount("/", None, MountFlags::empty(), Some(disk_device.clone())).unwrap();
 
     #[cfg(not(feature = "vf2"))]
     {
@@ -119,7 +142,7 @@ pub fn init() {
     // mount the dev file system under diskfs
     let devfs = get_filesystem("devfs");
     let devfs_root = devfs.mount("dev", Some(diskfs_root.clone()), MountFlags::empty(), None).unwrap();
-    init_devfs(devfs_root.clone());
+    init_devfs(devfs_root.clone(), disk_device);
     diskfs_root.add_child(devfs_root.clone());
     log::info!("[FS] insert path: {}", devfs_root.path());
     DCACHE.lock().insert(devfs_root.path(), devfs_root);
@@ -300,6 +323,40 @@ impl OpenFlags {
     }
 }
 
+bitflags! {
+    /// Define in <uapi/linux/openat2.h>
+    pub struct ResolveFlags: u64 {
+        /// Block mount-point crossings
+        const RESOLVE_NO_XDEV       = 0x01;
+        /// Block traversal through procfs-style "magic-links"
+        const RESOLVE_NO_MAGICLINKS = 0x02;
+        /// Block traversal through all symlinks
+        const RESOLVE_NO_SYMLINKS   = 0x04;
+        /// Block "lexical" trickery like "..", symlinks, and absolute paths
+        /// which escape the dirfd
+        const RESOLVE_BENEATH       = 0x08;
+        /// Make all jumps to "/" and ".." be scoped inside the dirfd
+        const RESOLVE_IN_ROOT       = 0x10;
+        /// Only complete if resolution can be completed through cached lookup
+        const RESOLVE_CACHED        = 0x20;
+    }
+}
+
+/// argument of openat2, defined in <uapi/linux/openat2.h>
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct OpenHow {
+    /// O_* flags
+    pub flags: u64,
+    /// O_CREAT or O_TMPFILE file mode
+    pub mode: u64,
+    /// RESOLVE_* flags
+    pub resolve: u64,
+}
+
+/// size of the first published OpenHow
+pub const OPEN_HOW_SIZE_VER0: usize = 24;
+
 bitflags! {
     pub struct RenameFlags: i32 {
         /// Don't overwrite target
@@ -311,6 +368,33 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Define in <uapi/linux/xattr.h>
+    pub struct XattrFlags: i32 {
+        /// fail if the attribute already exists
+        const XATTR_CREATE = 0x1;
+        /// fail if the attribute does not exist
+        const XATTR_REPLACE = 0x2;
+    }
+}
+
+/// longest name of an extended attribute
+pub const XATTR_NAME_MAX: usize = 255;
+/// largest value of an extended attribute
+pub const XATTR_SIZE_MAX: usize = 65536;
+/// largest list of the names of extended attributes
+pub const XATTR_LIST_MAX: usize = 65536;
+
+bitflags! {
+    /// Define in <uapi/linux/falloc.h>
+    pub struct FallocFlags: i32 {
+        /// Default is extend size
+        const FALLOC_FL_KEEP_SIZE = 0x01;
+        /// De-allocates range
+        const FALLOC_FL_PUNCH_HOLE = 0x02;
+    }
+}
+
 // Defined in <bits/struct_stat.h>
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
@@ -0,0 +1,264 @@
+//! mount table introspection, backs statmount and listmount
+
+use alloc::{format, string::{String, ToString}, sync::Arc, vec::Vec};
+
+use crate::{sync::mutex::SpinNoIrqLock, syscall::SysError};
+
+use super::{procfs::init_procfs, vfs::{fstype::{FSType, MountFlags}, Dentry, SuperBlock, DCACHE}, FS_MANAGER};
+
+/// a mounted file system as seen from user space
+pub struct MountEntry {
+    /// unique mount id
+    pub id: u64,
+    /// id of the mount the mount point lives on, the root mount is its own parent
+    pub parent_id: u64,
+    /// absolute path of the mount point
+    pub point: String,
+    /// file system type name
+    pub fs_type: String,
+    /// file system magic number
+    pub magic: u64,
+    /// the mounted super block
+    pub sb: Arc<dyn SuperBlock>,
+}
+
+/// take a snapshot of all mounts, sorted by mount id
+pub fn mount_table() -> Vec<MountEntry> {
+    let mut mounts = Vec::new();
+    for (_, fs) in FS_MANAGER.lock().iter() {
+        for (point, sb) in fs.inner().supers.lock().iter() {
+            mounts.push(MountEntry {
+                id: sb.inner().mnt_id,
+                parent_id: 0,
+                point: point.clone(),
+                fs_type: fs.type_name().to_string(),
+                magic: fs.magic(),
+                sb: sb.clone(),
+            });
+        }
+    }
+    mounts.sort_by_key(|m| m.id);
+    // the parent is the closest mount above the mount point,
+    // of two mounts on the same point the later one sits on the earlier one
+    let parents: Vec<u64> = mounts
+        .iter()
+        .map(|m| {
+            mounts
+                .iter()
+                .filter(|p| is_below(&m.point, &p.point) && (p.point != m.point || p.id < m.id))
+                .max_by_key(|p| (p.point.len(), p.id))
+                .map_or(m.id, |p| p.id)
+        })
+        .collect();
+    for (m, parent_id) in mounts.iter_mut().zip(parents) {
+        m.parent_id = parent_id;
+    }
+    mounts
+}
+
+/// mount a new instance of `fs` on the directory `target`,
+/// what was below the directory is hidden until the mount goes away
+pub fn attach_mount(fs: &'static Arc<dyn FSType>, target: Arc<dyn Dentry>, flags: MountFlags) -> Result<Arc<dyn Dentry>, SysError> {
+    let inode = target.inode().ok_or(SysError::ENOENT)?;
+    if target.is_negative() {
+        return Err(SysError::ENOENT);
+    }
+    if !inode.inode_type().is_dir() {
+        return Err(SysError::ENOTDIR);
+    }
+    // the root stays where it is
+    let parent = target.parent().ok_or(SysError::EBUSY)?;
+    let path = target.path();
+    let prefix = format!("{}/", path.trim_end_matches('/'));
+    DCACHE.lock().retain(|key, _| !key.starts_with(&prefix));
+    let root = fs.mount(target.name(), Some(parent.clone()), flags, None).ok_or(SysError::EINVAL)?;
+    if fs.name() == "procfs" {
+        init_procfs(root.clone());
+    }
+    parent.add_child(root.clone());
+    log::info!("[attach_mount] {} on {}", fs.type_name(), path);
+    Ok(root)
+}
+
+/// an old root left behind by pivot_root
+pub struct OldRoot {
+    /// absolute path of put_old, where the old root can be reached
+    pub put_old: String,
+    /// absolute path of the old root
+    pub root: String,
+}
+
+/// old roots reachable through their put_old, until put_old is unmounted
+static OLD_ROOTS: SpinNoIrqLock<Vec<OldRoot>> = SpinNoIrqLock::new(Vec::new());
+
+/// make the old root reachable at put_old
+pub fn add_old_root(put_old: String, root: String) {
+    // going back to a former root leaves nothing to reach
+    if put_old == root {
+        return;
+    }
+    let mut old_roots = OLD_ROOTS.lock();
+    old_roots.retain(|r| r.put_old != put_old);
+    old_roots.push(OldRoot { put_old, root });
+}
+
+/// unmount the old root at put_old, false if there is none
+pub fn remove_old_root(put_old: &str) -> bool {
+    let mut old_roots = OLD_ROOTS.lock();
+    let len = old_roots.len();
+    old_roots.retain(|r| r.put_old != put_old);
+    old_roots.len() != len
+}
+
+/// the path a path through a put_old stands for, None if it goes through none
+pub fn resolve_old_root(path: &str) -> Option<String> {
+    let old_roots = OLD_ROOTS.lock();
+    let old_root = old_roots
+        .iter()
+        .filter(|r| is_below(path, &r.put_old))
+        .max_by_key(|r| r.put_old.len())?;
+    let rest = &path[old_root.put_old.trim_end_matches('/').len()..];
+    Some(match (old_root.root.as_str(), rest) {
+        (root, "") => root.to_string(),
+        ("/", rest) => rest.to_string(),
+        (root, rest) => format!("{}{}", root, rest),
+    })
+}
+
+/// the absolute path as seen from `root`: relative to it when it lies beneath,
+/// else through the put_old of an old root when that lies beneath
+pub fn path_in_root(path: &str, root: &str) -> String {
+    let strip = |path: &str| {
+        if root == "/" {
+            return Some(path.to_string());
+        }
+        let rest = path.strip_prefix(root.trim_end_matches('/'))?;
+        match rest {
+            "" => Some("/".to_string()),
+            rest if rest.starts_with('/') => Some(rest.to_string()),
+            _ => None,
+        }
+    };
+    if let Some(path) = strip(path) {
+        return path;
+    }
+    for old_root in OLD_ROOTS.lock().iter() {
+        if !is_below(path, &old_root.root) {
+            continue;
+        }
+        let rest = path[old_root.root.trim_end_matches('/').len()..].trim_end_matches('/');
+        if let Some(path) = strip(&format!("{}{}", old_root.put_old.trim_end_matches('/'), rest)) {
+            return path;
+        }
+    }
+    path.to_string()
+}
+
+/// whether the absolute path is the mount point or lies beneath it
+pub fn is_below(path: &str, point: &str) -> bool {
+    let point = point.trim_end_matches('/');
+    path.strip_prefix(point)
+        .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// whether the mount lies anywhere beneath the ancestor mount
+pub fn is_descendant(mounts: &[MountEntry], mount: &MountEntry, ancestor_id: u64) -> bool {
+    let mut cur = mount;
+    // the parent chain ends at the root, which is its own parent
+    for _ in 0..mounts.len() {
+        if cur.parent_id == cur.id {
+            return false;
+        }
+        if cur.parent_id == ancestor_id {
+            return true;
+        }
+        match mounts.iter().find(|m| m.id == cur.parent_id) {
+            Some(parent) => cur = parent,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// argument of statmount and listmount, defined in <uapi/linux/mount.h>
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct MntIdReq {
+    /// size of the struct passed in
+    pub size: u32,
+    pub spare: u32,
+    /// mount to query
+    pub mnt_id: u64,
+    /// statmount mask or listmount start id
+    pub param: u64,
+    /// mount namespace id
+    pub mnt_ns_id: u64,
+}
+
+/// size of the first published MntIdReq
+pub const MNT_ID_REQ_SIZE_VER0: usize = 24;
+/// size of MntIdReq with mnt_ns_id
+pub const MNT_ID_REQ_SIZE_VER1: usize = 32;
+
+/// statmount result header, strings follow it and are referenced by offset
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Statmount {
+    /// total size, including strings
+    pub size: u32,
+    /// [str] mount options
+    pub mnt_opts: u32,
+    /// what results were written
+    pub mask: u64,
+    pub sb_dev_major: u32,
+    pub sb_dev_minor: u32,
+    pub sb_magic: u64,
+    /// SB_{RDONLY,SYNCHRONOUS,DIRSYNC,LAZYTIME}
+    pub sb_flags: u32,
+    /// [str] file system type
+    pub fs_type: u32,
+    pub mnt_id: u64,
+    pub mnt_parent_id: u64,
+    pub mnt_id_old: u32,
+    pub mnt_parent_id_old: u32,
+    /// MOUNT_ATTR_* flags
+    pub mnt_attr: u64,
+    /// MS_{SHARED,SLAVE,PRIVATE,UNBINDABLE}
+    pub mnt_propagation: u64,
+    pub mnt_peer_group: u64,
+    pub mnt_master: u64,
+    pub propagate_from: u64,
+    /// [str] root of the mount relative to the root of the fs
+    pub mnt_root: u32,
+    /// [str] mount point relative to the root
+    pub mnt_point: u32,
+    pub mnt_ns_id: u64,
+    pub spare: [u64; 49],
+}
+
+impl Default for Statmount {
+    fn default() -> Self {
+        Self {
+            size: 0, mnt_opts: 0, mask: 0,
+            sb_dev_major: 0, sb_dev_minor: 0, sb_magic: 0, sb_flags: 0, fs_type: 0,
+            mnt_id: 0, mnt_parent_id: 0, mnt_id_old: 0, mnt_parent_id_old: 0,
+            mnt_attr: 0, mnt_propagation: 0, mnt_peer_group: 0, mnt_master: 0, propagate_from: 0,
+            mnt_root: 0, mnt_point: 0, mnt_ns_id: 0, spare: [0; 49],
+        }
+    }
+}
+
+pub const STATMOUNT_SB_BASIC: u64 = 0x1;
+pub const STATMOUNT_MNT_BASIC: u64 = 0x2;
+pub const STATMOUNT_PROPAGATE_FROM: u64 = 0x4;
+pub const STATMOUNT_MNT_ROOT: u64 = 0x8;
+pub const STATMOUNT_MNT_POINT: u64 = 0x10;
+pub const STATMOUNT_FS_TYPE: u64 = 0x20;
+pub const STATMOUNT_MNT_OPTS: u64 = 0x80;
+
+/// list mounts in reverse order
+pub const LISTMOUNT_REVERSE: usize = 1;
+/// mnt_id asking for the root mount
+pub const LSMT_ROOT: u64 = u64::MAX;
+/// mounts do not propagate events
+pub const MS_PRIVATE: u64 = 1 << 18;
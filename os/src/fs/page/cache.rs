@@ -6,11 +6,11 @@
 use core::{cmp, sync::atomic::{AtomicUsize, Ordering}};
 
 use crate::{fs::vfs::Inode, sync::mutex::SpinNoIrqLock};
-use alloc::{collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+use alloc::{collections::btree_map::BTreeMap, sync::{Arc, Weak}, vec::Vec};
 // use hashbrown::HashMap;
 use log::info;
 
-use super::page::{Page, PAGE_SIZE};
+use super::{lru, page::{Page, PAGE_SIZE}};
 
 pub struct PageCache {
     /// from file offset(should be page aligned)
@@ -41,13 +41,41 @@ impl PageCache {
     /// get the page at file offset
     pub fn get_page(&self, offset: usize) -> Option<Arc<Page>> {
         assert!(offset % PAGE_SIZE == 0);
-        self.pages.lock().get(&offset).cloned()
+        let page = self.pages.lock().get(&offset).cloned();
+        if let Some(page) = page.as_ref() {
+            page.set_referenced();
+        }
+        page
     }
     /// insert the page at file offset
     pub fn insert_page(&self, offset: usize, page: Arc<Page>) {
         assert!(offset % PAGE_SIZE == 0);
         self.pages.lock().insert(offset, page);
     }
+    /// insert the page at file offset and let reclaim drop it,
+    /// dirty data is written back through `inode` first
+    /// only for caches that have a backing store
+    pub fn insert_evictable(self: &Arc<Self>, offset: usize, page: Arc<Page>, inode: Weak<dyn Inode>) {
+        self.insert_page(offset, page.clone());
+        lru::track(self, offset, &page, inode);
+        lru::shrink();
+    }
+    /// drop `page` at `offset` if it is still the cached one and nobody else uses it,
+    /// return whether it is gone from the cache
+    pub fn evict(&self, offset: usize, page: &Arc<Page>) -> bool {
+        let mut pages = self.pages.lock();
+        match pages.get(&offset) {
+            Some(cached) if Arc::ptr_eq(cached, page) => {}
+            _ => return true,
+        }
+        // the map and the caller hold the only references,
+        // and no new one can be taken while the lock is held
+        if Arc::strong_count(page) > 2 || page.is_mapped() || page.is_dirty() {
+            return false;
+        }
+        pages.remove(&offset);
+        true
+    }
     pub fn update_end(&self, offset: usize) {
         let end = self.end.load(Ordering::Acquire);
         // log::info!("updated end ({:#x} {:#x}) => {:#x}", end, offset, cmp::max(end, offset));
@@ -56,33 +84,93 @@ impl PageCache {
     pub fn end(&self) -> usize {
         self.end.load(Ordering::Acquire)
     }
-    /// flush all dirty pages
-    pub fn flush(&self, inode: Arc<dyn Inode>) {
+    /// write `page` cached at `offset` back through `inode` if it is dirty,
+    /// never past the end of the file
+    pub fn write_back(&self, offset: usize, page: &Page, inode: &dyn Inode) -> Result<(), i32> {
+        if !page.is_dirty() {
+            return Ok(());
+        }
+        let len = cmp::min(self.end().saturating_sub(offset), PAGE_SIZE);
+        // cleaned before the write, so that a store racing with it dirties the page again
+        page.set_clean();
+        if len == 0 {
+            return Ok(());
+        }
+        if let Err(e) = inode.write_at(offset, &page.get_slice::<u8>()[..len]) {
+            page.set_dirty();
+            return Err(e);
+        }
+        Ok(())
+    }
+    /// write all dirty pages back through `inode`,
+    /// keep going past a failed page and return the first error
+    pub fn flush(&self, inode: &dyn Inode) -> Result<(), i32> {
         info!("start to flush all pages");
-        let mut pages = self.pages.lock();
-        for (&offset, page) in pages.iter_mut() {
-            if page.is_dirty() == false {
-                continue;
-            }
-            inode.write_at(offset, page.get_slice::<u8>()).expect("[PageCache]: failed at flush");
+        let dirty: Vec<(usize, Arc<Page>)> = self.pages.lock()
+            .iter()
+            .filter(|(_, page)| page.is_dirty())
+            .map(|(&offset, page)| (offset, page.clone()))
+            .collect();
+        let mut ret = Ok(());
+        for (offset, page) in dirty {
+            ret = ret.and(self.write_back(offset, &page, inode));
         }
+        ret
     }
 
-    /// truncate the cache
-    /// reduce the cache size if given size is smaller
+    /// truncate the cache to the given size
+    /// pages past the size are dropped and the tail of the last page is zeroed,
+    /// so that a later expand reads zero
     pub fn truncate(&self, tsize: usize) {
-        if self.end() > tsize {
-            let mut remove_page_idxs = Vec::new();
-            let mut pages =  self.pages.lock();
-            for (&offset, _) in pages.iter() {
-                if offset >= tsize {
-                    remove_page_idxs.push(offset);
-                }
+        let mut pages = self.pages.lock();
+        pages.retain(|&offset, _| offset < tsize);
+        if tsize % PAGE_SIZE != 0 {
+            let page_offset = tsize / PAGE_SIZE * PAGE_SIZE;
+            if let Some(page) = pages.get(&page_offset) {
+                let in_page_offset = tsize - page_offset;
+                page.fill_zero(in_page_offset, PAGE_SIZE - in_page_offset);
+                page.set_dirty();
             }
-            for page_idx in remove_page_idxs {
-                pages.remove(&page_idx);
+        }
+        self.end.store(tsize, Ordering::Release);
+    }
+
+    /// zero the cached data in [start, end)
+    /// pages fully inside the range are dropped if `drop_full`,
+    /// which leaves a hole for those who can read zero from a missing page
+    pub fn zero_range(&self, start: usize, end: usize, drop_full: bool) {
+        if start >= end {
+            return;
+        }
+        let mut pages = self.pages.lock();
+        let offsets: Vec<usize> = pages
+            .range(start / PAGE_SIZE * PAGE_SIZE..end)
+            .map(|(&offset, _)| offset)
+            .collect();
+        for offset in offsets {
+            let zero_start = cmp::max(start, offset) - offset;
+            let zero_end = cmp::min(end, offset + PAGE_SIZE) - offset;
+            if drop_full && zero_start == 0 && zero_end == PAGE_SIZE {
+                pages.remove(&offset);
+            } else {
+                let page = pages.get(&offset).unwrap();
+                page.fill_zero(zero_start, zero_end - zero_start);
+                page.set_dirty();
             }
-            self.end.store(tsize, Ordering::Release);
         }
     }
-}
\ No newline at end of file
+
+    /// bytes backed by cached pages
+    pub fn allocated(&self) -> usize {
+        self.pages.lock().len() * PAGE_SIZE
+    }
+
+    /// end of the last cached page
+    pub fn allocated_end(&self) -> usize {
+        self.pages
+            .lock()
+            .keys()
+            .next_back()
+            .map_or(0, |&offset| offset + PAGE_SIZE)
+    }
+}
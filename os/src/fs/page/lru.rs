@@ -0,0 +1,116 @@
+//! Page cache reclaim
+//!
+//! Pages of caches that have a backing store are put on a global clock list.
+//! Once the list grows past the limit the hand sweeps it from the oldest page:
+//! a page used since the last pass gets a second chance, a mapped or busy page
+//! is passed over, and any other page is written back if dirty and dropped
+//! from its cache. Caches that hold the only copy of their data (tmpfs, shm)
+//! are never put on the list.
+//! The limit is set through /proc/sys/vm/page_cache_limit.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{collections::VecDeque, sync::{Arc, Weak}, vec::Vec};
+
+use crate::{fs::vfs::Inode, mm::allocator::total_frames, sync::mutex::SpinNoIrqLock};
+
+use super::{cache::PageCache, page::Page};
+
+/// a page on the clock list
+struct LruEntry {
+    cache: Weak<PageCache>,
+    inode: Weak<dyn Inode>,
+    offset: usize,
+    page: Weak<Page>,
+}
+
+static PAGE_LRU: SpinNoIrqLock<VecDeque<LruEntry>> = SpinNoIrqLock::new(VecDeque::new());
+/// reclaimable pages kept at most, 0 for a quarter of memory
+static PAGE_CACHE_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// reclaimable pages kept at most
+pub fn limit() -> usize {
+    match PAGE_CACHE_LIMIT.load(Ordering::Relaxed) {
+        0 => total_frames() / 4,
+        limit => limit,
+    }
+}
+
+/// set the reclaimable pages kept at most, 0 for the default,
+/// the cache shrinks to it at the next insertion
+pub fn set_limit(pages: usize) {
+    PAGE_CACHE_LIMIT.store(pages, Ordering::Relaxed);
+}
+
+/// put a freshly cached page on the clock list
+pub fn track(cache: &Arc<PageCache>, offset: usize, page: &Arc<Page>, inode: Weak<dyn Inode>) {
+    PAGE_LRU.lock().push_back(LruEntry {
+        cache: Arc::downgrade(cache),
+        inode,
+        offset,
+        page: Arc::downgrade(page),
+    });
+}
+
+/// sweep the clock list until it is back under the limit,
+/// stop after two rounds if there is not enough to reclaim
+pub fn shrink() {
+    let mut budget = PAGE_LRU.lock().len() * 2;
+    while budget > 0 {
+        budget -= 1;
+        let entry = {
+            let mut lru = PAGE_LRU.lock();
+            if lru.len() <= limit() {
+                return;
+            }
+            match lru.pop_front() {
+                Some(entry) => entry,
+                None => return,
+            }
+        };
+        // the list lock is not held here, writing back may sleep on the disk
+        if !reclaim(&entry) {
+            PAGE_LRU.lock().push_back(entry);
+        }
+    }
+}
+
+/// try to drop the page of `entry`, return whether it left its cache
+fn reclaim(entry: &LruEntry) -> bool {
+    let (Some(cache), Some(page)) = (entry.cache.upgrade(), entry.page.upgrade()) else {
+        // the cache or the page is already gone
+        return true;
+    };
+    if page.take_referenced() || page.is_mapped() {
+        return false;
+    }
+    if page.is_dirty() {
+        let Some(inode) = entry.inode.upgrade() else {
+            return false;
+        };
+        if cache.write_back(entry.offset, &page, inode.as_ref()).is_err() {
+            return false;
+        }
+    }
+    cache.evict(entry.offset, &page)
+}
+
+/// write back every dirty page on the clock list, as for sync,
+/// keep going past a failed page and return the first error
+pub fn sync() -> Result<(), i32> {
+    let entries: Vec<_> = PAGE_LRU.lock()
+        .iter()
+        .filter_map(|entry| Some((
+            entry.cache.upgrade()?,
+            entry.inode.upgrade()?,
+            entry.offset,
+            entry.page.upgrade()?,
+        )))
+        .collect();
+    // the list lock is not held here either
+    let mut ret = Ok(());
+    for (cache, inode, offset, page) in entries {
+        ret = ret.and(cache.write_back(offset, &page, inode.as_ref()));
+    }
+    ret
+}
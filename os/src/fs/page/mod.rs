@@ -1,3 +1,4 @@
 //! Page and Page cache for the file system
 pub mod page;
-pub mod cache;
\ No newline at end of file
+pub mod cache;
+pub mod lru;
@@ -10,6 +10,8 @@ use crate::{fs::vfs::Inode, mm::{allocator::{frames_alloc, FrameAllocator}, Fram
 pub struct Page {
     /// page frame state or attribute
     pub is_dirty: AtomicBool,
+    /// used since the reclaim hand last passed
+    pub referenced: AtomicBool,
     /// offset in a file (if is owned by file)
     pub index: usize, 
     /// the physical frame it owns
@@ -20,14 +22,25 @@ unsafe impl Send for Page {}
 unsafe impl Sync for Page {}
 
 pub const PAGE_SIZE: usize = Constant::PAGE_SIZE;
+
+/// pages held by page caches now
+static CACHED_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// pages held by page caches now
+pub fn cached_pages() -> usize {
+    CACHED_PAGES.load(Ordering::Relaxed)
+}
+
 impl Page {
     /// create a Page by allocating a frame
     pub fn new(index: usize) -> Arc<Self> {
         let frame = FrameAllocator.alloc_tracker(1).expect("[Page]: allocating page failed");
         // clean up the page
         frame.range_ppn.get_slice_mut::<u8>().fill(0);
+        CACHED_PAGES.fetch_add(1, Ordering::Relaxed);
         Arc::new(Self {
             is_dirty: AtomicBool::new(false), // need more flags
+            referenced: AtomicBool::new(false),
             index,
             frame: StrongArc::new(frame),
         })
@@ -59,6 +72,12 @@ impl Page {
         page_slice[offset..offset + write_size].copy_from_slice(&buf[..write_size]);
         write_size
     }
+    /// fill [offset, offset + len) of the page with zero
+    pub fn fill_zero(&self, offset: usize, len: usize) {
+        assert!(offset + len <= PAGE_SIZE);
+        let page_slice = self.frame.range_ppn.get_slice_mut::<u8>();
+        page_slice[offset..offset + len].fill(0);
+    }
     /// read out the page at a specific offset
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         assert!(offset < PAGE_SIZE);
@@ -99,4 +118,22 @@ impl Page {
     pub fn is_dirty(&self) -> bool {
         self.is_dirty.load(Ordering::Acquire)
     }
-}
\ No newline at end of file
+    /// mark the page used
+    pub fn set_referenced(&self) {
+        self.referenced.store(true, Ordering::Relaxed);
+    }
+    /// clear the used mark, returning whether it was set
+    pub fn take_referenced(&self) -> bool {
+        self.referenced.swap(false, Ordering::Relaxed)
+    }
+    /// is the frame mapped into some address space
+    pub fn is_mapped(&self) -> bool {
+        self.frame.get_owners() > 1
+    }
+}
+
+impl Drop for Page {
+    fn drop(&mut self) {
+        CACHED_PAGES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
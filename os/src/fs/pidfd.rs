@@ -0,0 +1,211 @@
+//! pidfd: a file referring to a process, readable once the process has exited
+
+use core::task::Waker;
+
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, sync::{Arc, Weak}};
+use alloc::boxed::Box;
+use async_trait::async_trait;
+
+use crate::{sync::mutex::SpinNoIrqLock, syscall::SysError, task::task::TaskControlBlock, utils::get_waker};
+
+use super::{vfs::{file::PollEvents, inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags};
+
+bitflags! {
+    /// flags for pidfd_open
+    pub struct PidFdFlags: u32 {
+        /// same as O_NONBLOCK
+        const PIDFD_NONBLOCK = 0o4000;
+    }
+}
+
+impl From<PidFdFlags> for OpenFlags {
+    fn from(value: PidFdFlags) -> Self {
+        let mut flags = OpenFlags::O_RDWR;
+        if value.contains(PidFdFlags::PIDFD_NONBLOCK) {
+            flags |= OpenFlags::O_NONBLOCK;
+        }
+        flags
+    }
+}
+
+/// pollers of pidfds waiting for a process to exit, by pid;
+/// a pid being reused only costs a spurious wake up
+static EXIT_WAKERS: SpinNoIrqLock<BTreeMap<usize, VecDeque<Waker>>> = SpinNoIrqLock::new(BTreeMap::new());
+
+/// the process `pid` has exited, its pidfds turn readable
+pub fn notify_exit(pid: usize) {
+    let wakers = EXIT_WAKERS.lock().remove(&pid);
+    for waker in wakers.into_iter().flatten() {
+        waker.wake();
+    }
+}
+
+pub struct PidFdInode {
+    inner: InodeInner,
+}
+
+impl PidFdInode {
+    pub fn new() -> Arc<Self> {
+        let inner = InodeInner::new(
+            None,
+            InodeMode::FILE | InodeMode::OWNER_READ | InodeMode::OWNER_WRITE,
+            0,
+        );
+        Arc::new(Self { inner })
+    }
+}
+
+impl Inode for PidFdInode {
+    fn inode_inner(&self) -> &InodeInner {
+        &self.inner
+    }
+
+    fn getattr(&self) -> Kstat {
+        let inner = self.inode_inner();
+        Kstat {
+            st_dev: inner.st_dev(),
+            st_ino: inner.ino as u64,
+            st_mode: inner.mode().bits() as _,
+            st_nlink: inner.nlink() as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            _pad0: 0,
+            st_size: 0,
+            _pad1: 0,
+            st_blksize: 0,
+            st_blocks: 0,
+            st_atime_sec: inner.atime().tv_sec as _,
+            st_atime_nsec: inner.atime().tv_nsec as _,
+            st_mtime_sec: inner.mtime().tv_sec as _,
+            st_mtime_nsec: inner.mtime().tv_nsec as _,
+            st_ctime_sec: inner.ctime().tv_sec as _,
+            st_ctime_nsec: inner.ctime().tv_nsec as _,
+        }
+    }
+}
+
+pub struct PidFdFile {
+    inode: Arc<PidFdInode>,
+    /// the thread group leader, held weakly so that the process can still be reaped
+    task: Weak<TaskControlBlock>,
+    pid: usize,
+    inner: FileInner,
+}
+
+impl PidFdFile {
+    fn new(dentry: Arc<dyn Dentry>, inode: Arc<PidFdInode>, task: &Arc<TaskControlBlock>, flags: PidFdFlags) -> Arc<Self> {
+        let inner = FileInner {
+            offset: 0.into(),
+            dentry,
+            flags: SpinNoIrqLock::new(flags.into()),
+        };
+        Arc::new(Self {
+            inode,
+            task: Arc::downgrade(task),
+            pid: task.pid(),
+            inner,
+        })
+    }
+
+    /// the pid of the process referred to
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    /// whether every thread of the process has exited, or it is even reaped already
+    pub fn exited(&self) -> bool {
+        self.task.upgrade().map_or(true, |task| task.thread_group.lock().get_alive() == 0)
+    }
+
+    /// the process referred to, as long as it has not exited
+    pub fn task(&self) -> Option<Arc<TaskControlBlock>> {
+        self.task.upgrade().filter(|_| !self.exited())
+    }
+}
+
+#[async_trait]
+impl File for PidFdFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
+        Ok(self.inode.clone())
+    }
+
+    async fn read(&self, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+
+    async fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            // checked under the lock, so that an exit in between is not missed
+            let mut wakers = EXIT_WAKERS.lock();
+            if self.exited() {
+                res |= PollEvents::IN;
+            } else {
+                wakers.entry(self.pid).or_default().push_back(waker);
+            }
+        }
+        res
+    }
+}
+
+pub struct PidFdDentry {
+    inner: DentryInner,
+}
+
+impl PidFdDentry {
+    pub fn new() -> Arc<Self> {
+        let inner = DentryInner::new("[pidfd]", None);
+        Arc::new(Self { inner })
+    }
+}
+
+unsafe impl Sync for PidFdDentry {}
+unsafe impl Send for PidFdDentry {}
+
+impl Dentry for PidFdDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+
+    fn new(
+        &self,
+        _name: &str,
+        _parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        panic!("cannot create a pidfd in this way");
+    }
+}
+
+/// global function to create a pidfd for the process led by `task`
+pub fn make_pidfd(task: &Arc<TaskControlBlock>, flags: PidFdFlags) -> Arc<dyn File> {
+    let inode = PidFdInode::new();
+    let dentry = PidFdDentry::new();
+    dentry.set_inode(inode.clone());
+    PidFdFile::new(dentry, inode, task, flags)
+}
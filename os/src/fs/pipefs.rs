@@ -1,13 +1,14 @@
 //! pipe file system
 //! adapt from phoenix
 
-use core::{future::Future, pin::Pin, task::{Context, Poll, Waker}};
+use core::{future::Future, ops::Range, pin::Pin, task::{Context, Poll, Waker}};
 
-use alloc::{collections::vec_deque::VecDeque, string::ToString, sync::Arc};
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, string::ToString, sync::{Arc, Weak}, vec, vec::Vec};
 use alloc::boxed::Box;
 use async_trait::async_trait;
+use hal::{addr::RangePPNHal, constant::{Constant, ConstantsHal}, util::smart_point::StrongArc};
 
-use crate::{fs::StatxTimestamp, sync::mutex::SpinNoIrqLock, syscall::{io::EPollEvents, SysError}, utils::{get_waker, RingBuffer}};
+use crate::{fs::StatxTimestamp, mm::FrameTracker, sync::mutex::SpinNoIrqLock, syscall::{io::EPollEvents, SysError}, utils::{get_waker, RingBuffer}};
 
 use super::{vfs::{file::PollEvents, inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags, Xstat, XstatMask};
 
@@ -19,22 +20,153 @@ pub struct PipeInode {
 }
 
 pub struct PipeMeta {
-    is_write_closed: bool,
-    is_read_closed: bool,
+    /// files open on the read end
+    readers: usize,
+    /// files open on the write end
+    writers: usize,
+    /// opens of the read end so far, a FIFO opened for writing waits for it to move
+    read_opens: usize,
+    /// opens of the write end so far, a FIFO opened for reading waits for it to move
+    write_opens: usize,
     ring_buffer: RingBuffer,
+    /// pages handed over by vmsplice with SPLICE_F_GIFT, in the order they were given
+    gifts: VecDeque<PipeGift>,
+    /// bytes still to be read from the gifted pages
+    gifted: usize,
+    /// bytes of the ring buffer queued after the last gifted page
+    behind: usize,
     read_waker: VecDeque<Waker>,
     write_waker: VecDeque<Waker>,
+    open_waker: VecDeque<Waker>,
+}
+
+impl PipeMeta {
+    /// queue a waker only once, so polling a ready pipe again and again
+    /// does not pile up the same waker
+    fn register(queue: &mut VecDeque<Waker>, waker: Waker) {
+        if !queue.iter().any(|w| w.will_wake(&waker)) {
+            queue.push_back(waker);
+        }
+    }
+
+    fn wake_all(queue: &mut VecDeque<Waker>) {
+        while let Some(waker) = queue.pop_front() {
+            waker.wake();
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ring_buffer.is_empty() && self.gifts.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.free_len() == 0
+    }
+
+    /// gifted pages take up room like the bytes they hold
+    fn free_len(&self) -> usize {
+        self.ring_buffer.free_len().saturating_sub(self.gifted)
+    }
+
+    /// read as much as possible to fill `buf`, from the ring buffer
+    /// and the gifted pages in the order the data came in
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        while len < buf.len() {
+            let Some(gift) = self.gifts.front_mut() else {
+                len += self.ring_buffer.read(&mut buf[len..]);
+                break;
+            };
+            if gift.ahead > 0 {
+                let n = gift.ahead.min(buf.len() - len);
+                let n = self.ring_buffer.read(&mut buf[len..len + n]);
+                gift.ahead -= n;
+                len += n;
+                continue;
+            }
+            let n = gift.copy_to(&mut buf[len..]);
+            gift.range.start += n;
+            self.gifted -= n;
+            len += n;
+            if gift.range.is_empty() {
+                self.gifts.pop_front();
+                if self.gifts.is_empty() {
+                    self.behind = 0;
+                }
+            }
+        }
+        len
+    }
+
+    /// copy what is at the head of the pipe into `buf` without consuming it,
+    /// stopping where the ring buffer and a gifted page meet
+    fn peek(&self, buf: &mut [u8]) -> usize {
+        match self.gifts.front() {
+            None => self.ring_buffer.peek(buf),
+            Some(gift) if gift.ahead > 0 => {
+                let n = gift.ahead.min(buf.len());
+                self.ring_buffer.peek(&mut buf[..n])
+            }
+            Some(gift) => gift.copy_to(buf),
+        }
+    }
+
+    /// write as much of `buf` as there is room for
+    fn write(&mut self, buf: &[u8]) -> usize {
+        let n = buf.len().min(self.free_len());
+        let n = self.ring_buffer.write(&buf[..n]);
+        if !self.gifts.is_empty() {
+            self.behind += n;
+        }
+        n
+    }
+
+    /// queue `range` of the page `frame` after what the pipe holds
+    fn gift(&mut self, frame: StrongArc<FrameTracker>, range: Range<usize>) {
+        let ahead = if self.gifts.is_empty() {
+            self.ring_buffer.len()
+        } else {
+            self.behind
+        };
+        self.behind = 0;
+        self.gifted += range.len();
+        self.gifts.push_back(PipeGift { ahead, frame, range });
+    }
+}
+
+/// a user page gifted to the pipe, read in place rather than copied in
+struct PipeGift {
+    /// bytes of the ring buffer to be read before this page
+    ahead: usize,
+    frame: StrongArc<FrameTracker>,
+    /// the part of the page not read yet
+    range: Range<usize>,
+}
+
+impl PipeGift {
+    fn copy_to(&self, buf: &mut [u8]) -> usize {
+        let page = self.frame.range_ppn.get_slice::<u8>();
+        let n = self.range.len().min(buf.len());
+        buf[..n].copy_from_slice(&page[self.range.start..self.range.start + n]);
+        n
+    }
 }
 
 impl PipeInode {
     pub fn new(len: usize) -> Arc<Self> {
         let inner = InodeInner::new(None, InodeMode::FIFO, len);
         let pipe_meta = SpinNoIrqLock::new(PipeMeta {
-            is_write_closed: false,
-            is_read_closed: false,
+            readers: 0,
+            writers: 0,
+            read_opens: 0,
+            write_opens: 0,
             ring_buffer: RingBuffer::new(len),
+            gifts: VecDeque::new(),
+            gifted: 0,
+            behind: 0,
             read_waker: VecDeque::new(),
             write_waker: VecDeque::new(),
+            open_waker: VecDeque::new(),
         });
         Arc::new(Self { inner, pipe_meta })
     }
@@ -48,7 +180,7 @@ impl Inode for PipeInode {
     fn getattr(&self) -> Kstat {
         let inner = self.inode_inner();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
@@ -112,8 +244,8 @@ impl Inode for PipeInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -143,11 +275,11 @@ impl Future for PipeWriteFuture {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut meta = self.pipe.pipe_meta.lock();
         let mut res = PollEvents::empty();
-        if meta.is_read_closed {
+        if meta.readers == 0 {
             res |= PollEvents::ERR;
             return Poll::Ready(res);
         }
-        if self.events.contains(PollEvents::OUT) && !meta.ring_buffer.is_full() {
+        if self.events.contains(PollEvents::OUT) && !meta.is_full() {
             res |= PollEvents::OUT;
             Poll::Ready(res)
         } else {
@@ -174,11 +306,11 @@ impl Future for PipeReadFuture {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut meta = self.pipe.pipe_meta.lock();
         let mut res = PollEvents::empty();
-        if self.events.contains(PollEvents::IN) && !meta.ring_buffer.is_empty() {
+        if self.events.contains(PollEvents::IN) && !meta.is_empty() {
             res |= PollEvents::IN;
             Poll::Ready(res)
         } else {
-            if meta.is_write_closed {
+            if meta.writers == 0 {
                 res |= PollEvents::HUP;
                 return Poll::Ready(res);
             }
@@ -188,14 +320,62 @@ impl Future for PipeReadFuture {
     }
 }
 
+/// waits for the other end of a FIFO to be opened
+pub struct PipeOpenFuture {
+    pipe: Arc<PipeInode>,
+    is_reader: bool,
+    /// opens of the other end seen when the wait began
+    opens: usize,
+}
+
+impl PipeOpenFuture {
+    fn new(pipe: Arc<PipeInode>, is_reader: bool, opens: usize) -> Self {
+        Self { pipe, is_reader, opens }
+    }
+}
+
+impl Future for PipeOpenFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut meta = self.pipe.pipe_meta.lock();
+        // the other end may have been opened and closed again since,
+        // which still lets this open through
+        let (others, opens) = if self.is_reader {
+            (meta.writers, meta.write_opens)
+        } else {
+            (meta.readers, meta.read_opens)
+        };
+        if others > 0 || opens != self.opens {
+            Poll::Ready(())
+        } else {
+            PipeMeta::register(&mut meta.open_waker, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
 pub struct PipeFile {
     pipe: Arc<PipeInode>,
-    operate: bool,
+    readable: bool,
+    writable: bool,
     inner: FileInner,
 }
 
 impl PipeFile {
-    fn new(dentry: Arc<dyn Dentry>, is_reader: bool, pipe: Arc<PipeInode>) -> Arc<Self> {
+    fn new(dentry: Arc<dyn Dentry>, readable: bool, writable: bool, pipe: Arc<PipeInode>) -> Arc<Self> {
+        {
+            let mut meta = pipe.pipe_meta.lock();
+            if readable {
+                meta.readers += 1;
+                meta.read_opens += 1;
+            }
+            if writable {
+                meta.writers += 1;
+                meta.write_opens += 1;
+            }
+            PipeMeta::wake_all(&mut meta.open_waker);
+        }
         let inner = FileInner {
             offset: 0.into(),
             dentry: dentry,
@@ -203,10 +383,92 @@ impl PipeFile {
         };
         Arc::new(Self {
             pipe,
-            operate: is_reader,
+            readable,
+            writable,
             inner,
         })
     }
+
+    /// whether a read (`IN`) or a write (`OUT`) would have to wait for now,
+    /// for splice and tee with SPLICE_F_NONBLOCK
+    pub fn would_block(&self, events: PollEvents) -> bool {
+        let meta = self.pipe.pipe_meta.lock();
+        if events.contains(PollEvents::IN) {
+            meta.is_empty() && meta.writers > 0
+        } else {
+            meta.is_full() && meta.readers > 0
+        }
+    }
+
+    /// wait until the pipe has room and tell how much, so that a splice
+    /// does not take more from its source than it can put in here
+    pub async fn room(&self) -> Result<usize, SysError> {
+        if !self.writable {
+            return Err(SysError::EBADF);
+        }
+        let revents = PipeWriteFuture::new(self.pipe.clone(), PollEvents::OUT).await;
+        if revents.contains(PollEvents::ERR) {
+            return Err(SysError::EPIPE);
+        }
+        Ok(self.pipe.pipe_meta.lock().free_len())
+    }
+
+    /// duplicate up to `len` bytes at the head of this pipe into `out`
+    /// without consuming them; the data is staged in between so that
+    /// the two pipes are never locked together
+    pub async fn tee(&self, out: &PipeFile, len: usize, nonblock: bool) -> Result<usize, SysError> {
+        if !self.readable || !out.writable {
+            return Err(SysError::EBADF);
+        }
+        if Arc::ptr_eq(&self.pipe, &out.pipe) {
+            return Err(SysError::EINVAL);
+        }
+        if len == 0 {
+            return Ok(0);
+        }
+        if nonblock && (self.would_block(PollEvents::IN) || out.would_block(PollEvents::OUT)) {
+            return Err(SysError::EAGAIN);
+        }
+        let revents = PipeReadFuture::new(self.pipe.clone(), PollEvents::IN).await;
+        if revents.contains(PollEvents::HUP) {
+            return Ok(0);
+        }
+        let room = out.room().await?;
+        let mut buf = vec![0u8; len.min(room)];
+        let len = self.pipe.pipe_meta.lock().peek(&mut buf);
+        let mut meta = out.pipe.pipe_meta.lock();
+        let len = meta.write(&buf[..len]);
+        PipeMeta::wake_all(&mut meta.read_waker);
+        Ok(len)
+    }
+
+    /// take the gifted pages `frames` in without copying them while the pipe
+    /// has room for the rest of a page, otherwise copy in what fits and wait;
+    /// returns the bytes taken, short only when the readers went away
+    pub async fn gift(&self, frames: Vec<StrongArc<FrameTracker>>) -> Result<usize, SysError> {
+        let mut len = 0;
+        for frame in frames {
+            let mut start = 0;
+            while start < Constant::PAGE_SIZE {
+                let room = match self.room().await {
+                    Ok(room) => room,
+                    Err(_) if len > 0 => return Ok(len),
+                    Err(e) => return Err(e),
+                };
+                let mut meta = self.pipe.pipe_meta.lock();
+                let n = if room >= Constant::PAGE_SIZE - start {
+                    meta.gift(frame.clone(), start..Constant::PAGE_SIZE);
+                    Constant::PAGE_SIZE - start
+                } else {
+                    meta.write(&frame.range_ppn.get_slice::<u8>()[start..])
+                };
+                start += n;
+                len += n;
+                PipeMeta::wake_all(&mut meta.read_waker);
+            }
+        }
+        Ok(len)
+    }
 }
 
 #[async_trait]
@@ -216,21 +478,15 @@ impl File for PipeFile {
     }
 
     fn readable(&self) -> bool {
-        self.operate
+        self.readable
     }
 
     fn writable(&self) -> bool {
-        !self.operate
-    }
-
-    /// override the inode, some test will need pipe inode
-    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
-        Ok(self.pipe.clone())
+        self.writable
     }
 
     async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
-        // assert!(self.operate == true);
-        if self.operate == false {
+        if !self.readable {
             return Err(SysError::EBADF)
         }
         let pipe = self.pipe.clone();
@@ -243,16 +499,13 @@ impl File for PipeFile {
         let mut meta = pipe.pipe_meta.lock();
 
         // log::info!("reading into buf ptr: {:p}", buf.as_ptr());
-        let len = meta.ring_buffer.read(buf);
-        if let Some(waker) = meta.write_waker.pop_front() {
-            waker.wake();
-        }
+        let len = meta.read(buf);
+        PipeMeta::wake_all(&mut meta.write_waker);
         return Ok(len);
     }
 
     async fn write(&self, buf: &[u8]) -> Result<usize, SysError> {
-        // assert!(self.operate == false);
-        if self.operate == true {
+        if !self.writable {
             return Err(SysError::EBADF)
         }
         let pipe = self.pipe.clone();
@@ -262,10 +515,9 @@ impl File for PipeFile {
         }
         assert!(revents.contains(PollEvents::OUT));
         let mut meta = pipe.pipe_meta.lock();
-        let len = meta.ring_buffer.write(buf);
-        if let Some(waker) = meta.read_waker.pop_front() {
-            waker.wake();
-        }
+        let len = meta.write(buf);
+        // every poller wants to know about new data, not only the first reader
+        PipeMeta::wake_all(&mut meta.read_waker);
         return Ok(len);
     }
 
@@ -278,57 +530,52 @@ impl File for PipeFile {
     }
 
     async fn base_poll(&self, events: PollEvents) -> PollEvents {
-        if self.operate == false {
-            // writer
-            let waker = get_waker().await;
-            let pipe = self.pipe.clone();
-            let mut meta = pipe.pipe_meta.lock();
-            let mut res = PollEvents::empty();
-            if meta.is_read_closed {
-                res |= PollEvents::ERR;
-            }
-            if events.contains(PollEvents::OUT) && !meta.ring_buffer.is_full() {
-                res |= PollEvents::OUT;
-            } else {
-                meta.write_waker.push_back(waker);
-            }
-            res
-        } else {
-            // reader
-            let pipe = self.pipe.clone();
-            let waker = get_waker().await;
-            let mut meta = pipe.pipe_meta.lock();
-            let mut res = PollEvents::empty();
-            if meta.is_write_closed {
+        let waker = get_waker().await;
+        let pipe = self.pipe.clone();
+        let mut meta = pipe.pipe_meta.lock();
+        let mut res = PollEvents::empty();
+        if self.readable {
+            if meta.writers == 0 {
                 res |= PollEvents::HUP;
             }
-            if events.contains(PollEvents::IN) && !meta.ring_buffer.is_empty() {
+            if events.contains(PollEvents::IN) && !meta.is_empty() {
                 res |= PollEvents::IN;
-            } else {
-                meta.read_waker.push_back(waker);
             }
-            res
+            // stay registered even when readable, so that epoll sees
+            // every new arrival of data
+            PipeMeta::register(&mut meta.read_waker, waker.clone());
         }
+        if self.writable {
+            if meta.readers == 0 {
+                res |= PollEvents::ERR;
+            }
+            if events.contains(PollEvents::OUT) && !meta.is_full() {
+                res |= PollEvents::OUT;
+            }
+            // stay registered even when writable, epoll edge triggering
+            // relies on being told about every change
+            PipeMeta::register(&mut meta.write_waker, waker);
+        }
+        res
     }
 }
 
 impl Drop for PipeFile {
     fn drop(&mut self) {
-        if self.operate == true {
+        let pipe = self.pipe.clone();
+        let mut meta = pipe.pipe_meta.lock();
+        if self.readable {
             log::warn!("drop a pipe reader");
-            let pipe = self.pipe.clone();
-            let mut meta = pipe.pipe_meta.lock();
-            meta.is_read_closed = true;
-            while let Some(waker) = meta.write_waker.pop_front() {
-                waker.wake();
+            meta.readers -= 1;
+            if meta.readers == 0 {
+                PipeMeta::wake_all(&mut meta.write_waker);
             }
-        } else {
+        }
+        if self.writable {
             log::warn!("drop a pipe writer");
-            let pipe = self.pipe.clone();
-            let mut meta = pipe.pipe_meta.lock();
-            meta.is_write_closed = true;
-            while let Some(waker) = meta.read_waker.pop_front() {
-                waker.wake();
+            meta.writers -= 1;
+            if meta.writers == 0 {
+                PipeMeta::wake_all(&mut meta.read_waker);
             }
         }
     }
@@ -369,7 +616,51 @@ pub fn make_pipe(capacity: usize) -> (Arc<dyn File>, Arc<dyn File>) {
     pipe_read_dentry.set_inode(pipe.clone());
     let pipe_write_dentry = PipeDentry::new();
     pipe_write_dentry.set_inode(pipe.clone());
-    let read_file = PipeFile::new(pipe_read_dentry,true, pipe.clone());
-    let write_file = PipeFile::new(pipe_write_dentry, false, pipe);
+    let read_file = PipeFile::new(pipe_read_dentry, true, false, pipe.clone());
+    let write_file = PipeFile::new(pipe_write_dentry, false, true, pipe);
     (read_file, write_file)
-}
\ No newline at end of file
+}
+
+/// the pipes behind the FIFOs now open, by the inode number of the FIFO;
+/// a pipe goes away with the last file open on it, and what it holds with it
+static FIFOS: SpinNoIrqLock<BTreeMap<usize, Weak<PipeInode>>> = SpinNoIrqLock::new(BTreeMap::new());
+
+/// the pipe a FIFO inode stands for, `inode` may also be the pipe itself
+/// when an anonymous pipe is opened again through /proc/<pid>/fd
+fn fifo_pipe(inode: Arc<dyn Inode>, capacity: usize) -> Arc<PipeInode> {
+    let ino = inode.inode_inner().ino;
+    if let Ok(pipe) = inode.downcast_arc::<PipeInode>() {
+        return pipe;
+    }
+    let mut fifos = FIFOS.lock();
+    if let Some(pipe) = fifos.get(&ino).and_then(|pipe| pipe.upgrade()) {
+        return pipe;
+    }
+    fifos.retain(|_, pipe| pipe.strong_count() > 0);
+    let pipe = PipeInode::new(capacity);
+    fifos.insert(ino, Arc::downgrade(&pipe));
+    pipe
+}
+
+/// open the FIFO `dentry` refers to: a reader and a writer opened through
+/// the same FIFO share one pipe; opening only one end waits until the other
+/// end is opened too, unless O_NONBLOCK is given, then a reader goes on at once
+/// and a writer fails with ENXIO while there is no reader
+pub async fn open_fifo(dentry: Arc<dyn Dentry>, flags: OpenFlags, capacity: usize) -> Result<Arc<dyn File>, SysError> {
+    let pipe = fifo_pipe(dentry.inode().ok_or(SysError::ENOENT)?, capacity);
+    let (readable, writable) = (flags.readable(), flags.writable());
+    let nonblock = flags.contains(OpenFlags::O_NONBLOCK);
+    let opens = {
+        let meta = pipe.pipe_meta.lock();
+        if writable && !readable && nonblock && meta.readers == 0 {
+            return Err(SysError::ENXIO);
+        }
+        if readable { meta.write_opens } else { meta.read_opens }
+    };
+    let file = PipeFile::new(dentry, readable, writable, pipe.clone());
+    // opened for both, the file is its own other end
+    if readable != writable && !nonblock {
+        PipeOpenFuture::new(pipe, readable, opens).await;
+    }
+    Ok(file)
+}
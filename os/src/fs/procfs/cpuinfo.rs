@@ -1,9 +1,11 @@
-use alloc::string::ToString;
+use alloc::{format, string::String, vec::Vec};
+#[cfg(target_arch = "loongarch64")]
 use hal::timer::{Timer, TimerHal};
 
-use crate::fs::tmpfs::inode::InodeContent;
-
+use crate::{fs::tmpfs::inode::InodeContent, processor::processor::online_processors};
 
+/// /proc/cpuinfo: a block for each processor brought up,
+/// laid out as Linux does on the architecture
 pub struct CpuInfo;
 
 impl CpuInfo {
@@ -13,33 +15,52 @@ impl CpuInfo {
 }
 
 impl InodeContent for CpuInfo {
-    fn serialize(&self) -> alloc::string::String {
-        let cpu_freq_mhz = Timer::get_timer_freq() / 1_000_000;
-        let mut res = "".to_string();
-        res += &"processor\t: 0\n".to_string();
-        res += &"vendor_id\t: Intel\n".to_string();
-        res += &"cpu family\t: 5\n".to_string();
-        res += &"model\t: 44\n".to_string();
-        res += &"model name\t: Intel Sucks\n".to_string();
-        res += &"stepping\t: 2\n".to_string();
-
-        res += &"MHz\t: ".to_string();
-        res += &cpu_freq_mhz.to_string();
-        res += &"\n".to_string();
-
-        res += &"cache size\t: 512 KB\n".to_string();
-        res += &"physical id\t: 0\n".to_string();
-        res += &"siblings\t: 1\n".to_string();
-        res += &"runqueue\t: 0\n".to_string();
-        res += &"fdiv_bug\t: no\n".to_string();
-        res += &"hlt_bug\t: no\n".to_string();
-        res += &"f00f_bug\t: no\n".to_string();
-        res += &"coma_bug\t: no\n".to_string();
-        res += &"fpu\t: yes\n".to_string();
-        res += &"fpu_exception\t: yes\n".to_string();
-        res += &"cpuid level\t: 2\n".to_string();
-        res += &"wp\t: yes\n".to_string();
-        res += &"flags\t: fpu vme de pse tsc msr pae mce\n".to_string();
-        res
+    fn serialize(&self) -> String {
+        let blocks: Vec<String> = online_processors().map(processor_block).collect();
+        header() + &blocks.join("\n")
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+fn header() -> String {
+    String::new()
+}
+
+#[cfg(target_arch = "riscv64")]
+fn processor_block(id: usize) -> String {
+    #[cfg(not(feature = "sv48"))]
+    const MMU: &str = "sv39";
+    #[cfg(feature = "sv48")]
+    const MMU: &str = "sv48";
+    let mut res = format!("processor\t: {}\n", id);
+    res += &format!("hart\t\t: {}\n", id);
+    res += &format!("isa\t\t: {}\n", hal::cpu::isa_string());
+    res += &format!("mmu\t\t: {}\n", MMU);
+    if let Some(uarch) = hal::cpu::model_name() {
+        res += &format!("uarch\t\t: {}\n", uarch);
     }
-}
\ No newline at end of file
+    res
+}
+
+#[cfg(target_arch = "loongarch64")]
+fn header() -> String {
+    String::from("system type\t\t: generic-loongson-machine\n\n")
+}
+
+#[cfg(target_arch = "loongarch64")]
+fn processor_block(id: usize) -> String {
+    // the constant timer runs at the core clock
+    let mhz = Timer::get_timer_freq() / 1_000_000;
+    let mut res = format!("processor\t\t: {}\n", id);
+    res += "package\t\t\t: 0\n";
+    res += &format!("core\t\t\t: {}\n", id);
+    res += "CPU Family\t\t: Loongson-64bit\n";
+    res += &format!("Model Name\t\t: {}\n", hal::cpu::model_name().unwrap_or_else(|| String::from("unknown")));
+    res += &format!("CPU MHz\t\t\t: {}.00\n", mhz);
+    res += "ISA\t\t\t: loongarch64\n";
+    // the isa string is the architecture and then its features
+    let isa = hal::cpu::isa_string();
+    let features = isa.split_once(' ').map_or("", |(_, features)| features);
+    res += &format!("Features\t\t: cpucfg {}\n", features);
+    res
+}
@@ -1,8 +1,8 @@
 use alloc::sync::Arc;
 
-use crate::{devices::BlockDevice, fs::{tmpfs::{dentry::TmpDentry, inode::TmpInode}, vfs::{fstype::{FSType, FSTypeInner, MountFlags}, inode::InodeMode, Dentry, DentryState, DCACHE}, SuperBlock, SuperBlockInner}};
+use crate::{devices::BlockDevice, fs::{tmpfs::inode::TmpInode, vfs::{fstype::{FSType, FSTypeInner, MountFlags}, inode::InodeMode, Dentry, DentryState, DCACHE}, SuperBlock, SuperBlockInner}};
 
-use super::superblock::ProcSuperBlock;
+use super::{root::ProcRootDentry, superblock::ProcSuperBlock};
 
 
 pub struct ProcFSType {
@@ -22,6 +22,14 @@ impl FSType for ProcFSType {
         &self.inner
     }
 
+    fn type_name(&self) -> &str {
+        "proc"
+    }
+
+    fn magic(&self) -> u64 {
+        0x9FA0
+    }
+
     fn mount(&'static self, name: &str, parent: Option<Arc<dyn Dentry>>, _flags: MountFlags, dev: Option<Arc<dyn BlockDevice>>) -> Option<Arc<dyn Dentry>> {
         let fs_type = unsafe {
             let ptr: *const dyn FSType = self;
@@ -29,7 +37,7 @@ impl FSType for ProcFSType {
         };
         let sb = ProcSuperBlock::new(SuperBlockInner::new(dev, fs_type.clone()));
         let root_inode = TmpInode::new(Arc::downgrade(&sb), InodeMode::DIR);
-        let root_dentry = TmpDentry::new(name, parent.clone());
+        let root_dentry = ProcRootDentry::new(name, parent.clone());
         root_dentry.set_inode(root_inode);
         root_dentry.set_state(DentryState::USED);
         sb.set_root_dentry(root_dentry.clone());
@@ -0,0 +1,33 @@
+//! /proc/loadavg
+
+use alloc::{format, string::String};
+
+use crate::{fs::tmpfs::inode::InodeContent, task::{last_tid, loadavg::{load_averages, FIXED_1, FSHIFT}, manager::TASK_MANAGER, task::TaskStatus}};
+
+pub struct LoadAvg;
+
+impl LoadAvg {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+/// integer part and two decimals of a fixed point load
+fn load_parts(load: usize) -> (usize, usize) {
+    (load >> FSHIFT, ((load & (FIXED_1 - 1)) * 100) >> FSHIFT)
+}
+
+impl InodeContent for LoadAvg {
+    fn serialize(&self) -> String {
+        let [(i1, f1), (i5, f5), (i15, f15)] = load_averages().map(load_parts);
+        let running = TASK_MANAGER.count_tasks(|task| matches!(
+            task.get_status(),
+            TaskStatus::Running | TaskStatus::Ready
+        ));
+        let total = TASK_MANAGER.count_tasks(|_| true);
+        format!(
+            "{}.{:02} {}.{:02} {}.{:02} {}/{} {}\n",
+            i1, f1, i5, f5, i15, f15, running, total, last_tid()
+        )
+    }
+}
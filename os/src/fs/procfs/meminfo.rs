@@ -7,7 +7,7 @@ use alloc::sync::{Arc, Weak};
 use async_trait::async_trait;
 use alloc::boxed::Box;
 
-use crate::{config::BLOCK_SIZE, fs::{tmpfs::inode::InodeContent, vfs::{inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags, StatxTimestamp, SuperBlock, Xstat, XstatMask}, syscall::SysError};
+use crate::{config::{BLOCK_SIZE, PAGE_SIZE}, mm::vm::commit, fs::{page::page::cached_pages, tmpfs::inode::InodeContent, vfs::{inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags, StatxTimestamp, SuperBlock, Xstat, XstatMask}, syscall::SysError};
 
 use alloc::string::{String, ToString};
 
@@ -18,7 +18,6 @@ pub static MEM_INFO: SpinNoIrqLock<MemInfo> = SpinNoIrqLock::new(MemInfo::new())
 const TOTAL_MEM: usize = 16251136;
 const FREE_MEM: usize = 327680;
 const BUFFER: usize = 373336;
-const CACHED: usize = 10391984;
 const TOTAL_SWAP: usize = 4194300;
 
 /// Mapping to free output: https://access.redhat.com/solutions/406773.
@@ -29,7 +28,6 @@ pub struct MemInfo {
     pub avail_mem: usize,
     /// Buffer and cache
     pub buffers: usize,
-    pub cached: usize,
     /// Swap space
     pub total_swap: usize,
     pub free_swap: usize,
@@ -45,7 +43,6 @@ impl MemInfo {
             free_mem: FREE_MEM,
             avail_mem: TOTAL_MEM - FREE_MEM,
             buffers: BUFFER,
-            cached: CACHED,
             total_swap: TOTAL_SWAP,
             free_swap: TOTAL_SWAP,
             shmem: 0,
@@ -58,11 +55,12 @@ impl InodeContent for MemInfo {
     fn serialize(&self) -> String {
         let mut res = "".to_string();
         let end = " KB\n";
+        let page_kb = PAGE_SIZE / 1024;
         let total_mem = "MemTotal:\t".to_string() + self.total_mem.to_string().as_str() + end;
         let free_mem = "MemFree:\t".to_string() + self.free_mem.to_string().as_str() + end;
         let avail_mem = "MemAvailable:\t".to_string() + self.avail_mem.to_string().as_str() + end;
         let buffers = "Buffers:\t".to_string() + self.buffers.to_string().as_str() + end;
-        let cached = "Cached:\t".to_string() + self.cached.to_string().as_str() + end;
+        let cached = "Cached:\t".to_string() + (cached_pages() * page_kb).to_string().as_str() + end;
         let cached_swap = "SwapCached:\t".to_string() + 0.to_string().as_str() + end;
         let total_swap = "SwapTotal:\t".to_string() + self.total_swap.to_string().as_str() + end;
         let free_swap = "SwapFree:\t".to_string() + self.free_swap.to_string().as_str() + end;
@@ -78,6 +76,10 @@ impl InodeContent for MemInfo {
         res += free_swap.as_str();
         res += shmem.as_str();
         res += slab.as_str();
+        let commit_limit = "CommitLimit:\t".to_string() + (commit::commit_limit() * page_kb).to_string().as_str() + end;
+        let committed = "Committed_AS:\t".to_string() + (commit::committed() * page_kb).to_string().as_str() + end;
+        res += commit_limit.as_str();
+        res += committed.as_str();
         res
     }
 }
\ No newline at end of file
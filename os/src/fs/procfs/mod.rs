@@ -2,7 +2,7 @@
 
 use alloc::sync::{Arc, Weak};
 
-use crate::fs::{fs::CNXFS, procfs::{cpuinfo::CpuInfo, interrupt::Interrupts, meminfo::{MemInfo, MEM_INFO}, mounts::{list_mounts, MountInfo}, selfdir::{exe::ExeInode, fd::FdDentry, maps::Maps}, sys::{fs::PipeMaxSize, kernel::{PidMax, Tainted}}}, tmpfs::{dentry::TmpDentry, inode::{InodeContent, TmpInode, TmpSysInode}}, vfs::{inode::InodeMode, Inode}, SuperBlock};
+use crate::fs::{fs::CNXFS, procfs::{cpuinfo::CpuInfo, interrupt::Interrupts, loadavg::LoadAvg, meminfo::{MemInfo, MEM_INFO}, mounts::{list_mounts, MountInfo}, stat::Stat, selfdir::{exe::ExeInode, fd::FdDentry, maps::Maps}, sys::{fs::PipeMaxSize, kernel::{CorePattern, PidMax, Tainted}, vm::{OvercommitMemory, PageCacheLimit}}, vmstat::VmStat}, tmpfs::{dentry::TmpDentry, inode::{InodeContent, TmpInode, TmpSysInode}}, vfs::{inode::InodeMode, Inode}, SuperBlock};
 
 use super::vfs::{Dentry, DCACHE};
 
@@ -13,7 +13,12 @@ pub mod mounts;
 pub mod meminfo;
 pub mod sys;
 pub mod interrupt;
+pub mod vmstat;
 pub mod cpuinfo;
+pub mod loadavg;
+pub mod stat;
+pub mod root;
+pub mod piddir;
 
 /// init the whole /proc
 pub fn init_procfs(root_dentry: Arc<dyn Dentry>) {
@@ -35,25 +40,38 @@ pub fn init_procfs(root_dentry: Arc<dyn Dentry>) {
     fd_dentry.set_inode(fd_dir_inode);
     self_dentry.add_child(fd_dentry);
 
-    // touch /proc/self/maps (fake, current empty)
-    CNXFS::create_sys_file(Arc::new(Maps {}), "maps", self_dentry.clone());
+    // touch /proc/self/maps
+    CNXFS::create_sys_file(Arc::new(Maps::new(None)), "maps", self_dentry.clone());
 
 
     // touch /proc/cpuinfo
     CNXFS::create_sys_file(Arc::new(CpuInfo::new()), "cpuinfo", root_dentry.clone());
+    // touch /proc/loadavg
+    CNXFS::create_sys_file(Arc::new(LoadAvg::new()), "loadavg", root_dentry.clone());
+    // touch /proc/stat
+    CNXFS::create_sys_file(Arc::new(Stat::new()), "stat", root_dentry.clone());
     // touch /proc/meminfo
     CNXFS::create_sys_file(Arc::new(MemInfo::new()), "meminfo", root_dentry.clone());
     // touch /proc/mounts
     CNXFS::create_sys_file(Arc::new(MountInfo::new()),"mounts", root_dentry.clone());
     // touch /proc/interrupt
     CNXFS::create_sys_file(Arc::new(Interrupts::new()), "interrupts", root_dentry.clone());
+    // touch /proc/vmstat
+    CNXFS::create_sys_file(Arc::new(VmStat::new()), "vmstat", root_dentry.clone());
     // touch /proc/sys/kernel/pid_max
     let sys_dentry = CNXFS::create_sys_dir("sys", sb.clone().unwrap(), root_dentry.clone());
     let kernel_dentry = CNXFS::create_sys_dir("kernel", sb.clone().unwrap(), sys_dentry.clone());
     CNXFS::create_sys_file(Arc::new(PidMax::new()), "pid_max", kernel_dentry.clone());
     // touch /proc/sys/kernel/tainted
-    CNXFS::create_sys_file(Arc::new(Tainted::new()), "tainted", kernel_dentry);
+    CNXFS::create_sys_file(Arc::new(Tainted::new()), "tainted", kernel_dentry.clone());
+    // touch /proc/sys/kernel/core_pattern
+    CNXFS::create_sys_file(Arc::new(CorePattern::new()), "core_pattern", kernel_dentry);
     // touch /proc/sys/fs/pipe-max-size
-    let fs_dentry = CNXFS::create_sys_dir("fs", sb.clone().unwrap(), sys_dentry);
+    let fs_dentry = CNXFS::create_sys_dir("fs", sb.clone().unwrap(), sys_dentry.clone());
     CNXFS::create_sys_file(Arc::new(PipeMaxSize::new()), "pipe-max-size", fs_dentry);
+    // touch /proc/sys/vm/overcommit_memory
+    let vm_dentry = CNXFS::create_sys_dir("vm", sb.clone().unwrap(), sys_dentry);
+    CNXFS::create_sys_file(Arc::new(OvercommitMemory::new()), "overcommit_memory", vm_dentry.clone());
+    // touch /proc/sys/vm/page_cache_limit
+    CNXFS::create_sys_file(Arc::new(PageCacheLimit::new()), "page_cache_limit", vm_dentry);
 }
\ No newline at end of file
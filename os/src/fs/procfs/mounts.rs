@@ -30,7 +30,7 @@ impl InodeContent for MountInfo {
                 res += mount_path;
                 res += " ";
                 // fs type name
-                res += fs.name();
+                res += fs.type_name();
                 res += " ";
                 // fs stat flags (todo)
                 res += "rw,nosuid,nodev,noexec,relatime";
@@ -0,0 +1,25 @@
+use alloc::{string::String, sync::Weak};
+
+use crate::{fs::tmpfs::inode::InodeContent, task::task::TaskControlBlock};
+
+/// /proc/<pid>/cmdline: the arguments, each ended by a NUL
+pub struct Cmdline {
+    task: Weak<TaskControlBlock>,
+}
+
+impl Cmdline {
+    pub fn new(task: Weak<TaskControlBlock>) -> Self {
+        Self { task }
+    }
+}
+
+impl InodeContent for Cmdline {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return String::new();
+        };
+        task.with_cmdline(|cmdline| {
+            cmdline.iter().flat_map(|arg| [arg.as_str(), "\0"]).collect()
+        })
+    }
+}
@@ -0,0 +1,31 @@
+use alloc::{string::String, sync::Weak};
+
+use crate::{fs::tmpfs::inode::InodeContent, task::task::TaskControlBlock};
+
+use super::proc_owner;
+
+/// /proc/<pid>/environ: the initial environment, `KEY=VALUE` pairs each ended by a NUL
+pub struct Environ {
+    task: Weak<TaskControlBlock>,
+}
+
+impl Environ {
+    pub fn new(task: Weak<TaskControlBlock>) -> Self {
+        Self { task }
+    }
+}
+
+impl InodeContent for Environ {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return String::new();
+        };
+        task.with_environ(|environ| {
+            environ.iter().flat_map(|env| [env.as_str(), "\0"]).collect()
+        })
+    }
+
+    fn owner(&self) -> Option<(u32, u32)> {
+        Some(proc_owner(&self.task))
+    }
+}
@@ -0,0 +1,223 @@
+//! /proc/<pid>: the files about one process, made up on each lookup,
+//! and /proc/<pid>/task/<tid>: the files about each of its threads
+
+pub mod cmdline;
+pub mod environ;
+pub mod stat;
+pub mod statm;
+pub mod status;
+pub mod syscall;
+pub mod task;
+pub mod wchan;
+
+use alloc::{string::{String, ToString}, sync::{Arc, Weak}, vec::Vec};
+
+use crate::{fs::{procfs::selfdir::maps::Maps, tmpfs::{dentry::TmpDentry, file::TmpFile, inode::{InodeContent, TmpInode, TmpSysInode}}, vfs::{inode::InodeMode, Dentry, DentryInner, DentryState, File}, OpenFlags}, syscall::SysError, task::{manager::TASK_MANAGER, task::{TaskControlBlock, TaskStatus}}};
+
+use self::{cmdline::Cmdline, environ::Environ, stat::Stat, statm::Statm, status::Status, syscall::Syscall, task::TaskDirDentry, wchan::Wchan};
+
+/// the files in every process directory
+const PID_FILES: &[&str] = &["cmdline", "environ", "maps", "stat", "statm", "status", "syscall", "task", "wchan"];
+/// the files in every thread directory
+const TID_FILES: &[&str] = &["stat", "status", "wchan"];
+
+/// the directory of a process, or of a thread under /proc/<pid>/task
+pub struct PidDentry {
+    inner: DentryInner,
+    /// the directory itself, the parent of the files made on lookup
+    this: Weak<PidDentry>,
+    task: Weak<TaskControlBlock>,
+    /// the files found in the directory
+    files: &'static [&'static str],
+    /// the task directory a thread directory is in, held as the files hold theirs,
+    /// None for the directory of a process
+    thread_parent: Option<Arc<dyn Dentry>>,
+}
+
+unsafe impl Send for PidDentry {}
+unsafe impl Sync for PidDentry {}
+
+impl PidDentry {
+    pub fn new(task: &Arc<TaskControlBlock>, parent: Arc<dyn Dentry>) -> Arc<dyn Dentry> {
+        Self::build(task, parent, PID_FILES, None)
+    }
+
+    /// the directory of a thread in /proc/<pid>/task
+    pub fn new_thread(task: &Arc<TaskControlBlock>, parent: Arc<dyn Dentry>) -> Arc<dyn Dentry> {
+        Self::build(task, parent.clone(), TID_FILES, Some(parent))
+    }
+
+    fn build(
+        task: &Arc<TaskControlBlock>,
+        parent: Arc<dyn Dentry>,
+        files: &'static [&'static str],
+        hold: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        let sb = parent.inode().unwrap().inode_inner().super_block.clone().unwrap();
+        let dentry = Arc::new_cyclic(|this| Self {
+            inner: DentryInner::new(&task.tid().to_string(), Some(parent)),
+            this: this.clone(),
+            task: Arc::downgrade(task),
+            files,
+            thread_parent: hold,
+        });
+        let mode = InodeMode::DIR | InodeMode::from_bits_truncate(0o555);
+        dentry.set_inode(TmpInode::new(sb, mode));
+        dentry
+    }
+
+    /// what the file `name` reads and who may read it
+    fn content(&self, name: &str) -> Option<(Arc<dyn InodeContent>, InodeMode)> {
+        let task = self.task.clone();
+        // the times and counts of a process are those of all its threads
+        let process = self.thread_parent.is_none();
+        match name {
+            "cmdline" => Some((Arc::new(Cmdline::new(task)), InodeMode::from_bits_truncate(0o444))),
+            "environ" => Some((Arc::new(Environ::new(task)), InodeMode::OWNER_READ)),
+            "maps" => Some((Arc::new(Maps::new(Some(task))), InodeMode::from_bits_truncate(0o444))),
+            "stat" => Some((Arc::new(Stat::new(task, process)), InodeMode::from_bits_truncate(0o444))),
+            "statm" => Some((Arc::new(Statm::new(task)), InodeMode::from_bits_truncate(0o444))),
+            "status" => Some((Arc::new(Status::new(task, process)), InodeMode::from_bits_truncate(0o444))),
+            "syscall" => Some((Arc::new(Syscall::new(task)), InodeMode::OWNER_READ)),
+            "wchan" => Some((Arc::new(Wchan::new(task)), InodeMode::from_bits_truncate(0o444))),
+            _ => None,
+        }
+    }
+
+    fn build_child(&self, name: &str) -> Option<Arc<dyn Dentry>> {
+        if !self.files.contains(&name) {
+            return None;
+        }
+        let dir: Arc<dyn Dentry> = self.this.upgrade()?;
+        if name == "task" {
+            return Some(TaskDirDentry::new(self.task.clone(), dir));
+        }
+        let (content, perm) = self.content(name)?;
+        let sb = dir.inode().and_then(|inode| inode.inode_inner().super_block.clone());
+        let dentry = PidFileDentry::new(name, dir);
+        dentry.set_inode(TmpSysInode::new(sb, InodeMode::FILE | perm, content));
+        Some(dentry)
+    }
+}
+
+impl Dentry for PidDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+    fn new(&self,
+        name: &str,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        TmpDentry::new(name, parent)
+    }
+
+    fn open(self: Arc<Self>, _flags: OpenFlags) -> Option<Arc<dyn File>> {
+        Some(TmpFile::new_arc(self.clone()))
+    }
+
+    /// gone with the process, also when the pid is taken by another one
+    fn is_negative(&self) -> bool {
+        match self.task.upgrade() {
+            Some(task) => !TASK_MANAGER.get_task(task.tid())
+                .map_or(false, |live| Arc::ptr_eq(&live, &task)),
+            None => true,
+        }
+    }
+
+    fn get_child(&self, name: &str) -> Option<Arc<dyn Dentry>> {
+        self.build_child(name)
+    }
+
+    fn load_child_dentry(self: Arc<Self>) -> Result<Vec<Arc<dyn Dentry>>, SysError> {
+        Ok(self.files.iter().filter_map(|name| self.build_child(name)).collect())
+    }
+
+    fn new_neg_dentry(self: Arc<Self>, name: &str) -> Result<Arc<dyn Dentry>, SysError> {
+        let neg_dentry = TmpDentry::new(name, Some(self.clone()));
+        neg_dentry.set_state(DentryState::NEGATIVE);
+        Ok(neg_dentry)
+    }
+}
+
+/// a file of a process directory, it holds the directory
+/// so that its path stays whole as long as the file is open
+pub struct PidFileDentry {
+    inner: DentryInner,
+    _dir: Arc<dyn Dentry>,
+}
+
+unsafe impl Send for PidFileDentry {}
+unsafe impl Sync for PidFileDentry {}
+
+impl PidFileDentry {
+    pub fn new(name: &str, dir: Arc<dyn Dentry>) -> Arc<dyn Dentry> {
+        Arc::new(Self {
+            inner: DentryInner::new(name, Some(dir.clone())),
+            _dir: dir,
+        })
+    }
+}
+
+impl Dentry for PidFileDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+    fn new(&self,
+        name: &str,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        TmpDentry::new(name, parent)
+    }
+
+    fn open(self: Arc<Self>, _flags: OpenFlags) -> Option<Arc<dyn File>> {
+        Some(TmpFile::new_arc(self.clone()))
+    }
+}
+
+/// who the private files of a process belong to: its user,
+/// or root once it runs with ids it did not start with (a set-user-ID program)
+/// or is gone, so that only the same user or root reads them
+pub fn proc_owner(task: &Weak<TaskControlBlock>) -> (u32, u32) {
+    let Some(task) = task.upgrade() else {
+        return (0, 0);
+    };
+    let (euid, egid) = (task.euid(), task.egid());
+    if task.ruid() != euid || task.suid() != euid || task.rgid() != egid || task.sgid() != egid {
+        return (0, 0);
+    }
+    (euid as u32, egid as u32)
+}
+
+/// the name of the program the task runs, as its comm: the last part of
+/// the path it was executed by, cut to 15 bytes
+pub fn task_comm(task: &Arc<TaskControlBlock>) -> String {
+    let name = task.elf.lock().as_ref()
+        .and_then(|elf| elf.dentry())
+        .map(|dentry| dentry.name().to_string())
+        .unwrap_or_default();
+    let mut end = name.len().min(15);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].to_string()
+}
+
+/// the state letter of the task and its name, as ps shows them;
+/// a task preempted in user space is still running
+pub fn task_state(task: &Arc<TaskControlBlock>) -> (char, &'static str) {
+    match task.get_status() {
+        TaskStatus::Ready | TaskStatus::Running => ('R', "running"),
+        TaskStatus::Interruptable => ('S', "sleeping"),
+        TaskStatus::UnInterruptable => ('D', "disk sleep"),
+        TaskStatus::Stopped => ('T', "stopped"),
+        TaskStatus::Zombie => ('Z', "zombie"),
+    }
+}
+
+/// the process id of the parent of the process, 0 when it has none
+pub fn task_ppid(task: &Arc<TaskControlBlock>) -> usize {
+    task.get_leader()
+        .parent()
+        .and_then(|parent| parent.upgrade())
+        .map_or(0, |parent| parent.pid())
+}
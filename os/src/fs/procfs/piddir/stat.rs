@@ -0,0 +1,58 @@
+use alloc::{format, string::String, sync::Weak};
+use hal::constant::{Constant, ConstantsHal};
+
+use crate::{fs::tmpfs::inode::InodeContent, task::task::TaskControlBlock, timer::duration_to_ticks};
+
+use super::{task_comm, task_ppid, task_state};
+
+/// /proc/<pid>/stat and /proc/<pid>/task/<tid>/stat: the one line of numbers `ps` reads,
+/// fields the kernel keeps no account of are 0
+pub struct Stat {
+    task: Weak<TaskControlBlock>,
+    /// the times are summed over the threads of the process
+    process: bool,
+}
+
+impl Stat {
+    pub fn new(task: Weak<TaskControlBlock>, process: bool) -> Self {
+        Self { task, process }
+    }
+}
+
+impl InodeContent for Stat {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return String::new();
+        };
+        let (state, _) = task_state(&task);
+        let threads = task.with_thread_group(|tg| tg.len());
+        let recorder = task.time_recorder_ref();
+        let (utime, stime) = if self.process && threads > 0 {
+            task.process_time_pair()
+        } else {
+            recorder.time_pair()
+        };
+        let (cutime, cstime) = recorder.child_time_pair();
+        let rt_priority = task.rt_priority();
+        let priority = if rt_priority > 0 {
+            -1 - rt_priority as i32
+        } else {
+            20 + task.nice()
+        };
+        let exit_signal = task.with_thread_group(|tg| tg.exit_signal);
+        let (vsize, rss) = task.with_vm_space(|vm| (vm.vm_size() * Constant::PAGE_SIZE, vm.rss()));
+        let mut res = format!(
+            "{} ({}) {} {} {} 0 0 -1 0 0 0 0 0 {} {} {} {} {} {} {} 0 0 {} {} {}",
+            task.tid(), task_comm(&task), state, task_ppid(&task), task.pgid(),
+            duration_to_ticks(utime), duration_to_ticks(stime),
+            duration_to_ticks(cutime), duration_to_ticks(cstime),
+            priority, task.nice(), threads, vsize, rss, u64::MAX,
+        );
+        // startcode to nswap, then cnswap
+        res += " 0 0 0 0 0 0 0 0 0 0 0 0";
+        res += &format!(" {} {} {} {}", exit_signal, task.processor_id(), rt_priority, task.sched_policy());
+        // delayacct_blkio_ticks to env_end
+        res += " 0 0 0 0 0 0 0 0 0 0";
+        res + &format!(" {}\n", task.exit_code())
+    }
+}
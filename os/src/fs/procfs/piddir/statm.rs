@@ -0,0 +1,27 @@
+use alloc::{format, string::String, sync::Weak};
+
+use crate::{fs::tmpfs::inode::InodeContent, task::task::TaskControlBlock};
+
+/// /proc/<pid>/statm: the memory of the process in pages,
+/// size resident shared text lib data dt, lib and dt are always 0
+pub struct Statm {
+    task: Weak<TaskControlBlock>,
+}
+
+impl Statm {
+    pub fn new(task: Weak<TaskControlBlock>) -> Self {
+        Self { task }
+    }
+}
+
+impl InodeContent for Statm {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return String::new();
+        };
+        task.with_vm_space(|vm| format!(
+            "{} {} {} {} 0 {} 0\n",
+            vm.vm_size(), vm.rss(), vm.shared_rss(), vm.text_size(), vm.data_size(),
+        ))
+    }
+}
@@ -0,0 +1,54 @@
+use alloc::{format, string::String, sync::Weak};
+use hal::constant::{Constant, ConstantsHal};
+
+use crate::{fs::tmpfs::inode::InodeContent, task::task::TaskControlBlock};
+
+use super::{task_comm, task_ppid, task_state};
+
+/// /proc/<pid>/status and /proc/<pid>/task/<tid>/status: the state, ids and memory
+/// of the task as `Field:\tvalue` lines, memory in kB
+pub struct Status {
+    task: Weak<TaskControlBlock>,
+    /// the context switches are summed over the threads of the process
+    process: bool,
+}
+
+impl Status {
+    pub fn new(task: Weak<TaskControlBlock>, process: bool) -> Self {
+        Self { task, process }
+    }
+}
+
+impl InodeContent for Status {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return String::new();
+        };
+        let (state, state_name) = task_state(&task);
+        let kb = |pages: usize| pages * Constant::PAGE_SIZE / 1024;
+        let (size, locked, rss, peak_rss) = task.with_vm_space(|vm| (vm.vm_size(), vm.locked_pages(), vm.rss(), vm.peak_rss()));
+        let threads = task.with_thread_group(|tg| tg.len());
+        let (nvcsw, nivcsw) = if self.process {
+            task.process_switch_pair()
+        } else {
+            task.time_recorder_ref().switch_pair()
+        };
+        let mut res = format!("Name:\t{}\n", task_comm(&task));
+        res += &format!("State:\t{} ({})\n", state, state_name);
+        res += &format!("Tgid:\t{}\n", task.pid());
+        res += &format!("Pid:\t{}\n", task.tid());
+        res += &format!("PPid:\t{}\n", task_ppid(&task));
+        res += "TracerPid:\t0\n";
+        // the file system ids follow the effective ones
+        res += &format!("Uid:\t{}\t{}\t{}\t{}\n", task.ruid(), task.euid(), task.suid(), task.euid());
+        res += &format!("Gid:\t{}\t{}\t{}\t{}\n", task.rgid(), task.egid(), task.sgid(), task.egid());
+        res += &format!("VmSize:\t{:>8} kB\n", kb(size));
+        res += &format!("VmLck:\t{:>8} kB\n", kb(locked));
+        res += &format!("VmHWM:\t{:>8} kB\n", kb(peak_rss));
+        res += &format!("VmRSS:\t{:>8} kB\n", kb(rss));
+        res += &format!("Threads:\t{}\n", threads);
+        res += &format!("Cpus_allowed:\t{:x}\n", task.cpu_allowed());
+        res += &format!("voluntary_ctxt_switches:\t{}\n", nvcsw);
+        res + &format!("nonvoluntary_ctxt_switches:\t{}\n", nivcsw)
+    }
+}
@@ -0,0 +1,42 @@
+use alloc::{format, string::String, sync::Weak};
+use hal::trap::TrapContextHal;
+
+use crate::{fs::tmpfs::inode::InodeContent, task::task::TaskControlBlock};
+
+use super::proc_owner;
+
+/// /proc/<pid>/syscall: the number and the six arguments of the syscall
+/// the task is in, then its user stack pointer and pc, all but the number in hex.
+/// `-1 sp pc` when it is in user space
+pub struct Syscall {
+    task: Weak<TaskControlBlock>,
+}
+
+impl Syscall {
+    pub fn new(task: Weak<TaskControlBlock>) -> Self {
+        Self { task }
+    }
+}
+
+impl InodeContent for Syscall {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return String::new();
+        };
+        let cx = task.get_trap_cx();
+        let (sp, pc) = (*cx.sp(), *cx.sepc());
+        let nr = task.syscall_nr();
+        if nr < 0 {
+            return format!("-1 {:#x} {:#x}\n", sp, pc);
+        }
+        let mut res = format!("{}", nr);
+        for i in 0..6 {
+            res += &format!(" {:#x}", cx.syscall_arg_nth(i));
+        }
+        res + &format!(" {:#x} {:#x}\n", sp, pc)
+    }
+
+    fn owner(&self) -> Option<(u32, u32)> {
+        Some(proc_owner(&self.task))
+    }
+}
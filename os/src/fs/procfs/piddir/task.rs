@@ -0,0 +1,80 @@
+use alloc::{sync::{Arc, Weak}, vec::Vec};
+
+use crate::{fs::{tmpfs::{dentry::TmpDentry, file::TmpFile, inode::TmpInode}, vfs::{inode::InodeMode, Dentry, DentryInner, DentryState, File}, OpenFlags}, syscall::SysError, task::task::TaskControlBlock};
+
+use super::PidDentry;
+
+/// /proc/<pid>/task: a directory for each thread of the process,
+/// it holds the process directory as its files do
+pub struct TaskDirDentry {
+    inner: DentryInner,
+    /// the directory itself, the parent of the thread directories made on lookup
+    this: Weak<TaskDirDentry>,
+    task: Weak<TaskControlBlock>,
+    dir: Arc<dyn Dentry>,
+}
+
+unsafe impl Send for TaskDirDentry {}
+unsafe impl Sync for TaskDirDentry {}
+
+impl TaskDirDentry {
+    pub fn new(task: Weak<TaskControlBlock>, dir: Arc<dyn Dentry>) -> Arc<dyn Dentry> {
+        let sb = dir.inode().unwrap().inode_inner().super_block.clone().unwrap();
+        let dentry = Arc::new_cyclic(|this| Self {
+            inner: DentryInner::new("task", Some(dir.clone())),
+            this: this.clone(),
+            task,
+            dir,
+        });
+        let mode = InodeMode::DIR | InodeMode::from_bits_truncate(0o555);
+        dentry.set_inode(TmpInode::new(sb, mode));
+        dentry
+    }
+
+    /// the live threads of the process, in the order of their ids
+    fn threads(&self) -> Vec<Arc<TaskControlBlock>> {
+        match self.task.upgrade() {
+            Some(task) => task.with_thread_group(|tg| tg.iter().collect()),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Dentry for TaskDirDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+    fn new(&self,
+        name: &str,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        TmpDentry::new(name, parent)
+    }
+
+    fn open(self: Arc<Self>, _flags: OpenFlags) -> Option<Arc<dyn File>> {
+        Some(TmpFile::new_arc(self.clone()))
+    }
+
+    fn is_negative(&self) -> bool {
+        self.dir.is_negative()
+    }
+
+    fn get_child(&self, name: &str) -> Option<Arc<dyn Dentry>> {
+        let tid: usize = name.parse().ok()?;
+        let thread = self.threads().into_iter().find(|thread| thread.tid() == tid)?;
+        Some(PidDentry::new_thread(&thread, self.this.upgrade()?))
+    }
+
+    fn load_child_dentry(self: Arc<Self>) -> Result<Vec<Arc<dyn Dentry>>, SysError> {
+        Ok(self.threads()
+            .iter()
+            .map(|thread| PidDentry::new_thread(thread, self.clone()))
+            .collect())
+    }
+
+    fn new_neg_dentry(self: Arc<Self>, name: &str) -> Result<Arc<dyn Dentry>, SysError> {
+        let neg_dentry = TmpDentry::new(name, Some(self.clone()));
+        neg_dentry.set_state(DentryState::NEGATIVE);
+        Ok(neg_dentry)
+    }
+}
@@ -0,0 +1,39 @@
+use alloc::{format, string::{String, ToString}, sync::Weak};
+
+use crate::{fs::tmpfs::inode::InodeContent, syscall::SyscallId, task::task::TaskControlBlock};
+
+/// /proc/<pid>/wchan: where the task sleeps in the kernel, `0` when it does not.
+/// a task waits inside the future of its syscall, there is no kernel stack
+/// of its own to walk, so the syscall handler is the place it is blocked in
+pub struct Wchan {
+    task: Weak<TaskControlBlock>,
+}
+
+impl Wchan {
+    pub fn new(task: Weak<TaskControlBlock>) -> Self {
+        Self { task }
+    }
+}
+
+impl InodeContent for Wchan {
+    fn serialize(&self) -> String {
+        let Some(task) = self.task.upgrade() else {
+            return "0".to_string();
+        };
+        if !(task.is_interruptable() || task.is_uninterruptable() || task.is_stopped()) {
+            return "0".to_string();
+        }
+        match SyscallId::from_repr(task.syscall_nr() as usize) {
+            Some(id) if task.syscall_nr() >= 0 => syscall_handler_name(id),
+            // stopped by a signal on the way back to user space
+            _ if task.is_stopped() => "do_signal_stop".to_string(),
+            _ => "0".to_string(),
+        }
+    }
+}
+
+/// name of the function handling the syscall, SYSCALL_WAIT4 is sys_wait4
+pub fn syscall_handler_name(id: SyscallId) -> String {
+    let name = format!("{:?}", id);
+    format!("sys_{}", name.trim_start_matches("SYSCALL_").to_lowercase())
+}
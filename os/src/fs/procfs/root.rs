@@ -0,0 +1,73 @@
+//! the root of /proc: the fixed entries plus a directory for each process
+
+use alloc::{sync::{Arc, Weak}, vec::Vec};
+
+use crate::{fs::{procfs::piddir::PidDentry, tmpfs::{dentry::TmpDentry, file::TmpFile}, vfs::{Dentry, DentryInner, DentryState, File}, OpenFlags}, syscall::SysError, task::manager::TASK_MANAGER};
+
+pub struct ProcRootDentry {
+    inner: DentryInner,
+    /// the root itself, the parent of the process directories made on lookup
+    this: Weak<ProcRootDentry>,
+}
+
+unsafe impl Send for ProcRootDentry {}
+unsafe impl Sync for ProcRootDentry {}
+
+impl ProcRootDentry {
+    pub fn new(
+        name: &str,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        Arc::new_cyclic(|this| Self {
+            inner: DentryInner::new(name, parent),
+            this: this.clone(),
+        })
+    }
+}
+
+impl Dentry for ProcRootDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+    fn new(&self,
+        name: &str,
+        parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        ProcRootDentry::new(name, parent)
+    }
+
+    fn open(self: Arc<Self>, _flags: OpenFlags) -> Option<Arc<dyn File>> {
+        Some(TmpFile::new_arc(self.clone()))
+    }
+
+    /// the process directories are not kept, they come and go with the processes
+    fn get_child(&self, name: &str) -> Option<Arc<dyn Dentry>> {
+        if let Some(child) = self.dentry_inner().children.lock().get(name).cloned() {
+            return Some(child);
+        }
+        let tid = name.parse().ok()?;
+        let task = TASK_MANAGER.get_task(tid)?;
+        Some(PidDentry::new(&task, self.this.upgrade()?))
+    }
+
+    fn load_child_dentry(self: Arc<Self>) -> Result<Vec<Arc<dyn Dentry>>, SysError> {
+        let mut child_dentrys: Vec<Arc<dyn Dentry>> = self.children()
+            .into_values()
+            .filter(|child| child.state() != DentryState::NEGATIVE)
+            .collect();
+        // only the processes are listed, the threads can still be looked up
+        for task in TASK_MANAGER.tasks_group() {
+            if task.is_leader() {
+                child_dentrys.push(PidDentry::new(&task, self.clone()));
+            }
+        }
+        Ok(child_dentrys)
+    }
+
+    fn new_neg_dentry(self: Arc<Self>, name: &str) -> Result<Arc<dyn Dentry>, SysError> {
+        let neg_dentry = TmpDentry::new(name, Some(self.clone()));
+        neg_dentry.set_state(DentryState::NEGATIVE);
+        Ok(neg_dentry)
+    }
+}
+
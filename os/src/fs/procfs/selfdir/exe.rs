@@ -22,7 +22,7 @@ impl Inode for ExeInode {
     fn getattr(&self) -> crate::fs::Kstat {
         let inner = self.inode_inner();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
@@ -86,8 +86,8 @@ impl Inode for ExeInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -99,6 +99,10 @@ impl Inode for ExeInode {
         }
     }
 
+    fn is_magic_link(&self) -> bool {
+        true
+    }
+
     fn readlink(&self) -> Result<String, SysError> {
         return Ok(current_task().unwrap().elf.lock().clone().ok_or(SysError::ENFILE)?.dentry().ok_or(SysError::ENOENT)?.path());
     }
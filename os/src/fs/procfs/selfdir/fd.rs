@@ -2,9 +2,11 @@
 
 use alloc::{string::ToString, sync::Arc, vec::Vec};
 use alloc::string::String;
+use spin::Lazy;
 
 use crate::fs::tmpfs::file::TmpFile;
 use crate::fs::vfs::Inode;
+use crate::fs::vfs::inode::InoAllocator;
 use crate::fs::{Kstat, StatxTimestamp, Xstat, XstatMask};
 use crate::{fs::{tmpfs::dentry::TmpDentry, vfs::{inode::InodeMode, Dentry, DentryInner, DentryState, InodeInner}}, syscall::SysError, task::current_task};
 
@@ -49,6 +51,7 @@ impl Dentry for FdDentry {
     fn load_child_dentry(self: Arc<Self>) -> Result<Vec<Arc<dyn Dentry>>, SysError> {
         let mut child_dentrys: Vec<Arc<dyn Dentry>> = Vec::new();
         let task = current_task().unwrap().clone();
+        let pid = task.pid();
         task.with_fd_table(|t| {
             let fds = &t.fd_table;
             for i in 0..fds.len() {
@@ -56,7 +59,7 @@ impl Dentry for FdDentry {
                     let name = i.to_string();
                     let child = TmpDentry::new(&name, None);
                     let path = fd_info.file.file_inner().dentry.path();
-                    let fd_inode = FdChildInode::new(&path);
+                    let fd_inode = FdChildInode::new(&path, pid, i);
                     child.set_inode(fd_inode);
                     child_dentrys.push(child);
                 }
@@ -67,6 +70,7 @@ impl Dentry for FdDentry {
 
     fn get_child(&self, name: &str) -> Option<Arc<dyn Dentry>> {
         let task = current_task().unwrap().clone();
+        let pid = task.pid();
         task.with_fd_table(|t| {
             let fds = &t.fd_table;
             for i in 0..fds.len() {
@@ -75,7 +79,7 @@ impl Dentry for FdDentry {
                     if fd_name == name {
                         let child = TmpDentry::new(&name, None);
                         let path = fd_info.file.file_inner().dentry.path();
-                        let fd_inode = FdChildInode::new(&path);
+                        let fd_inode = FdChildInode::new(&path, pid, i);
                         child.set_inode(fd_inode);
                         return Some(child)
                     }
@@ -91,9 +95,14 @@ pub struct FdChildInode {
     link_path: String,
 }
 
+/// numbers the fd links, which are built again on every lookup
+static FD_INO: Lazy<InoAllocator> = Lazy::new(InoAllocator::new);
+
 impl FdChildInode {
-    pub fn new(file_path: &str) -> Arc<Self> {
-        let inner = InodeInner::new(None, InodeMode::LINK, 0);
+    pub fn new(file_path: &str, pid: usize, fd: usize) -> Arc<Self> {
+        // the same fd of the same process keeps its number across lookups
+        let ino = FD_INO.fixed(pid << 20 | fd);
+        let inner = InodeInner::with_ino(None, ino, InodeMode::LINK, 0);
         Arc::new(Self {
             inner: inner,
             link_path: file_path.to_string()
@@ -109,7 +118,7 @@ impl Inode for FdChildInode {
     fn getattr(&self) -> crate::fs::Kstat {
         let inner = self.inode_inner();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
@@ -173,8 +182,8 @@ impl Inode for FdChildInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -186,6 +195,10 @@ impl Inode for FdChildInode {
         }
     }
 
+    fn is_magic_link(&self) -> bool {
+        true
+    }
+
     fn readlink(&self) -> Result<String, SysError> {
         Ok(self.link_path.clone())
     }
@@ -1,12 +1,122 @@
-use alloc::string::ToString;
+use alloc::{format, string::String, sync::Weak, vec::Vec};
+use hal::{addr::VirtPageNumHal, pagetable::MapPerm};
 
-use crate::fs::tmpfs::inode::InodeContent;
+use crate::{fs::tmpfs::inode::InodeContent, mm::vm::{MapFlags, UserVmArea, UserVmAreaType, UserVmFile}, task::{current_task, task::TaskControlBlock}};
 
+/// /proc/<pid>/maps: a line for each run of areas of the same mapping,
+/// `start-end perms offset dev inode pathname`
+pub struct Maps {
+    /// None for /proc/self/maps, which is about the task reading it
+    task: Option<Weak<TaskControlBlock>>,
+}
 
-pub struct Maps;
+impl Maps {
+    pub fn new(task: Option<Weak<TaskControlBlock>>) -> Self {
+        Self { task }
+    }
+}
+
+/// one line of the maps file
+struct MapsLine {
+    start: usize,
+    end: usize,
+    perm: MapPerm,
+    shared: bool,
+    offset: usize,
+    dev: (u32, u32),
+    ino: usize,
+    path: String,
+}
+
+impl MapsLine {
+    fn new(vma: &UserVmArea) -> Self {
+        let (dev, ino, path) = match &vma.file {
+            UserVmFile::File(file) => {
+                let path = file.dentry().map_or(String::new(), |dentry| dentry.path());
+                match file.inode() {
+                    Ok(inode) => (inode.inode_inner().dev(), inode.inode_inner().ino, path),
+                    Err(_) => ((0, 0), 0, path),
+                }
+            }
+            // the memory of a MAP_SHARED|MAP_ANONYMOUS mapping is no SysV segment
+            UserVmFile::Shm(shm) if shm.get_id() == 0 => ((0, 1), 0, String::from("/dev/zero (deleted)")),
+            UserVmFile::Shm(shm) => {
+                let key = shm.shmid_ds.lock().perm.key();
+                ((0, 1), shm.get_id(), format!("/SYSV{:08x} (deleted)", key))
+            }
+            UserVmFile::None => {
+                let path = match vma.vma_type {
+                    UserVmAreaType::Heap => "[heap]",
+                    UserVmAreaType::Stack => "[stack]",
+                    UserVmAreaType::Data | UserVmAreaType::Mmap => "[anon]",
+                };
+                ((0, 0), 0, String::from(path))
+            }
+        };
+        Self {
+            start: vma.range_vpn().start.start_addr().0,
+            end: vma.range_vpn().end.start_addr().0,
+            perm: vma.map_perm,
+            shared: vma.map_flags.contains(MapFlags::SHARED),
+            offset: if vma.file.is_none() { 0 } else { vma.offset },
+            dev,
+            ino,
+            path,
+        }
+    }
+
+    /// whether `next` goes on right after this line with the same attributes
+    fn continued_by(&self, next: &Self) -> bool {
+        self.end == next.start
+            && self.perm == next.perm
+            && self.shared == next.shared
+            && self.dev == next.dev
+            && self.ino == next.ino
+            && self.path == next.path
+            && (self.ino == 0 || self.offset + (self.end - self.start) == next.offset)
+    }
+}
+
+impl core::fmt::Display for MapsLine {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        /// the column the pathname starts at
+        const PATH_COLUMN: usize = 73;
+        let flag = |perm: MapPerm, c: char| if self.perm.contains(perm) { c } else { '-' };
+        let head = format!(
+            "{:08x}-{:08x} {}{}{}{} {:08x} {:02x}:{:02x} {} ",
+            self.start, self.end,
+            flag(MapPerm::R, 'r'), flag(MapPerm::W, 'w'), flag(MapPerm::X, 'x'),
+            if self.shared { 's' } else { 'p' },
+            self.offset, self.dev.0, self.dev.1, self.ino,
+        );
+        if self.path.is_empty() {
+            writeln!(f, "{}", head)
+        } else {
+            writeln!(f, "{:<width$}{}", head, self.path, width = PATH_COLUMN)
+        }
+    }
+}
 
 impl InodeContent for Maps {
-    fn serialize(&self) -> alloc::string::String {
-        "".to_string()
+    fn serialize(&self) -> String {
+        let task = match &self.task {
+            Some(task) => task.upgrade(),
+            None => current_task().cloned(),
+        };
+        let Some(task) = task else {
+            return String::new();
+        };
+        // the areas come in the order of their addresses
+        let lines = task.with_vm_space(|vm| {
+            let mut lines: Vec<MapsLine> = Vec::new();
+            for line in vm.areas().map(MapsLine::new) {
+                match lines.last_mut() {
+                    Some(last) if last.continued_by(&line) => last.end = line.end,
+                    _ => lines.push(line),
+                }
+            }
+            lines
+        });
+        lines.iter().map(|line| format!("{}", line)).collect()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,45 @@
+//! /proc/stat
+
+use alloc::{format, string::String};
+use core::time::Duration;
+
+use crate::{fs::tmpfs::inode::InodeContent, processor::processor::{get_processor, online_processors}, task::{manager::TASK_MANAGER, task::TaskStatus}, timer::duration_to_ticks};
+
+/// the time spent on the processors in clock ticks, a `cpu` line for all of them
+/// and a `cpuN` line for each, then the task counts
+pub struct Stat;
+
+impl Stat {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+/// `user nice system idle iowait irq softirq steal guest guest_nice`,
+/// only the time in user mode, in kernel mode and idle is tracked
+fn cpu_line(name: &str, (user, system, idle): (Duration, Duration, Duration)) -> String {
+    format!(
+        "{} {} 0 {} {} 0 0 0 0 0 0\n",
+        name, duration_to_ticks(user), duration_to_ticks(system), duration_to_ticks(idle)
+    )
+}
+
+impl InodeContent for Stat {
+    fn serialize(&self) -> String {
+        let mut total = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+        let mut lines = String::new();
+        for id in online_processors() {
+            let times = get_processor(id).cpu_times();
+            total.0 += times.0;
+            total.1 += times.1;
+            total.2 += times.2;
+            lines += &cpu_line(&format!("cpu{}", id), times);
+        }
+        let running = TASK_MANAGER.count_tasks(|task| matches!(
+            task.get_status(),
+            TaskStatus::Running | TaskStatus::Ready
+        ));
+        let blocked = TASK_MANAGER.count_tasks(|task| task.get_status() == TaskStatus::UnInterruptable);
+        cpu_line("cpu ", total) + &lines + &format!("procs_running {}\nprocs_blocked {}\n", running, blocked)
+    }
+}
@@ -2,9 +2,10 @@
 
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
+use spin::Lazy;
 
-use crate::fs::tmpfs::inode::InodeContent;
+use crate::{fs::tmpfs::inode::InodeContent, sync::mutex::SpinNoIrqLock};
 
 pub struct PidMax {
     pid_max: AtomicUsize,
@@ -45,7 +46,27 @@ impl InodeContent for Tainted {
     }
 }
 
+/// template used to name core dump files, see core(5)
+pub static CORE_PATTERN: Lazy<SpinNoIrqLock<String>> = Lazy::new(|| SpinNoIrqLock::new("core".to_string()));
 
+/// max length of core_pattern, same as linux
+const CORENAME_MAX_SIZE: usize = 128;
 
+pub struct CorePattern;
 
+impl CorePattern {
+    pub const fn new() -> Self { Self {} }
+}
 
+impl InodeContent for CorePattern {
+    fn serialize(&self) -> alloc::string::String {
+        CORE_PATTERN.lock().clone() + "\n"
+    }
+
+    fn deserialize(&self, buf: &[u8]) -> Result<usize, i32> {
+        let len = buf.len().min(CORENAME_MAX_SIZE - 1);
+        let pattern = String::from_utf8_lossy(&buf[..len]);
+        *CORE_PATTERN.lock() = pattern.trim_end_matches('\n').to_string();
+        Ok(buf.len())
+    }
+}
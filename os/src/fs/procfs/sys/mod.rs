@@ -1,3 +1,4 @@
 
 pub mod kernel;
-pub mod fs;
\ No newline at end of file
+pub mod fs;
+pub mod vm;
@@ -0,0 +1,49 @@
+//! contents of vm folder
+
+use alloc::string::{String, ToString};
+
+use crate::{fs::{page::lru, tmpfs::inode::InodeContent}, mm::vm::commit, syscall::SysError};
+
+/// the overcommit mode, see `mm::vm::commit`
+pub struct OvercommitMemory;
+
+impl OvercommitMemory {
+    pub const fn new() -> Self { Self {} }
+}
+
+impl InodeContent for OvercommitMemory {
+    fn serialize(&self) -> String {
+        commit::mode().to_string() + "\n"
+    }
+
+    fn deserialize(&self, buf: &[u8]) -> Result<usize, i32> {
+        let mode = core::str::from_utf8(buf)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or(SysError::EINVAL as i32)?;
+        commit::set_mode(mode).map_err(|e| e as i32)?;
+        Ok(buf.len())
+    }
+}
+
+/// the reclaimable page cache size in pages, see `fs::page::lru`
+pub struct PageCacheLimit;
+
+impl PageCacheLimit {
+    pub const fn new() -> Self { Self {} }
+}
+
+impl InodeContent for PageCacheLimit {
+    fn serialize(&self) -> String {
+        lru::limit().to_string() + "\n"
+    }
+
+    fn deserialize(&self, buf: &[u8]) -> Result<usize, i32> {
+        let pages = core::str::from_utf8(buf)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or(SysError::EINVAL as i32)?;
+        lru::set_limit(pages);
+        Ok(buf.len())
+    }
+}
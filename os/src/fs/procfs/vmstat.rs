@@ -0,0 +1,29 @@
+//! /proc/vmstat, only the TLB counters
+
+use core::sync::atomic::Ordering;
+
+use alloc::{format, string::String};
+use hal::instruction::TLB_FLUSH_STATS;
+
+use crate::{fs::tmpfs::inode::InodeContent, mm::vm::asid::ASID_ROLLOVERS};
+
+pub struct VmStat;
+
+impl VmStat {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl InodeContent for VmStat {
+    fn serialize(&self) -> String {
+        format!(
+            "nr_tlb_local_flush_all {}\nnr_tlb_local_flush_asid {}\nnr_tlb_local_flush_one {}\nnr_tlb_remote_flush {}\nnr_asid_rollover {}\n",
+            TLB_FLUSH_STATS.all.load(Ordering::Relaxed),
+            TLB_FLUSH_STATS.asid.load(Ordering::Relaxed),
+            TLB_FLUSH_STATS.addr.load(Ordering::Relaxed),
+            TLB_FLUSH_STATS.remote.load(Ordering::Relaxed),
+            ASID_ROLLOVERS.load(Ordering::Relaxed),
+        )
+    }
+}
@@ -0,0 +1,209 @@
+//! secretmem: the memory behind memfd_secret
+//!
+//! Every page of a secret inode is taken out of the kernel direct map as soon as it is
+//! allocated, so the only mapping left is the user one of the process that owns it.
+//! The invariant kept here is that the kernel never touches a secret page by its
+//! physical address: a stray access through the direct map faults in the kernel rather
+//! than leaking the contents. Paths that reach user memory through the direct map
+//! (`translate_uva_checked`, core dumps, fork) must check `is_secret_page` or the
+//! `MapFlags::SECRET` of the area and back off. The direct mapping is put back right
+//! before the frame returns to the allocator.
+//!
+//! For now the memory is scoped to the process that created it: only that process may
+//! map it and a forked child does not inherit the mapping.
+
+use alloc::{boxed::Box, collections::{btree_map::BTreeMap, btree_set::BTreeSet}, sync::Arc};
+use async_trait::async_trait;
+use hal::{addr::PhysPageNum, constant::{Constant, ConstantsHal}};
+
+use crate::{mm::{vm::{KernVmSpaceHal, MapFlags}, KVMSPACE}, sync::mutex::SpinNoIrqLock, syscall::{mm::MmapFlags, SysError}};
+
+use super::{page::page::Page, vfs::{inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, Kstat, OpenFlags};
+
+/// frames currently out of the kernel direct map
+static SECRET_PAGES: SpinNoIrqLock<BTreeSet<PhysPageNum>> = SpinNoIrqLock::new(BTreeSet::new());
+
+/// whether the frame belongs to secret memory and must not be touched by the kernel
+pub fn is_secret_page(ppn: PhysPageNum) -> bool {
+    SECRET_PAGES.lock().contains(&ppn)
+}
+
+pub struct SecretMemInode {
+    inner: InodeInner,
+    pages: SpinNoIrqLock<BTreeMap<usize, Arc<Page>>>,
+}
+
+impl SecretMemInode {
+    pub fn new() -> Arc<Self> {
+        let inner = InodeInner::new(
+            None,
+            InodeMode::FILE | InodeMode::OWNER_READ | InodeMode::OWNER_WRITE,
+            0,
+        );
+        Arc::new(Self { inner, pages: SpinNoIrqLock::new(BTreeMap::new()) })
+    }
+}
+
+impl Inode for SecretMemInode {
+    fn inode_inner(&self) -> &InodeInner {
+        &self.inner
+    }
+
+    /// allocate the page on first use and drop it from the direct map,
+    /// it is zeroed before that and never read by the kernel afterwards
+    fn read_page_at(self: Arc<Self>, offset: usize) -> Option<Arc<Page>> {
+        if offset % Constant::PAGE_SIZE != 0 || offset >= self.inner.size() {
+            return None;
+        }
+        let mut pages = self.pages.lock();
+        if let Some(page) = pages.get(&offset) {
+            return Some(page.clone());
+        }
+        let page = Page::new(offset);
+        KVMSPACE.lock().unmap_phys_page(page.ppn()).ok()?;
+        SECRET_PAGES.lock().insert(page.ppn());
+        pages.insert(offset, page.clone());
+        Some(page)
+    }
+
+    /// the size can only be set once, like on linux
+    fn truncate(&self, size: usize) -> Result<usize, SysError> {
+        if self.inner.size() != 0 {
+            return Err(SysError::EINVAL);
+        }
+        self.inner.set_size(size);
+        Ok(size)
+    }
+
+    fn getattr(&self) -> Kstat {
+        let inner = self.inode_inner();
+        Kstat {
+            st_dev: inner.st_dev(),
+            st_ino: inner.ino as u64,
+            st_mode: inner.mode().bits() as _,
+            st_nlink: inner.nlink() as u32,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            _pad0: 0,
+            st_size: inner.size() as _,
+            _pad1: 0,
+            st_blksize: Constant::PAGE_SIZE as _,
+            st_blocks: 0,
+            st_atime_sec: inner.atime().tv_sec as _,
+            st_atime_nsec: inner.atime().tv_nsec as _,
+            st_mtime_sec: inner.mtime().tv_sec as _,
+            st_mtime_nsec: inner.mtime().tv_nsec as _,
+            st_ctime_sec: inner.ctime().tv_sec as _,
+            st_ctime_nsec: inner.ctime().tv_nsec as _,
+        }
+    }
+}
+
+impl Drop for SecretMemInode {
+    fn drop(&mut self) {
+        // mappings hold the file, so no user mapping is left by now;
+        // the frames may still be on their way out, map them back before they are freed
+        let pages = self.pages.lock();
+        let mut secret = SECRET_PAGES.lock();
+        let mut kvm = KVMSPACE.lock();
+        for page in pages.values() {
+            secret.remove(&page.ppn());
+            kvm.remap_phys_page(page.ppn());
+        }
+    }
+}
+
+pub struct SecretMemFile {
+    inode: Arc<SecretMemInode>,
+    /// the process allowed to map the memory
+    owner: usize,
+    inner: FileInner,
+}
+
+impl SecretMemFile {
+    fn new(dentry: Arc<dyn Dentry>, inode: Arc<SecretMemInode>, flags: OpenFlags, owner: usize) -> Arc<Self> {
+        let inner = FileInner {
+            offset: 0.into(),
+            dentry,
+            flags: SpinNoIrqLock::new(flags),
+        };
+        Arc::new(Self { inode, owner, inner })
+    }
+
+    /// secret memory only takes shared mappings from its owner
+    pub fn check_mmap(&self, pid: usize, flags: MmapFlags) -> Result<(), SysError> {
+        if !MapFlags::from(flags).contains(MapFlags::SHARED) {
+            return Err(SysError::EINVAL);
+        }
+        if pid != self.owner {
+            return Err(SysError::EPERM);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl File for SecretMemFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn inode(&self) -> Result<Arc<dyn Inode>, SysError> {
+        Ok(self.inode.clone())
+    }
+
+    /// the contents are only reachable through the mapping
+    async fn read(&self, _buf: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+}
+
+pub struct SecretMemDentry {
+    inner: DentryInner,
+}
+
+impl SecretMemDentry {
+    pub fn new() -> Arc<Self> {
+        let inner = DentryInner::new("[secretmem]", None);
+        Arc::new(Self { inner })
+    }
+}
+
+unsafe impl Sync for SecretMemDentry {}
+unsafe impl Send for SecretMemDentry {}
+
+impl Dentry for SecretMemDentry {
+    fn dentry_inner(&self) -> &DentryInner {
+        &self.inner
+    }
+
+    fn new(
+        &self,
+        _name: &str,
+        _parent: Option<Arc<dyn Dentry>>,
+    ) -> Arc<dyn Dentry> {
+        panic!("cannot create a secretmem in this way");
+    }
+}
+
+/// global function to create an empty secret memory file owned by `owner`,
+/// its size is set by a later ftruncate
+pub fn make_secretmem(flags: OpenFlags, owner: usize) -> Arc<dyn File> {
+    let inode = SecretMemInode::new();
+    let dentry = SecretMemDentry::new();
+    dentry.set_inode(inode.clone());
+    SecretMemFile::new(dentry, inode, OpenFlags::O_RDWR | flags, owner)
+}
@@ -0,0 +1,178 @@
+//! signalfd: a file that pending signals are read from
+
+use core::{future::Future, pin::Pin, task::{Context, Poll}};
+
+use alloc::sync::{Arc, Weak};
+use alloc::boxed::Box;
+use async_trait::async_trait;
+
+use crate::{signal::{SigSet, SignalFdSigInfo}, sync::mutex::SpinNoIrqLock, syscall::SysError, task::task::TaskControlBlock, utils::get_waker};
+
+use super::{vfs::{file::PollEvents, File, FileInner}, OpenFlags};
+
+bitflags! {
+    /// flags for signalfd4
+    pub struct SignalFdFlags: u32 {
+        /// same as O_NONBLOCK
+        const SFD_NONBLOCK = 0o4000;
+        /// same as O_CLOEXEC
+        const SFD_CLOEXEC = 0o2000000;
+    }
+}
+
+impl From<SignalFdFlags> for OpenFlags {
+    fn from(value: SignalFdFlags) -> Self {
+        let mut flags = OpenFlags::empty();
+        if value.contains(SignalFdFlags::SFD_NONBLOCK) {
+            flags |= OpenFlags::O_NONBLOCK;
+        }
+        if value.contains(SignalFdFlags::SFD_CLOEXEC) {
+            flags |= OpenFlags::O_CLOEXEC;
+        }
+        flags
+    }
+}
+
+/// wait until a signal in the mask is pending
+pub struct SignalFdReadFuture<'a> {
+    file: &'a SignalFdFile,
+}
+
+impl Future for SignalFdReadFuture<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Some(task) = self.file.task.upgrade() else {
+            return Poll::Ready(());
+        };
+        let mask = self.file.mask();
+        let mut manager = task.sig_manager.lock();
+        if manager.check_pending_flag(mask) {
+            Poll::Ready(())
+        } else {
+            manager.signalfd_wakers.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+pub struct SignalFdFile {
+    file_inner: FileInner,
+    /// the task whose pending signals are read
+    task: Weak<TaskControlBlock>,
+    mask: SpinNoIrqLock<SigSet>,
+}
+
+impl SignalFdFile {
+    pub fn new(file_inner: FileInner, task: &Arc<TaskControlBlock>, mask: SigSet) -> Arc<Self> {
+        let mask = Self::valid_mask(mask);
+        task.with_mut_sig_manager(|m| m.attach_signalfd(mask));
+        Arc::new(Self {
+            file_inner,
+            task: Arc::downgrade(task),
+            mask: SpinNoIrqLock::new(mask),
+        })
+    }
+
+    /// SIGKILL and SIGSTOP cannot be read through a signalfd
+    fn valid_mask(mut mask: SigSet) -> SigSet {
+        mask.remove(SigSet::SIGKILL | SigSet::SIGSTOP);
+        mask
+    }
+
+    pub fn mask(&self) -> SigSet {
+        *self.mask.lock()
+    }
+
+    /// replace the set of signals read through this signalfd
+    pub fn set_mask(&self, mask: SigSet) {
+        let mask = Self::valid_mask(mask);
+        let mut old = self.mask.lock();
+        if let Some(task) = self.task.upgrade() {
+            task.with_mut_sig_manager(|m| {
+                m.detach_signalfd(*old);
+                m.attach_signalfd(mask);
+            });
+        }
+        *old = mask;
+    }
+}
+
+#[async_trait]
+impl File for SignalFdFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.file_inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
+        const RECORD_SIZE: usize = core::mem::size_of::<SignalFdSigInfo>();
+        if buf.len() < RECORD_SIZE {
+            return Err(SysError::EINVAL);
+        }
+        let task = self.task.upgrade().ok_or(SysError::EBADF)?;
+        loop {
+            let mask = self.mask();
+            let mut len = 0;
+            task.with_mut_sig_manager(|m| {
+                while len + RECORD_SIZE <= buf.len() && m.check_pending_flag(mask) {
+                    let Some(sig) = m.dequeue_expected_one(mask) else {
+                        break;
+                    };
+                    let info = SignalFdSigInfo::from(sig);
+                    let bytes = unsafe {
+                        core::slice::from_raw_parts(&info as *const _ as *const u8, RECORD_SIZE)
+                    };
+                    buf[len..len + RECORD_SIZE].copy_from_slice(bytes);
+                    len += RECORD_SIZE;
+                }
+            });
+            if len > 0 {
+                return Ok(len);
+            }
+            if self.flags().contains(OpenFlags::O_NONBLOCK) {
+                return Err(SysError::EAGAIN);
+            }
+            SignalFdReadFuture { file: self }.await;
+        }
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut res = PollEvents::empty();
+        let Some(task) = self.task.upgrade() else {
+            return res;
+        };
+        if events.contains(PollEvents::IN) {
+            let mask = self.mask();
+            let mut manager = task.sig_manager.lock();
+            if manager.check_pending_flag(mask) {
+                res |= PollEvents::IN;
+            } else {
+                manager.signalfd_wakers.push_back(waker);
+            }
+        }
+        res
+    }
+}
+
+impl Drop for SignalFdFile {
+    fn drop(&mut self) {
+        // the signals go back to the normal delivery
+        if let Some(task) = self.task.upgrade() {
+            let mask = self.mask();
+            task.with_mut_sig_manager(|m| m.detach_signalfd(mask));
+        }
+    }
+}
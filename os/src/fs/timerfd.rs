@@ -0,0 +1,235 @@
+//! timerfd: a file that counts the expirations of a timer in TIMER_MANAGER
+
+use core::{future::Future, pin::Pin, task::{Context, Poll, Waker}, time::Duration};
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::boxed::Box;
+use async_trait::async_trait;
+
+use crate::{sync::mutex::SpinNoIrqLock, syscall::SysError, timer::{clock::{realtime_offset, CLOCK_REALTIME}, get_current_time_duration, timer::{ITimerSpec, Timer, TimerEvent, TimerId, TIMER_MANAGER}}, utils::get_waker};
+
+use super::{vfs::{file::PollEvents, File, FileInner}, OpenFlags};
+
+bitflags! {
+    /// flags for timerfd_create
+    pub struct TimerFdFlags: u32 {
+        /// same as O_NONBLOCK
+        const TFD_NONBLOCK = 0o4000;
+        /// same as O_CLOEXEC
+        const TFD_CLOEXEC = 0o2000000;
+    }
+}
+
+bitflags! {
+    /// flags for timerfd_settime
+    pub struct TimerFdSetFlags: u32 {
+        /// `it_value` is an absolute time of the clock
+        const TFD_TIMER_ABSTIME = 1;
+        /// not supported, accepted and ignored
+        const TFD_TIMER_CANCEL_ON_SET = 2;
+    }
+}
+
+impl From<TimerFdFlags> for OpenFlags {
+    fn from(value: TimerFdFlags) -> Self {
+        let mut flags = OpenFlags::empty();
+        if value.contains(TimerFdFlags::TFD_NONBLOCK) {
+            flags |= OpenFlags::O_NONBLOCK;
+        }
+        if value.contains(TimerFdFlags::TFD_CLOEXEC) {
+            flags |= OpenFlags::O_CLOEXEC;
+        }
+        flags
+    }
+}
+
+/// the state shared by the file and the timer events
+pub struct TimerFd {
+    /// the clock `it_value` is measured with when armed with TFD_TIMER_ABSTIME
+    clockid: usize,
+    interval: Duration,
+    /// next expiration on the monotonic clock, zero if disarmed
+    next_expire: Duration,
+    /// bumped on every settime, so that events of the old setting are dropped
+    interval_id: TimerId,
+    expirations: u64,
+    read_waker: VecDeque<Waker>,
+}
+
+impl TimerFd {
+    fn new(clockid: usize) -> Self {
+        Self {
+            clockid,
+            interval: Duration::ZERO,
+            next_expire: Duration::ZERO,
+            interval_id: 0,
+            expirations: 0,
+            read_waker: VecDeque::new(),
+        }
+    }
+
+    fn current(&self) -> ITimerSpec {
+        ITimerSpec {
+            it_interval: self.interval.into(),
+            it_value: self.next_expire.saturating_sub(get_current_time_duration()).into(),
+        }
+    }
+}
+
+/// the event registered in TIMER_MANAGER for an armed timerfd
+pub struct TimerFdEvent {
+    timer: Arc<SpinNoIrqLock<TimerFd>>,
+    interval_id: TimerId,
+}
+
+impl TimerEvent for TimerFdEvent {
+    fn callback(self: Box<Self>) -> Option<Timer> {
+        let mut inner = self.timer.lock();
+        if inner.interval_id != self.interval_id || inner.next_expire.is_zero() {
+            return None;
+        }
+        let now = get_current_time_duration();
+        // count the periods missed since the last expiration
+        let count = if inner.interval > Duration::ZERO {
+            let late = now.saturating_sub(inner.next_expire);
+            (late.as_nanos() / inner.interval.as_nanos()) as u64 + 1
+        } else {
+            1
+        };
+        inner.expirations += count;
+        while let Some(waker) = inner.read_waker.pop_front() {
+            waker.wake();
+        }
+        if inner.interval > Duration::ZERO {
+            let next_expire = inner.next_expire + inner.interval * count as u32;
+            inner.next_expire = next_expire;
+            drop(inner);
+            Some(Timer::new(next_expire, self))
+        } else {
+            inner.next_expire = Duration::ZERO;
+            None
+        }
+    }
+}
+
+/// wait until the timer expires at least once
+pub struct TimerFdReadFuture {
+    timer: Arc<SpinNoIrqLock<TimerFd>>,
+}
+
+impl Future for TimerFdReadFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.timer.lock();
+        if inner.expirations > 0 {
+            Poll::Ready(())
+        } else {
+            inner.read_waker.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+pub struct TimerFdFile {
+    file_inner: FileInner,
+    timer: Arc<SpinNoIrqLock<TimerFd>>,
+}
+
+impl TimerFdFile {
+    pub fn new(file_inner: FileInner, clockid: usize) -> Arc<Self> {
+        Arc::new(Self {
+            file_inner,
+            timer: Arc::new(SpinNoIrqLock::new(TimerFd::new(clockid))),
+        })
+    }
+
+    /// arm or disarm the timer, return the old setting
+    pub fn settime(&self, flags: TimerFdSetFlags, new_value: &ITimerSpec) -> ITimerSpec {
+        let mut inner = self.timer.lock();
+        let old_value = inner.current();
+        inner.interval_id = inner.interval_id.wrapping_add(1);
+        inner.interval = new_value.it_interval.into();
+        inner.expirations = 0;
+        let value: Duration = new_value.it_value.into();
+        if value.is_zero() {
+            inner.next_expire = Duration::ZERO;
+            return old_value;
+        }
+        let now = get_current_time_duration();
+        let next_expire = if flags.contains(TimerFdSetFlags::TFD_TIMER_ABSTIME) {
+            // convert the time of the clock to the monotonic time the manager uses,
+            // a deadline in the past fires at once
+            let deviation = if inner.clockid == CLOCK_REALTIME { realtime_offset() } else { Duration::ZERO };
+            value.checked_sub(deviation).filter(|t| *t > now).unwrap_or(now)
+        } else {
+            now + value
+        };
+        inner.next_expire = next_expire;
+        let event = TimerFdEvent {
+            timer: self.timer.clone(),
+            interval_id: inner.interval_id,
+        };
+        drop(inner);
+        TIMER_MANAGER.add_timer(Timer::new(next_expire, Box::new(event)));
+        old_value
+    }
+
+    /// get the time until the next expiration and the interval
+    pub fn gettime(&self) -> ITimerSpec {
+        self.timer.lock().current()
+    }
+}
+
+#[async_trait]
+impl File for TimerFdFile {
+    fn file_inner(&self) -> &FileInner {
+        &self.file_inner
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
+        if buf.len() < 8 {
+            return Err(SysError::EINVAL);
+        }
+        loop {
+            {
+                let mut inner = self.timer.lock();
+                if inner.expirations > 0 {
+                    let expirations = core::mem::take(&mut inner.expirations);
+                    buf[..8].copy_from_slice(&expirations.to_ne_bytes());
+                    return Ok(8);
+                }
+            }
+            if self.flags().contains(OpenFlags::O_NONBLOCK) {
+                return Err(SysError::EAGAIN);
+            }
+            TimerFdReadFuture { timer: self.timer.clone() }.await;
+        }
+    }
+
+    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::EINVAL)
+    }
+
+    async fn base_poll(&self, events: PollEvents) -> PollEvents {
+        let waker = get_waker().await;
+        let mut inner = self.timer.lock();
+        let mut res = PollEvents::empty();
+        if events.contains(PollEvents::IN) {
+            if inner.expirations > 0 {
+                res |= PollEvents::IN;
+            } else {
+                inner.read_waker.push_back(waker);
+            }
+        }
+        res
+    }
+}
@@ -4,7 +4,7 @@ use alloc::sync::Arc;
 use async_trait::async_trait;
 use alloc::boxed::Box;
 
-use crate::{fs::{vfs::{file::SeekFrom, Dentry, File, FileInner}, OpenFlags}, sync::{mutex::SpinNoIrqLock, UPSafeCell}, syscall::SysError};
+use crate::{fs::{inotify::{notify_dentry, InotifyMask}, vfs::{file::SeekFrom, Dentry, File, FileInner}, OpenFlags}, sync::{mutex::SpinNoIrqLock, UPSafeCell}, syscall::SysError};
 
 
 pub struct TmpFile {
@@ -50,7 +50,9 @@ impl File for TmpFile {
     }
     async fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, SysError> {
         let inode = self.dentry().unwrap().inode().unwrap();
-        inode.access()?;
+        if !self.flags().contains(OpenFlags::O_NOATIME) {
+            inode.access()?;
+        }
         let size = if inode.cache().is_some() {
             inode.cache_read_at(offset, buf).unwrap()
         } else {
@@ -64,13 +66,17 @@ impl File for TmpFile {
         let size = if inode.cache().is_some() {
             inode.cache_write_at(offset, buf).unwrap()
         } else {
-            inode.write_at(offset, buf).unwrap()
+            // special files may refuse what is written
+            inode.write_at(offset, buf).map_err(SysError::from_i32)?
         };
+        notify_dentry(&self.dentry().unwrap(), InotifyMask::IN_MODIFY);
         Ok(size)
     }
     async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
         let inode = self.dentry().unwrap().inode().unwrap();
-        inode.access()?;
+        if !self.flags().contains(OpenFlags::O_NOATIME) {
+            inode.access()?;
+        }
         log::info!("[Tmp file] read start from pos {}", self.pos());
         let size = if inode.cache().is_some() {
             inode.cache_read_at(self.pos(), buf).unwrap()
@@ -82,13 +88,16 @@ impl File for TmpFile {
         Ok(size)
     }
     async fn write(&self, buf: &[u8]) -> Result<usize, SysError> {
-        if self.flags().contains(OpenFlags::O_APPEND) {
-            self.set_pos(self.size());
-        }
-        let pos = self.pos();
         log::debug!("[Tmp file] writing {}, state: {:?}", self.dentry().unwrap().path(), self.dentry().unwrap().state());
         let inode = self.dentry().unwrap().inode().unwrap();
         inode.modified()?;
+        if self.flags().contains(OpenFlags::O_APPEND) {
+            let (pos, size) = inode.append(buf)?;
+            self.set_pos(pos + size);
+            notify_dentry(&self.dentry().unwrap(), InotifyMask::IN_MODIFY);
+            return Ok(size);
+        }
+        let pos = self.pos();
         let size = if inode.cache().is_some() {
             inode.cache_write_at(pos, buf).unwrap()
         } else {
@@ -96,6 +105,7 @@ impl File for TmpFile {
         };
         log::debug!("[Tmp file] set pos at {}", pos + size);
         self.set_pos(pos + size);
+        notify_dentry(&self.dentry().unwrap(), InotifyMask::IN_MODIFY);
         Ok(size)
     }
 }
\ No newline at end of file
@@ -22,6 +22,10 @@ impl FSType for TmpFSType {
         &self.inner
     }
 
+    fn magic(&self) -> u64 {
+        0x0102_1994
+    }
+
     fn mount(&'static self, name: &str, parent: Option<Arc<dyn Dentry>>, _flags: MountFlags, dev: Option<Arc<dyn BlockDevice>>) -> Option<Arc<dyn Dentry>> {
         let fs_type = unsafe {
             let ptr: *const dyn FSType = self;
@@ -4,7 +4,7 @@ use core::cmp;
 
 use alloc::{string::{String, ToString}, sync::{Arc, Weak}};
 
-use crate::{config::{BLOCK_SIZE, PAGE_SIZE}, fs::{page::{cache::PageCache, page::Page}, vfs::{inode::InodeMode, Inode, InodeInner}, Kstat, StatxTimestamp, SuperBlock, Xstat, XstatMask}, sync::mutex::SpinNoIrqLock, syscall::SysError};
+use crate::{config::{BLOCK_SIZE, PAGE_SIZE}, fs::{page::{cache::PageCache, page::Page}, vfs::{inode::{decode_dev, InodeMode}, Inode, InodeInner}, FallocFlags, Kstat, StatxTimestamp, SuperBlock, Xstat, XstatMask}, sync::mutex::SpinNoIrqLock, syscall::SysError};
 
 pub struct TmpInode {
     inner: InodeInner,
@@ -137,43 +137,54 @@ impl Inode for TmpInode {
         Ok(0)
     }
 
+    fn rename(&self, _target: &str, _new_inode: Option<Arc<dyn Inode>>) -> Result<(), SysError> {
+        // do nothing
+        // the dentry tree is all there is, the dentries are moved by the caller
+        Ok(())
+    }
+
     fn truncate(&self, size: usize) -> Result<usize, SysError> {
-        let old_size = self.inner.size();
-        if size > old_size {
-            // expand the page cache
-            let page_cache = self.cache.clone();
-            let offset_aligned_start = old_size / PAGE_SIZE * PAGE_SIZE;
-            for offset_aligned in (offset_aligned_start..size).step_by(PAGE_SIZE) {
-                let page = Page::new(offset_aligned);
-                page_cache.insert_page(offset_aligned, page.clone());
+        // an expand leaves a hole, which reads zero
+        self.cache.truncate(size);
+        self.inner.set_size(size);
+        Ok(size)
+    }
+
+    fn fallocate(self: Arc<Self>, mode: FallocFlags, offset: usize, len: usize) -> Result<(), SysError> {
+        let end = offset + len;
+        let size = self.inner.size();
+        if mode.contains(FallocFlags::FALLOC_FL_PUNCH_HOLE) {
+            self.cache.zero_range(offset, cmp::min(end, size), true);
+            return Ok(());
+        }
+        for page_offset in (offset / PAGE_SIZE * PAGE_SIZE..end).step_by(PAGE_SIZE) {
+            if self.cache.get_page(page_offset).is_none() {
+                self.cache.insert_page(page_offset, Page::new(page_offset));
             }
-            self.inner.set_size(size);
-            Ok(size)
-        } else if old_size == size {
-            return Ok(size)
-        } else {
-            log::warn!("not support reduce size for tmp file");
-            self.inner.set_size(size);
-            return Ok(size)
         }
+        if !mode.contains(FallocFlags::FALLOC_FL_KEEP_SIZE) && end > size {
+            self.cache.update_end(end);
+            self.inner.set_size(end);
+        }
+        Ok(())
     }
 
     fn getattr(&self) -> Kstat {
         let inner = self.inode_inner();
         let size = inner.size();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
             st_uid: inner.uid(),
             st_gid: inner.gid(),
-            st_rdev: 0,
+            st_rdev: inner.rdev(),
             _pad0: 0,
             st_size: size as _,
             _pad1: 0,
             st_blksize: BLOCK_SIZE as _,
-            st_blocks: (size / BLOCK_SIZE) as _,
+            st_blocks: (self.cache.allocated() / BLOCK_SIZE) as _,
             st_atime_sec: inner.atime().tv_sec as _,
             st_atime_nsec: inner.atime().tv_nsec as _,
             st_mtime_sec: inner.mtime().tv_sec as _,
@@ -207,7 +218,7 @@ impl Inode for TmpInode {
             stx_mode: inner.mode().bits() as _,
             stx_ino: inner.ino as u64,
             stx_size: size as _,
-            stx_blocks: (size / BLOCK_SIZE) as _,
+            stx_blocks: (self.cache.allocated() / BLOCK_SIZE) as _,
             stx_attributes_mask: 0,
             stx_atime: StatxTimestamp {
                 tv_sec: inner.atime().tv_sec as _,
@@ -225,10 +236,10 @@ impl Inode for TmpInode {
                 tv_sec: inner.mtime().tv_sec as _,
                 tv_nsec: inner.mtime().tv_nsec as _,
             },
-            stx_rdev_major: 0,
-            stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_rdev_major: decode_dev(inner.rdev()).0,
+            stx_rdev_minor: decode_dev(inner.rdev()).1,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
@@ -263,35 +274,56 @@ impl Inode for TmpInode {
 
 pub trait InodeContent {
     fn serialize(&self) -> String;
+    /// update the content with what user writes, read only by default
+    fn deserialize(&self, _buf: &[u8]) -> Result<usize, i32> {
+        Ok(0)
+    }
+    /// uid and gid of the file when they follow someone else (like a process),
+    /// root by default
+    fn owner(&self) -> Option<(u32, u32)> {
+        None
+    }
 }
 
-/// special system file: read only
+/// special system file: read only unless the content accepts writes
 pub struct TmpSysInode {
     inner: InodeInner,
     content: Arc<dyn InodeContent>,
+    /// the content as the read from offset 0 found it, what the reads further on go through,
+    /// so that a file read in pieces is all of one moment
+    snapshot: SpinNoIrqLock<Option<String>>,
 }
 
 unsafe impl Send for TmpSysInode {}
 unsafe impl Sync for TmpSysInode {}
 
 impl TmpSysInode {
-    pub fn new(mode: InodeMode, content: Arc<dyn InodeContent>) -> Arc<Self> {
+    pub fn new(super_block: Option<Weak<dyn SuperBlock>>, mode: InodeMode, content: Arc<dyn InodeContent>) -> Arc<Self> {
         let inner = InodeInner::new(
-            None, 
+            super_block, 
             mode, 
             content.serialize().len()
         );
-        Arc::new(Self { inner, content })
+        Arc::new(Self { inner, content, snapshot: SpinNoIrqLock::new(None) })
     }
 }
 
 impl Inode for TmpSysInode {
     fn inode_inner(&self) -> &InodeInner {
+        // the owner is looked at again each time, permission checks go through here
+        if let Some((uid, gid)) = self.content.owner() {
+            self.inner.set_uid(uid);
+            self.inner.set_gid(gid);
+        }
         &self.inner
     }
 
     fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, i32> {
-        let content = self.content.serialize();
+        let mut snapshot = self.snapshot.lock();
+        if offset == 0 || snapshot.is_none() {
+            *snapshot = Some(self.content.serialize());
+        }
+        let content = snapshot.as_ref().unwrap();
         let size = content.len();
         self.inner.set_size(size);
         if offset > size {
@@ -302,8 +334,8 @@ impl Inode for TmpSysInode {
         Ok(read_size)
     }
 
-    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, i32> {
-        Ok(0)
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, i32> {
+        self.content.deserialize(buf)
     }
 
     fn getattr(&self) -> Kstat {
@@ -311,12 +343,12 @@ impl Inode for TmpSysInode {
         self.inode_inner().set_size(size);
         let inner = self.inode_inner();
         Kstat {
-            st_dev: 0,
+            st_dev: inner.st_dev(),
             st_ino: inner.ino as u64,
             st_mode: inner.mode().bits() as _,
             st_nlink: inner.nlink() as u32,
-            st_uid: 0,
-            st_gid: 0,
+            st_uid: inner.uid(),
+            st_gid: inner.gid(),
             st_rdev: 0,
             _pad0: 0,
             st_size: inner.size() as _,
@@ -353,8 +385,8 @@ impl Inode for TmpSysInode {
             stx_blksize: 0,
             stx_attributes: 0,
             stx_nlink: inner.nlink() as u32,
-            stx_uid: 0,
-            stx_gid: 0,
+            stx_uid: inner.uid(),
+            stx_gid: inner.gid(),
             stx_mode: inner.mode().bits() as _,
             stx_ino: inner.ino as u64,
             stx_size: inner.size() as _,
@@ -378,8 +410,8 @@ impl Inode for TmpSysInode {
             },
             stx_rdev_major: 0,
             stx_rdev_minor: 0,
-            stx_dev_major: 0,
-            stx_dev_minor: 0,
+            stx_dev_major: inner.dev().0,
+            stx_dev_minor: inner.dev().1,
             stx_mnt_id: 0,
             stx_dio_mem_align: 0,
             std_dio_offset_align: 0,
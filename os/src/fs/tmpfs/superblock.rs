@@ -2,7 +2,7 @@
 
 use alloc::sync::Arc;
 
-use crate::{devices::BlockDevice, fs::{vfs::Inode, SuperBlock, SuperBlockInner}};
+use crate::{config::PAGE_SIZE, devices::BlockDevice, fs::{vfs::Inode, StatFs, SuperBlock, SuperBlockInner}, mm::allocator::{free_frames, total_frames}};
 
 pub struct TmpSuperBlock {
     inner: SuperBlockInner,
@@ -21,4 +21,16 @@ impl SuperBlock for TmpSuperBlock {
     fn get_root_inode(&'static self, _name: &str) -> Arc<dyn Inode> {
         self.inner().root.get().unwrap().clone().inode().unwrap()
     }
+    fn statfs(&self) -> StatFs {
+        mem_statfs(&self.inner)
+    }
+}
+
+/// usage of a file system living in memory: half the memory at most,
+/// the default size of tmpfs, and an inode for each of its pages
+pub fn mem_statfs(inner: &SuperBlockInner) -> StatFs {
+    let blocks = total_frames() / 2;
+    let bfree = free_frames().min(blocks);
+    let used_files = inner.ino_allocator.allocated().min(blocks);
+    inner.statfs(PAGE_SIZE, blocks as u64, bfree as u64, blocks as u64, (blocks - used_files) as u64)
 }
\ No newline at end of file
@@ -2,12 +2,12 @@
 
 use core::{default, mem::MaybeUninit};
 
-use crate::{fs::{vfs::{dentry, inode::InodeMode}, AtFlags, OpenFlags}, sync::mutex::SpinNoIrqLock, syscall::{at_helper1, SysError}, task::task::TaskControlBlock, utils::rel_path_to_abs};
+use crate::{fs::{mount::resolve_old_root, vfs::{dentry, inode::InodeMode}, AtFlags, OpenFlags, ResolveFlags}, sync::mutex::SpinNoIrqLock, syscall::{at_helper1, SysError}, task::{current_task, task::TaskControlBlock}, utils::rel_path_to_abs};
 
 use super::{superblock, File, Inode, SuperBlock};
 
 use alloc::{
-    collections::btree_map::BTreeMap, format, string::{String, ToString}, sync::{Arc, Weak}, vec::Vec
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque}, format, string::{String, ToString}, sync::{Arc, Weak}, vec::Vec
 };
 use log::{info, warn};
 
@@ -93,10 +93,16 @@ pub trait Dentry: Send + Sync {
     }
     /// add a child
     fn add_child(&self, child: Arc<dyn Dentry>) {
+        // the name exists from now on
+        let path = child.path();
+        NEG_DCACHE.lock().invalidate(&path);
+        PATH_CACHE.lock().invalidate(&path);
         self.dentry_inner().children.lock().insert(child.name().to_string(), child);
     }
     /// remove a child
     fn remove_child(&self, name: &str) {
+        let path = format!("{}/{}", self.path().trim_end_matches('/'), name);
+        PATH_CACHE.lock().invalidate(&path);
         self.dentry_inner().children.lock().remove(name);
     }
     /// tider way to get name
@@ -144,6 +150,9 @@ pub trait Dentry: Send + Sync {
     }
 }
 
+/// max number of symlinks followed when resolving a path, same as linux
+const MAX_LINK_DEPTH: usize = 40;
+
 impl dyn Dentry {
     
     /// find the dentry by given path
@@ -207,6 +216,14 @@ impl dyn Dentry {
     /// if find, should return a USED dentry
     /// if not find, should return a NEGATIVE dentry
     pub fn walk(self: Arc<Self>, path: &str) -> Result<Arc<dyn Dentry>, SysError> {
+        let mut links = 0;
+        self.walk_inner(path, &mut links)
+    }
+
+    /// walk counting the symlinks followed so far,
+    /// symlinks in the middle of the path are always followed,
+    /// the last component is left to the caller (see follow)
+    fn walk_inner(self: Arc<Self>, path: &str, links: &mut usize) -> Result<Arc<dyn Dentry>, SysError> {
         let mut current_dentry = self.clone();
         // break down the path: string a/b/c -> vec [a, b, c]
         let name_vec: Vec<&str> = path
@@ -216,12 +233,36 @@ impl dyn Dentry {
         // use the vec to walk, loop
         // if the element exist, keeping walking
         // if not exist, stop.
-        for name in name_vec.iter() {
+        for (i, name) in name_vec.iter().enumerate() {
+            while i > 0 && current_dentry.is_link() {
+                *links += 1;
+                if *links > MAX_LINK_DEPTH {
+                    return Err(SysError::ELOOP);
+                }
+                // an absolute target starts from the root of the task
+                let target = match current_task() {
+                    Some(task) => task.link_path(&current_dentry)?,
+                    None => current_dentry.link_target()?,
+                };
+                let root_dentry = {
+                    let dcache = DCACHE.lock();
+                    Arc::clone(dcache.get("/").unwrap())
+                };
+                current_dentry = root_dentry.walk_inner(&target, links)?;
+                if current_dentry.state() == DentryState::NEGATIVE {
+                    return Err(SysError::ENOENT);
+                }
+            }
             if let Some(child_dentry) = current_dentry.get_child(name) {
                 // first look into self children field
                 // if find, just keep walking
                 current_dentry = child_dentry;
             } else {
+                // a recent lookup already found that the name does not exist
+                let child_path = format!("{}/{}", current_dentry.path().trim_end_matches('/'), name);
+                if let Some(neg_dentry) = NEG_DCACHE.lock().get(&child_path, |d| d.is_negative()) {
+                    return Ok(neg_dentry);
+                }
                 // not found, try to update the children
                 current_dentry.clone().load_child_dentry()?;
                 if let Some(child_dentry) = current_dentry.get_child(name) {
@@ -240,7 +281,7 @@ impl dyn Dentry {
                     // log::info!("current dentry path {}", current_dentry.path());
                     let neg_dentry = current_dentry.new_neg_dentry(name)?;
                     // info!("[DCACHE]: insert key: {}", neg_dentry.path());
-                    DCACHE.lock().insert(neg_dentry.path(), neg_dentry.clone());
+                    NEG_DCACHE.lock().insert(child_path, neg_dentry.clone());
                     return Ok(neg_dentry);
                 }
             }
@@ -249,10 +290,197 @@ impl dyn Dentry {
         return Ok(current_dentry.clone());
     }
 
+    /// walk the path as openat2 does, starting from this directory,
+    /// symlinks in the middle of the path are followed under the constraints of `resolve`,
+    /// the last one only if `follow_last`.
+    /// with RESOLVE_BENEATH or RESOLVE_IN_ROOT, this directory is the root of the walk,
+    /// else the root of the current task
+    pub fn resolve(self: Arc<Self>, path: &str, resolve: ResolveFlags, follow_last: bool) -> Result<Arc<dyn Dentry>, SysError> {
+        let root = if resolve.intersects(ResolveFlags::RESOLVE_BENEATH | ResolveFlags::RESOLVE_IN_ROOT) {
+            self.clone()
+        } else if let Some(task) = current_task() {
+            task.root()
+        } else {
+            let dcache = DCACHE.lock();
+            Arc::clone(dcache.get("/").unwrap())
+        };
+        let mut current = if path.starts_with('/') {
+            if resolve.contains(ResolveFlags::RESOLVE_BENEATH) {
+                return Err(SysError::EXDEV);
+            }
+            root.clone()
+        } else {
+            self.clone()
+        };
+        let mut components: VecDeque<String> = path
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .map(|s| s.to_string())
+            .collect();
+        let mut links = 0;
+        while let Some(name) = components.pop_front() {
+            if current.inode().map_or(true, |inode| inode.inode_type() != InodeMode::DIR) {
+                return Err(SysError::ENOTDIR);
+            }
+            if name == ".." {
+                if current.same_as(&root) {
+                    // the walk would escape the root
+                    if resolve.contains(ResolveFlags::RESOLVE_BENEATH) {
+                        return Err(SysError::EXDEV);
+                    }
+                    continue;
+                }
+                let parent = current.parent().unwrap_or(current.clone());
+                current = current.step(parent, resolve)?;
+                continue;
+            }
+            let child = current.clone().walk(&name)?;
+            if child.is_negative() {
+                if components.is_empty() {
+                    return Ok(child);
+                }
+                return Err(SysError::ENOENT);
+            }
+            if !child.is_link() || (components.is_empty() && !follow_last) {
+                current = current.step(child, resolve)?;
+                continue;
+            }
+            // follow the symlink
+            let inode = child.inode().unwrap();
+            if resolve.contains(ResolveFlags::RESOLVE_NO_SYMLINKS) {
+                return Err(SysError::ELOOP);
+            }
+            if inode.is_magic_link() {
+                if resolve.contains(ResolveFlags::RESOLVE_NO_MAGICLINKS) {
+                    return Err(SysError::ELOOP);
+                }
+                if resolve.intersects(ResolveFlags::RESOLVE_BENEATH | ResolveFlags::RESOLVE_IN_ROOT) {
+                    return Err(SysError::EXDEV);
+                }
+            }
+            links += 1;
+            if links > MAX_LINK_DEPTH {
+                return Err(SysError::ELOOP);
+            }
+            let target = inode.readlink()?;
+            if target.starts_with('/') {
+                if resolve.contains(ResolveFlags::RESOLVE_BENEATH) {
+                    return Err(SysError::EXDEV);
+                }
+                current = current.step(root.clone(), resolve)?;
+            }
+            // a relative target goes on from the directory holding the link
+            for part in target.split('/').rev().filter(|s| !s.is_empty() && *s != ".") {
+                components.push_front(part.to_string());
+            }
+        }
+        Ok(current)
+    }
+
+    /// whether both are the same dentry
+    fn same_as(&self, other: &Arc<dyn Dentry>) -> bool {
+        self as *const Self as *const () == Arc::as_ptr(other) as *const ()
+    }
+
+    /// move the walk to `next`, refused by RESOLVE_NO_XDEV
+    /// if `next` is on another file system
+    fn step(&self, next: Arc<dyn Dentry>, resolve: ResolveFlags) -> Result<Arc<dyn Dentry>, SysError> {
+        if resolve.contains(ResolveFlags::RESOLVE_NO_XDEV) {
+            let super_block = |dentry: &dyn Dentry| {
+                dentry.inode().and_then(|inode| inode.inode_inner().super_block.clone())
+            };
+            if let (Some(a), Some(b)) = (super_block(self), super_block(next.as_ref())) {
+                if !Weak::ptr_eq(&a, &b) {
+                    return Err(SysError::EXDEV);
+                }
+            }
+        }
+        Ok(next)
+    }
+
+    /// rename: what this dentry names is named by `target` from now on,
+    /// this dentry goes negative.
+    /// the name and parent of a dentry are fixed, so the cached subtree
+    /// is rebuilt under `target` instead of being moved
+    pub fn move_to(self: &Arc<Self>, target: &Arc<dyn Dentry>) {
+        let children = self.take_children();
+        let inode = self.inode();
+        self.clear_inode();
+        if let Some(inode) = inode {
+            target.replace_inode(inode);
+        }
+        target.graft(children);
+    }
+
+    /// RENAME_EXCHANGE: swap what this dentry and `other` name, subtrees included
+    pub fn exchange(self: &Arc<Self>, other: &Arc<dyn Dentry>) {
+        let (ours, theirs) = (self.take_children(), other.take_children());
+        let (inode, other_inode) = (self.inode(), other.inode());
+        if let Some(inode) = other_inode {
+            self.replace_inode(inode);
+        }
+        if let Some(inode) = inode {
+            other.replace_inode(inode);
+        }
+        self.graft(theirs);
+        other.graft(ours);
+    }
+
+    /// detach the cached children, forgetting every path below this dentry
+    fn take_children(&self) -> BTreeMap<String, Arc<dyn Dentry>> {
+        let path = self.path();
+        PATH_CACHE.lock().invalidate(&path);
+        NEG_DCACHE.lock().invalidate(&path);
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        DCACHE.lock().retain(|key, _| !key.starts_with(&prefix));
+        core::mem::take(&mut *self.dentry_inner().children.lock())
+    }
+
+    /// point at `inode`, whatever was pointed at before
+    fn replace_inode(&self, inode: Arc<dyn Inode>) {
+        inode.moved(&self.path());
+        *self.dentry_inner().inode.lock() = Some(inode);
+        self.set_state(DentryState::USED);
+    }
+
+    /// rebuild the positive `children` of another dentry under this one,
+    /// the old children stay with whoever holds them (open files) but are no longer found
+    fn graft(self: &Arc<Self>, children: BTreeMap<String, Arc<dyn Dentry>>) {
+        for (name, child) in children {
+            let inode = match child.inode() {
+                Some(inode) if !child.is_negative() => inode,
+                _ => continue,
+            };
+            let grandchildren = core::mem::take(&mut *child.dentry_inner().children.lock());
+            let moved = self.new(&name, Some(self.clone()));
+            moved.replace_inode(inode);
+            self.add_child(moved.clone());
+            DCACHE.lock().insert(moved.path(), moved.clone());
+            moved.graft(grandchildren);
+        }
+    }
+
+    /// whether the dentry is a symlink
+    pub fn is_link(&self) -> bool {
+        self.state() != DentryState::NEGATIVE
+            && self.inode().map_or(false, |inode| inode.inode_type() == InodeMode::LINK)
+    }
+
+    /// absolute path of the symlink target,
+    /// a relative target starts from the directory holding the link
+    pub fn link_target(&self) -> Result<String, SysError> {
+        let target = self.inode().ok_or(SysError::ENOENT)?.readlink()?;
+        if target.starts_with("/") {
+            Ok(target)
+        } else {
+            let parent = self.parent().ok_or(SysError::ENOENT)?.path();
+            rel_path_to_abs(&parent, &target).ok_or(SysError::ENOENT)
+        }
+    }
+
     /// follow the link and jump until reach the first NOT link Inode or reach the max depth
     /// need to translate runtime
-    pub fn follow(self: Arc<Self>, task: Arc<TaskControlBlock>, dirfd: isize, flags: AtFlags) -> Result<Arc<dyn Dentry>, SysError> {
-        const MAX_LINK_DEPTH: usize = 40;
+    pub fn follow(self: Arc<Self>, task: Arc<TaskControlBlock>, _dirfd: isize, _flags: AtFlags) -> Result<Arc<dyn Dentry>, SysError> {
         let mut current = self.clone();
         // log::info!("before follow, path {}", self.path());
 
@@ -262,25 +490,11 @@ impl dyn Dentry {
                 return Ok(current)
             }
 
-            let mode = current.inode().unwrap().inode_inner().mode();
-            // log::info!("[walk] mode {:?}", mode);
-            if mode.contains(InodeMode::LINK) {
+            if current.is_link() {
                 // follow to the next
-                let path =  current.inode().unwrap().readlink()?;
-                log::info!("path: {}", path);
-                // let mut no_follow_flags = flags;
-                // no_follow_flags.remove(AtFlags::AT_SYMLINK_FOLLOW);
-                // no_follow_flags |= AtFlags::AT_SYMLINK_NOFOLLOW;
-                // let new_dentry = at_helper1(task.clone(), dirfd, &path, no_follow_flags)?;
-                let new_path;
-                if path.starts_with("/") {
-                    new_path = path;
-                } else {
-                    let parent = current.parent().unwrap().path();
-                    new_path = rel_path_to_abs(&parent, &path).unwrap();
-                }
-                let new_dentry = global_find_dentry(&new_path).unwrap();
-                current = new_dentry;
+                let new_path = task.link_path(&current)?;
+                log::info!("path: {}", new_path);
+                current = global_find_dentry(&new_path)?;
             } else {
                 return Ok(current)
             }
@@ -314,24 +528,143 @@ pub static DCACHE: SpinNoIrqLock<BTreeMap<String, Arc<dyn Dentry>>> =
     SpinNoIrqLock::new(BTreeMap::new());
 
 
+/// a bounded LRU from absolute path to dentry
+pub struct DentryLru {
+    /// path -> (last use, dentry)
+    entries: BTreeMap<String, (u64, Arc<dyn Dentry>)>,
+    /// last use -> path, the first one is the least recently used
+    lru: BTreeMap<u64, String>,
+    /// use counter
+    clock: u64,
+    /// max number of entries
+    capacity: usize,
+}
+
+impl DentryLru {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            capacity,
+        }
+    }
+
+    /// look up a dentry and mark it as recently used,
+    /// the entry is dropped if it is no longer `valid`
+    pub fn get(&mut self, path: &str, valid: fn(&Arc<dyn Dentry>) -> bool) -> Option<Arc<dyn Dentry>> {
+        let (last_use, dentry) = self.entries.get(path).cloned()?;
+        if !valid(&dentry) {
+            self.remove(path);
+            return None;
+        }
+        self.clock += 1;
+        self.lru.remove(&last_use);
+        self.lru.insert(self.clock, path.to_string());
+        self.entries.insert(path.to_string(), (self.clock, dentry.clone()));
+        Some(dentry)
+    }
+
+    /// remember the dentry of `path`, evicting the least recently used entry when full
+    pub fn insert(&mut self, path: String, dentry: Arc<dyn Dentry>) {
+        self.remove(&path);
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.lru.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.lru.insert(self.clock, path.clone());
+        self.entries.insert(path, (self.clock, dentry));
+    }
+
+    /// forget a single entry
+    pub fn remove(&mut self, path: &str) {
+        if let Some((last_use, _)) = self.entries.remove(path) {
+            self.lru.remove(&last_use);
+        }
+    }
+
+    /// forget `path` and every path below it
+    pub fn invalidate(&mut self, path: &str) {
+        self.remove(path);
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let below: Vec<String> = self.entries
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in below {
+            self.remove(&key);
+        }
+    }
+}
+
+/// max number of negative dentries kept in NEG_DCACHE
+const NEG_DCACHE_CAPACITY: usize = 1024;
+
+/// negative dcache: the names known not to exist,
+/// so that probing them again (e.g. library search paths)
+/// does not reach the file system.
+/// the key is the absolute path, entries are dropped
+/// once the name gets created (see Dentry::add_child)
+pub static NEG_DCACHE: SpinNoIrqLock<DentryLru> =
+    SpinNoIrqLock::new(DentryLru::new(NEG_DCACHE_CAPACITY));
+
+/// max number of resolved paths kept in PATH_CACHE
+const PATH_CACHE_CAPACITY: usize = 1024;
+
+/// path walk cache: the absolute path to the dentry a full walk resolved,
+/// so that a hot path is found in one lookup instead of one per component.
+/// relative paths are made absolute against the dirfd before reaching here,
+/// so the absolute path also stands for the (dirfd, path) pair.
+/// only paths that are the dentry's own path are kept (no symlink
+/// or dynamic dentry along the way), entries are dropped when a component
+/// is added or removed (see Dentry::add_child and remove_child),
+/// and checked on use for a component that went negative (unlink, rename)
+pub static PATH_CACHE: SpinNoIrqLock<DentryLru> =
+    SpinNoIrqLock::new(DentryLru::new(PATH_CACHE_CAPACITY));
+
+/// whether the dentry and all its ancestors are still alive and positive
+fn path_is_live(dentry: &Arc<dyn Dentry>) -> bool {
+    let mut current = dentry.clone();
+    loop {
+        if current.is_negative() {
+            return false;
+        }
+        current = match current.dentry_inner().parent.as_ref() {
+            Some(parent) => match parent.upgrade() {
+                Some(parent) => parent,
+                None => return false,
+            },
+            None => return true,
+        };
+    }
+}
+
 /// helper function: Search from root using absolute path,
 /// return the target dentry: maybe negative
 /// first lookup in the dcache
 /// if not found, search from root
 pub fn global_find_dentry(path: &str) -> Result<Arc<dyn Dentry>, SysError> {
     log::debug!("global find dentry: {}", path);
-    // {
-    //     let cache = DCACHE.lock();
-    //     if let Some(dentry) = cache.get(path) {
-    //         return Ok(dentry.clone());
-    //     }
-    // }
+    // a path through the put_old of pivot_root leads to the old root
+    if let Some(path) = resolve_old_root(path) {
+        return global_find_dentry(&path);
+    }
+    if let Some(dentry) = PATH_CACHE.lock().get(path, path_is_live) {
+        return Ok(dentry);
+    }
     // get the root dentry
     let root_dentry = {
         let dcache = DCACHE.lock();
         Arc::clone(dcache.get("/").unwrap())
     };
-    root_dentry.walk(path)
+    let dentry = root_dentry.walk(path)?;
+    if !dentry.is_negative() && dentry.path() == path {
+        PATH_CACHE.lock().insert(path.to_string(), dentry.clone());
+    }
+    Ok(dentry)
 }
 
 /// helper function: try to update DCACHE when create new inode
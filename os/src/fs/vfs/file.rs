@@ -199,12 +199,27 @@ impl dyn File {
         if events.contains(EPollEvents::EPOLLOUT) {
             in_event |= PollEvents::OUT;
         }
+        if events.contains(EPollEvents::EPOLLPRI) {
+            in_event |= PollEvents::PRI;
+        }
         let revent = self.base_poll(in_event).await;
-        let mut res = events;
-        res.remove_input();
+        let mut res = EPollEvents::empty();
+        if revent.contains(PollEvents::IN) {
+            res |= EPollEvents::EPOLLIN;
+        }
+        if revent.contains(PollEvents::OUT) {
+            res |= EPollEvents::EPOLLOUT;
+        }
+        if revent.contains(PollEvents::PRI) {
+            res |= EPollEvents::EPOLLPRI;
+        }
+        // error and hang up are always reported
         if revent.contains(PollEvents::ERR) {
             res |= EPollEvents::EPOLLERR
         }
+        if revent.contains(PollEvents::HUP) {
+            res |= EPollEvents::EPOLLHUP
+        }
         res
     }
 }
@@ -238,6 +253,7 @@ pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<dyn File>> {
             let dentry = parent_dentry.new(&name, Some(parent_dentry.clone()));
             dentry.set_state(DentryState::USED);
             dentry.set_inode(inode);
+            parent_dentry.add_child(dentry.clone());
             dentry.open(flags)
         }
     } else {
@@ -35,6 +35,14 @@ pub trait FSType: Send + Sync {
     fn name(&self) -> &str {
         &self.inner().name
     }
+    /// the file system type shown to user space, the registered name by default
+    fn type_name(&self) -> &str {
+        self.name()
+    }
+    /// magic number identifying the file system type
+    fn magic(&self) -> u64 {
+        0
+    }
     /// use the mount path to get the super block
     fn get_sb(&self, abs_mount_path: &str) -> Option<Arc<dyn SuperBlock>> {
         self.inner()
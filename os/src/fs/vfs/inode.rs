@@ -1,12 +1,12 @@
 //! VFS Inode
 
-use core::{ops::Range, sync::atomic::{AtomicI32, AtomicU32, AtomicUsize, Ordering}};
+use core::{ops::Range, sync::atomic::{AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering}};
 
-use alloc::{string::String, sync::{Arc, Weak}, vec::Vec};
+use alloc::{collections::btree_map::BTreeMap, string::String, sync::{Arc, Weak}, vec::Vec};
 use downcast_rs::{impl_downcast, Downcast, DowncastSync};
 
-use super::SuperBlock;
-use crate::{fs::{page::{cache::PageCache, page::Page}, Xstat, XstatMask}, generate_atomic_accessors, generate_lock_accessors, generate_with_methods, sync::mutex::SpinNoIrqLock, syscall::{SysError, SysResult}, timer::{clock::{CLOCK_DEVIATION, CLOCK_MONOTONIC, CLOCK_REALTIME}, ffi::TimeSpec, get_current_time, get_current_time_duration}};
+use super::{lock::FileLocks, superblock::ANON_DEV_MINOR, SuperBlock};
+use crate::{fs::{inotify::InotifyWatch, page::{cache::PageCache, page::Page}, FallocFlags, XattrFlags, Xstat, XstatMask}, generate_atomic_accessors, generate_lock_accessors, generate_with_methods, sync::mutex::SpinNoIrqLock, syscall::{SysError, SysResult}, timer::{clock::realtime_time, ffi::TimeSpec}};
 use crate::fs::Kstat;
 
 /// the base Inode of all file system
@@ -23,6 +23,8 @@ pub struct InodeInner {
     pub uid: AtomicU32,
     /// group
     pub gid: AtomicU32,
+    /// device number of a device node, encoded as st_rdev
+    pub rdev: AtomicU64,
     /// mode of inode
     pub mode: SpinNoIrqLock<InodeMode>,
     /// last access time
@@ -32,47 +34,99 @@ pub struct InodeInner {
     #[allow(unused)]
     /// last state change time(todo: support state change)
     pub ctime: SpinNoIrqLock<TimeSpec>,
+    /// flock and record locks placed on the inode
+    pub locks: SpinNoIrqLock<FileLocks>,
+    /// held by an append from finding the end of the file to writing there
+    pub append: SpinNoIrqLock<()>,
+    /// extended attributes, for file systems keeping them in memory
+    pub xattrs: SpinNoIrqLock<BTreeMap<String, Vec<u8>>>,
+    /// inotify watches on this inode
+    pub watches: SpinNoIrqLock<Vec<InotifyWatch>>,
 }
 
 impl InodeInner {
     /// create a inner using super block
     pub fn new(super_block: Option<Weak<dyn SuperBlock>>, mode: InodeMode, size: usize) -> Self {
-        let current = get_current_time_duration();
-        let ts: TimeSpec = unsafe {
-            (CLOCK_DEVIATION[CLOCK_REALTIME] + current).into()
-        };
+        let ino = inode_alloc(&super_block);
+        Self::with_ino(super_block, ino, mode, size)
+    }
+    /// device number (major, minor) of the file system the inode lives on
+    pub fn dev(&self) -> (u32, u32) {
+        match self.super_block.as_ref().and_then(|sb| sb.upgrade()) {
+            Some(sb) => sb.inner().dev,
+            None => (0, ANON_DEV_MINOR),
+        }
+    }
+    /// device number encoded the way st_dev expects
+    pub fn st_dev(&self) -> u64 {
+        let (major, minor) = self.dev();
+        encode_dev(major, minor)
+    }
+    /// create a inner with a known inode number
+    pub fn with_ino(super_block: Option<Weak<dyn SuperBlock>>, ino: usize, mode: InodeMode, size: usize) -> Self {
+        let ts: TimeSpec = realtime_time().into();
         Self {
-            ino: inode_alloc(),
+            ino,
             super_block: super_block,
             size: AtomicUsize::new(size),
             nlink: AtomicUsize::new(1),
             uid: AtomicU32::new(0),
             gid: AtomicU32::new(0),
+            rdev: AtomicU64::new(0),
             mode: SpinNoIrqLock::new(mode),
             atime: SpinNoIrqLock::new(ts),
             mtime: SpinNoIrqLock::new(ts),
             ctime: SpinNoIrqLock::new(ts),
+            locks: SpinNoIrqLock::new(FileLocks::new()),
+            append: SpinNoIrqLock::new(()),
+            xattrs: SpinNoIrqLock::new(BTreeMap::new()),
+            watches: SpinNoIrqLock::new(Vec::new()),
         }
     }
     /// update access time
     pub fn update_atime(&self) {
-        let current = get_current_time_duration();
-        let ts: TimeSpec = unsafe {
-            (CLOCK_DEVIATION[CLOCK_REALTIME] + current).into()
-        };
+        let ts: TimeSpec = realtime_time().into();
         self.set_atime(ts);
     }
     /// update modified time
     pub fn update_mtime(&self) {
-        let current = get_current_time_duration();
-        let ts: TimeSpec = unsafe {
-            (CLOCK_DEVIATION[CLOCK_REALTIME] + current).into()
-        };
+        let ts: TimeSpec = realtime_time().into();
         self.set_mtime(ts);
     }
+    /// whether `uid` in group `gid` may access the inode as `mask` asks,
+    /// a mask of R_OK, W_OK and X_OK; root passes any check but executing
+    /// a file no one may execute; as for exec, an inode with no permission
+    /// bits at all comes from a file system that keeps none and is open to all
+    pub fn permission(&self, uid: u32, gid: u32, mask: i32) -> Result<(), SysError> {
+        let mode = self.mode();
+        if mode.bits() & 0o777 == 0 {
+            return Ok(());
+        }
+        if uid == 0 {
+            let exec = InodeMode::OWNER_EXEC | InodeMode::GROUP_EXEC | InodeMode::OTHER_EXEC;
+            if mask & 1 != 0 && mode.get_type() != InodeMode::DIR && !mode.intersects(exec) {
+                return Err(SysError::EACCES);
+            }
+            return Ok(());
+        }
+        // the rwx bits of the class the caller falls in line up with R_OK, W_OK and X_OK
+        let shift = if uid == self.uid() {
+            6
+        } else if gid == self.gid() {
+            3
+        } else {
+            0
+        };
+        let granted = (mode.bits() >> shift) as i32 & 0o7;
+        if mask & !granted != 0 {
+            return Err(SysError::EACCES);
+        }
+        Ok(())
+    }
     generate_atomic_accessors!(
         uid: u32,
         gid: u32,
+        rdev: u64,
         size: usize,
         nlink: usize
     );
@@ -135,10 +189,22 @@ pub trait Inode: DowncastSync {
     fn create(&self, _name: &str, _mode: InodeMode) -> Result<Arc<dyn Inode>, SysError> {
         todo!()
     }
+    /// create a special file under current inode, a FIFO, socket or a device
+    /// node standing for device `rdev`
+    fn mknod(&self, name: &str, mode: InodeMode, rdev: u64) -> Result<Arc<dyn Inode>, SysError> {
+        let inode = self.create(name, mode)?;
+        inode.inode_inner().set_rdev(rdev);
+        Ok(inode)
+    }
     /// resize the current inode
     fn truncate(&self, _size: usize) -> Result<usize, SysError> {
         todo!()
     }
+    /// manipulate the space of [offset, offset + len),
+    /// only supported by file system relying on page cache
+    fn fallocate(self: Arc<Self>, _mode: FallocFlags, _offset: usize, _len: usize) -> Result<(), SysError> {
+        Err(SysError::EOPNOTSUPP)
+    }
     /// get attributes of a file
     fn getattr(&self) -> Kstat {
         todo!()
@@ -147,6 +213,32 @@ pub trait Inode: DowncastSync {
     fn getxattr(&self, _mask: XstatMask) -> Xstat {
         todo!()
     }
+    /// get the value of the extended attribute `name`
+    fn get_xattr(&self, name: &str) -> Result<Vec<u8>, SysError> {
+        self.inode_inner().xattrs.lock().get(name).cloned().ok_or(SysError::ENODATA)
+    }
+    /// set the extended attribute `name` to `value`,
+    /// XATTR_CREATE and XATTR_REPLACE in `flags` ask it to be new or to exist
+    fn set_xattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> Result<(), SysError> {
+        let mut xattrs = self.inode_inner().xattrs.lock();
+        let exists = xattrs.contains_key(name);
+        if flags.contains(XattrFlags::XATTR_CREATE) && exists {
+            return Err(SysError::EEXIST);
+        }
+        if flags.contains(XattrFlags::XATTR_REPLACE) && !exists {
+            return Err(SysError::ENODATA);
+        }
+        xattrs.insert(String::from(name), value.to_vec());
+        Ok(())
+    }
+    /// names of all the extended attributes
+    fn list_xattr(&self) -> Result<Vec<String>, SysError> {
+        Ok(self.inode_inner().xattrs.lock().keys().cloned().collect())
+    }
+    /// remove the extended attribute `name`
+    fn remove_xattr(&self, name: &str) -> Result<(), SysError> {
+        self.inode_inner().xattrs.lock().remove(name).map(|_| ()).ok_or(SysError::ENODATA)
+    }
     /// create a symlink of this inode and return the symlink inode
     /// create a inode in link path [link_path]--->[target_path]
     fn symlink(&self, _target_path: &str, _link_path: &str) -> Result<Arc<dyn Inode>, SysError> {
@@ -160,6 +252,11 @@ pub trait Inode: DowncastSync {
     fn readlink(&self) -> Result<String, SysError> {
         todo!()
     }
+    /// whether the symlink is a magic link (like /proc/self/fd/*),
+    /// which refers to a file rather than to a path
+    fn is_magic_link(&self) -> bool {
+        false
+    }
     /// called by the unlink system call
     fn unlink(&self) -> Result<usize, i32> {
         todo!()
@@ -180,32 +277,128 @@ pub trait Inode: DowncastSync {
     fn rename(&self, _target: &str, _new_inode: Option<Arc<dyn Inode>>) -> Result<(), SysError> {
         Err(SysError::EINVAL)
     }
+    /// the inode is now reached by `path`, after itself or a directory above it
+    /// was renamed; only matters to file systems that find inodes by path
+    fn moved(&self, _path: &str) {
+        // do nothing
+    }
+    /// write the mode and owner kept in the inner to the file system after
+    /// chmod or chown; nothing to do for file systems living in memory
+    fn setattr(&self) -> Result<(), SysError> {
+        Ok(())
+    }
     /// set all cached pages clean when unlink
     fn clean_cached(&self) {
         // do nothing
     }
+    /// write the dirty data of the file to its device, with its metadata unless
+    /// `data_only`, in which case only what is needed to read the data back goes out;
+    /// nothing to do for files without a backing store
+    fn sync(&self, _data_only: bool) -> Result<(), SysError> {
+        Ok(())
+    }
 }
 
 impl dyn Inode {
+    /// the content was read, files opened with O_NOATIME skip this
     pub fn access(&self) -> Result<(), SysError> {
-        // TODO: add owner check
         self.inode_inner().update_atime();
         Ok(())
     }
 
+    /// the content was written, which changes the inode as well
     pub fn modified(&self) -> Result<(), SysError> {
-        self.inode_inner().update_atime();
-        self.inode_inner().update_mtime();
+        let ts: TimeSpec = realtime_time().into();
+        self.inode_inner().set_mtime(ts);
+        self.inode_inner().set_ctime(ts);
         Ok(())
     }
+
+    /// write `buf` at the end of the file, as for O_APPEND;
+    /// the end cannot move between being found and being written at,
+    /// so concurrent appends never overwrite each other
+    /// return the offset written at and the bytes written
+    pub fn append(self: Arc<Self>, buf: &[u8]) -> Result<(usize, usize), SysError> {
+        let _guard = self.inode_inner().append.lock();
+        let offset = self.getattr().st_size as usize;
+        let size = if self.cache().is_some() {
+            self.clone().cache_write_at(offset, buf)
+        } else {
+            self.write_at(offset, buf)
+        }
+        .map_err(SysError::from_i32)?;
+        Ok((offset, size))
+    }
 }
 
 impl_downcast!(sync Inode);
 
-static INODE_NUMBER: AtomicUsize = AtomicUsize::new(0);
+/// inode numbers of different allocators never collide:
+/// the high bits name the allocator, the low bits count its inodes
+const INO_ALLOCATOR_SHIFT: u32 = 32;
+
+/// allocators handed out so far, 0 is reserved for inodes without a super block
+static INO_ALLOCATORS: AtomicUsize = AtomicUsize::new(1);
+
+/// a monotonic inode number allocator,
+/// every super block owns one so its inodes get unique and stable numbers
+pub struct InoAllocator {
+    base: usize,
+    next: AtomicUsize,
+}
+
+impl InoAllocator {
+    /// an allocator whose numbers differ from those of every other allocator
+    pub fn new() -> Self {
+        Self::with_id(INO_ALLOCATORS.fetch_add(1, Ordering::Relaxed))
+    }
+
+    const fn with_id(id: usize) -> Self {
+        Self {
+            base: id << INO_ALLOCATOR_SHIFT,
+            // 0 marks a deleted entry in getdents
+            next: AtomicUsize::new(1),
+        }
+    }
 
-fn inode_alloc() -> usize {
-    INODE_NUMBER.fetch_add(1, Ordering::Relaxed)
+    /// next unused inode number
+    pub fn alloc(&self) -> usize {
+        self.base | self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// how many numbers were handed out so far
+    pub fn allocated(&self) -> usize {
+        self.next.load(Ordering::Relaxed) - 1
+    }
+
+    /// the number at index within the allocator,
+    /// for inodes that are built again on each lookup and must keep their number
+    pub fn fixed(&self, index: usize) -> usize {
+        self.base | index
+    }
+}
+
+/// inodes without a super block: pipes, sockets, eventfds...
+static ANON_INO: InoAllocator = InoAllocator::with_id(0);
+
+/// encode a device number like glibc makedev()
+pub fn encode_dev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32) | ((major & 0xfff) << 8) | ((minor & 0xffff_ff00) << 12) | (minor & 0xff)
+}
+
+/// major and minor of a device number encoded as st_rdev
+pub fn decode_dev(dev: u64) -> (u32, u32) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & 0xffff_f000);
+    let minor = (dev & 0xff) | ((dev >> 12) & 0xffff_ff00);
+    (major as u32, minor as u32)
+}
+
+fn inode_alloc(super_block: &Option<Weak<dyn SuperBlock>>) -> usize {
+    match super_block.as_ref().and_then(|sb| sb.upgrade()) {
+        Some(sb) => sb.inner().ino_allocator.alloc(),
+        None => ANON_INO.alloc(),
+    }
 }
 
 bitflags::bitflags! {
@@ -0,0 +1,208 @@
+//! Advisory file locks
+//! BSD flock locks belong to an open file description,
+//! POSIX record locks belong to a process and cover a byte range
+
+use core::{future::Future, pin::Pin, task::{Context, Poll, Waker}};
+
+use alloc::{collections::vec_deque::VecDeque, sync::{Arc, Weak}, vec::Vec};
+
+use super::{File, Inode};
+
+/// kind of an advisory lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// read lock, many holders allowed
+    Shared,
+    /// write lock, only one holder allowed
+    Exclusive,
+}
+
+impl LockKind {
+    fn conflicts(self, other: Self) -> bool {
+        self == LockKind::Exclusive || other == LockKind::Exclusive
+    }
+}
+
+/// a flock held by an open file description,
+/// released once the file description is dropped
+struct FlockEntry {
+    file: Weak<dyn File>,
+    kind: LockKind,
+}
+
+impl FlockEntry {
+    fn owned_by(&self, file: &Arc<dyn File>) -> bool {
+        self.file.as_ptr() as *const () == Arc::as_ptr(file) as *const ()
+    }
+}
+
+/// a POSIX record lock, covers [start, end)
+#[derive(Debug, Clone, Copy)]
+pub struct RecordLock {
+    /// owner process
+    pub pid: usize,
+    /// first byte locked
+    pub start: usize,
+    /// end of the range (exclusive), usize::MAX means up to EOF
+    pub end: usize,
+    /// lock kind
+    pub kind: LockKind,
+}
+
+impl RecordLock {
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// all the advisory locks placed on an inode
+pub struct FileLocks {
+    flocks: Vec<FlockEntry>,
+    records: Vec<RecordLock>,
+    waiters: VecDeque<Waker>,
+}
+
+impl FileLocks {
+    /// no lock at all
+    pub fn new() -> Self {
+        Self {
+            flocks: Vec::new(),
+            records: Vec::new(),
+            waiters: VecDeque::new(),
+        }
+    }
+
+    fn wake_all(&mut self) {
+        while let Some(waker) = self.waiters.pop_front() {
+            waker.wake();
+        }
+    }
+
+    /// try to flock with `kind` for the file description,
+    /// a lock it already holds is converted
+    pub fn try_flock(&mut self, file: &Arc<dyn File>, kind: LockKind) -> bool {
+        self.flocks.retain(|l| l.file.strong_count() > 0);
+        if self.flocks.iter().any(|l| !l.owned_by(file) && l.kind.conflicts(kind)) {
+            return false;
+        }
+        match self.flocks.iter_mut().find(|l| l.owned_by(file)) {
+            Some(lock) => lock.kind = kind,
+            None => self.flocks.push(FlockEntry { file: Arc::downgrade(file), kind }),
+        }
+        // a downgrade may let shared waiters in
+        self.wake_all();
+        true
+    }
+
+    /// drop the flock held by the file description
+    pub fn flock_unlock(&mut self, file: &Arc<dyn File>) {
+        self.flocks.retain(|l| l.file.strong_count() > 0 && !l.owned_by(file));
+        self.wake_all();
+    }
+
+    /// find a lock of another process that prevents `req`
+    pub fn record_conflict(&self, req: &RecordLock) -> Option<RecordLock> {
+        self.records
+            .iter()
+            .find(|l| l.pid != req.pid && l.overlaps(req.start, req.end) && l.kind.conflicts(req.kind))
+            .copied()
+    }
+
+    /// remove the range [start, end) from the locks of `pid`,
+    /// splitting the locks that cover it partially
+    fn cut_records(&mut self, pid: usize, start: usize, end: usize) {
+        let mut kept = Vec::with_capacity(self.records.len() + 1);
+        for lock in self.records.drain(..) {
+            if lock.pid != pid || !lock.overlaps(start, end) {
+                kept.push(lock);
+                continue;
+            }
+            if lock.start < start {
+                kept.push(RecordLock { end: start, ..lock });
+            }
+            if end < lock.end {
+                kept.push(RecordLock { start: end, ..lock });
+            }
+        }
+        self.records = kept;
+    }
+
+    /// try to place `req`, it replaces whatever the process held on the range
+    /// and is merged with its adjacent locks of the same kind
+    pub fn try_record(&mut self, req: RecordLock) -> bool {
+        if self.record_conflict(&req).is_some() {
+            return false;
+        }
+        self.cut_records(req.pid, req.start, req.end);
+        let mut merged = req;
+        self.records.retain(|l| {
+            let adjacent = l.pid == merged.pid && l.kind == merged.kind
+                && l.start <= merged.end && merged.start <= l.end;
+            if adjacent {
+                merged.start = merged.start.min(l.start);
+                merged.end = merged.end.max(l.end);
+            }
+            !adjacent
+        });
+        self.records.push(merged);
+        self.wake_all();
+        true
+    }
+
+    /// release the range [start, end) locked by `pid`
+    pub fn record_unlock(&mut self, pid: usize, start: usize, end: usize) {
+        self.cut_records(pid, start, end);
+        self.wake_all();
+    }
+
+    /// a file descriptor of `pid` was closed: drop all its record locks
+    /// and the flocks whose file description is gone
+    pub fn release(&mut self, pid: usize) {
+        if self.flocks.is_empty() && self.records.is_empty() {
+            return;
+        }
+        self.records.retain(|l| l.pid != pid);
+        self.flocks.retain(|l| l.file.strong_count() > 0);
+        self.wake_all();
+    }
+}
+
+/// called when a file descriptor of process `pid` referring to `file` is closed,
+/// the flock goes away with the last reference of the file description
+pub fn release_file_locks(file: Arc<dyn File>, pid: usize) {
+    let inode = match file.inode() {
+        Ok(inode) => inode,
+        Err(_) => return,
+    };
+    drop(file);
+    inode.inode_inner().locks.lock().release(pid);
+}
+
+/// future that resolves once `try_lock` succeeds on the locks of the inode,
+/// it retries every time the locks change
+pub struct FileLockFuture<F> {
+    inode: Arc<dyn Inode>,
+    try_lock: F,
+}
+
+impl<F: FnMut(&mut FileLocks) -> bool + Unpin> FileLockFuture<F> {
+    /// wait on the locks of `inode`
+    pub fn new(inode: Arc<dyn Inode>, try_lock: F) -> Self {
+        Self { inode, try_lock }
+    }
+}
+
+impl<F: FnMut(&mut FileLocks) -> bool + Unpin> Future for FileLockFuture<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut locks = this.inode.inode_inner().locks.lock();
+        if (this.try_lock)(&mut locks) {
+            Poll::Ready(())
+        } else {
+            locks.waiters.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
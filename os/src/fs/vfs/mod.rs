@@ -7,6 +7,7 @@ pub mod inode;
 pub mod file;
 pub mod dentry;
 pub mod fstype;
+pub mod lock;
 
 pub use superblock::{SuperBlockInner, SuperBlock};
 pub use inode::{InodeInner, Inode};
@@ -1,16 +1,37 @@
 //! vfs super block
 //! 
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 use alloc::sync::{Arc, Weak};
 use spin::Once;
 
+use crate::config::PAGE_SIZE;
 use crate::devices::BlockDevice;
+use crate::fs::StatFs;
 use crate::fs::vfs::Inode;
+use crate::syscall::SysError;
 
 use super::fstype::FSType;
+use super::inode::{encode_dev, InoAllocator};
 use super::Dentry;
 
+/// longest file name in a directory, the same for all the file systems here
+pub const NAME_MAX: usize = 255;
+
+/// minor of the device number shared by inodes without a super block
+pub const ANON_DEV_MINOR: u32 = 1;
+
+/// super blocks get anonymous device numbers (major 0) in mount order
+static NEXT_DEV_MINOR: AtomicU32 = AtomicU32::new(ANON_DEV_MINOR + 1);
+
+/// unique mount ids start here, the same as linux,
+/// so they are never confused with the old 32 bit ones
+pub const MNT_UNIQUE_ID_OFFSET: u64 = 1 << 32;
+
+/// every super block is mounted once, it gets the next mount id when created
+static NEXT_MNT_ID: AtomicU64 = AtomicU64::new(1);
+
 /// the base of super block of all file system
 pub struct SuperBlockInner {
     /// the block device fs using
@@ -19,15 +40,44 @@ pub struct SuperBlockInner {
     pub fs_type: Weak<dyn FSType>,
     /// the root dentry to the mount point
     pub root: Once<Arc<dyn Dentry>>,
+    /// numbers the inodes of the file system
+    pub ino_allocator: InoAllocator,
+    /// device number (major, minor) reported in st_dev,
+    /// different for every mounted file system
+    pub dev: (u32, u32),
+    /// unique id of the mount of the file system, stable while it is mounted
+    pub mnt_id: u64,
 }
 
 impl SuperBlockInner {
+    /// a statfs of the file system with the given block size and counts
+    pub fn statfs(&self, bsize: usize, blocks: u64, bfree: u64, files: u64, ffree: u64) -> StatFs {
+        let fsid = encode_dev(self.dev.0, self.dev.1);
+        StatFs {
+            f_type: self.fs_type.upgrade().map_or(0, |fs| fs.magic()) as i64,
+            f_bsize: bsize as i64,
+            f_blocks: blocks,
+            f_bfree: bfree,
+            f_bavail: bfree,
+            f_files: files,
+            f_ffree: ffree,
+            f_fsid: [fsid as i32, (fsid >> 32) as i32],
+            f_namelen: NAME_MAX as isize,
+            f_frsize: bsize as isize,
+            f_flags: 0,
+            f_spare: [0; 4],
+        }
+    }
+
     /// create a super block inner with device
     pub fn new(device: Option<Arc<dyn BlockDevice>>, fs_type: Arc<dyn FSType>) -> Self {
         Self {
             device,
             fs_type: Arc::downgrade(&fs_type),
             root: Once::new(),
+            ino_allocator: InoAllocator::new(),
+            dev: (0, NEXT_DEV_MINOR.fetch_add(1, Ordering::Relaxed)),
+            mnt_id: MNT_UNIQUE_ID_OFFSET + NEXT_MNT_ID.fetch_add(1, Ordering::Relaxed),
         }
     }
 }
@@ -42,6 +92,15 @@ pub trait SuperBlock: Send + Sync {
     }
     /// get root dir inode (will only use construct)
     fn get_root_inode(&'static self, name: &str) -> Arc<dyn Inode>;
+    /// write the cached metadata of the file system to its device
+    fn sync(&self) -> Result<(), SysError> {
+        Ok(())
+    }
+    /// usage of the file system as statfs reports it,
+    /// no blocks and no inodes to count by default, like procfs
+    fn statfs(&self) -> StatFs {
+        self.inner().statfs(PAGE_SIZE, 0, 0, 0, 0)
+    }
 }
 
 impl dyn SuperBlock {
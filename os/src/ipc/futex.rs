@@ -0,0 +1,170 @@
+//! futex wait queue
+
+use core::{hash::{BuildHasher, Hasher}, ops::DerefMut, task::Waker};
+
+use alloc::collections::vec_deque::VecDeque;
+use hal::addr::{PhysAddr, VirtAddr};
+use hashbrown::HashMap;
+
+use crate::sync::mutex::SpinNoIrqLock;
+
+/// futex hash key
+#[allow(missing_docs, unused)]
+#[derive(Debug, Hash, PartialEq, PartialOrd, Eq, Copy, Clone)]
+pub enum FutexHashKey {
+    /// shared futex, keyed by physical address so that
+    /// futexes in shared memory work across processes
+    Shared { paddr: PhysAddr },
+    /// private futex, only seen by threads in the same address space
+    Private { mm: usize, vaddr: VirtAddr },
+}
+
+/// global futex queue
+pub static FUTEX_QUEUE: SpinNoIrqLock<FutexQueue> =
+    SpinNoIrqLock::new(FutexQueue::new());
+
+/// lock the global futex queue,
+/// the futex word should be checked again under this lock before sleeping
+pub fn futex_queue() -> impl DerefMut<Target = FutexQueue> {
+    FUTEX_QUEUE.lock()
+}
+
+
+type Tid = usize;
+
+#[derive(Debug)]
+#[allow(missing_docs, unused)]
+pub struct FutexWaiter {
+    pub tid: Tid,
+    pub waker: Waker,
+    pub mask: u32,
+}
+
+#[allow(missing_docs, unused)]
+impl FutexWaiter {
+
+    pub const FUTEX_BITSET_MATCH_ANY: u32 = 0xFFFF_FFFF;
+
+    pub fn wake(self) {
+        self.waker.wake();
+    }
+}
+
+
+///
+pub struct XorHasher {
+    res: u64,
+}
+
+impl Hasher for XorHasher {
+    fn finish(&self) -> u64 {
+        self.res
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for x in bytes.chunks(8) {
+            let mut t = 0;
+            for &i in x {
+                t = (t << 8usize) | (i as u64);
+            }
+            self.res = self.res ^ 31 + t;
+        }
+    }
+}
+
+///
+pub struct FutexHashKeyBuilder;
+
+impl BuildHasher for FutexHashKeyBuilder {
+    type Hasher = XorHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        XorHasher { res: 114514 }
+    }
+}
+
+/// waiters of all futexes
+#[allow(missing_docs, unused)]
+pub struct FutexQueue {
+    futexs: HashMap<FutexHashKey, VecDeque<FutexWaiter>, FutexHashKeyBuilder>,
+}
+
+#[allow(missing_docs, unused)]
+impl FutexQueue {
+    pub const fn new() -> Self {
+        Self {
+            futexs: HashMap::with_hasher(FutexHashKeyBuilder)
+        }
+    }
+
+    pub fn add_waiter(&mut self, key: &FutexHashKey, waiter: FutexWaiter) {
+        // log::info!("[futex::add_waiter] {:?} in {:?} ", waiter, key);
+        self.futexs.entry(*key).or_insert_with(|| VecDeque::with_capacity(1)).push_back(waiter);
+    }
+
+    /// 用于移除任务，任务可能是过期了，也可能是被信号中断了
+    pub fn remove_waiter(&mut self, key: &FutexHashKey, tid: Tid) -> Option<FutexWaiter> {
+        let waiters = self.futexs.get_mut(key)?;
+        let i = waiters.iter().position(|w| w.tid == tid)?;
+        let waiter = waiters.remove(i);
+        if waiters.is_empty() {
+            self.futexs.remove(key);
+        }
+        waiter
+    }
+
+    /// wake at most `n` waiters, return the number of woken waiters
+    pub fn wake(&mut self, key: &FutexHashKey, n: u32) -> usize {
+        self.wake_bitset(key, n, FutexWaiter::FUTEX_BITSET_MATCH_ANY)
+    }
+
+    /// wake at most `n` waiters whose mask intersects `mask`
+    pub fn wake_bitset(&mut self, key: &FutexHashKey, n: u32, mask: u32) -> usize {
+        let Some(waiters) = self.futexs.get_mut(key) else {
+            log::debug!("can not find key {key:?}");
+            return 0;
+        };
+        let mut count = 0;
+        let mut i = 0;
+        while i < waiters.len() && count < n as usize {
+            if waiters[i].mask & mask != 0 {
+                let waiter = waiters.remove(i).unwrap();
+                log::debug!("[futex_wake] task {} has been woken at {:?}", waiter.tid, key);
+                waiter.wake();
+                count += 1;
+            } else {
+                i += 1;
+            }
+        }
+        if waiters.is_empty() {
+            self.futexs.remove(key);
+        }
+        count
+    }
+
+    /// move at most `n_req` waiters from `old` to `new`,
+    /// return the number of requeued waiters
+    pub fn requeue_waiters(
+        &mut self,
+        old: FutexHashKey,
+        new: FutexHashKey,
+        n_req: usize,
+    ) -> usize {
+        if old == new {
+            return self.futexs.get(&old).map_or(0, |w| w.len().min(n_req));
+        }
+        let Some(mut old_waiters) = self.futexs.remove(&old) else {
+            log::info!("[futex] no waiters in key {:?}", old);
+            return 0;
+        };
+        let n = core::cmp::min(n_req, old_waiters.len());
+        let new_waiters = self.futexs.entry(new).or_insert_with(|| VecDeque::with_capacity(n));
+        for waiter in old_waiters.drain(..n) {
+            new_waiters.push_back(waiter);
+        }
+        if !old_waiters.is_empty() {
+            self.futexs.insert(old, old_waiters);
+        }
+        n
+    }
+}
@@ -1,3 +1,5 @@
 
 /// System V
-pub mod sysv;
\ No newline at end of file
+pub mod sysv;
+/// futex wait queue
+pub mod futex;
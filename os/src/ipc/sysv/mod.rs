@@ -16,3 +16,10 @@ pub struct IpcPerm {
     mode: u16,
     seq: u16,
 }
+
+impl IpcPerm {
+    /// the key the object was looked up with, IPC_PRIVATE for one without
+    pub fn key(&self) -> i32 {
+        self.key
+    }
+}
@@ -88,6 +88,13 @@ impl ShmObj {
         };
         ret
     }
+
+    /// the memory behind a MAP_SHARED|MAP_ANONYMOUS mapping: it is no SysV
+    /// segment, so it stays out of SHM_MANAGER under id 0 and goes away
+    /// with the last mapping of it, in whichever process that is
+    pub fn new_anon(size: usize, pid: usize) -> Arc<Self> {
+        Arc::new(Self::new(0, size, pid))
+    }
 }
 
 impl ShmObj {
@@ -156,6 +163,10 @@ impl ShmManager {
             Some(shm)
         }
     }
+    /// bytes of all the segments
+    pub fn total_size(&self) -> usize {
+        self.files.lock().values().map(|shm| shm.shmid_ds.lock().segsz).sum()
+    }
     ///
     pub fn remove(&self, id: usize) -> Option<Arc<ShmObj>> {
         let _ = SHM_MANAGER.id_alloc.lock().dealloc(id);
@@ -61,6 +61,7 @@ pub mod syscall;
 pub mod signal;
 pub mod task;
 mod processor;
+mod selftest;
 pub mod timer;
 pub mod trap;
 mod executor;
@@ -94,16 +95,21 @@ fn main(id: usize, first: bool) -> bool {
         processor::processor::init(id);
         hal::trap::init();       
         devices::init();
+        timer::init_hz();
         fs::init();
         // fs::vfs::file::list_apps(); 
         // fs::ext4::page_cache_test();       
         #[cfg(not(feature = "smp"))]
         executor::init();
-        task::schedule::spawn_kernel_task(
-            async move{
-                task::add_initproc();
-            }
-        );
+        if devices::boot_arg("selftest") {
+            task::schedule::spawn_kernel_task(selftest::run());
+        } else {
+            task::schedule::spawn_kernel_task(
+                async move{
+                    task::add_initproc();
+                }
+            );
+        }
 
         #[cfg(feature = "smp")]
         processor_start(id);
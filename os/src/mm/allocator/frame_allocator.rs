@@ -31,14 +31,18 @@ struct BitMapFrameAllocator {
     align_log2: usize,
     inner: bitmap_allocator::BitAlloc16M,
     last: usize,
+    /// number of frames handed to the allocator
+    total: usize,
 }
 
 impl FrameAllocatorTrait for BitMapFrameAllocator {
     const DEFAULT: Self = BitMapFrameAllocator {
         range: PhysPageNum(0)..PhysPageNum(0),
-        align_log2: 8,
+        // big pages of user space need 2MiB aligned frames
+        align_log2: 9,
         inner: bitmap_allocator::BitAlloc16M::DEFAULT,
-        last: 0
+        last: 0,
+        total: 0,
     };
 
     fn init(&mut self, range_pa: Range<PhysAddr>) {
@@ -51,6 +55,7 @@ impl FrameAllocatorTrait for BitMapFrameAllocator {
         let beg = start.0 - aligned_range_ppn.start.0;
         let end = aligned_range_ppn.end.0 - aligned_range_ppn.start.0;
         self.last = end - beg;
+        self.total = self.last;
         info!("[FrameAllocator] pages: {}", self.last);
         self.inner.insert(beg..end);
     }
@@ -96,15 +101,26 @@ pub type FrameTracker = hal::common::FrameTracker<FrameAllocator>;
 
 impl FrameAllocatorHal for FrameAllocator {
 
+    #[track_caller]
     fn alloc_with_align(&self, cnt: usize, align_log2: usize) -> Option<Range<PhysPageNum>> {
         if cnt == 0 {
             return None
         }
         let mut alloc_guard = FRAME_ALLOCATOR.lock();
-        alloc_guard.alloc_contiguous(cnt, align_log2)
+        let range_ppn = alloc_guard.alloc_contiguous(cnt, align_log2);
+        drop(alloc_guard);
+        #[cfg(feature = "frame_debug")]
+        if let Some(range_ppn) = range_ppn.clone() {
+            super::frame_debug::on_alloc(range_ppn, core::panic::Location::caller());
+        }
+        range_ppn
     }
 
+    #[track_caller]
     fn dealloc(&self, range_ppn: Range<PhysPageNum>) {
+        // poison before the frames can be handed out again
+        #[cfg(feature = "frame_debug")]
+        super::frame_debug::on_dealloc(range_ppn.clone(), core::panic::Location::caller());
         let mut alloc_guard = FRAME_ALLOCATOR.lock();
         alloc_guard.dealloc_contiguous(range_ppn)
     }
@@ -138,7 +154,18 @@ pub fn init_frame_allocator() {
     );
 }
 
+/// number of free frames
+pub fn free_frames() -> usize {
+    FRAME_ALLOCATOR.lock().last
+}
+
+/// number of frames managed by the allocator
+pub fn total_frames() -> usize {
+    FRAME_ALLOCATOR.lock().total
+}
+
 /// allocate frames
+#[track_caller]
 pub fn frames_alloc(size: usize) -> Option<FrameTracker> {
     FrameAllocator
         .alloc(size)
@@ -148,6 +175,7 @@ pub fn frames_alloc(size: usize) -> Option<FrameTracker> {
 }
 
 /// allocate frames and clean
+#[track_caller]
 pub fn frames_alloc_clean(size: usize) -> Option<FrameTracker> {
     frames_alloc(size).map(|f| {
         f.range_ppn.get_slice_mut::<u8>().fill(0);
@@ -156,6 +184,7 @@ pub fn frames_alloc_clean(size: usize) -> Option<FrameTracker> {
 }
 
 /// deallocate frames
+#[track_caller]
 pub fn frames_dealloc(range_ppn: Range<PhysPageNum>) {
     if range_ppn.clone().count() > 0 {
         FrameAllocator.dealloc(range_ppn);
@@ -0,0 +1,74 @@
+//! Frame allocator debugging, enabled by the `frame_debug` feature
+//!
+//! Every freed frame is filled with [`POISON`] and remembers where it was
+//! allocated and freed, so that freeing it twice panics with both places.
+//! With `frame_debug_check` a frame is also checked to still hold the poison
+//! when it is handed out again, which catches writes through stale pointers.
+
+use core::{ops::Range, panic::Location};
+
+use alloc::collections::btree_map::BTreeMap;
+use hal::addr::{PhysPageNum, RangePPNHal};
+
+use crate::sync::mutex::SpinNoIrqLock;
+
+/// byte pattern of a freed frame
+pub const POISON: u8 = 0x6b;
+
+type Site = &'static Location<'static>;
+
+/// what is known about a frame that was allocated at least once
+struct FrameState {
+    alloc: Site,
+    free: Option<Site>,
+}
+
+/// physical page number to its state
+static FRAMES: SpinNoIrqLock<BTreeMap<usize, FrameState>> = SpinNoIrqLock::new(BTreeMap::new());
+
+/// record `range_ppn` handed out at `site`, checking the poison of reused frames
+pub fn on_alloc(range_ppn: Range<PhysPageNum>, site: Site) {
+    let mut frames = FRAMES.lock();
+    for ppn in range_ppn.start.0..range_ppn.end.0 {
+        let Some(state) = frames.get_mut(&ppn) else {
+            frames.insert(ppn, FrameState { alloc: site, free: None });
+            continue;
+        };
+        if state.free.is_none() {
+            panic!(
+                "[FrameDebug] frame {:#x} allocated at {} while in use since {}",
+                ppn, site, state.alloc
+            );
+        }
+        #[cfg(feature = "frame_debug_check")]
+        {
+            let frame = PhysPageNum(ppn)..PhysPageNum(ppn + 1);
+            if let Some(pos) = frame.get_slice::<u8>().iter().position(|&b| b != POISON) {
+                panic!(
+                    "[FrameDebug] frame {:#x} written at byte {:#x} after free, allocated at {}, freed at {}, reused at {}",
+                    ppn, pos, state.alloc, state.free.unwrap(), site
+                );
+            }
+        }
+        state.alloc = site;
+        state.free = None;
+    }
+}
+
+/// record `range_ppn` freed at `site` and poison it,
+/// panic if any frame of it is not in use
+pub fn on_dealloc(range_ppn: Range<PhysPageNum>, site: Site) {
+    let mut frames = FRAMES.lock();
+    for ppn in range_ppn.start.0..range_ppn.end.0 {
+        match frames.get_mut(&ppn) {
+            Some(state) if state.free.is_none() => state.free = Some(site),
+            Some(state) => panic!(
+                "[FrameDebug] double free of frame {:#x}: allocated at {}, freed at {}, freed again at {}",
+                ppn, state.alloc, state.free.unwrap(), site
+            ),
+            None => panic!("[FrameDebug] free of frame {:#x} never allocated, at {}", ppn, site),
+        }
+    }
+    drop(frames);
+    range_ppn.get_slice_mut::<u8>().fill(POISON);
+}
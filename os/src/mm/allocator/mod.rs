@@ -1,9 +1,11 @@
 mod frame_allocator;
+#[cfg(feature = "frame_debug")]
+mod frame_debug;
 mod heap_allocator;
 mod slab_allocator;
 
 #[allow(unused)]
-pub use frame_allocator::{FrameAllocator, init_frame_allocator, frames_alloc, frames_alloc_clean, frames_dealloc};
+pub use frame_allocator::{FrameAllocator, init_frame_allocator, frames_alloc, frames_alloc_clean, frames_dealloc, free_frames, total_frames};
 #[allow(unused)]
 pub use heap_allocator::{handle_alloc_error, init_heap, HeapAllocator};
 #[allow(unused)]
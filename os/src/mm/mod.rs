@@ -68,4 +68,5 @@ pub fn init() {
     allocator::init_heap();
     allocator::init_frame_allocator();
     vm::KernVmSpaceHal::enable(KVMSPACE.lock().deref());
+    vm::asid::init();
 }
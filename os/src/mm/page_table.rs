@@ -3,7 +3,7 @@ use core::{cmp::min, ptr::slice_from_raw_parts_mut};
 use alloc::{string::String, vec::Vec};
 use hal::{addr::{PhysAddr, PhysAddrHal, PhysPageNumHal, VirtAddr, VirtAddrHal, VirtPageNumHal}, constant::{Constant, ConstantsHal}, pagetable::{PageTableEntryHal, PageTableHal}};
 
-use crate::mm::vm::{PageFaultAccessType, UserVmSpaceHal};
+use crate::{fs::secretmem::is_secret_page, mm::vm::{PageFaultAccessType, UserVmSpaceHal}};
 
 use super::{allocator::FrameAllocator, vm::UserVmSpace, PageTable};
 
@@ -74,17 +74,23 @@ pub unsafe fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T
         .get_mut()
 }
 
-/// translate user va by user_vm_space
+/// translate user va by user_vm_space,
+/// secret memory gives None since the kernel cannot reach it by physical address
 pub fn translate_uva_checked(user_vm_space: &mut UserVmSpace, va: VirtAddr, access_type: PageFaultAccessType) -> Option<PhysAddr> {
-    match user_vm_space.get_page_table().find_pte(va.floor()) {
+    let pa = match user_vm_space.get_page_table().find_pte(va.floor()) {
         Some((pte, _)) if access_type.can_access(pte.flags()) => {
-            Some(pte.ppn().start_addr() + va.page_offset())
+            // the pte may be a big page, let the page table add the offset
+            user_vm_space.translate_va(va).unwrap()
         }
         _ => {
             user_vm_space.handle_page_fault(va, access_type).ok()?;
-            Some(user_vm_space.translate_va(va).unwrap())
+            user_vm_space.translate_va(va).unwrap()
         }
+    };
+    if is_secret_page(pa.floor()) {
+        return None;
     }
+    Some(pa)
 }
 
 
@@ -0,0 +1,196 @@
+//! address space ids
+//!
+//! A user address space gets an ASID the first time it is switched to, so that the
+//! translations it leaves in the TLB survive switching away. ASID 0 belongs to the kernel.
+//! Ids of dropped address spaces are recycled. Once the ids run out the generation is
+//! bumped: every address space picks a new id at its next switch and every hart flushes
+//! its whole TLB before running a user address space of the new generation.
+//!
+//! Translations a page table drops are flushed on the hart dropping them, the other harts
+//! learn about them through `PageTableHal::take_stale` and flush the ASID at their next
+//! switch to that page table. Harts running the table right now would not switch, so once
+//! an operation on the table is done `shootdown` sends them the span of everything it
+//! dropped in a single request. A span left behind by an operation failing halfway goes
+//! out with the next one.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::vec::Vec;
+use hal::{addr::VirtPageNumHal, board::MAX_PROCESSORS, constant::{Constant, ConstantsHal}, instruction::{Instruction, InstructionHal}, pagetable::PageTableHal};
+
+use crate::{mm::PageTable, processor::processor::current_processor_id, sync::mutex::SpinNoIrqLock};
+
+/// generation of the ids handed out now, starts at 1 so that every hart flushes once
+static GENERATION: AtomicUsize = AtomicUsize::new(1);
+/// bumped whenever kernel translations are dropped
+static KERNEL_GENERATION: AtomicUsize = AtomicUsize::new(1);
+/// largest id the hardware takes, 0 without ASID support
+static MAX_ASID: AtomicUsize = AtomicUsize::new(0);
+/// times the ids ran out
+pub static ASID_ROLLOVERS: AtomicUsize = AtomicUsize::new(0);
+/// a larger span is shot down as the whole address space
+const SHOOTDOWN_MAX_PAGES: usize = 32;
+
+static ASID_ALLOCATOR: SpinNoIrqLock<AsidAllocator> = SpinNoIrqLock::new(AsidAllocator::new());
+
+/// the generations a hart has flushed its TLB for
+struct HartTlb {
+    generation: AtomicUsize,
+    kernel_generation: AtomicUsize,
+    /// root of the user page table the hart switched to last, it may still run it
+    root: AtomicUsize,
+    /// id the hart runs that page table under, a thread on another hart may have given
+    /// the table a new id since
+    asid: AtomicUsize,
+}
+
+static HART_TLB: [HartTlb; MAX_PROCESSORS] = [const {
+    HartTlb {
+        generation: AtomicUsize::new(0),
+        kernel_generation: AtomicUsize::new(0),
+        root: AtomicUsize::new(0),
+        asid: AtomicUsize::new(0),
+    }
+}; MAX_PROCESSORS];
+
+struct AsidAllocator {
+    /// smallest id not yet handed out in this generation
+    next: usize,
+    /// ids given back in this generation
+    recycled: Vec<u16>,
+}
+
+impl AsidAllocator {
+    const fn new() -> Self {
+        Self { next: 1, recycled: Vec::new() }
+    }
+
+    fn alloc(&mut self) -> usize {
+        if let Some(asid) = self.recycled.pop() {
+            return asid as usize;
+        }
+        if self.next > MAX_ASID.load(Ordering::Relaxed) {
+            GENERATION.fetch_add(1, Ordering::AcqRel);
+            ASID_ROLLOVERS.fetch_add(1, Ordering::Relaxed);
+            self.next = 1;
+            self.recycled.clear();
+        }
+        self.next += 1;
+        self.next - 1
+    }
+}
+
+/// find out how many ids the hardware takes
+pub fn init() {
+    let max = Instruction::max_asid().min(u16::MAX as usize);
+    MAX_ASID.store(max, Ordering::Relaxed);
+    // probing may have left entries under the largest id
+    unsafe { Instruction::tlb_flush_all() };
+    log::info!("[asid] {} address space ids", max);
+}
+
+/// switch this hart to `page_table`, giving it an id of the current generation first;
+/// `generation` is the generation its id was handed out in
+pub fn activate(page_table: &PageTable, generation: &AtomicUsize) {
+    let hart = current_processor_id();
+    // published before looking at the stale harts: either this hart sees what
+    // is dropped from now on there, or `shootdown` sees this hart
+    HART_TLB[hart].root.store(page_table.root_ppn.0, Ordering::SeqCst);
+    if MAX_ASID.load(Ordering::Relaxed) == 0 {
+        unsafe {
+            page_table.enable_low();
+            Instruction::tlb_flush_all();
+        }
+        return;
+    }
+    let mut current = GENERATION.load(Ordering::Acquire);
+    if page_table.asid() == 0 || generation.load(Ordering::Relaxed) != current {
+        let mut allocator = ASID_ALLOCATOR.lock();
+        // threads of the space may race here on several harts
+        current = GENERATION.load(Ordering::Acquire);
+        if page_table.asid() == 0 || generation.load(Ordering::Relaxed) != current {
+            page_table.set_asid(allocator.alloc());
+            current = GENERATION.load(Ordering::Acquire);
+            generation.store(current, Ordering::Relaxed);
+        }
+    }
+    let tlb = &HART_TLB[hart];
+    tlb.asid.store(page_table.asid(), Ordering::SeqCst);
+    let kernel = KERNEL_GENERATION.load(Ordering::Acquire);
+    let new_generation = tlb.generation.swap(current, Ordering::AcqRel) != current;
+    let kernel_unmapped = tlb.kernel_generation.swap(kernel, Ordering::AcqRel) != kernel;
+    unsafe {
+        if new_generation || kernel_unmapped {
+            Instruction::tlb_flush_all();
+            page_table.take_stale(hart);
+        } else if page_table.take_stale(hart) {
+            Instruction::tlb_flush_asid(page_table.asid());
+        }
+        page_table.enable_low();
+    }
+}
+
+/// give the id of a dropped page table back
+pub fn release(page_table: &PageTable, generation: &AtomicUsize) {
+    let asid = page_table.asid();
+    if asid == 0 {
+        return;
+    }
+    let mut allocator = ASID_ALLOCATOR.lock();
+    if generation.load(Ordering::Relaxed) == GENERATION.load(Ordering::Acquire) {
+        allocator.recycled.push(asid as u16);
+    }
+}
+
+/// kernel translations were dropped, each hart flushes its whole TLB before it next
+/// switches to a user address space
+pub fn kernel_unmapped() {
+    KERNEL_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+/// drop what `page_table` dropped since the last call from the other harts running it
+pub fn shootdown(page_table: &PageTable) {
+    let Some(range) = page_table.take_flushed() else {
+        return;
+    };
+    let harts = other_harts(Some(page_table));
+    if harts == 0 {
+        return;
+    }
+    let pages = range.end.0 - range.start.0;
+    let (start, len) = if pages > SHOOTDOWN_MAX_PAGES {
+        (0, usize::MAX)
+    } else {
+        (range.start.start_addr().0, pages * Constant::PAGE_SIZE)
+    };
+    // after a rollover a hart may still run the table under the id of the last
+    // generation, flushing the new id would leave those entries behind
+    let asid = page_table.asid();
+    let stale = (0..MAX_PROCESSORS)
+        .filter(|&h| harts & 1 << h != 0 && HART_TLB[h].asid.load(Ordering::SeqCst) != asid)
+        .fold(0, |mask, h| mask | 1 << h);
+    unsafe {
+        if harts & !stale != 0 {
+            Instruction::tlb_shootdown(harts & !stale, asid, start, len);
+        }
+        if stale != 0 {
+            Instruction::tlb_shootdown(stale, 0, 0, usize::MAX);
+        }
+    }
+}
+
+/// mask of the other harts that may be running `page_table` now,
+/// or any user address space at all when it is None
+pub fn other_harts(page_table: Option<&PageTable>) -> usize {
+    let hart = current_processor_id();
+    (0..MAX_PROCESSORS)
+        .filter(|&h| h != hart)
+        .filter(|&h| {
+            let root = HART_TLB[h].root.load(Ordering::SeqCst);
+            match page_table {
+                Some(page_table) => root == page_table.root_ppn.0,
+                None => root != 0,
+            }
+        })
+        .fold(0, |mask, h| mask | 1 << h)
+}
@@ -0,0 +1,65 @@
+//! commit accounting
+//!
+//! A private writable mapping reserves its size when it is created or copied by fork, even
+//! though its frames are only allocated at the first fault. Checking the reservations keeps
+//! running out of memory an ENOMEM from mmap or fork instead of a failed fault later.
+//! How strictly they are checked is set through /proc/sys/vm/overcommit_memory.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{mm::allocator::total_frames, syscall::SysError};
+
+/// refuse only a single reservation larger than the whole memory
+pub const OVERCOMMIT_GUESS: usize = 0;
+/// never refuse, MAP_NORESERVE mappings are not even counted
+pub const OVERCOMMIT_ALWAYS: usize = 1;
+/// refuse once all reservations together pass the commit limit, MAP_NORESERVE is ignored
+pub const OVERCOMMIT_NEVER: usize = 2;
+
+static OVERCOMMIT_MEMORY: AtomicUsize = AtomicUsize::new(OVERCOMMIT_GUESS);
+/// pages reserved now
+static COMMITTED: AtomicUsize = AtomicUsize::new(0);
+
+/// the overcommit mode
+pub fn mode() -> usize {
+    OVERCOMMIT_MEMORY.load(Ordering::Relaxed)
+}
+
+/// switch the overcommit mode, Err for an unknown one
+pub fn set_mode(mode: usize) -> Result<(), SysError> {
+    if mode > OVERCOMMIT_NEVER {
+        return Err(SysError::EINVAL);
+    }
+    OVERCOMMIT_MEMORY.store(mode, Ordering::Relaxed);
+    Ok(())
+}
+
+/// pages reserved now
+pub fn committed() -> usize {
+    COMMITTED.load(Ordering::Relaxed)
+}
+
+/// pages that may be reserved in strict mode, all of memory since there is no swap
+pub fn commit_limit() -> usize {
+    total_frames()
+}
+
+/// reserve `pages`, ENOMEM if the mode does not allow it
+pub fn account(pages: usize) -> Result<(), SysError> {
+    let committed = COMMITTED.fetch_add(pages, Ordering::AcqRel) + pages;
+    let allowed = match mode() {
+        OVERCOMMIT_ALWAYS => true,
+        OVERCOMMIT_NEVER => committed <= commit_limit(),
+        _ => pages <= commit_limit(),
+    };
+    if !allowed {
+        COMMITTED.fetch_sub(pages, Ordering::AcqRel);
+        return Err(SysError::ENOMEM);
+    }
+    Ok(())
+}
+
+/// give back a reservation made by `account`
+pub fn unaccount(pages: usize) {
+    COMMITTED.fetch_sub(pages, Ordering::AcqRel);
+}
@@ -6,7 +6,7 @@ use range_map::RangeMap;
 
 use crate::{fs::vfs::File, mm::{allocator::FrameAllocator, vm::KernVmAreaType, PageTable}};
 
-use super::super::{KernVmArea, KernVmSpaceHal, PageFaultAccessType, UserVmSpace, UserVmSpaceHal};
+use super::super::{asid, KernVmArea, KernVmSpaceHal, PageFaultAccessType, UserVmSpace, UserVmSpaceHal};
 
 /// Kernel's VmSpace
 pub struct KernVmSpace {
@@ -19,7 +19,6 @@ impl KernVmSpaceHal for KernVmSpace {
     fn enable(&self) {
         unsafe { 
             self.page_table.enable_high();
-            Instruction::tlb_flush_all();
         }
     }
 
@@ -85,6 +84,7 @@ impl KernVmSpaceHal for KernVmSpace {
         let (range, area) = self.areas.get_key_value_mut(va.floor()).ok_or(())?;
         area.unmap(&mut self.page_table);
         self.areas.force_remove_one(range);
+        asid::kernel_unmapped();
         Ok(())
     }
     
@@ -109,6 +109,14 @@ impl KernVmSpaceHal for KernVmSpace {
         }
     }
 
+    fn unmap_phys_page(&mut self, _ppn: PhysPageNum) -> Result<(), ()> {
+        // physical memory is reached through the direct mapped windows,
+        // which cannot leave a single page out
+        Err(())
+    }
+
+    fn remap_phys_page(&mut self, _ppn: PhysPageNum) {}
+
 }
 
 
@@ -6,7 +6,7 @@ use range_map::RangeMap;
 
 use crate::{fs::vfs::File, mm::{allocator::FrameAllocator, vm::KernVmAreaType, PageTable}};
 
-use super::super::{KernVmArea, KernVmSpaceHal, PageFaultAccessType, UserVmSpace, UserVmSpaceHal};
+use super::super::{asid, KernVmArea, KernVmSpaceHal, PageFaultAccessType, UserVmSpace, UserVmSpaceHal};
 
 /// Kernel's VmSpace
 pub struct KernVmSpace {
@@ -20,8 +20,22 @@ impl KernVmSpace {
         let ptes = self.page_table.root_ppn
             .start_addr().get_mut::<[PageTableEntry; Constant::PTES_PER_PAGE]>();
 
+        // with sv48 the 1G entries are one level down, under a root entry that is
+        // allocated here too so that every user page table shares it
+        #[cfg(feature = "sv48")]
+        let ptes = {
+            let idx = VirtAddr::from(Constant::KERNEL_VM_BOTTOM).floor().indexes()[0];
+            if !ptes[idx].is_valid() {
+                let range_ppn = FrameAllocator.alloc(1).unwrap();
+                range_ppn.get_slice_mut::<u8>().fill(0);
+                ptes[idx] = PageTableEntry::new(range_ppn.start, MapPerm::empty());
+                ptes[idx].set_valid(true);
+            }
+            ptes[idx].ppn().start_addr().get_mut::<[PageTableEntry; Constant::PTES_PER_PAGE]>()
+        };
+
         const HUGE_PAGES: usize = Constant::KERNEL_VM_SIZE / (Constant::PAGE_SIZE * 512 * 512);
-        const VM_START: usize = (Constant::KERNEL_VM_BOTTOM & ((1 << Constant::VA_WIDTH)-1)) / (Constant::PAGE_SIZE * 512 * 512);
+        const VM_START: usize = (Constant::KERNEL_VM_BOTTOM / (Constant::PAGE_SIZE * 512 * 512)) % 512;
         let range_ppn = FrameAllocator.alloc(HUGE_PAGES).unwrap();
         range_ppn.get_slice_mut::<u8>().fill(0);
         let ppn = range_ppn.start;
@@ -194,6 +208,7 @@ impl KernVmSpaceHal for KernVmSpace {
         let (range, area) = self.areas.get_key_value_mut(va.floor()).ok_or(())?;
         area.unmap(&mut self.page_table);
         self.areas.force_remove_one(range);
+        asid::kernel_unmapped();
         Ok(())
     }
 
@@ -218,6 +233,27 @@ impl KernVmSpaceHal for KernVmSpace {
         }
     }
 
+    fn unmap_phys_page(&mut self, ppn: PhysPageNum) -> Result<(), ()> {
+        let vpn = VirtPageNum(ppn.0 | (Constant::KERNEL_ADDR_SPACE.start >> Constant::PAGE_SIZE_BITS));
+        // only a 4K leaf can go without taking its neighbours along
+        match self.page_table.find_pte(vpn) {
+            Some((_, i)) if PageLevel::from(i) == PageLevel::Small => {}
+            _ => return Err(()),
+        }
+        self.page_table.unmap(vpn)?;
+        // the lower levels are shared by every user page table, other harts drop
+        // their stale entry on the next address space switch
+        unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0); }
+        asid::kernel_unmapped();
+        Ok(())
+    }
+
+    fn remap_phys_page(&mut self, ppn: PhysPageNum) {
+        let vpn = VirtPageNum(ppn.0 | (Constant::KERNEL_ADDR_SPACE.start >> Constant::PAGE_SIZE_BITS));
+        let _ = self.page_table.map(vpn, ppn, MapPerm::R | MapPerm::W, PageLevel::Small);
+        unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0); }
+    }
+
 }
 
 #[allow(missing_docs, unused)]
@@ -0,0 +1,115 @@
+//! NUMA memory policies
+//!
+//! There is a single memory node, node 0, so no policy ever moves a page.
+//! What a policy still decides is whether anonymous memory may be backed
+//! by big pages: an interleave policy spreads memory across its nodes one
+//! page at a time, and a big page puts 512 of them on the same node.
+//!
+//! The rules, in order:
+//! - a VMA policy set by mbind applies to its range,
+//! - otherwise the task policy set by set_mempolicy applies,
+//! - otherwise memory is allocated on the local node,
+//! - MADV_HUGEPAGE only asks for big pages, the policy that applies has to
+//!   allow them too; interleaving ones never do.
+
+use crate::syscall::SysError;
+
+/// default policy, fall back to the task policy or local allocation
+pub const MPOL_DEFAULT: i32 = 0;
+/// allocate on the preferred node first
+pub const MPOL_PREFERRED: i32 = 1;
+/// allocate only on the given nodes
+pub const MPOL_BIND: i32 = 2;
+/// spread pages over the given nodes
+pub const MPOL_INTERLEAVE: i32 = 3;
+/// allocate on the node of the cpu that faults
+pub const MPOL_LOCAL: i32 = 4;
+/// allocate on any of the preferred nodes first
+pub const MPOL_PREFERRED_MANY: i32 = 5;
+/// spread pages over the given nodes by their weights
+pub const MPOL_WEIGHTED_INTERLEAVE: i32 = 6;
+
+/// the nodes are not remapped when the allowed nodes change
+pub const MPOL_F_STATIC_NODES: i32 = 1 << 15;
+/// the nodes are relative to the allowed nodes
+pub const MPOL_F_RELATIVE_NODES: i32 = 1 << 14;
+/// balance pages within the bound nodes
+pub const MPOL_F_NUMA_BALANCING: i32 = 1 << 13;
+const MPOL_MODE_FLAGS: i32 = MPOL_F_STATIC_NODES | MPOL_F_RELATIVE_NODES | MPOL_F_NUMA_BALANCING;
+
+/// get_mempolicy: return the node instead of the policy
+pub const MPOL_F_NODE: u32 = 1 << 0;
+/// get_mempolicy: look up the policy at an address
+pub const MPOL_F_ADDR: u32 = 1 << 1;
+/// get_mempolicy: return the nodes the task may use
+pub const MPOL_F_MEMS_ALLOWED: u32 = 1 << 2;
+
+/// mbind: fail if existing pages break the policy
+pub const MPOL_MF_STRICT: u32 = 1 << 0;
+/// mbind: move the pages of the task that break the policy
+pub const MPOL_MF_MOVE: u32 = 1 << 1;
+/// mbind: move every page that breaks the policy
+pub const MPOL_MF_MOVE_ALL: u32 = 1 << 2;
+
+/// the nodes memory can be allocated on
+pub const NODES_ALLOWED: u64 = 1;
+
+/// a memory policy, with its mode flags kept in `mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemPolicy {
+    /// MPOL_* mode or'ed with its MPOL_F_* flags
+    pub mode: i32,
+    /// bit n set for node n
+    pub nodes: u64,
+}
+
+impl MemPolicy {
+    pub const DEFAULT: Self = Self { mode: MPOL_DEFAULT, nodes: 0 };
+
+    /// check `mode` and `nodes` as given to set_mempolicy or mbind,
+    /// nodes that do not exist are dropped
+    pub fn new(mode: i32, nodes: u64) -> Result<Self, SysError> {
+        let flags = mode & MPOL_MODE_FLAGS;
+        let base = mode & !MPOL_MODE_FLAGS;
+        if flags & MPOL_F_STATIC_NODES != 0 && flags & MPOL_F_RELATIVE_NODES != 0 {
+            return Err(SysError::EINVAL);
+        }
+        if flags & MPOL_F_NUMA_BALANCING != 0 && base != MPOL_BIND && base != MPOL_PREFERRED_MANY {
+            return Err(SysError::EINVAL);
+        }
+        match base {
+            MPOL_DEFAULT | MPOL_LOCAL => {
+                if nodes != 0 || flags != 0 {
+                    return Err(SysError::EINVAL);
+                }
+                Ok(Self { mode: base, nodes: 0 })
+            }
+            // no node to prefer means the local one
+            MPOL_PREFERRED if nodes == 0 => Ok(Self { mode: MPOL_LOCAL, nodes: 0 }),
+            MPOL_PREFERRED | MPOL_BIND | MPOL_INTERLEAVE | MPOL_PREFERRED_MANY | MPOL_WEIGHTED_INTERLEAVE => {
+                let nodes = nodes & NODES_ALLOWED;
+                if nodes == 0 {
+                    return Err(SysError::EINVAL);
+                }
+                Ok(Self { mode, nodes })
+            }
+            _ => Err(SysError::EINVAL),
+        }
+    }
+
+    /// the mode without its flags
+    pub fn base_mode(&self) -> i32 {
+        self.mode & !MPOL_MODE_FLAGS
+    }
+
+    /// whether anonymous memory under this policy may be backed by big pages
+    pub fn allows_huge(&self) -> bool {
+        !matches!(self.base_mode(), MPOL_INTERLEAVE | MPOL_WEIGHTED_INTERLEAVE)
+    }
+}
+
+impl Default for MemPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -8,6 +8,7 @@ use xmas_elf::{reader::Reader, ElfFile};
 use crate::{ipc::sysv, fs::vfs::File, sync::mutex::{spin_mutex::SpinMutex, MutexSupport}, syscall::{mm::MmapFlags, SysError, SysResult}, task::utils::AuxHeader};
 
 use super::{allocator::{FrameAllocator, SlabAllocator}, FrameTracker, PageTable};
+use mempolicy::MemPolicy;
 
 /// Type of Kernel's Virtual Memory Area
 #[derive(Debug, Clone, Copy,  PartialEq, Eq)]
@@ -44,6 +45,16 @@ pub enum UserVmAreaType {
 bitflags! {
     pub struct MapFlags: u8 {
         const SHARED = 1 << 0;
+        /// backed by secret memory, see `fs::secretmem`
+        const SECRET = 1 << 1;
+        /// anonymous faults may map a whole big page, see `sys_madvise`
+        const HUGEPAGE = 1 << 2;
+        /// the size is reserved in `commit` until the area is dropped
+        const ACCOUNT = 1 << 3;
+        /// sealed by `sys_mseal`, the area can no longer be changed or unmapped
+        const SEALED = 1 << 4;
+        /// locked by `sys_mlock`, its pages are kept resident and never reclaimed
+        const LOCKED = 1 << 5;
     }
 }
 
@@ -53,6 +64,9 @@ impl From<MmapFlags> for MapFlags {
         if value.contains(MmapFlags::MAP_SHARED) || value.contains(MmapFlags::MAP_SHARED_VALIDATE) {
             ret.insert(MapFlags::SHARED);
         }
+        if value.contains(MmapFlags::MAP_LOCKED) {
+            ret.insert(MapFlags::LOCKED);
+        }
         ret
     }
 }
@@ -69,7 +83,7 @@ impl PartialEq for UserVmFile {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::File(l0), Self::File(r0)) => l0.as_ref() as *const _ == r0.as_ref(),
-            (Self::Shm(l0), Self::Shm(r0)) => l0.get_id() == r0.get_id(),
+            (Self::Shm(l0), Self::Shm(r0)) => Arc::ptr_eq(l0, r0),
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
@@ -162,11 +176,21 @@ pub struct UserVmArea {
     pub offset: usize,
     /// length of file
     pub len: usize,
+    /// policy set by mbind, None to follow the task policy
+    pub policy: Option<MemPolicy>,
+}
+
+impl Drop for UserVmArea {
+    fn drop(&mut self) {
+        if self.map_flags.contains(MapFlags::ACCOUNT) {
+            commit::unaccount(self.range_vpn().count());
+        }
+    }
 }
 
 impl Debug for UserVmArea {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("UserVmArea").field("range_va", &self.range_va).field("vma_type", &self.vma_type).field("map_perm", &self.map_perm).field("file", &self.file).field("map_flags", &self.map_flags).field("offset", &self.offset).field("len", &self.len).finish()
+        f.debug_struct("UserVmArea").field("range_va", &self.range_va).field("vma_type", &self.vma_type).field("map_perm", &self.map_perm).field("file", &self.file).field("map_flags", &self.map_flags).field("offset", &self.offset).field("len", &self.len).field("policy", &self.policy).finish()
     }
 }
 
@@ -230,7 +254,8 @@ impl UserVmArea {
             file: UserVmFile::None,
             map_flags: MapFlags::empty(),
             offset: 0,
-            len: 0
+            len: 0,
+            policy: None,
         }
     }
 
@@ -250,7 +275,8 @@ impl UserVmArea {
             file,
             map_flags: flags.into(),
             offset,
-            len
+            len,
+            policy: None,
         }
     }
 
@@ -284,6 +310,13 @@ impl KernVmArea {
         vma_type: KernVmAreaType, 
         map_perm: MapPerm
     ) -> Self {
+        // kernel pages are the same under every asid, so their tlb entries are kept
+        // across switches; the user reachable trampoline stays per address space
+        let map_perm = if map_perm.contains(MapPerm::U) {
+            map_perm
+        } else {
+            map_perm | MapPerm::G
+        };
         Self {
             range_va,
             vma_type,
@@ -355,6 +388,12 @@ pub trait KernVmSpaceHal {
     fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr>;
 
     fn handle_page_fault(&mut self, va: VirtAddr, access_type: PageFaultAccessType) -> Result<(), ()>;
+
+    /// drop the direct mapping of a physical page, the kernel faults on touching it afterwards
+    fn unmap_phys_page(&mut self, ppn: PhysPageNum) -> Result<(), ()>;
+
+    /// restore a direct mapping dropped by `unmap_phys_page`
+    fn remap_phys_page(&mut self, ppn: PhysPageNum);
 }
 
 #[allow(missing_docs, unused)]
@@ -377,7 +416,7 @@ pub trait UserVmSpaceHal: Sized {
     fn from_elf<T: Reader + ?Sized>(elf: &ElfFile<'_, T>, elf_file: Option<Arc<dyn File>>) -> 
         Result<(Self, StackTop, EntryPoint, Vec<AuxHeader>), SysError>;
 
-    fn from_existed(uvm_space: &mut Self) -> Self;
+    fn from_existed(uvm_space: &mut Self) -> Result<Self, SysError>;
 
     /// warning: data must must be page-aligned
     fn push_area(&mut self, area: UserVmArea, data: Option<&[u8]>) -> &mut UserVmArea;
@@ -416,5 +455,11 @@ pub trait UserVmSpaceHal: Sized {
 mod uvm;
 pub use uvm::*;
 
+pub mod asid;
+
+pub mod commit;
+
+pub mod mempolicy;
+
 mod kvm;
 pub use kvm::*;
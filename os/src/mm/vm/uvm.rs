@@ -1,4 +1,4 @@
-use core::ops::{Deref, DerefMut, Range};
+use core::{ops::{Deref, DerefMut, Range}, sync::atomic::{AtomicUsize, Ordering}};
 
 use alloc::{collections::btree_map::BTreeMap, format, string::{String, ToString}, sync::Arc, vec::Vec};
 use hal::{addr::{PhysAddr, PhysAddrHal, PhysPageNum, PhysPageNumHal, RangePPNHal, VirtAddr, VirtAddrHal, VirtPageNum, VirtPageNumHal}, allocator::{FrameAllocatorHal, FrameAllocatorTrackerExt}, constant::{Constant, ConstantsHal}, instruction::{Instruction, InstructionHal}, pagetable::{MapPerm, PageLevel, PageTableEntry, PageTableEntryHal, PageTableHal, VpnPageRangeIter}, println, util::smart_point::StrongArc};
@@ -6,15 +6,31 @@ use log::info;
 use range_map::RangeMap;
 use xmas_elf::reader::Reader;
 
-use crate::{config::PAGE_SIZE, fs::{page, utils::FileReader, vfs::{dentry::global_find_dentry, inode::InodeMode, DentryState, File}, OpenFlags}, ipc::sysv::{self, ShmObj}, mm::{allocator::{frames_alloc, FrameAllocator, SlabAllocator}, vm, FrameTracker, PageTable, KVMSPACE}, sync::mutex::{spin_rw_mutex::SpinRwMutex, MutexSupport, SpinNoIrqLock}, syscall::{mm::MmapFlags, SysError, SysResult}, task::utils::{generate_early_auxv, AuxHeader, AT_BASE, AT_CLKTCK, AT_EGID, AT_ENTRY, AT_EUID, AT_FLAGS, AT_GID, AT_HWCAP, AT_NOTELF, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM, AT_PLATFORM, AT_RANDOM, AT_SECURE, AT_UID}, utils::{round_down_to_page, timer::TimerGuard}};
+use crate::{config::PAGE_SIZE, processor::processor::current_processor_id, fs::{page, secretmem::SecretMemFile, utils::FileReader, vfs::{dentry::global_find_dentry, inode::InodeMode, DentryState, File}, OpenFlags}, ipc::sysv::{self, ShmObj}, mm::{allocator::{frames_alloc, free_frames, FrameAllocator, SlabAllocator}, vm, FrameTracker, PageTable, KVMSPACE}, sync::mutex::{spin_rw_mutex::SpinRwMutex, MutexSupport, SpinNoIrqLock}, syscall::{mm::{MmapFlags, MCL_FUTURE}, SysError, SysResult}, task::utils::{generate_early_auxv, AuxHeader, AT_BASE, AT_CLKTCK, AT_EGID, AT_ENTRY, AT_EUID, AT_FLAGS, AT_GID, AT_HWCAP, AT_NOTELF, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM, AT_PLATFORM, AT_RANDOM, AT_SECURE, AT_UID}, utils::{round_down_to_page, timer::TimerGuard}};
 
-use super::{KernVmArea, KernVmAreaType, KernVmSpaceHal, MapFlags, MaxEndVpn, PageFaultAccessType, StartPoint, UserVmArea, UserVmAreaType, UserVmAreaView, UserVmFile, UserVmSpaceHal};
+use super::{asid, commit, mempolicy::MemPolicy, KernVmArea, KernVmAreaType, KernVmSpaceHal, MapFlags, MaxEndVpn, PageFaultAccessType, StartPoint, UserVmArea, UserVmAreaType, UserVmAreaView, UserVmFile, UserVmSpaceHal};
 
 /// User's VmSpace
 pub struct UserVmSpace {
     page_table: PageTable,
     areas: RangeMap<VirtPageNum, UserVmArea>,
-    brk: Range<VirtAddr>
+    brk: Range<VirtAddr>,
+    /// largest number of resident frames seen before pages were dropped
+    peak_rss: usize,
+    /// generation the ASID of the page table was handed out in
+    asid_generation: AtomicUsize,
+    /// task policy set by set_mempolicy, for the areas without one of their own;
+    /// shared by the threads of the process where Linux keeps one per thread
+    policy: MemPolicy,
+    /// membarrier registration commands issued in this space, a forked
+    /// or exec'ed process starts without any, as on Linux
+    membarrier: i32,
+    /// flags of the last mlockall, MCL_FUTURE locks the areas mapped later;
+    /// memory locks are not inherited by a forked or exec'ed process
+    mlockall: i32,
+    /// harts that have not fetched instructions since a local icache flush
+    /// of riscv_flush_icache, each of them flushes on its next activation
+    icache_stale: AtomicUsize,
 }
 
 impl UserVmSpace {
@@ -24,16 +40,30 @@ impl UserVmSpace {
             page_table: PageTable::new_in(0, FrameAllocator),
             areas: RangeMap::new(),
             brk: VirtAddr(0)..VirtAddr(0),
+            peak_rss: 0,
+            asid_generation: AtomicUsize::new(0),
+            policy: MemPolicy::DEFAULT,
+            membarrier: 0,
+            mlockall: 0,
+            icache_stale: AtomicUsize::new(0),
         }
     }
 
     pub fn enable(&self) {
-        unsafe {
-            self.get_page_table().enable_low();
-            Instruction::tlb_flush_all();
+        asid::activate(&self.page_table, &self.asid_generation);
+        let hart = 1 << current_processor_id();
+        if self.icache_stale.load(Ordering::Relaxed) & hart != 0 {
+            self.icache_stale.fetch_and(!hart, Ordering::Relaxed);
+            unsafe { Instruction::icache_flush(false) };
         }
     }
 
+    /// every hart but the current one has to flush its icache
+    /// before it runs this space again
+    pub fn mark_icache_stale(&self) {
+        self.icache_stale.store(!(1 << current_processor_id()), Ordering::Relaxed);
+    }
+
     pub fn get_page_table(&self) -> &PageTable {
         &self.page_table
     }
@@ -134,8 +164,8 @@ impl UserVmSpace {
         auxv.push(AuxHeader::new(AT_GID, 0 as usize));
         auxv.push(AuxHeader::new(AT_EGID, 0 as usize));
         auxv.push(AuxHeader::new(AT_PLATFORM, 0 as usize));
-        auxv.push(AuxHeader::new(AT_HWCAP, 0 as usize));
-        auxv.push(AuxHeader::new(AT_CLKTCK, 100 as usize));
+        auxv.push(AuxHeader::new(AT_HWCAP, hal::cpu::hwcap()));
+        auxv.push(AuxHeader::new(AT_CLKTCK, crate::timer::hz()));
         auxv.push(AuxHeader::new(AT_SECURE, 0 as usize));
         auxv.push(AuxHeader::new(AT_NOTELF, 0x112d as usize));
 
@@ -188,14 +218,17 @@ impl UserVmSpace {
             Some(heap) => heap.range_vpn(),
             None => {
                 if new_brk > self.brk.end {
-                    self.push_area(
-                        UserVmArea::new(
-                            self.brk.start..new_brk,
-                            UserVmAreaType::Heap,
-                            MapPerm::R | MapPerm::W | MapPerm::U,
-                        ), 
-                        None
+                    let mut heap = UserVmArea::new(
+                        self.brk.start..new_brk,
+                        UserVmAreaType::Heap,
+                        MapPerm::R | MapPerm::W | MapPerm::U,
                     );
+                    self.lock_if_future(&mut heap);
+                    let locked = heap.map_flags.contains(MapFlags::LOCKED);
+                    self.push_area(heap, None);
+                    if locked {
+                        let _ = self.populate(self.brk.start, new_brk.0 - self.brk.start.0);
+                    }
                     self.brk.end = new_brk;
                     return new_brk;
                 } else {
@@ -213,52 +246,84 @@ impl UserVmSpace {
                 Ok(_) => {
                     let heap = self.areas.get_mut(range.start).unwrap();
                     heap.range_va.end = new_brk;
+                    let locked = heap.map_flags.contains(MapFlags::LOCKED);
+                    let old_end = range.end.start_addr();
+                    if locked {
+                        let _ = self.populate(old_end, new_brk.0 - old_end.0);
+                    }
                     self.brk.end = new_brk;
                     return new_brk
                 }
                 Err(_) => return self.brk.end
             }
         } else if new_brk >= self.brk.start {
-            while let Some(range) = self.find_heap().map(|vma| vma.range_vpn()) {
-                let new_range = range.start..new_brk.ceil();
-                if range == new_range {
-                    self.brk.end = new_brk;
-                    return new_brk;
-                }
-                if new_range.start >= new_range.end {
-                    let heap = self.areas.force_remove_one(range);
-                    heap.unmap(&mut self.page_table);
-                    self.brk.end = new_brk.max(self.brk.start);
-                } else {
-                    match self.areas.reduce_back(new_range) {
-                        Ok(_) => {
-                            let heap = self.areas.get_mut(range.start).unwrap();
-                            let right = heap.split_off(new_brk.ceil());
-                            right.unmap(&mut self.page_table);
-                            self.brk.end = new_brk;
-                            return new_brk;
-                        }
-                        Err(_) => return self.brk.end
-                    }
-                }
+            if self.check_unsealed(new_brk, self.brk.end.0.saturating_sub(new_brk.0)).is_err() {
+                return self.brk.end;
             }
-            return self.brk.end;
+            self.note_rss();
+            let brk = self.shrink_heap(new_brk);
+            asid::shootdown(&self.page_table);
+            return brk;
         } else {
             return self.brk.end;
         }
     }
+
+    /// move the heap break down to `new_brk`, unmapping what is above it
+    fn shrink_heap(&mut self, new_brk: VirtAddr) -> VirtAddr {
+        while let Some(range) = self.find_heap().map(|vma| vma.range_vpn()) {
+            let new_range = range.start..new_brk.ceil();
+            if range == new_range {
+                self.brk.end = new_brk;
+                return new_brk;
+            }
+            if new_range.start >= new_range.end {
+                let heap = self.areas.force_remove_one(range);
+                heap.unmap(&mut self.page_table);
+                self.brk.end = new_brk.max(self.brk.start);
+            } else {
+                match self.areas.reduce_back(new_range) {
+                    Ok(_) => {
+                        let heap = self.areas.get_mut(range.start).unwrap();
+                        let right = heap.split_off(new_brk.ceil());
+                        right.unmap(&mut self.page_table);
+                        self.brk.end = new_brk;
+                        return new_brk;
+                    }
+                    Err(_) => return self.brk.end
+                }
+            }
+        }
+        self.brk.end
+    }
     
-    pub fn from_existed(uvm_space: &mut Self) -> Self {
+    /// copy the space for fork, ENOMEM if the reservations of the copy are refused
+    pub fn from_existed(uvm_space: &mut Self) -> Result<Self, SysError> {
         let mut ret = KVMSPACE.lock().to_user();
         ret.brk = uvm_space.brk.clone();
+        ret.policy = uvm_space.policy;
         for (_, area) in uvm_space.areas.iter_mut() {
-            if let Ok(new_area) =  area.clone_cow(&mut uvm_space.page_table) {
-                ret.push_area(new_area, None);
-            } else {
-                ret.push_area(area.clone(), None);
+            // secret memory stays with its owner
+            if area.map_flags.contains(MapFlags::SECRET) {
+                continue;
             }
+            let mut new_area = match area.clone_cow(&mut uvm_space.page_table) {
+                Ok(new_area) => new_area,
+                Err(_) => area.clone(),
+            };
+            new_area.map_flags.remove(MapFlags::LOCKED);
+            if new_area.map_flags.contains(MapFlags::ACCOUNT) {
+                if let Err(e) = commit::account(new_area.range_vpn().count()) {
+                    new_area.map_flags.remove(MapFlags::ACCOUNT);
+                    asid::shootdown(&uvm_space.page_table);
+                    return Err(e);
+                }
+            }
+            ret.push_area(new_area, None);
         }
-        ret
+        // threads of the parent must stop writing to the now shared frames
+        asid::shootdown(&uvm_space.page_table);
+        Ok(ret)
     }
     
     pub fn alloc_mmap_area(&mut self, va: VirtAddr, len: usize, perm: MapPerm, flags: MmapFlags, file: Arc<dyn File>, offset: usize) -> Result<VirtAddr, SysError> {
@@ -280,7 +345,12 @@ impl UserVmSpace {
         };
         let range_va = range.start.start_addr()..range.end.start_addr();
         let start = range_va.start;
-        let vma = UserVmArea::new_mmap(range_va, perm, flags, UserVmFile::File(file.clone()), offset, len);
+        let mut vma = UserVmArea::new_mmap(range_va, perm, flags, UserVmFile::File(file.clone()), offset, len);
+        if file.downcast_ref::<SecretMemFile>().is_some() {
+            vma.map_flags.insert(MapFlags::SECRET);
+        }
+        vma.reserve(flags)?;
+        self.lock_if_future(&mut vma);
         self.push_area(vma, None);
         Ok(start)
     }
@@ -309,10 +379,13 @@ impl UserVmSpace {
         let range_va = range.start.start_addr()..range.end.start_addr();
         let start = range_va.start;
         if let Some(shm) = shm {
-            let vma = UserVmArea::new_mmap(range_va.clone(), perm, flags, UserVmFile::Shm(shm), 0, len);
+            let mut vma = UserVmArea::new_mmap(range_va.clone(), perm, flags, UserVmFile::Shm(shm), 0, len);
+            self.lock_if_future(&mut vma);
             self.push_area(vma, None);
         } else {
-            let vma = UserVmArea::new_mmap(range_va.clone(), perm, flags, UserVmFile::None, range_va.start.0, len);
+            let mut vma = UserVmArea::new_mmap(range_va.clone(), perm, flags, UserVmFile::None, range_va.start.0, len);
+            vma.reserve(flags)?;
+            self.lock_if_future(&mut vma);
             self.push_area(vma, None);
         }
         Ok(start)
@@ -346,27 +419,24 @@ impl UserVmSpace {
     /// unmap one vma in the vpn range `va.floor()..(va+len).ceil()`
     /// return Err when no matched vma
     pub fn unmap(&mut self, va: VirtAddr, len: usize) -> Result<UserVmArea, SysError> {
+        self.note_rss();
         let vpn = va.floor();
         let pg_len = (va + len).ceil().0 - vpn.0;
         let _ = self.try_union(vpn, pg_len);
         
-        let mut mid: UserVmArea;
-        let old_range;
-        let new_range;
-        if let Some((range_vpn, front)) = self.areas.get_key_value_mut(vpn) {
-            mid = front.split_off(va.floor());
-            new_range = front.range_vpn();
-            old_range = range_vpn;
-        } else {
-            if let Some((range_vpn, front)) = self.areas.range_mut(vpn..vpn+pg_len).next() {
-                mid = front.split_off(va.floor());
-                new_range = front.range_vpn();
-                old_range = range_vpn;
-            } else {
-                log::warn!("[unmap] no matched area");
-                return Err(SysError::EINVAL);
-            }
-        }
+        // the area holding `vpn`, or else the first one starting inside the range;
+        // in the latter case the whole front of that area is unmapped
+        let Some(start) = self.areas
+            .get_key_value(vpn)
+            .or_else(|| self.areas.range(vpn..vpn + pg_len).next())
+            .map(|(range_vpn, _)| range_vpn.start)
+        else {
+            log::warn!("[unmap] no matched area");
+            return Err(SysError::EINVAL);
+        };
+        let (old_range, front) = self.areas.get_key_value_mut(start).unwrap();
+        let mut mid = front.split_off(vpn.max(start));
+        let new_range = front.range_vpn();
 
         if new_range.is_empty() {
             // front area is empty, remove it
@@ -388,20 +458,333 @@ impl UserVmSpace {
         }
         
         mid.unmap(&mut self.page_table);
+        asid::shootdown(&self.page_table);
 
         Ok(mid)
     }
     
+    /// change the permission of `va.floor()..(va+len).ceil()` to `perm`,
+    /// VMAs partially covered by the range are split first
+    pub fn mprotect(&mut self, va: VirtAddr, len: usize, perm: MapPerm) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        // every page in the range must belong to some VMA
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let area = self.areas.get(vpn).ok_or(SysError::EINVAL)?;
+            if area.map_flags.contains(MapFlags::SEALED) {
+                return Err(SysError::EPERM);
+            }
+            area.check_perm(perm)?;
+            vpn = area.range_vpn().end;
+        }
+
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let (old_range, area) = self.areas.get_key_value_mut(vpn).unwrap();
+            let end = old_range.end.min(range.end);
+            if area.map_perm == perm {
+                vpn = end;
+                continue;
+            }
+            let mut mid = if old_range.start < vpn {
+                let mid = area.split_off(vpn);
+                let _ = self.areas.reduce_back(old_range.start..vpn);
+                mid
+            } else {
+                self.areas.force_remove_one(old_range)
+            };
+            if end < mid.range_vpn().end {
+                let back = mid.split_off(end);
+                self.areas.try_insert(back.range_vpn(), back).map_err(|_| SysError::EFAULT)?;
+            }
+            mid.change_perm(&mut self.page_table, perm);
+            self.areas.try_insert(mid.range_vpn(), mid).map_err(|_| SysError::EFAULT)?;
+            vpn = end;
+        }
+        asid::shootdown(&self.page_table);
+        Ok(())
+    }
+
+    /// allow or forbid big pages for the anonymous memory in `va.floor()..(va+len).ceil()`,
+    /// VMAs partially covered by the range are split first
+    pub fn set_hugepage(&mut self, va: VirtAddr, len: usize, huge: bool) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        let mut vpn = range.start;
+        while vpn < range.end {
+            vpn = self.areas.get(vpn).ok_or(SysError::ENOMEM)?.range_vpn().end;
+        }
+
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let (old_range, area) = self.areas.get_key_value_mut(vpn).unwrap();
+            let end = old_range.end.min(range.end);
+            if area.map_flags.contains(MapFlags::HUGEPAGE) == huge || area.file.is_some() {
+                vpn = end;
+                continue;
+            }
+            let mut mid = if old_range.start < vpn {
+                let mid = area.split_off(vpn);
+                let _ = self.areas.reduce_back(old_range.start..vpn);
+                mid
+            } else {
+                self.areas.force_remove_one(old_range)
+            };
+            if end < mid.range_vpn().end {
+                let back = mid.split_off(end);
+                self.areas.try_insert(back.range_vpn(), back).map_err(|_| SysError::EFAULT)?;
+            }
+            mid.map_flags.set(MapFlags::HUGEPAGE, huge);
+            self.areas.try_insert(mid.range_vpn(), mid).map_err(|_| SysError::EFAULT)?;
+            vpn = end;
+        }
+        Ok(())
+    }
+
+    /// seal the areas in `va.floor()..(va+len).ceil()` for good,
+    /// VMAs partially covered by the range are split first
+    pub fn seal(&mut self, va: VirtAddr, len: usize) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        let mut vpn = range.start;
+        while vpn < range.end {
+            vpn = self.areas.get(vpn).ok_or(SysError::ENOMEM)?.range_vpn().end;
+        }
+
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let (old_range, area) = self.areas.get_key_value_mut(vpn).unwrap();
+            let end = old_range.end.min(range.end);
+            if area.map_flags.contains(MapFlags::SEALED) {
+                vpn = end;
+                continue;
+            }
+            let mut mid = if old_range.start < vpn {
+                let mid = area.split_off(vpn);
+                let _ = self.areas.reduce_back(old_range.start..vpn);
+                mid
+            } else {
+                self.areas.force_remove_one(old_range)
+            };
+            if end < mid.range_vpn().end {
+                let back = mid.split_off(end);
+                self.areas.try_insert(back.range_vpn(), back).map_err(|_| SysError::EFAULT)?;
+            }
+            mid.map_flags.insert(MapFlags::SEALED);
+            self.areas.try_insert(mid.range_vpn(), mid).map_err(|_| SysError::EFAULT)?;
+            vpn = end;
+        }
+        Ok(())
+    }
+
+    /// EPERM if some area touching `va.floor()..(va+len).ceil()` is sealed
+    pub fn check_unsealed(&self, va: VirtAddr, len: usize) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        if range.is_empty() {
+            return Ok(());
+        }
+        let first = self.areas.get(range.start);
+        let sealed = first
+            .into_iter()
+            .chain(self.areas.range(range).map(|(_, area)| area))
+            .any(|area| area.map_flags.contains(MapFlags::SEALED));
+        if sealed {
+            return Err(SysError::EPERM);
+        }
+        Ok(())
+    }
+
+    /// lock or unlock the areas in `va.floor()..(va+len).ceil()`,
+    /// VMAs partially covered by the range are split first;
+    /// ENOMEM if some page of the range is not mapped
+    pub fn set_locked(&mut self, va: VirtAddr, len: usize, locked: bool) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        let mut vpn = range.start;
+        while vpn < range.end {
+            vpn = self.areas.get(vpn).ok_or(SysError::ENOMEM)?.range_vpn().end;
+        }
+
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let (old_range, area) = self.areas.get_key_value_mut(vpn).unwrap();
+            let end = old_range.end.min(range.end);
+            if area.map_flags.contains(MapFlags::LOCKED) == locked {
+                vpn = end;
+                continue;
+            }
+            let mut mid = if old_range.start < vpn {
+                let mid = area.split_off(vpn);
+                let _ = self.areas.reduce_back(old_range.start..vpn);
+                mid
+            } else {
+                self.areas.force_remove_one(old_range)
+            };
+            if end < mid.range_vpn().end {
+                let back = mid.split_off(end);
+                self.areas.try_insert(back.range_vpn(), back).map_err(|_| SysError::EFAULT)?;
+            }
+            mid.map_flags.set(MapFlags::LOCKED, locked);
+            self.areas.try_insert(mid.range_vpn(), mid).map_err(|_| SysError::EFAULT)?;
+            vpn = end;
+        }
+        Ok(())
+    }
+
+    /// lock or unlock every area of the space
+    pub fn set_all_locked(&mut self, locked: bool) {
+        for (_, area) in self.areas.iter_mut() {
+            area.map_flags.set(MapFlags::LOCKED, locked);
+        }
+    }
+
+    /// fault in the pages of every locked area but the stack,
+    /// which is only faulted in as it grows
+    pub fn populate_locked(&mut self) -> Result<(), SysError> {
+        let ranges: Vec<Range<VirtAddr>> = self.areas
+            .iter()
+            .filter(|(_, vma)| vma.map_flags.contains(MapFlags::LOCKED) && vma.vma_type != UserVmAreaType::Stack)
+            .map(|(_, vma)| vma.range_va.clone())
+            .collect();
+        for range in ranges {
+            self.populate(range.start, range.end.0 - range.start.0)?;
+        }
+        Ok(())
+    }
+
+    /// number of pages locked in the space, the stack counts what it has faulted in
+    pub fn locked_pages(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|(_, vma)| vma.map_flags.contains(MapFlags::LOCKED))
+            .map(|(_, vma)| vma.locked_size())
+            .sum()
+    }
+
+    /// number of pages locking `va.floor()..(va+len).ceil()` would add to `locked_pages`
+    pub fn unlocked_pages(&self, va: VirtAddr, len: usize) -> usize {
+        let range = va.floor()..(va + len).ceil();
+        self.areas
+            .iter()
+            .filter(|(_, vma)| !vma.map_flags.contains(MapFlags::LOCKED))
+            .map(|(_, vma)| {
+                let vma_range = vma.range_vpn();
+                (vma_range.start.max(range.start)..vma_range.end.min(range.end)).count()
+            })
+            .sum()
+    }
+
+    /// number of pages locking every area would make `locked_pages`
+    pub fn lockable_pages(&self) -> usize {
+        self.areas.iter().map(|(_, vma)| vma.locked_size()).sum()
+    }
+
+    /// flags of the last mlockall
+    pub fn mlockall_flags(&self) -> i32 {
+        self.mlockall
+    }
+
+    pub fn set_mlockall_flags(&mut self, flags: i32) {
+        self.mlockall = flags;
+    }
+
+    /// lock a new area if mlockall(MCL_FUTURE) asked for it
+    fn lock_if_future(&self, vma: &mut UserVmArea) {
+        if self.mlockall & MCL_FUTURE != 0 {
+            vma.map_flags.insert(MapFlags::LOCKED);
+        }
+    }
+
+    /// the membarrier registration commands issued so far
+    pub fn membarrier_registrations(&self) -> i32 {
+        self.membarrier
+    }
+
+    pub fn register_membarrier(&mut self, cmd: i32) {
+        self.membarrier |= cmd;
+    }
+
+    /// the task policy
+    pub fn task_policy(&self) -> MemPolicy {
+        self.policy
+    }
+
+    pub fn set_task_policy(&mut self, policy: MemPolicy) {
+        self.policy = policy;
+    }
+
+    /// the policy at `va`: the policy of its area, or the task policy if the area has none
+    pub fn policy_at(&self, va: VirtAddr) -> Option<MemPolicy> {
+        let area = self.areas.get(va.floor())?;
+        Some(area.policy.unwrap_or(self.policy))
+    }
+
+    /// set the policy of the areas in `va.floor()..(va+len).ceil()`, None to follow the task policy,
+    /// VMAs partially covered by the range are split first; big pages the policy
+    /// does not allow are split into base pages
+    pub fn set_area_policy(&mut self, va: VirtAddr, len: usize, policy: Option<MemPolicy>) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        let mut vpn = range.start;
+        while vpn < range.end {
+            vpn = self.areas.get(vpn).ok_or(SysError::EFAULT)?.range_vpn().end;
+        }
+
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let (old_range, area) = self.areas.get_key_value_mut(vpn).unwrap();
+            let end = old_range.end.min(range.end);
+            if area.policy == policy {
+                vpn = end;
+                continue;
+            }
+            let mut mid = if old_range.start < vpn {
+                let mid = area.split_off(vpn);
+                let _ = self.areas.reduce_back(old_range.start..vpn);
+                mid
+            } else {
+                self.areas.force_remove_one(old_range)
+            };
+            if end < mid.range_vpn().end {
+                let back = mid.split_off(end);
+                self.areas.try_insert(back.range_vpn(), back).map_err(|_| SysError::EFAULT)?;
+            }
+            mid.policy = policy;
+            self.areas.try_insert(mid.range_vpn(), mid).map_err(|_| SysError::EFAULT)?;
+            vpn = end;
+        }
+
+        if !policy.unwrap_or(self.policy).allows_huge() {
+            let count = PageLevel::Big.page_count();
+            for vpn in range.step_by(count) {
+                self.page_table.split_leaf(vpn);
+            }
+            asid::shootdown(&self.page_table);
+        }
+        Ok(())
+    }
+
     pub fn check_free(&self, va: VirtAddr, len: usize) -> Result<(), ()> {
         let range = va.floor()..(va+len).ceil();
         self.areas.is_range_free(range)
     }
+
+    /// check whether every page of the range belongs to some VMA
+    pub fn check_mapped(&self, va: VirtAddr, len: usize) -> Result<(), ()> {
+        let range = va.floor()..(va+len).ceil();
+        let mut vpn = range.start;
+        while vpn < range.end {
+            vpn = self.areas.get(vpn).ok_or(())?.range_vpn().end;
+        }
+        Ok(())
+    }
     
     pub fn get_area_view(&self, va: VirtAddr) -> Option<UserVmAreaView> {
         let area = self.areas.get(va.floor())?;
         Some(area.into())
     }
 
+    /// views of all the areas, in address order
+    pub fn area_views(&self) -> Vec<UserVmAreaView> {
+        self.areas.iter().map(|(_, area)| area.into()).collect()
+    }
+
     pub fn get_area_mut(&mut self, va: VirtAddr) -> Option<&mut UserVmArea> {
         self.areas.get_mut(va.floor())
     }
@@ -413,7 +796,9 @@ impl UserVmSpace {
     pub fn handle_page_fault(&mut self, va: VirtAddr, access_type: super::PageFaultAccessType) -> Result<(), ()> {
         let vpn = va.floor();
         if let Some(area) = self.areas.get_mut(va.floor()) {
-            area.handle_page_fault(&mut self.page_table, vpn, access_type)
+            let ret = area.handle_page_fault(&mut self.page_table, vpn, access_type, self.policy);
+            asid::shootdown(&self.page_table);
+            ret
         } else {
             // log::error!("[handle_page_fault] va: {va:?}, no matched vma");
             return Err(());
@@ -465,7 +850,7 @@ impl UserVmSpace {
             if let Some(area) = self.areas.get_mut(vpn) {
                 for vpn in vpn..end.min(area.range_vpn().end) {
                     if !area.access_no_fault(vpn, access_type) {
-                        area.handle_page_fault(&mut self.page_table, vpn, access_type)?;
+                        area.handle_page_fault(&mut self.page_table, vpn, access_type, self.policy)?;
                     }
                 }
                 vpn = area.range_vpn().end;
@@ -473,6 +858,7 @@ impl UserVmSpace {
                 return Err(())
             }
         }
+        asid::shootdown(&self.page_table);
         return Ok(());
     }
 
@@ -524,9 +910,10 @@ impl UserVmSpace {
             if let Some(area) = vm.areas.get_mut(vpn) {
                 for vpn in vpn..end.min(area.range_vpn().end) {
                     if !area.access_no_fault(vpn, access_type) {
-                        area.handle_page_fault(&mut vm.page_table, vpn, access_type)?;
+                        area.handle_page_fault(&mut vm.page_table, vpn, access_type, vm.policy)?;
                     }
                 }
+                asid::shootdown(&vm.page_table);
             } else {
                 return Err(())
             }
@@ -534,6 +921,84 @@ impl UserVmSpace {
         return Ok(());
     }
 
+    /// fault in every page of `va.floor()..(va+len).ceil()` up front, as for MAP_POPULATE;
+    /// writable pages are faulted for write so that private ones get their own frame,
+    /// file pages past the end of the file are left alone
+    pub fn populate(&mut self, va: VirtAddr, len: usize) -> Result<(), SysError> {
+        let range = va.floor()..(va + len).ceil();
+        // do not start what can not be finished
+        if range.clone().count() > free_frames() {
+            return Err(SysError::ENOMEM);
+        }
+        let mut vpn = range.start;
+        while vpn < range.end {
+            let area = self.areas.get_mut(vpn).ok_or(SysError::ENOMEM)?;
+            let access_type = if area.map_perm.contains(MapPerm::W) {
+                PageFaultAccessType::WRITE
+            } else if area.map_perm.intersects(MapPerm::R | MapPerm::X) {
+                PageFaultAccessType::READ
+            } else {
+                vpn = area.range_vpn().end;
+                continue;
+            };
+            let mut end = area.range_vpn().end.min(range.end);
+            if let UserVmFile::File(file) = &area.file {
+                let size = file.inode().map_or(0, |inode| inode.getattr().st_size as usize);
+                let pages = (size.saturating_sub(area.offset) + Constant::PAGE_SIZE - 1) / Constant::PAGE_SIZE;
+                end = end.min(area.range_vpn().start + pages);
+            }
+            for vpn in vpn..end {
+                if !area.access_no_fault(vpn, access_type) {
+                    area.handle_page_fault(&mut self.page_table, vpn, access_type, self.policy)
+                        .map_err(|_| SysError::ENOMEM)?;
+                }
+            }
+            vpn = area.range_vpn().end;
+        }
+        asid::shootdown(&self.page_table);
+        Ok(())
+    }
+
+    /// take a reference on the frames behind the pages of `va..va+len` for
+    /// vmsplice with SPLICE_F_GIFT; the pages turn copy on write, so that the
+    /// owner writing to them later does not change what was gifted.
+    /// Only private pages can be gifted, EINVAL tells the caller to copy
+    pub fn gift_pages(&mut self, va: VirtAddr, len: usize) -> Result<Vec<StrongArc<FrameTracker>>, SysError> {
+        self.ensure_access(va, len, PageFaultAccessType::READ).map_err(|_| SysError::EFAULT)?;
+        let mut frames = Vec::new();
+        for vpn in va.floor()..(va + len).ceil() {
+            let area = self.areas.get_mut(vpn).ok_or(SysError::EFAULT)?;
+            if area.map_flags.contains(MapFlags::SHARED) {
+                return Err(SysError::EINVAL);
+            }
+            self.page_table.split_leaf(vpn);
+            let frame = area.frames.get(&vpn).ok_or(SysError::EINVAL)?;
+            if let Some((pte, _)) = self.page_table.find_pte(vpn) {
+                if pte.is_valid() && pte.is_writable() {
+                    pte.set_writable(false);
+                    pte.set_cow(true);
+                    self.page_table.flush_vpn(vpn);
+                }
+            }
+            frames.push(frame.clone());
+        }
+        asid::shootdown(&self.page_table);
+        Ok(frames)
+    }
+
+    /// a byte for each page of `va.floor()..(va+len).ceil()`, 1 if the page is resident;
+    /// ENOMEM if some page of the range is not mapped
+    pub fn residency(&self, va: VirtAddr, len: usize) -> Result<Vec<u8>, SysError> {
+        self.check_mapped(va, len).map_err(|_| SysError::ENOMEM)?;
+        let range = va.floor()..(va + len).ceil();
+        Ok(range
+            .map(|vpn| {
+                let resident = self.page_table.find_pte(vpn).map_or(false, |(pte, _)| pte.is_valid());
+                resident as u8
+            })
+            .collect())
+    }
+
     pub fn translate_vpn(&self, vpn: VirtPageNum) -> Option<PhysPageNum> {
         self.get_page_table().translate_vpn(vpn)
     }
@@ -543,6 +1008,7 @@ impl UserVmSpace {
     }
 
     pub fn clear(&mut self) {
+        self.note_rss();
         self.areas.iter_mut().for_each(|(_, vma)| {
             vma.frames.clear();
         });
@@ -550,6 +1016,65 @@ impl UserVmSpace {
 }
 
 impl UserVmSpace {
+    /// number of frames mapped in the space
+    pub fn rss(&self) -> usize {
+        self.areas
+            .iter()
+            .map(|(_, vma)| vma.frames.values().filter(|frame| !is_zero_page(frame)).count())
+            .sum()
+    }
+
+    /// number of pages the areas of the space cover, mapped or not
+    pub fn vm_size(&self) -> usize {
+        self.areas
+            .iter()
+            .map(|(_, vma)| vma.range_vpn().count())
+            .sum()
+    }
+
+    /// the areas in the order of their addresses
+    pub fn areas(&self) -> impl Iterator<Item = &UserVmArea> {
+        self.areas.iter().map(|(_, vma)| vma)
+    }
+
+    /// number of frames mapped in the areas shared with others:
+    /// shared mappings and the pages of a file
+    pub fn shared_rss(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|(_, vma)| vma.map_flags.contains(MapFlags::SHARED) || vma.file != UserVmFile::None)
+            .map(|(_, vma)| vma.frames.values().filter(|frame| !is_zero_page(frame)).count())
+            .sum()
+    }
+
+    /// number of pages of the executable areas
+    pub fn text_size(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|(_, vma)| vma.map_perm.contains(MapPerm::X))
+            .map(|(_, vma)| vma.range_vpn().count())
+            .sum()
+    }
+
+    /// number of pages of the private writable areas, the stack included
+    pub fn data_size(&self) -> usize {
+        self.areas
+            .iter()
+            .filter(|(_, vma)| vma.map_perm.contains(MapPerm::W) && !vma.map_flags.contains(MapFlags::SHARED))
+            .map(|(_, vma)| vma.range_vpn().count())
+            .sum()
+    }
+
+    /// largest number of frames the space has held,
+    /// only sampled before frames are dropped since the count only grows in between
+    pub fn peak_rss(&self) -> usize {
+        self.peak_rss.max(self.rss())
+    }
+
+    fn note_rss(&mut self) {
+        self.peak_rss = self.peak_rss();
+    }
+
     fn find_heap(&mut self) -> Option<&mut UserVmArea> {
         self.areas.get_mut(self.brk.end.ceil() - 1).and_then(|vma| {
             if vma.vma_type != UserVmAreaType::Heap {
@@ -614,10 +1139,15 @@ impl UserVmSpace {
 
 impl Drop for UserVmSpace {
     fn drop(&mut self) {
+        // the last user of the space is gone, shared file mappings end here
+        for (_, area) in self.areas.iter() {
+            area.write_back(&self.page_table);
+        }
         // if this page table is using, switch to KVMSPACE
         if self.page_table.enabled() {
             KVMSPACE.lock().enable();
         }
+        asid::release(&self.page_table, &self.asid_generation);
     }
 }
 
@@ -628,6 +1158,14 @@ impl UserVmArea {
         self.range_va.start.floor()..self.range_va.end.ceil()
     }
 
+    /// pages the area counts for once locked: the stack only what it has faulted in
+    fn locked_size(&self) -> usize {
+        match self.vma_type {
+            UserVmAreaType::Stack => self.frames.len(),
+            _ => self.range_vpn().count(),
+        }
+    }
+
     fn copy_data(&mut self, page_table: &PageTable, data: &[u8], pg_offset: usize) {
         let mut range = self.range_vpn();
         range.start += pg_offset;
@@ -665,8 +1203,8 @@ impl UserVmArea {
             file: self.file.clone(),
             offset: new_offset,
             map_flags: self.map_flags,
-            len: new_len
-
+            len: new_len,
+            policy: self.policy,
         };
         self.range_va = self.range_va.start..p.start_addr();
         ret
@@ -680,6 +1218,10 @@ impl UserVmArea {
     }
 
     fn map(&mut self, page_table: &mut PageTable) {
+        // PROT_NONE keeps its frames unmapped, as in change_perm
+        if !self.map_perm.intersects(MapPerm::R | MapPerm::W | MapPerm::X) {
+            return;
+        }
         for (&vpn, frame) in self.frames.iter() {
             let pte = page_table
                 .map(vpn, frame.range_ppn.start, self.map_perm, PageLevel::Small)
@@ -687,25 +1229,132 @@ impl UserVmArea {
             if frame.get_owners() > 1 && !self.map_flags.contains(MapFlags::SHARED) {
                 pte.set_writable(false);
                 pte.set_dirty(false);
+                pte.set_cow(self.map_perm.contains(MapPerm::W));
             }
         }
     }
 
     fn unmap(&self, page_table: &mut PageTable) {
+        self.write_back(page_table);
         for &vpn in self.frames.keys() {
+            // the big page may reach out of this area
+            page_table.split_leaf(vpn);
             page_table.unmap(vpn);
-            unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0); }
+            page_table.flush_vpn(vpn);
+        }
+    }
+
+    /// hand what was written through a shared file mapping to the file:
+    /// stores through a writable PTE never fault, so only the dirty bits
+    /// of the page table tell which cached pages they changed
+    fn write_back(&self, page_table: &PageTable) {
+        let UserVmFile::File(file) = &self.file else {
+            return;
+        };
+        if !self.map_flags.contains(MapFlags::SHARED) {
+            return;
+        }
+        let Ok(inode) = file.inode() else {
+            return;
+        };
+        let Some(cache) = inode.cache() else {
+            return;
+        };
+        let start = self.range_vpn().start;
+        let mut dirty = false;
+        for &vpn in self.frames.keys() {
+            match page_table.find_pte(vpn) {
+                Some((pte, _)) if pte.is_valid() && pte.is_dirty() => {}
+                _ => continue,
+            }
+            let offset = self.offset + (vpn.0 - start.0) * Constant::PAGE_SIZE;
+            if let Some(page) = cache.get_page(offset) {
+                page.set_dirty();
+                dirty = true;
+            }
+        }
+        // as fsync would, file systems living in memory have nothing to do
+        if dirty {
+            if let Err(e) = inode.sync(true) {
+                log::warn!("[write_back] lost writes to a shared mapping: {:?}", e);
+            }
+        }
+    }
+
+    /// check whether the backing file allows the new permission
+    fn check_perm(&self, perm: MapPerm) -> Result<(), SysError> {
+        let UserVmFile::File(file) = &self.file else {
+            return Ok(());
+        };
+        if perm.contains(MapPerm::W) && self.map_flags.contains(MapFlags::SHARED) && !file.writable() {
+            return Err(SysError::EACCES);
+        }
+        if perm.contains(MapPerm::X) {
+            let mode = file.inode()?.inode_inner().mode();
+            let exec = InodeMode::OWNER_EXEC | InodeMode::GROUP_EXEC | InodeMode::OTHER_EXEC;
+            // inodes without any permission bits are not restricted
+            let perm_mask = InodeMode::OWNER_MASK | InodeMode::GROUP_MASK | InodeMode::OTHER_MASK;
+            if mode.intersects(perm_mask) && !mode.intersects(exec) {
+                return Err(SysError::EACCES);
+            }
+        }
+        Ok(())
+    }
+
+    /// reserve the size of a private writable mapping in `commit`,
+    /// MAP_NORESERVE skips it unless overcommit is off
+    fn reserve(&mut self, flags: MmapFlags) -> Result<(), SysError> {
+        if self.map_flags.contains(MapFlags::SHARED) || !self.map_perm.contains(MapPerm::W) {
+            return Ok(());
+        }
+        if flags.contains(MmapFlags::MAP_NORESERVE) && commit::mode() != commit::OVERCOMMIT_NEVER {
+            return Ok(());
+        }
+        commit::account(self.range_vpn().count())?;
+        self.map_flags.insert(MapFlags::ACCOUNT);
+        Ok(())
+    }
+
+    /// rewrite the permission of every mapped page
+    fn change_perm(&mut self, page_table: &mut PageTable, perm: MapPerm) {
+        self.map_perm = perm;
+        let shared = self.map_flags.contains(MapFlags::SHARED);
+        // PROT_NONE: a valid pte without R/W/X would be taken as a directory,
+        // so drop the pte but keep the frame for later mprotect
+        let accessible = perm.intersects(MapPerm::R | MapPerm::W | MapPerm::X);
+        for (&vpn, frame) in self.frames.iter() {
+            let mut new_perm = perm;
+            // frames still shared with others must fault on write to be copied
+            let cow = !shared && frame.get_owners() > 1 && perm.contains(MapPerm::W);
+            if cow {
+                new_perm.remove(MapPerm::W);
+            }
+            page_table.split_leaf(vpn);
+            let mapped = page_table.find_pte(vpn).map_or(false, |(pte, _)| pte.is_valid());
+            if !accessible {
+                if mapped {
+                    let _ = page_table.unmap(vpn);
+                }
+            } else if mapped {
+                let (pte, _) = page_table.find_pte(vpn).unwrap();
+                pte.set_flags(new_perm);
+                pte.set_cow(cow);
+            } else if let Ok(pte) = page_table.map(vpn, frame.range_ppn.start, new_perm, PageLevel::Small) {
+                pte.set_cow(cow);
+            }
+            page_table.flush_vpn(vpn);
         }
     }
 
     fn clone_cow(&mut self, page_table: &mut PageTable) -> Result<Self, ()> {
         if !self.map_flags.contains(MapFlags::SHARED) && self.map_perm.contains(MapPerm::W) {
-            /// update flag bit
+            // both sides share the frames read-only, the first write copies
             for &vpn in self.frames.keys() {
                 let (pte, _) = page_table.find_pte(vpn).unwrap();
                 pte.set_writable(false);
                 pte.set_dirty(false);
-                unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0); }
+                pte.set_cow(true);
+                page_table.flush_vpn(vpn);
             }
         }
         Ok(Self {
@@ -716,19 +1365,26 @@ impl UserVmArea {
             file: self.file.clone(),
             map_flags: self.map_flags.clone(),
             offset: self.offset,
-            len: self.len
+            len: self.len,
+            policy: self.policy,
         })
     }
 
-    pub fn extend(&mut self, size: usize) {
+    /// grow the area by `size` bytes, ENOMEM if the reservation for them is refused
+    pub fn extend(&mut self, size: usize) -> Result<(), SysError> {
         if size == 0 {
-            return;
+            return Ok(());
+        }
+        if self.map_flags.contains(MapFlags::ACCOUNT) {
+            let end = (self.range_va.end + size).ceil();
+            commit::account(end.0 - self.range_vpn().end.0)?;
         }
         self.range_va.end += size;
         self.range_va.end = self.range_va.end.ceil().start_addr();
         if self.file.is_some() {
             self.len += size;
         }
+        Ok(())
     }
 
     pub fn shrink(&mut self, size: usize) {
@@ -748,10 +1404,12 @@ impl UserVmArea {
         self.frames.clear();
     }
 
+    /// `task_policy` is the policy for the area if it has none of its own
     pub fn handle_page_fault(&mut self, 
         page_table: &mut PageTable, 
         vpn: VirtPageNum,
-        access_type: PageFaultAccessType
+        access_type: PageFaultAccessType,
+        task_policy: MemPolicy,
     ) -> Result<(), ()> {
         if !access_type.can_access(self.map_perm) {
             log::warn!(
@@ -760,6 +1418,12 @@ impl UserVmArea {
             );
             return Err(());
         }
+        if access_type.contains(PageFaultAccessType::WRITE)
+            && page_table.find_pte(vpn).map_or(false, |(pte, _)| pte.is_valid() && !pte.is_writable())
+        {
+            // pages of a big page are copied one by one
+            page_table.split_leaf(vpn);
+        }
         match page_table.find_pte(vpn).map(|(pte, i)| (pte, PageLevel::from(i)) ) {
             Some((pte, _)) if pte.is_valid() => {
                 if !access_type.contains(PageFaultAccessType::WRITE) {
@@ -771,24 +1435,46 @@ impl UserVmArea {
                 if self.map_flags.contains(MapFlags::SHARED) {
                     pte.set_writable(true);
                     pte.set_dirty(true);
-                    unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0); }
+                    page_table.flush_vpn(vpn);
                     return Ok(());
                 }
+                // only a page marked copy-on-write may be written after a copy,
+                // a write to any other read-only page is a fault
+                if !pte.is_cow() {
+                    return Err(());
+                }
                 let old_frame = self.frames.get_mut(&vpn).unwrap();
-                if old_frame.get_owners() > 1 {
-                    let new_frame = frames_alloc(1).unwrap();
+                if is_zero_page(old_frame) {
+                    // nothing to copy from the zero page
+                    let new_frame = frames_alloc(1).ok_or(())?;
+                    new_frame.range_ppn.get_slice_mut::<usize>().fill(0);
+                    pte.set_ppn(new_frame.range_ppn.start);
+                    *old_frame = StrongArc::new(new_frame);
+                } else if old_frame.get_owners() > 1 {
+                    // the last owner takes the frame back, others copy it
+                    let new_frame = frames_alloc(1).ok_or(())?;
                     new_frame.range_ppn.get_slice_mut::<usize>().copy_from_slice(
                         old_frame.range_ppn.get_slice()
                     );
                     pte.set_ppn(new_frame.range_ppn.start);
                     old_frame.emplace(new_frame);
                 }
+                pte.set_cow(false);
                 pte.set_writable(true);
                 pte.set_dirty(true);
-                unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0); }
+                page_table.flush_vpn(vpn);
                 Ok(())
             }
             _ => {
+                // MADV_HUGEPAGE asks for a big page, the policy has the last word
+                if self.map_flags.contains(MapFlags::HUGEPAGE)
+                    && matches!(self.vma_type, UserVmAreaType::Heap | UserVmAreaType::Mmap)
+                    && self.file.is_none()
+                    && self.policy.unwrap_or(task_policy).allows_huge()
+                    && PageFaultProcessor::map_huge_zero_page(page_table, vpn, access_type, self.range_vpn(), self.map_perm, &mut self.frames).is_ok()
+                {
+                    return Ok(());
+                }
                 let ret = match self.vma_type {
                     UserVmAreaType::Data =>
                         UserDataHandler::handle_lazy_page_fault(self, page_table, vpn, access_type),
@@ -817,6 +1503,9 @@ impl UserVmArea {
         if self.map_flags != back.map_flags {
             return false;
         }
+        if self.policy != back.policy {
+            return false;
+        }
         if self.file != back.file {
             return false;
         }
@@ -830,6 +1519,10 @@ impl UserVmArea {
         self.range_va.end = back.range_va.end;
         self.len += back.len;
         self.frames.append(&mut back.frames);
+        // the reservation of `back` now belongs to `self`
+        if self.map_flags.contains(MapFlags::ACCOUNT) {
+            back.map_flags.remove(MapFlags::ACCOUNT);
+        }
     }
 
     pub fn push_back(&mut self, back: Self) -> Result<(), Self> {
@@ -859,6 +1552,10 @@ impl Clone for UserVmArea {
         if !self.map_flags.contains(MapFlags::SHARED) {
             let mut new_frames = BTreeMap::new();
             for (&vpn, frame) in self.frames.iter() {
+                if is_zero_page(frame) {
+                    new_frames.insert(vpn, frame.clone());
+                    continue;
+                }
                 let new_frame = FrameAllocator.alloc_tracker(frame.range_ppn.clone().count()).unwrap();
                 new_frame.range_ppn.get_slice_mut::<usize>().copy_from_slice(frame.range_ppn.get_slice());
                 new_frames.insert(vpn, StrongArc::new(new_frame));
@@ -875,7 +1572,8 @@ impl Clone for UserVmArea {
             file: self.file.clone(),
             map_flags: self.map_flags.clone(),
             offset: self.offset,
-            len: self.len
+            len: self.len,
+            policy: self.policy,
         }
     }
 }
@@ -895,7 +1593,9 @@ trait UserLazyFaultHandler {
 #[repr(C, align(4096))]
 struct ZeroPage([u8; 4096]);
 
-const ZERO_PAGE: ZeroPage = ZeroPage([0u8; 4096]);
+/// the one frame every page that was only ever read is mapped to,
+/// read only and copied up on the first write
+static ZERO_PAGE: ZeroPage = ZeroPage([0u8; 4096]);
 
 lazy_static::lazy_static!{
     static ref ZERO_PAGE_ARC: StrongArc<FrameTracker> = {
@@ -906,6 +1606,11 @@ lazy_static::lazy_static!{
     };
 }
 
+/// whether `frame` is the shared zero page
+fn is_zero_page(frame: &StrongArc<FrameTracker>) -> bool {
+    frame.range_ppn.start == ZERO_PAGE_ARC.range_ppn.start
+}
+
 /// tool structure
 struct PageFaultProcessor;
 
@@ -933,12 +1638,54 @@ impl PageFaultProcessor {
             let pte = page_table
                     .map(vpn, ZERO_PAGE_ARC.range_ppn.start, new_perm, PageLevel::Small)
                     .expect(format!("vpn: {:#x} is mapped", vpn.0).as_str());
+            pte.set_cow(perm.contains(MapPerm::W));
             frames.insert(vpn, ZERO_PAGE_ARC.clone());
         }
         unsafe { Instruction::tlb_flush_addr(vpn.start_addr().0) };
         Ok(())
     }
 
+    /// map the naturally aligned big page holding `vpn` at once when the area asked for it,
+    /// the big page lies inside `range` and none of its pages is there yet;
+    /// on Err the fault falls back to base pages
+    fn map_huge_zero_page(
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        access_type: PageFaultAccessType,
+        range: Range<VirtPageNum>,
+        perm: MapPerm,
+        frames: &mut BTreeMap<VirtPageNum, StrongArc<FrameTracker>>,
+    ) -> Result<(), ()> {
+        // loongarch user tables only hold base pages
+        if !cfg!(target_arch = "riscv64") {
+            return Err(());
+        }
+        // reads are served by the zero page
+        if !access_type.contains(PageFaultAccessType::WRITE) {
+            return Err(());
+        }
+        let count = PageLevel::Big.page_count();
+        let start = VirtPageNum(vpn.0 & !(count - 1));
+        if start < range.start || start + count > range.end {
+            return Err(());
+        }
+        if frames.range(start..start + count).next().is_some() {
+            return Err(());
+        }
+        let range_ppn = FrameAllocator.alloc_with_align(count, count.trailing_zeros() as usize).ok_or(())?;
+        range_ppn.get_slice_mut::<usize>().fill(0);
+        let pte = page_table
+            .map(start, range_ppn.start, perm, PageLevel::Big)
+            .map_err(|_| FrameAllocator.dealloc(range_ppn.clone()))?;
+        pte.set_dirty(true);
+        // one tracker per page, so that the big page can be split later
+        for (i, ppn) in range_ppn.enumerate() {
+            frames.insert(start + i, StrongArc::new(FrameTracker::new_in(ppn..ppn + 1, FrameAllocator)));
+        }
+        unsafe { Instruction::tlb_flush_addr(start.start_addr().0) };
+        Ok(())
+    }
+
     /// map private file
     fn map_private_file(
         page_table: &mut PageTable,
@@ -1000,6 +1747,7 @@ impl PageFaultProcessor {
                 let pte = page_table
                     .map(vpn, page.ppn(), new_perm, PageLevel::Small)
                     .expect(format!("vpn: {:#x} is mapped", vpn.0).as_str());
+                pte.set_cow(perm.contains(MapPerm::W));
                 frames.insert(vpn, page.frame());
             }
         }
@@ -26,6 +26,8 @@ pub mod listen_table;
 pub mod socketpair;
 /// raw socket
 pub mod raw;
+/// unix domain socket
+pub mod unix;
 #[repr(u16)]
 #[derive(Debug, Clone, Copy, FromRepr, PartialEq, Eq, PartialOrd, Ord)]
 /// socket address family, used for syscalls
@@ -177,6 +179,8 @@ pub static SOCKET_SET: Lazy<SocketSetWrapper> = Lazy::new(SocketSetWrapper::new)
 pub const TCP_RX_BUF_LEN: usize = 64 * 1024;
 /// TCP RX and TX buffer size
 pub const TCP_TX_BUF_LEN: usize = 64 * 1024;
+/// smallest TCP buffer SO_RCVBUF / SO_SNDBUF can shrink to
+pub const TCP_MIN_BUF_LEN: usize = 4 * 1024;
 const UDP_RX_BUF_LEN: usize = 64 * 1024;
 const UDP_TX_BUF_LEN: usize = 64 * 1024;
 const RAW_RX_BUF_LEN: usize = 64 * 1024;
@@ -262,7 +266,8 @@ impl InterfaceWrapper {
     }
 
 }
-/// random port alloc
+/// random port alloc, the listen table is indexed by port only,
+/// so IPv4 and IPv6 endpoints share one port space
 pub fn get_ephemeral_port() -> SockResult<u16> {
     const PORT_START: u16 = 0xc000;
     const PORT_END: u16 = 0xffff;
@@ -292,8 +297,12 @@ impl <'a> SocketSetWrapper<'a> {
     }
     /// allocate tx buffer and rx buffer ,return a Socket struct in smoltcp
     pub fn new_tcp_socket() -> smoltcp::socket::tcp::Socket<'a> {
-        let rx_buffer = SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tx_buffer = SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
+        Self::new_tcp_socket_with_len(TCP_RX_BUF_LEN, TCP_TX_BUF_LEN)
+    }
+    /// allocate a tcp socket with the given rx and tx buffer length
+    pub fn new_tcp_socket_with_len(rx_len: usize, tx_len: usize) -> smoltcp::socket::tcp::Socket<'a> {
+        let rx_buffer = SocketBuffer::new(vec![0; rx_len]);
+        let tx_buffer = SocketBuffer::new(vec![0; tx_len]);
         Socket::new(rx_buffer, tx_buffer)
     }
     /// allocate a udp socket, return a Socket struct in smoltcp
@@ -371,36 +380,135 @@ impl <'a> SocketSetWrapper<'a> {
 pub fn poll_interfaces() -> smoltcp::time::Instant {
     SOCKET_SET.poll_interfaces()
 }
+/// how long the loopback echo may take before it is given up
+const LOOPBACK_ECHO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connect two tcp sockets over the loopback device and bounce `payload` off one of them.
+///
+/// It drives smoltcp sockets directly instead of going through `TcpSocket`, which
+/// waits on the current task, so it can run from a kernel task like the boot self-test.
+pub async fn tcp_loopback_echo(payload: &[u8]) -> Result<(), &'static str> {
+    let server = SOCKET_SET.add_socket(SocketSetWrapper::new_tcp_socket());
+    let client = SOCKET_SET.add_socket(SocketSetWrapper::new_tcp_socket());
+    let ret = tcp_echo(server, client, payload).await;
+    SOCKET_SET.remove(server);
+    SOCKET_SET.remove(client);
+    ret
+}
+
+async fn tcp_echo(server: SocketHandle, client: SocketHandle, payload: &[u8]) -> Result<(), &'static str> {
+    let port = get_ephemeral_port().map_err(|_| "no free port")?;
+    let local_port = get_ephemeral_port().map_err(|_| "no free port")?;
+    SOCKET_SET
+        .with_socket_mut::<Socket, _, _>(server, |socket| socket.listen(port))
+        .map_err(|_| "listen")?;
+    let remote = (LOCAL_IPV4, port);
+    SOCKET_SET
+        .with_socket_mut::<Socket, _, _>(client, |socket| {
+            socket.connect(ETH0.get().unwrap().iface.lock().context(), remote, local_port)
+        })
+        .map_err(|_| "connect")?;
+
+    let deadline = get_current_time_duration() + LOOPBACK_ECHO_TIMEOUT;
+    let mut echoed = vec![0u8; payload.len()];
+    let mut pending: Vec<u8> = Vec::new();
+    let (mut sent, mut received) = (0, 0);
+    while received < payload.len() {
+        if get_current_time_duration() > deadline {
+            return Err("timed out");
+        }
+        SOCKET_SET.poll_interfaces();
+        SOCKET_SET.with_socket_mut::<Socket, _, _>(client, |socket| {
+            if sent < payload.len() && socket.can_send() {
+                sent += socket.send_slice(&payload[sent..]).unwrap_or(0);
+            }
+            if socket.can_recv() {
+                received += socket.recv_slice(&mut echoed[received..]).unwrap_or(0);
+            }
+        });
+        // the server sends back whatever it got
+        SOCKET_SET.with_socket_mut::<Socket, _, _>(server, |socket| {
+            if socket.can_recv() {
+                let mut buf = [0u8; 512];
+                let len = socket.recv_slice(&mut buf).unwrap_or(0);
+                pending.extend_from_slice(&buf[..len]);
+            }
+            if !pending.is_empty() && socket.can_send() {
+                let len = socket.send_slice(&pending).unwrap_or(0);
+                pending.drain(..len);
+            }
+        });
+        crate::utils::yield_now().await;
+    }
+    if echoed != payload {
+        return Err("echoed data differs");
+    }
+    Ok(())
+}
+
 /// modify the socket first, a helper method for use smoltcp consume
 pub fn modify_packet(buf: &[u8], sockets: &mut SocketSet<'_>, is_ethernet: bool) ->Result<(), smoltcp::wire::Error>{
-    use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
+    use smoltcp::wire::EthernetFrame;
     log::warn!("[modify packet]receive packet");
-    let ipv4_packet = if is_ethernet {
+    if is_ethernet {
         let ether_frame = EthernetFrame::new_checked(buf)?;
-        let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
-        log::warn!("[modify packet] check IPV4 Packet ehternet frame");
-        ipv4_packet
+        modify_ip_packet(ether_frame.payload(), sockets)
     }else {
-        Ipv4Packet::new_checked(buf)?
-    };
-    log::warn!("  Source IP: {}", ipv4_packet.src_addr());
-    log::warn!("  Destination IP: {}", ipv4_packet.dst_addr());
-    log::warn!("  Next Header (Protocol): {:?}", ipv4_packet.next_header());
-    log::warn!("  Payload length: {} bytes", ipv4_packet.payload().len());
-    if ipv4_packet.next_header() == IpProtocol::Tcp {
-        log::warn!("[modify packet] ipv4 packet.next_header() == IpProtocol::Tcp");
-        let tcp_packet = TcpPacket::new_checked(ipv4_packet.payload())?;
-        let src_addr = (ipv4_packet.src_addr(), tcp_packet.src_port()).into();
-        let dst_addr = (ipv4_packet.dst_addr(),tcp_packet.dst_port()).into();
-        log::warn!(" tcp_packet.syn(): {}, tcp_packet.ack(): {}",tcp_packet.syn(), tcp_packet.ack());
-        let first_flag = tcp_packet.syn() && !tcp_packet.ack();
-        if first_flag {
-            info!("[modify packet] tcp_packet.syn() && !tcp_packet.ack() == true ");
-            LISTEN_TABLE.handle_coming_packet(src_addr, dst_addr, sockets);
+        modify_ip_packet(buf, sockets)
+    }
+}
+/// snoop an IPv4 or IPv6 packet for incoming tcp connections
+fn modify_ip_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::{IpProtocol, IpVersion, Ipv4Packet, Ipv6Packet};
+    match IpVersion::of_packet(buf)? {
+        IpVersion::Ipv4 => {
+            let ipv4_packet = Ipv4Packet::new_checked(buf)?;
+            log::warn!("  Source IP: {}", ipv4_packet.src_addr());
+            log::warn!("  Destination IP: {}", ipv4_packet.dst_addr());
+            log::warn!("  Next Header (Protocol): {:?}", ipv4_packet.next_header());
+            log::warn!("  Payload length: {} bytes", ipv4_packet.payload().len());
+            if ipv4_packet.next_header() == IpProtocol::Tcp {
+                log::warn!("[modify packet] ipv4 packet.next_header() == IpProtocol::Tcp");
+                snoop_tcp_packet(
+                    IpAddress::Ipv4(ipv4_packet.src_addr()),
+                    IpAddress::Ipv4(ipv4_packet.dst_addr()),
+                    ipv4_packet.payload(),
+                    sockets,
+                )?;
+            }
+        }
+        IpVersion::Ipv6 => {
+            let ipv6_packet = Ipv6Packet::new_checked(buf)?;
+            log::warn!("  Source IP: {}", ipv6_packet.src_addr());
+            log::warn!("  Destination IP: {}", ipv6_packet.dst_addr());
+            // extension headers are not walked, tcp has to follow the fixed header
+            if ipv6_packet.next_header() == IpProtocol::Tcp {
+                log::warn!("[modify packet] ipv6 packet.next_header() == IpProtocol::Tcp");
+                snoop_tcp_packet(
+                    IpAddress::Ipv6(ipv6_packet.src_addr()),
+                    IpAddress::Ipv6(ipv6_packet.dst_addr()),
+                    ipv6_packet.payload(),
+                    sockets,
+                )?;
+            }
         }
     }
     Ok(())
 }
+/// hand a SYN to the listen table so that a socket is ready for it
+fn snoop_tcp_packet(src: IpAddress, dst: IpAddress, payload: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::TcpPacket;
+    let tcp_packet = TcpPacket::new_checked(payload)?;
+    let src_addr = (src, tcp_packet.src_port()).into();
+    let dst_addr = (dst, tcp_packet.dst_port()).into();
+    log::warn!(" tcp_packet.syn(): {}, tcp_packet.ack(): {}",tcp_packet.syn(), tcp_packet.ack());
+    let first_flag = tcp_packet.syn() && !tcp_packet.ack();
+    if first_flag {
+        info!("[modify packet] tcp_packet.syn() && !tcp_packet.ack() == true ");
+        LISTEN_TABLE.handle_coming_packet(src_addr, dst_addr, sockets);
+    }
+    Ok(())
+}
 /// a port allocator for udp socket bind
 pub struct PortManager {
     port_map: SpinNoIrqLock<BTreeMap<u16, (usize, IpListenEndpoint)>>,
@@ -457,11 +565,13 @@ pub fn init_network(dev: Box<dyn NetDevice>, dev_flag: bool) {
         "127.0.0.1".parse().unwrap()
     };
     unsafe { LOCAL_IP = ip };
-    let ip_addrs = if dev_flag {
+    let mut ip_addrs = if dev_flag {
         vec![IpCidr::new(IP.parse().unwrap(), 8),IpCidr::new(ip, IP_PREFIX)]
     }else {
         vec![IpCidr::new(ip, 8)]
     };
+    // IPv6 loopback, so that ::1 is reachable as well as 127.0.0.1
+    ip_addrs.push(IpCidr::new(IpAddress::Ipv6(Ipv6Address::LOOPBACK), 128));
     eth0.iface.lock().update_ip_addrs(|inner_ip_addrs|{
         inner_ip_addrs.extend(ip_addrs);
     });
@@ -481,19 +591,15 @@ pub fn init_network(dev: Box<dyn NetDevice>, dev_flag: bool) {
 }
 
 
-/// Unix Sock
-pub struct UnixSocket {}
-
 /// ip white list
 pub const LOCAL_IPS: &[IpAddress] = &[
     IpAddress::v4(127, 0, 0, 1),
     IpAddress::v4(0, 0, 0, 0),
-    IpAddress::v4(10,250,225,200)
-    //  // IPv6 loopback (::1)
-    // IpAddress::Ipv6(smoltcp::wire::Ipv6Address::LOOPBACK),
-    
-    // // IPv6 unspecified address (::)
-    // IpAddress::Ipv6(smoltcp::wire::Ipv6Address::UNSPECIFIED),
+    IpAddress::v4(10,250,225,200),
+    // IPv6 loopback (::1)
+    IpAddress::Ipv6(Ipv6Address::LOOPBACK),
+    // IPv6 unspecified address (::)
+    IpAddress::Ipv6(Ipv6Address::UNSPECIFIED),
 ];
 
 pub static mut LOCAL_IP: IpAddress = LOCAL_IPV4;
\ No newline at end of file
@@ -6,7 +6,7 @@ use fatfs::info;
 use smoltcp::{socket::udp, wire::{IpAddress, IpEndpoint, IpListenEndpoint, IpProtocol}};
 use crate::{fs::{vfs::{file::PollEvents, inode::InodeMode, Dentry, DentryInner, File, FileInner, Inode, InodeInner}, OpenFlags}, net::{addr::ZERO_IPV4_ENDPOINT, crypto::{encode_raw, AlgInstance, AlgType, SockAddrAlg}, raw::RawSocket, socketpair::{SocketPairConnection, SocketPairInternal}, LOCAL_IPS}, sync::mutex::{SpinNoIrq, SpinNoIrqLock}, syscall::sys_error::SysError, task::current_task, timer::ffi::TimeSpec};
 use crate::syscall::net::SocketType;
-use super::{addr::{SockAddr, SockAddrIn4, ZERO_IPV4_ADDR}, poll_interfaces, tcp::TcpSocket, udp::UdpSocket, SaFamily, UnixSocket};
+use super::{addr::{SockAddr, SockAddrIn4, ZERO_IPV4_ADDR}, poll_interfaces, tcp::TcpSocket, udp::UdpSocket, unix::UnixSocket, SaFamily};
 pub type SockResult<T> = Result<T, SysError>;
 /// a trait for differnt socket types
 /// net poll results.
@@ -32,7 +32,6 @@ impl Sock {
         match self {
             Sock::TCP(tcp) => tcp.connect(addr).await,
             Sock::UDP(udp) => udp.connect(addr),
-            Sock::Unix(_) => {Ok(())},
             _ =>  Err(SysError::EAFNOSUPPORT),
         }
     }
@@ -65,8 +64,7 @@ impl Sock {
                     udp.bind(local_endpoint)
                 }
             }
-            // todo: suit for most cases
-            Sock::Unix(_) => Err(SysError::ENOTDIR),
+            Sock::Unix(unix) => unix.bind(unsafe { local_addr.unix }),
             _ => {
                 Err(SysError::EAFNOSUPPORT)
             }
@@ -77,6 +75,7 @@ impl Sock {
         match self {
             Sock::TCP(tcp) => tcp.listen(),
             Sock::UDP(udp) => Err(SysError::EOPNOTSUPP),
+            Sock::Unix(unix) => unix.listen(),
             _ => Err(SysError::EAFNOSUPPORT),
         }
     }
@@ -85,6 +84,7 @@ impl Sock {
         match self {
             Sock::TCP(tcp) => tcp.set_nonblocking(),
             Sock::UDP(udp) => udp.set_nonblocking(),
+            Sock::Unix(unix) => unix.set_nonblocking(),
             _ => {},
         }
     }
@@ -99,6 +99,7 @@ impl Sock {
                 let peer_addr = udp_socket.peer_addr()?;
                 Ok(SockAddr::from_endpoint(peer_addr))
             },
+            Sock::Unix(unix) => unix.peer_addr(),
             _ =>  Err(SysError::EAFNOSUPPORT),
         }
     }
@@ -113,6 +114,7 @@ impl Sock {
                 let local_addr = udp_socket.local_addr()?;
                 Ok(SockAddr::from_endpoint(local_addr))
             },
+            Sock::Unix(unix) => unix.local_addr(),
             _ =>  Err(SysError::EAFNOSUPPORT),
         }
     }
@@ -127,7 +129,7 @@ impl Sock {
                 }
             },
             Sock::Raw(raw) => raw.send(data, remote_addr).await,
-            Sock::Unix(_) =>  Err(SysError::EAFNOSUPPORT),
+            Sock::Unix(unix) => unix.send(data).await,
             Sock::SocketPair(socket_pair) => socket_pair.send(data).await,
         }
     }
@@ -136,7 +138,7 @@ impl Sock {
         match self {
             Sock::TCP(tcp) => tcp.recv(data).await,
             Sock::UDP(udp_socket) => udp_socket.recv(data).await,
-            Sock::Unix(_) =>  Err(SysError::EAFNOSUPPORT),
+            Sock::Unix(unix) => unix.recv(data).await.map(|len| (len, ZERO_IPV4_ENDPOINT)),
             Sock::SocketPair(pair) => {
                 let res = pair.recv(data).await?;
                 Ok((res, ZERO_IPV4_ENDPOINT))
@@ -149,7 +151,7 @@ impl Sock {
         match self {
             Sock::TCP(tcp) => tcp.shutdown(how),
             Sock::UDP(udp_socket) => udp_socket.shutdown(),
-            Sock::Unix(_) =>  Err(SysError::EAFNOSUPPORT),
+            Sock::Unix(unix) => unix.shutdown(),
             Sock::SocketPair(pair) => Ok(pair.close()),
             Sock::Raw(raw) => raw.shutdown(),
         }
@@ -206,6 +208,8 @@ pub struct Socket {
     pub send_buf_size: AtomicUsize,
     /// recv_buf_size
     pub recv_buf_size: AtomicUsize,
+    /// SO_REUSEPORT flag
+    pub reuse_port: AtomicBool,
     /// congestion flag
     pub congestion:  SpinNoIrqLock<String>,
     /// socketopt dout route flag
@@ -234,7 +238,7 @@ impl Socket {
                     _ => Sock::TCP(TcpSocket::new_v4_without_handle()),
                 }
             },
-            SaFamily::AfUnix => Sock::Unix(UnixSocket {  }),
+            SaFamily::AfUnix => Sock::Unix(UnixSocket::new()),
             SaFamily::Alg => Sock::TCP(TcpSocket::new_v4_without_handle()),
             _ => Sock::TCP(TcpSocket::new_v4_without_handle()),
         };
@@ -256,6 +260,7 @@ impl Socket {
             },
             send_buf_size: AtomicUsize::new(16 * 4096),
             recv_buf_size: AtomicUsize::new(16 * 4096),
+            reuse_port: AtomicBool::new(false),
             congestion: SpinNoIrqLock::new((String::from("reno"))),
             dont_route: false,
             is_af_alg: AtomicBool::new(false),
@@ -278,6 +283,7 @@ impl Socket {
             },
             send_buf_size: AtomicUsize::new(16 * 4096),
             recv_buf_size: AtomicUsize::new(16 * 4096),
+            reuse_port: AtomicBool::new(false),
             congestion: SpinNoIrqLock::new((String::from("reno"))),
             dont_route: false,
             is_af_alg: AtomicBool::new(false),
@@ -303,6 +309,14 @@ impl Socket {
     pub fn set_recv_buf_size(&self, size: usize) {
         self.recv_buf_size.store(size, atomic::Ordering::Release)
     }
+    /// get SO_REUSEPORT flag
+    pub fn get_reuse_port(&self) -> bool {
+        self.reuse_port.load(atomic::Ordering::Acquire)
+    }
+    /// set SO_REUSEPORT flag
+    pub fn set_reuse_port(&self, reuse: bool) {
+        self.reuse_port.store(reuse, atomic::Ordering::Release)
+    }
     /// get congestion state
     pub fn get_congestion(&self) -> String {
         self.congestion.lock().clone()
@@ -403,7 +417,9 @@ impl File for Socket {
     async fn base_poll(&self, events:PollEvents) -> PollEvents {
         if let Sock::SocketPair(socket_pair) = &self.sk {
             return socket_pair.poll(events).await;
-        }else {
+        } else if let Sock::Unix(unix) = &self.sk {
+            return unix.poll(events).await;
+        } else {
              let mut res = PollEvents::empty();
             poll_interfaces();
             let netstate = self.sk.poll().await;
@@ -519,6 +535,7 @@ impl Socket {
             },
             send_buf_size: AtomicUsize::new(16 * 4096),
             recv_buf_size: AtomicUsize::new(16 * 4096),
+            reuse_port: AtomicBool::new(false),
             congestion: SpinNoIrqLock::new((String::from("reno"))),
             dont_route: false,
             is_af_alg: AtomicBool::new(true),
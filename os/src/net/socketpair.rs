@@ -190,6 +190,28 @@ impl SocketPairConnection {
         Ok(len)
     }
 
+    /// whether recv returns without waiting
+    pub fn readable(&self) -> bool {
+        let meta = self.internal.meta.lock();
+        let (read_endpoint, other_end_closed) = if self.is_first_end {
+            (&meta.end2, meta.end2_closed)
+        } else {
+            (&meta.end1, meta.end1_closed)
+        };
+        !read_endpoint.buffer.lock().is_empty() || other_end_closed
+    }
+
+    /// whether send returns without waiting
+    pub fn writable(&self) -> bool {
+        let meta = self.internal.meta.lock();
+        let (write_endpoint, peer_closed) = if self.is_first_end {
+            (&meta.end1, meta.end2_closed)
+        } else {
+            (&meta.end2, meta.end1_closed)
+        };
+        !write_endpoint.buffer.lock().is_full() || peer_closed
+    }
+
     /// Implementing poll logic
     pub async fn poll(&self, events: PollEvents) -> PollEvents {
         let mut res = PollEvents::empty();
@@ -1,8 +1,8 @@
-use core::{fmt::UpperExp, future::Future, net::SocketAddr, sync::atomic::{AtomicBool, AtomicU8, Ordering}, time::{self, Duration}};
+use core::{fmt::UpperExp, future::Future, net::SocketAddr, sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicUsize, Ordering}, time::{self, Duration}};
 
 use crate::{ net::addr::LOCAL_IPV4, sync::{mutex::SpinNoIrqLock, UPSafeCell}, syscall::{sys_error::SysError, SysResult}, task::current_task, timer::{ffi::TimeSpec, get_current_time, get_current_time_duration, timed_task::ksleep}, utils::{get_waker, suspend_now, yield_now}};
 
-use super::{addr::{ ZERO_IPV4_ADDR, ZERO_IPV4_ENDPOINT}, get_ephemeral_port, listen_table::ListenTable, socket::{PollState, Sock}, NetPollTimer, SocketSetWrapper, ETH0, LISTEN_TABLE, PORT_END, PORT_START, RCV_SHUTDOWN, SEND_SHUTDOWN, SHUTDOWN_MASK, SHUTRD, SHUTRDWR, SHUTWR, SOCKET_SET, SOCK_RAND_SEED, TCP_TX_BUF_LEN};
+use super::{addr::{ ZERO_IPV4_ADDR, ZERO_IPV4_ENDPOINT}, get_ephemeral_port, listen_table::ListenTable, socket::{PollState, Sock}, NetPollTimer, SocketSetWrapper, ETH0, LISTEN_TABLE, PORT_END, PORT_START, RCV_SHUTDOWN, SEND_SHUTDOWN, SHUTDOWN_MASK, SHUTRD, SHUTRDWR, SHUTWR, SOCKET_SET, SOCK_RAND_SEED, TCP_MIN_BUF_LEN, TCP_RX_BUF_LEN, TCP_TX_BUF_LEN};
 use alloc::vec::Vec;
 use fatfs::warn;
 use hal::println;
@@ -18,6 +18,9 @@ use rand::rngs::SmallRng;
 use rand::RngCore;
 use log::info;
 
+/// keep-alive interval in seconds when SO_KEEPALIVE is set
+const TCP_KEEP_ALIVE_INTERVAL: u64 = 60;
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SocketState {
@@ -61,6 +64,16 @@ pub struct TcpSocket {
     shutdown_flag: UPSafeCell<u8>,
     /// reuse addr flag
     reuse_addr_flag: AtomicBool,
+    /// TCP_NODELAY, nagle is disabled when set
+    nodelay_flag: AtomicBool,
+    /// SO_KEEPALIVE flag
+    keep_alive_flag: AtomicBool,
+    /// rx buffer length used when the smoltcp socket is created
+    rx_buf_len: AtomicUsize,
+    /// tx buffer length used when the smoltcp socket is created
+    tx_buf_len: AtomicUsize,
+    /// pending error reported by SO_ERROR, 0 if none
+    sock_error: AtomicI32,
     /// timeout flag
     pub timeout: SpinNoIrqLock<Option<TimeSpec>>,
 }
@@ -79,6 +92,11 @@ impl TcpSocket {
             nonblock_flag: AtomicBool::new(false),
             shutdown_flag: UPSafeCell::const_new(0),
             reuse_addr_flag: AtomicBool::new(false),
+            nodelay_flag: AtomicBool::new(false),
+            keep_alive_flag: AtomicBool::new(false),
+            rx_buf_len: AtomicUsize::new(TCP_RX_BUF_LEN),
+            tx_buf_len: AtomicUsize::new(TCP_TX_BUF_LEN),
+            sock_error: AtomicI32::new(0),
             timeout: SpinNoIrqLock::new(None),
         }
     }
@@ -92,6 +110,11 @@ impl TcpSocket {
             nonblock_flag: AtomicBool::new(false),
             shutdown_flag: UPSafeCell::const_new(0),
             reuse_addr_flag: AtomicBool::new(false),
+            nodelay_flag: AtomicBool::new(false),
+            keep_alive_flag: AtomicBool::new(false),
+            rx_buf_len: AtomicUsize::new(TCP_RX_BUF_LEN),
+            tx_buf_len: AtomicUsize::new(TCP_TX_BUF_LEN),
+            sock_error: AtomicI32::new(0),
             timeout: SpinNoIrqLock::new(None),
         }
     }
@@ -197,6 +220,74 @@ impl TcpSocket {
         self.reuse_addr_flag.store(reuse_flag, Ordering::Release)
     }
 
+    /// get TCP_NODELAY flag
+    pub fn nodelay(&self) -> bool {
+        self.nodelay_flag.load(Ordering::Acquire)
+    }
+    /// set TCP_NODELAY flag, applied at once if the smoltcp socket exists
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.nodelay_flag.store(nodelay, Ordering::Release);
+        self.reconfigure();
+    }
+    /// get SO_KEEPALIVE flag
+    pub fn keep_alive(&self) -> bool {
+        self.keep_alive_flag.load(Ordering::Acquire)
+    }
+    /// set SO_KEEPALIVE flag, applied at once if the smoltcp socket exists
+    pub fn set_keep_alive(&self, keep_alive: bool) {
+        self.keep_alive_flag.store(keep_alive, Ordering::Release);
+        self.reconfigure();
+    }
+    /// get rx buffer length
+    pub fn rx_buf_len(&self) -> usize {
+        self.rx_buf_len.load(Ordering::Acquire)
+    }
+    /// set rx buffer length for the next smoltcp socket, return the length in use
+    pub fn set_rx_buf_len(&self, len: usize) -> usize {
+        let len = len.clamp(TCP_MIN_BUF_LEN, TCP_RX_BUF_LEN);
+        self.rx_buf_len.store(len, Ordering::Release);
+        len
+    }
+    /// get tx buffer length
+    pub fn tx_buf_len(&self) -> usize {
+        self.tx_buf_len.load(Ordering::Acquire)
+    }
+    /// set tx buffer length for the next smoltcp socket, return the length in use
+    pub fn set_tx_buf_len(&self, len: usize) -> usize {
+        let len = len.clamp(TCP_MIN_BUF_LEN, TCP_TX_BUF_LEN);
+        self.tx_buf_len.store(len, Ordering::Release);
+        len
+    }
+    /// take the pending error for SO_ERROR, 0 if none
+    pub fn take_error(&self) -> i32 {
+        self.sock_error.swap(0, Ordering::AcqRel)
+    }
+    /// record a pending error for SO_ERROR
+    fn set_error(&self, err: SysError) {
+        self.sock_error.store(err as i32, Ordering::Release);
+    }
+    /// copy the socket options of a listening socket to an accepted one
+    fn inherit_options(&self, listener: &TcpSocket) {
+        self.nodelay_flag.store(listener.nodelay(), Ordering::Release);
+        self.keep_alive_flag.store(listener.keep_alive(), Ordering::Release);
+        self.reconfigure();
+    }
+    /// apply the stored options to a smoltcp socket
+    fn configure(&self, socket: &mut tcp::Socket) {
+        socket.set_nagle_enabled(!self.nodelay());
+        socket.set_keep_alive(if self.keep_alive() {
+            Some(smoltcp::time::Duration::from_secs(TCP_KEEP_ALIVE_INTERVAL))
+        } else {
+            None
+        });
+    }
+    /// apply the stored options to the smoltcp socket if it exists
+    fn reconfigure(&self) {
+        if let Some(handle) = self.handle() {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| self.configure(socket));
+        }
+    }
+
     /// get timeout
     pub fn get_timeout(&self) -> Option<TimeSpec> {
         *self.timeout.lock()
@@ -213,9 +304,12 @@ impl TcpSocket {
         yield_now().await;
         // now change the state to connecting , wait for poll connect event
         self.update_state(SocketState::Closed, SocketState::Connecting, ||{
-            let handle = self.handle().unwrap_or_else(||SOCKET_SET.add_socket(SocketSetWrapper::new_tcp_socket()));
+            let handle = self.handle().unwrap_or_else(|| {
+                SOCKET_SET.add_socket(SocketSetWrapper::new_tcp_socket_with_len(self.rx_buf_len(), self.tx_buf_len()))
+            });
             let robust_endpoint = self.robost_port_endpoint()?;
             let (local_endpoint, remote_endpoint) = SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket|{
+                self.configure(socket);
                 socket.connect(ETH0.get().unwrap().iface.lock().context(),addr,robust_endpoint)
                 .or_else(|e| match e {
                     ConnectError::InvalidState => {
@@ -252,6 +346,8 @@ impl TcpSocket {
                     Ok(())
                 }else {
                     log::warn!("[TcpSocket::connect] connection refused");
+                    // reported here, not through SO_ERROR
+                    self.take_error();
                     Err(SysError::ECONNREFUSED)
                 }
             }).await
@@ -612,6 +708,7 @@ impl TcpSocket {
                     self.local_endpoint.exclusive_access().replace(ZERO_IPV4_ENDPOINT);
                     self.remote_endpoint.exclusive_access().replace(ZERO_IPV4_ENDPOINT);
                     self.set_state(SocketState::Closed as u8);
+                    self.set_error(SysError::ECONNREFUSED);
                     true
                 }
             } 
@@ -672,7 +769,9 @@ impl TcpSocket {
         self.block_on(|| {
             let (handle, (local_endpoint, remote_endpoint)) = LISTEN_TABLE.accept(local_port)?;
             // info!("TCP socket accepted a new connection {}", remote_endpoint);
-            Ok(TcpSocket::new_v4_connected(handle, local_endpoint, remote_endpoint))
+            let socket = TcpSocket::new_v4_connected(handle, local_endpoint, remote_endpoint);
+            socket.inherit_options(self);
+            Ok(socket)
         }).await
     }
 }
@@ -128,9 +128,21 @@ impl UdpSocket {
             None => Err(SysError::ENOTCONN),
         }
     }
+    /// a datagram is sent whole, one larger than the tx buffer never fits
+    fn check_datagram_len(&self, len: usize) -> SockResult<()> {
+        let capacity = SOCKET_SET.with_socket::<smoltcp::socket::udp::Socket,_,_>(self.handle, |socket| {
+            socket.payload_send_capacity()
+        });
+        if len > capacity {
+            log::warn!("[UdpSocket] datagram of {} bytes exceeds the tx buffer", len);
+            return Err(SysError::EMSGSIZE);
+        }
+        Ok(())
+    }
     /// send data to the peer
     pub async fn send(&self, data: &[u8]) -> SockResult<usize> {
         let remote_endpoint = self.peer_addr()?;
+        self.check_datagram_len(data.len())?;
         if self.local_endpoint.read().is_none() {
             self.bind(UNSPECIFIED_LISTEN_ENDPOINT)?;
         }
@@ -164,6 +176,7 @@ impl UdpSocket {
             log::warn!("socket send_to() failed: invalid remote address");
             return Err(SysError::EINVAL);
         }
+        self.check_datagram_len(data.len())?;
         if self.local_endpoint.read().is_none() {
             log::warn!(
                 "[send_impl] UDP socket {}: not bound. Use 127.0.0.1",
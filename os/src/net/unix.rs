@@ -0,0 +1,384 @@
+//! Unix domain stream sockets for local IPC
+//! a connection is a pair of in-kernel byte buffers shared with socketpair,
+//! listeners are found by the address they bound, either a socket inode
+//! in the VFS or a name in the abstract namespace
+
+use core::{future::Future, pin::Pin, sync::atomic::{AtomicBool, Ordering}, task::{Context, Poll, Waker}};
+
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, string::String, sync::{Arc, Weak}, vec::Vec};
+
+use crate::{fs::vfs::{dentry::global_find_dentry, file::PollEvents, inode::InodeMode, Dentry, DCACHE}, sync::mutex::SpinNoIrqLock, syscall::SysError, task::current_task, utils::{abs_path_to_name, get_waker, rel_path_to_abs}};
+
+use super::{addr::{SockAddr, SockAddrUn}, socket::{SockResult, SocketDentry, SocketInode}, socketpair::{SocketPairConnection, SocketPairInternal}, SaFamily};
+
+/// capacity of the buffer in each direction of a connection
+const UNIX_BUF_SIZE: usize = 16 * 4096;
+
+/// address of a unix domain socket
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixAddr {
+    /// not bound to any address
+    Unnamed,
+    /// bound to a socket inode, the absolute path
+    Path(String),
+    /// abstract namespace, the name after the leading NUL
+    Abstract(Vec<u8>),
+}
+
+impl UnixAddr {
+    /// parse the user address, relative paths start from the cwd
+    pub fn from_sockaddr(addr: &SockAddrUn) -> SockResult<Self> {
+        if addr.path[0] == 0 {
+            let len = addr.path.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            if len == 0 {
+                return Ok(UnixAddr::Unnamed);
+            }
+            return Ok(UnixAddr::Abstract(addr.path[1..len].to_vec()));
+        }
+        let len = addr.path.iter().position(|&b| b == 0).unwrap_or(addr.path.len());
+        let path = core::str::from_utf8(&addr.path[..len]).map_err(|_| SysError::EINVAL)?;
+        let abs_path = if path.starts_with('/') {
+            String::from(path)
+        } else {
+            let cwd = current_task().unwrap().cwd().path();
+            rel_path_to_abs(&cwd, path).ok_or(SysError::ENOENT)?
+        };
+        Ok(UnixAddr::Path(abs_path))
+    }
+
+    /// convert into the user address
+    pub fn to_sockaddr(&self) -> SockAddr {
+        let mut unix = SockAddrUn {
+            family: SaFamily::AfUnix as u16,
+            path: [0; 108],
+        };
+        match self {
+            UnixAddr::Unnamed => {}
+            UnixAddr::Path(path) => {
+                // keep the terminating NUL
+                let len = path.len().min(unix.path.len() - 1);
+                unix.path[..len].copy_from_slice(&path.as_bytes()[..len]);
+            }
+            UnixAddr::Abstract(name) => {
+                let len = name.len().min(unix.path.len() - 1);
+                unix.path[1..1 + len].copy_from_slice(&name[..len]);
+            }
+        }
+        SockAddr { unix }
+    }
+}
+
+/// bound addresses and the accept queues of the sockets bound to them
+static UNIX_ADDRS: SpinNoIrqLock<BTreeMap<UnixAddr, Weak<UnixAcceptQueue>>> =
+    SpinNoIrqLock::new(BTreeMap::new());
+
+struct UnixAcceptQueueInner {
+    /// `listen` was called on the socket bound to the address
+    listening: bool,
+    /// server ends of connections not accepted yet, with the address of the client
+    pending: VecDeque<(SocketPairConnection, UnixAddr)>,
+    /// tasks waiting in accept or poll
+    wakers: VecDeque<Waker>,
+}
+
+/// per-address queue of connections waiting for accept
+pub struct UnixAcceptQueue {
+    inner: SpinNoIrqLock<UnixAcceptQueueInner>,
+}
+
+impl UnixAcceptQueue {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            inner: SpinNoIrqLock::new(UnixAcceptQueueInner {
+                listening: false,
+                pending: VecDeque::new(),
+                wakers: VecDeque::new(),
+            }),
+        })
+    }
+
+    /// queue a new connection for the listener
+    fn push(&self, conn: SocketPairConnection, peer: UnixAddr) -> SockResult<()> {
+        let mut inner = self.inner.lock();
+        if !inner.listening {
+            return Err(SysError::ECONNREFUSED);
+        }
+        inner.pending.push_back((conn, peer));
+        while let Some(waker) = inner.wakers.pop_front() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+/// wait until a connection is queued
+pub struct UnixAcceptFuture<'a> {
+    queue: &'a UnixAcceptQueue,
+    nonblock: bool,
+}
+
+impl Future for UnixAcceptFuture<'_> {
+    type Output = SockResult<(SocketPairConnection, UnixAddr)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.queue.inner.lock();
+        if let Some(conn) = inner.pending.pop_front() {
+            return Poll::Ready(Ok(conn));
+        }
+        if self.nonblock {
+            return Poll::Ready(Err(SysError::EAGAIN));
+        }
+        inner.wakers.push_back(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+enum UnixState {
+    Unconnected,
+    Listening,
+    Connected(Arc<SocketPairConnection>),
+}
+
+struct UnixSocketInner {
+    state: UnixState,
+    local: UnixAddr,
+    peer: UnixAddr,
+    /// the accept queue registered at the local address
+    queue: Option<Arc<UnixAcceptQueue>>,
+}
+
+/// Unix domain stream socket
+pub struct UnixSocket {
+    inner: SpinNoIrqLock<UnixSocketInner>,
+    nonblock: AtomicBool,
+}
+
+impl UnixSocket {
+    pub fn new() -> Self {
+        Self {
+            inner: SpinNoIrqLock::new(UnixSocketInner {
+                state: UnixState::Unconnected,
+                local: UnixAddr::Unnamed,
+                peer: UnixAddr::Unnamed,
+                queue: None,
+            }),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    fn new_connected(conn: SocketPairConnection, local: UnixAddr, peer: UnixAddr) -> Self {
+        Self {
+            inner: SpinNoIrqLock::new(UnixSocketInner {
+                state: UnixState::Connected(Arc::new(conn)),
+                local,
+                peer,
+                queue: None,
+            }),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_nonblocking(&self) {
+        self.nonblock.store(true, Ordering::Release);
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// the connection to the peer, the lock is not held while waiting on it
+    fn connection(&self) -> SockResult<Arc<SocketPairConnection>> {
+        match &self.inner.lock().state {
+            UnixState::Connected(conn) => Ok(conn.clone()),
+            _ => Err(SysError::ENOTCONN),
+        }
+    }
+
+    /// create the socket inode a pathname address is bound to
+    fn create_socket_inode(path: &str) -> SockResult<()> {
+        let dentry = global_find_dentry(path)?;
+        if !dentry.is_negative() {
+            return Err(SysError::EADDRINUSE);
+        }
+        // the walk stopped before the last component: the parent does not exist
+        if abs_path_to_name(path) != abs_path_to_name(&dentry.path()) {
+            return Err(SysError::ENOENT);
+        }
+        let parent = dentry.parent().ok_or(SysError::ENOENT)?;
+        let new_dentry = Arc::new(SocketDentry::new(dentry.name(), Some(parent.clone())));
+        new_dentry.set_inode(Arc::new(SocketInode::new()));
+        parent.add_child(new_dentry.clone());
+        DCACHE.lock().insert(new_dentry.path(), new_dentry);
+        Ok(())
+    }
+
+    /// bind the socket to a pathname or an abstract address
+    pub fn bind(&self, addr: SockAddrUn) -> SockResult<()> {
+        let addr = UnixAddr::from_sockaddr(&addr)?;
+        let mut inner = self.inner.lock();
+        if inner.local != UnixAddr::Unnamed {
+            return Err(SysError::EINVAL);
+        }
+        if addr == UnixAddr::Unnamed {
+            return Ok(());
+        }
+        let mut addrs = UNIX_ADDRS.lock();
+        match &addr {
+            // the inode decides, a socket whose path was unlinked no longer owns it
+            UnixAddr::Path(path) => Self::create_socket_inode(path)?,
+            _ => {
+                if addrs.get(&addr).is_some_and(|q| q.strong_count() > 0) {
+                    return Err(SysError::EADDRINUSE);
+                }
+            }
+        }
+        let queue = UnixAcceptQueue::new();
+        addrs.insert(addr.clone(), Arc::downgrade(&queue));
+        inner.local = addr;
+        inner.queue = Some(queue);
+        Ok(())
+    }
+
+    /// accept connections on the bound address
+    pub fn listen(&self) -> SockResult<()> {
+        let mut inner = self.inner.lock();
+        match inner.state {
+            UnixState::Unconnected => {}
+            UnixState::Listening => return Ok(()),
+            UnixState::Connected(_) => return Err(SysError::EINVAL),
+        }
+        let queue = inner.queue.clone().ok_or(SysError::EINVAL)?;
+        queue.inner.lock().listening = true;
+        inner.state = UnixState::Listening;
+        Ok(())
+    }
+
+    /// connect to the socket listening on `addr`,
+    /// the connection is usable once it is queued for accept
+    pub fn connect(&self, addr: SockAddrUn) -> SockResult<()> {
+        let addr = UnixAddr::from_sockaddr(&addr)?;
+        let mut inner = self.inner.lock();
+        match inner.state {
+            UnixState::Unconnected => {}
+            UnixState::Listening => return Err(SysError::EINVAL),
+            UnixState::Connected(_) => return Err(SysError::EISCONN),
+        }
+        match &addr {
+            UnixAddr::Unnamed => return Err(SysError::EINVAL),
+            UnixAddr::Path(path) => {
+                let dentry = global_find_dentry(path)?;
+                if dentry.is_negative() {
+                    return Err(SysError::ENOENT);
+                }
+                let inode = dentry.inode().ok_or(SysError::ENOENT)?;
+                if inode.inode_type() != InodeMode::SOCKET {
+                    return Err(SysError::ECONNREFUSED);
+                }
+            }
+            UnixAddr::Abstract(_) => {}
+        }
+        let queue = UNIX_ADDRS.lock()
+            .get(&addr)
+            .and_then(|q| q.upgrade())
+            .ok_or(SysError::ECONNREFUSED)?;
+        let internal = SocketPairInternal::new(UNIX_BUF_SIZE);
+        let server_end = SocketPairConnection {
+            internal: internal.clone(),
+            is_first_end: false,
+        };
+        queue.push(server_end, inner.local.clone())?;
+        inner.state = UnixState::Connected(Arc::new(SocketPairConnection {
+            internal,
+            is_first_end: true,
+        }));
+        inner.peer = addr;
+        Ok(())
+    }
+
+    /// wait for a connection and return the socket of the server end
+    pub async fn accept(&self) -> SockResult<UnixSocket> {
+        let (queue, local) = {
+            let inner = self.inner.lock();
+            if !matches!(inner.state, UnixState::Listening) {
+                return Err(SysError::EINVAL);
+            }
+            (inner.queue.clone().unwrap(), inner.local.clone())
+        };
+        let (conn, peer) = UnixAcceptFuture {
+            queue: &queue,
+            nonblock: self.is_nonblocking(),
+        }.await?;
+        Ok(UnixSocket::new_connected(conn, local, peer))
+    }
+
+    pub async fn send(&self, data: &[u8]) -> SockResult<usize> {
+        let conn = self.connection()?;
+        if self.is_nonblocking() && !conn.writable() {
+            return Err(SysError::EAGAIN);
+        }
+        conn.send(data).await
+    }
+
+    pub async fn recv(&self, data: &mut [u8]) -> SockResult<usize> {
+        let conn = self.connection()?;
+        if self.is_nonblocking() && !conn.readable() {
+            return Err(SysError::EAGAIN);
+        }
+        conn.recv(data).await
+    }
+
+    pub fn shutdown(&self) -> SockResult<()> {
+        self.connection()?.close();
+        Ok(())
+    }
+
+    pub async fn poll(&self, events: PollEvents) -> PollEvents {
+        let (conn, queue) = {
+            let inner = self.inner.lock();
+            match &inner.state {
+                UnixState::Connected(conn) => (Some(conn.clone()), None),
+                UnixState::Listening => (None, inner.queue.clone()),
+                UnixState::Unconnected => return PollEvents::empty(),
+            }
+        };
+        if let Some(conn) = conn {
+            return conn.poll(events).await;
+        }
+        // a listener is readable when a connection is waiting for accept
+        let queue = queue.unwrap();
+        let waker = get_waker().await;
+        let mut inner = queue.inner.lock();
+        if !inner.pending.is_empty() {
+            return events & PollEvents::IN;
+        }
+        if events.contains(PollEvents::IN) {
+            inner.wakers.push_back(waker);
+        }
+        PollEvents::empty()
+    }
+
+    pub fn local_addr(&self) -> SockResult<SockAddr> {
+        Ok(self.inner.lock().local.to_sockaddr())
+    }
+
+    pub fn peer_addr(&self) -> SockResult<SockAddr> {
+        let inner = self.inner.lock();
+        match inner.state {
+            UnixState::Connected(_) => Ok(inner.peer.to_sockaddr()),
+            _ => Err(SysError::ENOTCONN),
+        }
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        // free the address, a pathname stays in the fs until unlinked
+        let inner = self.inner.lock();
+        if let Some(queue) = &inner.queue {
+            let mut addrs = UNIX_ADDRS.lock();
+            if addrs.get(&inner.local).is_some_and(|q| q.as_ptr() == Arc::as_ptr(queue)) {
+                addrs.remove(&inner.local);
+            }
+        }
+    }
+}
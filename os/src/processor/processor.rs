@@ -1,13 +1,12 @@
 //!Implementation of [`Processor`] and Intersection of control flow
 use core::arch::asm;
-use core::sync::atomic::{AtomicU64, AtomicUsize};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
 use crate::sync::mutex::SpinNoIrqLock;
 use crate::task::task::{get_cpu_mask, new_shared, turn_cpu_mask_to_id, Shared, TaskControlBlock, TaskStatus};
 use crate::sync::UPSafeCell;
 use crate::processor::context::EnvContext;
-use alloc::collections::vec_deque::VecDeque;
 use alloc::sync::Arc;
-use async_task::Runnable;
 use hal::instruction::{Instruction, InstructionHal};
 use hal::pagetable::PageTableHal;
 use hal::println;
@@ -21,7 +20,9 @@ pub static mut PROCESSORS: [Processor; MAX_PROCESSORS] = [const { Processor::new
 #[cfg(feature = "smp")]
 use super::schedule::TaskLoadTracker;
 #[cfg(feature = "smp")]
-pub type TaskQueue = VecDeque<Runnable>;
+pub type TaskQueue = RunQueue;
+#[cfg(feature = "smp")]
+use crate::executor::sched::RunQueue;
 ///Processor management structure
 pub struct Processor {
     id: usize,
@@ -41,7 +42,15 @@ pub struct Processor {
     /// mark whether there is a task need to be migrate
     pub need_migrate: AtomicUsize,
     /// the cpu timeline
-    pub timeline: AtomicU64
+    pub timeline: AtomicU64,
+    /// microseconds the tasks ran in user mode on this processor
+    user_time: AtomicU64,
+    /// microseconds the tasks ran in kernel mode on this processor
+    system_time: AtomicU64,
+    /// microseconds spent waiting for an interrupt with nothing to run
+    idle_time: AtomicU64,
+    /// user and kernel time of the current task when it was switched in
+    switch_in_times: (Duration, Duration),
 }
 #[cfg(feature = "smp")]
 #[macro_export]
@@ -81,6 +90,10 @@ impl Processor {
             #[cfg(feature = "smp")]
             sche_entity: None,
             timeline: AtomicU64::new(0),
+            user_time: AtomicU64::new(0),
+            system_time: AtomicU64::new(0),
+            idle_time: AtomicU64::new(0),
+            switch_in_times: (Duration::ZERO, Duration::ZERO),
             #[cfg(feature = "smp")]
             need_migrate: AtomicUsize::new(0),
         }
@@ -119,7 +132,7 @@ impl Processor {
     #[cfg(feature = "smp")]
     /// set task_queue when first initiated
     pub fn set_task_queue(&mut self) {
-        self.task_queue = Some(new_shared(VecDeque::new()));
+        self.task_queue = Some(new_shared(RunQueue::new()));
     }
     #[cfg(feature = "smp")]
     generate_unwrap_with_methods!(
@@ -162,6 +175,24 @@ impl Processor {
         let new_timeline = current_timeline.wrapping_add(added_timeline);
         self.timeline.store(new_timeline, core::sync::atomic::Ordering::SeqCst);
     }
+    /// count the time a task ran on this processor since it was switched in
+    fn account_run(&self, (user, kernel): (Duration, Duration)) {
+        let (user_start, kernel_start) = self.switch_in_times;
+        self.user_time.fetch_add(user.saturating_sub(user_start).as_micros() as u64, Ordering::Relaxed);
+        self.system_time.fetch_add(kernel.saturating_sub(kernel_start).as_micros() as u64, Ordering::Relaxed);
+    }
+    /// count the time this processor waited for an interrupt
+    pub fn account_idle(&self, idle: Duration) {
+        self.idle_time.fetch_add(idle.as_micros() as u64, Ordering::Relaxed);
+    }
+    /// the time spent in user mode, in kernel mode and idle on this processor
+    pub fn cpu_times(&self) -> (Duration, Duration, Duration) {
+        (
+            Duration::from_micros(self.user_time.load(Ordering::Relaxed)),
+            Duration::from_micros(self.system_time.load(Ordering::Relaxed)),
+            Duration::from_micros(self.idle_time.load(Ordering::Relaxed)),
+        )
+    }
     /**
      * used in following circumstances: 
      * 1. when a task switch out
@@ -210,6 +241,7 @@ pub fn switch_to_current_task(processor: &mut Processor, task: &mut Arc<TaskCont
     task.set_processor_id(processor.id());
     //info!("[in switch to current task] processor id: {}, task id: {}", processor.id(),task.tid.0);
     task.time_recorder().record_switch_in();
+    processor.switch_in_times = task.time_recorder().time_pair();
     //info!("[in switch to current task] task id: {}kernel_time:{:?}",task.tid(),task.time_recorder().kernel_time());
     if processor.current().is_none() {
         info!("fail to set current! processor id: {}, task id: {}", processor.id(),task.tid.0);
@@ -231,6 +263,7 @@ pub fn switch_out_current_task(processor: &mut Processor, env: &mut EnvContext){
     core::mem::swap(processor.env_mut(), env);
     let current = processor.current().unwrap();
     current.time_recorder().record_switch_out();
+    processor.account_run(current.time_recorder().time_pair());
     processor.add_current_timeline(current.time_recorder().processor_time().as_micros() as u64);
     //info!("task id: {}kernel_time:{:?}",current.tid(),current.time_recorder().kernel_time());
     // float_pointer saved, marked restore is needed
@@ -278,4 +311,14 @@ pub fn current_processor_id() -> usize {
 pub fn init(id: usize){
     info!("init processor {}", id);
     set_processor(id);
+    ONLINE_PROCESSORS.fetch_or(1 << id, Ordering::Relaxed);
+}
+
+/// a bit for each processor brought up
+static ONLINE_PROCESSORS: AtomicUsize = AtomicUsize::new(0);
+
+/// ids of the processors brought up, in order
+pub fn online_processors() -> impl Iterator<Item = usize> {
+    let online = ONLINE_PROCESSORS.load(Ordering::Relaxed);
+    (0..MAX_PROCESSORS).filter(move |id| online & (1 << id) != 0)
 }
\ No newline at end of file
@@ -167,10 +167,28 @@ pub fn load_balance() -> bool {
     }
 }
 
-#[allow(unused)]
-fn migrate_tasks(from_core: usize, to_core: usize) {
-    let task = unsafe{PROCESSORS[from_core].unwrap_with_mut_task_queue(|queue|queue.pop_back().unwrap())};
-    unsafe{PROCESSORS[to_core].unwrap_with_mut_task_queue(|queue| queue.push_back(task))};
+/// move the least urgent task waiting on `from_core` that may run on `to_core`
+/// over to it, false if `from_core` had none left
+fn migrate_tasks(from_core: usize, to_core: usize) -> bool {
+    let Some((task, band, cpus)) = (unsafe{PROCESSORS[from_core].unwrap_with_mut_task_queue(|queue|queue.pop_back_for(to_core))}) else {
+        return false;
+    };
+    unsafe{PROCESSORS[to_core].unwrap_with_mut_task_queue(|queue| queue.push_back(task, band, cpus))};
+    true
+}
+
+/// pull a task waiting on the processor with the longest run queue
+/// over to `idle_core`, which has nothing left to run
+pub fn idle_balance(idle_core: usize) -> bool {
+    use super::processor::online_processors;
+    let busiest = online_processors()
+        .filter(|&id| id != idle_core)
+        .map(|id| (id, unsafe { PROCESSORS[id].unwrap_with_task_queue(|queue| queue.len()) }))
+        .max_by_key(|&(_, len)| len);
+    match busiest {
+        Some((busiest_core, len)) if len > 0 => migrate_tasks(busiest_core, idle_core),
+        _ => false,
+    }
 }
 
 pub fn select_run_queue_index() -> usize {
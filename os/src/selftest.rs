@@ -0,0 +1,307 @@
+//! boot-time self test
+//!
+//! With `selftest` among the boot arguments the kernel runs the cases below instead of
+//! starting initproc, prints a summary and shuts down, reporting a failure to the host
+//! if any case failed. Build and boot it with `make selftest-rv` or `make selftest-la`.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use hal::{
+    addr::{PhysAddrHal, RangePPNHal, VirtAddr, VirtAddrHal},
+    constant::{Constant, ConstantsHal},
+    instruction::{Instruction, InstructionHal},
+    pagetable::{MapPerm, PageLevel, PageTableEntryHal, PageTableHal},
+    println,
+};
+
+use crate::{
+    fs::vfs::{inode::InodeMode, Inode, DCACHE},
+    mm::{
+        allocator::{frames_alloc, free_frames, FrameAllocator},
+        vm::{KernVmSpaceHal, PageFaultAccessType, UserVmSpace, UserVmSpaceHal},
+        FrameTracker, PageTable, KVMSPACE,
+    },
+    net,
+    syscall::mm::MmapFlags,
+};
+
+type CaseResult = Result<(), &'static str>;
+
+/// tally of the cases run so far
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+}
+
+impl Report {
+    fn check(&mut self, name: &str, result: CaseResult) {
+        match result {
+            Ok(()) => {
+                self.passed += 1;
+                println!("[selftest] {:<32} ok", name);
+            }
+            Err(msg) => {
+                self.failed += 1;
+                println!("[selftest] {:<32} FAILED: {}", name, msg);
+            }
+        }
+    }
+}
+
+/// run the whole suite, then shut the machine down
+pub async fn run() {
+    println!("[selftest] start");
+    let mut report = Report::default();
+    report.check("frame alloc/free stress", frame_stress());
+    report.check("page table map/unmap", page_table_round_trip());
+    report.check("cow fork", cow_fork());
+    report.check("global kernel mappings", global_mappings());
+    report.check("page cache write/read/flush", page_cache());
+    report.check("loopback tcp echo", net::tcp_loopback_echo(&echo_payload()).await);
+    println!(
+        "[selftest] {} passed, {} failed: {}",
+        report.passed,
+        report.failed,
+        if report.failed == 0 { "PASS" } else { "FAIL" }
+    );
+    unsafe { Instruction::shutdown(report.failed != 0) }
+}
+
+const STRESS_ROUNDS: usize = 8;
+const STRESS_ALLOCS: usize = 256;
+
+/// the word a block of round `round` is filled with
+fn stress_tag(round: usize, i: usize) -> usize {
+    (round << 32) | i | 0x5a5a_0000
+}
+
+/// allocate blocks of mixed sizes, make sure none of them overlap, free them in a
+/// fragmenting order and make sure every frame comes back
+fn frame_stress() -> CaseResult {
+    let mut frames: Vec<FrameTracker> = Vec::with_capacity(STRESS_ALLOCS);
+    let before = free_frames();
+    for round in 0..STRESS_ROUNDS {
+        for i in 0..STRESS_ALLOCS {
+            let frame = frames_alloc(1 + (i + round) % 4).ok_or("out of frames")?;
+            frame.range_ppn.get_slice_mut::<usize>().fill(stress_tag(round, i));
+            frames.push(frame);
+        }
+        for (i, frame) in frames.iter().enumerate() {
+            if frame.range_ppn.get_slice::<usize>().iter().any(|&w| w != stress_tag(round, i)) {
+                return Err("allocated blocks overlap");
+            }
+        }
+        // free every other block first so that the next round sees holes
+        let mut odd = false;
+        frames.retain(|_| {
+            odd = !odd;
+            odd
+        });
+        frames.clear();
+    }
+    if free_frames() < before {
+        return Err("frames leaked");
+    }
+    Ok(())
+}
+
+const PT_PAGES: usize = 64;
+/// spread the pages over many leaf tables
+const PT_STRIDE: usize = 257;
+
+/// map pages into a fresh page table, translate them back and unmap them, twice so
+/// that the second round reuses the intermediate tables
+fn page_table_round_trip() -> CaseResult {
+    let frames = frames_alloc(PT_PAGES).ok_or("out of frames")?;
+    let mut page_table = PageTable::new_in(0, FrameAllocator);
+    let base = VirtAddr::from(Constant::USER_FILE_BEG).floor();
+    let perm = MapPerm::R | MapPerm::W | MapPerm::U;
+    for _ in 0..2 {
+        for i in 0..PT_PAGES {
+            page_table
+                .map(base + i * PT_STRIDE, frames.range_ppn.start + i, perm, PageLevel::Small)
+                .map_err(|_| "map")?;
+        }
+        for i in 0..PT_PAGES {
+            if page_table.translate_vpn(base + i * PT_STRIDE) != Some(frames.range_ppn.start + i) {
+                return Err("wrong translation after map");
+            }
+        }
+        for i in 0..PT_PAGES {
+            page_table.unmap(base + i * PT_STRIDE).map_err(|_| "unmap")?;
+        }
+        for i in 0..PT_PAGES {
+            if page_table.translate_vpn(base + i * PT_STRIDE).is_some() {
+                return Err("page still mapped after unmap");
+            }
+        }
+    }
+    Ok(())
+}
+
+const COW_PAGES: usize = 4;
+
+/// the bytes of the page at `va`, faulted in for writing first
+fn write_fault(vm: &mut UserVmSpace, va: VirtAddr) -> Result<&'static mut [u8], &'static str> {
+    vm.handle_page_fault(va, PageFaultAccessType::WRITE).map_err(|_| "write fault")?;
+    let pa = vm.translate_va(va).ok_or("page not mapped")?;
+    Ok(pa.get_slice_mut::<u8>(Constant::PAGE_SIZE))
+}
+
+/// the bytes of the page at `va` as they are now, without faulting
+fn peek(vm: &UserVmSpace, va: VirtAddr) -> Result<&'static [u8], &'static str> {
+    let pa = vm.translate_va(va).ok_or("page not mapped")?;
+    Ok(pa.get_slice::<u8>(Constant::PAGE_SIZE))
+}
+
+/// fork an address space and check that writes on either side stay private
+fn cow_fork() -> CaseResult {
+    let mut parent = KVMSPACE.lock().to_user();
+    let len = COW_PAGES * Constant::PAGE_SIZE;
+    let va = parent
+        .alloc_anon_area(
+            VirtAddr::from(0),
+            len,
+            MapPerm::R | MapPerm::W | MapPerm::U,
+            MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS,
+            None,
+        )
+        .map_err(|_| "mmap")?;
+    let pages = || (0..COW_PAGES).map(move |i| va + i * Constant::PAGE_SIZE);
+    for page in pages() {
+        write_fault(&mut parent, page)?.fill(0xaa);
+    }
+
+    let mut child = UserVmSpace::from_existed(&mut parent).map_err(|_| "fork")?;
+    for page in pages() {
+        if parent.translate_va(page) != child.translate_va(page) {
+            return Err("fork did not share the frames");
+        }
+    }
+    // the child writes first and gets copies
+    for page in pages() {
+        write_fault(&mut child, page)?.fill(0x55);
+    }
+    for page in pages() {
+        if parent.translate_va(page) == child.translate_va(page) {
+            return Err("child write did not copy the frame");
+        }
+        if peek(&parent, page)?.iter().any(|&b| b != 0xaa) {
+            return Err("child write reached the parent");
+        }
+        if peek(&child, page)?.iter().any(|&b| b != 0x55) {
+            return Err("child lost its write");
+        }
+    }
+    // the parent is now the only owner and writes in place
+    for page in pages() {
+        let before = parent.translate_va(page);
+        write_fault(&mut parent, page)?.fill(0x11);
+        if parent.translate_va(page) != before {
+            return Err("sole owner copied the frame");
+        }
+        if peek(&child, page)?.iter().any(|&b| b != 0x55) {
+            return Err("parent write reached the child");
+        }
+    }
+    Ok(())
+}
+
+/// written from under a fresh user page table
+static GLOBAL_PROBE: AtomicUsize = AtomicUsize::new(0);
+const GLOBAL_MAGIC: usize = 0x6c6f_6261_6c00;
+
+/// kernel pages are global and user pages never are, and the kernel stays
+/// reachable right after a fresh user page table is enabled
+fn global_mappings() -> CaseResult {
+    // loongarch reaches the kernel image through a direct window instead
+    if cfg!(target_arch = "riscv64") {
+        let kvm = KVMSPACE.lock();
+        let probe = VirtAddr::from(&GLOBAL_PROBE as *const AtomicUsize as usize).floor();
+        let (pte, _) = kvm.get_page_table().find_pte(probe).ok_or("kernel data not mapped")?;
+        if !pte.flags().contains(MapPerm::G) {
+            return Err("kernel page not global");
+        }
+    }
+
+    let mut vm = KVMSPACE.lock().to_user();
+    let va = vm
+        .alloc_anon_area(
+            VirtAddr::from(0),
+            Constant::PAGE_SIZE,
+            MapPerm::R | MapPerm::W | MapPerm::U,
+            MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS,
+            None,
+        )
+        .map_err(|_| "mmap")?;
+    write_fault(&mut vm, va)?.fill(0x5a);
+    let page_table = vm.get_page_table();
+    let (pte, _) = page_table.find_pte(va.floor()).ok_or("user page not mapped")?;
+    if pte.flags().contains(MapPerm::G) {
+        return Err("user page global");
+    }
+    let trampoline = VirtAddr::from(Constant::SIGRET_TRAMPOLINE_BOTTOM).floor();
+    if let Some((pte, _)) = page_table.find_pte(trampoline) {
+        if pte.is_valid() && pte.flags().contains(MapPerm::G) {
+            return Err("sigreturn trampoline global");
+        }
+    }
+
+    // kernel data, heap and stack, all touched before anything refills the tlb
+    vm.enable();
+    GLOBAL_PROBE.store(GLOBAL_MAGIC, Ordering::SeqCst);
+    let heap = vec![GLOBAL_MAGIC; 8];
+    let stack = [GLOBAL_MAGIC; 8];
+    let ok = GLOBAL_PROBE.load(Ordering::SeqCst) == GLOBAL_MAGIC
+        && heap.iter().chain(stack.iter()).all(|&w| w == GLOBAL_MAGIC);
+    // dropping the enabled space switches back to the kernel page table
+    drop(vm);
+    if !ok {
+        return Err("kernel data lost under the user page table");
+    }
+    Ok(())
+}
+
+const CACHE_FILE: &str = "/selftest_page_cache";
+const CACHE_LEN: usize = 4 * Constant::PAGE_SIZE;
+
+/// write a file through the page cache, read it back, flush it and check the disk
+fn page_cache() -> CaseResult {
+    let root = DCACHE
+        .lock()
+        .get("/")
+        .cloned()
+        .ok_or("no root dentry")?
+        .inode()
+        .ok_or("no root inode")?;
+    let inode = root.create(CACHE_FILE, InodeMode::FILE).map_err(|_| "create")?;
+    let ret = page_cache_round_trip(inode);
+    let _ = root.remove(CACHE_FILE, InodeMode::FILE);
+    ret
+}
+
+fn page_cache_round_trip(inode: Arc<dyn Inode>) -> CaseResult {
+    let data: Vec<u8> = (0..CACHE_LEN).map(|i| (i % 251) as u8).collect();
+    if inode.clone().cache_write_at(0, &data) != Ok(CACHE_LEN) {
+        return Err("cache write");
+    }
+    let mut buf = vec![0u8; CACHE_LEN];
+    if inode.clone().cache_read_at(0, &mut buf) != Ok(CACHE_LEN) || buf != data {
+        return Err("cache read returned other data");
+    }
+    inode.cache().ok_or("no page cache")?.flush(inode.as_ref()).map_err(|_| "flush")?;
+    // bypass the cache to see what reached the disk
+    buf.fill(0);
+    if inode.read_at(0, &mut buf) != Ok(CACHE_LEN) || buf != data {
+        return Err("flushed data not on disk");
+    }
+    Ok(())
+}
+
+const ECHO_LEN: usize = 8 * 1024;
+
+fn echo_payload() -> Vec<u8> {
+    (0..ECHO_LEN).map(|i| (i * 7 % 256) as u8).collect()
+}
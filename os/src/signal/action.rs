@@ -51,6 +51,18 @@ bitflags! {
     }
 }
 
+impl SigAction {
+    /// the user provided return address of the handler, set by libc with SA_RESTORER
+    pub fn restorer(&self) -> Option<usize> {
+        let flags = SigActionFlag::from_bits_truncate(self.sa_flags);
+        if flags.contains(SigActionFlag::SA_RESTORER) && self.sa_restorer != 0 {
+            Some(self.sa_restorer)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 /// signal action warpper for kernel
 pub struct KSigAction {
@@ -6,7 +6,7 @@
 
 use log::*;
 
-use crate::{signal::{SigSet, SIGABRT, SIGALRM, SIGBUS, SIGCHLD, SIGCONT, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGIO, SIGKILL, SIGPIPE, SIGPROF, SIGPWR, SIGQUIT, SIGRTMAX, SIGSEGV, SIGSTKFLT, SIGSTOP, SIGSYS, SIGTERM, SIGTRAP, SIGTSTP, SIGTTIN, SIGTTOU, SIGURG, SIGUSR1, SIGUSR2, SIGVTALRM, SIGWINCH, SIGXCPU, SIGXFSZ}, task::current_task, utils::{dyn_future, Async}};
+use crate::{signal::{SigInfo, SigSet, SIGABRT, SIGALRM, SIGBUS, SIGCHLD, SIGCONT, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGIO, SIGKILL, SIGPIPE, SIGPROF, SIGPWR, SIGQUIT, SIGRTMAX, SIGSEGV, SIGSTKFLT, SIGSTOP, SIGSYS, SIGTERM, SIGTRAP, SIGTSTP, SIGTTIN, SIGTTOU, SIGURG, SIGUSR1, SIGUSR2, SIGVTALRM, SIGWINCH, SIGXCPU, SIGXFSZ}, task::{coredump::do_coredump, current_task, task::WaitReport}, utils::{dyn_future, Async}};
 
 pub const SIG_ERR: usize = usize::MAX;
 /// when sig_handler is set to SIG_DFL
@@ -45,10 +45,11 @@ pub fn core_sig_handler(signo: i32) {
     let task = current_task().unwrap().clone();
     info!("[core_sig_handler]: task {} recv sig {}, terminated and coredump", task.gettid(), signo);
 
+    // the core dump flag is only reported when a core file was written
+    let core_flag = if do_coredump(&task, signo as usize) { 0x80 } else { 0 };
     // exit all the members of a thread group (process)
-    task.do_group_exit((signo as usize & 0x7f) | 0x80);
+    task.do_group_exit((signo as usize & 0x7f) | core_flag);
     // task.do_group_exit(0);
-    // todo: produce a core dump file?
 }
 
 /// handlers for Stop
@@ -57,14 +58,16 @@ pub fn stop_sig_handler(signo: i32) {
     let task = current_task().unwrap().clone();
     info!("[stop_sig_handler]: task {} recv sig {}, stop", task.gettid(), signo);
 
-    task.with_thread_group(|tg| {
+    task.with_mut_thread_group(|tg| {
         for t in tg.iter() {
             // set the task status as stopped
             t.set_stopped();
             // the task should be wake up by SIGCONT
             t.set_wake_up_sigs(SigSet::SIGCONT);
         }
-    })
+        tg.wait_report = Some(WaitReport::Stopped(signo as usize));
+    });
+    task.get_leader().signal_parent_stop(SigInfo::CLD_STOPPED);
 }
 
 /// handlers for Cont
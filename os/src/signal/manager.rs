@@ -2,7 +2,7 @@
 //! every process & thread have a signal manager
 //! it is responsible for receving signal and check and handle them
 
-use core::arch::global_asm;
+use core::{arch::global_asm, task::Waker};
 
 use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
 use hal::{addr::VirtAddr, signal::*};
@@ -10,7 +10,7 @@ use crate::mm::vm::{KernVmSpaceHal, UserVmSpaceHal};
 use log::*;
 use crate::processor::processor::{current_task,current_trap_cx};
 
-use super::{action::KSigAction, get_default_handler, ign_sig_handler, SigInfo, SigSet, SIGKILL, SIGRTMAX, SIGRTMIN, SIGSTOP};
+use super::{action::{KSigAction, SigActionFlag}, get_default_handler, ign_sig_handler, SigInfo, SigSet, SIGKILL, SIGRTMAX, SIGRTMIN, SIGSEGV, SIGSTOP};
 
 pub struct SigManager {
     /// Pending standard signals
@@ -27,6 +27,10 @@ pub struct SigManager {
     pub sig_handler: [KSigAction; SIGRTMAX + 1],
     /// Wake up signals
     pub wake_sigs: SigSet,
+    /// how many signalfds take each signal away from the normal delivery
+    pub signalfd_refs: [usize; SIGRTMAX + 1],
+    /// signalfd readers waiting for a signal
+    pub signalfd_wakers: VecDeque<Waker>,
 }
 
 impl SigManager {
@@ -39,6 +43,8 @@ impl SigManager {
             blocked_sigs: SigSet::empty(),
             sig_handler: core::array::from_fn(|signo| KSigAction::new(signo, false)),
             wake_sigs: SigSet::empty(),
+            signalfd_refs: [0; SIGRTMAX + 1],
+            signalfd_wakers: VecDeque::new(),
         }
     }
     pub fn from_another(sig_manager: &SigManager) -> Self {
@@ -51,6 +57,8 @@ impl SigManager {
             blocked_sigs: SigSet::empty(),
             sig_handler: sig_manager.sig_handler,
             wake_sigs: SigSet::empty(),
+            signalfd_refs: [0; SIGRTMAX + 1],
+            signalfd_wakers: VecDeque::new(),
         }
     }
     /// signal manager receive a new signal
@@ -71,7 +79,40 @@ impl SigManager {
                 .or_insert_with(VecDeque::new)
                 .push_back(signo_info);
         }
-        
+        if self.signalfd_refs[signo] > 0 {
+            while let Some(waker) = self.signalfd_wakers.pop_front() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// signals in `mask` are read through a signalfd from now on
+    pub fn attach_signalfd(&mut self, mask: SigSet) {
+        for signo in 1..=SIGRTMAX {
+            if mask.contain_sig(signo) {
+                self.signalfd_refs[signo] += 1;
+            }
+        }
+    }
+
+    /// a signalfd reading `mask` is closed or changes its mask
+    pub fn detach_signalfd(&mut self, mask: SigSet) {
+        for signo in 1..=SIGRTMAX {
+            if mask.contain_sig(signo) {
+                self.signalfd_refs[signo] = self.signalfd_refs[signo].saturating_sub(1);
+            }
+        }
+    }
+
+    /// signals that are read through signalfds instead of delivered to handlers
+    pub fn signalfd_sigs(&self) -> SigSet {
+        let mut ret = SigSet::empty();
+        for signo in 1..=SIGRTMAX {
+            if self.signalfd_refs[signo] > 0 {
+                ret.add_sig(signo);
+            }
+        }
+        ret
     }
 
     /// return all pending signals
@@ -148,6 +189,18 @@ impl SigManager {
         }
     }
 
+    /// the action that handles `signo` now,
+    /// a SA_RESETHAND action reverts to the default before its handler is entered,
+    /// so the next occurrence, even a nested one with SA_NODEFER, takes the default action
+    pub fn take_action(&mut self, signo: usize) -> KSigAction {
+        let action = self.sig_handler[signo];
+        let flags = SigActionFlag::from_bits_truncate(action.sa.sa_flags);
+        if action.is_user && flags.contains(SigActionFlag::SA_RESETHAND) {
+            self.sig_handler[signo] = KSigAction::new(signo, false);
+        }
+        action
+    }
+
     /// dequeue a pending signal to handle
     /// called by `check_and_handle`
     pub fn dequeue_one(&mut self) -> Option<SigInfo> {
@@ -159,7 +212,7 @@ impl SigManager {
         while cnt < len {
             let sig = self.pending_sigs.pop_front().unwrap();
             cnt += 1;
-            if sig.si_signo != SIGKILL && sig.si_signo != SIGSTOP && self.blocked_sigs.contain_sig(sig.si_signo) {
+            if sig.si_signo != SIGKILL && sig.si_signo != SIGSTOP && (self.blocked_sigs.contain_sig(sig.si_signo) || self.signalfd_refs[sig.si_signo] > 0) {
                 // cannot handle currently, push back to wait for unblock
                 self.pending_sigs.push_back(sig);
                 continue;
@@ -173,7 +226,7 @@ impl SigManager {
         for (&signo, queue) in self.pending_rt_sigs.iter_mut() {
             assert!(signo >= SIGRTMIN);
             assert!(signo <= SIGRTMAX);
-            if self.blocked_sigs.contain_sig(signo) {
+            if self.blocked_sigs.contain_sig(signo) || self.signalfd_refs[signo] > 0 {
                 continue;
             }
             if let Some(sig) = queue.pop_front() {
@@ -243,4 +296,12 @@ impl SigManager {
         sigmask.remove(SigSet::SIGSTOP | SigSet::SIGKILL);
         self.blocked_sigs = sigmask;
     }
+
+    /// the frame of a handler cannot be written: SIGSEGV takes its default
+    /// action whatever the task set for it, like force_sigsegv of Linux
+    pub fn force_sigsegv(&mut self) {
+        self.sig_handler[SIGSEGV] = KSigAction::new(SIGSEGV, false);
+        self.blocked_sigs.remove_sig(SIGSEGV);
+        self.receive(SigInfo { si_signo: SIGSEGV, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 });
+    }
 }
\ No newline at end of file
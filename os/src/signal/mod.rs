@@ -184,6 +184,8 @@ pub struct SigInfo {
     pub si_code: i32,
     /// pid of sender
     pub si_pid: Option<usize>,
+    /// sigval sent with sigqueue
+    pub si_value: usize,
 }
 
 impl SigInfo {
@@ -232,4 +234,64 @@ pub struct LinuxSigInfo {
     pub si_code: i32,
     pub _pad: [i32; 29],
     _align: [u64; 0],
-}
\ No newline at end of file
+}
+
+impl LinuxSigInfo {
+    /// the sigval of `_rt`, at offset 24 of siginfo_t
+    pub fn si_value(&self) -> usize {
+        (self._pad[3] as u32 as usize) | ((self._pad[4] as u32 as usize) << 32)
+    }
+}
+
+impl From<SigInfo> for LinuxSigInfo {
+    fn from(info: SigInfo) -> Self {
+        let mut ret = Self::default();
+        ret.si_signo = info.si_signo as _;
+        ret.si_code = info.si_code;
+        ret._pad[1] = info.si_pid.unwrap_or(0) as i32;
+        ret._pad[3] = info.si_value as i32;
+        ret._pad[4] = (info.si_value >> 32) as i32;
+        ret
+    }
+}
+
+/// the record read from a signalfd, `struct signalfd_siginfo` in linux
+#[derive(Default, Copy, Clone)]
+#[repr(C)]
+pub struct SignalFdSigInfo {
+    pub ssi_signo: u32,
+    pub ssi_errno: i32,
+    pub ssi_code: i32,
+    pub ssi_pid: u32,
+    pub ssi_uid: u32,
+    pub ssi_fd: i32,
+    pub ssi_tid: u32,
+    pub ssi_band: u32,
+    pub ssi_overrun: u32,
+    pub ssi_trapno: u32,
+    pub ssi_status: i32,
+    pub ssi_int: i32,
+    pub ssi_ptr: u64,
+    pub ssi_utime: u64,
+    pub ssi_stime: u64,
+    pub ssi_addr: u64,
+    pub ssi_addr_lsb: u16,
+    pub _pad2: u16,
+    pub ssi_syscall: i32,
+    pub ssi_call_addr: u64,
+    pub ssi_arch: u32,
+    pub _pad: [u8; 28],
+}
+
+impl From<SigInfo> for SignalFdSigInfo {
+    fn from(info: SigInfo) -> Self {
+        Self {
+            ssi_signo: info.si_signo as u32,
+            ssi_code: info.si_code,
+            ssi_pid: info.si_pid.unwrap_or(0) as u32,
+            ssi_int: info.si_value as i32,
+            ssi_ptr: info.si_value as u64,
+            ..Default::default()
+        }
+    }
+}
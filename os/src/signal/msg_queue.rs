@@ -364,6 +364,7 @@ pub fn dispatch_notification(registration: &NotifyRegistration, sender_pid: usiz
                 si_signo: registration.event.sigev_signo as usize,
                 si_code: SigInfo::MESGQ,
                 si_pid: Some(sender_pid),
+                si_value: unsafe { registration.event.sigev_value.sival_ptr } as usize,
             };
             registration.task.recv_sigs_process_level(sig_info);
         }
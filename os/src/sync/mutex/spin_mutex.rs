@@ -49,6 +49,8 @@ impl<T, S: MutexSupport> SpinMutex<T, S> {
             if cur_owner >= Constant::MAX_PROCESSORS {
                 panic!("owner {:#x} {} > MAX_PROCESSORS", &self.owner as *const _ as usize, cur_owner);
             }
+            // the owner may be waiting for this hart to flush its TLB
+            Instruction::handle_remote_requests();
             core::hint::spin_loop();
             try_count += 1;
             if try_count == 0x1000000 {
@@ -5,7 +5,7 @@ use crate::{fs::{tmpfs::{dentry::TmpDentry, inode::{EmptyFile, TmpSysInode}}, vf
 pub fn tmp_fd() -> Result<usize, SysError> {
     let task = current_task().unwrap().clone();
     let dentry = TmpDentry::new("fake", None);
-    let inode = TmpSysInode::new(InodeMode::FILE, Arc::new(EmptyFile {}));
+    let inode = TmpSysInode::new(None, InodeMode::FILE, Arc::new(EmptyFile {}));
     dentry.set_inode(inode);
     let file = dentry.open(OpenFlags::empty()).unwrap();
     let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
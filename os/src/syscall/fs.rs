@@ -1,14 +1,22 @@
 //! File and filesystem-related syscalls
 use core::{any::Any, cmp, ops::DerefMut, ptr::copy_nonoverlapping};
 
-use alloc::{string::{String, ToString}, sync::Arc, vec};
+use alloc::{format, string::{String, ToString}, sync::{Arc, Weak}, vec, vec::Vec};
 use hal::{addr::{PhysAddrHal, PhysPageNumHal, VirtAddr, VirtAddrHal}, constant::{Constant, ConstantsHal}, instruction::{Instruction, InstructionHal}, pagetable::PageTableHal, println};
 use log::{info, warn};
 use strum::FromRepr;
 use virtio_drivers::PAGE_SIZE;
 use crate::{config::BLOCK_SIZE, drivers::BLOCK_DEVICE, fs::{
-    fs::CNXFS, get_filesystem, pipefs::make_pipe, vfs::{dentry::{self, global_find_dentry, global_update_dentry}, file::{open_file, SeekFrom}, fstype::MountFlags, inode::{DirentFileType, InodeMode}, Dentry, DentryState, File}, AtFlags, Kstat, OpenFlags, RenameFlags, RwfFlags, SpliceFlags, StatFs, Xstat, XstatMask, BLKSSZGET
-}, mm::{translate_uva_checked, vm::{PageFaultAccessType, UserVmSpaceHal}, UserPtrRaw, UserSliceRaw}, processor::context::SumGuard, task::{fs::{FdFlags, FdInfo}, signal::IntrBySignalFuture, task::TaskControlBlock}, timer::{ffi::TimeSpec, get_current_time_duration}, utils::{block_on, is_page_aligned, Select2Futures, SelectOutput}};
+    fs::CNXFS, get_filesystem, sync_all, pipefs::{make_pipe, open_fifo, PipeFile}, devfs::node::open_dev_node, eventfd::{make_eventfd, EventFdFlags}, inotify::{make_inotify, notify_child, notify_dentry, notify_inode, notify_move, notify_unlinked, InotifyFile, InotifyFlags, InotifyMask}, vfs::{dentry::{self, global_find_dentry, global_update_dentry}, file::{open_file, PollEvents, SeekFrom}, fstype::MountFlags, inode::{DirentFileType, InodeMode}, lock::{release_file_locks, FileLockFuture, FileLocks, LockKind, RecordLock}, Dentry, Inode, DentryState, File}, AtFlags, FallocFlags, Kstat, OpenFlags, OpenHow, RenameFlags, ResolveFlags, RwfFlags, SpliceFlags, StatFs, Xstat, XstatMask, BLKSSZGET, OPEN_HOW_SIZE_VER0
+}, mm::{translate_uva_checked, vm::{PageFaultAccessType, UserVmSpaceHal}, UserPtrRaw, UserSliceRaw}, processor::context::SumGuard, task::{fs::{FdFlags, FdInfo}, manager::TASK_MANAGER, signal::IntrBySignalFuture, task::TaskControlBlock}, timer::{clock::realtime_time, ffi::TimeSpec}, utils::{block_on, is_page_aligned, Select2Futures, SelectOutput}};
+use crate::fs::fscontext::{
+    find_context_fs, make_fscontext, make_mount_file, DetachedMount, FsConfigCmd, FsContextFile, FsContextPhase, FsMountFlags, FsOpenFlags, MountAttr, MountFile, MoveMountFlags
+};
+use crate::fs::mount::{
+    add_old_root, attach_mount, is_below, is_descendant, mount_table, remove_old_root, MntIdReq, Statmount, LISTMOUNT_REVERSE, LSMT_ROOT, MNT_ID_REQ_SIZE_VER0, MNT_ID_REQ_SIZE_VER1, MS_PRIVATE,
+    STATMOUNT_FS_TYPE, STATMOUNT_MNT_BASIC, STATMOUNT_MNT_OPTS, STATMOUNT_MNT_POINT, STATMOUNT_MNT_ROOT, STATMOUNT_PROPAGATE_FROM, STATMOUNT_SB_BASIC
+};
+use crate::fs::vfs::superblock::{MNT_UNIQUE_ID_OFFSET, NAME_MAX};
 use crate::utils::{
     path::*,
     string::*,
@@ -26,7 +34,7 @@ pub async fn sys_write(fd: usize, buf: usize, len: usize) -> SysResult {
             .ensure_read(&mut task.get_vm_space().lock())
             .ok_or(SysError::EFAULT)?;
     let buf = user_buf.to_ref();
-    let ret = file.write(buf).await?;
+    let ret = write_interruptible(&task, &file, buf).await?;
 
     // let start = buf & !(Constant::PAGE_SIZE - 1);
     // let end = buf + len;
@@ -45,6 +53,54 @@ pub async fn sys_write(fd: usize, buf: usize, len: usize) -> SysResult {
     return Ok(ret as isize);
 }
 
+/// whether a write to `file` may have to wait for the other side:
+/// pipes, sockets and terminals
+fn write_may_block(file: &Arc<dyn File>) -> bool {
+    file.inode().map_or(false, |inode| {
+        matches!(inode.inode_type(), InodeMode::FIFO | InodeMode::SOCKET | InodeMode::CHAR)
+    })
+}
+
+/// write `buf` to `file`; where the write may have to wait, keep writing until
+/// all of it is taken, unless O_NONBLOCK asks for a single try. A signal cuts
+/// the wait short: the bytes already taken are returned, EINTR only when there
+/// are none, and the same goes for an error after some progress
+async fn write_interruptible(task: &Arc<TaskControlBlock>, file: &Arc<dyn File>, buf: &[u8]) -> Result<usize, SysError> {
+    if !write_may_block(file) {
+        return file.write(buf).await;
+    }
+    let nonblock = file.flags().contains(OpenFlags::O_NONBLOCK);
+    let mask = task.sig_manager.lock().get_sigmask();
+    task.set_interruptable();
+    task.set_wake_up_sigs(!mask);
+    let mut written = 0;
+    let mut error = None;
+    while written < buf.len() {
+        let write_future = file.write(&buf[written..]);
+        let intr_future = IntrBySignalFuture { task: task.clone(), mask };
+        match Select2Futures::new(write_future, intr_future).await {
+            SelectOutput::Output1(Ok(0)) => break,
+            SelectOutput::Output1(Ok(len)) => written += len,
+            SelectOutput::Output1(Err(e)) => {
+                error = Some(e);
+                break;
+            }
+            SelectOutput::Output2(_) => {
+                log::info!("[write_interruptible]: write intr by signal after {written} bytes");
+                error = Some(SysError::EINTR);
+                break;
+            }
+        }
+        if nonblock {
+            break;
+        }
+    }
+    task.set_running();
+    match error {
+        Some(e) if written == 0 => Err(e),
+        _ => Ok(written),
+    }
+}
 
 /// syscall: read
 pub async fn sys_read(fd: usize, buf: usize, len: usize) -> SysResult {
@@ -106,7 +162,12 @@ pub async fn sys_read(fd: usize, buf: usize, len: usize) -> SysResult {
 pub fn sys_close(fd: usize) -> SysResult {
     log::info!("[sys_close]: close on fd: {}", fd);
     let task = current_task().unwrap();
-    task.with_mut_fd_table(|table| table.remove(fd))?;
+    let file = task.with_mut_fd_table(|table| {
+        let file = table.get_file(fd)?;
+        table.remove(fd)?;
+        Ok::<_, SysError>(file)
+    })?;
+    release_file_locks(file, task.pid());
     Ok(0)
 }
 
@@ -150,7 +211,7 @@ pub fn sys_getcwd(buf: usize, len: usize) -> SysResult {
     let task = current_task().unwrap();
     
     task.with_cwd(|cwd| {
-        let path = cwd.path();
+        let path = task.visible_path(cwd);
         if len < path.len() + 1 {
             info!("[sys_getcwd]: buf len too small to recv path");
             return Err(SysError::ERANGE);
@@ -199,9 +260,14 @@ pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: u32) -> SysResult {
 /// If pathname is relative and dirfd is the special value AT_FDCWD, 
 /// then pathname is interpreted relative to the current working directory of the calling process (like open(2)).
 /// If pathname is absolute, then dirfd is ignored.
-pub fn sys_openat(dirfd: isize, pathname: *const u8, flags: i32, _mode: u32) -> SysResult {
+pub async fn sys_openat(dirfd: isize, pathname: *const u8, flags: i32, _mode: u32) -> SysResult {
     let open_flags = OpenFlags::from_bits(flags as i32).unwrap();
-    let at_flags = AtFlags::from_bits_truncate(flags as i32);
+    // the other bits of flags are open flags, only O_NOFOLLOW stops following the last link
+    let at_flags = if open_flags.contains(OpenFlags::O_NOFOLLOW) {
+        AtFlags::AT_SYMLINK_NOFOLLOW
+    } else {
+        AtFlags::empty()
+    };
     let task = current_task().unwrap().clone();
     let path = user_path_to_string(
             UserPtrRaw::new(pathname), 
@@ -210,22 +276,33 @@ pub fn sys_openat(dirfd: isize, pathname: *const u8, flags: i32, _mode: u32) ->
     // log::warn!("path {:?}", path);
     // log::info!("task {} trying to open {}, oflags: {:?}, atflags: {:?}, dirfd {}", task.tid(), path, open_flags, at_flags, dirfd);
     let dentry = at_helper(task.clone(), dirfd, pathname, at_flags)?;
+    // the dir may not exist
+    if open_flags.contains(OpenFlags::O_CREAT)
+        && abs_path_to_name(&path).unwrap() != abs_path_to_name(&dentry.path()).unwrap() {
+        return Err(SysError::ENOENT);
+    }
+    open_dentry(task, dentry, open_flags).await
+}
+
+/// open the dentry found by a open syscall, create the file if asked
+async fn open_dentry(task: Arc<TaskControlBlock>, dentry: Arc<dyn Dentry>, open_flags: OpenFlags) -> SysResult {
+    let mut created = false;
     if open_flags.contains(OpenFlags::O_CREAT) {
         // log::warn!("[sys_openat]: O_CREAT met");
-        // the dir may not exist
-        if abs_path_to_name(&path).unwrap() != abs_path_to_name(&dentry.path()).unwrap() {
-            return Err(SysError::ENOENT);
-        }
-        // inode not exist, create it as a regular file
         if open_flags.contains(OpenFlags::O_EXCL) && dentry.state() != DentryState::NEGATIVE {
             return Err(SysError::EEXIST);
         }
+    }
+    // inode not exist, create it as a regular file;
+    // an existing one is opened as it is, a FIFO must not turn into a regular file
+    if open_flags.contains(OpenFlags::O_CREAT) && dentry.state() == DentryState::NEGATIVE {
         let parent = dentry.parent().expect("[sys_openat]: can not open root as file!");
-        let name = abs_path_to_name(&path).unwrap();
+        let name = dentry.name().to_string();
         let new_inode = parent.inode().unwrap().create(&name, InodeMode::FILE);
         match new_inode {
             Ok(inode) => {
                 dentry.set_inode(inode);
+                created = true;
             }
             Err(SysError::EEXIST) => {}
             _ => {
@@ -235,16 +312,61 @@ pub fn sys_openat(dirfd: isize, pathname: *const u8, flags: i32, _mode: u32) ->
         
         // we shall not add child to parent until child is valid!
         parent.add_child(dentry.clone());
+        if created {
+            notify_child(&parent, &name, InotifyMask::IN_CREATE, 0);
+        }
     }
     if dentry.state() == DentryState::NEGATIVE {
-        log::warn!("cannot open {}, not exist", path);
+        log::warn!("cannot open {}, not exist", dentry.path());
         return Err(SysError::ENOENT);
     }
     let inode = dentry.inode().unwrap();
+    // a file created just now may be opened whatever mode it was given
+    if !created && !open_flags.contains(OpenFlags::O_PATH) {
+        let mut mask = 0;
+        if open_flags.readable() {
+            mask |= R_OK;
+        }
+        if open_flags.writable() || open_flags.contains(OpenFlags::O_TRUNC) {
+            mask |= W_OK;
+        }
+        inode.inode_inner().permission(task.euid() as u32, task.egid() as u32, mask)?;
+    }
+    if open_flags.contains(OpenFlags::O_NOFOLLOW) && !open_flags.contains(OpenFlags::O_PATH)
+        && inode.inode_type() == InodeMode::LINK {
+        return Err(SysError::ELOOP);
+    }
     if open_flags.contains(OpenFlags::O_DIRECTORY) && inode.inode_type() != InodeMode::DIR {
         return Err(SysError::ENOTDIR);
     }
-    let file = dentry.open(open_flags).unwrap();
+    // only files holding their data in a page cache can be truncated,
+    // the files made up by procfs and devfs take writes as they come
+    if open_flags.contains(OpenFlags::O_TRUNC) && open_flags.writable()
+        && !created && inode.inode_type() == InodeMode::FILE && inode.cache().is_some() {
+        inode.truncate(0)?;
+        notify_dentry(&dentry, InotifyMask::IN_MODIFY);
+    }
+    let file = match inode.inode_type() {
+        _ if open_flags.contains(OpenFlags::O_PATH) => dentry.open(open_flags).unwrap(),
+        InodeMode::FIFO => {
+            let current_mask = task.sig_manager.lock().get_sigmask();
+            let intr_future = IntrBySignalFuture {
+                task: task.clone(),
+                mask: current_mask,
+            };
+            task.set_interruptable();
+            task.set_wake_up_sigs(!current_mask);
+            let result = Select2Futures::new(open_fifo(dentry, open_flags, PIPE_BUF_LEN), intr_future).await;
+            task.set_running();
+            match result {
+                SelectOutput::Output1(file) => file?,
+                SelectOutput::Output2(_) => return Err(SysError::EINTR),
+            }
+        }
+        // the nodes of devfs open by themselves, only nodes made by mknod carry a device number
+        InodeMode::CHAR | InodeMode::BLOCK if inode.inode_inner().rdev() != 0 => open_dev_node(dentry, open_flags)?,
+        _ => dentry.open(open_flags).unwrap(),
+    };
     file.set_flags(open_flags);
     let fd = task.with_mut_fd_table(|table| table.alloc_fd())?;
     let fd_info = FdInfo { file, flags: open_flags.into() };
@@ -253,6 +375,67 @@ pub fn sys_openat(dirfd: isize, pathname: *const u8, flags: i32, _mode: u32) ->
     return Ok(fd as isize)
 }
 
+/// syscall: openat2
+/// an extension of openat, `how` gives the flags, the mode
+/// and the RESOLVE_* constraints on the path walk,
+/// `size` is the size of `how` known by the caller, so that the struct can grow
+pub async fn sys_openat2(dirfd: isize, pathname: *const u8, how: *const u8, size: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    if size < OPEN_HOW_SIZE_VER0 {
+        return Err(SysError::EINVAL);
+    }
+    if size > PAGE_SIZE {
+        return Err(SysError::E2BIG);
+    }
+    let how = {
+        let mut vm = task.get_vm_space().lock();
+        let raw = UserSliceRaw::new(how, size)
+            .ensure_read(&mut vm)
+            .ok_or(SysError::EFAULT)?;
+        let raw = raw.to_ref();
+        // fields unknown to us must be zero
+        if raw[OPEN_HOW_SIZE_VER0..].iter().any(|&b| b != 0) {
+            return Err(SysError::E2BIG);
+        }
+        let field = |i: usize| u64::from_ne_bytes(raw[i * 8..i * 8 + 8].try_into().unwrap());
+        OpenHow { flags: field(0), mode: field(1), resolve: field(2) }
+    };
+    let open_flags = u32::try_from(how.flags).ok()
+        .and_then(|flags| OpenFlags::from_bits(flags as i32))
+        .ok_or(SysError::EINVAL)?;
+    let resolve = ResolveFlags::from_bits(how.resolve).ok_or(SysError::EINVAL)?;
+    if how.mode != 0 && !open_flags.intersects(OpenFlags::O_CREAT | OpenFlags::O_TMPFILE) {
+        return Err(SysError::EINVAL);
+    }
+    if how.mode & !0o7777 != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if resolve.contains(ResolveFlags::RESOLVE_BENEATH | ResolveFlags::RESOLVE_IN_ROOT) {
+        return Err(SysError::EINVAL);
+    }
+    if resolve.contains(ResolveFlags::RESOLVE_CACHED) {
+        // lookups may reach the file system, let the caller retry without it
+        return Err(SysError::EAGAIN);
+    }
+    let path = user_path_to_string(
+        UserPtrRaw::new(pathname),
+        &mut task.get_vm_space().lock()
+    )?;
+    log::info!("[sys_openat2] task {} open {}, flags {:?}, resolve {:?}", task.tid(), path, open_flags, resolve);
+    if path.is_empty() {
+        return Err(SysError::ENOENT);
+    }
+    let start = if dirfd as i32 == AtFlags::AT_FDCWD.bits() {
+        task.with_cwd(|d| d.clone())
+    } else {
+        let dir = task.with_fd_table(|t| t.get_file(dirfd as usize))?;
+        dir.dentry().ok_or(SysError::ENOTDIR)?
+    };
+    let follow_last = !open_flags.contains(OpenFlags::O_NOFOLLOW);
+    let dentry = start.resolve(&path, resolve, follow_last)?;
+    open_dentry(task, dentry, open_flags).await
+}
+
 /// syscall: mkdirat
 /// If the pathname given in pathname is relative, 
 /// then it is interpreted relative to the directory referred to by the file descriptor dirfd 
@@ -280,23 +463,84 @@ pub fn sys_mkdirat(dirfd: isize, pathname: *const u8, mode: usize) -> SysResult
     dentry.set_inode(new_inode);
     dentry.set_state(DentryState::USED);
     parent.add_child(dentry);
+    notify_child(&parent, &name, InotifyMask::IN_CREATE | InotifyMask::IN_ISDIR, 0);
+    Ok(0)
+}
+
+/// syscall: mknodat
+/// create a file of the type in `mode`: a regular file, a FIFO, a socket,
+/// or a character or block device node standing for device `dev`, which only root may make;
+/// the permission bits of `mode` go to the new file, owned by the caller
+pub fn sys_mknodat(dirfd: isize, pathname: *const u8, mode: u32, dev: u64) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let path = user_path_to_string(
+            UserPtrRaw::new(pathname),
+            &mut task.get_vm_space().lock()
+    )?;
+    let mode = InodeMode::from_bits_truncate(mode);
+    let (ty, rdev) = match mode.get_type() {
+        ty if ty.is_empty() || ty == InodeMode::FILE => (InodeMode::FILE, 0),
+        InodeMode::FIFO | InodeMode::SOCKET => (mode.get_type(), 0),
+        InodeMode::CHAR | InodeMode::BLOCK => {
+            if task.euid() != 0 {
+                return Err(SysError::EPERM);
+            }
+            (mode.get_type(), dev)
+        }
+        InodeMode::DIR => return Err(SysError::EPERM),
+        _ => return Err(SysError::EINVAL),
+    };
+    let dentry = at_helper(task.clone(), dirfd, pathname, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    if dentry.state() != DentryState::NEGATIVE {
+        return Err(SysError::EEXIST);
+    }
+    let parent = dentry.parent().ok_or(SysError::ENOENT)?;
+    let name = abs_path_to_name(&path).ok_or(SysError::ENOENT)?;
+    let inode = parent.inode().ok_or(SysError::ENOENT)?.mknod(&name, ty, rdev)?;
+    let inner = inode.inode_inner();
+    inner.set_mode(ty | (mode & InodeMode::from_bits_truncate(0o7777)));
+    inner.set_uid(task.euid() as u32);
+    inner.set_gid(task.egid() as u32);
+    inode.setattr()?;
+    dentry.set_inode(inode);
+    dentry.set_state(DentryState::USED);
+    parent.add_child(dentry);
+    notify_child(&parent, &name, InotifyMask::IN_CREATE, 0);
     Ok(0)
 }
 
 /// syscall: fstatat
+/// with AT_EMPTY_PATH and an empty (or NULL) path, stat what `dirfd` refers to,
+/// whatever it is and even for an O_PATH fd, like fstat does;
+/// with AT_SYMLINK_NOFOLLOW a trailing symlink is stated rather than its target
 pub fn sys_fstatat(dirfd: isize, pathname: *const u8, stat_buf: usize, flags: i32) -> SysResult {
     let _sum_guard= SumGuard::new();
     let at_flags = AtFlags::from_bits_truncate(flags);
-    let o_flags = OpenFlags::from_bits_truncate(flags);
-    log::debug!("fstatat dirfd {}, at_flags {:?}, oflags {:?}", dirfd, at_flags, o_flags);
     let task = current_task().unwrap().clone();
-    let dentry = at_helper(task.clone(), dirfd, pathname, at_flags)?;
-    log::info!("fstatat dirfd {}, path {}, at_flags {:?}, oflags {:?}", dirfd, dentry.path(), at_flags, o_flags);
-    let inode = dentry.inode().ok_or(SysError::ENOENT)?;
-    // debug_assert!(dentry.is_negative() == false);
-    inode.clone().access()?;
-    let stat = inode.getattr();
-    // log::info!("[sys_fstatat]: {} size {}", dentry.path(), stat.st_size);
+    let empty_path = at_flags.contains(AtFlags::AT_EMPTY_PATH);
+    let path = if pathname.is_null() && empty_path {
+        String::new()
+    } else {
+        if pathname.is_null() {
+            return Err(SysError::EFAULT);
+        }
+        user_path_to_string(UserPtrRaw::new(pathname), &mut task.get_vm_space().lock())?
+    };
+    log::info!("fstatat dirfd {}, path {}, at_flags {:?}", dirfd, path, at_flags);
+    let stat = if path.is_empty() && empty_path && dirfd as i32 != AtFlags::AT_FDCWD.bits() {
+        if dirfd < 0 {
+            return Err(SysError::EBADF);
+        }
+        let file = task.with_fd_table(|t| t.get_file(dirfd as usize))?;
+        file.inode()?.getattr()
+    } else {
+        if !path.starts_with("/") && dirfd < 0 && dirfd as i32 != AtFlags::AT_FDCWD.bits() {
+            return Err(SysError::EBADF);
+        }
+        let dentry = at_helper1(task.clone(), dirfd, &path, at_flags)?;
+        let inode = dentry.inode().ok_or(SysError::ENOENT)?;
+        inode.getattr()
+    };
     let stat_ptr = UserPtrRaw::new(stat_buf as *const Kstat)
         .ensure_write(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
@@ -315,12 +559,10 @@ pub fn sys_chdir(path: *const u8) -> SysResult {
             &mut task.get_vm_space().lock()
         )?;
     info!("try to switch to path {}", path);
-    let old_dentry = task.cwd();
-    let new_dentry = if path.starts_with("/") {
-        global_find_dentry(&path)?
-    } else {
-        old_dentry.find(&path)?.ok_or(SysError::ENOENT)?
-    };
+    if path.is_empty() {
+        return Err(SysError::ENOENT);
+    }
+    let new_dentry = at_helper1(task.clone(), AtFlags::AT_FDCWD.bits() as _, &path, AtFlags::empty())?;
     if new_dentry.is_negative() {
         log::warn!("[sys_chdir]: dentry not found");
         return Err(SysError::ENOENT);
@@ -334,6 +576,31 @@ pub fn sys_chdir(path: *const u8) -> SysResult {
     }
 }
 
+/// chroot() changes the root directory of the calling process to `path`,
+/// absolute paths and `..` no longer lead above it; the cwd stays where it is
+pub fn sys_chroot(path: *const u8) -> SysResult {
+    let task = current_task().unwrap().clone();
+    if task.euid() != 0 {
+        return Err(SysError::EPERM);
+    }
+    let path = user_path_to_string(
+        UserPtrRaw::new(path),
+        &mut task.get_vm_space().lock()
+    )?;
+    if path.is_empty() {
+        return Err(SysError::ENOENT);
+    }
+    let dentry = at_helper1(task.clone(), AtFlags::AT_FDCWD.bits() as _, &path, AtFlags::empty())?;
+    if dentry.is_negative() {
+        return Err(SysError::ENOENT);
+    }
+    if !dentry.inode().unwrap().inode_type().is_dir() {
+        return Err(SysError::ENOTDIR);
+    }
+    task.set_root(dentry);
+    Ok(0)
+}
+
 /// The fchdir() function shall be equivalent to chdir() except that
 /// the directory that is to be the new current working directory is
 /// specified by the file descriptor fildes.
@@ -381,6 +648,84 @@ pub fn sys_pipe2(pipe: *mut i32, flags: u32) -> SysResult {
     Ok(0)
 }
 
+/// syscall eventfd2
+pub fn sys_eventfd2(initval: u32, flags: u32) -> SysResult {
+    let flags = EventFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    let file = make_eventfd(initval as u64, flags);
+    let fd_flags = if flags.contains(EventFdFlags::EFD_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+    task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: fd_flags }))?;
+    info!("[sys_eventfd2] fd: {}, initval: {}, flags: {:?}", fd, initval, flags);
+    Ok(fd as isize)
+}
+
+/// syscall: inotify_init1
+/// create an inotify instance, its fd reads the events of the files it watches
+pub fn sys_inotify_init1(flags: u32) -> SysResult {
+    let flags = InotifyFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    let file = make_inotify(flags);
+    let fd_flags = if flags.contains(InotifyFlags::IN_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+    task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: fd_flags }))?;
+    info!("[sys_inotify_init1] fd: {}, flags: {:?}", fd, flags);
+    Ok(fd as isize)
+}
+
+/// the inotify instance `fd` refers to
+fn inotify_file(task: &Arc<TaskControlBlock>, fd: usize) -> Result<Arc<InotifyFile>, SysError> {
+    let file = task.with_fd_table(|t| t.get_file(fd))?;
+    file.downcast_arc::<InotifyFile>().map_err(|_| SysError::EINVAL)
+}
+
+/// syscall: inotify_add_watch
+/// watch the file at `pathname` for the events in `mask`, which needs read permission on it;
+/// return the watch descriptor, the same one for a file watched already
+pub fn sys_inotify_add_watch(fd: usize, pathname: *const u8, mask: u32) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let mask = InotifyMask::from_bits(mask).ok_or(SysError::EINVAL)?;
+    if !mask.intersects(InotifyMask::ALL_EVENTS)
+        || mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE) {
+        return Err(SysError::EINVAL);
+    }
+    let inotify = inotify_file(&task, fd)?;
+    let at_flags = if mask.contains(InotifyMask::IN_DONT_FOLLOW) {
+        AtFlags::AT_SYMLINK_NOFOLLOW
+    } else {
+        AtFlags::empty()
+    };
+    let dentry = at_helper(task.clone(), AtFlags::AT_FDCWD.bits() as isize, pathname, at_flags)?;
+    if dentry.is_negative() {
+        return Err(SysError::ENOENT);
+    }
+    let inode = dentry.inode().ok_or(SysError::ENOENT)?;
+    if mask.contains(InotifyMask::IN_ONLYDIR) && inode.inode_type() != InodeMode::DIR {
+        return Err(SysError::ENOTDIR);
+    }
+    inode.inode_inner().permission(task.euid() as u32, task.egid() as u32, R_OK)?;
+    let wd = inotify.inotify().add_watch(&inode, mask)?;
+    info!("[sys_inotify_add_watch] fd {} watch {} as {}, mask {:?}", fd, dentry.path(), wd, mask);
+    Ok(wd as isize)
+}
+
+/// syscall: inotify_rm_watch
+/// stop the watch `wd`, an IN_IGNORED is read for it
+pub fn sys_inotify_rm_watch(fd: usize, wd: i32) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let inotify = inotify_file(&task, fd)?;
+    inotify.inotify().rm_watch(wd)?;
+    Ok(0)
+}
+
 /// syscall fstat
 pub fn sys_fstat(fd: usize, stat_buf: usize) -> SysResult {
     let _sum_guard = SumGuard::new();
@@ -397,41 +742,51 @@ pub fn sys_fstat(fd: usize, stat_buf: usize) -> SysResult {
 }
 
 /// syscall statfs
-/// TODO
+/// the usage of the file system the path lives on
 pub fn sys_statfs(path: usize, buf_ptr: usize) -> SysResult {
     let task = current_task().unwrap().clone();
-    let path = user_path_to_string(
-        UserPtrRaw::new(path as *const u8),
-        &mut task.get_vm_space().lock()
-    )?;
-    let dentry = global_find_dentry(&path)?;
-    if dentry.path() != path {
-        return Err(SysError::ENOTDIR)
-    }
+    let dentry = at_helper(task.clone(), AtFlags::AT_FDCWD.bits() as isize, path as *const u8, AtFlags::empty())?;
+    let inode = dentry.inode().ok_or(SysError::ENOENT)?;
     if dentry.is_negative() {
         return Err(SysError::ENOENT)
     }
     let buf_ptr = UserPtrRaw::new(buf_ptr as *mut StatFs)
         .ensure_write(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
-    let info = StatFs {
-        f_type: 0x2011BAB0 as i64,
-        f_bsize: BLOCK_SIZE as i64,
-        f_blocks: 1 << 27,
-        f_bfree: 1 << 26,
-        f_bavail: 1 << 20,
-        f_files: 1 << 10,
-        f_ffree: 1 << 9,
-        f_fsid: [0; 2],
-        f_namelen: 1 << 8,
-        f_frsize: 1 << 9,
-        f_flags: 1 << 1 as i64,
-        f_spare: [0; 4],
-    };
-    buf_ptr.write(info);
+    buf_ptr.write(inode_statfs(&inode));
     Ok(0)
 }
 
+/// syscall fstatfs
+/// the usage of the file system the open file lives on
+pub fn sys_fstatfs(fd: usize, buf_ptr: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let file = task.with_fd_table(|t| t.get_file(fd))?;
+    let inode = file.inode()?;
+    let buf_ptr = UserPtrRaw::new(buf_ptr as *mut StatFs)
+        .ensure_write(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?;
+    buf_ptr.write(inode_statfs(&inode));
+    Ok(0)
+}
+
+/// magic of the file system of the inodes without one: pipes, sockets, eventfds...
+const ANON_INODE_FS_MAGIC: i64 = 0x0904_1934;
+
+/// what statfs reports for the file system holding the inode
+fn inode_statfs(inode: &Arc<dyn Inode>) -> StatFs {
+    match inode.inode_inner().super_block.as_ref().and_then(|sb| sb.upgrade()) {
+        Some(sb) => sb.statfs(),
+        None => StatFs {
+            f_type: ANON_INODE_FS_MAGIC,
+            f_bsize: PAGE_SIZE as i64,
+            f_namelen: NAME_MAX as isize,
+            f_frsize: PAGE_SIZE as isize,
+            ..Default::default()
+        },
+    }
+}
+
 /// syscall statx
 pub fn sys_statx(dirfd: isize, pathname: *const u8, flags: i32, mask: u32, statx_buf: VirtAddr) -> SysResult {
     let _sum_guard = SumGuard::new();
@@ -490,72 +845,69 @@ pub fn sys_getdents64(fd: usize, buf: usize, len: usize) -> SysResult {
     let task = current_task().unwrap().clone();
     let user_buf = UserSliceRaw::new(buf as *mut u8, len)
         .ensure_write(&mut task.get_vm_space().lock())
-        .ok_or(SysError::EINVAL)?;
+        .ok_or(SysError::EFAULT)?;
     let buf_slice = user_buf.to_mut();
-    assert!(buf_slice.len() == len);
 
     let file = task.with_fd_table(|t| t.get_file(fd))?;
-    let dentry = file.dentry().unwrap();
+    let dentry = file.dentry().ok_or(SysError::ENOTDIR)?;
     // log::info!("reading dentry path {}", dentry.path());
-    let mut buf_it = buf_slice;
-    let mut writen_len = 0;
 
-    // the first will be "." the second will be ".."
-    let dents = dentry.clone().load_child_dentry()?;
-    // dents.insert(0, dentry.clone());
-    // if let Some(parent) = dentry.parent() {
-    //     dents.insert(0, parent);
-    // }
-    
-    for (idx, child) in dents.iter().skip(file.pos()).enumerate() {
-        // assert!(child.state() != DentryState::NEGATIVE);
-        // let name = if idx == 0 {
-        //     ".".to_string()
-        // } else if idx == 1 {
-        //     "..".to_string()
-        // } else {
-        //     child.name().to_string()
-        // };
-        let name = child.name().to_string();
-        // log::info!("[{idx}] loading child {}", name);
+    // the entries in cookie order, the file position is the cookie
+    // of the last entry returned, so a later call goes on after it
+    let mut dents: Vec<(u64, Arc<dyn Dentry>)> = dentry.clone().load_child_dentry()?
+        .into_iter()
+        .filter(|child| !child.is_negative() && child.inode().is_some())
+        .map(|child| (dirent_cookie(child.name()), child))
+        .filter(|(cookie, _)| *cookie > file.pos() as u64)
+        .collect();
+    dents.sort_by_key(|(cookie, _)| *cookie);
 
+    let mut writen_len = 0;
+    for (cookie, child) in dents {
+        let name = child.name();
         // align to 8 bytes
-        let c_name_len = name.len() + 1;
-        let rec_len = (LEN_BEFORE_NAME + c_name_len + 7) & !0x7;
-        // let rec_len = LEN_BEFORE_NAME + c_name_len;
+        let rec_len = (LEN_BEFORE_NAME + name.len() + 1 + 7) & !0x7;
+        if writen_len + rec_len > len {
+            // Result buffer is too small.
+            if writen_len == 0 {
+                return Err(SysError::EINVAL);
+            }
+            break;
+        }
         let inode = child.inode().unwrap();
-        let d_type = DirentFileType::from_inode_mode(inode.inode_inner().mode());
         let linux_dirent = LinuxDirent64 {
             d_ino: inode.inode_inner().ino as u64,
-            d_off: file.pos() as u64,
+            d_off: cookie,
             d_reclen: rec_len as u16,
-            d_type: d_type.bits(),
+            d_type: DirentFileType::from_inode_mode(inode.inode_type()).bits(),
         };
-
         //info!("[sys_getdents64] linux dirent {linux_dirent:?}");
-        if writen_len + rec_len > len {
-            // Result buffer is too small.
-            log::info!("early break");
-            // return Err(SysError::EINVAL)
-            break;
-        }
-
-        file.seek(SeekFrom::Current(1))?;
-        let ptr = buf_it.as_mut_ptr() as *mut LinuxDirent64;
+        let record = &mut buf_slice[writen_len..writen_len + rec_len];
         unsafe {
-            ptr.copy_from_nonoverlapping(&linux_dirent, 1);
+            (record.as_mut_ptr() as *mut LinuxDirent64).write_unaligned(linux_dirent);
         }
-        buf_it[LEN_BEFORE_NAME..LEN_BEFORE_NAME + c_name_len - 1]
-            .copy_from_slice(name.as_bytes());
-        buf_it[LEN_BEFORE_NAME + c_name_len - 1] = b'\0';
-        buf_it = &mut buf_it[rec_len..];
+        record[LEN_BEFORE_NAME..LEN_BEFORE_NAME + name.len()].copy_from_slice(name.as_bytes());
+        record[LEN_BEFORE_NAME + name.len()..].fill(0);
+        file.set_pos(cookie as usize);
         writen_len += rec_len;
-        // log::info!("[{idx}] end");
     }
     // log::info!("writen_len: {}", writen_len);
     return Ok(writen_len as isize);
 }
 
+/// the d_off of a directory entry: a hash of the name rather than an index,
+/// entries added or removed between two calls do not move the others.
+/// kept to 63 bits for lseek and away from 0, the start of the directory
+fn dirent_cookie(name: &str) -> u64 {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash >> 1).max(1)
+}
+
 /// unlink() deletes a name from the filesystem.  If that name was the
 /// last link to a file and no processes have the file open, the file
 /// is deleted and the space it was using is made available for reuse.
@@ -605,6 +957,8 @@ pub fn sys_unlinkat(dirfd: isize, pathname: *const u8, flags: i32) -> SysResult
         }
     }
 
+    // the watches on the inode hear of it while it is still there
+    notify_unlinked(&inode);
     // should clear inode first to drop inode (flush datas to disk)
     dentry.clear_inode();
     inode.clean_cached();
@@ -614,6 +968,8 @@ pub fn sys_unlinkat(dirfd: isize, pathname: *const u8, flags: i32) -> SysResult
     let parent = dentry.parent().unwrap();
     parent.inode().unwrap().remove(&name, inode_mode).expect("remove failed");
     parent.remove_child(&name);
+    let dir_mask = if is_dir { InotifyMask::IN_ISDIR } else { InotifyMask::empty() };
+    notify_child(&parent, &name, InotifyMask::IN_DELETE | dir_mask, 0);
 
     //inode.unlink().expect("inode unlink failed");
     Ok(0)
@@ -633,16 +989,17 @@ pub fn sys_symlinkat(old_path_ptr: *const u8, new_dirfd: isize, new_path_ptr: *c
     }
     log::info!("[sys_symlinkat] task {}, sym-link old path {} to new path {}, fd {new_dirfd}", task.tid(), old_path, new_path);
     let new_dentry = at_helper(task, new_dirfd, new_path_ptr, AtFlags::AT_SYMLINK_NOFOLLOW)?;
-    let new_path = new_dentry.path();
-    let parent = new_dentry.parent().unwrap().inode().unwrap();
-    // let old_path = old_dentry.path();
-    let new_inode = parent.symlink(&old_path, &new_path)?;
     if new_dentry.inode().is_some() && !new_dentry.is_negative() {
         return Err(SysError::EEXIST);
-    } else {
-        log::info!("create a new symlink, path {}", new_dentry.path());
-        new_dentry.set_inode(new_inode.clone());
     }
+    let new_path = new_dentry.path();
+    let parent = new_dentry.parent().ok_or(SysError::ENOENT)?;
+    // let old_path = old_dentry.path();
+    let new_inode = parent.inode().ok_or(SysError::ENOENT)?.symlink(&old_path, &new_path)?;
+    log::info!("create a new symlink, path {}", new_dentry.path());
+    new_dentry.set_inode(new_inode.clone());
+    parent.add_child(new_dentry.clone());
+    notify_child(&parent, new_dentry.name(), InotifyMask::IN_CREATE, 0);
     // global_update_dentry(&new_path, new_inode)?;
     Ok(0)
 }
@@ -651,22 +1008,24 @@ pub fn sys_symlinkat(old_path_ptr: *const u8, new_dirfd: isize, new_path_ptr: *c
 /// directory or location
 pub fn sys_fchmodat(dirfd: isize, pathname: *const u8, mode: u32, flags: i32) -> SysResult {
     let task = current_task().unwrap().clone();
-    let mode = InodeMode::from_bits_truncate(mode);
+    if flags & !(AtFlags::AT_EMPTY_PATH | AtFlags::AT_SYMLINK_NOFOLLOW).bits() != 0 {
+        return Err(SysError::EINVAL);
+    }
     let at_flags = AtFlags::from_bits_truncate(flags);
-    let path = UserPtrRaw::new(pathname)
-        .cstr_slice(&mut task.vm_space.lock())?
-        .to_str()
-        .unwrap()
-        .to_string();
-    log::info!("[sys_fchmodat] task {} change {} mode to {:?}, flags", task.tid(), path, mode);
-    let dentry = at_helper(task, dirfd, pathname, at_flags)?;
+    let path = user_path_to_string(
+        UserPtrRaw::new(pathname),
+        &mut task.get_vm_space().lock())?;
+    log::info!("[sys_fchmodat] task {} change {} mode to {:#o}, flags {:?}", task.tid(), path, mode, at_flags);
+    let dentry = at_helper(task.clone(), dirfd, pathname, at_flags)?;
     if dentry.is_negative() && dentry.inode().is_none() {
         return Err(SysError::ENOENT);
     }
     let inode = dentry.inode().unwrap();
-    let inode_type = inode.inode_type();
-    inode.inode_inner().set_mode(mode | inode_type);
-    Ok(0)
+    // the mode of a link itself means nothing
+    if at_flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW) && inode.inode_type() == InodeMode::LINK {
+        return Err(SysError::EOPNOTSUPP);
+    }
+    chmod_inode(&task, &inode, mode)
 }
 
 /// The fchmod() function shall be equivalent to chmod() except that
@@ -675,10 +1034,21 @@ pub fn sys_fchmodat(dirfd: isize, pathname: *const u8, mode: u32, flags: i32) ->
 pub fn sys_fchmod(fd: isize, mode: u32) -> SysResult {
     let task = current_task().unwrap().clone();
     let file = task.with_fd_table(|t| t.get_file(fd as usize))?;
-    let mode = InodeMode::from_bits_truncate(mode);
     let inode = file.inode().unwrap();
-    let inode_type = inode.inode_type();
-    inode.inode_inner().set_mode(mode | inode_type);
+    chmod_inode(&task, &inode, mode)
+}
+
+/// set the permission bits of `inode`, which only its owner and root may do
+fn chmod_inode(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, mode: u32) -> SysResult {
+    let inner = inode.inode_inner();
+    let euid = task.euid() as u32;
+    if euid != 0 && euid != inner.uid() {
+        return Err(SysError::EPERM);
+    }
+    let mode = InodeMode::from_bits_truncate(mode & 0o7777);
+    inner.set_mode(mode | inode.inode_type());
+    inode.setattr()?;
+    notify_inode(inode, InotifyMask::IN_ATTRIB);
     Ok(0)
 }
 
@@ -695,26 +1065,12 @@ pub fn sys_fchownat(dirfd: isize, pathname: *const u8, uid: i32, gid: i32, flags
         UserPtrRaw::new(pathname), 
         &mut task.get_vm_space().lock())?;
     log::info!("[sys_fchownat] path {} owner {}, gid {}", path, uid, gid);
-    let dentry = at_helper(task, dirfd, pathname, at_flags)?;
+    let dentry = at_helper(task.clone(), dirfd, pathname, at_flags)?;
     if dentry.is_negative() && dentry.inode().is_none() {
         return Err(SysError::ENOENT);
     }
     let inode = dentry.inode().unwrap();
-    if gid != -1 {
-        inode.inode_inner().set_gid(gid as u32);
-    }
-    if uid != -1 {
-        inode.inode_inner().set_uid(uid as u32);
-    }
-    let old_mode = inode.inode_inner().mode();
-    let inode_type = old_mode.get_type();
-    let new_mode = if !old_mode.contains(InodeMode::GROUP_EXEC) {
-        old_mode.intersection(!InodeMode::SET_UID)
-    } else {
-        old_mode.intersection(!(InodeMode::SET_GID | InodeMode::SET_UID))
-    };
-    inode.inode_inner().set_mode(new_mode | inode_type);
-    Ok(0)
+    chown_inode(&task, &inode, uid, gid)
 }
 
 
@@ -722,20 +1078,37 @@ pub fn sys_fchown(fd: isize, uid: i32, gid: i32) -> SysResult {
     let task = current_task().unwrap().clone();
     let file = task.with_fd_table(|t| t.get_file(fd as usize))?;
     let inode = file.inode().unwrap();
+    chown_inode(&task, &inode, uid, gid)
+}
+
+/// set the owner and group of `inode`, -1 keeps either as it is;
+/// other than root, the owner may only move the file into its own group
+fn chown_inode(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, uid: i32, gid: i32) -> SysResult {
+    let inner = inode.inode_inner();
+    let euid = task.euid() as u32;
+    if euid != 0 {
+        let keeps_owner = uid == -1 || uid as u32 == inner.uid();
+        let own_group = gid == -1 || gid as u32 == inner.gid() || gid == task.egid();
+        if euid != inner.uid() || !keeps_owner || !own_group {
+            return Err(SysError::EPERM);
+        }
+    }
     if gid != -1 {
-        inode.inode_inner().set_gid(gid as u32);
+        inner.set_gid(gid as u32);
     }
     if uid != -1 {
-        inode.inode_inner().set_uid(uid as u32);
+        inner.set_uid(uid as u32);
     }
-    let old_mode = inode.inode_inner().mode();
+    let old_mode = inner.mode();
     let inode_type = old_mode.get_type();
     let new_mode = if !old_mode.contains(InodeMode::GROUP_EXEC) {
         old_mode.intersection(!InodeMode::SET_UID)
     } else {
         old_mode.intersection(!(InodeMode::SET_GID | InodeMode::SET_UID))
     };
-    inode.inode_inner().set_mode(new_mode | inode_type);
+    inner.set_mode(new_mode | inode_type);
+    inode.setattr()?;
+    notify_inode(inode, InotifyMask::IN_ATTRIB);
     Ok(0)
 }
 
@@ -752,7 +1125,7 @@ pub fn sys_readlinkat(dirfd: isize, pathname: *const u8, buf: usize, len: usize)
         return Err(SysError::EBADF);
     }
     let inode = dentry.inode().ok_or(SysError::ENOENT)?;
-    if inode.inode_inner().mode() != InodeMode::LINK {
+    if inode.inode_type() != InodeMode::LINK {
         return Err(SysError::EINVAL);
     }
     
@@ -781,11 +1154,28 @@ pub fn sys_utimensat(dirfd: isize, pathname: *const u8, times: usize, flags: i32
     const UTIME_NOW: usize = 0x3fffffff;
     const UTIME_OMIT: usize = 0x3ffffffe;
     let task = current_task().unwrap().clone();
+    if flags & !(AtFlags::AT_SYMLINK_NOFOLLOW | AtFlags::AT_EMPTY_PATH).bits() != 0 {
+        return Err(SysError::EINVAL);
+    }
     let at_flags = AtFlags::from_bits_truncate(flags);
     log::info!("[sys_utimensat]: dirfd {}, pathname ptr {:#x}, flags {:?}", dirfd, pathname as usize, at_flags);
     if dirfd as i32 != AtFlags::AT_FDCWD.bits() && pathname.is_null() && at_flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW) {
         return Err(SysError::EINVAL)
     }
+    // null times means now for both
+    let times = if times == 0 {
+        [TimeSpec { tv_sec: 0, tv_nsec: UTIME_NOW }; 2]
+    } else {
+        let times_ptr = UserSliceRaw::new(times as *const TimeSpec, 2)
+            .ensure_read(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        let times = times_ptr.to_ref();
+        [times[0], times[1]]
+    };
+    log::info!("[sys_utimensat] times {:?}", times);
+    if times.iter().any(|t| t.tv_nsec >= 1_000_000_000 && t.tv_nsec != UTIME_NOW && t.tv_nsec != UTIME_OMIT) {
+        return Err(SysError::EINVAL);
+    }
     // VERSIONS: .... To support this, the Linux
     // utimensat() system call implements a nonstandard feature: if
     // pathname is NULL, then the call modifies the timestamps of the
@@ -801,33 +1191,33 @@ pub fn sys_utimensat(dirfd: isize, pathname: *const u8, times: usize, flags: i32
         }
         dentry.inode().unwrap()
     };
-    
+    if times.iter().all(|t| t.tv_nsec == UTIME_OMIT) {
+        return Ok(0);
+    }
+
     let inner = inode.inode_inner();
-    
-    let current_time = TimeSpec::from(get_current_time_duration());
-    if times == 0 {
-        inner.set_atime(current_time);
-        inner.set_ctime(current_time);
-        inner.set_mtime(current_time);
-    } else {
-        let times_ptr =
-            UserSliceRaw::new(times as *mut TimeSpec, 2)
-            .ensure_write(&mut task.get_vm_space().lock())
-            .ok_or(SysError::EFAULT)?;
-        let times = times_ptr.to_mut();
-        log::info!("[sys_utimensat] times {:?}", times);
-        match times[0].tv_nsec {
-            UTIME_NOW => inner.set_atime(current_time),
-            UTIME_OMIT => {}
-            _ => inner.set_atime(times[0]),
-        }
-        match times[1].tv_nsec {
-            UTIME_NOW => *inner.mtime.lock() = current_time,
-            UTIME_OMIT => {}
-            _ => inner.set_mtime(times[1]),
+    // any time needs the owner or root, the current time also whoever may write the file
+    let euid = task.euid() as u32;
+    if euid != 0 && euid != inner.uid() {
+        if times.iter().any(|t| t.tv_nsec != UTIME_NOW && t.tv_nsec != UTIME_OMIT) {
+            return Err(SysError::EPERM);
         }
-        inner.set_ctime(current_time);
-    }
+        inner.permission(euid, task.egid() as u32, W_OK)?;
+    }
+    // the clock reads and writes stamp the file with
+    let current_time = TimeSpec::from(realtime_time());
+    match times[0].tv_nsec {
+        UTIME_NOW => inner.set_atime(current_time),
+        UTIME_OMIT => {}
+        _ => inner.set_atime(times[0]),
+    }
+    match times[1].tv_nsec {
+        UTIME_NOW => inner.set_mtime(current_time),
+        UTIME_OMIT => {}
+        _ => inner.set_mtime(times[1]),
+    }
+    inner.set_ctime(current_time);
+    notify_inode(&inode, InotifyMask::IN_ATTRIB);
     Ok(0)
 }
 
@@ -869,8 +1259,192 @@ pub fn sys_mount(
     Ok(0)
 }
 
-/// fake unmount
-pub fn sys_umount2(_target: *const u8, _flags: u32) -> SysResult {
+/// fake unmount, except for the old root pivot_root left at put_old
+pub fn sys_umount2(target: *const u8, _flags: u32) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let target = user_path_to_string(
+        UserPtrRaw::new(target),
+        &mut task.get_vm_space().lock()
+    )?;
+    remove_old_root(&task.global_path_at(&task.cwd(), &target));
+    Ok(0)
+}
+
+/// pivot_root() changes the root directory of the calling process, and of
+/// every process with the same root, to `new_root`, which must be a mount point;
+/// the old root can be reached at `put_old` until that is unmounted
+pub fn sys_pivot_root(new_root: *const u8, put_old: *const u8) -> SysResult {
+    let task = current_task().unwrap().clone();
+    if task.euid() != 0 {
+        return Err(SysError::EPERM);
+    }
+    let new_root = at_helper(task.clone(), AtFlags::AT_FDCWD.bits() as _, new_root, AtFlags::empty())?;
+    let put_old = at_helper(task.clone(), AtFlags::AT_FDCWD.bits() as _, put_old, AtFlags::empty())?;
+    for dentry in [&new_root, &put_old] {
+        if dentry.is_negative() {
+            return Err(SysError::ENOENT);
+        }
+        if !dentry.inode().unwrap().inode_type().is_dir() {
+            return Err(SysError::ENOTDIR);
+        }
+    }
+    let old_root = task.root();
+    let old_root_path = old_root.path();
+    let new_root_path = new_root.path();
+    let put_old_path = put_old.path();
+    if new_root_path == old_root_path {
+        return Err(SysError::EBUSY);
+    }
+    if !mount_table().iter().any(|m| m.point == new_root_path) {
+        log::warn!("[sys_pivot_root]: {} is not a mount point", new_root_path);
+        return Err(SysError::EINVAL);
+    }
+    if !is_below(&put_old_path, &new_root_path) {
+        return Err(SysError::EINVAL);
+    }
+    add_old_root(put_old_path, old_root_path.clone());
+    TASK_MANAGER.for_each_task(|t| {
+        if t.root().path() == old_root_path {
+            t.set_root(new_root.clone());
+        }
+        if t.cwd().path() == old_root_path {
+            t.set_cwd(new_root.clone());
+        }
+    });
+    Ok(0)
+}
+
+/// fsopen() creates a blank filesystem configuration context within the kernel
+/// for the filesystem named by `fstype` and returns a file descriptor to it
+pub fn sys_fsopen(fstype: *const u8, flags: u32) -> SysResult {
+    let flags = FsOpenFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    if task.euid() != 0 {
+        return Err(SysError::EPERM);
+    }
+    let fs_type = user_path_to_string(
+        UserPtrRaw::new(fstype),
+        &mut task.get_vm_space().lock()
+    )?;
+    let fs = find_context_fs(&fs_type).ok_or(SysError::ENODEV)?;
+    let file = make_fscontext(fs);
+    let fd_flags = if flags.contains(FsOpenFlags::FSOPEN_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+    task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: fd_flags }))?;
+    info!("[sys_fsopen] fstype {}, fd {}", fs_type, fd);
+    Ok(fd as isize)
+}
+
+/// fsconfig() sets parameters of the filesystem context `fd` refers to
+/// and creates the superblock with FSCONFIG_CMD_CREATE
+pub fn sys_fsconfig(fd: usize, cmd: u32, key: *const u8, value: *const u8, aux: i32) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let file = task.with_fd_table(|t| t.get_file(fd))?;
+    let file = file.downcast_arc::<FsContextFile>().map_err(|_| SysError::EINVAL)?;
+    let cmd = FsConfigCmd::from_repr(cmd).ok_or(SysError::EOPNOTSUPP)?;
+    let read_str = |ptr: *const u8| user_path_to_string(
+        UserPtrRaw::new(ptr),
+        &mut task.get_vm_space().lock()
+    );
+    info!("[sys_fsconfig] fd {}, cmd {:?}", fd, cmd);
+    let mut ctx = file.ctx.lock();
+    match cmd {
+        FsConfigCmd::FSCONFIG_SET_FLAG => {
+            if key.is_null() || !value.is_null() || aux != 0 {
+                return Err(SysError::EINVAL);
+            }
+            ctx.set_param(&read_str(key)?, None)?;
+        }
+        FsConfigCmd::FSCONFIG_SET_STRING => {
+            if key.is_null() || value.is_null() || aux != 0 {
+                return Err(SysError::EINVAL);
+            }
+            ctx.set_param(&read_str(key)?, Some(&read_str(value)?))?;
+        }
+        FsConfigCmd::FSCONFIG_CMD_CREATE | FsConfigCmd::FSCONFIG_CMD_CREATE_EXCL => {
+            if !key.is_null() || !value.is_null() || aux != 0 {
+                return Err(SysError::EINVAL);
+            }
+            ctx.create()?;
+        }
+        // none of our file systems takes a blob, a path or a fd,
+        // and a context to reconfigure comes from fspick
+        _ => return Err(SysError::EOPNOTSUPP),
+    }
+    Ok(0)
+}
+
+/// fsmount() turns the created filesystem context `fs_fd` into a mount
+/// that is attached nowhere and returns a file descriptor to it
+pub fn sys_fsmount(fs_fd: usize, flags: u32, attr_flags: u32) -> SysResult {
+    let flags = FsMountFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let attr = MountAttr::from_bits(attr_flags).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    let file = task.with_fd_table(|t| t.get_file(fs_fd))?;
+    let file = file.downcast_arc::<FsContextFile>().map_err(|_| SysError::EINVAL)?;
+    let mount = {
+        let mut ctx = file.ctx.lock();
+        if ctx.phase != FsContextPhase::Created {
+            return Err(SysError::EBUSY);
+        }
+        ctx.phase = FsContextPhase::Mounted;
+        DetachedMount {
+            fs: ctx.fs,
+            flags: ctx.sb_flags | attr.into(),
+            source: ctx.source.clone(),
+            attached: None,
+        }
+    };
+    let file = make_mount_file(mount);
+    let fd_flags = if flags.contains(FsMountFlags::FSMOUNT_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+    task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: fd_flags }))?;
+    info!("[sys_fsmount] fs_fd {}, mount fd {}, attr {:?}", fs_fd, fd, attr);
+    Ok(fd as isize)
+}
+
+/// move_mount() attaches the detached mount `from_dirfd` refers to
+/// (with MOVE_MOUNT_F_EMPTY_PATH and an empty `from_path`) on `to_path`;
+/// moving a mount already attached is not supported
+pub fn sys_move_mount(from_dirfd: isize, from_path: *const u8, to_dirfd: isize, to_path: *const u8, flags: u32) -> SysResult {
+    let flags = MoveMountFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    if task.euid() != 0 {
+        return Err(SysError::EPERM);
+    }
+    let from = if from_path.is_null() {
+        String::new()
+    } else {
+        user_path_to_string(UserPtrRaw::new(from_path), &mut task.get_vm_space().lock())?
+    };
+    if !from.is_empty() || !flags.contains(MoveMountFlags::MOVE_MOUNT_F_EMPTY_PATH) {
+        return Err(SysError::EINVAL);
+    }
+    let file = task.with_fd_table(|t| t.get_file(from_dirfd as usize))?;
+    let file = file.downcast_arc::<MountFile>().map_err(|_| SysError::EINVAL)?;
+    let mut at_flags = AtFlags::empty();
+    if flags.contains(MoveMountFlags::MOVE_MOUNT_T_EMPTY_PATH) {
+        at_flags |= AtFlags::AT_EMPTY_PATH;
+    }
+    if !flags.contains(MoveMountFlags::MOVE_MOUNT_T_SYMLINKS) {
+        at_flags |= AtFlags::AT_SYMLINK_NOFOLLOW;
+    }
+    let target = at_helper(task.clone(), to_dirfd, to_path, at_flags)?;
+    let mut mount = file.mount.lock();
+    if mount.attached.is_some() {
+        return Err(SysError::EINVAL);
+    }
+    let root = attach_mount(mount.fs, target, mount.flags)?;
+    info!("[sys_move_mount] {:?} attached on {}", mount.source, root.path());
+    mount.attached = Some(root.path());
     Ok(0)
 }
 
@@ -917,8 +1491,130 @@ pub enum FcntlOp {
     F_UNIMPL,
 }
 
+/// struct flock used by fcntl record locks
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+#[allow(missing_docs)]
+pub struct Flock {
+    pub l_type: i16,
+    pub l_whence: i16,
+    pub l_start: isize,
+    pub l_len: isize,
+    pub l_pid: i32,
+}
+
+/// read lock for Flock.l_type
+pub const F_RDLCK: i16 = 0;
+/// write lock for Flock.l_type
+pub const F_WRLCK: i16 = 1;
+/// unlock for Flock.l_type
+pub const F_UNLCK: i16 = 2;
+
+/// turn a user flock into the byte range [start, end) it covers
+fn flock_range(file: &Arc<dyn File>, inode: &Arc<dyn Inode>, flock: &Flock) -> Result<(usize, usize), SysError> {
+    let base = match flock.l_whence {
+        0 => 0,
+        1 => file.pos() as isize,
+        2 => inode.inode_inner().size() as isize,
+        _ => return Err(SysError::EINVAL),
+    };
+    let mut start = base.checked_add(flock.l_start).ok_or(SysError::EOVERFLOW)?;
+    let end = if flock.l_len > 0 {
+        start.checked_add(flock.l_len).ok_or(SysError::EOVERFLOW)? as usize
+    } else if flock.l_len == 0 {
+        usize::MAX
+    } else {
+        // a negative length covers the bytes before start
+        let end = start;
+        start = start.checked_add(flock.l_len).ok_or(SysError::EINVAL)?;
+        end as usize
+    };
+    if start < 0 {
+        return Err(SysError::EINVAL);
+    }
+    Ok((start as usize, end))
+}
+
+/// wait until `try_lock` succeeds on the inode, interrupted by signals
+async fn wait_file_lock<F>(task: &Arc<TaskControlBlock>, inode: Arc<dyn Inode>, try_lock: F) -> SysResult
+where
+    F: FnMut(&mut FileLocks) -> bool + Unpin,
+{
+    let current_mask = task.sig_manager.lock().get_sigmask();
+    let intr_future = IntrBySignalFuture {
+        task: task.clone(),
+        mask: current_mask,
+    };
+    task.set_interruptable();
+    task.set_wake_up_sigs(!current_mask);
+    let result = Select2Futures::new(FileLockFuture::new(inode, try_lock), intr_future).await;
+    task.set_running();
+    match result {
+        SelectOutput::Output1(_) => Ok(0),
+        SelectOutput::Output2(_) => Err(SysError::EINTR),
+    }
+}
+
+/// fcntl F_GETLK, F_SETLK and F_SETLKW
+async fn fcntl_record_lock(task: &Arc<TaskControlBlock>, fd: usize, op: FcntlOp, arg: usize) -> SysResult {
+    let file = task.with_fd_table(|table| table.get_file(fd))?;
+    let inode = file.inode()?;
+    let flock = *UserPtrRaw::new(arg as *const Flock)
+        .ensure_read(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?
+        .to_ref();
+    let (start, end) = flock_range(&file, &inode, &flock)?;
+    let pid = task.pid();
+    let kind = match flock.l_type {
+        F_RDLCK => LockKind::Shared,
+        F_WRLCK => LockKind::Exclusive,
+        F_UNLCK if op != FcntlOp::F_GETLK && op != FcntlOp::F_GETLK64 => {
+            inode.inode_inner().locks.lock().record_unlock(pid, start, end);
+            return Ok(0);
+        }
+        _ => return Err(SysError::EINVAL),
+    };
+    let req = RecordLock { pid, start, end, kind };
+    match op {
+        FcntlOp::F_GETLK | FcntlOp::F_GETLK64 => {
+            let conflict = inode.inode_inner().locks.lock().record_conflict(&req);
+            let result = match conflict {
+                Some(lock) => Flock {
+                    l_type: if lock.kind == LockKind::Exclusive { F_WRLCK } else { F_RDLCK },
+                    l_whence: 0,
+                    l_start: lock.start as isize,
+                    l_len: if lock.end == usize::MAX { 0 } else { (lock.end - lock.start) as isize },
+                    l_pid: lock.pid as i32,
+                },
+                None => Flock { l_type: F_UNLCK, ..flock },
+            };
+            UserPtrRaw::new(arg as *mut Flock)
+                .ensure_write(&mut task.get_vm_space().lock())
+                .ok_or(SysError::EFAULT)?
+                .write(result);
+            Ok(0)
+        }
+        _ => {
+            let allowed = match kind {
+                LockKind::Shared => file.readable(),
+                LockKind::Exclusive => file.writable(),
+            };
+            if !allowed {
+                return Err(SysError::EBADF);
+            }
+            if inode.inode_inner().locks.lock().try_record(req) {
+                return Ok(0);
+            }
+            if op != FcntlOp::F_SETLKW && op != FcntlOp::F_SETLKW64 {
+                return Err(SysError::EAGAIN);
+            }
+            wait_file_lock(task, inode, move |locks| locks.try_record(req)).await
+        }
+    }
+}
+
 /// syscall: fcntl
-pub fn sys_fnctl(fd: usize, op: isize, arg: usize) -> SysResult {
+pub async fn sys_fnctl(fd: usize, op: isize, arg: usize) -> SysResult {
     let op = FcntlOp::from_repr(op).unwrap_or_default();
     let task = current_task().unwrap().clone();
     log::info!("[fcntl] op {:?}", op);
@@ -963,6 +1659,10 @@ pub fn sys_fnctl(fd: usize, op: isize, arg: usize) -> SysResult {
             file.set_flags(old_flags.masked_set_flags(flags, mask));
             Ok(0)
         }
+        FcntlOp::F_GETLK | FcntlOp::F_SETLK | FcntlOp::F_SETLKW |
+        FcntlOp::F_GETLK64 | FcntlOp::F_SETLK64 | FcntlOp::F_SETLKW64 => {
+            fcntl_record_lock(&task, fd, op, arg).await
+        }
         _ => {
             log::warn!("fcntl cmd: {op:?} not implemented");
             Ok(0)
@@ -970,6 +1670,38 @@ pub fn sys_fnctl(fd: usize, op: isize, arg: usize) -> SysResult {
     }
 }
 
+/// flock operation: shared lock
+pub const LOCK_SH: usize = 1;
+/// flock operation: exclusive lock
+pub const LOCK_EX: usize = 2;
+/// flock operation: do not block
+pub const LOCK_NB: usize = 4;
+/// flock operation: unlock
+pub const LOCK_UN: usize = 8;
+
+/// syscall: flock
+pub async fn sys_flock(fd: usize, operation: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let file = task.with_fd_table(|table| table.get_file(fd))?;
+    let inode = file.inode()?;
+    let kind = match operation & !LOCK_NB {
+        LOCK_SH => LockKind::Shared,
+        LOCK_EX => LockKind::Exclusive,
+        LOCK_UN => {
+            inode.inode_inner().locks.lock().flock_unlock(&file);
+            return Ok(0);
+        }
+        _ => return Err(SysError::EINVAL),
+    };
+    if inode.inode_inner().locks.lock().try_flock(&file, kind) {
+        return Ok(0);
+    }
+    if operation & LOCK_NB != 0 {
+        return Err(SysError::EAGAIN);
+    }
+    wait_file_lock(&task, inode, move |locks| locks.try_flock(&file, kind)).await
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 #[allow(missing_docs)]
@@ -1022,6 +1754,8 @@ pub async fn sys_writev(fd: usize, iov: usize, iovcnt: usize) -> SysResult {
     let iovs = UserSliceRaw::new(iov as *const IoVec, iovcnt)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EINVAL)?;
+    let append = file.flags().contains(OpenFlags::O_APPEND);
+    let mut gathered = Vec::new();
     let mut totol_len = 0usize;
     for (i, iov) in iovs.to_ref().iter().enumerate() {
         if iov.len == 0 {
@@ -1037,8 +1771,23 @@ pub async fn sys_writev(fd: usize, iov: usize, iovcnt: usize) -> SysResult {
             UserSliceRaw::new(iov.base as *mut u8, iov.len)
                 .ensure_read(&mut task.get_vm_space().lock())
                 .ok_or(SysError::EFAULT)?;
-        let ret = file.write(iov_buf.to_ref()).await?;
+        if append {
+            // appended in one go below, so that nothing lands between the iovecs
+            gathered.extend_from_slice(iov_buf.to_ref());
+            continue;
+        }
+        // a short write ends the call, a later failure does not undo what was written
+        let ret = match write_interruptible(&task, &file, iov_buf.to_ref()).await {
+            Err(_) if totol_len > 0 => break,
+            ret => ret?,
+        };
         totol_len += ret;
+        if ret < iov.len {
+            break;
+        }
+    }
+    if !gathered.is_empty() {
+        totol_len = write_interruptible(&task, &file, &gathered).await?;
     }
     Ok(totol_len as isize)
 }
@@ -1094,6 +1843,10 @@ pub async fn sys_preadv(fd: usize, iov: usize, iovcnt: usize, offset: usize) ->
 /// It performs the same task as writev(), but adds a fourth argument, offset, 
 /// which specifies the file offset at which the output operation is to be performed.
 pub async fn sys_pwritev(fd: usize, iov: usize, iovcnt: usize, offset: usize) -> SysResult {
+    // only pwritev2 takes -1 for the file offset
+    if (offset as isize) < 0 {
+        return Err(SysError::EINVAL);
+    }
     sys_pwritev2(fd, iov, iovcnt, offset, 0).await
 }
 
@@ -1159,7 +1912,8 @@ pub async fn sys_pwritev2(fd: usize, iov: usize, iovcnt: usize, offset: usize, f
     let flags = RwfFlags::from_bits_truncate(flags);
     info!("pwritev2 using flags {:?}", flags);
     
-    if file.inode()?.inode_type() == InodeMode::DIR {
+    let inode = file.inode()?;
+    if inode.inode_type() == InodeMode::DIR {
             return Err(SysError::EISDIR);
     }
 
@@ -1168,11 +1922,19 @@ pub async fn sys_pwritev2(fd: usize, iov: usize, iovcnt: usize, offset: usize, f
         .ok_or(SysError::EFAULT)?;
     let mut total_len = 0usize;
     
-    if (offset as isize) < 0 && !flags.contains(RwfFlags::RWF_APPEND) {
+    // -1 stands for the file offset
+    if (offset as isize) < -1 {
         return Err(SysError::EINVAL)
     }
+    let use_pos = offset as isize == -1;
+    // the per call flags override O_APPEND, only regular files have an end to append at
+    let append = inode.inode_type() == InodeMode::FILE
+        && !flags.contains(RwfFlags::RWF_NOAPPEND)
+        && (flags.contains(RwfFlags::RWF_APPEND) || file.flags().contains(OpenFlags::O_APPEND));
 
-    let mut current_offset = offset;
+    let mut requested = 0usize;
+    let mut gathered = Vec::new();
+    let mut current_offset = if use_pos { file.pos() } else { offset };
     for (i, iov) in iovs.to_ref().iter().enumerate() {
         if iov.len == 0 {
             continue;
@@ -1180,9 +1942,10 @@ pub async fn sys_pwritev2(fd: usize, iov: usize, iovcnt: usize, offset: usize, f
         if (iov.len as isize) < 0 {
             return Err(SysError::EINVAL);
         }
-        if total_len + iov.len < total_len {
+        if requested + iov.len < requested {
             return Err(SysError::EINVAL);
         }
+        requested += iov.len;
         log::debug!("[sys_pwritev]: iov[{}], ptr: {:#x}, len: {:#x}, file pos {}", i, iov.base, iov.len, file.pos());
 
         let iov_buf =
@@ -1190,19 +1953,23 @@ pub async fn sys_pwritev2(fd: usize, iov: usize, iovcnt: usize, offset: usize, f
                 .ensure_read(&mut task.get_vm_space().lock())
                 .ok_or(SysError::EFAULT)?;
 
-        let ret = if flags.contains(RwfFlags::RWF_APPEND) {
-            let old_pos = file.pos();
-            let write_size = file.write_at(old_pos, iov_buf.to_ref()).await?;
-            if (offset as isize) == -1 {
-                file.set_pos(old_pos + write_size);
-            }
-            write_size
-        } else {
-            file.write_at(current_offset, iov_buf.to_ref()).await?
-        };
+        if append {
+            // appended in one go below, so that nothing lands between the iovecs
+            gathered.extend_from_slice(iov_buf.to_ref());
+            continue;
+        }
+        let ret = file.write_at(current_offset, iov_buf.to_ref()).await?;
         total_len += ret;
         current_offset += ret;
-
+    }
+    if !gathered.is_empty() {
+        let (pos, size) = inode.append(&gathered)?;
+        total_len = size;
+        current_offset = pos + size;
+    }
+    // positioned writes leave the file offset alone, even when appending
+    if use_pos {
+        file.set_pos(current_offset);
     }
     Ok(total_len as isize)
 }
@@ -1212,19 +1979,23 @@ pub async fn sys_pwritev2(fd: usize, iov: usize, iovcnt: usize, offset: usize, f
 /// is opened for reading, the vmsplice() system call fills nr_segs
 /// ranges of user memory described by iov from a pipe.  The file
 /// descriptor fd must refer to a pipe.
-/// TODOS: now write into pipe instead of mapping memory
+/// With SPLICE_F_GIFT the pipe takes references on the user pages instead
+/// of copying them, private pages only, the others are still copied.
 pub async fn sys_vmsplice(fd: usize, iovs_ptr: usize, nr_segs: usize, flags: u32) -> SysResult {
-    
     let task = current_task().unwrap().clone();
     let file = task.with_fd_table(|t| t.get_file(fd))?;
     let flags = SpliceFlags::from_bits_truncate(flags);
-    if file.inode()?.inode_type() != InodeMode::FIFO {
-        return Err(SysError::EBADF);
-    }
-    let is_read = file.readable();
+    let pipe = file.clone().downcast_arc::<PipeFile>().map_err(|_| SysError::EBADF)?;
+    // a pipe open for both is written to
+    let to_pipe = file.writable();
     if nr_segs > IOV_MAX {
         return Err(SysError::EINVAL);
     }
+    if flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)
+        && pipe.would_block(if to_pipe { PollEvents::OUT } else { PollEvents::IN })
+    {
+        return Err(SysError::EAGAIN);
+    }
 
     let iovs = UserSliceRaw::new(iovs_ptr as *const IoVec, nr_segs)
         .ensure_read(&mut task.get_vm_space().lock())
@@ -1236,23 +2007,55 @@ pub async fn sys_vmsplice(fd: usize, iovs_ptr: usize, nr_segs: usize, flags: u32
             continue;
         }
         log::info!("[sys_vmsplice]: ptr: {:#x}, len: {:#x}", iov.base, iov.len);
-        if flags.contains(SpliceFlags::SPLICE_F_GIFT) {
-            if !is_page_aligned(iov.len) | !is_page_aligned(iov.base) {
-                return Err(SysError::EINVAL);
-            }
-        }
-        let ret = if is_read {
+        if !to_pipe {
             let iov_buf = UserSliceRaw::new(iov.base as *const u8, iov.len)
                 .ensure_write(&mut task.get_vm_space().lock())
                 .ok_or(SysError::EFAULT)?;
-            file.read(iov_buf.to_mut()).await?
-        } else {
-            let iov_buf = UserSliceRaw::new(iov.base as *const u8, iov.len)
-                .ensure_read(&mut task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-            file.write(iov_buf.to_ref()).await?
-        };
-        total_size += ret;
+            let ret = file.read(iov_buf.to_mut()).await?;
+            total_size += ret;
+            // the pipe ran dry
+            if ret < iov.len {
+                break;
+            }
+            continue;
+        }
+        // only whole pages can be given away, the rest is copied
+        if flags.contains(SpliceFlags::SPLICE_F_GIFT) && is_page_aligned(iov.len) && is_page_aligned(iov.base) {
+            let frames = task.get_vm_space().lock().gift_pages(VirtAddr::from(iov.base), iov.len);
+            match frames {
+                Ok(frames) => {
+                    let ret = match pipe.gift(frames).await {
+                        Err(_) if total_size > 0 => break,
+                        ret => ret?,
+                    };
+                    total_size += ret;
+                    if ret < iov.len {
+                        break;
+                    }
+                    continue;
+                }
+                // not ours to give away, copy it
+                Err(SysError::EINVAL) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let iov_buf = UserSliceRaw::new(iov.base as *const u8, iov.len)
+            .ensure_read(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        let buf = iov_buf.to_ref();
+        // a pipe write takes what fits, go on until all of it is in
+        let mut written = 0;
+        while written < buf.len() {
+            match file.write(&buf[written..]).await {
+                Ok(n) => written += n,
+                Err(_) if total_size + written > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        total_size += written;
+        if written < buf.len() {
+            break;
+        }
     }
     Ok(total_size as isize)
 }
@@ -1301,14 +2104,18 @@ pub async fn sys_sendfile(out_fd: usize, in_fd: usize, offset: usize, count: usi
 /// up to size bytes of data from the file descriptor fd_in to the
 /// file descriptor fd_out, where one of the file descriptors must
 /// refer to a pipe.
-pub async fn sys_splice(in_fd: usize, in_off_ptr: usize, out_fd: usize, out_off_ptr: usize, size: usize, _flags: i32) -> SysResult {
+/// NOTE: a pipe keeps bytes rather than pages, SPLICE_F_MOVE still copies
+pub async fn sys_splice(in_fd: usize, in_off_ptr: usize, out_fd: usize, out_off_ptr: usize, size: usize, flags: i32) -> SysResult {
     let task = current_task().unwrap().clone();
+    let flags = SpliceFlags::from_bits_truncate(flags as u32);
     let in_file = task.with_fd_table(|t| t.get_file(in_fd))?;
     let out_file = task.with_fd_table(|t| t.get_file(out_fd))?;
     in_file.inode()?.support_splice()?;
     out_file.inode()?.support_splice()?;
-    let in_is_pipe = in_file.inode()?.inode_type() == InodeMode::FIFO;
-    let out_is_pipe = out_file.inode()?.inode_type() == InodeMode::FIFO;
+    let in_pipe = in_file.clone().downcast_arc::<PipeFile>().ok();
+    let out_pipe = out_file.clone().downcast_arc::<PipeFile>().ok();
+    let in_is_pipe = in_pipe.is_some();
+    let out_is_pipe = out_pipe.is_some();
     log::info!("in_is_pipe {in_is_pipe}, out_is_pipe {out_is_pipe}");
 
     // cannot refer to the same pipe
@@ -1321,11 +2128,29 @@ pub async fn sys_splice(in_fd: usize, in_off_ptr: usize, out_fd: usize, out_off_
     if in_is_pipe && !in_file.readable() {
         return Err(SysError::EBADF)
     }
-
-    let mut buf = vec![0u8; size];
     if !in_is_pipe && !out_is_pipe {
         return Err(SysError::EINVAL);
     }
+    if (in_is_pipe && in_off_ptr != 0) || (out_is_pipe && out_off_ptr != 0) {
+        return Err(SysError::ESPIPE);
+    }
+    if size == 0 {
+        return Ok(0);
+    }
+    if flags.contains(SpliceFlags::SPLICE_F_NONBLOCK) {
+        let in_blocks = in_pipe.as_ref().is_some_and(|pipe| pipe.would_block(PollEvents::IN));
+        let out_blocks = out_pipe.as_ref().is_some_and(|pipe| pipe.would_block(PollEvents::OUT));
+        if in_blocks || out_blocks {
+            return Err(SysError::EAGAIN);
+        }
+    }
+    // what is taken from the source has to fit into the pipe
+    let size = match &out_pipe {
+        Some(pipe) => size.min(pipe.room().await?),
+        None => size.min(PIPE_BUF_LEN),
+    };
+
+    let mut buf = vec![0u8; size];
     let read_size = if in_is_pipe {
         if !in_file.readable() {
             return Err(SysError::EBADF);
@@ -1378,6 +2203,23 @@ pub async fn sys_splice(in_fd: usize, in_off_ptr: usize, out_fd: usize, out_off_
     Ok(write_size as isize)
 }
 
+/// tee() duplicates up to len bytes of data from the pipe referred to
+/// by fd_in to the pipe referred to by fd_out.  It does not consume
+/// the data that is duplicated from fd_in; therefore, that data can
+/// be copied by a subsequent splice().
+pub async fn sys_tee(in_fd: usize, out_fd: usize, len: usize, flags: u32) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let flags = SpliceFlags::from_bits_truncate(flags);
+    let in_file = task.with_fd_table(|t| t.get_file(in_fd))?;
+    let out_file = task.with_fd_table(|t| t.get_file(out_fd))?;
+    log::info!("[sys_tee] in fd {in_fd}, out fd {out_fd}, len {len}, flags {:?}", flags);
+    let (Ok(in_pipe), Ok(out_pipe)) = (in_file.downcast_arc::<PipeFile>(), out_file.downcast_arc::<PipeFile>()) else {
+        return Err(SysError::EINVAL);
+    };
+    let len = in_pipe.tee(&out_pipe, len, flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)).await?;
+    Ok(len as isize)
+}
+
 /// syscall: copy file range
 /// It copies up to size bytes of data from the source
 /// file descriptor fd_in to the target file descriptor fd_out,
@@ -1438,10 +2280,12 @@ pub fn sys_linkat(old_dirfd: isize, old_pathname: *const u8, new_dirfd: isize, n
     log::info!("[sys_linkat]: old inode size {}", old_inode.inode_inner().size());
     log::info!("[sys_linkat]: old inode getattr size {}", old_inode.getattr().st_size);
     old_inode.link(&new_dentry.path())?;
+    notify_inode(&old_inode, InotifyMask::IN_ATTRIB);
     new_dentry.set_inode(old_inode);
     new_dentry.set_state(DentryState::USED);
     let parent = new_dentry.parent().ok_or(SysError::ENOENT)?;
-    parent.add_child(new_dentry);
+    parent.add_child(new_dentry.clone());
+    notify_child(&parent, new_dentry.name(), InotifyMask::IN_CREATE, 0);
     Ok(0)
 }
 
@@ -1452,27 +2296,15 @@ pub const R_OK: i32 = 4;
 /// syscall: faccessat
 /// access() checks whether the calling process can access the file
 /// pathname.  If pathname is a symbolic link, it is dereferenced.
-/// TODO: now do nothing
-pub fn sys_faccessat(dirfd: isize, pathname: *const u8, _mode: usize, flags: i32) -> SysResult {
-    let at_flags = AtFlags::from_bits_truncate(flags);
-    let task = current_task().unwrap();
-    let path = user_path_to_string(
-            UserPtrRaw::new(pathname), 
-            &mut task.get_vm_space().lock()
-    )?;
-    log::warn!("[faccessat] path {:?}",path);
-    let task = current_task().unwrap().clone();
-    let dentry = at_helper(task, dirfd, pathname, at_flags)?;
-    if dentry.is_negative() {
-        log::warn!("[faccessat] dentry is negative");
-        return Err(SysError::ENOENT);
-    }
-    Ok(0)
+pub fn sys_faccessat(dirfd: isize, pathname: *const u8, mode: i32) -> SysResult {
+    sys_faccessat2(dirfd, pathname, mode, 0)
 }
 
 // Test access permitted for effective IDs, not real IDs.
 pub const AT_EACCESS: i32 = 0x200;
-/// 
+/// syscall: faccessat2
+/// faccessat with flags; the check is made with the real ids
+/// of the caller unless AT_EACCESS asks for the effective ones
 pub fn sys_faccessat2(dirfd: isize, pathname: *const u8, mode: i32, flags: i32) -> SysResult {
     if flags != 0 && flags & !(AT_EACCESS | (AtFlags::AT_EMPTY_PATH | AtFlags::AT_SYMLINK_NOFOLLOW).bits()) != 0 
     {
@@ -1489,12 +2321,20 @@ pub fn sys_faccessat2(dirfd: isize, pathname: *const u8, mode: i32, flags: i32)
     )?;
 
     let at_flags = AtFlags::from_bits_truncate(flags);
-    log::info!("path {} at_flags: {:?}", path, at_flags);
-    let task = current_task().unwrap().clone();
-    let dentry = at_helper(task, dirfd, pathname, at_flags)?;
+    log::info!("[sys_faccessat2] path {} mode {:#o} at_flags: {:?}", path, mode, at_flags);
+    let dentry = at_helper(task.clone(), dirfd, pathname, at_flags)?;
     if dentry.is_negative() {
         return Err(SysError::ENOENT);
     }
+    if mode == F_OK {
+        return Ok(0);
+    }
+    let (uid, gid) = if flags & AT_EACCESS != 0 {
+        (task.euid(), task.egid())
+    } else {
+        (task.ruid(), task.rgid())
+    };
+    dentry.inode().unwrap().inode_inner().permission(uid as u32, gid as u32, mode)?;
     Ok(0)
 }
 
@@ -1507,54 +2347,123 @@ pub fn sys_faccessat2(dirfd: isize, pathname: *const u8, mode: i32, flags: i32)
 pub fn sys_renameat2(old_dirfd: isize, old_path: *const u8, new_dirfd: isize, new_path: *const u8, flags: i32) -> Result<isize, SysError> {
     let task = current_task().unwrap().clone();
     let flags = RenameFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
-
-    let old_dentry = at_helper(task.clone(), old_dirfd, old_path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
-    let new_dentry = at_helper(task.clone(), new_dirfd, new_path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
-
-    let old_path = user_path_to_string(UserPtrRaw::new(old_path), &mut task.vm_space.lock())?;
-    let new_path = user_path_to_string(UserPtrRaw::new(new_path), &mut task.vm_space.lock())?;
-    info!(" rename {} -> {}, using flags {:?}", old_path, new_path, flags);
-
     if flags.contains(RenameFlags::RENAME_EXCHANGE)
             && (flags.contains(RenameFlags::RENAME_NOREPLACE)
                 || flags.contains(RenameFlags::RENAME_WHITEOUT))
     {
         return Err(SysError::EINVAL);
     }
-    // the new dentry can not be the descendant of the old dentry
-    let mut parent_opt = new_dentry.parent();
-    while let Some(parent) = parent_opt {
-        if Arc::ptr_eq(&parent, &old_dentry) {
-            return Err(SysError::EINVAL);
-        }
-        parent_opt = parent.parent();
-    }
 
-    if new_dentry.state() == DentryState::NEGATIVE && flags.contains(RenameFlags::RENAME_EXCHANGE) {
+    let old_dentry = at_helper(task.clone(), old_dirfd, old_path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    let new_dentry = at_helper(task.clone(), new_dirfd, new_path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    info!(" rename {} -> {}, using flags {:?}", old_dentry.path(), new_dentry.path(), flags);
+    if old_dentry.is_negative() {
         return Err(SysError::ENOENT);
-    } else if flags.contains(RenameFlags::RENAME_NOREPLACE) {
-        return Err(SysError::EEXIST);
+    }
+    let exchange = flags.contains(RenameFlags::RENAME_EXCHANGE);
+    if exchange && new_dentry.is_negative() {
+        return Err(SysError::ENOENT);
+    }
+    // the root can not be moved, nor replaced
+    let (old_parent, new_parent) = match (old_dentry.parent(), new_dentry.parent()) {
+        (Some(old_parent), Some(new_parent)) => (old_parent, new_parent),
+        _ => return Err(SysError::EBUSY),
+    };
+    // both names and what they name must be on one file system,
+    // which also keeps mount points where they are
+    let super_block = |dentry: &Arc<dyn Dentry>| {
+        dentry.inode().and_then(|inode| inode.inode_inner().super_block.clone())
+    };
+    let fs = super_block(&old_dentry);
+    let mut others = vec![old_parent.clone(), new_parent.clone()];
+    if !new_dentry.is_negative() {
+        others.push(new_dentry.clone());
+    }
+    for other in others.iter() {
+        let same = match (&fs, super_block(other)) {
+            (Some(a), Some(b)) => Weak::ptr_eq(a, &b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        if !same {
+            return Err(SysError::EXDEV);
+        }
+    }
+    if Arc::ptr_eq(&old_dentry, &new_dentry) {
+        return Ok(0);
+    }
+    // neither may end up inside itself
+    let is_below = |dentry: &Arc<dyn Dentry>, ancestor: &Arc<dyn Dentry>| {
+        let mut parent_opt = dentry.parent();
+        while let Some(parent) = parent_opt {
+            if Arc::ptr_eq(&parent, ancestor) {
+                return true;
+            }
+            parent_opt = parent.parent();
+        }
+        false
+    };
+    if is_below(&new_dentry, &old_dentry) || (exchange && is_below(&old_dentry, &new_dentry)) {
+        return Err(SysError::EINVAL);
     }
 
     let old_inode = old_dentry.inode().ok_or(SysError::ENOENT)?;
-    let new_inode = new_dentry.inode();
-    log::info!("old inode size {}", old_inode.getattr().st_size);
-    old_inode.rename(&new_dentry.path(), new_inode)?;
-    new_dentry.set_inode(old_inode.clone());
-    let parent = new_dentry.parent().unwrap();
-    parent.add_child(new_dentry.clone());
-
-    log::info!("old dentry {}, old inode size {}; new dentry {}, new inode size {}", old_dentry.path(), old_inode.getattr().st_size, new_dentry.path(), new_dentry.inode().unwrap().getattr().st_size);
+    if exchange {
+        let new_inode = new_dentry.inode().ok_or(SysError::ENOENT)?;
+        // the file systems only move one name at a time,
+        // so the old one goes through a temporary name next to it
+        let (old_path, new_path) = (old_dentry.path(), new_dentry.path());
+        let tmp_path = format!("{}/.rename-{}", old_parent.path().trim_end_matches('/'), old_inode.inode_inner().ino);
+        old_inode.rename(&tmp_path, None)?;
+        if let Err(e) = new_inode.rename(&old_path, None) {
+            let _ = old_inode.rename(&old_path, None);
+            return Err(e);
+        }
+        if let Err(e) = old_inode.rename(&new_path, None) {
+            let _ = new_inode.rename(&new_path, None);
+            let _ = old_inode.rename(&old_path, None);
+            return Err(e);
+        }
+        old_dentry.exchange(&new_dentry);
+        notify_move(&old_parent, old_dentry.name(), &new_parent, new_dentry.name(), &old_inode);
+        notify_move(&new_parent, new_dentry.name(), &old_parent, old_dentry.name(), &new_inode);
+        return Ok(0);
+    }
 
-    // warning: due to lwext4 unsupport for RENAME_EXCHANGE
-    if flags.contains(RenameFlags::RENAME_EXCHANGE) {
-        old_dentry.set_inode(new_dentry.inode().unwrap());
-    } else {
-        old_dentry.clear_inode();
+    let new_inode = if new_dentry.is_negative() { None } else { new_dentry.inode() };
+    if let Some(new_inode) = new_inode.as_ref() {
+        if flags.contains(RenameFlags::RENAME_NOREPLACE) {
+            return Err(SysError::EEXIST);
+        }
+        let old_is_dir = old_inode.inode_type() == InodeMode::DIR;
+        let new_is_dir = new_inode.inode_type() == InodeMode::DIR;
+        if old_is_dir && !new_is_dir {
+            return Err(SysError::ENOTDIR);
+        }
+        if !old_is_dir && new_is_dir {
+            return Err(SysError::EISDIR);
+        }
+        if new_is_dir && !new_dentry.clone().load_child_dentry()?.is_empty() {
+            return Err(SysError::ENOTEMPTY);
+        }
     }
+    old_inode.rename(&new_dentry.path(), new_inode.clone())?;
+    // the replaced file is unlinked, nothing of it is to be written back
+    if let Some(new_inode) = new_inode {
+        notify_unlinked(&new_inode);
+        new_inode.clean_cached();
+    }
+    old_dentry.move_to(&new_dentry);
+    old_parent.remove_child(old_dentry.name());
+    new_parent.add_child(new_dentry.clone());
+    notify_move(&old_parent, old_dentry.name(), &new_parent, new_dentry.name(), &old_inode);
     Ok(0)
 }
 
+/// renameat() is renameat2() without flags
+pub fn sys_renameat(old_dirfd: isize, old_path: *const u8, new_dirfd: isize, new_path: *const u8) -> Result<isize, SysError> {
+    sys_renameat2(old_dirfd, old_path, new_dirfd, new_path, 0)
+}
+
 /// syscall: ftruncate
 pub fn sys_ftruncate(fildes: usize, length: usize) -> SysResult {
     let task = current_task().unwrap().clone();
@@ -1563,8 +2472,12 @@ pub fn sys_ftruncate(fildes: usize, length: usize) -> SysResult {
     }
     log::info!("[sys_ftruncate] fd {} truncate size to {}", fildes, length);
     let file = task.with_fd_table(|f| f.get_file(fildes))?;
+    if !file.writable() {
+        return Err(SysError::EINVAL)
+    }
     let dentry = file.dentry().ok_or(SysError::EINVAL)?;
     dentry.inode().unwrap().truncate(length)?;
+    notify_dentry(&dentry, InotifyMask::IN_MODIFY);
     Ok(0)
 }
 
@@ -1580,19 +2493,73 @@ pub fn sys_truncate(pathname: *const u8, length: usize) -> SysResult {
     let dentry = at_helper1(task, -100, &path, AtFlags::empty())?;
     log::info!("[sys_truncate] {}({}) truncate size to {}", path, dentry.path(), length);
     if (length as isize) < 0 {
-        return Err(SysError::E2BIG)
+        return Err(SysError::EINVAL)
     }
     let inode = dentry.inode().ok_or(SysError::EINVAL)?;
     inode.inode_type().is_dir_err()?;
     inode.truncate(length)?;
+    notify_dentry(&dentry, InotifyMask::IN_MODIFY);
+    Ok(0)
+}
+
+/// fallocate: manipulate the allocated disk space of the file in [offset, offset + len)
+/// by default the space is allocated and the file is expanded,
+/// FALLOC_FL_KEEP_SIZE keeps the file size,
+/// FALLOC_FL_PUNCH_HOLE zeroes the range, it must come with FALLOC_FL_KEEP_SIZE
+pub fn sys_fallocate(fd: usize, mode: i32, offset: isize, len: isize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    log::info!("[sys_fallocate] fd {} mode {:#x} offset {} len {}", fd, mode, offset, len);
+    let mode = FallocFlags::from_bits(mode).ok_or(SysError::EOPNOTSUPP)?;
+    if offset < 0 || len <= 0 {
+        return Err(SysError::EINVAL)
+    }
+    if mode.contains(FallocFlags::FALLOC_FL_PUNCH_HOLE)
+        && !mode.contains(FallocFlags::FALLOC_FL_KEEP_SIZE) {
+        return Err(SysError::EOPNOTSUPP)
+    }
+    let (offset, len) = (offset as usize, len as usize);
+    offset.checked_add(len).ok_or(SysError::EFBIG)?;
+    let file = task.with_fd_table(|f| f.get_file(fd))?;
+    if !file.writable() {
+        return Err(SysError::EBADF)
+    }
+    let inode = file.inode()?;
+    match inode.inode_type() {
+        InodeMode::DIR => return Err(SysError::EISDIR),
+        InodeMode::FIFO => return Err(SysError::ESPIPE),
+        _ => {}
+    }
+    inode.fallocate(mode, offset, len)?;
+    notify_inode(&inode, InotifyMask::IN_MODIFY);
+    Ok(0)
+}
+
+/// sync: write all cached file data and metadata to the devices
+pub fn sys_sync() -> SysResult {
+    sync_all()?;
     Ok(0)
 }
 
+/// write the cached data and metadata of the file `fd` refers to its device
+pub fn sys_fsync(fd: usize) -> SysResult {
+    fsync(fd, false)
+}
 
-/// fake async
+/// as fsync, leaving out metadata not needed to read the data back
 pub fn sys_fdatasync(fd: usize) -> SysResult {
+    fsync(fd, true)
+}
+
+fn fsync(fd: usize, data_only: bool) -> SysResult {
     let task = current_task().unwrap().clone();
-    let _ = task.with_fd_table(|t| t.get_file(fd))?;
+    let file = task.with_fd_table(|t| t.get_file(fd))?;
+    let inode = file.inode().map_err(|_| SysError::EINVAL)?;
+    // pipes, sockets and character devices have nothing to write back
+    match inode.inode_type() {
+        InodeMode::FILE | InodeMode::DIR | InodeMode::LINK | InodeMode::BLOCK => {}
+        _ => return Err(SysError::EINVAL),
+    }
+    inode.sync(data_only)?;
     Ok(0)
 }
 
@@ -1632,13 +2599,9 @@ pub fn at_helper(task: Arc<TaskControlBlock>, dirfd: isize, pathname: *const u8,
 pub fn at_helper1(task: Arc<TaskControlBlock>, dirfd: isize, path: &str, flags: AtFlags) -> Result<Arc<dyn Dentry>, SysError> {
     let dentry = if path != "" {
         if path.starts_with("/") {
-            let fpath = rel_path_to_abs(path, &"").unwrap();
-            // println!("fpath: {}", fpath);
-            global_find_dentry(&fpath)?
+            global_find_dentry(&task.global_path_at(&task.root(), path))?
         } else {
-            // getting full path (absolute path)
-            let mut rel_path = path.to_string();
-            let mut parent_dentry = if dirfd as i32 == AtFlags::AT_FDCWD.bits() {
+            let parent_dentry = if dirfd as i32 == AtFlags::AT_FDCWD.bits() {
                 // look up in the current dentry
                 task.with_cwd(|d| d.clone())
             } else {
@@ -1650,27 +2613,8 @@ pub fn at_helper1(task: Arc<TaskControlBlock>, dirfd: isize, path: &str, flags:
             if parent_dentry.is_negative() {
                 return Err(SysError::ENOENT)
             }
-
-            // resolve ../ to get final parent
-            // Symbolic links may contain ..  path components, which (if used at
-            // the start of the link) refer to the parent directories of that in
-            // which the link resides.
-            let mut cnt = 0usize;
-            while rel_path.starts_with("../") {
-                info!("before resolve parent {}, rel_path {}", parent_dentry.path(), rel_path);
-                if cnt > 0 {
-                    parent_dentry = parent_dentry.parent().expect("failed no parent");
-                }
-                rel_path = rel_path.trim_start_matches("..").to_string();
-                rel_path = rel_path.trim_start_matches("/").to_string();
-                cnt += 1;
-                if cnt >= 40 {
-                    warn!("should not have so many ../, may occur some error");
-                }
-                info!("finish resolve parent {}, rel_path {}", parent_dentry.path(), rel_path);
-            }
-
-            let fpath = rel_path_to_abs(&parent_dentry.path(), &rel_path).unwrap();
+            // getting full path (absolute path), `..` never climbs above the root
+            let fpath = task.global_path_at(&parent_dentry, path);
             info!("ffpath: {}", fpath);
             global_find_dentry(&fpath)?
         }
@@ -1714,4 +2658,166 @@ pub fn sys_fadvise(fd: usize, _offset: usize, _len: usize, advice: i32) -> SysRe
         return Err(SysError::ESPIPE);
     }
     Ok(0)
-}
\ No newline at end of file
+}
+/// read the mnt_id_req shared by statmount and listmount
+fn read_mnt_id_req(task: &Arc<TaskControlBlock>, req: *const u8) -> Result<MntIdReq, SysError> {
+    let mut vm = task.get_vm_space().lock();
+    let size = *UserPtrRaw::new(req as *const u32)
+        .ensure_read(&mut vm)
+        .ok_or(SysError::EFAULT)?
+        .to_ref() as usize;
+    if size < MNT_ID_REQ_SIZE_VER0 {
+        return Err(SysError::EINVAL);
+    }
+    if size > PAGE_SIZE {
+        return Err(SysError::E2BIG);
+    }
+    let raw = UserSliceRaw::new(req, size)
+        .ensure_read(&mut vm)
+        .ok_or(SysError::EFAULT)?;
+    let raw = raw.to_ref();
+    // fields unknown to us must be zero
+    if size > MNT_ID_REQ_SIZE_VER1 && raw[MNT_ID_REQ_SIZE_VER1..].iter().any(|&b| b != 0) {
+        return Err(SysError::E2BIG);
+    }
+    let field = |i: usize| raw.get(i * 8..i * 8 + 8)
+        .map_or(0, |b| u64::from_ne_bytes(b.try_into().unwrap()));
+    let req = MntIdReq {
+        size: size as u32,
+        spare: u32::from_ne_bytes(raw[4..8].try_into().unwrap()),
+        mnt_id: field(1),
+        param: field(2),
+        mnt_ns_id: field(3),
+    };
+    if req.spare != 0 {
+        return Err(SysError::EINVAL);
+    }
+    // there is only the initial mount namespace
+    if req.mnt_ns_id != 0 {
+        return Err(SysError::ENOENT);
+    }
+    Ok(req)
+}
+
+/// syscall: statmount
+/// report the fields of a mount picked by the mask in req.param,
+/// strings are stored after the fixed header
+pub fn sys_statmount(req: *const u8, buf: usize, bufsize: usize, flags: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    if flags != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let req = read_mnt_id_req(&task, req)?;
+    let mounts = mount_table();
+    let mount = mounts.iter().find(|m| m.id == req.mnt_id).ok_or(SysError::ENOENT)?;
+    let mask = req.param;
+    let mut sm = Statmount::default();
+    let mut strs: Vec<u8> = Vec::new();
+    let mut push_str = |s: &str| {
+        let off = strs.len() as u32;
+        strs.extend_from_slice(s.as_bytes());
+        strs.push(0);
+        off
+    };
+    if mask & STATMOUNT_SB_BASIC != 0 {
+        let (major, minor) = mount.sb.inner().dev;
+        sm.sb_dev_major = major;
+        sm.sb_dev_minor = minor;
+        sm.sb_magic = mount.magic;
+        sm.sb_flags = 0;
+        sm.mask |= STATMOUNT_SB_BASIC;
+    }
+    if mask & STATMOUNT_MNT_BASIC != 0 {
+        sm.mnt_id = mount.id;
+        sm.mnt_parent_id = mount.parent_id;
+        sm.mnt_id_old = (mount.id - MNT_UNIQUE_ID_OFFSET) as u32;
+        sm.mnt_parent_id_old = (mount.parent_id - MNT_UNIQUE_ID_OFFSET) as u32;
+        sm.mnt_attr = 0;
+        sm.mnt_propagation = MS_PRIVATE;
+        sm.mask |= STATMOUNT_MNT_BASIC;
+    }
+    if mask & STATMOUNT_PROPAGATE_FROM != 0 {
+        sm.propagate_from = 0;
+        sm.mask |= STATMOUNT_PROPAGATE_FROM;
+    }
+    if mask & STATMOUNT_MNT_ROOT != 0 {
+        sm.mnt_root = push_str("/");
+        sm.mask |= STATMOUNT_MNT_ROOT;
+    }
+    if mask & STATMOUNT_MNT_POINT != 0 {
+        sm.mnt_point = push_str(&mount.point);
+        sm.mask |= STATMOUNT_MNT_POINT;
+    }
+    if mask & STATMOUNT_FS_TYPE != 0 {
+        sm.fs_type = push_str(&mount.fs_type);
+        sm.mask |= STATMOUNT_FS_TYPE;
+    }
+    if mask & STATMOUNT_MNT_OPTS != 0 {
+        // no file system reports options of its own
+        sm.mnt_opts = push_str("");
+        sm.mask |= STATMOUNT_MNT_OPTS;
+    }
+    let header_size = core::mem::size_of::<Statmount>();
+    let total = header_size + strs.len();
+    if !strs.is_empty() && total > bufsize {
+        return Err(SysError::EOVERFLOW);
+    }
+    sm.size = total as u32;
+    let header = unsafe {
+        core::slice::from_raw_parts(&sm as *const Statmount as *const u8, header_size)
+    };
+    // a short buffer gets a truncated header when no string is asked for
+    let len = total.min(bufsize);
+    if len == 0 {
+        return Ok(0);
+    }
+    let user_buf = UserSliceRaw::new(buf as *mut u8, len)
+        .ensure_write(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?;
+    let out = user_buf.to_mut();
+    let head_len = header_size.min(len);
+    out[..head_len].copy_from_slice(&header[..head_len]);
+    out[head_len..].copy_from_slice(&strs[..len - head_len]);
+    Ok(0)
+}
+
+/// syscall: listmount
+/// list the ids of the mounts beneath a mount, in id order,
+/// starting after the id in req.param
+pub fn sys_listmount(req: *const u8, mnt_ids: usize, nr_mnt_ids: usize, flags: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    if flags & !LISTMOUNT_REVERSE != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let req = read_mnt_id_req(&task, req)?;
+    let mounts = mount_table();
+    let base_id = if req.mnt_id == LSMT_ROOT {
+        mounts.iter().find(|m| m.parent_id == m.id).ok_or(SysError::ENOENT)?.id
+    } else {
+        req.mnt_id
+    };
+    if !mounts.iter().any(|m| m.id == base_id) {
+        return Err(SysError::ENOENT);
+    }
+    let mut ids: Vec<u64> = mounts
+        .iter()
+        .filter(|m| is_descendant(&mounts, m, base_id))
+        .map(|m| m.id)
+        .collect();
+    if flags & LISTMOUNT_REVERSE != 0 {
+        ids.reverse();
+        // in reverse, a zero start means from the last mount
+        ids.retain(|&id| req.param == 0 || id < req.param);
+    } else {
+        ids.retain(|&id| id > req.param);
+    }
+    ids.truncate(nr_mnt_ids);
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let user_ids = UserSliceRaw::new(mnt_ids as *mut u64, ids.len())
+        .ensure_write(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?;
+    user_ids.to_mut().copy_from_slice(&ids);
+    Ok(ids.len() as isize)
+}
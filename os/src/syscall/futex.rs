@@ -1,12 +1,11 @@
-use core::{hash::{BuildHasher, Hasher}, ops::DerefMut, sync::atomic::{AtomicU32, Ordering}, task::Waker, time::Duration};
+use core::{sync::atomic::{AtomicU32, Ordering}, time::Duration};
 
-use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
-use hal::{addr::{PhysAddr, VirtAddr}, println};
-use hashbrown::HashMap;
+use alloc::sync::Arc;
+use hal::addr::VirtAddr;
 use log::{info, warn};
 use smoltcp::time;
 
-use crate::{mm::{translate_uva_checked, vm::{PageFaultAccessType, UserVmSpaceHal}, UserPtrRaw}, processor::context::SumGuard, signal::{SigSet, SIGKILL, SIGSTOP}, sync::mutex::SpinNoIrqLock, task::{self, current_task, manager::TASK_MANAGER, task::TaskControlBlock}, timer::{self, ffi::TimeSpec, get_current_time_duration, timed_task::suspend_timeout}, utils::{suspend_now, SendWrapper}};
+use crate::{ipc::futex::{futex_queue, FutexHashKey, FutexQueue, FutexWaiter}, mm::{translate_uva_checked, vm::{PageFaultAccessType, UserVmSpaceHal}, UserPtrRaw}, processor::context::SumGuard, signal::{SigSet, SIGKILL, SIGSTOP}, sync::mutex::SpinNoIrqLock, task::{self, current_task, manager::TASK_MANAGER, task::TaskControlBlock}, timer::{self, ffi::TimeSpec, get_current_time_duration, timed_task::suspend_timeout}, utils::{suspend_now, SendWrapper}};
 
 use super::{SysError, SysResult};
 
@@ -24,7 +23,7 @@ const FUTEX_OP_CMP_LE: u32 = 3;
 const FUTEX_OP_CMP_GT: u32 = 4;
 const FUTEX_OP_CMP_GE: u32 = 5;
 
-fn add_awaiter(fm: &mut FutexManager, task: &Arc<TaskControlBlock>, key: FutexHashKey, mask: u32) {
+fn add_awaiter(fm: &mut FutexQueue, task: &Arc<TaskControlBlock>, key: FutexHashKey, mask: u32) {
     task.set_interruptable();
     let wake_up_sigs = task.with_sig_manager(|s| {
         !s.blocked_sigs
@@ -96,7 +95,7 @@ pub async fn sys_futex(
                         return Err(SysError::EAGAIN);
                     }
                     // lock futex manager before check
-                    let mut fm = futex_manager();
+                    let mut fm = futex_queue();
                     if uaddr.load(Ordering::Acquire) != val {
                         return Err(SysError::EAGAIN);
                     }
@@ -110,7 +109,7 @@ pub async fn sys_futex(
                         return Err(SysError::EAGAIN);
                     }
                     // lock futex manager before check
-                    let mut fm = futex_manager();
+                    let mut fm = futex_queue();
                     if uaddr.load(Ordering::Acquire) != val {
                         return Err(SysError::EAGAIN);
                     }
@@ -138,7 +137,7 @@ pub async fn sys_futex(
                     }
                 }
                 let rem = suspend_timeout(&task, dur).await;
-                let mut fm = futex_manager();
+                let mut fm = futex_queue();
                 if rem.is_zero() {
                     task.set_running();
                     if fm.remove_waiter(&key, task.tid()).is_none() {
@@ -148,7 +147,7 @@ pub async fn sys_futex(
                     return Err(SysError::ETIMEOUT);
                 }
             }
-            let mut fm = futex_manager();
+            let mut fm = futex_queue();
             let wake_up_sigs = task.with_sig_manager(|s| {
                     !s.blocked_sigs
                 });
@@ -165,14 +164,14 @@ pub async fn sys_futex(
             Ok(0)
         }
         FutexOp::Wake => {
-            let n_wake = futex_manager().wake(&key, val)?;
-            return Ok(n_wake);
+            let n_wake = futex_queue().wake(&key, val);
+            return Ok(n_wake as isize);
         }
         FutexOp::Fd => {
             return Err(SysError::EINVAL);
         }
         FutexOp::Requeue => {
-            let n_woke = futex_manager().wake(&key, val)?;
+            let n_woke = futex_queue().wake(&key, val);
             let new_key = if is_private {
                 FutexHashKey::Private {
                     mm: task.get_raw_vm_ptr(),
@@ -190,8 +189,8 @@ pub async fn sys_futex(
             };
             // info!("[sys_futex] requeue {:?} to {:?}", key, new_key);
             let timeout = timeout.0 as usize;
-            futex_manager().requeue_waiters(key, new_key, timeout)?;
-            Ok(n_woke)
+            let n_requeued = futex_queue().requeue_waiters(key, new_key, timeout);
+            Ok((n_woke + n_requeued) as isize)
         }
         FutexOp::CmpRequeue => {
             if {
@@ -200,7 +199,7 @@ pub async fn sys_futex(
             } != val3 {
                 return Err(SysError::EAGAIN);
             }
-            let n_woke = futex_manager().wake(&key, val)?;
+            let n_woke = futex_queue().wake(&key, val);
             let new_key = if is_private {
                 FutexHashKey::Private {
                     mm: task.get_raw_vm_ptr(),
@@ -217,8 +216,8 @@ pub async fn sys_futex(
                 FutexHashKey::Shared { paddr }
             };
             let timeout = timeout.0 as usize;
-            futex_manager().requeue_waiters(key, new_key, timeout)?;
-            Ok(n_woke)
+            let n_requeued = futex_queue().requeue_waiters(key, new_key, timeout);
+            Ok((n_woke + n_requeued) as isize)
         }
         FutexOp::WakeOp => {
             info!("[sys_futex] wake op");
@@ -259,8 +258,8 @@ pub async fn sys_futex(
                 }
                 spin_times += 1;
             }
-            let mut fm = futex_manager();
-            let n_woke1 = fm.wake(&key, val)?;
+            let mut fm = futex_queue();
+            let n_woke1 = fm.wake(&key, val);
 
             let check = match cmp {
                 FUTEX_OP_CMP_EQ => oldval == cmparg,
@@ -288,19 +287,19 @@ pub async fn sys_futex(
                     })?;
                     FutexHashKey::Shared { paddr }
                 };
-                fm.wake(&key, val2)?
+                fm.wake(&key2, val2)
             } else {
                 0
             };
 
-            Ok(n_woke1 + n_woke2)
+            Ok((n_woke1 + n_woke2) as isize)
         }
         FutexOp::WakeBitset => {
             if val3 == 0 {
                 return Err(SysError::EINVAL);
             }
-            let n_wake = futex_manager().wake_bitset(&key, val, val3)?;
-            return Ok(n_wake);
+            let n_wake = futex_queue().wake_bitset(&key, val, val3);
+            return Ok(n_wake as isize);
         }
         _ => {
             log::warn!("unimplemented futexop {:?}", futex_op);
@@ -381,184 +380,6 @@ impl From<i32> for FutexOp {
 }
 
 
-/// futex hash key
-#[allow(missing_docs, unused)]
-#[derive(Debug, Hash, PartialEq, PartialOrd, Eq, Copy, Clone)]
-pub enum FutexHashKey {
-    Shared { paddr: PhysAddr },
-    Private { mm: usize, vaddr: VirtAddr },
-}
-
-///
-pub static FUTEX_MANAGER: SpinNoIrqLock<FutexManager> =
-    SpinNoIrqLock::new(FutexManager::new());
-
-///
-pub fn futex_manager() -> impl DerefMut<Target = FutexManager> {
-    FUTEX_MANAGER.lock()
-}
-
-
-type Tid = usize;
-
-#[derive(Debug)]
-#[allow(missing_docs, unused)]
-pub struct FutexWaiter {
-    pub tid: Tid,
-    pub waker: Waker,
-    pub mask: u32,
-}
-
-#[allow(missing_docs, unused)]
-impl FutexWaiter {
-
-    const FUTEX_BITSET_MATCH_ANY: u32 = 0xFFFF_FFFF;
-
-    pub fn wake(self) {
-        self.waker.wake();
-    }
-}
-
-
-///
-pub struct XorHasher {
-    res: u64,
-}
-
-impl Hasher for XorHasher {
-    fn finish(&self) -> u64 {
-        self.res
-    }
-
-    fn write(&mut self, bytes: &[u8]) {
-        for x in bytes.chunks(8) {
-            let mut t = 0;
-            for &i in x {
-                t = (t << 8usize) | (i as u64);
-            }
-            self.res = self.res ^ 31 + t;
-        }
-    }
-}
-
-/// 
-pub struct FutexHashKeyBuilder;
-
-impl BuildHasher for FutexHashKeyBuilder {
-    type Hasher = XorHasher;
-
-    fn build_hasher(&self) -> Self::Hasher {
-        XorHasher { res: 114514 }
-    }
-}
-
-#[allow(missing_docs, unused)]
-pub struct FutexManager {
-    futexs: HashMap<FutexHashKey, VecDeque<FutexWaiter>, FutexHashKeyBuilder>,
-}
-
-#[allow(missing_docs, unused)]
-impl FutexManager {
-    pub const fn new() -> Self {
-        Self {
-            futexs: HashMap::with_hasher(FutexHashKeyBuilder)
-        }
-    }
-
-    pub fn add_waiter(&mut self, key: &FutexHashKey, waiter: FutexWaiter) {
-        // log::info!("[futex::add_waiter] {:?} in {:?} ", waiter, key);
-        if let Some(waiters) = self.futexs.get_mut(key) {
-            waiters.push_back(waiter);
-        } else {
-            let mut waiters = VecDeque::with_capacity(1);
-            waiters.push_back(waiter);
-            self.futexs.insert(*key, waiters);
-        }
-    }
-
-    /// 用于移除任务，任务可能是过期了，也可能是被信号中断了
-    pub fn remove_waiter(&mut self, key: &FutexHashKey, tid: Tid) -> Option<FutexWaiter> {
-        if let Some(waiters) = self.futexs.get_mut(key) {
-            for i in 0..waiters.len() {
-                if waiters[i].tid == tid {
-                    return waiters.remove(i);
-                }
-            }
-        }
-        None
-    }
-
-    pub fn wake(&mut self, key: &FutexHashKey, n: u32) -> SysResult {
-        if let Some(waiters) = self.futexs.get_mut(key) {
-            let n = core::cmp::min(n as usize, waiters.len());
-            for _ in 0..n {
-                let waiter = waiters.pop_front().unwrap();
-                log::debug!("[futex_wake] task {} has been woken at {:?}", waiter.tid, key);
-                waiter.wake();
-            }
-            Ok(n as isize)
-        } else {
-            log::debug!("can not find key {key:?}");
-            Err(SysError::EINVAL)
-        }
-    }
-
-    pub fn wake_bitset(&mut self, key: &FutexHashKey, n: u32, mask: u32) -> SysResult {
-        if let Some(waiters) = self.futexs.get_mut(key) {
-            let mut count = 0;
-            let max_count = n as usize;
-
-            let mut i = 0;
-            let len = waiters.len();
-            while i < len && count < max_count {
-                if (waiters[len - 1 - i].mask & mask) != 0 {
-                    let waiter = waiters.remove(i).unwrap();
-                    // log::info!("[futex_wake] {:?} has been woken", waiter);
-                    waiter.wake();
-                    count += 1;
-                } else {
-                    i += 1;
-                }
-            }
-            
-            Ok(count as isize)
-        } else {
-            log::debug!("can not find key {key:?}");
-            Err(SysError::EINVAL)
-        }
-    }
-
-    pub fn requeue_waiters(
-        &mut self,
-        old: FutexHashKey,
-        new: FutexHashKey,
-        n_req: usize,
-    ) -> SysResult {
-        let mut old_waiters = self.futexs.remove(&old).ok_or_else(|| {
-            log::info!("[futex] no waiters in key {:?}", old);
-            SysError::EINVAL
-        })?;
-        let n = core::cmp::min(n_req as usize, old_waiters.len());
-        if let Some(new_waiters) = self.futexs.get_mut(&new) {
-            for _ in 0..n {
-                new_waiters.push_back(old_waiters.pop_front().unwrap());
-            }
-        } else {
-            let mut new_waiters = VecDeque::with_capacity(n);
-            for _ in 0..n {
-                new_waiters.push_back(old_waiters.pop_front().unwrap());
-            }
-            self.futexs.insert(new, new_waiters);
-        }
-
-        if !old_waiters.is_empty() {
-            self.futexs.insert(old, old_waiters);
-        }
-
-        Ok(n as isize)
-    }
-}
-
 /// Per-lock list entry - embedded in user-space locks, somewhere close
 /// to the futex field. (Note: user-space uses a double-linked list to
 /// achieve O(1) list add and remove, but the kernel only needs to know
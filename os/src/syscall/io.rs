@@ -1,8 +1,8 @@
 //! io related syscall
 
-use core::{cmp, future::Future, mem, num::NonZeroI64, pin::Pin, ptr::read, sync::atomic::AtomicUsize, task::{Context, Poll}, time::Duration, usize};
-use alloc::{boxed::Box, string::ToString};
-use alloc::{collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+use core::{future::Future, mem, num::NonZeroI64, pin::Pin, ptr::read, sync::atomic::{AtomicBool, AtomicUsize, Ordering}, task::{Context, Poll, Waker}, time::Duration, usize};
+use alloc::{boxed::Box, string::ToString, task::Wake};
+use alloc::{collections::btree_map::BTreeMap, sync::{Arc, Weak}, vec::Vec};
 use async_trait::async_trait;
 use hal::instruction::{Instruction, InstructionHal};
 use log::SetLoggerError;
@@ -13,7 +13,7 @@ use universal_hash::generic_array::functional;
 use virtio_drivers::device::socket::SocketError;
 use xmas_elf::reader;
 
-use crate::{fs::{vfs::{file::PollEvents, File, FileInner}, OpenFlags}, mm::{UserPtrRaw, UserSliceRaw}, signal::{msg_queue::{MessageQueue, MqAttr, MqError, NotifyRegistration, Sigevent, MQ_FLAG_NONBLOCK, SIGEV_NONE, SIGEV_SIGNAL}, SigSet, SIGKILL}, sync::mutex::SpinNoIrqLock, task::{current_task, fs::{FdFlags, FdInfo}, signal::IntrBySignalFuture}, timer::{ffi::TimeSpec, get_current_time_duration, timed_task::{PendingFuture, TimedTaskFuture, TimedTaskOutput}}, utils::{suspend_now, user_path_to_string, Select2Futures, SelectOutput}};
+use crate::{fs::{vfs::{file::PollEvents, File, FileInner}, OpenFlags}, mm::{UserPtrRaw, UserSliceRaw}, signal::{msg_queue::{MessageQueue, MqAttr, MqError, NotifyRegistration, Sigevent, MQ_FLAG_NONBLOCK, SIGEV_NONE, SIGEV_SIGNAL}, SigSet, SIGKILL}, sync::mutex::SpinNoIrqLock, task::{current_task, fs::{FdFlags, FdInfo}, signal::IntrBySignalFuture}, timer::{ffi::TimeSpec, get_current_time_duration, timed_task::{PendingFuture, TimedTaskFuture, TimedTaskOutput}}, utils::{get_waker, suspend_now, user_path_to_string, Select2Futures, SelectOutput}};
 use crate::fs::tmpfs::dentry::TmpDentry;
 use super::{SysError, SysResult};
 
@@ -513,15 +513,84 @@ pub struct EPollEvent {
     data: usize,
 }
 
+/// the waker an interest entry hands to its file,
+/// the file waking it means its state changed: a new edge for EPOLLET
+struct EPollEdge {
+    triggered: AtomicBool,
+    waiter: SpinNoIrqLock<Option<Waker>>,
+}
+
+impl Wake for EPollEdge {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.triggered.store(true, Ordering::Release);
+        if let Some(waker) = self.waiter.lock().take() {
+            waker.wake();
+        }
+    }
+}
+
 pub struct EPollFd {
-    file:  Arc<dyn File>,
+    /// the interest does not keep the file open: once the last fd
+    /// referring to it is closed, the entry goes away by itself
+    file:  Weak<dyn File>,
     event: EPollEvent,
+    /// an EPOLLONESHOT entry that already reported, until EPOLL_CTL_MOD
+    disabled: bool,
+    edge: Arc<EPollEdge>,
+    waker: Waker,
+}
+
+impl EPollFd {
+    fn new(file: &Arc<dyn File>, event: EPollEvent) -> Self {
+        // a new entry reports the readiness it already has, even in EPOLLET
+        let edge = Arc::new(EPollEdge {
+            triggered: AtomicBool::new(true),
+            waiter: SpinNoIrqLock::new(None),
+        });
+        Self { file: Arc::downgrade(file), event, disabled: false, waker: Waker::from(edge.clone()), edge }
+    }
+
+    /// whether the file is still open somewhere
+    fn is_open(&self) -> bool {
+        self.file.strong_count() > 0
+    }
+
+    /// poll the file, return the events to report
+    fn check(&mut self, waiter: &Waker) -> Option<EPollEvent> {
+        if self.disabled {
+            return None;
+        }
+        let file = self.file.upgrade()?;
+        *self.edge.waiter.lock() = Some(waiter.clone());
+        let triggered = self.edge.triggered.swap(false, Ordering::AcqRel);
+        let events = self.event.events;
+        // the file registers our own waker, so we learn about its changes
+        let mut cx = Context::from_waker(&self.waker);
+        let revents = match unsafe { Pin::new_unchecked(&mut file.epoll(events)).poll(&mut cx) } {
+            Poll::Ready(revents) => revents,
+            Poll::Pending => unreachable!(),
+        };
+        if revents.is_empty() {
+            return None;
+        }
+        // edge triggered: only once per change of the file
+        if events.contains(EPollEvents::EPOLLET) && !triggered {
+            return None;
+        }
+        if events.contains(EPollEvents::EPOLLONESHOT) {
+            self.disabled = true;
+        }
+        Some(EPollEvent { events: revents, data: self.event.data })
+    }
 }
 
 // for the epoll machamic
 pub struct EPollInstance {
     interest: SpinNoIrqLock<BTreeMap<usize, EPollFd>>,
-    ready: SpinNoIrqLock<Vec<(usize, EPollEvent)>>,
     file_inner: FileInner, 
 }
 
@@ -534,7 +603,6 @@ impl EPollInstance  {
 
         Self { 
             interest: SpinNoIrqLock::new(BTreeMap::new()),
-            ready: SpinNoIrqLock::new(Vec::new()),
             file_inner: FileInner { 
                 dentry: TmpDentry::new("", None),
                 offset: AtomicUsize::new(0),
@@ -545,42 +613,50 @@ impl EPollInstance  {
 
     pub fn add(&self, fd: usize, event: EPollEvent, file: Arc<dyn File>) -> Result<(), SysError> {
         let mut list = self.interest.lock();
-        if list.contains_key(&fd) {
+        // an entry left by a closed file does not hold the fd number
+        if list.get(&fd).is_some_and(|epoll_fd| epoll_fd.is_open()) {
             return Err(SysError::EEXIST)
         }
-        list.insert(fd, EPollFd { file, event });
+        list.insert(fd, EPollFd::new(&file, event));
         Ok(()) 
     }
 
     pub fn remove(&self, fd: usize) -> Result<(), SysError> {
         let mut list = self.interest.lock();
-        if !list.contains_key(&fd) {
-            return Err(SysError::ENOENT)
+        match list.remove(&fd) {
+            Some(epoll_fd) if epoll_fd.is_open() => Ok(()),
+            _ => Err(SysError::ENOENT),
         }
-        list.remove(&fd);
-        Ok(())
     }
 
     pub fn modify(&self, fd: usize, event: EPollEvent) -> Result<(), SysError> {
         let mut list = self.interest.lock();
-        if !list.contains_key(&fd) {
-            return Err(SysError::ENOENT)
-        }
-        if let Some(epoll_fd) = list.get_mut(&fd) {
+        if let Some(epoll_fd) = list.get_mut(&fd).filter(|epoll_fd| epoll_fd.is_open()) {
+            // re-arm, the current readiness counts as a new edge
             epoll_fd.event = event;
+            epoll_fd.disabled = false;
+            epoll_fd.edge.triggered.store(true, Ordering::Release);
         } else {
             return Err(SysError::ENOENT)
         }
         Ok(())
     }
-    /// try to fill the event vec as much as possible
-    pub fn get_ready(&self, event_vec: &mut [EPollEvent]) -> usize {
-        let ready_list = self.ready.lock();
-        let get_size = cmp::min(event_vec.len(), ready_list.len());
-        for i in 0..get_size {
-            event_vec[i] = ready_list[i].1;
+
+    /// poll every entry once and collect at most `max` ready events,
+    /// `waiter` is woken when one of the files changes afterward
+    pub fn scan(&self, waiter: &Waker, max: usize) -> Vec<EPollEvent> {
+        let mut ready = Vec::new();
+        let mut list = self.interest.lock();
+        list.retain(|_, epoll_fd| epoll_fd.is_open());
+        for epoll_fd in list.values_mut() {
+            if ready.len() >= max {
+                break;
+            }
+            if let Some(event) = epoll_fd.check(waiter) {
+                ready.push(event);
+            }
         }
-        get_size
+        ready
     }
 }
 
@@ -651,13 +727,21 @@ const EPOLL_CTL_MOD: usize = 3;
 
 
 pub fn sys_epoll_ctl(epfd: usize, op: usize, fd: usize, event_ptr: usize) -> SysResult {
-    if fd == epfd || event_ptr == 0 {
+    if fd == epfd {
         return Err(SysError::EINVAL)
     }
     let task = current_task().unwrap().clone();
     let epoll_inst = task.with_fd_table(|t| t.get_file(epfd))?;
     let epoll_inst = epoll_inst.downcast_ref::<EPollInstance>().ok_or(SysError::EINVAL)?;
     let file = task.with_fd_table(|t| t.get_file(fd))?;
+    // the event is ignored by EPOLL_CTL_DEL and may be NULL
+    if op == EPOLL_CTL_DEL {
+        epoll_inst.remove(fd)?;
+        return Ok(0);
+    }
+    if event_ptr == 0 {
+        return Err(SysError::EFAULT)
+    }
     let event_ptr = UserPtrRaw::new(event_ptr as *const EPollEvent)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
@@ -666,9 +750,6 @@ pub fn sys_epoll_ctl(epfd: usize, op: usize, fd: usize, event_ptr: usize) -> Sys
         EPOLL_CTL_ADD => {
             epoll_inst.add(fd, event, file)?;
         }
-        EPOLL_CTL_DEL => {
-            epoll_inst.remove(fd)?;
-        }
         EPOLL_CTL_MOD => {
             epoll_inst.modify(fd, event)?;
         }
@@ -677,37 +758,32 @@ pub fn sys_epoll_ctl(epfd: usize, op: usize, fd: usize, event_ptr: usize) -> Sys
     Ok(0)
 }
 
-/// poll the fds from epoll instance
-/// poll from the interest list,
-/// once the fd is finish, place it in ready list 
+/// poll the fds from epoll instance,
+/// ready once some of them have events to report
 pub struct EPollFuture {
     epoll_inst: Arc<EPollInstance>,
+    max: usize,
 }
 
 impl Future for EPollFuture {
-    type Output = ();
+    type Output = Vec<EPollEvent>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        for (fd, epoll_fd) in self.epoll_inst.interest.lock().iter() {
-            let file = epoll_fd.file.clone();
-            let events = epoll_fd.event.events;
-            let r = unsafe {
-                Pin::new_unchecked(&mut file.epoll(events))
-                .poll(cx)
-            };
-            match r {
-                Poll::Pending => unreachable!(),
-                Poll::Ready(result) => {
-                    let mut ret_event = epoll_fd.event;
-                    ret_event.events = result;
-                    self.epoll_inst.ready.lock().push((*fd, ret_event));
-                }
-            }
+        let ready = self.epoll_inst.scan(cx.waker(), self.max);
+        if ready.is_empty() {
+            Poll::Pending
+        } else {
+            Poll::Ready(ready)
         }
-        Poll::Ready(())
     }
 }
 
+/// copy the ready events out to the user buffer
+fn fill_events(events: &mut [EPollEvent], ready: Vec<EPollEvent>) -> SysResult {
+    events[..ready.len()].copy_from_slice(&ready);
+    Ok(ready.len() as isize)
+}
+
 pub async fn sys_epoll_pwait(epfd: usize, events_ptr: usize, maxenvets: usize, timeout: usize, sigmask_ptr: usize) -> SysResult {
     let task = current_task().unwrap().clone();
     let ep_inst_file = task.with_fd_table(|t| t.get_file(epfd))?;
@@ -721,18 +797,18 @@ pub async fn sys_epoll_pwait(epfd: usize, events_ptr: usize, maxenvets: usize, t
     let events = UserSliceRaw::new(events_ptr as *mut EPollEvent, maxenvets)
         .ensure_write(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
-
-    // check the ready list, if not empty, return immediately
-    match ep_inst.clone().get_ready(events.to_mut()) {
-        0 => {},
-        nfds => return Ok(nfds as isize)
+    // check the files once, if some are ready, return immediately
+    let waker = get_waker().await;
+    let ready = ep_inst.scan(&waker, maxenvets);
+    if !ready.is_empty() {
+        return fill_events(events.to_mut(), ready);
     }
      
     // no ready events, start to wait
-    let timeout = match timeout {
+    let timeout = match timeout as i32 {
         0 => return Ok(0), // return immediately, even no ready event
-        -1 => None,
-        t => Some(TimeSpec::from_ms(t)),
+        t if t < 0 => None,
+        t => Some(TimeSpec::from_ms(t as usize)),
     };
 
     let old_sigmask = task.sig_manager.lock().get_sigmask();
@@ -746,7 +822,7 @@ pub async fn sys_epoll_pwait(epfd: usize, events_ptr: usize, maxenvets: usize, t
     task.sig_manager.lock().set_sigmask(new_sigmask);
     
     let intr_future = IntrBySignalFuture { task: task.clone(), mask: new_sigmask };
-    let epoll_future = EPollFuture { epoll_inst: ep_inst.clone() };
+    let epoll_future = EPollFuture { epoll_inst: ep_inst.clone(), max: maxenvets };
 
     task.set_interruptable();
     task.set_wake_up_sigs(!new_sigmask);
@@ -762,11 +838,9 @@ pub async fn sys_epoll_pwait(epfd: usize, events_ptr: usize, maxenvets: usize, t
         task.set_running();
         task.sig_manager.lock().set_sigmask(old_sigmask);
         match sel_res {
-            SelectOutput::Output1(_) => {
-                let nfds = ep_inst.get_ready(events.to_mut());
-                return Ok(nfds as isize)
-            }
-            SelectOutput::Output2(_) => return Err(SysError::EINTR)
+            SelectOutput::Output1(TimedTaskOutput::OK(ready)) => fill_events(events.to_mut(), ready),
+            SelectOutput::Output1(TimedTaskOutput::TimedOut) => Ok(0),
+            SelectOutput::Output2(_) => Err(SysError::EINTR)
         }
     } else {
         // select from intr and epoll event
@@ -774,11 +848,8 @@ pub async fn sys_epoll_pwait(epfd: usize, events_ptr: usize, maxenvets: usize, t
         task.set_running();
         task.sig_manager.lock().set_sigmask(old_sigmask);
         match sel_res {
-            SelectOutput::Output1(_) => {
-                let nfds = ep_inst.get_ready(events.to_mut());
-                return Ok(nfds as isize)
-            }
-            SelectOutput::Output2(_) => return Err(SysError::EINTR)
+            SelectOutput::Output1(ready) => fill_events(events.to_mut(), ready),
+            SelectOutput::Output2(_) => Err(SysError::EINTR)
         }
     }
 }
@@ -103,6 +103,10 @@ pub fn sys_shmdt(shmaddr: VirtAddr) -> SysResult {
     let mut vm_space = task.get_vm_space().lock();
     if let Some(vma) = vm_space.get_area_ref(shmaddr) {
         if let UserVmFile::Shm(shm) = vma.file.clone() {
+            // a shared anonymous mapping, not an attached segment
+            if shm.get_id() == 0 {
+                return Err(SysError::EINVAL);
+            }
             assert!(vma.map_flags.contains(MapFlags::SHARED));
             let len = vma.range_va.clone().count();
             vm_space.unmap(shmaddr, len)?;
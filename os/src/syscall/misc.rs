@@ -8,12 +8,18 @@ use hal::instruction::{Instruction, InstructionHal};
 use strum::FromRepr;
 use lazy_static::lazy_static;
 
+use crate::config::PAGE_SIZE;
 use crate::fs::FanotifyFlags;
-use crate::mm::{UserPtrRaw, UserSliceRaw};
+use crate::mm::{vm::asid, UserPtrRaw, UserSliceRaw};
 use crate::sync::mutex::SpinNoIrqLock;
 use crate::syscall::fd::tmp_fd;
 use crate::syscall::SysError;
-use crate::{fs::devfs::urandom::RNG, task::{current_task, manager::TASK_MANAGER}, timer::{get_current_time,ffi::TimeVal}};
+use crate::task::fs::NR_OPEN;
+use crate::fs::{page::page::cached_pages, procfs::meminfo::MEM_INFO};
+use crate::ipc::sysv::SHM_MANAGER;
+use crate::mm::allocator::{free_frames, total_frames};
+use crate::task::loadavg::{load_averages, FSHIFT};
+use crate::{task::{current_task, manager::TASK_MANAGER}, timer::{get_current_time_sec, ffi::TimeVal}, utils::random::fill_random};
 
 use super::SysResult;
 
@@ -55,29 +61,32 @@ pub struct Sysinfo {
     pub _f: [u8; _F_SIZE],
 }
 
+/// the load averages of sysinfo have SI_LOAD_SHIFT bits of fraction
+const SI_LOAD_SHIFT: usize = 16;
+
 /// syscall: sysinfo
-/// TODO: unimlement
+/// the memory is in bytes, `mem_unit` is 1
 pub fn sys_sysinfo(info: usize) -> SysResult {
+    let loads = load_averages().map(|load| (load << (SI_LOAD_SHIFT - FSHIFT)) as u64);
+    let mem_info = MEM_INFO.lock();
     let sysinfo = Sysinfo {
-        uptime: get_current_time() as i64,
-        loads: [0; 3],
-        totalram: 0,
-        freeram: 0,
-        sharedram: 0,
-        bufferram: 0,
-        totalswap: 0,
-        freeswap: 0,
-        procs: 0,
+        uptime: get_current_time_sec() as i64,
+        loads,
+        totalram: (total_frames() * PAGE_SIZE) as u64,
+        freeram: (free_frames() * PAGE_SIZE) as u64,
+        sharedram: SHM_MANAGER.total_size() as u64,
+        bufferram: (cached_pages() * PAGE_SIZE) as u64,
+        // the swap /proc/meminfo tells of, in kB there
+        totalswap: (mem_info.total_swap * 1024) as u64,
+        freeswap: (mem_info.free_swap * 1024) as u64,
+        procs: TASK_MANAGER.count_tasks(|_| true).min(u16::MAX as usize) as u16,
         pad: 0,
         totalhigh: 0,
         freehigh: 0,
-        mem_uint: 0,
+        mem_uint: 1,
         _f: [0; _F_SIZE],
     };
-    // unsafe {
-    //     Instruction::set_sum();
-    //     (info as *mut Sysinfo).write_volatile(sysinfo);
-    // }
+    drop(mem_info);
     let task = current_task().unwrap();
     let info = UserPtrRaw::new(info as *mut Sysinfo)
         .ensure_write(&mut task.get_vm_space().lock())
@@ -86,20 +95,37 @@ pub fn sys_sysinfo(info: usize) -> SysResult {
     Ok(0)
 }
 
+bitflags! {
+    /// flags of getrandom
+    pub struct GrndFlags: usize {
+        /// don't block if the pool is not ready
+        const GRND_NONBLOCK = 0x0001;
+        /// draw from the "random" source
+        const GRND_RANDOM = 0x0002;
+        /// allow insecure bytes before the pool is ready
+        const GRND_INSECURE = 0x0004;
+    }
+}
+
 /// syscall: get random
-pub fn sys_getrandom(buf: usize, len: usize, _flags: usize) -> SysResult {
-    // let mut buf_slice = unsafe {
-    //     Instruction::set_sum();
-    //     core::slice::from_raw_parts_mut(buf as *mut u8, len)
-    // };
-    log::info!("getrandom: buf: {:?}, len: {:?}, flags: {:?}", buf, len, _flags);
+/// the generator seeds itself on first use, so it never blocks,
+/// GRND_RANDOM draws from the same source as /dev/urandom
+pub fn sys_getrandom(buf: usize, len: usize, flags: usize) -> SysResult {
+    log::info!("getrandom: buf: {:#x}, len: {:?}, flags: {:?}", buf, len, flags);
+    let flags = GrndFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    if flags.contains(GrndFlags::GRND_RANDOM | GrndFlags::GRND_INSECURE) {
+        return Err(SysError::EINVAL);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
     let task = current_task().unwrap();
     let buf = UserSliceRaw::new(buf as *mut u8, len)
         .ensure_write(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
     
     let buf_slice = buf.to_mut();
-    RNG.lock().fill_buf(buf_slice);
+    fill_random(buf_slice);
     Ok(buf_slice.len() as isize)
 }
 
@@ -160,6 +186,8 @@ pub struct RLimit {
 }
 
 pub const RLIM_INFINITY: usize = usize::MAX;
+/// default RLIMIT_MEMLOCK, as on Linux
+pub const MLOCK_LIMIT: usize = 8 * 1024 * 1024;
 
 impl RLimit {
     pub fn new(rlim_cur: usize) -> Self {
@@ -191,6 +219,8 @@ pub fn sys_prlimit64(pid: usize, resource: i32, new_limit: usize, old_limit: usi
                 rlim_max: hal::constant::Constant::USER_STACK_SIZE,
             },
             Resource::NOFILE => task.with_fd_table(|table| table.rlimit()),
+            Resource::CORE => task.with_core_limit(|limit| *limit),
+            Resource::MEMLOCK => task.with_memlock_limit(|limit| *limit),
             r => {
                 log::warn!("[sys_prlimit64] get old_limit : unimplemented {r:?}");
                 RLimit {
@@ -220,8 +250,30 @@ pub fn sys_prlimit64(pid: usize, resource: i32, new_limit: usize, old_limit: usi
         match resource {
             Resource::NOFILE => {
                 log::debug!("[sys_prlimit64] new_limit: {limit:?}");
+                if limit.rlim_cur > limit.rlim_max {
+                    return Err(SysError::EINVAL);
+                }
+                if limit.rlim_max > NR_OPEN {
+                    return Err(SysError::EPERM);
+                }
                 task.with_mut_fd_table(|table| table.set_rlimit(limit));
             }
+            Resource::CORE => {
+                if limit.rlim_cur > limit.rlim_max {
+                    return Err(SysError::EINVAL);
+                }
+                task.with_mut_core_limit(|core_limit| *core_limit = limit);
+            }
+            Resource::MEMLOCK => {
+                if limit.rlim_cur > limit.rlim_max {
+                    return Err(SysError::EINVAL);
+                }
+                // only a privileged process raises the hard limit
+                if limit.rlim_max > task.with_memlock_limit(|old| old.rlim_max) && task.euid() != 0 {
+                    return Err(SysError::EPERM);
+                }
+                task.with_mut_memlock_limit(|memlock_limit| *memlock_limit = limit);
+            }
             r => {
                 log::warn!("[sys_prlimit64] set new_limit : unimplemented {r:?}");
             }
@@ -296,50 +348,34 @@ const RUSAGE_THREAD: i32 = 1;
 pub fn sys_getrusage(who: i32, usage: usize) -> SysResult {
     let task = current_task().unwrap();
     let mut res = Rusage::default();
-    match who {
-        RUSAGE_SELF => {
-            let (utime, stime) = task.time_recorder().time_pair();
-            res.ru_utime = utime.into();
-            res.ru_stime = stime.into();
-            // unsafe {
-            //     let usage_ptr = usage as *mut Rusage;
-            //     usage_ptr.write(res);
-            // }
-            let usage_ptr = UserPtrRaw::new(usage as *mut Rusage)
-                .ensure_write(&mut task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-            usage_ptr.write(res);
-        }
+    let ((utime, stime), (nvcsw, nivcsw), maxrss) = match who {
+        RUSAGE_SELF => (
+            task.process_time_pair(),
+            task.process_switch_pair(),
+            task.get_vm_space().lock().peak_rss(),
+        ),
         RUSAGE_CHILDREN => {
-            let (c_utime, c_stime) = task.time_recorder().child_time_pair();
-            res.ru_utime = c_utime.into();
-            res.ru_stime = c_stime.into();
-            // unsafe {
-            //     let usage_ptr = usage as *mut Rusage;
-            //     usage_ptr.write(res);
-            // }
-            let usage_ptr = UserPtrRaw::new(usage as *mut Rusage)
-                .ensure_write(&mut task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-            usage_ptr.write(res);
+            let recorder = task.time_recorder();
+            (recorder.child_time_pair(), recorder.child_switch_pair(), recorder.child_maxrss())
         }
         RUSAGE_THREAD => {
-            let (utime, stime) = task.time_recorder().time_pair();
-            res.ru_utime = utime.into();
-            res.ru_stime = stime.into();
-            // unsafe {
-            //     let usage_ptr = usage as *mut Rusage;
-            //     usage_ptr.write(res);
-            // }
-            let usage_ptr = UserPtrRaw::new(usage as *mut Rusage)
-                .ensure_write(&mut task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-            usage_ptr.write(res);
+            let recorder = task.time_recorder();
+            (recorder.time_pair(), recorder.switch_pair(), task.get_vm_space().lock().peak_rss())
         }
         _ => {
             return Err(SysError::EINVAL);
         }
-    } 
+    };
+    res.ru_utime = utime.into();
+    res.ru_stime = stime.into();
+    // in kilobytes
+    res.ru_maxrss = maxrss * PAGE_SIZE / 1024;
+    res.ru_nvcsw = nvcsw;
+    res.ru_nivcsw = nivcsw;
+    let usage_ptr = UserPtrRaw::new(usage as *mut Rusage)
+        .ensure_write(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?;
+    usage_ptr.write(res);
     Ok(0)
 }
 
@@ -470,4 +506,76 @@ pub fn sys_fanotify_mark(fd: usize, flags: u32, mask: usize, dirfd: usize, pathn
     
 
     return Ok(0)
-}
\ No newline at end of file
+}
+pub const MEMBARRIER_CMD_QUERY: i32 = 0;
+pub const MEMBARRIER_CMD_GLOBAL: i32 = 1 << 0;
+pub const MEMBARRIER_CMD_GLOBAL_EXPEDITED: i32 = 1 << 1;
+pub const MEMBARRIER_CMD_REGISTER_GLOBAL_EXPEDITED: i32 = 1 << 2;
+pub const MEMBARRIER_CMD_PRIVATE_EXPEDITED: i32 = 1 << 3;
+pub const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: i32 = 1 << 4;
+pub const MEMBARRIER_CMD_PRIVATE_EXPEDITED_SYNC_CORE: i32 = 1 << 5;
+pub const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED_SYNC_CORE: i32 = 1 << 6;
+pub const MEMBARRIER_CMD_GET_REGISTRATIONS: i32 = 1 << 9;
+/// the commands answered by MEMBARRIER_CMD_QUERY, there is no rseq to restart
+const MEMBARRIER_CMD_SUPPORTED: i32 = MEMBARRIER_CMD_GLOBAL
+    | MEMBARRIER_CMD_GLOBAL_EXPEDITED
+    | MEMBARRIER_CMD_REGISTER_GLOBAL_EXPEDITED
+    | MEMBARRIER_CMD_PRIVATE_EXPEDITED
+    | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED
+    | MEMBARRIER_CMD_PRIVATE_EXPEDITED_SYNC_CORE
+    | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED_SYNC_CORE
+    | MEMBARRIER_CMD_GET_REGISTRATIONS;
+
+/// a full memory barrier on this hart and on the harts in `harts`,
+/// with the instruction fetch of this hart synchronized too if `sync_core`
+fn membarrier_fence(harts: usize, sync_core: bool) {
+    unsafe {
+        Instruction::dcache_flush();
+        // a uniprocessor kernel runs no other thread at the same time
+        #[cfg(feature = "smp")]
+        Instruction::remote_fence(harts);
+        #[cfg(not(feature = "smp"))]
+        let _ = harts;
+        if sync_core {
+            Instruction::icache_flush(false);
+        }
+    }
+}
+
+/// syscall membarrier: every thread running now, of any process for the
+/// global commands or of the calling one for the private commands, goes
+/// through a full memory barrier before the call returns
+pub fn sys_membarrier(cmd: i32, flags: u32, _cpu_id: i32) -> SysResult {
+    if flags != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap().clone();
+    match cmd {
+        MEMBARRIER_CMD_QUERY => Ok(MEMBARRIER_CMD_SUPPORTED as isize),
+        MEMBARRIER_CMD_GLOBAL | MEMBARRIER_CMD_GLOBAL_EXPEDITED => {
+            membarrier_fence(asid::other_harts(None), false);
+            Ok(0)
+        }
+        MEMBARRIER_CMD_REGISTER_GLOBAL_EXPEDITED
+        | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED
+        | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED_SYNC_CORE => {
+            task.with_mut_vm_space(|vm| vm.register_membarrier(cmd));
+            Ok(0)
+        }
+        MEMBARRIER_CMD_PRIVATE_EXPEDITED | MEMBARRIER_CMD_PRIVATE_EXPEDITED_SYNC_CORE => {
+            // each private command is allowed by the registration one bit above it
+            let harts = task.with_mut_vm_space(|vm| {
+                if vm.membarrier_registrations() & (cmd << 1) == 0 {
+                    return Err(SysError::EPERM);
+                }
+                Ok(asid::other_harts(Some(vm.get_page_table())))
+            })?;
+            membarrier_fence(harts, cmd == MEMBARRIER_CMD_PRIVATE_EXPEDITED_SYNC_CORE);
+            Ok(0)
+        }
+        MEMBARRIER_CMD_GET_REGISTRATIONS => {
+            Ok(task.with_mut_vm_space(|vm| vm.membarrier_registrations()) as isize)
+        }
+        _ => Err(SysError::EINVAL),
+    }
+}
@@ -3,28 +3,33 @@
 
 use core::time::Duration;
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec, vec::Vec};
 use hal::{
-    addr::{VirtAddr, VirtAddrHal, VirtPageNumHal},
+    addr::{PhysAddrHal, VirtAddr, VirtAddrHal, VirtPageNumHal},
     constant::{Constant, ConstantsHal},
-    pagetable::{MapPerm, PageTableEntryHal, PageTableHal},
+    instruction::{Instruction, InstructionHal},
+    pagetable::MapPerm,
     println,
 };
 use log::info;
 
 use crate::{
     config::PAGE_SIZE,
-    ipc::sysv::SHM_MANAGER,
+    fs::{secretmem::{make_secretmem, SecretMemFile}, vfs::{inode::InodeMode, File}, OpenFlags},
+    ipc::sysv::ShmObj,
     mm::{
-        vm::{self, MapFlags, UserVmArea, UserVmAreaType, UserVmFile, UserVmSpaceHal},
-        UserSliceRaw, UserVmSpace,
+        translate_uva_checked,
+        vm::{
+            self, mempolicy::*, MapFlags, PageFaultAccessType, UserVmArea, UserVmAreaType, UserVmFile, UserVmSpaceHal,
+        },
+        UserPtrRaw, UserSliceRaw, UserVmSpace,
     },
     sync::mutex::{
         spin_mutex::{self, MutexGuard},
         SpinNoIrq,
     },
-    syscall::IoVec,
-    task::{current_task, manager::TASK_MANAGER, task::TaskControlBlock},
+    syscall::{IoVec, IOV_MAX},
+    task::{current_task, fs::{FdFlags, FdInfo}, manager::TASK_MANAGER, task::TaskControlBlock},
     timer::get_current_time_duration,
     utils::timer::TimerGuard,
 };
@@ -49,8 +54,12 @@ bitflags! {
         const MAP_FIXED = 0x10;
         /// Don't use a file.
         const MAP_ANONYMOUS = 0x20;
+        /// Lock the pages of the mapping.
+        const MAP_LOCKED = 0x02000;
         /// Don't check for reservations.
         const MAP_NORESERVE = 0x04000;
+        /// Populate (prefault) pagetables.
+        const MAP_POPULATE = 0x08000;
     }
 }
 
@@ -115,6 +124,27 @@ impl From<MmapProt> for MapPerm {
     }
 }
 
+/// check a file against the mapping asked of it, before any VMA is made:
+/// only what can hold pages is mapped, and the file must have been opened
+/// for reading, and for writing too if writes are to reach it
+fn check_mmap_file(file: &Arc<dyn File>, prot: MmapProt, flags: MmapFlags) -> Result<(), SysError> {
+    let open_flags = file.flags();
+    if open_flags.contains(OpenFlags::O_PATH) {
+        return Err(SysError::EBADF);
+    }
+    if file.inode().is_ok_and(|inode| inode.inode_type() == InodeMode::DIR) {
+        return Err(SysError::ENODEV);
+    }
+    if !open_flags.readable() {
+        return Err(SysError::EACCES);
+    }
+    if flags.intersection(MmapFlags::MAP_TYPE_MASK) == MmapFlags::MAP_SHARED
+        && prot.contains(MmapProt::PROT_WRITE) && !open_flags.writable() {
+        return Err(SysError::EACCES);
+    }
+    Ok(())
+}
+
 /// syscall mmap
 pub fn sys_mmap(
     addr: VirtAddr,
@@ -129,9 +159,15 @@ pub fn sys_mmap(
     let perm = MapPerm::from(prot);
     let task = current_task().unwrap().clone();
     // info!("[sys_mmap] addr: {:#x} length: {}, prot: {:?}, flags: {:?}, fd: {}, offset: {}", addr.0, length, prot, flags, fd, offset);
-    if !flags.contains(MmapFlags::MAP_ANONYMOUS) {
-        task.with_fd_table(|t| t.get_file(fd))?;
-    }
+    let file = if flags.contains(MmapFlags::MAP_ANONYMOUS) {
+        None
+    } else {
+        let file = task.with_fd_table(|t| t.get_file(fd))?;
+        if let Some(secret) = file.downcast_ref::<SecretMemFile>() {
+            secret.check_mmap(task.pid(), flags)?;
+        }
+        Some(file)
+    };
 
     if length == 0 {
         return Err(SysError::EINVAL);
@@ -140,69 +176,86 @@ pub fn sys_mmap(
     } else if offset % PAGE_SIZE != 0 {
         return Err(SysError::EINVAL);
     }
+    if !matches!(flags.intersection(MmapFlags::MAP_TYPE_MASK), MmapFlags::MAP_SHARED | MmapFlags::MAP_PRIVATE) {
+        return Err(SysError::EINVAL);
+    }
+    if let Some(file) = file.as_ref() {
+        check_mmap_file(file, prot, flags)?;
+    }
 
     if flags.contains(MmapFlags::MAP_FIXED) {
-        task.with_mut_vm_space(|m| m.unmap(addr, length))?;
-    }
-
-    match flags.intersection(MmapFlags::MAP_TYPE_MASK) {
-        MmapFlags::MAP_SHARED => {
-            if flags.contains(MmapFlags::MAP_ANONYMOUS) {
-                let start_va = task.with_mut_vm_space(|m| {
-                    m.alloc_anon_area(
-                        addr,
-                        length,
-                        perm,
-                        flags,
-                        SHM_MANAGER.alloc(length, task.pid()),
-                    )
-                })?;
-                Ok(start_va.0 as _)
-            } else {
-                let file = task.with_fd_table(|t| t.get_file(fd))?;
-                let start_va = task.with_mut_vm_space(|m| {
-                    m.alloc_mmap_area(addr, length, perm, flags, file, offset)
-                })?;
-                Ok(start_va.0 as _)
-            }
+        task.with_mut_vm_space(|m| {
+            m.check_unsealed(addr, length)?;
+            m.unmap(addr, length)
+        })?;
+    }
+
+    // a locked mapping, asked for or by mlockall(MCL_FUTURE), is faulted in unless MCL_ONFAULT
+    let mlockall = task.with_vm_space(|m| m.mlockall_flags());
+    let locked = flags.contains(MmapFlags::MAP_LOCKED) || mlockall & MCL_FUTURE != 0;
+    if locked {
+        let pages = (addr.page_offset() + length + PAGE_SIZE - 1) / PAGE_SIZE;
+        task.with_vm_space(|m| check_memlock(&task, m.locked_pages() + pages))
+            .map_err(|_| SysError::EAGAIN)?;
+    }
+
+    let start_va = match (flags.intersection(MmapFlags::MAP_TYPE_MASK), file) {
+        (MmapFlags::MAP_SHARED, None) => {
+            task.with_mut_vm_space(|m| {
+                m.alloc_anon_area(
+                    addr,
+                    length,
+                    perm,
+                    flags,
+                    Some(ShmObj::new_anon(length, task.pid())),
+                )
+            })?
         }
-        MmapFlags::MAP_PRIVATE => {
-            if flags.contains(MmapFlags::MAP_ANONYMOUS) {
-                let start_va =
-                    task.with_mut_vm_space(|m| m.alloc_anon_area(addr, length, perm, flags, None))?;
-                // log::info!("[sys_mmap] private anonymous: {:?}", start_va);
-                Ok(start_va.0 as _)
-            } else {
-                let file = task.with_fd_table(|t| t.get_file(fd))?;
-                // TODO: private copy on write
-                let start_va = task.with_mut_vm_space(|m| {
-                    m.alloc_mmap_area(addr, length, perm, flags, file, offset)
-                })?;
-                Ok(start_va.0 as _)
-            }
+        (MmapFlags::MAP_PRIVATE, None) => {
+            // log::info!("[sys_mmap] private anonymous: {:?}", start_va);
+            task.with_mut_vm_space(|m| m.alloc_anon_area(addr, length, perm, flags, None))?
         }
-        _ => Err(SysError::EINVAL),
+        // TODO: private copy on write
+        (_, Some(file)) => {
+            task.with_mut_vm_space(|m| {
+                m.alloc_mmap_area(addr, length, perm, flags, file, offset)
+            })?
+        }
+        _ => return Err(SysError::EINVAL),
+    };
+    let populate = flags.contains(MmapFlags::MAP_POPULATE)
+        || flags.contains(MmapFlags::MAP_LOCKED)
+        || (locked && mlockall & MCL_ONFAULT == 0);
+    if populate {
+        // the mapping stays even if it could not be filled
+        task.with_mut_vm_space(|m| m.populate(start_va, length))?;
     }
+    Ok(start_va.0 as _)
 }
 
 /// syscall munmap
 pub fn sys_munmap(addr: VirtAddr, mut length: usize) -> SysResult {
     let task = current_task().unwrap().clone();
+    if addr.page_offset() != 0 {
+        return Err(SysError::EINVAL);
+    }
     if length == 0 {
         return Ok(0);
     }
     length = (length - 1 + Constant::PAGE_SIZE) & !(Constant::PAGE_SIZE - 1);
     task.with_mut_vm_space(|m| {
+        // nothing is unmapped if any of the range is sealed
+        m.check_unsealed(addr, length)?;
         let end_vpn = (addr + length).ceil();
         let mut cur_vpn = addr.floor();
+        // each round takes out the part of one VMA inside the range, splitting it
+        // when the range ends in its middle; holes between VMAs are skipped
         while cur_vpn < end_vpn {
-            if let Ok(vma) = m.unmap(cur_vpn.start_addr(), length) {
-                let new_vpn = vma.range_vpn().end;
-                length -= (new_vpn.0 - cur_vpn.0) << Constant::PAGE_SIZE_BITS;
-                cur_vpn = new_vpn;
-            } else {
+            let len = (end_vpn.0 - cur_vpn.0) << Constant::PAGE_SIZE_BITS;
+            let Ok(vma) = m.unmap(cur_vpn.start_addr(), len) else {
                 break;
-            }
+            };
+            cur_vpn = vma.range_vpn().end;
         }
         Ok(())
     })?;
@@ -210,30 +263,342 @@ pub fn sys_munmap(addr: VirtAddr, mut length: usize) -> SysResult {
 }
 
 /// syscall mprotect
-pub fn sys_mprotect(addr: VirtAddr, mut length: usize, prot: i32) -> SysResult {
-    if addr.page_offset() != 0 || length == 0 || length % Constant::PAGE_SIZE != 0 {
+pub fn sys_mprotect(addr: VirtAddr, length: usize, prot: i32) -> SysResult {
+    if addr.page_offset() != 0 || length == 0 {
         return Err(SysError::EINVAL);
     }
     let prot = MmapProt::from_bits_truncate(prot);
     let perm = MapPerm::from(prot);
     // println!("[mprotect] {:#x} {:#x} {:?}", addr.0, length, prot);
     let task = current_task().unwrap().clone();
-    task.with_mut_vm_space(|vm| -> SysResult {
-        let end_vpn = (addr + length).ceil();
-        let mut cur_vpn = addr.floor();
-        while cur_vpn < end_vpn {
-            if let Ok(mut vma) = vm.unmap(cur_vpn.start_addr(), length) {
-                let new_vpn = vma.range_vpn().end;
-                length -= (new_vpn.0 - cur_vpn.0) << Constant::PAGE_SIZE_BITS;
-                cur_vpn = new_vpn;
-                vma.map_perm = perm;
-                vm.push_area(vma, None);
-            } else {
-                break;
+    task.with_mut_vm_space(|vm| vm.mprotect(addr, length, perm))?;
+    // loongarch has no cacheflush syscall, code written before it is made
+    // executable must not run from instructions this hart fetched earlier
+    #[cfg(target_arch = "loongarch64")]
+    if prot.contains(MmapProt::PROT_EXEC) {
+        unsafe { Instruction::icache_flush(false) };
+    }
+    Ok(0)
+}
+
+/// advice: the range will not be accessed, its pages may be dropped
+pub const MADV_DONTNEED: usize = 4;
+/// advice: the range may be freed lazily
+pub const MADV_FREE: usize = 8;
+/// advice: free the range and its backing store
+pub const MADV_REMOVE: usize = 9;
+/// advice: MADV_DONTNEED that also applies to locked pages
+pub const MADV_DONTNEED_LOCKED: usize = 24;
+/// advice: back the range with big pages where possible
+pub const MADV_HUGEPAGE: usize = 14;
+/// advice: back the range with base pages only
+pub const MADV_NOHUGEPAGE: usize = 15;
+
+/// syscall madvise, only the big page advice has an effect
+pub fn sys_madvise(addr: VirtAddr, length: usize, advice: usize) -> SysResult {
+    if addr.page_offset() != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if length == 0 {
+        return Ok(0);
+    }
+    let task = current_task().unwrap().clone();
+    match advice {
+        // would throw away the contents of a sealed area
+        MADV_DONTNEED | MADV_FREE | MADV_REMOVE | MADV_DONTNEED_LOCKED => {
+            task.with_mut_vm_space(|vm| vm.check_unsealed(addr, length))?
+        }
+        MADV_HUGEPAGE => task.with_mut_vm_space(|vm| vm.set_hugepage(addr, length, true))?,
+        MADV_NOHUGEPAGE => task.with_mut_vm_space(|vm| vm.set_hugepage(addr, length, false))?,
+        _ => {}
+    }
+    Ok(0)
+}
+
+/// the nodes set in the user nodemask at `nodemask`, `maxnode` counts
+/// one past the last bit like Linux does; bits past the nodes there are ignored
+fn read_nodemask(task: &Arc<TaskControlBlock>, nodemask: usize, maxnode: usize) -> Result<u64, SysError> {
+    let bits = maxnode.saturating_sub(1);
+    if nodemask == 0 || bits == 0 {
+        return Ok(0);
+    }
+    if bits > PAGE_SIZE * 8 {
+        return Err(SysError::EINVAL);
+    }
+    let words = (bits + 63) / 64;
+    let mut vm = task.get_vm_space().lock();
+    let mask = UserSliceRaw::new(nodemask as *const u64, words)
+        .ensure_read(&mut vm)
+        .ok_or(SysError::EFAULT)?;
+    let first = mask.to_ref()[0];
+    Ok(if bits < 64 { first & ((1 << bits) - 1) } else { first })
+}
+
+/// syscall set_mempolicy: the policy for the memory of the calling task
+/// not covered by a policy set with mbind
+pub fn sys_set_mempolicy(mode: i32, nodemask: usize, maxnode: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    let nodes = read_nodemask(&task, nodemask, maxnode)?;
+    let policy = MemPolicy::new(mode, nodes)?;
+    task.with_mut_vm_space(|vm| vm.set_task_policy(policy));
+    Ok(0)
+}
+
+/// syscall get_mempolicy: the task policy, the policy at `addr` with MPOL_F_ADDR,
+/// the node of a page with MPOL_F_NODE or the nodes usable with MPOL_F_MEMS_ALLOWED;
+/// everything is on node 0
+pub fn sys_get_mempolicy(mode: usize, nodemask: usize, maxnode: usize, addr: usize, flags: u32) -> SysResult {
+    if flags & !(MPOL_F_NODE | MPOL_F_ADDR | MPOL_F_MEMS_ALLOWED) != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if flags & MPOL_F_MEMS_ALLOWED != 0 && flags & (MPOL_F_NODE | MPOL_F_ADDR) != 0 {
+        return Err(SysError::EINVAL);
+    }
+    // the mask must have room for node 0
+    if nodemask != 0 && maxnode == 0 {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap().clone();
+    let (out_mode, out_nodes) = if flags & MPOL_F_MEMS_ALLOWED != 0 {
+        (MPOL_DEFAULT, NODES_ALLOWED)
+    } else {
+        let policy = if flags & MPOL_F_ADDR != 0 {
+            task.with_vm_space(|vm| vm.policy_at(VirtAddr::from(addr))).ok_or(SysError::EFAULT)?
+        } else if addr != 0 {
+            return Err(SysError::EINVAL);
+        } else {
+            task.with_vm_space(|vm| vm.task_policy())
+        };
+        if flags & MPOL_F_NODE != 0 {
+            // the node of the page at `addr`, or the next node the task interleaves on
+            if flags & MPOL_F_ADDR == 0
+                && !matches!(policy.base_mode(), MPOL_INTERLEAVE | MPOL_WEIGHTED_INTERLEAVE)
+            {
+                return Err(SysError::EINVAL);
             }
+            (0, policy.nodes)
+        } else {
+            (policy.mode, policy.nodes)
         }
-        Ok(0)
-    })
+    };
+    let mut vm = task.get_vm_space().lock();
+    if mode != 0 {
+        UserPtrRaw::new(mode as *const i32)
+            .ensure_write(&mut vm)
+            .ok_or(SysError::EFAULT)?
+            .write(out_mode);
+    }
+    // as in Linux, `maxnode` counts one past the last bit written
+    let words = (maxnode.saturating_sub(1) + 63) / 64;
+    if nodemask != 0 && words != 0 {
+        let mask = UserSliceRaw::new(nodemask as *const u64, words)
+            .ensure_write(&mut vm)
+            .ok_or(SysError::EFAULT)?;
+        let mask = mask.to_mut();
+        mask.fill(0);
+        mask[0] = out_nodes;
+    }
+    Ok(0)
+}
+
+/// syscall mbind: set the policy of the memory in `addr..addr+len`,
+/// MPOL_DEFAULT makes it follow the task policy again;
+/// every page is on node 0, so nothing ever needs to move
+pub fn sys_mbind(addr: VirtAddr, len: usize, mode: i32, nodemask: usize, maxnode: usize, flags: u32) -> SysResult {
+    if addr.page_offset() != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if flags & !(MPOL_MF_STRICT | MPOL_MF_MOVE | MPOL_MF_MOVE_ALL) != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap().clone();
+    if flags & MPOL_MF_MOVE_ALL != 0 && task.euid() != 0 {
+        return Err(SysError::EPERM);
+    }
+    let nodes = read_nodemask(&task, nodemask, maxnode)?;
+    let policy = MemPolicy::new(mode, nodes)?;
+    if len == 0 {
+        return Ok(0);
+    }
+    let policy = (policy.mode != MPOL_DEFAULT).then_some(policy);
+    task.with_mut_vm_space(|vm| vm.set_area_policy(addr, len, policy))?;
+    Ok(0)
+}
+
+/// syscall mseal: seal the mappings in `addr..addr+len`, for good:
+/// they can no longer be unmapped, remapped, mapped over, have their
+/// protection changed or their contents discarded, all these fail with EPERM
+pub fn sys_mseal(addr: VirtAddr, len: usize, flags: usize) -> SysResult {
+    if addr.page_offset() != 0 || flags != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let len = len.checked_add(Constant::PAGE_SIZE - 1).ok_or(SysError::EINVAL)? & !(Constant::PAGE_SIZE - 1);
+    addr.0.checked_add(len).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    task.with_mut_vm_space(|vm| vm.seal(addr, len))?;
+    Ok(0)
+}
+
+/// syscall mincore: a byte in `vec` for each page of the range,
+/// with the low bit set if the page is resident
+pub fn sys_mincore(addr: VirtAddr, len: usize, vec: usize) -> SysResult {
+    if addr.page_offset() != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let end = addr.0.checked_add(len).ok_or(SysError::ENOMEM)?;
+    if end > Constant::USER_ADDR_SPACE.end {
+        return Err(SysError::ENOMEM);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let task = current_task().unwrap().clone();
+    let mut vm = task.get_vm_space().lock();
+    let resident = vm.residency(addr, len)?;
+    let out = UserSliceRaw::new(vec as *mut u8, resident.len())
+        .ensure_write(&mut vm)
+        .ok_or(SysError::EFAULT)?;
+    out.to_mut().copy_from_slice(&resident);
+    Ok(0)
+}
+
+/// lock the pages currently mapped
+pub const MCL_CURRENT: i32 = 1;
+/// lock the pages mapped from now on
+pub const MCL_FUTURE: i32 = 2;
+/// lock the pages as they are faulted in instead of up front
+pub const MCL_ONFAULT: i32 = 4;
+/// lock the pages of mlock2 as they are faulted in
+pub const MLOCK_ONFAULT: u32 = 1;
+
+/// the pages of `va..va+len` rounded out to whole pages, EINVAL if the end overflows
+fn page_range(addr: VirtAddr, len: usize) -> Result<(VirtAddr, usize), SysError> {
+    let start = addr.floor().start_addr();
+    let end = addr.0.checked_add(len)
+        .and_then(|end| end.checked_add(Constant::PAGE_SIZE - 1))
+        .ok_or(SysError::EINVAL)? & !(Constant::PAGE_SIZE - 1);
+    Ok((start, end - start.0))
+}
+
+/// ENOMEM if an unprivileged process would hold more than RLIMIT_MEMLOCK
+/// locked with `pages` locked, EPERM if the limit allows none at all
+fn check_memlock(task: &Arc<TaskControlBlock>, pages: usize) -> Result<(), SysError> {
+    if task.euid() == 0 {
+        return Ok(());
+    }
+    let limit = task.with_memlock_limit(|limit| limit.rlim_cur);
+    if limit == 0 {
+        return Err(SysError::EPERM);
+    }
+    if pages.saturating_mul(Constant::PAGE_SIZE) > limit {
+        return Err(SysError::ENOMEM);
+    }
+    Ok(())
+}
+
+/// syscall mlock
+pub fn sys_mlock(addr: VirtAddr, len: usize) -> SysResult {
+    sys_mlock2(addr, len, 0)
+}
+
+/// syscall mlock2: lock the pages of the range in memory, faulting them in now
+/// unless MLOCK_ONFAULT; ENOMEM if some page is not mapped or can not be faulted in
+pub fn sys_mlock2(addr: VirtAddr, len: usize, flags: u32) -> SysResult {
+    if flags & !MLOCK_ONFAULT != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let (start, len) = page_range(addr, len)?;
+    if len == 0 {
+        return Ok(0);
+    }
+    let task = current_task().unwrap().clone();
+    task.with_mut_vm_space(|vm| {
+        vm.check_mapped(start, len).map_err(|_| SysError::ENOMEM)?;
+        check_memlock(&task, vm.locked_pages() + vm.unlocked_pages(start, len))?;
+        vm.set_locked(start, len, true)?;
+        if flags & MLOCK_ONFAULT == 0 {
+            vm.populate(start, len)?;
+        }
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// syscall munlock
+pub fn sys_munlock(addr: VirtAddr, len: usize) -> SysResult {
+    let (start, len) = page_range(addr, len)?;
+    if len == 0 {
+        return Ok(0);
+    }
+    let task = current_task().unwrap().clone();
+    task.with_mut_vm_space(|vm| vm.set_locked(start, len, false))?;
+    Ok(0)
+}
+
+/// syscall mlockall: MCL_CURRENT locks what is mapped,
+/// MCL_FUTURE what gets mapped later
+pub fn sys_mlockall(flags: i32) -> SysResult {
+    if flags & !(MCL_CURRENT | MCL_FUTURE | MCL_ONFAULT) != 0
+        || flags & (MCL_CURRENT | MCL_FUTURE) == 0
+    {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap().clone();
+    task.with_mut_vm_space(|vm| {
+        if flags & MCL_CURRENT != 0 {
+            check_memlock(&task, vm.lockable_pages())?;
+        } else {
+            check_memlock(&task, 1)?;
+        }
+        vm.set_mlockall_flags(flags & (MCL_FUTURE | MCL_ONFAULT));
+        if flags & MCL_CURRENT != 0 {
+            vm.set_all_locked(true);
+            if flags & MCL_ONFAULT == 0 {
+                vm.populate_locked()?;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// syscall munlockall
+pub fn sys_munlockall() -> SysResult {
+    let task = current_task().unwrap().clone();
+    task.with_mut_vm_space(|vm| {
+        vm.set_mlockall_flags(0);
+        vm.set_all_locked(false);
+    });
+    Ok(0)
+}
+
+/// only flush the icache of current hart
+#[cfg(target_arch = "riscv64")]
+const SYS_RISCV_FLUSH_ICACHE_LOCAL: usize = 1;
+
+/// syscall riscv_flush_icache: make the stores to the range visible to the instruction
+/// fetch of every thread of the process, or only of the calling one with the LOCAL flag
+#[cfg(target_arch = "riscv64")]
+pub fn sys_riscv_flush_icache(start: VirtAddr, end: VirtAddr, flags: usize) -> SysResult {
+    if flags & !SYS_RISCV_FLUSH_ICACHE_LOCAL != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap().clone();
+    if start < end {
+        task.with_mut_vm_space(|vm| vm.check_mapped(start, end.0 - start.0))
+            .map_err(|_| SysError::EFAULT)?;
+    }
+    if flags & SYS_RISCV_FLUSH_ICACHE_LOCAL != 0 {
+        unsafe { Instruction::icache_flush(false) };
+        // the caller may be migrated later, the harts it lands on flush then
+        task.with_mut_vm_space(|vm| vm.mark_icache_stale());
+    } else {
+        unsafe { Instruction::icache_flush(true) };
+    }
+    Ok(0)
 }
 
 /// syscall
@@ -266,6 +631,7 @@ pub fn sys_mremap(
     if old_area.vma_type != UserVmAreaType::Mmap {
         return Err(SysError::EINVAL);
     }
+    vm.check_unsealed(old_addr, old_size)?;
 
     if flags.contains(MremapFlags::DONTUNMAP)
         && !old_area
@@ -287,8 +653,9 @@ pub fn sys_mremap(
             .is_ok()
         {
             let mut old_area = vm.unmap(old_addr, old_size)?;
-            old_area.extend(new_size - old_size);
+            let ret = old_area.extend(new_size - old_size);
             vm.push_area(old_area, None);
+            ret?;
             return Ok(old_size as isize);
         }
         if flags.is_empty() {
@@ -344,279 +711,166 @@ pub fn sys_mremap(
     Ok(new_addr.0 as isize)
 }
 
-pub fn sys_process_vm_readv(
-    pid: i32, lvec: usize, liovcnt: usize, rvec: usize, riovcnt: usize, flags: usize
-) -> SysResult {
-    const IOV_MAX: usize = 1024;
-    let mut total_read_len: usize = 0; // 类型修正为 usize
-    if liovcnt > IOV_MAX || riovcnt > IOV_MAX || flags != 0 {
-        return Err(SysError::EINVAL);
-    }
-    
-    // todo 权限检查
-    let caller_task = current_task().unwrap();
-    let target_task = TASK_MANAGER.get_task(pid as usize).ok_or(SysError::ESRCH)?;
-
-    // 访问并检查本地和远程的iovec数组
-    let lvec_slice_raw = UserSliceRaw::new(lvec as *const IoVec, liovcnt)
-        .ensure_read(&mut caller_task.get_vm_space().lock())
-        .ok_or(SysError::EFAULT)?;
-    let rvec_slice_raw = UserSliceRaw::new(rvec as *const IoVec, riovcnt)
-        .ensure_read(&mut target_task.get_vm_space().lock())
-        .ok_or(SysError::EFAULT)?;
+/// direction of a process_vm_readv/process_vm_writev transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessVmDir {
+    /// remote to local
+    Read,
+    /// local to remote
+    Write,
+}
 
-    let caller_vec: &[IoVec] = lvec_slice_raw.to_ref();
-    let target_vec: &[IoVec] = rvec_slice_raw.to_ref();
-    
-    
-    for iov in caller_vec.iter() {
-        if iov.len > 0 {
-            let _ = UserSliceRaw::new(iov.base as *mut u8, iov.len)
-                .ensure_write(&mut caller_task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-        }
+/// whether the caller may read or write the memory of the target,
+/// the same rule as ptrace_may_access with PTRACE_MODE_ATTACH_REALCREDS
+fn may_access_vm(caller: &Arc<TaskControlBlock>, target: &Arc<TaskControlBlock>) -> bool {
+    if caller.pid() == target.pid() || caller.euid() == 0 {
+        return true;
     }
-    for iov in target_vec.iter() {
-        if iov.len > 0 {
-            let _ = UserSliceRaw::new(iov.base as *const u8, iov.len)
-                .ensure_read(&mut target_task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-        }
-    }
-
-    let mut caller_idx = 0;
-    let mut target_idx = 0;
-    let mut caller_offset: usize = 0; 
-    let mut target_offset: usize = 0; 
-
-    while caller_idx < liovcnt && target_idx < riovcnt {
-        let current_caller_iov = &caller_vec[caller_idx];
-        let current_target_iov = &target_vec[target_idx];
-
-        let caller_rem_len = current_caller_iov.len.saturating_sub(caller_offset);
-        let target_rem_len = current_target_iov.len.saturating_sub(target_offset);
-        let copy_len = caller_rem_len.min(target_rem_len);
-
-        if copy_len == 0 {
-            if caller_rem_len == 0 { caller_idx += 1; caller_offset = 0; }
-            if target_rem_len == 0 { target_idx += 1; target_offset = 0; }
-            continue;
-        }
-
-        let caller_start_addr = current_caller_iov.base + caller_offset;
-        let target_start_addr = current_target_iov.base + target_offset;
-        
-        let res = copy_from_userspace_unsafe(
-            &target_task,
-            &caller_task,
-            VirtAddr::from(caller_start_addr),
-            VirtAddr::from(target_start_addr),
-            copy_len
-        )?;
-        
-        total_read_len += res as usize;
-        caller_offset += res as usize;
-        target_offset += res as usize;
-    }
-
-    Ok(total_read_len as isize)
+    let (uid, gid) = (caller.ruid(), caller.rgid());
+    [target.ruid(), target.euid(), target.suid()].iter().all(|&id| id == uid)
+        && [target.rgid(), target.egid(), target.sgid()].iter().all(|&id| id == gid)
 }
 
-fn copy_from_userspace_unsafe(target: &Arc<TaskControlBlock>, caller: &Arc<TaskControlBlock>, caller_start_addr: VirtAddr, target_start_addr: VirtAddr, len: usize) -> SysResult {
-    let target_vm_space = target.get_vm_space().lock();
-    let target_page_table = target_vm_space.get_page_table();
-    let caller_vm_space = caller.get_vm_space().lock();
-    let caller_page_table = caller_vm_space.get_page_table();
-    let mut current_len: usize = 0;
-    
-    let kernel_direct_map_offset = hal::constant::Constant::KERNEL_ENTRY_PA; 
-
-    while current_len < len {
-        let target_va = VirtAddr::from(target_start_addr.0 + current_len);
-        let caller_va = VirtAddr::from(caller_start_addr.0 + current_len);
-
-        let target_page_offset = target_va.page_offset();
-        let caller_page_offset = caller_va.page_offset();
-
-        let (target_pte, _) = target_page_table.find_pte(target_va.floor()).ok_or(SysError::EFAULT)?;
-        if !target_pte.is_valid() {
-            return Err(SysError::EFAULT);
-        }
-        // 确保远程地址可读
-        if !target_pte.flags().contains(MapPerm::R) {
-            return Err(SysError::EFAULT);
-        }
-        let target_ppn = target_pte.ppn();
-
-        let (caller_pte, _) = caller_page_table.find_pte(caller_va.floor()).ok_or(SysError::EFAULT)?;
-        if !caller_pte.is_valid() || !caller_pte.flags().contains(MapPerm::W) {
-            return Err(SysError::EFAULT);
-        }
-        let caller_ppn = caller_pte.ppn();
-
-        let len_in_page = (PAGE_SIZE - target_page_offset).min(PAGE_SIZE - caller_page_offset).min(len - current_len);
-        
-
-        let target_phys_addr = target_ppn.0 << 12;
-        let caller_phys_addr = caller_ppn.0 << 12;
-
-        let target_ptr = (target_phys_addr + kernel_direct_map_offset) as *const u8 ;
-        let caller_ptr =  (caller_phys_addr + kernel_direct_map_offset) as *mut u8 ;
-
-        unsafe {
-            let src = target_ptr.add(target_page_offset);
-            let dst = caller_ptr.add(caller_page_offset);
-            core::ptr::copy_nonoverlapping(src, dst, len_in_page);
-        }
-        current_len += len_in_page;
+/// copy an iovec array out of the caller
+fn read_iovecs(vm: &mut UserVmSpace, iov: usize, iovcnt: usize) -> Result<Vec<IoVec>, SysError> {
+    if iovcnt == 0 {
+        return Ok(Vec::new());
+    }
+    let iovs = UserSliceRaw::new(iov as *const IoVec, iovcnt)
+        .ensure_read(vm)
+        .ok_or(SysError::EFAULT)?
+        .to_ref()
+        .to_vec();
+    let total = iovs.iter().try_fold(0usize, |acc, iov| acc.checked_add(iov.len));
+    match total {
+        Some(total) if total <= isize::MAX as usize => Ok(iovs),
+        _ => Err(SysError::EINVAL),
     }
-
-    Ok(current_len as isize)
 }
 
-
-pub fn sys_process_vm_writev(
-    pid: i32,
-    lvec: usize,
-    liovcnt: usize,
-    rvec: usize,
-    riovcnt: usize,
-    flags: usize,
+/// move data between the caller and another process, one remote page at a time.
+/// Only one of the two vm spaces is locked at any moment, bytes go through a
+/// kernel buffer in between, so two processes accessing each other
+/// (or a process accessing itself) can not deadlock.
+/// A fault stops the transfer, the bytes moved so far are returned if there are any.
+fn process_vm_rw(
+    pid: i32, lvec: usize, liovcnt: usize, rvec: usize, riovcnt: usize, flags: usize, dir: ProcessVmDir
 ) -> SysResult {
-    const IOV_MAX: usize = 1024;
-    let mut total_write_len: usize = 0;
     if liovcnt > IOV_MAX || riovcnt > IOV_MAX || flags != 0 {
         return Err(SysError::EINVAL);
     }
-
-    let caller_task = current_task().unwrap();
-    let target_task = TASK_MANAGER.get_task(pid as usize).ok_or(SysError::ESRCH)?;
-
-    let lvec_slice_raw = UserSliceRaw::new(lvec as *const IoVec, liovcnt)
-        .ensure_read(&mut caller_task.get_vm_space().lock())
-        .ok_or(SysError::EFAULT)?;
-    let rvec_slice_raw = UserSliceRaw::new(rvec as *const IoVec, riovcnt)
-        .ensure_read(&mut target_task.get_vm_space().lock())
-        .ok_or(SysError::EFAULT)?;
-
-    let caller_vec: &[IoVec] = lvec_slice_raw.to_ref();
-    let target_vec: &[IoVec] = rvec_slice_raw.to_ref();
-
-    // 预先检查所有内存区域
-    for iov in caller_vec.iter() {
-        if iov.len > 0 {
-            let _ = UserSliceRaw::new(iov.base as *const u8, iov.len)
-                .ensure_read(&mut caller_task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-        }
+    let caller = current_task().unwrap().clone();
+    // both iovec arrays live in the caller
+    let (local, remote) = {
+        let mut vm = caller.get_vm_space().lock();
+        (read_iovecs(&mut vm, lvec, liovcnt)?, read_iovecs(&mut vm, rvec, riovcnt)?)
+    };
+    if pid <= 0 {
+        return Err(SysError::ESRCH);
     }
-    for iov in target_vec.iter() {
-        if iov.len > 0 {
-            let _ = UserSliceRaw::new(iov.base as *mut u8, iov.len)
-                .ensure_write(&mut target_task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?;
-        }
+    let target = TASK_MANAGER.get_task(pid as usize).ok_or(SysError::ESRCH)?;
+    if !may_access_vm(&caller, &target) {
+        return Err(SysError::EPERM);
     }
 
-    let mut caller_idx = 0;
-    let mut target_idx = 0;
-    let mut caller_offset: usize = 0;
-    let mut target_offset: usize = 0;
-
-    while caller_idx < liovcnt && target_idx < riovcnt {
-        let current_caller_iov = &caller_vec[caller_idx];
-        let current_target_iov = &target_vec[target_idx];
-
-        let caller_rem_len = current_caller_iov.len.saturating_sub(caller_offset);
-        let target_rem_len = current_target_iov.len.saturating_sub(target_offset);
-        let copy_len = caller_rem_len.min(target_rem_len);
-
-        if copy_len == 0 {
-            if caller_rem_len == 0 {
-                caller_idx += 1;
-                caller_offset = 0;
-            }
-            if target_rem_len == 0 {
-                target_idx += 1;
-                target_offset = 0;
-            }
+    let access = match dir {
+        ProcessVmDir::Read => PageFaultAccessType::READ,
+        ProcessVmDir::Write => PageFaultAccessType::WRITE,
+    };
+    let mut bounce = vec![0u8; PAGE_SIZE];
+    let mut done = 0;
+    let (mut l_idx, mut l_off) = (0, 0);
+    let (mut r_idx, mut r_off) = (0, 0);
+    let fault = |done: usize| if done > 0 { Ok(done as isize) } else { Err(SysError::EFAULT) };
+    while l_idx < local.len() && r_idx < remote.len() {
+        let (l_iov, r_iov) = (&local[l_idx], &remote[r_idx]);
+        if l_off == l_iov.len {
+            (l_idx, l_off) = (l_idx + 1, 0);
             continue;
         }
-
-        let caller_start_addr = current_caller_iov.base + caller_offset;
-        let target_start_addr = current_target_iov.base + target_offset;
-
-        let res = copy_to_userspace_unsafe(
-            &target_task,
-            &caller_task,
-            VirtAddr::from(caller_start_addr),
-            VirtAddr::from(target_start_addr),
-            copy_len,
-        )?;
-
-        total_write_len += res as usize;
-        caller_offset += res as usize;
-        target_offset += res as usize;
+        if r_off == r_iov.len {
+            (r_idx, r_off) = (r_idx + 1, 0);
+            continue;
+        }
+        let l_va = l_iov.base + l_off;
+        let r_va = r_iov.base + r_off;
+        let len = (l_iov.len - l_off)
+            .min(r_iov.len - r_off)
+            .min(PAGE_SIZE - VirtAddr::from(r_va).page_offset());
+        if r_va.checked_add(len).map_or(true, |end| end > Constant::USER_ADDR_SPACE.end) {
+            return fault(done);
+        }
+        let buf = &mut bounce[..len];
+        if dir == ProcessVmDir::Write {
+            let mut vm = caller.get_vm_space().lock();
+            let Some(src) = UserSliceRaw::new(l_va as *const u8, len).ensure_read(&mut vm) else {
+                return fault(done);
+            };
+            buf.copy_from_slice(src.to_ref());
+        }
+        {
+            // faulting the page in also breaks copy on write before it is written
+            let mut vm = target.get_vm_space().lock();
+            let Some(pa) = translate_uva_checked(&mut vm, VirtAddr::from(r_va), access) else {
+                return fault(done);
+            };
+            let page = unsafe { core::slice::from_raw_parts_mut(pa.get_ptr::<u8>(), len) };
+            match dir {
+                ProcessVmDir::Read => buf.copy_from_slice(page),
+                ProcessVmDir::Write => page.copy_from_slice(buf),
+            }
+        }
+        if dir == ProcessVmDir::Read {
+            let mut vm = caller.get_vm_space().lock();
+            let Some(dst) = UserSliceRaw::new(l_va as *mut u8, len).ensure_write(&mut vm) else {
+                return fault(done);
+            };
+            dst.to_mut().copy_from_slice(buf);
+        }
+        done += len;
+        l_off += len;
+        r_off += len;
     }
-
-    Ok(total_write_len as isize)
+    Ok(done as isize)
 }
 
-fn copy_to_userspace_unsafe(
-    target: &Arc<TaskControlBlock>,
-    caller: &Arc<TaskControlBlock>,
-    caller_start_addr: VirtAddr,
-    target_start_addr: VirtAddr,
-    len: usize,
+/// syscall: process_vm_readv
+/// read the memory of process `pid` described by `rvec` into the caller's `lvec`
+pub fn sys_process_vm_readv(
+    pid: i32, lvec: usize, liovcnt: usize, rvec: usize, riovcnt: usize, flags: usize
 ) -> SysResult {
-    let target_vm_space = target.get_vm_space().lock();
-    let target_page_table = target_vm_space.get_page_table();
-    let caller_vm_space = caller.get_vm_space().lock();
-    let caller_page_table = caller_vm_space.get_page_table();
-    let mut current_len: usize = 0;
-
-    let kernel_direct_map_offset = hal::constant::Constant::KERNEL_ENTRY_PA;
-
-    while current_len < len {
-        let target_va = VirtAddr::from(target_start_addr.0 + current_len);
-        let caller_va = VirtAddr::from(caller_start_addr.0 + current_len);
-
-        let target_page_offset = target_va.page_offset();
-        let caller_page_offset = caller_va.page_offset();
-
-        let (target_pte, _) = target_page_table
-            .find_pte(target_va.floor())
-            .ok_or(SysError::EFAULT)?;
-        if !target_pte.is_valid() || !target_pte.flags().contains(MapPerm::W) {
-            return Err(SysError::EFAULT);
-        }
-        let target_ppn = target_pte.ppn();
-
-        let (caller_pte, _) = caller_page_table
-            .find_pte(caller_va.floor())
-            .ok_or(SysError::EFAULT)?;
-        if !caller_pte.is_valid() || !caller_pte.flags().contains(MapPerm::R) {
-            return Err(SysError::EFAULT);
-        }
-        let caller_ppn = caller_pte.ppn();
-
-        let len_in_page = (PAGE_SIZE - target_page_offset)
-            .min(PAGE_SIZE - caller_page_offset)
-            .min(len - current_len);
-
-        let target_phys_addr = target_ppn.0 << 12;
-        let caller_phys_addr = caller_ppn.0 << 12;
+    process_vm_rw(pid, lvec, liovcnt, rvec, riovcnt, flags, ProcessVmDir::Read)
+}
 
-        let target_ptr = (target_phys_addr + kernel_direct_map_offset) as *mut u8;
-        let caller_ptr = (caller_phys_addr + kernel_direct_map_offset) as *const u8;
+/// syscall: process_vm_writev
+/// write the caller's `lvec` into the memory of process `pid` described by `rvec`
+pub fn sys_process_vm_writev(
+    pid: i32, lvec: usize, liovcnt: usize, rvec: usize, riovcnt: usize, flags: usize
+) -> SysResult {
+    process_vm_rw(pid, lvec, liovcnt, rvec, riovcnt, flags, ProcessVmDir::Write)
+}
 
-        unsafe {
-            let src = caller_ptr.add(caller_page_offset);
-            let dst = target_ptr.add(target_page_offset);
-            core::ptr::copy_nonoverlapping(src, dst, len_in_page);
-        }
-        current_len += len_in_page;
+/// syscall: memfd_secret
+/// create a file whose pages, once mapped, are taken out of the kernel direct map
+pub fn sys_memfd_secret(flags: u32) -> SysResult {
+    // loongarch reaches physical memory through the direct mapped windows,
+    // no page can be hidden from the kernel there
+    if cfg!(target_arch = "loongarch64") {
+        return Err(SysError::ENOSYS);
     }
-
-    Ok(current_len as isize)
+    if flags & !(OpenFlags::O_CLOEXEC.bits() as u32) != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let flags = OpenFlags::from_bits_truncate(flags as i32);
+    let task = current_task().unwrap().clone();
+    let file = make_secretmem(flags, task.pid());
+    let fd_flags = if flags.contains(OpenFlags::O_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+    task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: fd_flags }))?;
+    info!("[sys_memfd_secret] fd: {}, flags: {:?}", fd, flags);
+    Ok(fd as isize)
 }
@@ -48,8 +48,10 @@ pub enum SyscallId {
     SYSCALL_UNLINKAT = 35,
     SYSCALL_SYMLINKAT = 36,
     SYSCALL_LINKAT = 37,
+    SYSCALL_RENAMEAT = 38,
     SYSCALL_UMOUNT2 = 39,
     SYSCALL_MOUNT = 40,
+    SYSCALL_PIVOT_ROOT = 41,
     SYSCALL_STATFS = 43,
     SYSCALL_FSTATFS = 44,
     SYSCALL_TRUNCATE = 45,
@@ -117,12 +119,15 @@ pub enum SyscallId {
     SYSCALL_CLOCK_GETRES = 114,
     SYSCALL_CLOCK_NANOSLEEP = 115,
     SYSCALL_SYSLOG = 116,
+    SYSCALL_SCHED_SETPARAM = 118,
     SYSCALL_SCHED_SETSCHEDULER = 119,
     SYSCALL_SCHED_GETSCHEDULER = 120,
     SYSCALL_SCHED_GETPARAM = 121,
     SYSCALL_SCHED_SETAFFINITY = 122,
     SYSCALL_SCHED_GETAFFINITY = 123,
     SYSCALL_YIELD = 124,
+    SYSCALL_SCHED_GET_PRIORITY_MAX = 125,
+    SYSCALL_SCHED_GET_PRIORITY_MIN = 126,
     SYSCALL_KILL = 129,
     SYSCALL_TKILL = 130,
     SYSCALL_TGKILL = 131,
@@ -132,6 +137,7 @@ pub enum SyscallId {
     SYSCALL_RT_SIGPROCMASK = 135,
     SYSCALL_RT_SIGPENDING = 136,
     SYSCALL_RT_SIGTIMEDWAIT = 137,
+    SYSCALL_RT_SIGQUEUEINFO = 138,
     SYSCALL_RT_SIGRETURN = 139,
     SYSCALL_SETPRIORITY = 140,
     SYSCALL_GETPRIORITY = 141,
@@ -217,9 +223,14 @@ pub enum SyscallId {
     SYSCALL_MUNLOCKALL = 231,
     SYSCALL_MINCORE = 232,
     SYSCALL_MADSIVE = 233,
+    SYSCALL_MBIND = 235,
     SYSCALL_GET_MEMPOLICY = 236,
+    SYSCALL_SET_MEMPOLICY = 237,
+    SYSCALL_RT_TGSIGQUEUEINFO = 240,
     SYSCALL_PERF_EVENT_OPEN = 241,
     SYSCALL_ACCEPT4 = 242,
+    #[cfg(target_arch = "riscv64")]
+    SYSCALL_RISCV_FLUSH_ICACHE = 259,
     SYSCALL_WAITPID = 260,
     SYSCALL_PRLIMIT64 = 261,
     SYSCALL_FANOTIFY_INIT = 262,
@@ -247,14 +258,23 @@ pub enum SyscallId {
     SYSCALL_PKEYALLOC = 289,
     SYSCALL_PKEYFREE = 290,
     SYSCALL_STATX = 291,
+    SYSCALL_PIDFD_SEND_SIGNAL = 424,
     SYSCALL_IO_URING_SETUP = 425,
     SYSCALL_OPEN_TREE = 428,
+    SYSCALL_MOVE_MOUNT = 429,
     SYSCALL_FSOPEN = 430,
+    SYSCALL_FSCONFIG = 431,
+    SYSCALL_FSMOUNT = 432,
     SYSCALL_FSPICK = 433,
     SYSCALL_PIDFD_OPEN = 434,
     SYSCALL_CLONE3 = 435,
+    SYSCALL_OPENAT2 = 437,
     SYSCALL_FACCESSAT2 = 439,
     SYSCALL_EPOLL_PWAIT2 = 441,
+    SYSCALL_MEMFD_SECRET = 447,
+    SYSCALL_STATMOUNT = 457,
+    SYSCALL_LISTMOUNT = 458,
+    SYSCALL_MSEAL = 462,
 }
 
 
@@ -277,6 +297,7 @@ pub mod net;
 /// ipc
 pub mod ipc;
 pub mod reboot;
+pub mod xattr;
 use alloc::format;
 pub use fs::*;
 use futex::{sys_futex, sys_get_robust_list, sys_set_robust_list, FUTEX_OWNER_DIED, FUTEX_TID_MASK, FUTEX_WAITERS};
@@ -284,7 +305,9 @@ use hal::{addr::VirtAddr, println};
 use io::*;
 use ipc::sysv::{sys_shmat, sys_shmctl, sys_shmdt, sys_shmget};
 use misc::*;
-use mm::{sys_mmap, sys_mprotect, sys_mremap, sys_munmap};
+use mm::{sys_madvise, sys_memfd_secret, sys_mincore, sys_mlock, sys_mlock2, sys_mlockall, sys_mmap, sys_mprotect, sys_mremap, sys_mseal, sys_munlock, sys_munlockall, sys_munmap};
+#[cfg(target_arch = "riscv64")]
+use mm::sys_riscv_flush_icache;
 use net::*;
 pub use process::*;
 use strum::FromRepr;
@@ -292,8 +315,9 @@ pub use time::*;
 pub use signal::*;
 pub use sche::*;
 pub use reboot::*;
+use xattr::*;
 pub use self::sys_error::SysError;
-use crate::{fs::RenameFlags, mm::{UserPtr, UserPtrRaw}, signal::{SigAction, SigSet}, syscall::{fd::sys_allocfd, mm::{sys_process_vm_readv, sys_process_vm_writev}}, task::current_task, timer::{ffi::{TimeVal, Tms}, timer::TimerId}, utils::{timer::TimerGuard, SendWrapper}};
+use crate::{fs::RenameFlags, mm::{UserPtr, UserPtrRaw}, signal::{LinuxSigInfo, SigAction, SigSet}, syscall::{fd::sys_allocfd, mm::{sys_process_vm_readv, sys_process_vm_writev}}, task::current_task, timer::{ffi::{TimeVal, Tms}, timer::TimerId}, utils::{timer::TimerGuard, SendWrapper}};
 /// The result of a syscall, either Ok(return value) or Err(error code)
 pub type SysResult = Result<isize, SysError>;
 
@@ -309,51 +333,55 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     // log::warn!("task: {}, id: {},  syscall: {:?}, args: {:x?}", current_task().unwrap().tid() , num, syscall_id, args);
 
     let result = match syscall_id { 
-        SYSCALL_SETXATTR => sys_temp(syscall_id),
-        SYSCALL_LSETXATTR => sys_temp(syscall_id),
-        SYSCALL_FSETXATTR => sys_temp(syscall_id),
-        SYSCALL_GETXATTR => sys_temp(syscall_id),
-        SYSCALL_LGETXATTR => sys_temp(syscall_id),
-        SYSCALL_FGETXATTR => sys_temp(syscall_id),
-        SYSCALL_LISTXATTR => sys_temp(syscall_id),
-        SYSCALL_LLISTXATTR => sys_temp(syscall_id),
-        SYSCALL_FLISTXATTR => sys_temp(syscall_id),
-        SYSCALL_REMOVEXATTR => sys_temp(syscall_id),
-        SYSCALL_LREMOVEXATTR => sys_temp(syscall_id),
-        SYSCALL_FREMOVEXATTR => sys_temp(syscall_id),
+        SYSCALL_SETXATTR => sys_setxattr(args[0], args[1], args[2], args[3], args[4] as i32),
+        SYSCALL_LSETXATTR => sys_lsetxattr(args[0], args[1], args[2], args[3], args[4] as i32),
+        SYSCALL_FSETXATTR => sys_fsetxattr(args[0], args[1], args[2], args[3], args[4] as i32),
+        SYSCALL_GETXATTR => sys_getxattr(args[0], args[1], args[2], args[3]),
+        SYSCALL_LGETXATTR => sys_lgetxattr(args[0], args[1], args[2], args[3]),
+        SYSCALL_FGETXATTR => sys_fgetxattr(args[0], args[1], args[2], args[3]),
+        SYSCALL_LISTXATTR => sys_listxattr(args[0], args[1], args[2]),
+        SYSCALL_LLISTXATTR => sys_llistxattr(args[0], args[1], args[2]),
+        SYSCALL_FLISTXATTR => sys_flistxattr(args[0], args[1], args[2]),
+        SYSCALL_REMOVEXATTR => sys_removexattr(args[0], args[1]),
+        SYSCALL_LREMOVEXATTR => sys_lremovexattr(args[0], args[1]),
+        SYSCALL_FREMOVEXATTR => sys_fremovexattr(args[0], args[1]),
         SYSCALL_IO_GETEVENTS => sys_temp(syscall_id),
         SYSCALL_GETCWD => sys_getcwd(args[0] as usize, args[1] as usize),
-        SYSCALL_EVENTFD => sys_allocfd(syscall_id),
+        SYSCALL_EVENTFD => sys_eventfd2(args[0] as u32, args[1] as u32),
         SYSCALL_EPOLL_CREATE1 => sys_epoll_create1(args[0]),
         SYSCALL_EPOLL_CTL => sys_epoll_ctl(args[0], args[1], args[2], args[3]),
         SYSCALL_EPOLL_PWAIT => sys_epoll_pwait(args[0], args[1], args[2], args[3], args[4]).await,
         SYSCALL_DUP => sys_dup(args[0] as usize),
         SYSCALL_DUP3 => sys_dup3(args[0] as usize, args[1] as usize, args[2] as u32),
-        SYSCALL_INOTIFY_INIT1 => sys_allocfd(syscall_id),
-        SYSCALL_INOTIFY_ADD_WATCH => sys_temp(syscall_id),
-        SYSCALL_INOTIFY_RM_WATCH => sys_temp(syscall_id),
-        SYSCALL_FCNTL => sys_fnctl(args[0], args[1] as isize, args[2]),
+        SYSCALL_INOTIFY_INIT1 => sys_inotify_init1(args[0] as u32),
+        SYSCALL_INOTIFY_ADD_WATCH => sys_inotify_add_watch(args[0], args[1] as *const u8, args[2] as u32),
+        SYSCALL_INOTIFY_RM_WATCH => sys_inotify_rm_watch(args[0], args[1] as i32),
+        SYSCALL_FCNTL => sys_fnctl(args[0], args[1] as isize, args[2]).await,
         SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
         SYSCALL_IOPRIO_SET => sys_temp(syscall_id),
         SYSCALL_IOPRIO_GET => sys_temp(syscall_id),
-        SYSCALL_MKNODAT => sys_temp(syscall_id),
-        SYSCALL_FLOCK => sys_temp(syscall_id),
-        SYSCALL_OPENAT => sys_openat(args[0] as isize , args[1] as *const u8, args[2] as i32, args[3] as u32),
+        SYSCALL_MKNODAT => sys_mknodat(args[0] as isize, args[1] as *const u8, args[2] as u32, args[3] as u64),
+        SYSCALL_FLOCK => sys_flock(args[0], args[1]).await,
+        SYSCALL_OPENAT => sys_openat(args[0] as isize , args[1] as *const u8, args[2] as i32, args[3] as u32).await,
+        SYSCALL_OPENAT2 => sys_openat2(args[0] as isize, args[1] as *const u8, args[2] as *const u8, args[3]).await,
+        SYSCALL_STATMOUNT => sys_statmount(args[0] as *const u8, args[1], args[2], args[3]),
+        SYSCALL_LISTMOUNT => sys_listmount(args[0] as *const u8, args[1], args[2], args[3]),
         SYSCALL_MKDIR => sys_mkdirat(args[0] as isize, args[1] as *const u8, args[2] as usize),
         SYSCALL_UNLINKAT => sys_unlinkat(args[0] as isize, args[1] as *const u8, args[3] as i32),
         SYSCALL_SYMLINKAT => sys_symlinkat(args[0] as *const u8, args[1] as isize, args[2] as *const u8),
         SYSCALL_LINKAT => sys_linkat(args[0] as isize, args[1] as *const u8, args[2] as isize, args[3] as *const u8, args[4] as i32),
         SYSCALL_MOUNT => sys_mount(args[0] as *const u8, args[1] as *const u8, args[2] as *const u8, args[3] as u32, args[4] as usize),
         SYSCALL_STATFS => sys_statfs(args[0], args[1]),
-        SYSCALL_FSTATFS => sys_temp(syscall_id),
+        SYSCALL_FSTATFS => sys_fstatfs(args[0], args[1]),
         SYSCALL_TRUNCATE => sys_truncate(args[0] as *const u8, args[1]),
         SYSCALL_FTRUNCATE => sys_ftruncate(args[0], args[1]),
-        SYSCALL_FALLOCATE => sys_temp(syscall_id),
-        SYSCALL_FACCESSAT => sys_faccessat(args[0] as isize, args[1] as *const u8, args[2], args[3] as i32),
+        SYSCALL_FALLOCATE => sys_fallocate(args[0], args[1] as i32, args[2] as isize, args[3] as isize),
+        SYSCALL_FACCESSAT => sys_faccessat(args[0] as isize, args[1] as *const u8, args[2] as i32),
         SYSCALL_UMOUNT2 => sys_umount2(args[0] as *const u8, args[1] as u32),
+        SYSCALL_PIVOT_ROOT => sys_pivot_root(args[0] as *const u8, args[1] as *const u8),
         SYSCALL_CHDIR => sys_chdir(args[0] as *const u8),
         SYSCALL_FCHDIR => sys_fchdir(args[0]),
-        SYSCALL_CHROOT => sys_temp(syscall_id),
+        SYSCALL_CHROOT => sys_chroot(args[0] as *const u8),
         SYSCALL_FCHMOD => sys_fchmod(args[0] as isize, args[1] as u32),
         SYSCALL_FCHMODAT => sys_fchmodat(args[0] as isize, args[1] as *const u8, args[2] as u32, args[3] as i32),
         SYSCALL_FCHOWNAT => sys_fchownat(args[0] as isize, args[1] as *const u8, args[2] as i32, args[3] as i32, args[4] as i32),
@@ -372,11 +400,11 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_PWRITEV => sys_pwritev(args[0], args[1], args[2], args[3]).await,
         SYSCALL_SENDFILE => sys_sendfile(args[0], args[1], args[2], args[3]).await,
         SYSCALL_PPOLL => sys_ppoll(args[0], args[1], args[2], args[3]).await,
-        SYSCALL_SIGNALFD => sys_allocfd(syscall_id),
+        SYSCALL_SIGNALFD => sys_signalfd4(args[0] as isize, args[1] as *const u64, args[2], args[3] as u32),
         SYSCALL_PSELECT6 => sys_pselect6(args[0] as i32, args[1], args[2], args[3], args[4], args[5]).await,
         SYSCALL_VMSPLICE => sys_vmsplice(args[0], args[1], args[2], args[3] as u32).await,
         SYSCALL_SPLICE => sys_splice(args[0], args[1], args[2], args[3], args[4], args[5] as i32).await,
-        SYSCALL_TEE => sys_temp(syscall_id),
+        SYSCALL_TEE => sys_tee(args[0], args[1], args[2], args[3] as u32).await,
         SYSCALL_READLINKAT => sys_readlinkat(args[0] as isize, args[1] as *const u8, args[2], args[3]),
         SYSCALL_FSTATAT => sys_fstatat(args[0] as isize, args[1] as *const u8, args[2], args[3] as i32),
         SYSCALL_FSTAT => sys_fstat(args[0], args[1]),
@@ -406,14 +434,17 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_SYSLOG => sys_syslog(args[0], args[1], args[2]),
         SYSCALL_SCHED_SETAFFINITY => sys_sched_setaffinity(args[0] , args[1] , args[2] ),
         SYSCALL_SCHED_GETAFFINITY => sys_sched_getaffinity(args[0] , args[1] , args[2] ),
-        SYSCALL_SCHED_GETSCHEDULER => sys_sched_getscheduler(),
-        SYSCALL_SCHED_SETSCHEDULER => sys_sched_setscheduler(),
-        SYSCALL_SCHED_GETPARAM => sys_sched_getparam(),
+        SYSCALL_SCHED_GETSCHEDULER => sys_sched_getscheduler(args[0] as isize),
+        SYSCALL_SCHED_SETSCHEDULER => sys_sched_setscheduler(args[0] as isize, args[1] as i32, args[2]),
+        SYSCALL_SCHED_GETPARAM => sys_sched_getparam(args[0] as isize, args[1]),
+        SYSCALL_SCHED_SETPARAM => sys_sched_setparam(args[0] as isize, args[1]),
+        SYSCALL_SCHED_GET_PRIORITY_MAX => sys_sched_get_priority_max(args[0] as i32),
+        SYSCALL_SCHED_GET_PRIORITY_MIN => sys_sched_get_priority_min(args[0] as i32),
         SYSCALL_YIELD => sys_yield().await,
         SYSCALL_KILL => sys_kill(args[0] as isize, args[1] as i32),
         SYSCALL_TKILL => sys_tkill(args[0] as isize, args[1] as i32),
         SYSCALL_TGKILL => sys_tgkill( args[0] as isize, args[1] as isize, args[2] as i32),
-        SYSCALL_SIGALTSTACK => sys_signalstack(args[0], args[1]),
+        SYSCALL_SIGALTSTACK => sys_sigaltstack(args[0], args[1]),
         SYSCALL_RT_SIGSUSPEND => sys_rt_sigsuspend(args[0]).await,
         SYSCALL_RT_SIGACTION => sys_rt_sigaction(args[0] as i32, args[1] as *const SigAction, args[2] as *mut SigAction),
         SYSCALL_RT_SIGPROCMASK => sys_rt_sigprocmask(args[0] as i32, args[1] as *const u32, args[2] as *mut SigSet),
@@ -422,6 +453,8 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_SETPRIORITY => sys_set_priority(args[0], args[1] as usize, args[2] as i32),
         SYSCALL_GETPRIORITY => sys_get_priority(args[0], args[1] as usize),
         SYSCALL_RT_SIGTIMEDWAIT => sys_rt_sigtimedwait(args[0] , args[1] , args[2] ).await,
+        SYSCALL_RT_SIGQUEUEINFO => sys_rt_sigqueueinfo(args[0] as isize, args[1] as i32, args[2] as *const LinuxSigInfo),
+        SYSCALL_RT_TGSIGQUEUEINFO => sys_rt_tgsigqueueinfo(args[0] as isize, args[1] as isize, args[2] as i32, args[3] as *const LinuxSigInfo),
         SYSCALL_REBOOT => sys_reboot(args[0] as _, args[0] as _, args[0] as _, args[0]).await,
         SYSCALL_SETRESUID => sys_setresuid(args[0] as i32, args[1] as i32, args[2] as i32),
         SYSCALL_GETRESUID => sys_getresuid(args[0], args[1], args[2]),
@@ -430,7 +463,7 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_TIMES => sys_times(args[0]),
         SYSCALL_UNAME => sys_uname(args[0]),
         SYSCALL_UMASK => sys_umask(args[0] as i32),
-        SYSCALL_PRCTL => sys_temp(syscall_id),
+        SYSCALL_PRCTL => sys_prctl(args[0] as i32, args[1]),
         SYSCALL_GETCPU => sys_getcpu(args[0], args[1]),
         SYSCALL_GETTIMEOFDAY => sys_gettimeofday(args[0]),
         SYSCALL_SETTIMEOFDAY => sys_temp(syscall_id),
@@ -463,7 +496,7 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_GETPGID => sys_getpgid(args[0]),
         SYSCALL_CLONE => sys_clone(args[0] as u64, args[1].into(), args[2].into(), args[3].into(), args[4].into()).await,
         SYSCALL_CLONE3 => sys_clone3(args[0], args[1]).await,
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1], args[2] as i32).await,
+        SYSCALL_WAITPID => sys_wait4(args[0] as isize, args[1], args[2] as i32, args[3]).await,
         SYSCALL_SETHOSTNAME => sys_sethostname(args[0], args[1]).await,
         SYSCALL_SETDOMAINNAME =>  sys_setdomainname(args[0], args[1]),
         SYSCALL_PRLIMIT64 => sys_prlimit64(args[0], args[1] as i32, args[2], args[3]),
@@ -493,11 +526,12 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_KCMP => sys_temp(syscall_id),
         SYSCALL_SCHED_GETATTR => sys_temp(syscall_id),
         SYSCALL_SCHED_SETATTR => sys_temp(syscall_id),
+        SYSCALL_RENAMEAT => sys_renameat(args[0] as isize, args[1] as *const u8, args[2] as isize, args[3] as *const u8),
         SYSCALL_RENAMEAT2 => sys_renameat2(args[0] as isize, args[1] as *const u8, args[2] as isize, args[3] as *const u8, args[4] as i32),
         SYSCALL_GETRANDOM => sys_getrandom(args[0], args[1], args[2]),
         SYSCALL_GETRLIMIT => sys_temp(syscall_id),
-        SYSCALL_PREADV2 => sys_preadv2(args[0], args[1], args[2], args[3], args[4] as i32).await,
-        SYSCALL_PWRITEV2 => sys_pwritev2(args[0], args[1], args[2], args[3], args[4] as i32).await,
+        SYSCALL_PREADV2 => sys_preadv2(args[0], args[1], args[2], args[3], args[5] as i32).await,
+        SYSCALL_PWRITEV2 => sys_pwritev2(args[0], args[1], args[2], args[3], args[5] as i32).await,
         SYSCALL_PKEY_DISABLEACCESS => sys_temp(syscall_id),
         SYSCALL_PKEYALLOC => sys_temp(syscall_id),
         SYSCALL_PKEYFREE => sys_temp(syscall_id),
@@ -519,24 +553,29 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_RECVMSG => sys_recvmsg(args[0], args[1], args[2]).await,
         SYSCALL_READAHEAD => sys_readahead(args[0], args[1], args[2]),
         SYSCALL_MPROTECE => sys_mprotect(args[0].into(), args[1], args[2] as _),
-        SYSCALL_MUNLOCK => sys_temp(syscall_id),
-        SYSCALL_MLOCKALL => sys_temp(syscall_id),
-        SYSCALL_MUNLOCKALL => sys_temp(syscall_id),
-        SYSCALL_MINCORE => sys_temp(syscall_id),
-        SYSCALL_MADSIVE =>  sys_temp(syscall_id),
-        SYSCALL_GET_MEMPOLICY => sys_temp(syscall_id),
+        SYSCALL_MUNLOCK => sys_munlock(args[0].into(), args[1]),
+        SYSCALL_MLOCKALL => sys_mlockall(args[0] as i32),
+        SYSCALL_MUNLOCKALL => sys_munlockall(),
+        SYSCALL_MINCORE => sys_mincore(args[0].into(), args[1], args[2]),
+        SYSCALL_MADSIVE => sys_madvise(args[0].into(), args[1], args[2]),
+        SYSCALL_MSEAL => sys_mseal(args[0].into(), args[1], args[2]),
+        SYSCALL_MBIND => sys_mbind(args[0].into(), args[1], args[2] as i32, args[3], args[4], args[5] as u32),
+        SYSCALL_GET_MEMPOLICY => sys_get_mempolicy(args[0], args[1], args[2], args[3], args[4] as u32),
+        SYSCALL_SET_MEMPOLICY => sys_set_mempolicy(args[0] as i32, args[1], args[2]),
         SYSCALL_PERF_EVENT_OPEN => sys_allocfd(syscall_id),
         SYSCALL_ACCEPT4 => sys_accept(args[0], args[1], args[2]).await,
-        SYSCALL_SYNC => sys_temp(syscall_id),
-        SYSCALL_FSYNC => sys_temp(syscall_id),
+        #[cfg(target_arch = "riscv64")]
+        SYSCALL_RISCV_FLUSH_ICACHE => sys_riscv_flush_icache(args[0].into(), args[1].into(), args[2]),
+        SYSCALL_SYNC => sys_sync(),
+        SYSCALL_FSYNC => sys_fsync(args[0]),
         SYSCALL_FDATASYNC => sys_fdatasync(args[0]),
-        SYSCALL_TIMERFD_CREATE => sys_timerfd_create(args[0], args[1]),
-        SYSCALL_TIMERFD_SETTIME => sys_timerfd_settime(args[0], args[1], args[2], args[3]),
+        SYSCALL_TIMERFD_CREATE => sys_timerfd_create(args[0], args[1] as u32),
+        SYSCALL_TIMERFD_SETTIME => sys_timerfd_settime(args[0], args[1] as u32, args[2], args[3]),
         SYSCALL_TIMERFD_GETTIME => sys_timerfd_gettime(args[0], args[1]),
         SYSCALL_MSYNC => sys_temp(syscall_id),
-        SYSCALL_MLOCK => sys_temp(syscall_id),
-        SYSCALL_MEMBARRIER => sys_temp(syscall_id),
-        SYSCALL_MLOCK2 => sys_temp(syscall_id),
+        SYSCALL_MLOCK => sys_mlock(args[0].into(), args[1]),
+        SYSCALL_MEMBARRIER => sys_membarrier(args[0] as i32, args[1] as u32, args[2] as i32),
+        SYSCALL_MLOCK2 => sys_mlock2(args[0].into(), args[1], args[2] as u32),
         SYSCALL_COPY_FILE_RANGE => sys_copy_file_range(args[0], args[1], args[2], args[3], args[4], args[5] as u32).await,
         SYSCALL_IO_URING_SETUP => sys_allocfd(syscall_id),
         SYSCALL_SETREGID => sys_setregid(args[0] as i32, args[1] as i32),
@@ -552,10 +591,15 @@ pub async fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
         SYSCALL_USERFAULTFD => sys_allocfd(syscall_id),
         SYSCALL_FACCESSAT2 => sys_faccessat2(args[0] as isize, args[1] as *const u8, args[2] as i32, args[3] as i32),
         SYSCALL_EPOLL_PWAIT2 => sys_temp(syscall_id),
-        SYSCALL_FSOPEN => sys_allocfd(syscall_id),
-        SYSCALL_PIDFD_OPEN => sys_allocfd(syscall_id),
+        SYSCALL_FSOPEN => sys_fsopen(args[0] as *const u8, args[1] as u32),
+        SYSCALL_FSCONFIG => sys_fsconfig(args[0], args[1] as u32, args[2] as *const u8, args[3] as *const u8, args[4] as i32),
+        SYSCALL_FSMOUNT => sys_fsmount(args[0], args[1] as u32, args[2] as u32),
+        SYSCALL_MOVE_MOUNT => sys_move_mount(args[0] as isize, args[1] as *const u8, args[2] as isize, args[3] as *const u8, args[4] as u32),
+        SYSCALL_PIDFD_OPEN => sys_pidfd_open(args[0] as isize, args[1] as u32),
+        SYSCALL_PIDFD_SEND_SIGNAL => sys_pidfd_send_signal(args[0], args[1] as i32, args[2] as *const LinuxSigInfo, args[3] as u32),
         SYSCALL_FSPICK => sys_allocfd(syscall_id),
         SYSCALL_MEMFD_CREATE => sys_allocfd(syscall_id),
+        SYSCALL_MEMFD_SECRET => sys_memfd_secret(args[0] as u32),
         SYSCALL_OPEN_TREE => sys_allocfd(syscall_id),
         /* 
         _ => { 
@@ -6,7 +6,7 @@ use hal::{addr, instruction::{Instruction, InstructionHal}, println};
 use lwext4_rust::bindings::EXT4_SUPERBLOCK_FLAGS_TEST_FILESYS;
 use smoltcp::{socket::dns::Socket, time::Duration, wire::{IpAddress, IpProtocol, Ipv4Address}};
 
-use crate::{config::PAGE_SIZE, fs::{pipefs, vfs::{file::open_file, File}, OpenFlags}, mm::{UserPtr, UserPtrRaw, UserSliceRaw}, net::{addr::{SockAddr, SockAddrIn4, SockAddrIn6, SockAddrUn}, crypto::{encode, AlgInstance, AlgType, SockAddrAlg}, socket::{self, Sock, SockResult}, socketpair::make_socketpair, tcp::TcpSocket, SaFamily, SOCKET_SET}, signal::SigSet, syscall::{misc::UTS, process}, task::{current_task, fs::{FdFlags, FdInfo}, task::TaskControlBlock}, timer::ffi::TimeSpec, utils::yield_now};
+use crate::{config::PAGE_SIZE, fs::{pipefs, vfs::{file::open_file, File}, OpenFlags}, mm::{UserPtr, UserPtrRaw, UserSliceRaw}, net::{addr::{SockAddr, SockAddrIn4, SockAddrIn6, SockAddrUn, ZERO_IPV4_ENDPOINT}, crypto::{encode, AlgInstance, AlgType, SockAddrAlg}, socket::{self, Sock, SockResult}, socketpair::make_socketpair, tcp::TcpSocket, SaFamily, SOCKET_SET}, signal::SigSet, syscall::{misc::UTS, process}, task::{current_task, fs::{FdFlags, FdInfo}, task::TaskControlBlock}, timer::ffi::TimeSpec, utils::yield_now};
 
 use super::{IoVec, SysError, SysResult};
 
@@ -252,6 +252,10 @@ pub async fn sys_connect(fd: usize, addr: usize, addr_len: usize) -> SysResult {
         .downcast_arc::<socket::Socket>()
         .map_err(|_| SysError::ENOTSOCK)?;
     log::info!("[sys_connect] socket_file_type {:#?}", socket_file.sk_type);
+    if let Sock::Unix(unix) = &socket_file.sk {
+        unix.connect(unsafe { remote_addr.unix })?;
+        return Ok(0);
+    }
     socket_file.sk.connect(remote_addr.into_endpoint()?).await?;
     // yield_now().await;
     Ok(0)
@@ -307,16 +311,25 @@ pub async fn sys_accept(fd: usize, addr: usize, addr_len: usize) -> SysResult {
     task.set_interruptable();
     let old_mask = task.sig_manager.lock().blocked_sigs;
     task.set_wake_up_sigs(!old_mask);
-    let accept_sk = socket_file.sk.accept().await?;
+    let (accept_sk, peer_addr) = match &socket_file.sk {
+        Sock::Unix(unix) => {
+            let accept_sk = unix.accept().await?;
+            let peer_addr = accept_sk.peer_addr()?;
+            (Sock::Unix(accept_sk), peer_addr)
+        }
+        sk => {
+            let accept_sk = sk.accept().await?;
+            let peer_addr_endpoint = accept_sk.peer_addr().unwrap();
+            (Sock::TCP(accept_sk), SockAddr::from_endpoint(peer_addr_endpoint))
+        }
+    };
     task.set_running();
     log::info!("get accept correct");
-    let peer_addr_endpoint = accept_sk.peer_addr().unwrap();
-    let peer_addr = SockAddr::from_endpoint(peer_addr_endpoint);
     // log::info!("Accept a connection from {:?}", peer_addr);
     // write to pointer
    sockaddr_writer(task,addr, addr_len, peer_addr)?;
 
-    let accept_socket = Arc::new(socket::Socket::from_another(&socket_file, Sock::TCP(accept_sk)));
+    let accept_socket = Arc::new(socket::Socket::from_another(&socket_file, accept_sk));
     let fd_info = FdInfo {
         file: accept_socket,
         flags: OpenFlags::empty().into(),
@@ -679,8 +692,12 @@ impl SocketOption {
                 if opt.len() < 4 {
                     return Err(SysError::EINVAL);
                 }
-                let len = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
-                socket.set_send_buf_size(len as usize);
+                let len = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap()).max(0) as usize;
+                let len = match &socket.sk {
+                    Sock::TCP(tcp) => tcp.set_tx_buf_len(len),
+                    _ => len,
+                };
+                socket.set_send_buf_size(len);
                 Ok(0)
             }
 
@@ -688,8 +705,21 @@ impl SocketOption {
                 if opt.len() < 4 {
                     return Err(SysError::EINVAL);
                 }
-                let len = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
-                socket.set_recv_buf_size(len as usize);
+                let len = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap()).max(0) as usize;
+                let len = match &socket.sk {
+                    Sock::TCP(tcp) => tcp.set_rx_buf_len(len),
+                    _ => len,
+                };
+                socket.set_recv_buf_size(len);
+                Ok(0)
+            }
+
+            SocketOption::REUSEPORT => {
+                if opt.len() < 4 {
+                    return Err(SysError::EINVAL);
+                }
+                let value = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
+                socket.set_reuse_port(value != 0);
                 Ok(0)
             }
 
@@ -697,23 +727,10 @@ impl SocketOption {
                 if opt.len() < 4 {
                     return Err(SysError::EINVAL);
                 }
-                let len_opt = u32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
-                let expire = if len_opt != 0 {
-                    Some(Duration::from_secs(60 as u64))
-                }else {
-                    None
-                };
-                match &socket.sk {
-                    Sock::TCP(tcp) => {
-                        if let Some(handle) = tcp.handle(){
-                            SOCKET_SET.with_socket_mut::<smoltcp::socket::tcp::Socket,_,_>(handle, |socket| {
-                                socket.set_keep_alive(expire);
-                            });
-                        }
-                    }
-                    _ =>{}
+                let value = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
+                if let Sock::TCP(tcp) = &socket.sk {
+                    tcp.set_keep_alive(value != 0);
                 }
-                socket.set_recv_buf_size(len_opt as usize);
                 Ok(0)
             }
 
@@ -803,6 +820,36 @@ impl SocketOption {
                 Ok(0)
             }
 
+            SocketOption::REUSEPORT => {
+                if buf_len < 4 {
+                    return Err(SysError::EINVAL)
+                }
+                let value: i32 = if socket.get_reuse_port() {1} else {0};
+                let opt_w = UserSliceRaw::new(opt as *mut u8, 4)
+                    .ensure_write(&mut task.get_vm_space().lock())
+                    .ok_or(SysError::EFAULT)?;
+                opt_w.to_mut().copy_from_slice(&value.to_ne_bytes());
+                opt_len_w.write(4);
+                Ok(0)
+            }
+
+            SocketOption::ERROR => {
+                if buf_len < 4 {
+                    return Err(SysError::EINVAL)
+                }
+                // reading SO_ERROR clears the pending error
+                let value: i32 = match &socket.sk {
+                    Sock::TCP(tcp) => tcp.take_error(),
+                    _ => 0,
+                };
+                let opt_w = UserSliceRaw::new(opt as *mut u8, 4)
+                    .ensure_write(&mut task.get_vm_space().lock())
+                    .ok_or(SysError::EFAULT)?;
+                opt_w.to_mut().copy_from_slice(&value.to_ne_bytes());
+                opt_len_w.write(4);
+                Ok(0)
+            }
+
             SocketOption::KEEPALIVE => {
                 if buf_len < 4 {
                     return Err(SysError::EINVAL)
@@ -810,18 +857,12 @@ impl SocketOption {
                 let opt_w = UserSliceRaw::new(opt as *mut u8, 4)
                                     .ensure_write(&mut task.get_vm_space().lock())
                                     .ok_or(SysError::EFAULT)?;
-                match &socket.sk {
-                    Sock::TCP(tcp) => {
-                        if let Some(handle) = tcp.handle(){
-                            SOCKET_SET.with_socket::<smoltcp::socket::tcp::Socket,_,_>(handle, |socket| {
-                                let value: i32 = if socket.keep_alive().is_none() {1} else {0};
-                                opt_w.to_mut().copy_from_slice(&value.to_ne_bytes());
-                                opt_len_w.write(4);
-                            });
-                        }
-                    }
-                    _ => {}
-                }
+                let value: i32 = match &socket.sk {
+                    Sock::TCP(tcp) if tcp.keep_alive() => 1,
+                    _ => 0,
+                };
+                opt_w.to_mut().copy_from_slice(&value.to_ne_bytes());
+                opt_len_w.write(4);
                 Ok(0)
             }
 
@@ -878,34 +919,26 @@ impl TryFrom<usize> for TcpSocketOption {
 
 impl TcpSocketOption {
     pub fn set(&self, raw_socket: &crate::net::socket::Socket, opt: &[u8]) -> SockResult<isize> {
-        match &raw_socket.sk {
-            Sock::TCP(tcp) => {
-                if let Some(handle) = tcp.handle(){
-                    let res: Result<isize, SysError> = SOCKET_SET.with_socket_mut::<smoltcp::socket::tcp::Socket,_,_>(handle, |socket| {
-                        match self {
-                            TcpSocketOption::NODELAY => {
-                                if opt.len() < 4 {
-                                    return Err(SysError::EINVAL);
-                                }
-                                let opt_value = u32::from_be_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
-                                socket.set_nagle_enabled(opt_value == 0);
-                                return Ok(0);
-                            }
-                            TcpSocketOption::MAXSEG => todo!(),
-                            TcpSocketOption::INFO => {return Ok(0);},
-                            TcpSocketOption::CONGESTION => {
-                                raw_socket.set_congestion(String::from_utf8(Vec::from(opt)).unwrap());
-                                return Ok(0);
-                            },
-                        }
-                    });
-                    return res;
-                }else {
-                    Ok(0)
+        let Sock::TCP(tcp) = &raw_socket.sk else {
+            return Ok(0);
+        };
+        match self {
+            TcpSocketOption::NODELAY => {
+                if opt.len() < 4 {
+                    return Err(SysError::EINVAL);
                 }
-                
+                let value = i32::from_ne_bytes(<[u8; 4]>::try_from(&opt[0..4]).unwrap());
+                tcp.set_nodelay(value != 0);
+                Ok(0)
+            }
+            // the segment size is decided by smoltcp
+            TcpSocketOption::MAXSEG => Ok(0),
+            TcpSocketOption::INFO => Ok(0),
+            TcpSocketOption::CONGESTION => {
+                let name = String::from_utf8(Vec::from(opt)).map_err(|_| SysError::EINVAL)?;
+                raw_socket.set_congestion(name);
+                Ok(0)
             }
-            _ => {Ok(0)},
         }
     }
 
@@ -915,55 +948,46 @@ impl TcpSocketOption {
         let buf_len = *opt_len_r.to_ref();
         let opt_len_w = opt_len.ensure_write(&mut task.get_vm_space().lock())
             .ok_or(SysError::EFAULT)?;
-        match &rawsocket.sk {
-            Sock::TCP(tcp) => {
-                if let Some(handle) = tcp.handle(){
-                    let res: Result<isize, SysError> = SOCKET_SET.with_socket_mut::<smoltcp::socket::tcp::Socket,_,_>(handle, |socket| {
-                        match self {
-                            TcpSocketOption::NODELAY => {
-                                if buf_len < 4 {
-                                    return Err(SysError::EINVAL);
-                                }
-                                let value: i32 = if socket.nagle_enabled() {0} else {1};
-                                let value = value.to_ne_bytes();
-                                let opt_addr_w = UserSliceRaw::new(opt_addr as *mut u8, 4)
-                                    .ensure_write(&mut task.get_vm_space().lock())
-                                    .ok_or(SysError::EFAULT)?;
-                                opt_addr_w.to_mut().copy_from_slice(&value);
-                                opt_len_w.write(4);
-                                Ok(0)
-                            }
-                            TcpSocketOption::MAXSEG => {
-                                let len = size_of::<usize>();
-                                let value: usize = 1500;
-                                let value = value.to_ne_bytes();
-                                let opt_addr_w = UserSliceRaw::new(opt_addr as *mut u8, len)
-                                    .ensure_write(&mut task.get_vm_space().lock())
-                                    .ok_or(SysError::EFAULT)?;
-                                opt_addr_w.to_mut().copy_from_slice(&value);
-                                opt_len_w.write(len as u32);
-                                Ok(0)
-                            },
-                            TcpSocketOption::INFO => {Ok(0)},
-                            TcpSocketOption::CONGESTION => {
-                                let bytes = rawsocket.get_congestion();
-                                let bytes = bytes.as_bytes();
-                                let len = bytes.len();
-                                let opt_addr_w = UserSliceRaw::new(opt_addr as *mut u8, len)
-                                    .ensure_write(&mut task.get_vm_space().lock())
-                                    .ok_or(SysError::EFAULT)?;
-                                opt_addr_w.to_mut().copy_from_slice(bytes);
-                                opt_len_w.write(len as u32);
-                                Ok(0)
-                            },
-                        }
-                    });
-                    res
-                }else {
-                    Ok(0)
+        let Sock::TCP(tcp) = &rawsocket.sk else {
+            return Ok(0);
+        };
+        match self {
+            TcpSocketOption::NODELAY => {
+                if buf_len < 4 {
+                    return Err(SysError::EINVAL);
                 }
+                let value: i32 = if tcp.nodelay() {1} else {0};
+                let value = value.to_ne_bytes();
+                let opt_addr_w = UserSliceRaw::new(opt_addr as *mut u8, 4)
+                    .ensure_write(&mut task.get_vm_space().lock())
+                    .ok_or(SysError::EFAULT)?;
+                opt_addr_w.to_mut().copy_from_slice(&value);
+                opt_len_w.write(4);
+                Ok(0)
             }
-            _ => Ok(0)
+            TcpSocketOption::MAXSEG => {
+                let len = size_of::<usize>();
+                let value: usize = 1500;
+                let value = value.to_ne_bytes();
+                let opt_addr_w = UserSliceRaw::new(opt_addr as *mut u8, len)
+                    .ensure_write(&mut task.get_vm_space().lock())
+                    .ok_or(SysError::EFAULT)?;
+                opt_addr_w.to_mut().copy_from_slice(&value);
+                opt_len_w.write(len as u32);
+                Ok(0)
+            },
+            TcpSocketOption::INFO => {Ok(0)},
+            TcpSocketOption::CONGESTION => {
+                let bytes = rawsocket.get_congestion();
+                let bytes = bytes.as_bytes();
+                let len = bytes.len();
+                let opt_addr_w = UserSliceRaw::new(opt_addr as *mut u8, len)
+                    .ensure_write(&mut task.get_vm_space().lock())
+                    .ok_or(SysError::EFAULT)?;
+                opt_addr_w.to_mut().copy_from_slice(bytes);
+                opt_len_w.write(len as u32);
+                Ok(0)
+            },
         }
     }
 }
@@ -1223,7 +1247,7 @@ pub fn sys_getsockopt (
         return Err(SysError::EFAULT);
     }
     let Ok(level) = SocketLevel::try_from(level) else {
-        return Err(SysError::EOPNOTSUPP);
+        return Err(SysError::ENOPROTOOPT);
     };
     let task = current_task().unwrap();
     let file = task.with_fd_table(|table| {
@@ -1376,11 +1400,11 @@ pub struct MsgHdr {
     /// iovecs ptr
     pub msg_iov: usize,
     /// iovecs len
-    pub msg_iovlen: u32,
+    pub msg_iovlen: usize,
     /// ancillary data ptr
     pub msg_control: usize,
     /// ancillary data len
-    pub msg_controllen: u32,
+    pub msg_controllen: usize,
     /// flags
     pub msg_flags: i32,
 }
@@ -1389,12 +1413,48 @@ pub struct MsgHdr {
 #[derive(Clone, Copy)]
 /// accillary data object information for recvmsg() and sendmsg() system calls
 pub struct CmsgHdr {
+    /// data len, including this header
+    pub cmsg_len: usize,
     /// level
-    pub cmsg_level: u32,
+    pub cmsg_level: i32,
     /// type
-    pub cmsg_type: u32,
-    /// data len
-    pub cmsg_len: u32,
+    pub cmsg_type: i32,
+}
+
+/// CMSG_ALIGN: control messages are aligned to the size of a pointer
+const fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+/// walk the control messages in the ancillary data of a msghdr,
+/// like CMSG_FIRSTHDR / CMSG_NXTHDR, yielding each header with its data
+pub struct CmsgIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> CmsgIter<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for CmsgIter<'a> {
+    type Item = SockResult<(CmsgHdr, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        const HDR_LEN: usize = size_of::<CmsgHdr>();
+        if self.buf.len() < HDR_LEN {
+            return None;
+        }
+        let hdr = unsafe { (self.buf.as_ptr() as *const CmsgHdr).read_unaligned() };
+        if hdr.cmsg_len < HDR_LEN || hdr.cmsg_len > self.buf.len() {
+            self.buf = &[];
+            return Some(Err(SysError::EINVAL));
+        }
+        let data = &self.buf[cmsg_align(HDR_LEN)..hdr.cmsg_len];
+        self.buf = &self.buf[cmsg_align(hdr.cmsg_len).min(self.buf.len())..];
+        Some(Ok((hdr, data)))
+    }
 }
 
 /// send a message through a connection-mode or connectionless-mode socket. 
@@ -1414,15 +1474,10 @@ pub async fn sys_sendmsg(
         table.get_file(fd)})?
         .downcast_arc::<socket::Socket>()
         .map_err(|_| SysError::ENOTSOCK)?;
-    // let msg_ptr = msg as *const MsgHdr;
-    // let msg = unsafe { msg_ptr.read() };
     let msg = *UserPtrRaw::new(msg as *const MsgHdr)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?
         .to_ref();
-    // if msg.msg_controllen != 0 {
-    //     log::warn!("unsupported control data");
-    // }
     let mut peer_addr = None;
     if socket_file.domain != SaFamily::Alg && socket_file.domain != SaFamily::AfUnix {
         if msg.msg_namelen > 0 {
@@ -1431,41 +1486,44 @@ pub async fn sys_sendmsg(
         }
     }
 
-    let iovs_slice = UserSliceRaw::new(msg.msg_iov as *const IoVec, msg.msg_iovlen as usize)
+    let iovs_slice = UserSliceRaw::new(msg.msg_iov as *const IoVec, msg.msg_iovlen)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
     let iovs = iovs_slice.to_ref();
 
-    // todo: use control data in af_alg case
-    let control_slice = UserSliceRaw::new(msg.msg_control as *const u8, msg.msg_controllen as usize)
+    let control_slice = UserSliceRaw::new(msg.msg_control as *const u8, msg.msg_controllen)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
 
-    let mut kernel_iovec_buf = Vec::new();
-    let total_len: usize = iovs.iter().map(|iov| iov.len as usize ).sum();
-    if total_len > 0{
-        kernel_iovec_buf.resize(total_len, 0);
-        let mut offset = 0;
-        for iov in iovs.iter() {
-            let len = iov.len as usize;
-            if len > 0 {
-                let src = UserSliceRaw::new(iov.base as *const u8, iov.len)
-                    .ensure_read(&mut task.get_vm_space().lock())
-                    .ok_or(SysError::EFAULT)?;
-                kernel_iovec_buf[offset..offset+len].copy_from_slice(src.to_ref());
-                offset += len;
-            }
-        }
-    }
     if socket_file.domain == SaFamily::Alg {
         // todo: encode the given msg then return encode len in recv
         return encode(&socket_file, iovs, control_slice.to_ref())
     }
 
-    match socket_file.sk.send(kernel_iovec_buf.as_slice(), peer_addr).await {
-        Ok(len) => Ok(len as isize),
-        Err(e) => Err(e),
+    for cmsg in CmsgIter::new(control_slice.to_ref()) {
+        let (hdr, _data) = cmsg?;
+        match (hdr.cmsg_level, hdr.cmsg_type) {
+            // SCM_RIGHTS / SCM_CREDENTIALS of unix sockets are handled here
+            (level, ty) => {
+                log::warn!("[sys_sendmsg] unsupported control message, level {}, type {}", level, ty);
+            }
+        }
+    }
+
+    // gather the iovecs into one message
+    let total_len: usize = iovs.iter().map(|iov| iov.len).sum();
+    let mut kernel_iovec_buf = vec![0u8; total_len];
+    let mut offset = 0;
+    for iov in iovs.iter().filter(|iov| iov.len > 0) {
+        let src = UserSliceRaw::new(iov.base as *const u8, iov.len)
+            .ensure_read(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        kernel_iovec_buf[offset..offset + iov.len].copy_from_slice(src.to_ref());
+        offset += iov.len;
     }
+
+    let len = socket_file.sk.send(kernel_iovec_buf.as_slice(), peer_addr).await?;
+    Ok(len as isize)
 }
 
 bitflags::bitflags! {
@@ -1521,26 +1579,17 @@ pub async fn sys_recvmsg(
         table.get_file(fd)})?
         .downcast_arc::<socket::Socket>()
         .map_err(|_| SysError::ENOTSOCK)?;
-    let inner_msg = *UserPtrRaw::new(msg as *const MsgHdr)
+    let mut inner_msg = *UserPtrRaw::new(msg as *const MsgHdr)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?
         .to_ref();
-    
-    if inner_msg.msg_controllen != 0 {
-        log::warn!("unsupported control data");
-    }
-    if (inner_msg.msg_namelen as i32) < 0 || (inner_msg.msg_controllen as i32) < 0 {
+    if (inner_msg.msg_namelen as i32) < 0 || (inner_msg.msg_controllen as isize) < 0 {
         return Err(SysError::EINVAL);
     }
-    // let iovs = unsafe {
-    //     Instruction::set_sum();
-    //     core::slice::from_raw_parts(inner_msg.msg_iov as *const IoVec, inner_msg.msg_iovlen as usize)
-    // };
-    let iovec_count = inner_msg.msg_iovlen as i32;
-    if iovec_count < 0 {
+    if (inner_msg.msg_iovlen as isize) < 0 {
         return Err(SysError::EMSGSIZE);
     }
-    let iovs_slice = UserSliceRaw::new(inner_msg.msg_iov as *const IoVec, inner_msg.msg_iovlen as usize)
+    let iovs_slice = UserSliceRaw::new(inner_msg.msg_iov as *const IoVec, inner_msg.msg_iovlen)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
     let iovs = iovs_slice.to_ref();
@@ -1548,56 +1597,53 @@ pub async fn sys_recvmsg(
     for iov in iovs {
         total_len = total_len.saturating_add(iov.len);
     }
-    if total_len == 0 {
-        return Ok(0);
-    }
     let mut tmp_buf = vec![0u8; total_len];
-    let (recv_len,src_addr) = socket_file.sk.recv(&mut tmp_buf).await?;
-    if recv_len == 0 {
-        return Ok(0);
-    }
+    let (recv_len, src_addr) = if total_len == 0 {
+        (0, ZERO_IPV4_ENDPOINT)
+    } else {
+        socket_file.sk.recv(&mut tmp_buf).await?
+    };
 
+    // scatter the message across the iovecs
     let mut copied = 0;
-    let data = tmp_buf[..recv_len].to_vec();
     for iov in iovs {
         if copied >= recv_len {
             break;
         }
-        let to_copy = (iov.len as usize).min(recv_len - copied);
-        let dst = iov.base as *mut u8;
-        unsafe  {
-            core::ptr::copy_nonoverlapping(data[copied..].as_ptr(), dst, to_copy)
-        };
+        let to_copy = iov.len.min(recv_len - copied);
+        let dst = UserSliceRaw::new(iov.base as *mut u8, to_copy)
+            .ensure_write(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        dst.to_mut().copy_from_slice(&tmp_buf[copied..copied + to_copy]);
         copied += to_copy;
     }
 
-    if inner_msg.msg_name != 0 {
+    // the peer address is only known for inet sockets
+    let is_inet = matches!(socket_file.domain, SaFamily::AfInet | SaFamily::AfInet6);
+    if inner_msg.msg_name != 0 && is_inet {
         let addr = SockAddr::from_endpoint(src_addr);
-        // unsafe {
-        //     match SaFamily::try_from(addr.family)? {
-        //         SaFamily::AfInet => {
-        //             let addr_ptr = inner_msg.msg_name as *mut SockAddrIn4;
-        //             addr_ptr.write_volatile(addr.ipv4);
-        //             let addr_len_ptr = inner_msg.msg_namelen as *mut u32;
-        //             addr_len_ptr.write_volatile(size_of::<SockAddrIn4>() as u32);
-        //         },
-        //         SaFamily::AfInet6 => {
-        //             let addr_ptr = inner_msg.msg_name as *mut SockAddrIn6;
-        //             addr_ptr.write_volatile(addr.ipv6);
-        //             let addr_len_ptr = inner_msg.msg_namelen as *mut u32;
-        //             addr_len_ptr.write_volatile(size_of::<SockAddrIn6>() as u32);
-        //         },
-        //         SaFamily::AfUnix => {
-        //             let addr_ptr = inner_msg.msg_name as *mut SockAddrUn;
-        //             addr_ptr.write_volatile(addr.unix);
-        //             let addr_len_ptr = inner_msg.msg_namelen as *mut u32;
-        //             addr_len_ptr.write_volatile(size_of::<SockAddrUn>() as u32);
-        //         },
-        //         _ => todo!()
-        //     }
-        // }
-        sockaddr_writer(task, inner_msg.msg_name, inner_msg.msg_namelen as usize, addr)?;
-    }      
+        let addr_len = match src_addr.addr {
+            IpAddress::Ipv4(_) => size_of::<SockAddrIn4>(),
+            IpAddress::Ipv6(_) => size_of::<SockAddrIn6>(),
+        };
+        // a short buffer gets a truncated address, msg_namelen tells the real length
+        let len = addr_len.min(inner_msg.msg_namelen as usize);
+        let name = UserSliceRaw::new(inner_msg.msg_name as *mut u8, len)
+            .ensure_write(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        let bytes = unsafe { core::slice::from_raw_parts(&addr as *const SockAddr as *const u8, len) };
+        name.to_mut().copy_from_slice(bytes);
+        inner_msg.msg_namelen = addr_len as u32;
+    } else {
+        inner_msg.msg_namelen = 0;
+    }
+    // no control messages are delivered yet
+    inner_msg.msg_controllen = 0;
+    inner_msg.msg_flags = 0;
+    UserPtrRaw::new(msg as *mut MsgHdr)
+        .ensure_write(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?
+        .write(inner_msg);
     Ok(copied as isize)
 }
 
@@ -1624,11 +1670,15 @@ pub fn sockaddr_reader(addr: usize, addr_len: usize, task: &Arc<TaskControlBlock
             Ok(addr)
         },
         SaFamily::AfUnix => {
-            if addr_len < size_of::<SockAddrUn>() {
+            if addr_len < size_of::<u16>() || addr_len > size_of::<SockAddrUn>() {
                 log::info!("in this, size of SockAddrUn: {}",size_of::<SockAddrUn>());
                 return Err(SysError::EINVAL);
             }
-           Ok(addr)
+            // the bytes after addr_len are not part of the address,
+            // an abstract name is not NUL terminated
+            let mut unix = unsafe { addr.unix };
+            unix.path[addr_len - size_of::<u16>()..].fill(0);
+            Ok(SockAddr { unix })
         },
         SaFamily::Alg => {
             if addr_len < size_of::<SockAddrAlg>() {
@@ -3,25 +3,26 @@
 use core::ops::{Add, DerefMut};
 use core::ptr::null;
 use core::sync::atomic::Ordering;
-use crate::config::PAGE_SIZE;
 use crate::fs::utils::FileReader;
 use crate::fs::vfs::dentry::global_find_dentry;
 use crate::fs::vfs::DentryState;
 use crate::fs::AtFlags;
+use crate::fs::pidfd::{make_pidfd, PidFdFlags};
 use crate::fs::{
     vfs::file::open_file,
     OpenFlags,
 };
-use crate::mm::UserPtrRaw;
+use crate::mm::{UserPtrRaw, UserSliceRaw};
 use crate::processor::context::SumGuard;
 use crate::syscall::at_helper;
+use crate::task::fs::{FdFlags, FdInfo};
 use crate::task::schedule::spawn_user_task;
-use crate::task::INITPROC;
+use crate::task::{tid_alloc, tid_alloc_specific, INITPROC, INITPROC_PID};
 use crate::task::manager::{TaskManager, PROCESS_GROUP_MANAGER, TASK_MANAGER};
 use crate::processor::processor::{current_processor, current_task, current_trap_cx, current_user_token, PROCESSORS};
-use crate::signal::{SigInfo, SigSet, SIGKILL};
+use crate::signal::{SigInfo, SigSet, SIGCHLD, SIGKILL, SIGRTMAX};
 use crate::timer::get_current_time_duration;
-use crate::utils::{suspend_now, user_path_to_string};
+use crate::utils::{get_waker, suspend_now, user_path_to_string};
 use alloc::string::ToString;
 use alloc::{sync::Arc, vec::Vec, string::String};
 use fatfs::warn;
@@ -34,6 +35,10 @@ use lwext4_rust::bindings::EINVAL;
 use crate::mm::vm::{KernVmSpaceHal, UserVmSpaceHal};
 use log::info;
 
+use crate::config::PAGE_SIZE;
+use crate::syscall::misc::Rusage;
+use crate::task::task::{TaskControlBlock, WaitReport};
+
 use super::{SysResult,SysError};
 
 bitflags! {
@@ -90,13 +95,19 @@ bitflags! {
 
 bitflags! {
     /// Defined in <bits/waitflags.h>.
-    pub struct WaitOptions: i32 {
+    pub struct WaitOptions: u32 {
         /// Don't block waiting.
         const WNOHANG = 0x00000001;
         /// Report status of stopped children.
         const WUNTRACED = 0x00000002;
         /// Report continued child.
         const WCONTINUED = 0x00000008;
+        /// Don't wait on children of other threads in this group
+        const __WNOTHREAD = 0x20000000;
+        /// Wait on all children, regardless of type
+        const __WALL = 0x40000000;
+        /// Wait only on non-SIGCHLD children
+        const __WCLONE = 0x80000000;
     }
 }
 
@@ -251,7 +262,10 @@ pub fn sys_set_tid_address(tid_ptr: usize) -> SysResult {
 /// fork a new process
 pub fn sys_fork() -> isize {
     let current_task = current_task().unwrap();
-    let new_task = current_task.fork(CloneFlags { bits: 0 });
+    let new_task = match current_task.fork(CloneFlags { bits: 0 }) {
+        Ok(task) => task,
+        Err(e) => return -e.code(),
+    };
     //info!("complete sys_fork, new_task = {:}",new_task.pid() );
     let new_pid = new_task.pid();
     // modify trap context of new_task, because it returns immediately after switching
@@ -270,58 +284,47 @@ pub fn sys_fork() -> isize {
 #[cfg(target_arch="riscv64")]
 pub async fn sys_clone(flags: u64, stack: VirtAddr, parent_tid: VirtAddr, tls: VirtAddr, child_tid: VirtAddr) -> SysResult {
     // info!("[sys_clone]: into clone, stack addr: {:#x}, parent tid: {:?}", stack.0, parent_tid);
-    let signo = SigSet::from_bits_truncate(1 << ((flags & 0xff) - 1));
-    let flags = CloneFlags::from_bits(flags & !0xff).ok_or(SysError::EINVAL)?;
-    let task = current_task().unwrap();
-    let new_task = task.fork(flags);
-    new_task.get_trap_cx().set_ret_nth(0, 0);
-    let new_tid = new_task.tid();
-    task.get_trap_cx().set_ret_nth(0, new_tid);
-    // set new stack
-    if stack.0 != 0 {
-        *new_task.get_trap_cx().sp() = stack.0;
-    }
-
-    // set parent tid and child tid
-    if flags.contains(CloneFlags::PARENT_SETTID) {
-        let user_ptr = UserPtrRaw::new(parent_tid.0 as *mut u32)
-            .ensure_write(&mut task.get_vm_space().lock())
-            .ok_or(SysError::EINVAL)?;
-        user_ptr.write(new_tid as u32);
-    }
-    if flags.contains(CloneFlags::CHILD_SETTID) {
-        // If a thread is started using clone(2) with the
-        // CLONE_CHILD_SETTID flag, set_child_tid is set to the value
-        // passed in the ctid argument of that system call.
-        new_task.tid_address().set_child_tid = Some(child_tid.0);
-        // When set_child_tid is set, the very first thing the new
-        // thread does is to write its thread ID at this address.
-        let user_ptr = UserPtrRaw::new(child_tid.0 as *mut u32)
-            .ensure_write(&mut task.get_vm_space().lock())
-            .ok_or(SysError::EINVAL)?;
-        user_ptr.write(new_tid as u32);
-    }
-    if flags.contains(CloneFlags::CHILD_CLEARTID) {
-        new_task.tid_address().clear_child_tid = Some(child_tid.0);
-    }
-    // todo: more flags...
-    if flags.contains(CloneFlags::SETTLS) {
-        *new_task.get_trap_cx().tp() = tls.0;
-    }
-    spawn_user_task(new_task);
-    if flags.contains(CloneFlags::VFORK) {
-        suspend_now().await;
-    }
-    Ok(new_tid as isize)
+    legacy_clone(flags, stack, parent_tid, child_tid, tls).await
 }
 
 /// clone a new process/thread/ using clone flags
 #[cfg(target_arch="loongarch64")]
 pub async fn sys_clone(flags: u64, stack: VirtAddr, parent_tid: VirtAddr, child_tid: VirtAddr, tls: VirtAddr) -> SysResult {
     // info!("[sys_clone]: into clone, stack addr: {:#x}, parent tid: {:?}", stack.0, parent_tid);
+    legacy_clone(flags, stack, parent_tid, child_tid, tls).await
+}
+
+/// clone(2) returns the pidfd where the parent tid would go, so the two exclude each other
+async fn legacy_clone(flags: u64, stack: VirtAddr, parent_tid: VirtAddr, child_tid: VirtAddr, tls: VirtAddr) -> SysResult {
+    let both = CloneFlags::PIDFD.bits() | CloneFlags::PARENT_SETTID.bits();
+    if flags & both == both {
+        return Err(SysError::EINVAL);
+    }
+    do_clone(flags, stack, parent_tid, child_tid, tls, parent_tid, None).await
+}
+
+/// the clone shared by clone and clone3, with CLONE_PIDFD the pidfd
+/// of the child is stored at `pidfd`; `set_tid` asks for the tid the child should get
+async fn do_clone(flags: u64, stack: VirtAddr, parent_tid: VirtAddr, child_tid: VirtAddr, tls: VirtAddr, pidfd: VirtAddr, set_tid: Option<usize>) -> SysResult {
+    let exit_signal = (flags & 0xff) as usize;
     let flags = CloneFlags::from_bits(flags & !0xff).ok_or(SysError::EINVAL)?;
     let task = current_task().unwrap();
-    let new_task = task.fork(flags);
+    // init has no parent to share
+    if flags.contains(CloneFlags::PARENT) && task.pid() == INITPROC_PID {
+        return Err(SysError::EINVAL);
+    }
+    // a pidfd refers to a process, not to a thread
+    if flags.contains(CloneFlags::PIDFD) && flags.contains(CloneFlags::THREAD) {
+        return Err(SysError::EINVAL);
+    }
+    let tid_handle = match set_tid {
+        Some(tid) => tid_alloc_specific(tid).ok_or(SysError::EEXIST)?,
+        None => tid_alloc(),
+    };
+    let new_task = task.fork_with_tid(flags, tid_handle)?;
+    if !flags.contains(CloneFlags::THREAD) {
+        new_task.with_mut_thread_group(|tg| tg.exit_signal = exit_signal);
+    }
     new_task.get_trap_cx().set_ret_nth(0, 0);
     let new_tid = new_task.tid();
     task.get_trap_cx().set_ret_nth(0, new_tid);
@@ -334,7 +337,7 @@ pub async fn sys_clone(flags: u64, stack: VirtAddr, parent_tid: VirtAddr, child_
     if flags.contains(CloneFlags::PARENT_SETTID) {
         let user_ptr = UserPtrRaw::new(parent_tid.0 as *mut u32)
             .ensure_write(&mut task.get_vm_space().lock())
-            .ok_or(SysError::EINVAL)?;
+            .ok_or(SysError::EFAULT)?;
         user_ptr.write(new_tid as u32);
     }
     if flags.contains(CloneFlags::CHILD_SETTID) {
@@ -352,6 +355,15 @@ pub async fn sys_clone(flags: u64, stack: VirtAddr, parent_tid: VirtAddr, child_
     if flags.contains(CloneFlags::CHILD_CLEARTID) {
         new_task.tid_address().clear_child_tid = Some(child_tid.0);
     }
+    if flags.contains(CloneFlags::PIDFD) {
+        let file = make_pidfd(&new_task, PidFdFlags::empty());
+        let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+        task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: FdFlags::CLOEXEC }))?;
+        let user_ptr = UserPtrRaw::new(pidfd.0 as *mut i32)
+            .ensure_write(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        user_ptr.write(fd as i32);
+    }
     // todo: more flags...
     if flags.contains(CloneFlags::SETTLS) {
         *new_task.get_trap_cx().tp() = tls.0;
@@ -458,9 +470,10 @@ pub async fn sys_execve(pathname: usize, argv: usize, envp: usize) -> SysResult
 }
 
 
-/// The waitpid() system call suspends execution of the calling thread
-/// until a child specified by pid argument has changed state.  By
-/// default, waitpid() waits only for terminated children, but this
+/// The wait4() system call suspends execution of the calling thread
+/// until a child specified by pid argument has changed state, like waitpid(),
+/// and returns the resource usage of the child in `rusage`.  By
+/// default, it waits only for terminated children, but this
 /// behavior is modifiable via the options argument, as described
 /// below.
 /// pid < -1 meaning wait for any child process whose process group ID
@@ -469,149 +482,136 @@ pub async fn sys_execve(pathname: usize, argv: usize, envp: usize) -> SysResult
 /// pid = 0 meaning wait for any child process whose process group ID
 /// is equal to that of the calling process at the time of the call to waitpid().
 /// pid > 0 meaning wait for the child whose process ID is equal to the value of pid.
-pub async fn sys_waitpid(pid: isize, exit_code_ptr: usize, option: i32) -> SysResult {
-    if option < 0 {
-        return Err(SysError::EINVAL);
-    }
+pub async fn sys_wait4(pid: isize, wstatus: usize, options: i32, rusage: usize) -> SysResult {
+    let options = WaitOptions::from_bits(options as u32).ok_or(SysError::EINVAL)?;
     let task = current_task().unwrap().clone();
-    // println!("[sys_waitpid]: TCB: {}, pid: {}, exitcode_ptr: {:x}, option: {}", task.tid(), pid, exit_code_ptr, option);
-    let option = WaitOptions::from_bits_truncate(option);
-    // todo: now only support for pid == -1 and pid > 0
-    // get the all target zombie process
-    let res_task = {
-        let children = task.children();
-        if children.is_empty() {
-            return Err(SysError::ECHILD);
-        }
-        match pid {
-            -1 => {
-                children
-                    .values()
-                    .find(|c|c.is_zombie() && c.thread_group.lock().get_alive() == 0)
-            }
-            pid if pid > 0 => {
-                if let Some(child) = children.get(&(pid as usize)) {
-                    if child.is_zombie() && child.thread_group.lock().get_alive() == 0 {
-                        Some(child)
-                    } else {
-                        None
-                    }
-                } else {
-                    log::warn!("[sys_waitpid]: no child with pid {}", pid);
-                    return Err(SysError::ECHILD);
-                }
+    let mut waker = None;
+    loop {
+        if let Some((child, status)) = wait_child(&task, pid, options)? {
+            if let Some(waker) = waker {
+                task.with_mut_thread_group(|tg| tg.child_wakers.retain(|w| !w.will_wake(&waker)));
             }
-            _ => {
-                log::warn!("[sys_waitpid]: not implement");
-                return Err(SysError::EINVAL);
+            return report_child(&task, &child, status, wstatus, rusage);
+        }
+        if options.contains(WaitOptions::WNOHANG) {
+            return Ok(0);
+        }
+        let Some(registered) = waker.take() else {
+            // look once more with the waker in place, a child changing in between is not missed
+            let new_waker = get_waker().await;
+            task.with_mut_thread_group(|tg| tg.child_wakers.push_back(new_waker.clone()));
+            waker = Some(new_waker);
+            continue;
+        };
+        log::debug!("[sys_wait4]: TCB {} waiting for a child", task.gettid());
+        task.set_interruptable();
+        let block_sig = task.with_sig_manager(|sig_manager| sig_manager.blocked_sigs);
+        task.set_wake_up_sigs(!block_sig);
+        suspend_now().await;
+        task.set_running();
+        task.with_mut_thread_group(|tg| tg.child_wakers.retain(|w| !w.will_wake(&registered)));
+        // a SIGCHLD nobody handles only wakes us up
+        let interrupted = task.with_mut_sig_manager(|sig_manager| {
+            let mut intr_sigs = !block_sig;
+            if !sig_manager.sig_handler[SIGCHLD].is_user {
+                sig_manager.dequeue_expected_one(SigSet::SIGCHLD);
+                intr_sigs.remove(SigSet::SIGCHLD);
             }
-        }.cloned()
-    };
-
-    if let Some(res_task) = res_task {
-        res_task.time_recorder().update_child_time(res_task.time_recorder().time_pair());
-
-        if exit_code_ptr != 0 {
-            let mut vm = task.get_vm_space().lock();
-            let exit_code_ptr = UserPtrRaw::new(exit_code_ptr as *mut i32)
-                .ensure_write(vm.deref_mut())
-                .ok_or(SysError::EFAULT)?;
-            let exit_code_mut = exit_code_ptr.to_mut();
-            let exit_code = res_task.exit_code();
-            *exit_code_mut = exit_code as i32;
+            sig_manager.check_pending_flag(intr_sigs)
+        });
+        if interrupted {
+            log::warn!("[sys_wait4] task {} wake up by unexpected signal", task.tid());
+            return Err(SysError::EINTR);
         }
+    }
+}
 
-        let mut res_task_tg = res_task.thread_group.lock();
-        for thread in res_task_tg.iter() {
-            TASK_MANAGER.remove_task(thread.tid());
-        }
-        res_task_tg.clear();
-        
-        let tid = res_task.tid();
-        task.remove_child(tid);
-        PROCESS_GROUP_MANAGER.remove(&task);
-        return Ok(tid as isize);
-    } else if option.contains(WaitOptions::WNOHANG) {
-        return Ok(0);
-    } else {
-        log::debug!("[sys_waitpid]: TCB {} waiting for SIGCHLD", task.gettid());
-        let res_task = loop {
-            task.set_interruptable();
-            let block_sig = task.with_sig_manager(|sig_manager|{
-                sig_manager.blocked_sigs
-            });
-            task.set_wake_up_sigs(!block_sig | SigSet::SIGCHLD);
-            
-            suspend_now().await;
-            task.set_running();
-            
-            // todo: missing check if getting the expect signal
-            // now check the child one more time
-            let si = task.with_mut_sig_manager(|sig_manager|{
-                // log::warn!("replace check to dequeue");
-                // sig_manager.check_pending(SigSet::SIGCHLD)
-                sig_manager.dequeue_expected_one(SigSet::SIGCHLD)
-            });
-            if let Some(si) = si {
-                log::debug!("[sys_waitpid] task {} get signal: {}", task.gettid(), si.si_signo);
-                let children = task.children();
-                let child = match pid {
-                    -1 => {
-                        children
-                            .values()
-                            .find(|c|c.is_zombie() && c.thread_group.lock().get_alive() == 0)
-                    }
-                    pid if pid > 0 => {
-                        if let Some(child) = children.get(&(pid as usize)) {
-                            if child.is_zombie() && child.thread_group.lock().get_alive() == 0 {
-                                Some(child)
-                            } else {
-                                None
-                            }
-                        } else {
-                            log::warn!("[sys_waitpid]: no child with pid {}", pid);
-                            return Err(SysError::ECHILD);
-                        }
-                    }
-                    _ => {
-                        log::warn!("[sys_waitpid]: not implement");
-                        return Err(SysError::EINVAL);
-                    }
-                };
-                if let Some(child) = child {
-                    break child.clone();
+/// write out the status and resource usage of a waited for child,
+/// a terminated child is reaped
+fn report_child(task: &Arc<TaskControlBlock>, child: &Arc<TaskControlBlock>, status: i32, wstatus: usize, rusage: usize) -> SysResult {
+    if wstatus != 0 {
+        let status_ptr = UserPtrRaw::new(wstatus as *mut i32)
+            .ensure_write(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        status_ptr.write(status);
+    }
+    if rusage != 0 {
+        let ((utime, stime), (nvcsw, nivcsw), maxrss) = child.usage_with_children();
+        let mut usage = Rusage::default();
+        usage.ru_utime = utime.into();
+        usage.ru_stime = stime.into();
+        // in kilobytes
+        usage.ru_maxrss = maxrss * PAGE_SIZE / 1024;
+        usage.ru_nvcsw = nvcsw;
+        usage.ru_nivcsw = nivcsw;
+        let usage_ptr = UserPtrRaw::new(rusage as *mut Rusage)
+            .ensure_write(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        usage_ptr.write(usage);
+    }
+    // stopped and continued children stay
+    if status & 0x7f != 0x7f && status != 0xffff {
+        reap_child(task, child);
+    }
+    Ok(child.pid() as isize)
+}
+
+/// look for a child selected by `pid` and `options` with something to report,
+/// and the wait status for it; ECHILD if no child is selected at all
+fn wait_child(task: &Arc<TaskControlBlock>, pid: isize, options: WaitOptions) -> Result<Option<(Arc<TaskControlBlock>, i32)>, SysError> {
+    let children = task.children();
+    let mut selected = children.values().filter(|child| {
+        let by_pid = match pid {
+            -1 => true,
+            0 => child.pgid() == task.pgid(),
+            pid if pid > 0 => child.pid() == pid as usize,
+            pid => child.pgid() == (-pid) as usize,
+        };
+        // a clone child tells its parent about its exit with another signal than SIGCHLD
+        let is_clone = child.with_thread_group(|tg| tg.exit_signal != SIGCHLD);
+        by_pid && (options.contains(WaitOptions::__WALL) || options.contains(WaitOptions::__WCLONE) == is_clone)
+    }).peekable();
+    if selected.peek().is_none() {
+        log::warn!("[sys_wait4]: no child selected by pid {}", pid);
+        return Err(SysError::ECHILD);
+    }
+    for child in selected {
+        let status = child.with_mut_thread_group(|tg| {
+            if child.is_zombie() && tg.get_alive() == 0 {
+                return Some(tg.group_exit_code as i32);
+            }
+            match tg.wait_report {
+                Some(WaitReport::Stopped(signo)) if options.contains(WaitOptions::WUNTRACED) => {
+                    tg.wait_report = None;
+                    Some(((signo as i32) << 8) | 0x7f)
                 }
-            }else {
-                log::warn!("[sys_waitpid] task {} wake up by unexpected signal", task.tid());
-                return Err(SysError::EINTR);
+                Some(WaitReport::Continued) if options.contains(WaitOptions::WCONTINUED) => {
+                    tg.wait_report = None;
+                    Some(0xffff)
+                }
+                _ => None,
             }
-        };
-
-        res_task.time_recorder().update_child_time(res_task.time_recorder().time_pair());
-        
-        if exit_code_ptr != 0 {
-            let mut vm: crate::sync::mutex::spin_mutex::MutexGuard<'_, crate::mm::vm::UserVmSpace, crate::sync::mutex::SpinNoIrq> = task.get_vm_space().lock();
-            let exit_code_ptr = UserPtrRaw::new(exit_code_ptr as *mut i32)
-                .ensure_write(vm.deref_mut())
-                .ok_or(SysError::EINVAL)?;
-            let exit_code_mut = exit_code_ptr.to_mut();
-            let exit_code = res_task.exit_code();
-            *exit_code_mut = exit_code as i32;
+        });
+        if let Some(status) = status {
+            return Ok(Some((child.clone(), status)));
         }
+    }
+    Ok(None)
+}
 
-        let mut res_task_tg = res_task.thread_group.lock();
-        for thread in res_task_tg.iter() {
-            TASK_MANAGER.remove_task(thread.tid());
-        }
-        res_task_tg.clear();
-        
-        let tid = res_task.tid();
-        task.remove_child(tid);
-        PROCESS_GROUP_MANAGER.remove(&task);
-        return Ok(tid as isize);
+/// release a zombie child that has been waited for
+fn reap_child(task: &Arc<TaskControlBlock>, child: &Arc<TaskControlBlock>) {
+    task.account_reaped_child(child);
+    let mut child_tg = child.thread_group.lock();
+    for thread in child_tg.iter() {
+        TASK_MANAGER.remove_task(thread.tid());
     }
+    child_tg.clear();
+    drop(child_tg);
+    task.remove_child(child.tid());
+    PROCESS_GROUP_MANAGER.remove(child);
 }
-/// yield immediatly to another process
+/// give the processor to the other tasks waiting on it, the caller runs again after them
 pub async fn sys_yield() -> SysResult {
     crate::utils::async_utils::yield_now().await;
     Ok(0)
@@ -624,6 +624,27 @@ pub fn sys_brk(addr: VirtAddr) -> SysResult {
     Ok(ret)
 }
 
+/// pidfd_open() creates a file descriptor that refers to the process
+/// whose PID is specified in pid.  The file descriptor is created with
+/// the close-on-exec flag set, it becomes readable once the process exits.
+pub fn sys_pidfd_open(pid: isize, flags: u32) -> SysResult {
+    let flags = PidFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    if pid <= 0 {
+        return Err(SysError::EINVAL);
+    }
+    let target = TASK_MANAGER.get_task(pid as usize).ok_or(SysError::ESRCH)?;
+    // only a thread group leader stands for a process
+    if !target.is_leader() {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap().clone();
+    let file = make_pidfd(&target, flags);
+    let fd = task.with_mut_fd_table(|t| t.alloc_fd())?;
+    task.with_mut_fd_table(|t| t.put_file(fd, FdInfo { file, flags: FdFlags::CLOEXEC }))?;
+    log::info!("[sys_pidfd_open] pid {pid}, fd {fd}, flags {:?}", flags);
+    Ok(fd as isize)
+}
+
 /// syscall: get_ppid
 pub fn sys_getppid() -> SysResult {
     let task = current_task().unwrap().clone();
@@ -652,20 +673,22 @@ pub fn sys_getpgid(pid: usize) -> SysResult {
 }
 /// set the process group id of the specified process
 pub fn sys_setpgid(pid: usize, pgid: usize) -> SysResult {
+    if (pgid as isize) < 0 {
+        return Err(SysError::EINVAL);
+    }
     let task =  if pid == 0{
         current_task().unwrap().clone()
     }else {
-        TASK_MANAGER.get_task(pid).unwrap()
+        TASK_MANAGER.get_task(pid).ok_or(SysError::ESRCH)?
     };
 
-    if pgid == 0 {
+    if pgid == 0 || pgid == task.tid() {
         PROCESS_GROUP_MANAGER.add_group(&task);
+    }else if PROCESS_GROUP_MANAGER.get_group(pgid).is_some() {
+        PROCESS_GROUP_MANAGER.add_task_to_group(pgid, &task);
     }else {
-        if PROCESS_GROUP_MANAGER.get_group(pgid).is_some() {
-            PROCESS_GROUP_MANAGER.add_task_to_group(pgid, &task);
-        }else {
-            PROCESS_GROUP_MANAGER.add_group(&task);
-        }
+        // the group to join has to exist
+        return Err(SysError::EPERM);
     }
     Ok(0)
 }
@@ -707,6 +730,42 @@ pub fn sys_getegid() -> SysResult {
     Ok(task.egid() as isize)
 }
 
+/// prctl option: get unaligned access control bits
+pub const PR_GET_UNALIGN: i32 = 5;
+/// prctl option: set unaligned access control bits
+pub const PR_SET_UNALIGN: i32 = 6;
+/// silently fix up misaligned accesses
+pub const PR_UNALIGN_NOPRINT: u32 = 1;
+/// generate SIGBUS on misaligned accesses, the default
+pub const PR_UNALIGN_SIGBUS: u32 = 2;
+
+/// syscall: prctl
+/// only PR_SET_UNALIGN and PR_GET_UNALIGN are supported, other options are ignored
+pub fn sys_prctl(option: i32, arg2: usize) -> SysResult {
+    let task = current_task().unwrap().clone();
+    match option {
+        PR_SET_UNALIGN => {
+            let ctl = arg2 as u32;
+            if ctl & !(PR_UNALIGN_NOPRINT | PR_UNALIGN_SIGBUS) != 0 {
+                return Err(SysError::EINVAL);
+            }
+            task.set_unalign_ctl(ctl);
+            Ok(0)
+        }
+        PR_GET_UNALIGN => {
+            let ptr = UserPtrRaw::new(arg2 as *mut u32)
+                .ensure_write(&mut task.get_vm_space().lock())
+                .ok_or(SysError::EFAULT)?;
+            ptr.write(task.unalign_ctl());
+            Ok(0)
+        }
+        _ => {
+            log::warn!("[sys_prctl] option {} ignore", option);
+            Ok(0)
+        }
+    }
+}
+
 ///
 pub fn sys_setsid() -> SysResult {
     let task = current_task().unwrap();
@@ -715,43 +774,86 @@ pub fn sys_setsid() -> SysResult {
 ///  long syscall(SYS_clone3, struct clone_args *cl_args, size_t size);
 ///  glibc provides no wrapper for clone3(), necessitating the
 /// use of syscall(2).
+/// `size` tells which version of `struct clone_args` the caller passes.
 pub async fn sys_clone3(cl_args_ptr: usize, size: usize) -> SysResult {
     let task = current_task().unwrap();
     // log::info!("[sys_clone3]: cl_args_ptr: {:x}, size: {}" , cl_args_ptr, size);
-
-    if size > PAGE_SIZE {
-        return Err(SysError::E2BIG);
+    if !matches!(size, CLONE_ARGS_SIZE_VER0 | CLONE_ARGS_SIZE_VER1 | CLONE_ARGS_SIZE_VER2) {
+        return Err(SysError::EINVAL);
     }
-    if size < CLONE_ARGS_SIZE_VER0 {
+    let cl_args = {
+        let mut vm = task.get_vm_space().lock();
+        let raw = UserSliceRaw::new(cl_args_ptr as *const u8, size)
+            .ensure_read(&mut vm)
+            .ok_or(SysError::EFAULT)?;
+        let raw = raw.to_ref();
+        // fields newer than the caller's version read as zero
+        let field = |i: usize| raw.get(i * 8..i * 8 + 8)
+            .map_or(0, |b| u64::from_ne_bytes(b.try_into().unwrap()));
+        CloneArgs {
+            flags: field(0),
+            pidfd: field(1),
+            child_tid: field(2),
+            parent_tid: field(3),
+            exit_signal: field(4),
+            stack: field(5),
+            stack_size: field(6),
+            tls: field(7),
+            set_tid: field(8),
+            set_tid_size: field(9),
+            cgroup: field(10),
+        }
+    };
+    // log::info!("[sys_clone3]: {:?}", cl_args);
+    // the exit signal has its own field, CSIGNAL and CLONE_DETACHED are not valid here
+    if cl_args.flags & 0xff != 0 || cl_args.flags & CloneFlags::DETACHED.bits() != 0 {
+        return Err(SysError::EINVAL);
+    }
+    if cl_args.exit_signal > SIGRTMAX as u64 {
+        return Err(SysError::EINVAL);
+    }
+    // a stack is given by its lowest address and its size, both or neither
+    if (cl_args.stack == 0) != (cl_args.stack_size == 0) {
         return Err(SysError::EINVAL);
     }
-    // let cl_args = unsafe {
-    //     Instruction::set_sum();
-    //     *(cl_args_ptr as *const CloneArgs)
-    // };
-    let cl_args = *UserPtrRaw::new(cl_args_ptr as *const CloneArgs)
-    .ensure_read(&mut task.get_vm_space().lock())
-    .ok_or(SysError::EFAULT)?
-    .to_ref();
-    let flags = cl_args.flags;
-    // log::info!("[sys_clone3]: flags: {:x}", flags);
-    let stack = VirtAddr::from(cl_args.stack);
-    // log::info!("[sys_clone3]: stack: {:x}", stack.0);
-    let parent_tid = VirtAddr::from(cl_args.parent_tid);
-    // log::info!("[sys_clone3]: parent_tid: {:x}", parent_tid.0);
-    let tls = VirtAddr::from(cl_args.tls);
-    // log::info!("[sys_clone3]: tls: {:x}", tls.0);
-    let child_tid = VirtAddr::from(cl_args.child_tid);
-    // log::info!("[sys_clone3]: child_tid: {:x}", child_tid.0);
-    // log::info!("[sys_clone3]: stack_size: {}, set_tid_size: {}, cgroup: {}" , cl_args.stack_size, cl_args.set_tid_size, cl_args.cgroup);
-    #[cfg(target_arch="riscv64")]
-    {
-        sys_clone(flags, stack + cl_args.stack_size, parent_tid, tls, child_tid).await
-    } 
-    #[cfg(target_arch="loongarch64")] 
-    {
-        sys_clone(flags, stack + cl_args.stack_size, parent_tid, child_tid, tls).await
+    if (cl_args.set_tid == 0) != (cl_args.set_tid_size == 0) {
+        return Err(SysError::EINVAL);
+    }
+    // there is only the root pid namespace, so at most one tid can be chosen
+    if cl_args.set_tid_size > 1 {
+        return Err(SysError::EINVAL);
     }
+    let set_tid = if cl_args.set_tid_size == 1 {
+        let tid = *UserPtrRaw::new(cl_args.set_tid as *const i32)
+            .ensure_read(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?
+            .to_ref();
+        if tid <= 0 {
+            return Err(SysError::EINVAL);
+        }
+        // choosing a pid needs CAP_CHECKPOINT_RESTORE
+        if task.euid() != 0 {
+            return Err(SysError::EPERM);
+        }
+        Some(tid as usize)
+    } else {
+        None
+    };
+    let flags = cl_args.flags | cl_args.exit_signal;
+    let stack = match cl_args.stack {
+        0 => VirtAddr::from(0),
+        // the stack grows down from the top of the given area
+        stack => VirtAddr::from((stack + cl_args.stack_size) as usize),
+    };
+    do_clone(
+        flags,
+        stack,
+        VirtAddr::from(cl_args.parent_tid as usize),
+        VirtAddr::from(cl_args.child_tid as usize),
+        VirtAddr::from(cl_args.tls as usize),
+        VirtAddr::from(cl_args.pidfd as usize),
+        set_tid,
+    ).await
 }
 
 //  * @flags:        Flags for the new process.
@@ -798,22 +900,18 @@ pub async fn sys_clone3(cl_args_ptr: usize, size: usize) -> SysResult {
 #[repr(C)]
 struct CloneArgs {
     pub flags: u64,
-    pub pidfd: usize,
-    pub child_tid: usize,
-    pub parent_tid: usize,
-    pub exit_signal: i32,
-    pub _pad: i32,
-    pub stack: usize,
-    pub stack_size: usize,
-    pub tls: usize,
-    pub set_tid: usize,
-    pub set_tid_size: usize,
-    pub cgroup: i32,
-    pub idle: i32,
-    pub func: usize,
-    pub func_arg: usize,
-}
-
-const  CLONE_ARGS_SIZE_VER0: usize = 64;
-const _CLONE_ARGS_SIZE_VER1:usize =  80; /* sizeof second published struct */
-const _CLONE_ARGS_SIZE_VER2: usize =  88; /* sizeof third published struct */
+    pub pidfd: u64,
+    pub child_tid: u64,
+    pub parent_tid: u64,
+    pub exit_signal: u64,
+    pub stack: u64,
+    pub stack_size: u64,
+    pub tls: u64,
+    pub set_tid: u64,
+    pub set_tid_size: u64,
+    pub cgroup: u64,
+}
+
+const CLONE_ARGS_SIZE_VER0: usize = 64; /* sizeof first published struct */
+const CLONE_ARGS_SIZE_VER1: usize = 80; /* sizeof second published struct */
+const CLONE_ARGS_SIZE_VER2: usize = 88; /* sizeof third published struct */
@@ -1,9 +1,8 @@
-use alloc::task;
+use alloc::{sync::Arc, task, vec, vec::Vec};
 
 use super::{SysError,SysResult};
-use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
 
-use crate::{mm::UserPtrRaw, syscall::process, task::{current_task, manager::{PROCESS_GROUP_MANAGER, TASK_MANAGER}, task::CpuMask}}; 
+use crate::{executor::sched::{SchedPolicy, NICE_MAX, NICE_MIN, SCHED_RESET_ON_FORK}, mm::UserPtrRaw, syscall::process, task::{current_task, manager::{PROCESS_GROUP_MANAGER, TASK_MANAGER}, task::{CpuMask, TaskControlBlock}}}; 
 
 /// syscall: 
 /// sets the CPU affinity mask of the thread whose ID is pid to the value specified by mask.
@@ -117,111 +116,183 @@ pub fn sys_sched_getaffinity(pid: usize, cpusetusize: usize, mask_ptr: usize) ->
     *mask = cpu_mask;
     Ok(size_of::<CpuMask>() as isize)
 }
-///
-pub fn sys_sched_setscheduler() -> SysResult {
-    log::warn!("[sys_sched_setscheduler] unimplemented");
+/// struct sched_param
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SchedParam {
+    pub sched_priority: i32,
+}
+
+/// the thread a sched_* syscall is about, 0 means the caller
+fn sched_target(pid: isize) -> Result<Arc<TaskControlBlock>, SysError> {
+    match pid {
+        0 => Ok(current_task().unwrap().clone()),
+        pid if pid < 0 => Err(SysError::EINVAL),
+        pid => TASK_MANAGER.get_task(pid as usize).ok_or(SysError::ESRCH),
+    }
+}
+
+fn read_sched_param(param: usize) -> Result<SchedParam, SysError> {
+    if param == 0 {
+        return Err(SysError::EINVAL);
+    }
+    let task = current_task().unwrap();
+    let param = *UserPtrRaw::new(param as *const SchedParam)
+        .ensure_read(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?
+        .to_ref();
+    Ok(param)
+}
+
+/// check that the priority fits the policy and that the caller may apply it,
+/// only a privileged caller makes a task realtime or changes another user's task
+fn check_sched(task: &Arc<TaskControlBlock>, policy: SchedPolicy, priority: i32) -> Result<u32, SysError> {
+    let (min, max) = policy.priority_range();
+    let priority = u32::try_from(priority).map_err(|_| SysError::EINVAL)?;
+    if priority < min || priority > max {
+        return Err(SysError::EINVAL);
+    }
+    let caller = current_task().unwrap();
+    if caller.euid() != 0
+        && (policy.is_realtime() || (caller.euid() != task.euid() && caller.euid() != task.ruid()))
+    {
+        return Err(SysError::EPERM);
+    }
+    Ok(priority)
+}
+
+/// syscall: sched_setscheduler
+/// set the scheduling policy and static priority of thread `pid`
+pub fn sys_sched_setscheduler(pid: isize, policy: i32, param: usize) -> SysResult {
+    // SCHED_RESET_ON_FORK is accepted but children always inherit the policy
+    let policy = u32::try_from(policy).ok()
+        .and_then(|policy| SchedPolicy::from_repr(policy & !SCHED_RESET_ON_FORK))
+        .ok_or(SysError::EINVAL)?;
+    let param = read_sched_param(param)?;
+    let task = sched_target(pid)?;
+    let priority = check_sched(&task, policy, param.sched_priority)?;
+    log::info!("[sys_sched_setscheduler] task {} policy {:?} priority {}", task.tid(), policy, priority);
+    task.set_policy(policy, priority);
     Ok(0)
 }
-///
-pub fn sys_sched_getscheduler() -> SysResult {
-    log::warn!("[sys_sched_getscheduler] unimplemented");
+
+/// syscall: sched_getscheduler
+pub fn sys_sched_getscheduler(pid: isize) -> SysResult {
+    let task = sched_target(pid)?;
+    Ok(task.policy() as isize)
+}
+
+/// syscall: sched_setparam
+/// change the static priority of thread `pid` within its policy
+pub fn sys_sched_setparam(pid: isize, param: usize) -> SysResult {
+    let param = read_sched_param(param)?;
+    let task = sched_target(pid)?;
+    let policy = task.policy();
+    let priority = check_sched(&task, policy, param.sched_priority)?;
+    task.set_policy(policy, priority);
     Ok(0)
 }
-/// 
-pub fn sys_sched_getparam() -> SysResult {
-    log::warn!("[sys_sched_getparam] unimplemented");
+
+/// syscall: sched_getparam
+pub fn sys_sched_getparam(pid: isize, param: usize) -> SysResult {
+    if param == 0 {
+        return Err(SysError::EINVAL);
+    }
+    let task = sched_target(pid)?;
+    let cur_task = current_task().unwrap();
+    let param = UserPtrRaw::new(param as *mut SchedParam)
+        .ensure_write(&mut cur_task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?;
+    param.write(SchedParam { sched_priority: task.rt_priority() as i32 });
     Ok(0)
 }
 
+/// syscall: sched_get_priority_max
+pub fn sys_sched_get_priority_max(policy: i32) -> SysResult {
+    let policy = u32::try_from(policy).ok().and_then(SchedPolicy::from_repr).ok_or(SysError::EINVAL)?;
+    Ok(policy.priority_range().1 as isize)
+}
+
+/// syscall: sched_get_priority_min
+pub fn sys_sched_get_priority_min(policy: i32) -> SysResult {
+    let policy = u32::try_from(policy).ok().and_then(SchedPolicy::from_repr).ok_or(SysError::EINVAL)?;
+    Ok(policy.priority_range().0 as isize)
+}
+
 const PRIO_PROCESS: usize = 0;
 const PRIO_PGRP: usize = 1;
 const PRIO_USER: usize = 2;
-const MIN_PRIORITY: i32 = 0;
-const MAX_PRIORITY: i32 = 39;
-const NZERO : i32 = 20;
-///
-pub fn sys_set_priority(which: usize, who: usize, process_priority: i32) -> SysResult {
-    let task= current_task().unwrap();
-    match which {
-        PRIO_PROCESS => {
-            if who == 0 || who == task.pid() {
-                task.with_thread_group(|tg| {
-                    for thread in tg.iter() {
-                    let new_priority = (NZERO+ process_priority).max(MIN_PRIORITY as i32).min(MAX_PRIORITY as i32);
-                        log::info!("now new priority is {}", new_priority);
-                        thread.set_priority(new_priority);
-                    }
-                });
-            }else {
-                let target_task = TASK_MANAGER.get_task(who).ok_or(SysError::ESRCH)?;
-                target_task.with_thread_group(|tg| {
-                    for thread in tg.iter() {
-                        let new_priority = (NZERO+ process_priority).max(MIN_PRIORITY as i32).min(MAX_PRIORITY as i32);
-                        log::info!("now new priority is {}", new_priority);
-                        thread.set_priority(new_priority);
-                    }
-                });
-            }
-            return Ok(0);
-        }
+/// getpriority reports `NZERO - nice` so that the result is never negative
+const NZERO: i32 = 20;
 
-        PRIO_PGRP => {
-            let target_pg = PROCESS_GROUP_MANAGER.get_group(which)
-               .ok_or(SysError::ESRCH)?;
-            for task in target_pg.iter().filter_map(|t| t.upgrade()) {
-                let new_priority = (NZERO+ process_priority).max(MIN_PRIORITY as i32).min(MAX_PRIORITY as i32);
-                task.set_priority(new_priority);
-            }
-            return Ok(0);
-        }
+/// the threads of every process a PRIO_* selector picks, 0 meaning the caller's
+/// process, process group or user
+fn prio_targets(which: usize, who: usize) -> Result<Vec<Arc<TaskControlBlock>>, SysError> {
+    let task = current_task().unwrap();
+    let processes = match which {
+        PRIO_PROCESS => match who {
+            0 => vec![task.clone()],
+            pid => vec![TASK_MANAGER.get_task(pid).ok_or(SysError::ESRCH)?],
+        },
+        PRIO_PGRP => PROCESS_GROUP_MANAGER.group_members(if who == 0 { task.pgid() } else { who }),
         PRIO_USER => {
-            log::warn!("[sys_set_priority] unimplemented for PRIO_USER");
-        }
-        _ => {
-            log::warn!("[sys_set_priority] invalid which value");
-            return Err(SysError::EINVAL);
+            let uid = if who == 0 { task.ruid() } else { who as i32 };
+            let mut processes = Vec::new();
+            TASK_MANAGER.for_each_task(|task| {
+                if task.is_leader() && task.ruid() == uid {
+                    processes.push(task.clone());
+                }
+            });
+            processes
         }
+        _ => return Err(SysError::EINVAL),
+    };
+    let mut threads = Vec::new();
+    for process in processes {
+        process.with_thread_group(|tg| threads.extend(tg.iter()));
     }
-    Ok(0)
+    if threads.is_empty() {
+        return Err(SysError::ESRCH);
+    }
+    Ok(threads)
 }
 
-/// 
-pub fn sys_get_priority(which: usize, who: usize) -> SysResult {
-    let task= current_task().unwrap();
-    match which {
-        PRIO_PROCESS => {
-            if who == 0 || who == task.pid() {
-                let priority = task.priority().load(Ordering::SeqCst);
-                return Ok((2*NZERO - priority) as isize);
-            }else {
-                let target_task = TASK_MANAGER.get_task(who).ok_or(SysError::ESRCH)?;
-                let priority = target_task.priority().load(Ordering::SeqCst);
-                return Ok((2*NZERO - priority) as isize);
+/// syscall: setpriority
+/// set the nice value of every thread `which` and `who` pick, clamped to -20..19;
+/// only a privileged caller may change another user's tasks or move a task below
+/// nice 0 and under its current value
+pub fn sys_set_priority(which: usize, who: usize, nice: i32) -> SysResult {
+    let nice = nice.clamp(NICE_MIN, NICE_MAX);
+    let targets = prio_targets(which, who)?;
+    let caller = current_task().unwrap();
+    if caller.euid() != 0 {
+        for target in targets.iter() {
+            if caller.euid() != target.euid() && caller.euid() != target.ruid() {
+                return Err(SysError::EPERM);
             }
-        }
-        PRIO_PGRP => {
-            let target_pg = PROCESS_GROUP_MANAGER.get_group(which)
-               .ok_or(SysError::ESRCH)?;
-            let mut min_priority =  MAX_PRIORITY;
-            for task in target_pg.iter().filter_map(|t| t.upgrade()) {
-                let priority = task.priority().load(Ordering::SeqCst); 
-                log::info!("priority is {}", priority);
-                min_priority = min_priority.min(priority);
-                log::info!("min_priority is {}", min_priority);
+            if nice < 0 && nice < target.nice() {
+                return Err(SysError::EACCES);
             }
-            return Ok((2*NZERO - min_priority) as isize);
-        }
-        PRIO_USER => {
-            log::warn!("[sys_get_priority] unimplemented for PRIO_USER");
-        }
-        _ => {
-            log::warn!("[sys_set_priority] invalid which value");
-            return Err(SysError::EINVAL);
         }
     }
+    log::info!("[sys_set_priority] which {} who {} nice {}", which, who, nice);
+    for target in targets {
+        target.set_nice(nice);
+    }
     Ok(0)
 }
 
+/// syscall: getpriority
+/// the lowest nice value among the threads `which` and `who` pick, as `NZERO - nice`
+pub fn sys_get_priority(which: usize, who: usize) -> SysResult {
+    let nice = prio_targets(which, who)?
+        .iter()
+        .map(|task| task.nice())
+        .min()
+        .unwrap();
+    Ok((NZERO - nice) as isize)
+}
+
 pub fn sys_getcpu(cpu_ptr: usize, node_ptr: usize) -> SysResult {
     let task = current_task().unwrap().clone();
     let cpu_ptr = UserPtrRaw::new(cpu_ptr as *mut u32)
@@ -16,13 +16,18 @@ use hal::{
 };
 use log::*;
 use super::{SysError,SysResult};
+use crate::fs::pidfd::PidFdFile;
+use crate::fs::signalfd::{SignalFdFile, SignalFdFlags};
+use crate::fs::tmpfs::{dentry::TmpDentry, inode::{EmptyFile, TmpSysInode}};
+use crate::fs::vfs::{inode::InodeMode, FileInner};
 use crate::mm::UserPtrRaw;
 use crate::sync::mutex::SpinNoIrqLock;
 use crate::{processor, timer};
 use crate::processor::context::SumGuard;
 use crate::processor::processor::current_processor;
 use crate::signal::*;
-use crate::task::{current_task,INITPROC_PID};
+use crate::task::{current_task, task::TaskControlBlock, INITPROC_PID};
+use crate::task::fs::{FdFlags, FdInfo};
 use crate::processor::processor::current_trap_cx;
 use crate::task::manager::{PROCESS_GROUP_MANAGER, TASK_MANAGER};
 use crate::timer::ffi::TimeSpec;
@@ -31,82 +36,65 @@ use crate::utils::suspend_now;
 
 /// syscall: kill
 pub fn sys_kill(pid: isize, signo: i32) -> SysResult {
-    if signo == 0 {
-        // If sig is 0, then no signal is sent
-        return Ok(0);
-    } else if signo < 0 || signo as usize >= SIGRTMAX {
+    if signo < 0 || signo as usize >= SIGRTMAX {
         return Err(SysError::EINVAL);
     }
     let cur_task = current_task().unwrap().clone();
     log::info!("[sys_kill]: task {} sending signo: {} to pid: {}", cur_task.tid(), signo, pid);
-    let pgid = cur_task.pgid();
-    match pid {
+    let targets: Vec<Arc<TaskControlBlock>> = match pid {
         0 => {
             // sent to every process in the process group of current process
-            for process in PROCESS_GROUP_MANAGER
-                .get_group(pgid)
-                .unwrap()
-                .into_iter()
-                .map(|inner| inner.upgrade().unwrap())
-                .filter(|inner| inner.is_leader())
-            {
-                process.recv_sigs_process_level(
-                    SigInfo {
-                        si_signo: signo as usize,
-                        si_code: SigInfo::USER,
-                        si_pid: Some(cur_task.pid())
-                    }
-                );
-            }
+            PROCESS_GROUP_MANAGER.group_members(cur_task.pgid())
         }
         -1 => {
-            // sent to every process which current process has permission ( except init proc )
-            //panic!("[sys_kill] unsupport for sending signal to all process");
-            TASK_MANAGER.for_each_task(|task|{
-                if task.tid() == INITPROC_PID {
-                    return;
-                }
-                if signo != 0 && task.is_leader(){
-                    task.recv_sigs_process_level(
-                        SigInfo { si_signo: signo as usize, si_code: SigInfo::USER, si_pid: Some(cur_task.pid()) },
-                    );
+            // sent to every process which current process has permission ( except init proc and itself )
+            let mut tasks = Vec::new();
+            TASK_MANAGER.for_each_task(|task| {
+                if task.pid() != INITPROC_PID && task.pid() != cur_task.pid() {
+                    tasks.push(task.clone());
                 }
             });
+            tasks
         }
         _ if pid < -1 => {
             // sent to every process in process group whose ID is -pid
-            //panic!("[sys_kill] unsupport for sending signal to specific process group");
-            let inner_pid = -pid as usize;
-            for task in PROCESS_GROUP_MANAGER
-                .get_group(pgid)
-                .ok_or_else(|| SysError::ESRCH)?
-                .into_iter()
-                .filter_map(|t| t.upgrade())
-            {
-                if task.tid() == inner_pid {
-                    task.recv_sigs_process_level(SigInfo { si_signo: signo as usize, si_code: SigInfo::USER, si_pid: Some(cur_task.pgid()) });
-                }
-            }
+            PROCESS_GROUP_MANAGER.group_members(pid.unsigned_abs())
         }
-        _ if pid > 0 => {
+        _ => {
             // sent to the process specified with pid
-            //assert!(task.gettid() != pid as usize); // should not send to itself
-            if let Some(task) = TASK_MANAGER.get_task(pid as usize) {
-                if task.is_leader() {
-                    task.recv_sigs_process_level(
-                        SigInfo { si_signo: signo as usize, si_code: SigInfo::USER, si_pid: Some(cur_task.pid()) },
-                    );
-                }else {
-                    // todo standard error
-                    return Err(SysError::ESRCH);
-                }
-            }else {
-                return Err(SysError::ESRCH);
-            }
+            TASK_MANAGER.get_task(pid as usize).into_iter().collect()
+        }
+    };
+    let mut found = false;
+    let mut sent = false;
+    for task in targets.into_iter().filter(|task| task.is_leader()) {
+        found = true;
+        if !can_send_signal(&cur_task, &task) {
+            continue;
+        }
+        sent = true;
+        // If sig is 0, then no signal is sent, only the checks are done
+        if signo != 0 {
+            task.recv_sigs_process_level(
+                SigInfo { si_signo: signo as usize, si_code: SigInfo::USER, si_pid: Some(cur_task.pid()), si_value: 0 },
+            );
         }
-        _ => {}
     }
-    Ok(0)
+    match (found, sent) {
+        (_, true) => Ok(0),
+        (true, false) => Err(SysError::EPERM),
+        (false, _) => Err(SysError::ESRCH),
+    }
+}
+
+/// a privileged sender may signal anyone, otherwise the real or effective uid
+/// of the sender has to match the real or saved uid of the target
+fn can_send_signal(sender: &Arc<TaskControlBlock>, target: &Arc<TaskControlBlock>) -> bool {
+    if sender.euid() == 0 {
+        return true;
+    }
+    let sender_ids = [sender.ruid(), sender.euid()];
+    sender_ids.contains(&target.ruid()) || sender_ids.contains(&target.suid())
 }
 
 
@@ -241,13 +229,22 @@ pub fn sys_rt_sigreturn() -> SysResult {
     let _sum_guard = SumGuard::new();
     let task = current_task().unwrap();
     let ucontext_ptr = task.sig_ucontext_ptr();
-    let ucontext = unsafe {
-        *(ucontext_ptr as *const UContext)
-    };
+    let ucontext = *UserPtrRaw::new(ucontext_ptr as *const UContext)
+        .ensure_read(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?
+        .to_ref();
+    // back to the frame of the handler this one interrupted
+    task.set_sig_ucontext_ptr(ucontext.uc_link);
     let mut sig_manager = task.sig_manager.lock();
     // restore the old sig mask
-    sig_manager.blocked_sigs = SigSet::from_bits_truncate(ucontext.uc_sigmask);
-    // restore the old context (todo: restore signal stack)
+    sig_manager.set_sigmask(SigSet::from_bits_truncate(ucontext.uc_sigmask));
+    // arm again the alternate stack disarmed on entry of the handler
+    if ucontext.uc_stack.ss_flags as u32 & SS_AUTODISARM != 0 {
+        let mut stack = ucontext.uc_stack;
+        stack.ss_flags &= SS_AUTODISARM as i32;
+        task.set_signal_stack(Some(stack));
+    }
+    // restore the old context
     let cx = current_trap_cx(current_processor());
     ucontext.restore_old_context(cx);
     Ok(cx.arg_nth(0) as isize)
@@ -292,42 +289,47 @@ pub async fn sys_rt_sigtimedwait(
         *(set_ptr as *mut SigSet)
     };
     set.remove(SigSet::SIGKILL | SigSet::SIGSTOP);
-    let pending_sigs = task.with_mut_sig_manager(|sig_manager| {
-        if let Some(si) = sig_manager.check_pending(set) {
-            Some(si.si_signo)
-        }else {
+    // a pending signal is consumed right away, real-time signals one instance per call
+    let pending_sig = task.with_mut_sig_manager(|sig_manager| {
+        if sig_manager.check_pending_flag(set) {
+            sig_manager.dequeue_expected_one(set)
+        } else {
             sig_manager.wake_sigs = set | SigSet::SIGKILL | SigSet::SIGSTOP;
             None
         }
     });
-    if let Some(si) = pending_sigs {
-        return Ok(si as isize);
-    }
-    task.set_interruptable();
-    if timeout_ptr == 0 {
-        // log::warn!("[sys_rt_sigtimedwait] task {} start to suspend", task.tid());
-        suspend_now().await;
-    } else {
-        let timeout = unsafe {
-            let _sum_guard = SumGuard::new();
-            *(timeout_ptr as *const TimeSpec)
-        };
-        log::warn!("[sys_rt_sigtimedwait] task {} set timeout {:?}",task.tid(), timeout);
-        if !timeout.is_valid() {
-            return  Err(SysError::EINVAL);
+    let si = match pending_sig {
+        Some(si) => Some(si),
+        None => {
+            task.set_interruptable();
+            if timeout_ptr == 0 {
+                // log::warn!("[sys_rt_sigtimedwait] task {} start to suspend", task.tid());
+                suspend_now().await;
+            } else {
+                let timeout = unsafe {
+                    let _sum_guard = SumGuard::new();
+                    *(timeout_ptr as *const TimeSpec)
+                };
+                log::warn!("[sys_rt_sigtimedwait] task {} set timeout {:?}",task.tid(), timeout);
+                if !timeout.is_valid() {
+                    task.set_running();
+                    return  Err(SysError::EINVAL);
+                }
+                suspend_timeout(current_task().unwrap(), timeout.into()).await;
+            }
+            task.set_running();
+            task.with_mut_sig_manager(|sig_manager| {
+                sig_manager.dequeue_expected_one(set)
+            })
         }
-        suspend_timeout(current_task().unwrap(), timeout.into()).await;
-    }
-    task.set_running();
-    let si = task.with_mut_sig_manager(|sig_manager| {
-        sig_manager.dequeue_expected_one(set)
-    });
+    };
     if let Some(si) = si {
         log::warn!("[sys_rt_sigtimedwait] task {} woken by {:#?}", task.tid(), si);
         if info_ptr != 0 {
-            unsafe {
-                (info_ptr as *mut SigInfo).write(si);
-            }
+            UserPtrRaw::new(info_ptr as *mut LinuxSigInfo)
+                .ensure_write(&mut task.get_vm_space().lock())
+                .ok_or(SysError::EFAULT)?
+                .write(LinuxSigInfo::from(si));
         }
         return  Ok(si.si_signo as isize);
     } else {
@@ -401,11 +403,108 @@ pub fn sys_tkill(tid: isize, sig: i32) -> SysResult {
             si_signo: sig as usize,
             si_code: SigInfo::TKILL,
             si_pid: Some(cur_task.pid()),
+            si_value: 0,
         }
     );
     Ok(0)
 }
 
+/// syscall: rt_sigqueueinfo
+/// sends the signal sig with the siginfo in `uinfo` to the process pid,
+/// real-time signals are queued so every call is delivered once,
+/// in order, carrying the value of `si_value`
+pub fn sys_rt_sigqueueinfo(pid: isize, sig: i32, uinfo: *const LinuxSigInfo) -> SysResult {
+    info!("[sys_rt_sigqueueinfo] {} {}", pid, sig);
+    if sig < 0 || sig as usize > SIGRTMAX || pid <= 0 {
+        return Err(SysError::EINVAL);
+    }
+    let sig_info = read_queued_siginfo(pid as usize, sig, uinfo)?;
+    let task = TASK_MANAGER.get_task(pid as usize)
+        .ok_or(SysError::ESRCH)?;
+    if !task.is_leader() {
+        return Err(SysError::ESRCH);
+    }
+    if sig == 0 {
+        return Ok(0);
+    }
+    task.recv_sigs_process_level(sig_info);
+    Ok(0)
+}
+
+/// like rt_sigqueueinfo, but the signal is sent to the thread tid in the thread group tgid
+pub fn sys_rt_tgsigqueueinfo(tgid: isize, tid: isize, sig: i32, uinfo: *const LinuxSigInfo) -> SysResult {
+    info!("[sys_rt_tgsigqueueinfo] {} {} {}", tgid, tid, sig);
+    if sig < 0 || sig as usize > SIGRTMAX || tgid <= 0 || tid <= 0 {
+        return Err(SysError::EINVAL);
+    }
+    let sig_info = read_queued_siginfo(tgid as usize, sig, uinfo)?;
+    let task = TASK_MANAGER.get_task(tgid as usize).ok_or(SysError::ESRCH)?;
+    if !task.is_leader() {
+        return Err(SysError::ESRCH);
+    }
+    task.with_mut_thread_group(|thread_group| -> SysResult {
+        let thread = thread_group.iter()
+            .find(|thread| thread.tid() == tid as usize)
+            .ok_or(SysError::ESRCH)?;
+        if sig != 0 {
+            thread.recv_sigs(sig_info);
+        }
+        Ok(0)
+    })
+}
+
+/// send the signal sig to the process referred to by pidfd; with `uinfo`
+/// the siginfo is given by the caller as for rt_sigqueueinfo
+pub fn sys_pidfd_send_signal(pidfd: usize, sig: i32, uinfo: *const LinuxSigInfo, flags: u32) -> SysResult {
+    info!("[sys_pidfd_send_signal] pidfd {} sig {} flags {}", pidfd, sig, flags);
+    if flags != 0 || sig < 0 || sig as usize > SIGRTMAX {
+        return Err(SysError::EINVAL);
+    }
+    let cur_task = current_task().unwrap().clone();
+    let file = cur_task.with_fd_table(|t| t.get_file(pidfd))?;
+    let pidfd = file.downcast_arc::<PidFdFile>().map_err(|_| SysError::EBADF)?;
+    let task = pidfd.task().ok_or(SysError::ESRCH)?;
+    if !can_send_signal(&cur_task, &task) {
+        return Err(SysError::EPERM);
+    }
+    let sig_info = if uinfo.is_null() {
+        SigInfo { si_signo: sig as usize, si_code: SigInfo::USER, si_pid: Some(cur_task.pid()), si_value: 0 }
+    } else {
+        let signo = UserPtrRaw::new(uinfo)
+            .ensure_read(&mut cur_task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?
+            .to_ref()
+            .si_signo;
+        if signo != sig {
+            return Err(SysError::EINVAL);
+        }
+        read_queued_siginfo(task.pid(), sig, uinfo)?
+    };
+    if sig != 0 {
+        task.recv_sigs_process_level(sig_info);
+    }
+    Ok(0)
+}
+
+/// read the user siginfo of a queued signal sent to process `pid`
+fn read_queued_siginfo(pid: usize, sig: i32, uinfo: *const LinuxSigInfo) -> Result<SigInfo, SysError> {
+    let cur_task = current_task().unwrap().clone();
+    let info = *UserPtrRaw::new(uinfo)
+        .ensure_read(&mut cur_task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?
+        .to_ref();
+    // only the kernel may send positive si_code, or tkill to other processes
+    if (info.si_code >= 0 || info.si_code == SigInfo::TKILL) && pid != cur_task.pid() {
+        return Err(SysError::EPERM);
+    }
+    Ok(SigInfo {
+        si_signo: sig as usize,
+        si_code: info.si_code,
+        si_pid: Some(cur_task.pid()),
+        si_value: info.si_value(),
+    })
+}
+
 /// sends the signal sig to the thread with the thread ID tid
 ///        in the thread group tgid.  (By contrast, kill(2) can be used to
 ///        send a signal only to a process (i.e., thread group) as a whole,
@@ -425,7 +524,7 @@ pub fn sys_tgkill(tgid: isize, tid: isize, signo: i32) -> SysResult {
         task.with_mut_thread_group(|thread_group| -> SysResult {
             for thread in thread_group.iter() {
                 if thread.tid() == tid as usize {
-                    thread.recv_sigs(SigInfo { si_signo: signo as usize, si_code: SigInfo::TKILL, si_pid: Some(cur_task.pid())});
+                    thread.recv_sigs(SigInfo { si_signo: signo as usize, si_code: SigInfo::TKILL, si_pid: Some(cur_task.pid()), si_value: 0 });
                     return Ok(0)
                 }
             }
@@ -438,38 +537,103 @@ pub fn sys_tgkill(tgid: isize, tid: isize, signo: i32) -> SysResult {
 
 pub const SS_ONSTACK: u32 = 1;
 pub const SS_DISABLE: u32 = 2;
+pub const SS_AUTODISARM: u32 = 1 << 31;
+/// minimal size of an alternate signal stack
+pub const MINSIGSTKSZ: usize = 2048;
 
-pub fn sys_signalstack(ss_ptr: usize, old_ss_ptr: usize) -> SysResult {
+/// syscall: sigaltstack
+pub fn sys_sigaltstack(ss_ptr: usize, old_ss_ptr: usize) -> SysResult {
     let task = current_task().unwrap().clone();
-    log::warn!("[sys_signalstack] {:#x} {:#x}", ss_ptr, old_ss_ptr);
+    log::debug!("[sys_sigaltstack] {:#x} {:#x}", ss_ptr, old_ss_ptr);
+    let on_stack = task.on_signal_stack(*task.get_trap_cx().sp());
 
-    let ss = task.get_signal_stack();
     if old_ss_ptr != 0 {
         let old_ss_ptr = UserPtrRaw::new(old_ss_ptr as *mut SigStack)
         .ensure_write(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
-        if let Some(stack) = ss {
-            log::warn!("return old signal stack");
-            old_ss_ptr.write(stack);
-        } else {
-            log::warn!("return disable stack info");
-            let no_stack = SigStack {
+        let old_stack = match task.get_signal_stack() {
+            Some(mut stack) => {
+                if on_stack {
+                    stack.ss_flags |= SS_ONSTACK as i32;
+                }
+                stack
+            }
+            None => SigStack {
                 ss_flags: SS_DISABLE as i32,
                 ss_sp: 0,
                 ss_size: 0,
-            };
-            old_ss_ptr.write(no_stack);
-        }
+            },
+        };
+        old_ss_ptr.write(old_stack);
     }
 
     if ss_ptr != 0 {
         let ss_ptr = UserPtrRaw::new(ss_ptr as *const SigStack)
         .ensure_read(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
-        let stack = *ss_ptr.to_ref();
-        log::warn!("set new signal stack");
-        task.set_signal_stack(Some(stack));
+        let mut stack = *ss_ptr.to_ref();
+        // cannot change the stack while the handler is running on it
+        if on_stack {
+            return Err(SysError::EPERM);
+        }
+        match stack.ss_flags as u32 & !SS_AUTODISARM {
+            SS_DISABLE => task.set_signal_stack(None),
+            0 | SS_ONSTACK => {
+                if stack.ss_size < MINSIGSTKSZ {
+                    return Err(SysError::ENOMEM);
+                }
+                stack.ss_flags &= SS_AUTODISARM as i32;
+                task.set_signal_stack(Some(stack));
+            }
+            _ => return Err(SysError::EINVAL),
+        }
     }
 
     Ok(0)
-}
\ No newline at end of file
+}
+
+/// syscall: signalfd4
+/// create a signalfd reading the signals in `mask`, or change the mask of signalfd `fd`
+pub fn sys_signalfd4(fd: isize, mask: *const u64, sizemask: usize, flags: u32) -> SysResult {
+    if sizemask != core::mem::size_of::<SigSet>() {
+        return Err(SysError::EINVAL);
+    }
+    let flags = SignalFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap().clone();
+    let mask = SigSet::from_bits_truncate(
+        *UserPtrRaw::new(mask)
+            .ensure_read(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?
+            .to_ref() as usize
+    );
+    info!("[sys_signalfd4] fd {}, mask {:?}, flags {:?}", fd, mask, flags);
+    if fd != -1 {
+        let file = task.with_fd_table(|table| table.get_file(fd as usize))?
+            .downcast_arc::<SignalFdFile>()
+            .map_err(|_| SysError::EINVAL)?;
+        file.set_mask(mask);
+        return Ok(fd);
+    }
+    let dentry = TmpDentry::new("", None);
+    let inode = TmpSysInode::new(None, InodeMode::FILE, Arc::new(EmptyFile {}));
+    dentry.set_inode(inode);
+    let file = SignalFdFile::new(
+        FileInner {
+            dentry,
+            offset: 0.into(),
+            flags: SpinNoIrqLock::new(flags.into()),
+        },
+        &task,
+        mask,
+    );
+    let fd_flags = if flags.contains(SignalFdFlags::SFD_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    task.with_mut_fd_table(|table| {
+        let fd = table.alloc_fd()?;
+        table.put_file(fd, FdInfo { file, flags: fd_flags })?;
+        Ok(fd as isize)
+    })
+}
@@ -84,8 +84,12 @@ pub enum SysError {
     ENOTEMPTY = 39,
     /// Too many symbolic links encountered
     ELOOP = 40,
+    /// No data available
+    ENODATA = 61,
     /// Timer expired   
     ETIME = 62,
+    /// Value too large for defined data type
+    EOVERFLOW = 75,
     /// Socket operation on non-socket
     ENOTSOCK = 88,
     /// sendmsg bigger than biggest message
@@ -6,26 +6,25 @@ use alloc::{boxed::Box, fmt, sync::Arc, task};
 use fatfs::{info, Time};
 use hal::instruction::{Instruction, InstructionHal};
 use rand::rand_core::le;
-use spin::Mutex;
 use xmas_elf::program::Flags;
 
 use super::{SysError, SysResult};
 use crate::{
-    fs::{procfs::interrupt, tmpfs::{dentry::TmpDentry, inode::{EmptyFile, TmpSysInode}}, vfs::{inode::InodeMode, File, FileInner}, OpenFlags}, mm::UserPtrRaw, processor::context::SumGuard, signal::msg_queue::Sigevent, sync::mutex::SpinNoIrqLock, task::{
-        current_task, fs::{FdFlags, FdInfo}, task::{new_shared, Shared}
+    fs::{procfs::interrupt, timerfd::{TimerFdFile, TimerFdFlags, TimerFdSetFlags}, tmpfs::{dentry::TmpDentry, inode::{EmptyFile, TmpSysInode}}, vfs::{inode::InodeMode, File, FileInner}, OpenFlags}, mm::UserPtrRaw, processor::context::SumGuard, signal::msg_queue::Sigevent, sync::mutex::SpinNoIrqLock, task::{
+        current_task, fs::{FdFlags, FdInfo}, signal::IntrBySignalFuture, task::{new_shared, Shared}
     }, timer::{
         clock::{
-            CLOCK_BOOTTIME, CLOCK_DEVIATION, CLOCK_MONOTONIC, CLOCK_MONOTONIC_COARSE,
-            CLOCK_MONOTONIC_RAW, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME, CLOCK_REALTIME_COARSE,
+            clock_time, realtime_seq, realtime_time, set_realtime, RealtimeStepFuture,
+            CLOCK_BOOTTIME, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME,
             CLOCK_THREAD_CPUTIME_ID,
         },
         ffi::{TimeSpec, TimeVal},
-        get_current_time_duration, get_current_time_ms, get_current_time_us,
-        timed_task::{ksleep, suspend_timeout},
+        duration_to_ticks, get_current_time_duration, get_current_time_ms, tick_usec,
+        timed_task::{ksleep, suspend_timeout, PendingFuture, TimedTaskFuture},
         timer::{
-            alloc_timer_id, ITimerSpec, ITimerVal, PosixTimer, RealITimer, Timer, TimerFd, TimerFdEvent, TimerFdFile, TimerId, TIMER_MANAGER
+            alloc_timer_id, ITimerSpec, ITimerVal, PosixTimer, RealITimer, Timer, TimerId, ITIMER_PROF, ITIMER_REAL, TIMER_MANAGER
         },
-    }, utils::Select2Futures
+    }, utils::{Select2Futures, SelectOutput}
 };
 /// get current time of day
 pub fn sys_gettimeofday(tv: usize) -> SysResult {
@@ -35,7 +34,7 @@ pub fn sys_gettimeofday(tv: usize) -> SysResult {
         let tv_ptr = UserPtrRaw::new(tv as *mut TimeVal)
             .ensure_write(&mut vm)
             .ok_or(SysError::EFAULT)?;
-        let current_time = get_current_time_us();
+        let current_time = realtime_time().as_micros() as usize;
         let time_val = TimeVal {
             sec: current_time / 1_000_000,
             usec: (current_time % 1_000_000),
@@ -52,11 +51,14 @@ pub fn sys_times(tms: usize) -> SysResult {
         let tms_ptr = UserPtrRaw::new(tms as *mut Tms)
             .ensure_write(&mut task.get_vm_space().lock())
             .ok_or(SysError::EFAULT)?;
-        let current_task = current_task().unwrap();
-        let tms_val = Tms::from_time_recorder(current_task.time_recorder());
+        let tms_val = Tms::from_time_pairs(
+            task.process_time_pair(),
+            task.time_recorder().child_time_pair(),
+        );
         tms_ptr.write(tms_val);
     }
-    Ok(0)
+    // clock ticks since boot
+    Ok(duration_to_ticks(get_current_time_duration()) as isize)
 }
 /// sleep syscall
 pub async fn sys_nanosleep(time_ptr: usize, time_out_ptr: usize) -> SysResult {
@@ -99,18 +101,6 @@ pub fn sys_clock_gettime(clock_id: usize, ts: usize) -> SysResult {
         .ok_or(SysError::EFAULT)?;
     // log::info!("[sys_clock_gettime]: clock id {}", clock_id);
     match clock_id {
-        CLOCK_REALTIME | CLOCK_MONOTONIC => {
-            let current = get_current_time_duration();
-            unsafe {
-                ts_ptr.write((CLOCK_DEVIATION[clock_id] + current).into());
-            }
-        }
-        CLOCK_MONOTONIC_RAW => {
-            let current = get_current_time_duration();
-            unsafe {
-                ts_ptr.write((CLOCK_DEVIATION[CLOCK_MONOTONIC] + current).into());
-            }
-        }
         CLOCK_PROCESS_CPUTIME_ID => {
             let cpu_time = task.process_cpu_time();
             ts_ptr.write(cpu_time.into());
@@ -120,27 +110,12 @@ pub fn sys_clock_gettime(clock_id: usize, ts: usize) -> SysResult {
             let cpu_time = user_time + kernel_time;
             ts_ptr.write(cpu_time.into());
         }
-        CLOCK_REALTIME_COARSE => {
-            let current = get_current_time_duration();
-            unsafe {
-                ts_ptr.write((CLOCK_DEVIATION[CLOCK_REALTIME] + current).into());
-            }
-        }
-        CLOCK_MONOTONIC_COARSE => {
-            let current = get_current_time_duration();
-            unsafe {
-                ts_ptr.write((CLOCK_DEVIATION[CLOCK_MONOTONIC] + current).into());
-            }
-        }
-        CLOCK_BOOTTIME => {
-            let current = get_current_time_duration();
-            unsafe {
-                ts_ptr.write((CLOCK_DEVIATION[CLOCK_MONOTONIC] + current).into());
-            }
-        }
         _ => {
-            log::warn!("[sys_clock_gettime] unsupported clockid {}", clock_id);
-            return Err(SysError::EINVAL);
+            let Some(current) = clock_time(clock_id) else {
+                log::warn!("[sys_clock_gettime] unsupported clockid {}", clock_id);
+                return Err(SysError::EINVAL);
+            };
+            ts_ptr.write(current.into());
         }
     }
     Ok(0)
@@ -167,10 +142,7 @@ pub fn sys_clock_settime(clock_id: usize, ts_ptr: usize) -> SysResult {
             if tp.into_ms() < get_current_time_ms() {
                 return Err(SysError::EINVAL);
             }
-            unsafe {
-                // CLOCK_DEVIATION[clock_id] = duration - get_current_time_duration();
-                CLOCK_DEVIATION[clock_id] = duration.saturating_sub(get_current_time_duration());
-            }
+            set_realtime(duration);
         }
         _ => {
             log::warn!("[clock_settime] unsupport clock {clock_id}");
@@ -199,10 +171,11 @@ pub fn sys_clock_getres(_clockid: usize, res_ptr: usize) -> SysResult {
     Ok(0)
 }
 
-/// Interval timer allows processes to receive signals after a specified time interval
-/// set a itimer, now only irealtimer implemented
+/// Interval timer allows processes to receive signals after a specified time interval:
+/// ITIMER_REAL counts real time, ITIMER_VIRTUAL the user time of the process
+/// and ITIMER_PROF its user and system time
 pub fn sys_setitimer(which: usize, new_ptr: usize, old_ptr: usize) -> SysResult {
-    if which > 2 {
+    if which > ITIMER_PROF {
         return Err(SysError::EINVAL);
     }
 
@@ -214,6 +187,7 @@ pub fn sys_setitimer(which: usize, new_ptr: usize, old_ptr: usize) -> SysResult
     if !new.is_valid() {
         return Err(SysError::EINVAL);
     }
+    let now = task.itimer_clock(which);
     let id = alloc_timer_id();
     let (prev_timeval, next_expire) = task.with_mut_itimers(|itimers| {
         let itimer = &mut itimers[which];
@@ -221,7 +195,7 @@ pub fn sys_setitimer(which: usize, new_ptr: usize, old_ptr: usize) -> SysResult
             it_interval: itimer.interval.into(),
             it_value: itimer
                 .next_expire
-                .saturating_sub(get_current_time_duration())
+                .saturating_sub(now)
                 .into(),
         };
         itimer.interval = new.it_interval.into();
@@ -230,13 +204,14 @@ pub fn sys_setitimer(which: usize, new_ptr: usize, old_ptr: usize) -> SysResult
             itimer.next_expire = Duration::ZERO;
             (prev_timeval, Duration::ZERO)
         } else {
-            let next_expire = get_current_time_duration() + new.it_value.into();
+            let next_expire = now + new.it_value.into();
             itimer.next_expire = next_expire;
             (prev_timeval, next_expire)
         }
     });
 
-    if !new.it_value.is_zero() {
+    // the cpu time timers are checked on timer interrupts instead
+    if which == ITIMER_REAL && !new.it_value.is_zero() {
         let timer = Timer::new(
             next_expire,
             Box::new(RealITimer {
@@ -246,12 +221,6 @@ pub fn sys_setitimer(which: usize, new_ptr: usize, old_ptr: usize) -> SysResult
         );
         TIMER_MANAGER.add_timer(timer);
     }
-    // if old_ptr != 0{
-    //     unsafe {
-    //         let oldptr = old_ptr as *mut ITimerVal;
-    //         oldptr.write(prev_timeval);
-    //     }
-    // }
     if old_ptr != 0 {
         let old_ptr = UserPtrRaw::new(old_ptr as *mut ITimerVal)
             .ensure_write(&mut task.get_vm_space().lock())
@@ -263,18 +232,19 @@ pub fn sys_setitimer(which: usize, new_ptr: usize, old_ptr: usize) -> SysResult
 }
 /// write current itimerval into now_ptr
 pub fn sys_getitimer(which: usize, now_ptr: usize) -> SysResult {
-    if which > 2 {
+    if which > ITIMER_PROF {
         return Err(SysError::EINVAL);
     }
     let current = current_task().unwrap();
     if now_ptr != 0 {
+        let now = current.itimer_clock(which);
         let itimerval = current.with_itimers(|itimers| {
             let itimer = &itimers[which];
             ITimerVal {
                 it_interval: itimer.interval.into(),
                 it_value: itimer
                     .next_expire
-                    .saturating_sub(get_current_time_duration())
+                    .saturating_sub(now)
                     .into(),
             }
         });
@@ -294,42 +264,65 @@ pub async fn sys_clock_nanosleep(
     t_ptr: usize,
     rem_ptr: usize,
 ) -> SysResult {
+    /// the request is an absolute time of the clock
+    const TIMER_ABSTIME: usize = 1;
     let task = current_task().unwrap();
-    match clock_id {
-        CLOCK_REALTIME | CLOCK_MONOTONIC => {
-            let t = *(UserPtrRaw::new(t_ptr as *const TimeSpec)
-                .ensure_read(&mut task.get_vm_space().lock())
-                .ok_or(SysError::EFAULT)?
-                .to_ref());
-            if !t.is_valid() {
-                return Err(SysError::EINVAL);
+    if clock_id == CLOCK_THREAD_CPUTIME_ID {
+        return Err(SysError::EINVAL);
+    }
+    if clock_time(clock_id).is_none() {
+        return Err(SysError::EOPNOTSUPP);
+    }
+    if flags & !TIMER_ABSTIME != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let t = *(UserPtrRaw::new(t_ptr as *const TimeSpec)
+        .ensure_read(&mut task.get_vm_space().lock())
+        .ok_or(SysError::EFAULT)?
+        .to_ref());
+    if !t.is_valid() {
+        return Err(SysError::EINVAL);
+    }
+    let req_time: Duration = t.into();
+    let mask = task.sig_manager.lock().get_sigmask();
+    task.set_interruptable();
+    task.set_wake_up_sigs(!mask);
+
+    if flags & TIMER_ABSTIME != 0 {
+        // the deadline is re-evaluated against the clock whenever CLOCK_REALTIME is stepped,
+        // an interrupted absolute sleep can simply be restarted, so rem is left alone
+        loop {
+            let seq = realtime_seq();
+            let now = clock_time(clock_id).unwrap();
+            if req_time <= now {
+                task.set_running();
+                return Ok(0);
             }
-            let req_time: Duration = t.into();
-            let remain_time = if flags == 1 {
-                let current_time = get_current_time_duration();
-                if req_time.le(&current_time) {
-                    return Ok(0);
-                }
-                let sleep_time = req_time - current_time;
-                suspend_timeout(task, sleep_time).await
-            } else {
-                suspend_timeout(task, req_time).await
-            };
-            if remain_time.is_zero() {
-                Ok(0)
-            } else {
-                log::warn!("[sys_clock_nanosleep] rem_ptr: {}", rem_ptr);
-                let remptr = UserPtrRaw::new(rem_ptr as *mut TimeSpec)
-                    .ensure_write(&mut task.get_vm_space().lock())
-                    .ok_or(SysError::EFAULT)?;
-                if rem_ptr != 0 {
-                    remptr.write(remain_time.into());
-                }
-                Err(SysError::EINTR)
+            let sleep_future = TimedTaskFuture::new(req_time - now, RealtimeStepFuture { seq });
+            let intr_future = IntrBySignalFuture { task: task.clone(), mask };
+            if let SelectOutput::Output2(_) = Select2Futures::new(sleep_future, intr_future).await {
+                task.set_running();
+                return Err(SysError::EINTR);
             }
         }
-        _ => {
-            return Err(SysError::EOPNOTSUPP);
+    }
+
+    let expire = get_current_time_duration() + req_time;
+    let sleep_future = TimedTaskFuture::new(req_time, PendingFuture {});
+    let intr_future = IntrBySignalFuture { task: task.clone(), mask };
+    let result = Select2Futures::new(sleep_future, intr_future).await;
+    task.set_running();
+    match result {
+        SelectOutput::Output1(_) => Ok(0),
+        SelectOutput::Output2(_) => {
+            if rem_ptr != 0 {
+                let remain = expire.saturating_sub(get_current_time_duration());
+                UserPtrRaw::new(rem_ptr as *mut TimeSpec)
+                    .ensure_write(&mut task.get_vm_space().lock())
+                    .ok_or(SysError::EFAULT)?
+                    .write(remain.into());
+            }
+            Err(SysError::EINTR)
         }
     }
 }
@@ -484,8 +477,10 @@ pub fn sys_adjtimex(timex: usize) -> SysResult {
             ret = add_offset(&delta)?;
         }
         if modes.contains(TimexModes::ADJ_TICK) {
+            // within 10% of the length of a tick
             let tick = timex.kt_tick;
-            if tick < 9000 || tick > 11000 {
+            let tick_usec = tick_usec() as i64;
+            if tick < tick_usec * 9 / 10 || tick > tick_usec * 11 / 10 {
                 return Err(SysError::EINVAL);
             }
         }
@@ -523,11 +518,12 @@ pub fn sys_adjtimex(timex: usize) -> SysResult {
         .ok_or(SysError::EFAULT)?;
     if modes == 0 {
         unsafe { w_timex.write(TIMEX) };
+        w_timex.to_mut().kt_tick = tick_usec() as i64;
         return Ok(0);
     }
 
     let status = do_adjtimex(&mut r_timex)?;
-    w_timex.to_mut().kt_tick = 10000;
+    w_timex.to_mut().kt_tick = tick_usec() as i64;
     unsafe {
         TIMEX.clone_from(w_timex.to_mut());
     }
@@ -695,52 +691,29 @@ pub fn sys_timer_getoverrun(timerid: TimerId) -> SysResult {
     })
 }
 
-pub fn sys_timerfd_create(clockid: usize, flags: usize) -> SysResult {
+pub fn sys_timerfd_create(clockid: usize, flags: u32) -> SysResult {
     log::info!("timerfd_create clock_id is {}, flags is {}", clockid, flags);
     if clockid != CLOCK_MONOTONIC && clockid != CLOCK_REALTIME && clockid != CLOCK_BOOTTIME{
         return Err(SysError::EINVAL);
     }
-    if (flags as isize) < 0 {
-        return Err(SysError::EINVAL);
-    }
-    const TFD_NONBLOCK: i32= 0x800;
-    const TFD_CLOEXEC: i32 = 0x1;
+    let flags = TimerFdFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
     let current = current_task().unwrap();
-    let timer = Arc::new(Mutex::new(TimerFd {
-        task: Arc::downgrade(&current),
-        interval: Duration::ZERO,
-        next_expire: Duration::ZERO,
-        interval_id: current.alloc_timer_id(),
-        expirations: 0,
-        wait_future: None,
-    }));
-    let nonblock = (flags as i32 & TFD_NONBLOCK) != 0;
-    let cloexec = (flags as i32 & TFD_CLOEXEC) != 0;
     // Create a new file object for timerfd
     let dentry = TmpDentry::new("", None);
-    let inode = TmpSysInode::new(InodeMode::FILE, Arc::new(EmptyFile {}));
+    let inode = TmpSysInode::new(None, InodeMode::FILE, Arc::new(EmptyFile {}));
     dentry.set_inode(inode);
-    let file = Arc::new(TimerFdFile {
-         file_inner: FileInner {
-                dentry: dentry,
-                offset: AtomicUsize::new(0),
-                flags: SpinNoIrqLock::new({
-                    let mut oflags = OpenFlags::empty();
-                    if nonblock {
-                        oflags.insert(OpenFlags::O_NONBLOCK);
-                    }
-                    if cloexec {
-                        oflags.insert(OpenFlags::O_CLOEXEC);
-                    }
-                    OpenFlags::from_bits_truncate(flags as i32)
-                }),
-            },
-        timer: timer,
-    });
+    let file = TimerFdFile::new(
+        FileInner {
+            dentry: dentry,
+            offset: AtomicUsize::new(0),
+            flags: SpinNoIrqLock::new(flags.into()),
+        },
+        clockid,
+    );
 
     current.with_mut_fd_table(|table| {
         let fd = table.alloc_fd()?;
-        let flags = if cloexec {
+        let flags = if flags.contains(TimerFdFlags::TFD_CLOEXEC) {
             FdFlags::CLOEXEC
         } else {
             FdFlags::empty()
@@ -754,8 +727,9 @@ pub fn sys_timerfd_create(clockid: usize, flags: usize) -> SysResult {
     })
 }
 
-pub fn sys_timerfd_settime(fd: usize, _flags: usize, new_value_ptr: usize, old_value_ptr: usize) -> SysResult {
+pub fn sys_timerfd_settime(fd: usize, flags: u32, new_value_ptr: usize, old_value_ptr: usize) -> SysResult {
     let task = current_task().unwrap();
+    let flags = TimerFdSetFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
     let file = task.with_fd_table(|table| {
         table.get_file(fd)
     })?.downcast_arc::<TimerFdFile>().map_err(|_| SysError::EINVAL)?;
@@ -778,24 +752,9 @@ pub fn sys_timerfd_settime(fd: usize, _flags: usize, new_value_ptr: usize, old_v
         None
     };
 
-    let mut timer = file.timer.lock();
-
+    let old_spec = file.settime(flags, &new_value);
     if let Some(old_value) = old_value {
-        old_value.write(ITimerSpec {
-            it_interval: timer.interval.into(),
-            it_value: timer.next_expire.saturating_sub(get_current_time_duration()).into(),
-        });
-    }
-
-    timer.interval = new_value.it_interval.into();
-
-    let now = get_current_time_duration();
-    if !new_value.it_value.is_zero() {
-        let next_expire = now + new_value.it_value.into();
-        timer.next_expire = next_expire;
-        TIMER_MANAGER.add_timer(Timer::new(next_expire, Box::new(TimerFdEvent(Arc::clone(&file.timer)))))
-    }else {
-        timer.next_expire = Duration::ZERO;
+        old_value.write(old_spec);
     }
     Ok(0)
 }
@@ -806,16 +765,11 @@ pub fn sys_timerfd_gettime(fd: usize, curr_value_ptr: usize) -> SysResult{
         table.get_file(fd)
     })?.downcast_arc::<TimerFdFile>().map_err(|_| SysError::EINVAL)?;
 
-    let timer = file.timer.lock();
-    let now = get_current_time_duration();
     let curr_value_user_ptr = UserPtrRaw::new(curr_value_ptr as *mut ITimerSpec)
         .ensure_write(&mut task.get_vm_space().lock())
         .ok_or(SysError::EFAULT)?;
     
-    curr_value_user_ptr.write(ITimerSpec {
-        it_interval: timer.interval.into(),
-        it_value: timer.next_expire.saturating_sub(now).into(),
-    });
+    curr_value_user_ptr.write(file.gettime());
     Ok(0)
 
 }
\ No newline at end of file
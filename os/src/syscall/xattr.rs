@@ -0,0 +1,200 @@
+//! extended attribute related syscall
+
+use alloc::{string::{String, ToString}, sync::Arc, vec::Vec};
+
+use crate::{fs::{inotify::{notify_inode, InotifyMask}, vfs::{inode::InodeMode, Inode}, AtFlags, XattrFlags, XATTR_LIST_MAX, XATTR_NAME_MAX, XATTR_SIZE_MAX}, mm::{UserPtrRaw, UserSliceRaw}, task::{current_task, task::TaskControlBlock}};
+
+use super::{at_helper, SysError, SysResult, R_OK, W_OK};
+
+/// the namespaces an attribute name may start with
+const XATTR_PREFIXES: [&str; 4] = ["security.", "system.", "trusted.", "user."];
+
+/// syscall setxattr
+pub fn sys_setxattr(path: usize, name: usize, value: usize, size: usize, flags: i32) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::empty())?;
+    setxattr_inode(&task, &inode, name, value, size, flags)
+}
+
+/// syscall lsetxattr, on the symlink itself
+pub fn sys_lsetxattr(path: usize, name: usize, value: usize, size: usize, flags: i32) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    setxattr_inode(&task, &inode, name, value, size, flags)
+}
+
+/// syscall fsetxattr
+pub fn sys_fsetxattr(fd: usize, name: usize, value: usize, size: usize, flags: i32) -> SysResult {
+    let (task, inode) = fd_inode(fd)?;
+    setxattr_inode(&task, &inode, name, value, size, flags)
+}
+
+/// syscall getxattr
+pub fn sys_getxattr(path: usize, name: usize, value: usize, size: usize) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::empty())?;
+    getxattr_inode(&task, &inode, name, value, size)
+}
+
+/// syscall lgetxattr, on the symlink itself
+pub fn sys_lgetxattr(path: usize, name: usize, value: usize, size: usize) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    getxattr_inode(&task, &inode, name, value, size)
+}
+
+/// syscall fgetxattr
+pub fn sys_fgetxattr(fd: usize, name: usize, value: usize, size: usize) -> SysResult {
+    let (task, inode) = fd_inode(fd)?;
+    getxattr_inode(&task, &inode, name, value, size)
+}
+
+/// syscall listxattr
+pub fn sys_listxattr(path: usize, list: usize, size: usize) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::empty())?;
+    listxattr_inode(&task, &inode, list, size)
+}
+
+/// syscall llistxattr, on the symlink itself
+pub fn sys_llistxattr(path: usize, list: usize, size: usize) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    listxattr_inode(&task, &inode, list, size)
+}
+
+/// syscall flistxattr
+pub fn sys_flistxattr(fd: usize, list: usize, size: usize) -> SysResult {
+    let (task, inode) = fd_inode(fd)?;
+    listxattr_inode(&task, &inode, list, size)
+}
+
+/// syscall removexattr
+pub fn sys_removexattr(path: usize, name: usize) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::empty())?;
+    removexattr_inode(&task, &inode, name)
+}
+
+/// syscall lremovexattr, on the symlink itself
+pub fn sys_lremovexattr(path: usize, name: usize) -> SysResult {
+    let (task, inode) = path_inode(path, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+    removexattr_inode(&task, &inode, name)
+}
+
+/// syscall fremovexattr
+pub fn sys_fremovexattr(fd: usize, name: usize) -> SysResult {
+    let (task, inode) = fd_inode(fd)?;
+    removexattr_inode(&task, &inode, name)
+}
+
+fn path_inode(path: usize, flags: AtFlags) -> Result<(Arc<TaskControlBlock>, Arc<dyn Inode>), SysError> {
+    let task = current_task().unwrap().clone();
+    let dentry = at_helper(task.clone(), AtFlags::AT_FDCWD.bits() as isize, path as *const u8, flags)?;
+    if dentry.is_negative() {
+        return Err(SysError::ENOENT);
+    }
+    let inode = dentry.inode().ok_or(SysError::ENOENT)?;
+    Ok((task, inode))
+}
+
+fn fd_inode(fd: usize) -> Result<(Arc<TaskControlBlock>, Arc<dyn Inode>), SysError> {
+    let task = current_task().unwrap().clone();
+    let file = task.with_fd_table(|t| t.get_file(fd))?;
+    let inode = file.inode()?;
+    Ok((task, inode))
+}
+
+/// read the attribute name from the user, it must be in a known namespace
+fn xattr_name(task: &Arc<TaskControlBlock>, name: usize) -> Result<String, SysError> {
+    let name = UserPtrRaw::new(name as *const u8)
+        .cstr_slice(&mut task.get_vm_space().lock())?
+        .to_str()
+        .map_err(|_| SysError::EINVAL)?
+        .to_string();
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return Err(SysError::ERANGE);
+    }
+    if !XATTR_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+        return Err(SysError::EOPNOTSUPP);
+    }
+    Ok(name)
+}
+
+/// whether the task may read (`mask` R_OK) or write (W_OK) the attribute `name`:
+/// trusted ones are for root only, user ones follow the permission of the file
+/// and only regular files and directories have them
+fn xattr_permission(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, name: &str, mask: i32) -> Result<(), SysError> {
+    if name.starts_with("trusted.") && task.euid() != 0 {
+        return Err(SysError::EPERM);
+    }
+    if name.starts_with("user.") {
+        let ty = inode.inode_type();
+        if ty != InodeMode::FILE && ty != InodeMode::DIR {
+            return Err(if mask == W_OK { SysError::EPERM } else { SysError::ENODATA });
+        }
+        inode.inode_inner().permission(task.euid() as u32, task.egid() as u32, mask)?;
+    }
+    Ok(())
+}
+
+/// hand `data` to the user buffer of `size` bytes, or only tell its length when `size` is 0
+fn copy_xattr_out(task: &Arc<TaskControlBlock>, data: &[u8], buf: usize, size: usize) -> SysResult {
+    if size == 0 {
+        return Ok(data.len() as isize);
+    }
+    if size < data.len() {
+        return Err(SysError::ERANGE);
+    }
+    if !data.is_empty() {
+        let user_buf = UserSliceRaw::new(buf as *mut u8, data.len())
+            .ensure_write(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?;
+        user_buf.to_mut().copy_from_slice(data);
+    }
+    Ok(data.len() as isize)
+}
+
+fn setxattr_inode(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, name: usize, value: usize, size: usize, flags: i32) -> SysResult {
+    let flags = XattrFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let name = xattr_name(task, name)?;
+    if size > XATTR_SIZE_MAX {
+        return Err(SysError::E2BIG);
+    }
+    let value: Vec<u8> = if size == 0 {
+        Vec::new()
+    } else {
+        UserSliceRaw::new(value as *mut u8, size)
+            .ensure_read(&mut task.get_vm_space().lock())
+            .ok_or(SysError::EFAULT)?
+            .to_ref()
+            .to_vec()
+    };
+    log::info!("[sys_setxattr] task {} set {} of {} bytes, flags {:?}", task.tid(), name, size, flags);
+    xattr_permission(task, inode, &name, W_OK)?;
+    inode.set_xattr(&name, &value, flags)?;
+    notify_inode(inode, InotifyMask::IN_ATTRIB);
+    Ok(0)
+}
+
+fn getxattr_inode(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, name: usize, value: usize, size: usize) -> SysResult {
+    let name = xattr_name(task, name)?;
+    xattr_permission(task, inode, &name, R_OK)?;
+    let data = inode.get_xattr(&name)?;
+    copy_xattr_out(task, &data, value, size)
+}
+
+fn listxattr_inode(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, list: usize, size: usize) -> SysResult {
+    let is_root = task.euid() == 0;
+    // the names each ended by a NUL, without those the caller may not see
+    let names: Vec<u8> = inode.list_xattr()?
+        .iter()
+        .filter(|name| is_root || !name.starts_with("trusted."))
+        .flat_map(|name| name.bytes().chain(Some(0)))
+        .collect();
+    if names.len() > XATTR_LIST_MAX {
+        return Err(SysError::E2BIG);
+    }
+    copy_xattr_out(task, &names, list, size)
+}
+
+fn removexattr_inode(task: &Arc<TaskControlBlock>, inode: &Arc<dyn Inode>, name: usize) -> SysResult {
+    let name = xattr_name(task, name)?;
+    xattr_permission(task, inode, &name, W_OK)?;
+    inode.remove_xattr(&name)?;
+    notify_inode(inode, InotifyMask::IN_ATTRIB);
+    Ok(0)
+}
@@ -0,0 +1,467 @@
+//! core dump of a process killed by a signal
+//!
+//! the core file is an ELF file of type ET_CORE, see core(5):
+//! a PT_NOTE segment holds the registers of every thread (NT_PRSTATUS),
+//! the process info (NT_PRPSINFO) and the mapped files (NT_FILE),
+//! then every vma gets a PT_LOAD segment, with the contents of the
+//! anonymous or writable ones.
+
+use core::mem::size_of;
+
+use alloc::{format, string::{String, ToString}, sync::Arc, vec, vec::Vec};
+use hal::{addr::{PhysAddrHal, PhysPageNumHal, VirtAddrHal, VirtPageNum}, pagetable::MapPerm, signal::{UContext, UContextHal}};
+
+use crate::{config::PAGE_SIZE, fs::{procfs::sys::kernel::CORE_PATTERN, vfs::{dentry::global_find_dentry, inode::InodeMode, DentryState, File}, OpenFlags}, mm::vm::{MapFlags, UserVmAreaView, UserVmFile}, syscall::SysError, timer::{ffi::TimeVal, get_current_time_duration}, utils::block_on};
+
+use super::task::TaskControlBlock;
+
+const ET_CORE: u16 = 4;
+#[cfg(target_arch = "riscv64")]
+const EM_ARCH: u16 = 243;
+#[cfg(target_arch = "loongarch64")]
+const EM_ARCH: u16 = 258;
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_FILE: u32 = 0x46494c45;
+
+/// size of elf_gregset_t: pc and x1 ~ x31
+#[cfg(target_arch = "riscv64")]
+const ELF_NGREG: usize = 32;
+/// size of elf_gregset_t: r0 ~ r31, orig_a0, era, badv and 10 reserved
+#[cfg(target_arch = "loongarch64")]
+const ELF_NGREG: usize = 45;
+
+static ZERO_PAGE: [u8; PAGE_SIZE] = [0; PAGE_SIZE];
+
+#[repr(C)]
+struct Elf64Ehdr {
+    e_ident: [u8; 16],
+    e_type: u16,
+    e_machine: u16,
+    e_version: u32,
+    e_entry: u64,
+    e_phoff: u64,
+    e_shoff: u64,
+    e_flags: u32,
+    e_ehsize: u16,
+    e_phentsize: u16,
+    e_phnum: u16,
+    e_shentsize: u16,
+    e_shnum: u16,
+    e_shstrndx: u16,
+}
+
+#[repr(C)]
+struct Elf64Phdr {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
+
+#[repr(C)]
+struct ElfSigInfo {
+    si_signo: i32,
+    si_code: i32,
+    si_errno: i32,
+}
+
+/// struct elf_prstatus, the padding is spelled out so that
+/// no uninitialized byte reaches the core file
+#[repr(C)]
+struct ElfPrStatus {
+    pr_info: ElfSigInfo,
+    pr_cursig: i16,
+    _pad0: u16,
+    pr_sigpend: usize,
+    pr_sighold: usize,
+    pr_pid: i32,
+    pr_ppid: i32,
+    pr_pgrp: i32,
+    pr_sid: i32,
+    pr_utime: TimeVal,
+    pr_stime: TimeVal,
+    pr_cutime: TimeVal,
+    pr_cstime: TimeVal,
+    pr_reg: [usize; ELF_NGREG],
+    pr_fpvalid: i32,
+    _pad1: u32,
+}
+
+/// struct elf_prpsinfo, with its padding spelled out
+#[repr(C)]
+struct ElfPrPsInfo {
+    pr_state: u8,
+    pr_sname: u8,
+    pr_zomb: u8,
+    pr_nice: i8,
+    _pad0: u32,
+    pr_flag: usize,
+    pr_uid: u32,
+    pr_gid: u32,
+    pr_pid: i32,
+    pr_ppid: i32,
+    pr_pgrp: i32,
+    pr_sid: i32,
+    pr_fname: [u8; 16],
+    pr_psargs: [u8; 80],
+}
+
+fn as_bytes<T>(v: &T) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(v as *const T as *const u8, size_of::<T>()) }
+}
+
+fn align4(buf: &mut Vec<u8>) {
+    buf.resize((buf.len() + 3) & !3, 0);
+}
+
+fn push_note(buf: &mut Vec<u8>, ty: u32, desc: &[u8]) {
+    const NAME: &[u8] = b"CORE\0";
+    buf.extend_from_slice(&(NAME.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&ty.to_le_bytes());
+    buf.extend_from_slice(NAME);
+    align4(buf);
+    buf.extend_from_slice(desc);
+    align4(buf);
+}
+
+/// general registers in the layout of elf_gregset_t
+fn elf_gregs(task: &TaskControlBlock) -> [usize; ELF_NGREG] {
+    let ucontext = UContext::save_current_context(0, task.get_trap_cx());
+    let mut regs = [0; ELF_NGREG];
+    #[cfg(target_arch = "riscv64")]
+    {
+        // the ucontext already keeps pc in place of the zero register
+        regs.copy_from_slice(&ucontext.uc_mcontext.user_x);
+    }
+    #[cfg(target_arch = "loongarch64")]
+    {
+        // the ucontext keeps era in place of the zero register
+        regs[1..32].copy_from_slice(&ucontext.uc_mcontext.user_r[1..]);
+        regs[32] = regs[4];
+        regs[33] = ucontext.uc_mcontext.user_r[0];
+    }
+    regs
+}
+
+fn prstatus(task: &Arc<TaskControlBlock>, signo: usize, ppid: i32) -> ElfPrStatus {
+    let (pending, blocked) = task.with_sig_manager(|sm| (sm.bitmap.bits(), sm.blocked_sigs.bits()));
+    let (utime, stime) = task.time_recorder().time_pair();
+    let (cutime, cstime) = task.time_recorder().child_time_pair();
+    ElfPrStatus {
+        pr_info: ElfSigInfo { si_signo: signo as i32, si_code: 0, si_errno: 0 },
+        pr_cursig: signo as i16,
+        _pad0: 0,
+        pr_sigpend: pending,
+        pr_sighold: blocked,
+        pr_pid: task.tid() as i32,
+        pr_ppid: ppid,
+        pr_pgrp: task.pgid() as i32,
+        pr_sid: 0,
+        pr_utime: utime.into(),
+        pr_stime: stime.into(),
+        pr_cutime: cutime.into(),
+        pr_cstime: cstime.into(),
+        pr_reg: elf_gregs(task),
+        pr_fpvalid: 0,
+        _pad1: 0,
+    }
+}
+
+fn prpsinfo(task: &Arc<TaskControlBlock>, comm: &str, ppid: i32) -> ElfPrPsInfo {
+    let mut info = ElfPrPsInfo {
+        pr_state: 0,
+        pr_sname: b'R',
+        pr_zomb: 0,
+        pr_nice: 0,
+        _pad0: 0,
+        pr_flag: 0,
+        pr_uid: task.euid() as u32,
+        pr_gid: task.egid() as u32,
+        pr_pid: task.pid() as i32,
+        pr_ppid: ppid,
+        pr_pgrp: task.pgid() as i32,
+        pr_sid: 0,
+        pr_fname: [0; 16],
+        pr_psargs: [0; 80],
+    };
+    // both are nul terminated
+    let len = comm.len().min(15);
+    info.pr_fname[..len].copy_from_slice(&comm.as_bytes()[..len]);
+    let len = comm.len().min(79);
+    info.pr_psargs[..len].copy_from_slice(&comm.as_bytes()[..len]);
+    info
+}
+
+/// NT_FILE: count, page size, then (start, end, offset in pages) for
+/// each file mapping, then the nul terminated names
+fn nt_file(vmas: &[UserVmAreaView]) -> Vec<u8> {
+    let files: Vec<(&UserVmAreaView, String)> = vmas.iter()
+        .filter_map(|vma| match &vma.file {
+            UserVmFile::File(file) => file.dentry().map(|d| (vma, d.path())),
+            _ => None,
+        })
+        .collect();
+    let mut desc = Vec::new();
+    desc.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    desc.extend_from_slice(&(PAGE_SIZE as u64).to_le_bytes());
+    for (vma, _) in files.iter() {
+        desc.extend_from_slice(&(vma.range_va.start.0 as u64).to_le_bytes());
+        desc.extend_from_slice(&(vma.range_va.end.0 as u64).to_le_bytes());
+        desc.extend_from_slice(&((vma.offset / PAGE_SIZE) as u64).to_le_bytes());
+    }
+    for (_, path) in files.iter() {
+        desc.extend_from_slice(path.as_bytes());
+        desc.push(0);
+    }
+    desc
+}
+
+/// file backed read only mappings can be found in the files themselves,
+/// secret memory is out of the kernel's reach
+fn should_dump(vma: &UserVmAreaView) -> bool {
+    vma.map_perm.contains(MapPerm::R)
+        && (!vma.file.is_file() || vma.map_perm.contains(MapPerm::W))
+        && !vma.map_flags.contains(MapFlags::SECRET)
+}
+
+fn segment_flags(perm: MapPerm) -> u32 {
+    let mut flags = 0;
+    if perm.contains(MapPerm::R) {
+        flags |= PF_R;
+    }
+    if perm.contains(MapPerm::W) {
+        flags |= PF_W;
+    }
+    if perm.contains(MapPerm::X) {
+        flags |= PF_X;
+    }
+    flags
+}
+
+/// expand the specifiers of core_pattern, see core(5)
+fn expand_pattern(pattern: &str, task: &Arc<TaskControlBlock>, comm: &str, signo: usize) -> String {
+    let mut name = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            name.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => name.push('%'),
+            Some('p') | Some('P') => name += &task.pid().to_string(),
+            Some('i') | Some('I') => name += &task.tid().to_string(),
+            Some('e') => name += comm,
+            Some('s') => name += &signo.to_string(),
+            Some('t') => name += &get_current_time_duration().as_secs().to_string(),
+            Some('u') => name += &task.ruid().to_string(),
+            Some('g') => name += &task.rgid().to_string(),
+            Some('h') => name += "chronix",
+            Some(c) => log::warn!("[coredump] unsupported specifier %{} in core_pattern", c),
+            None => {}
+        }
+    }
+    name
+}
+
+/// create the core file, or truncate an existing regular file
+fn create_core_file(path: &str) -> Result<Arc<dyn File>, SysError> {
+    let dentry = global_find_dentry(path)?;
+    if dentry.state() == DentryState::NEGATIVE {
+        let parent = dentry.parent().ok_or(SysError::ENOENT)?;
+        let inode = parent.inode().ok_or(SysError::ENOENT)?.create(dentry.name(), InodeMode::FILE)?;
+        dentry.set_inode(inode);
+        parent.add_child(dentry.clone());
+    } else {
+        let inode = dentry.inode().ok_or(SysError::ENOENT)?;
+        if inode.inode_type() != InodeMode::FILE {
+            return Err(SysError::EISDIR);
+        }
+        inode.truncate(0)?;
+    }
+    dentry.open(OpenFlags::O_WRONLY).ok_or(SysError::EACCES)
+}
+
+/// writes the core file sequentially, truncating it at RLIMIT_CORE
+struct CoreWriter {
+    file: Arc<dyn File>,
+    written: usize,
+    limit: usize,
+}
+
+impl CoreWriter {
+    /// false once the limit is reached or the file can not take more
+    fn write(&mut self, buf: &[u8]) -> bool {
+        let len = buf.len().min(self.limit - self.written);
+        if len == 0 {
+            return buf.is_empty();
+        }
+        match block_on(self.file.write(&buf[..len])) {
+            Ok(n) => {
+                self.written += n;
+                n == buf.len()
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn pad_to(&mut self, offset: usize) -> bool {
+        while self.written < offset {
+            let len = (offset - self.written).min(PAGE_SIZE);
+            if !self.write(&ZERO_PAGE[..len]) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// write the contents of a vma, pages never touched are read as zero
+fn dump_vma(task: &TaskControlBlock, vma: &UserVmAreaView, writer: &mut CoreWriter) -> bool {
+    let mut page = vec![0u8; PAGE_SIZE];
+    let range: core::ops::Range<VirtPageNum> = vma.range_va.start.floor()..vma.range_va.end.ceil();
+    for vpn in range {
+        let present = task.with_vm_space(|vm| {
+            vm.translate_vpn(vpn).map(|ppn| page.copy_from_slice(ppn.start_addr().get_slice::<u8>(PAGE_SIZE)))
+        }).is_some();
+        let data = if present { &page[..] } else { &ZERO_PAGE[..] };
+        if !writer.write(data) {
+            return false;
+        }
+    }
+    true
+}
+
+/// dump the thread group of `task` killed by `signo`,
+/// return whether a core file was written
+pub fn do_coredump(task: &Arc<TaskControlBlock>, signo: usize) -> bool {
+    let limit = task.with_core_limit(|limit| limit.rlim_cur);
+    if limit == 0 {
+        return false;
+    }
+    let pattern = CORE_PATTERN.lock().clone();
+    if pattern.is_empty() {
+        return false;
+    }
+    if pattern.starts_with('|') {
+        log::warn!("[coredump] piping the core to a program is not supported");
+        return false;
+    }
+
+    let comm = task.elf.lock().as_ref()
+        .and_then(|elf| elf.dentry())
+        .map(|dentry| dentry.name().to_string())
+        .unwrap_or_default();
+    let name = expand_pattern(&pattern, task, &comm, signo);
+    let path = if name.starts_with('/') {
+        name
+    } else {
+        let cwd = task.cwd().path();
+        format!("{}/{}", cwd.trim_end_matches('/'), name)
+    };
+    let file = match create_core_file(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            log::warn!("[coredump] can not create {}: {:?}", path, e);
+            return false;
+        }
+    };
+
+    let ppid = task.get_leader().parent()
+        .and_then(|parent| parent.upgrade())
+        .map(|parent| parent.pid() as i32)
+        .unwrap_or(0);
+    // the dumping thread goes first, debuggers take it as the crashed one
+    let mut threads = vec![task.clone()];
+    task.with_thread_group(|tg| {
+        threads.extend(tg.iter().filter(|t| !Arc::ptr_eq(t, task)))
+    });
+    let vmas = task.with_vm_space(|vm| vm.area_views());
+
+    let mut notes = Vec::new();
+    for thread in threads.iter() {
+        push_note(&mut notes, NT_PRSTATUS, as_bytes(&prstatus(thread, signo, ppid)));
+    }
+    push_note(&mut notes, NT_PRPSINFO, as_bytes(&prpsinfo(task, &comm, ppid)));
+    push_note(&mut notes, NT_FILE, &nt_file(&vmas));
+
+    let phnum = vmas.len() + 1;
+    let notes_offset = size_of::<Elf64Ehdr>() + phnum * size_of::<Elf64Phdr>();
+    let data_offset = (notes_offset + notes.len() + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+
+    let mut e_ident = [0u8; 16];
+    // ELFMAG, ELFCLASS64, ELFDATA2LSB, EV_CURRENT
+    e_ident[..7].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1]);
+    let ehdr = Elf64Ehdr {
+        e_ident,
+        e_type: ET_CORE,
+        e_machine: EM_ARCH,
+        e_version: 1,
+        e_entry: 0,
+        e_phoff: size_of::<Elf64Ehdr>() as u64,
+        e_shoff: 0,
+        e_flags: 0,
+        e_ehsize: size_of::<Elf64Ehdr>() as u16,
+        e_phentsize: size_of::<Elf64Phdr>() as u16,
+        e_phnum: phnum as u16,
+        e_shentsize: 0,
+        e_shnum: 0,
+        e_shstrndx: 0,
+    };
+    let mut headers = Vec::with_capacity(notes_offset);
+    headers.extend_from_slice(as_bytes(&ehdr));
+    let note_phdr = Elf64Phdr {
+        p_type: PT_NOTE,
+        p_flags: 0,
+        p_offset: notes_offset as u64,
+        p_vaddr: 0,
+        p_paddr: 0,
+        p_filesz: notes.len() as u64,
+        p_memsz: 0,
+        p_align: 4,
+    };
+    headers.extend_from_slice(as_bytes(&note_phdr));
+    let mut offset = data_offset;
+    for vma in vmas.iter() {
+        let size = vma.range_va.end.0 - vma.range_va.start.0;
+        let filesz = if should_dump(vma) { size } else { 0 };
+        let phdr = Elf64Phdr {
+            p_type: PT_LOAD,
+            p_flags: segment_flags(vma.map_perm),
+            p_offset: offset as u64,
+            p_vaddr: vma.range_va.start.0 as u64,
+            p_paddr: 0,
+            p_filesz: filesz as u64,
+            p_memsz: size as u64,
+            p_align: PAGE_SIZE as u64,
+        };
+        headers.extend_from_slice(as_bytes(&phdr));
+        offset += filesz;
+    }
+
+    log::info!("[coredump] task {} dumping core to {}", task.tid(), path);
+    let mut writer = CoreWriter { file, written: 0, limit };
+    let complete = writer.write(&headers)
+        && writer.write(&notes)
+        && writer.pad_to(data_offset)
+        && vmas.iter()
+            .filter(|vma| should_dump(vma))
+            .all(|vma| dump_vma(task, vma, &mut writer));
+    if !complete {
+        log::info!("[coredump] core truncated at {} bytes", writer.written);
+    }
+    true
+}
@@ -1,24 +1,31 @@
 //! file system support for Task
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{format, string::{String, ToString}, sync::Arc, vec::Vec};
 use fatfs::info;
-use log::warn;
 
-use crate::{fs::{devfs::tty::TTY, vfs::{Dentry, File}, OpenFlags, Stdin}, syscall::{misc::RLimit, SysError}, task::current_task};
+use crate::{fs::{devfs::tty::TTY, mount::{is_below, path_in_root}, vfs::{lock::release_file_locks, Dentry, File}, OpenFlags, Stdin}, syscall::{misc::RLimit, SysError}, task::current_task};
 
 use super::task::TaskControlBlock;
 
 #[derive(Clone)]
-/// the fd table
+/// the fd table, it grows on demand up to the RLIMIT_NOFILE soft limit
 pub struct FdTable {
     /// the inner table
     pub fd_table: Vec<Option<FdInfo>>,
     /// resource limit: max fds
     pub rlimit: RLimit,
+    /// every fd below it is in use, allocation starts looking here
+    first_free: usize,
 }
 
-/// Max file descriptors counts
+/// Default soft limit of file descriptors
 pub const MAX_FDS: usize = 1024;
+/// Default hard limit of file descriptors
+pub const MAX_FDS_HARD: usize = 4096;
+/// Ceiling of RLIMIT_NOFILE, the default fs.nr_open of linux
+pub const NR_OPEN: usize = 1024 * 1024;
+/// slots of the table when it first grows past the standard fds
+const FD_TABLE_INIT_LEN: usize = 16;
 
 impl FdTable {
     /// new and init fd table
@@ -41,41 +48,60 @@ impl FdTable {
         
         Self { 
             fd_table: table,
-            rlimit: RLimit { rlim_cur: MAX_FDS, rlim_max: MAX_FDS }
+            rlimit: RLimit { rlim_cur: MAX_FDS, rlim_max: MAX_FDS_HARD },
+            first_free: 3,
         }
     }
-    /// allocate a new fd for the task
-    /// will not expend the fd table
-    pub fn alloc_fd(&mut self) -> Result<usize, SysError> {
-        if let Some (fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            Ok(fd)
-        } else if self.fd_table.len() < self.rlimit.rlim_max {
-            self.fd_table.push(None);
-            Ok(self.fd_table.len() - 1)
-        } else {
-            Err(SysError::EMFILE)
-        }
+    /// no fd may reach the soft limit
+    fn limit(&self) -> usize {
+        self.rlimit.rlim_cur.min(NR_OPEN)
     }
-    /// allocate a new fd greater or equal to given bound
-    /// expend the table if the max fd is not enough
-    pub fn alloc_fd_from(&mut self, bound: usize) -> Result<usize, SysError> {
-        if bound > self.rlimit.rlim_max {
-            return Err(SysError::EMFILE)
+    /// grow the table to at least `min_len` slots,
+    /// doubling its size but never past the limit
+    fn expand(&mut self, min_len: usize) {
+        if self.fd_table.len() >= min_len {
+            return;
         }
-
-        if self.fd_table.len() <= bound {
-            // expand the fd table
-            self.fd_table.resize(bound + 1, None);
+        let new_len = (self.fd_table.len() * 2)
+            .max(FD_TABLE_INIT_LEN)
+            .max(min_len)
+            .min(self.limit());
+        self.fd_table.resize(new_len, None);
+    }
+    /// find the lowest free fd greater or equal to `bound`,
+    /// grow the table when every slot is taken
+    fn find_free(&mut self, bound: usize) -> Result<usize, SysError> {
+        let limit = self.limit();
+        let start = bound.max(self.first_free);
+        let end = self.fd_table.len().min(limit);
+        let fd = match (start..end).find(|&fd| self.fd_table[fd].is_none()) {
+            Some(fd) => fd,
+            None => {
+                let fd = start.max(end);
+                if fd >= limit {
+                    return Err(SysError::EMFILE);
+                }
+                self.expand(fd + 1);
+                fd
+            }
+        };
+        if bound <= self.first_free {
+            // the search started at the hint, so everything before fd is in use
+            self.first_free = fd;
         }
-        if let Some(fd) = (bound..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            Ok(fd)
-        } else if self.fd_table.len() < self.rlimit.rlim_max {
-            // no space, append to end
-            self.fd_table.push(None);
-            Ok(self.fd_table.len() - 1)
-        } else {
-            return Err(SysError::EMFILE)
+        Ok(fd)
+    }
+    /// allocate the lowest free fd for the task
+    /// the slot is taken once a file is put into it
+    pub fn alloc_fd(&mut self) -> Result<usize, SysError> {
+        self.find_free(0)
+    }
+    /// allocate the lowest free fd greater or equal to given bound
+    pub fn alloc_fd_from(&mut self, bound: usize) -> Result<usize, SysError> {
+        if bound >= self.limit() {
+            return Err(SysError::EINVAL)
         }
+        self.find_free(bound)
     }
     /// get the fd_info using fd
     pub fn get_fd_info(&self, fd: usize) -> Result<FdInfo, SysError> {
@@ -134,6 +160,7 @@ impl FdTable {
             return Err(SysError::EBADF);
         } else {
             self.fd_table[fd] = None;
+            self.first_free = self.first_free.min(fd);
             Ok(())
         } 
     }
@@ -162,9 +189,10 @@ impl FdTable {
     /// new fd will use the given flags
     pub fn dup3(&mut self, old_fd: usize, new_fd: usize, flags: FdFlags) -> Result<usize, SysError> {
         let file = self.get_file(old_fd)?;
-        if self.fd_table.len() <= new_fd {
-            self.fd_table.resize(new_fd.checked_add(1).ok_or(SysError::EMFILE)?, None);
+        if new_fd >= self.limit() {
+            return Err(SysError::EBADF);
         }
+        self.expand(new_fd + 1);
         self.fd_table[new_fd] = Some(FdInfo {file, flags});
         Ok(new_fd)
     }
@@ -172,23 +200,32 @@ impl FdTable {
     /// new fd will use the old fd's flag
     pub fn dup3_with_flags(&mut self, old_fd: usize, new_fd: usize) -> Result<usize, SysError> {
         let fd_info = self.get_fd_info(old_fd)?;
-        if self.fd_table.len() <= new_fd {
-            self.fd_table.resize(new_fd.checked_add(1).ok_or(SysError::EMFILE)?, None);
+        if new_fd >= self.limit() {
+            return Err(SysError::EBADF);
         }
+        self.expand(new_fd + 1);
         self.fd_table[new_fd] = Some(fd_info);
         Ok(new_fd)
     }
+    /// close every fd of an exiting process `pid`,
+    /// releasing the file locks held through them
+    pub fn close_all(&mut self, pid: usize) {
+        while let Some(fd_info) = self.fd_table.pop() {
+            if let Some(fd_info) = fd_info {
+                release_file_locks(fd_info.file, pid);
+            }
+        }
+        self.first_free = 0;
+    }
     /// get rlimit
     pub fn rlimit(&self) -> RLimit {
         self.rlimit
     }
     /// set rlimit
+    /// fds already open above a lowered limit stay open,
+    /// only new allocations are refused
     pub fn set_rlimit(&mut self, rlimit: RLimit) {
         self.rlimit = rlimit;
-        if rlimit.rlim_max <= self.fd_table.len() {
-            warn!("[Fd table]: set limit smaller than table size, truncate");
-            self.fd_table.truncate(rlimit.rlim_max);
-        }
     }
     /// handle close-on-exec flag
     pub fn do_close_on_exec(&mut self) {
@@ -202,6 +239,7 @@ impl FdTable {
                 }
             }
         }
+        self.first_free = self.fd_table.iter().position(|fd| fd.is_none()).unwrap_or(self.fd_table.len());
     }
 }
 
@@ -256,6 +294,64 @@ impl TaskControlBlock {
         log::info!("switching task {}'s cwd to {}", self.gettid(), dentry.path());
         *self.cwd.lock() = dentry;
     }
+    /// get the root dir
+    pub fn root(&self) -> Arc<dyn Dentry> {
+        self.root.lock().clone()
+    }
+    /// change the root dir
+    pub fn set_root(&self, dentry: Arc<dyn Dentry>) {
+        log::info!("switching task {}'s root to {}", self.gettid(), dentry.path());
+        *self.root.lock() = dentry;
+    }
+    /// the absolute path from the global root of an absolute path the task gives
+    pub fn global_path(&self, path: &str) -> String {
+        let root = self.root().path();
+        match (root.as_str(), path) {
+            ("/", path) => path.to_string(),
+            (root, "/") => root.to_string(),
+            (root, path) => format!("{}{}", root, path),
+        }
+    }
+    /// the absolute path from the global root of `path` taken from the directory `dir`,
+    /// `..` at the root of the task stays at the root
+    pub fn global_path_at(&self, dir: &Arc<dyn Dentry>, path: &str) -> String {
+        let root = self.root().path();
+        let dir_path = dir.path();
+        if !path.starts_with('/') && !is_below(&dir_path, &root) {
+            // a directory left outside the root, as chroot leaves the cwd
+            return normalize_path(&dir_path, path);
+        }
+        let base = match path.starts_with('/') {
+            true => String::from("/"),
+            false => path_in_root(&dir_path, &root),
+        };
+        self.global_path(&normalize_path(&base, path))
+    }
+    /// the absolute path from the global root the symlink points to, seen from the task
+    pub fn link_path(&self, link: &Arc<dyn Dentry>) -> Result<String, SysError> {
+        let target = link.inode().ok_or(SysError::ENOENT)?.readlink()?;
+        let parent = link.parent().ok_or(SysError::ENOENT)?;
+        Ok(self.global_path_at(&parent, &target))
+    }
+    /// the absolute path of a dentry as the task sees it
+    pub fn visible_path(&self, dentry: &Arc<dyn Dentry>) -> String {
+        path_in_root(&dentry.path(), &self.root().path())
+    }
     
     
-}
\ No newline at end of file
+}
+
+/// join the relative path to the absolute base, `..` at `/` stays there
+fn normalize_path(base: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in base.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            name => parts.push(name),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
@@ -0,0 +1,57 @@
+//! the load averages: the number of tasks running, ready to run
+//! or in an uninterruptible sleep, averaged over 1, 5 and 15 minutes
+//! the way Linux does, in fixed point with FSHIFT bits of fraction
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::timer::get_current_time_ms;
+
+use super::{manager::TASK_MANAGER, task::TaskStatus};
+
+/// bits of fraction of the load averages
+pub const FSHIFT: usize = 11;
+/// 1.0 in fixed point
+pub const FIXED_1: usize = 1 << FSHIFT;
+/// the tasks are counted every 5 seconds
+const LOAD_FREQ_MS: usize = 5_000;
+/// 1/exp(5sec/1min), 1/exp(5sec/5min) and 1/exp(5sec/15min) in fixed point
+const EXP: [usize; 3] = [1884, 2014, 2037];
+
+static AVENRUN: [AtomicUsize; 3] = [AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)];
+/// when the tasks are counted next, in milliseconds since boot
+static NEXT_SAMPLE: AtomicUsize = AtomicUsize::new(LOAD_FREQ_MS);
+
+/// one step of the exponential decay of `load` towards `active`
+fn calc_load(load: usize, exp: usize, active: usize) -> usize {
+    let mut new_load = load * exp + active * (FIXED_1 - exp);
+    // round up while the load grows, so that it can reach a steady load
+    if active >= load {
+        new_load += FIXED_1 - 1;
+    }
+    new_load / FIXED_1
+}
+
+/// called on each timer tick, on any processor:
+/// once the period is over the first one to see it counts the tasks
+pub fn calc_global_load() {
+    let now = get_current_time_ms();
+    let next = NEXT_SAMPLE.load(Ordering::Relaxed);
+    if now < next {
+        return;
+    }
+    if NEXT_SAMPLE.compare_exchange(next, now + LOAD_FREQ_MS, Ordering::Relaxed, Ordering::Relaxed).is_err() {
+        return;
+    }
+    let active = TASK_MANAGER.count_tasks(|task| matches!(
+        task.get_status(),
+        TaskStatus::Running | TaskStatus::Ready | TaskStatus::UnInterruptable
+    )) * FIXED_1;
+    for (avg, exp) in AVENRUN.iter().zip(EXP) {
+        avg.store(calc_load(avg.load(Ordering::Relaxed), exp, active), Ordering::Relaxed);
+    }
+}
+
+/// the 1, 5 and 15 minute load averages, with FSHIFT bits of fraction
+pub fn load_averages() -> [usize; 3] {
+    [0, 1, 2].map(|i| AVENRUN[i].load(Ordering::Relaxed))
+}
@@ -45,6 +45,10 @@ impl TaskManager {
         .map(|task| task.clone())
         .collect()
     }
+    /// number of tasks `pred` holds for, counted under the lock
+    pub fn count_tasks<F: Fn(&Arc<TaskControlBlock>) -> bool>(&self, pred: F) -> usize {
+        self.0.lock().values().filter(|task| pred(task)).count()
+    }
     /// do something for each task
     pub fn for_each_task<F: FnMut(&Arc<TaskControlBlock>)>(&self, mut f: F) {
         for task in self.tasks_group() {
@@ -62,31 +66,42 @@ impl ProcessGroupManager {
     }
     /// initiate a group by group leader
     pub fn add_group(&self, group_leader: &Arc<TaskControlBlock>) {
-        info!("add group with leader {}, now pgid is {}" , group_leader.tid(), group_leader.pgid());
         let pgid = group_leader.tid();
-        group_leader.set_pgid(pgid);
-        info!("add group with leader {}, now pgid is {}" , group_leader.tid(), group_leader.pgid());
-        let mut group = Vec::new();
-        group.push(Arc::downgrade(group_leader));
-        self.0.lock().insert(pgid, group); 
-        info!("insert group {} with leader {}" , pgid, group_leader.tid());
-    }
-    /// add a task to a group
+        self.add_task_to_group(pgid, group_leader);
+        info!("add group {} with leader {}", pgid, group_leader.tid());
+    }
+    /// add a task to a group, leaving the group it was in
     pub fn add_task_to_group(&self,pgid: PGid, task: &Arc<TaskControlBlock>) {
         //info!("add task {} to group {}, processor id {}", task.tid(), pgid, current_processor().id() );
+        let mut groups = self.0.lock();
+        Self::leave_group(&mut groups, task);
         task.set_pgid(pgid);
-        self.0.lock().get_mut(&pgid).unwrap()
-        .push(Arc::downgrade(task));
+        groups.entry(pgid).or_insert_with(Vec::new).push(Arc::downgrade(task));
     }
     /// get a group by pgid
     pub fn get_group(&self, pgid: PGid) -> Option<Vec<Weak<TaskControlBlock>>> {
         self.0.lock().get(&pgid).cloned()
     }
+    /// get the live members of a group
+    pub fn group_members(&self, pgid: PGid) -> Vec<Arc<TaskControlBlock>> {
+        self.0.lock()
+            .get(&pgid)
+            .map_or(Vec::new(), |group| group.iter().filter_map(|t| t.upgrade()).collect())
+    }
     /// remove a task from a group
     pub fn remove(&self, task: &Arc<TaskControlBlock>) {
         //info!("remove task {} from group {}", task.tid(), task.pgid());
-        self.0.lock().get_mut(&task.pgid()).unwrap()
-        .retain(|t|t.upgrade().map_or(false, |inner| Arc::ptr_eq(task, &inner)));
+        Self::leave_group(&mut self.0.lock(), task);
+    }
+    /// drop the task and the dead members from its group, and the group once empty
+    fn leave_group(groups: &mut BTreeMap<PGid, Vec<Weak<TaskControlBlock>>>, task: &Arc<TaskControlBlock>) {
+        let pgid = task.pgid();
+        if let Some(group) = groups.get_mut(&pgid) {
+            group.retain(|t| t.upgrade().map_or(false, |inner| !Arc::ptr_eq(task, &inner)));
+            if group.is_empty() {
+                groups.remove(&pgid);
+            }
+        }
     }
 }
 /// The global task manager
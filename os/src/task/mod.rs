@@ -23,6 +23,8 @@ pub mod manager;
 pub mod utils;
 pub mod fs;
 pub mod signal;
+pub mod coredump;
+pub mod loadavg;
 
 #[allow(clippy::module_inception)]
 #[allow(rustdoc::private_intra_doc_links)]
@@ -40,7 +42,7 @@ use manager::{PROCESS_GROUP_MANAGER, TASK_MANAGER};
 use task::{TaskControlBlock, TaskStatus};
 use log::*;
 
-pub use tid::{tid_alloc, TidAllocator, TidHandle};
+pub use tid::{last_tid, tid_alloc, tid_alloc_specific, TidAllocator, TidHandle};
 pub use crate::processor::processor::{
     current_user_token,current_task,
     Processor,
@@ -90,7 +90,12 @@ pub async fn run_tasks(task: Arc<TaskControlBlock>) {
         // check current task status before return
         match task.get_status() {
             TaskStatus::Zombie => break,
-            TaskStatus::Stopped => suspend_now().await,
+            TaskStatus::Stopped => {
+                suspend_now().await;
+                // handle the signal that woke the stopped task before it runs again
+                task.check_and_handle(false, 0);
+                continue;
+            }
             _ => {}
         }
 
@@ -6,9 +6,9 @@ use alloc::sync::Arc;
 use fatfs::info;
 use hal::{addr::VirtAddr, println, signal::{sigreturn_trampoline_addr, SigStack, UContext, UContextHal}, trap::TrapContextHal};
 
-use crate::{mm::{vm::UserVmSpaceHal, UserPtrRaw}, signal::{KSigAction, LinuxSigInfo, SigAction, SigActionFlag, SigHandler, SigInfo, SigSet, SIGCHLD, SIGKILL, SIGSTOP}, task::INITPROC_PID, trap::trap_return};
+use crate::{mm::{vm::UserVmSpaceHal, UserPtrRaw}, signal::{KSigAction, LinuxSigInfo, SigAction, SigActionFlag, SigHandler, SigInfo, SigSet, SIGCHLD, SIGCONT, SIGKILL, SIGSTOP}, task::INITPROC_PID, syscall::signal::{SS_AUTODISARM, SS_ONSTACK}, trap::trap_return};
 
-use super::task::TaskControlBlock;
+use super::task::{TaskControlBlock, WaitReport};
 
 
 /// for the signal mechanism
@@ -36,9 +36,16 @@ impl TaskControlBlock {
     /// as we may need to wake up a task when wake up signal come
     pub fn recv_sigs(&self, sig: SigInfo) {
         log::info!("[TCB]: tid {} recv signo {:?}", self.gettid(), sig);
+        // SIGCONT resumes a stopped process when it is sent, whatever its disposition
+        if sig.si_signo == SIGCONT {
+            self.continue_stopped();
+        }
         self.with_mut_sig_manager(|manager| {
             manager.receive(sig);
-            if manager.wake_sigs.contain_sig(sig.si_signo) && self.is_interruptable() {
+            if manager.wake_sigs.contain_sig(sig.si_signo)
+                && manager.signalfd_refs[sig.si_signo] == 0
+                && (self.is_interruptable() || self.is_stopped())
+            {
                 //info!("[TCB]: tid {} has been wake up", self.gettid());
                 self.wake();
             } 
@@ -52,39 +59,81 @@ impl TaskControlBlock {
     /// in Process-level, all threads in the same process share the same signal mask
     pub fn recv_sigs_process_level(&self, sig_info: SigInfo) {
         log::info!("[TCB::recv_sigs_process_level]: tid {} recv signo {} at process level",self.tid(),sig_info.si_signo);
-        self.with_mut_thread_group(|tg| {
-            let mut signal_delivered = false;
-            for thread in tg.iter() {
-                if thread.sig_manager.lock().blocked_sigs.contain_sig(sig_info.si_signo) {
-                    continue;
-                }
-                thread.recv_sigs(sig_info);
-                signal_delivered = true;
-                break;
+        // the thread is picked under the lock, receiving may take the lock again
+        let target = self.with_thread_group(|tg| {
+            tg.iter()
+                .find(|thread| !thread.sig_manager.lock().blocked_sigs.contain_sig(sig_info.si_signo))
+                .or_else(|| tg.iter().next())
+        });
+        if let Some(target) = target {
+            target.recv_sigs(sig_info);
+        }
+    }
+
+    /// wake up every stopped thread of the process and report it to the parent
+    fn continue_stopped(&self) {
+        let leader = self.with_mut_thread_group(|tg| {
+            let mut continued = false;
+            for thread in tg.iter().filter(|t| t.is_stopped()) {
+                thread.set_running();
+                thread.wake();
+                continued = true;
             }
-            if !signal_delivered {
-                let task = tg.iter().next().unwrap();
-                task.recv_sigs(sig_info);
+            if !continued {
+                return None;
             }
-        })
+            tg.wait_report = Some(WaitReport::Continued);
+            tg.iter().find(|t| t.is_leader())
+        });
+        if let Some(leader) = leader {
+            leader.signal_parent_stop(SigInfo::CLD_CONTINUED);
+        }
+    }
+
+    /// wake up the threads of this process waiting in wait4 for a child
+    pub fn wake_child_waiters(&self) {
+        let wakers = core::mem::take(&mut self.thread_group.lock().child_wakers);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// let the parent know about a change of this process:
+    /// send it `signo` (nothing for 0) and wake up its waiters in wait4
+    fn signal_parent(self: &Arc<Self>, signo: usize, si_code: i32) {
+        let Some(parent) = self.parent().and_then(|p| p.upgrade()) else {
+            log::error!("no parent !");
+            return;
+        };
+        if signo != 0 {
+            parent.recv_sigs_process_level(
+                SigInfo { si_signo: signo, si_code, si_pid: Some(self.pid()), si_value: 0 }
+            );
+        }
+        parent.wake_child_waiters();
     }
 
     /// child process notify parent
-    /// send SIGCHLD signal to parent
+    /// send the exit signal, usually SIGCHLD, to parent
     /// Let a parent know about the death of a child.
-    /// TODOS: should closer to linux design; si_code;
     pub fn notify_parent(self: &Arc<Self>) {
-        
-        if let Some(parent) = self.parent() {
-            if let Some(parent) = parent.upgrade() {
-                // log::info!("[TCB] task {} notify parent", self.gettid());
-                parent.recv_sigs_process_level(
-                    SigInfo { si_signo: SIGCHLD, si_code: SigInfo::CLD_EXITED, si_pid: Some(self.pid()) }
-                );
-            }else {
-                log::error!("no parent !");
-            }
-        }
+        let (code, exit_signal) = self.with_thread_group(|tg| (tg.group_exit_code, tg.exit_signal));
+        let si_code = match code & 0x7f {
+            0 => SigInfo::CLD_EXITED,
+            _ if code & 0x80 != 0 => SigInfo::CLD_DUMPED,
+            _ => SigInfo::CLD_KILLED,
+        };
+        self.signal_parent(exit_signal, si_code);
+    }
+
+    /// the process stopped or continued, the parent gets SIGCHLD
+    /// unless it set SA_NOCLDSTOP
+    pub fn signal_parent_stop(self: &Arc<Self>, si_code: i32) {
+        let nocldstop = self.parent().and_then(|p| p.upgrade()).map_or(false, |parent| {
+            let flags = parent.sig_manager.lock().sig_handler[SIGCHLD].sa.sa_flags;
+            SigActionFlag::from_bits_truncate(flags).contains(SigActionFlag::SA_NOCLDSTOP)
+        });
+        self.signal_parent(if nocldstop { 0 } else { SIGCHLD }, si_code);
     }
     
     /// signal manager should check the signal queue
@@ -97,13 +146,9 @@ impl TaskControlBlock {
             if let Some(sig) = sig_manager.dequeue_one() {
                 // handle a signal
                 // assert!(sig.si_signo != 0);
-                let sig_action = sig_manager.sig_handler[sig.si_signo];
+                let sig_action = sig_manager.take_action(sig.si_signo);
                 // log::info!("[check_and_handle] task {} action {:?}", self.tid(), sig_action);
                 let sa_flags = SigActionFlag::from_bits_truncate(sig_action.sa.sa_flags);
-                if sa_flags.contains(SigActionFlag::SA_ONSTACK) {
-                        log::warn!("using new signal stack");
-                        // todo
-                };
                 let trap_cx = self.trap_context.exclusive_access();
                 
                 if sa_flags.contains(SigActionFlag::SA_RESTART) && is_intr {
@@ -116,26 +161,49 @@ impl TaskControlBlock {
 
                 if sig_action.is_user {
                     let old_blocked_sigs = sig_manager.blocked_sigs; // save for later restore
+                    // block the handled signal and sa_mask while the handler runs,
+                    // sigreturn restores the mask saved in the ucontext
+                    let mut handler_mask = old_blocked_sigs | sig_action.sa.sa_mask[0];
                     if !sa_flags.contains(SigActionFlag::SA_NODEFER) {
-                        sig_manager.blocked_sigs.add_sig(sig.si_signo);
+                        handler_mask.add_sig(sig.si_signo);
                     };
-                    sig_manager.blocked_sigs |= sig_action.sa.sa_mask[0];
+                    sig_manager.set_sigmask(handler_mask);
                     // save fx state
                     trap_cx.fx_encounter_signal();
-                    // push the current Ucontext into user stack
-                    // (todo) notice that user may provide signal stack
-                    // but now we dont support this flag
-                    if sa_flags.contains(SigActionFlag::SA_ONSTACK) {
-                        log::warn!("using new signal stack");
-                        // todo
-                    };
-
-
+                    // push the current Ucontext into user stack,
+                    // or into the alternate signal stack with SA_ONSTACK if not already on it
                     let sp = *trap_cx.sp();
-                    let mut new_sp = sp - size_of::<UContext>();
-                    let ucontext = UContext::save_current_context(old_blocked_sigs.bits(), trap_cx);
-                    let dst = 
-                        UserPtrRaw::new(new_sp as *mut UContext).ensure_write(&mut self.get_vm_space().lock()).unwrap();
+                    let alt_stack = self.get_signal_stack()
+                        .filter(|_| sa_flags.contains(SigActionFlag::SA_ONSTACK) && !self.on_signal_stack(sp));
+                    let stack_top = match alt_stack {
+                        Some(ss) => {
+                            log::debug!("[check_and_handle] using signal stack {:#x}, size {:#x}", ss.ss_sp, ss.ss_size);
+                            (ss.ss_sp + ss.ss_size) & !0xf
+                        }
+                        None => sp,
+                    };
+                    let mut new_sp = stack_top - size_of::<UContext>();
+                    let mut ucontext = UContext::save_current_context(old_blocked_sigs.bits(), trap_cx);
+                    if let Some(mut ss) = self.get_signal_stack() {
+                        if self.on_signal_stack(sp) {
+                            ss.ss_flags |= SS_ONSTACK as i32;
+                        }
+                        ucontext.uc_stack = ss;
+                        // the handler may switch away from the stack with swapcontext,
+                        // it is armed again by sigreturn from the ucontext
+                        if ss.ss_flags as u32 & SS_AUTODISARM != 0 {
+                            self.set_signal_stack(None);
+                        }
+                    }
+                    // link to the frame of the interrupted handler, sigreturn goes back to it
+                    ucontext.uc_link = self.sig_ucontext_ptr();
+                    // the alternate stack comes unchecked from sigaltstack
+                    let Some(dst) =
+                        UserPtrRaw::new(new_sp as *mut UContext).ensure_write(&mut self.get_vm_space().lock()) else {
+                        log::warn!("[check_and_handle] cannot write the signal frame at {:#x}", new_sp);
+                        sig_manager.force_sigsegv();
+                        continue;
+                    };
                     // println!("copy_out to {:#x}", new_sp);
                     // copy_out(&mut self.get_vm_space().lock(), VirtAddr(new_sp), ucontext_bytes);
                     dst.write(ucontext);
@@ -151,13 +219,13 @@ impl TaskControlBlock {
                         // the second argument
                         trap_cx.set_arg_nth(2, new_sp);
                         // the third argument
-                        let mut siginfo_v = LinuxSigInfo::default();
-                        siginfo_v.si_signo = sig.si_signo as _;
-                        siginfo_v.si_code = sig.si_code;
-                        siginfo_v._pad[1] = sig.si_pid.unwrap_or(0) as i32;
+                        let siginfo_v = LinuxSigInfo::from(sig);
                         new_sp -= size_of::<LinuxSigInfo>();
-                        let dst = 
-                            UserPtrRaw::new(new_sp as *mut LinuxSigInfo).ensure_write(&mut self.get_vm_space().lock()).unwrap();
+                        let Some(dst) =
+                            UserPtrRaw::new(new_sp as *mut LinuxSigInfo).ensure_write(&mut self.get_vm_space().lock()) else {
+                            sig_manager.force_sigsegv();
+                            continue;
+                        };
                         dst.write(siginfo_v);
                         trap_cx.set_arg_nth(1, new_sp);
                     }
@@ -167,9 +235,9 @@ impl TaskControlBlock {
                     *trap_cx.sepc() = sig_action.sa.sa_handler as *const usize as usize;
                     // sp
                     *trap_cx.sp() = new_sp;
-                    // ra: when user signal handler ended, return to sigreturn_trampoline
-                    // which calls sys_sigreturn
-                    *trap_cx.ra() = sigreturn_trampoline_addr();
+                    // ra: when user signal handler ended, return to the restorer given by libc,
+                    // or to sigreturn_trampoline, both of which call sys_sigreturn
+                    *trap_cx.ra() = sig_action.sa.restorer().unwrap_or_else(sigreturn_trampoline_addr);
                     *trap_cx.tp() = ucontext.uc_mcontext.get_tp();
 
                     break;
@@ -195,6 +263,12 @@ impl TaskControlBlock {
     pub fn get_signal_stack(&self) -> Option<SigStack> {
         *self.sig_stack.lock()
     }
+
+    /// is `sp` inside the alternate signal stack
+    pub fn on_signal_stack(&self, sp: usize) -> bool {
+        self.get_signal_stack()
+            .map_or(false, |ss| sp > ss.ss_sp && sp - ss.ss_sp <= ss.ss_size)
+    }
 }
 
 /// the future that check if recv expect signal
@@ -209,7 +283,9 @@ impl Future for IntrBySignalFuture {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let has_signal = !(self.task.sig_manager.lock().bitmap & !self.mask).is_empty();
+        let manager = self.task.sig_manager.lock();
+        // signals read through signalfds never interrupt
+        let has_signal = !(manager.bitmap & !self.mask & !manager.signalfd_sigs()).is_empty();
         if has_signal {
             log::warn!("[IntrBySignalFuture] received interupt signal");
             Poll::Ready(())
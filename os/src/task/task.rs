@@ -8,7 +8,7 @@ use super::{tid_alloc, schedule, INITPROC};
 use crate::fs::devfs::tty::TTY;
 use crate::processor::context::{EnvContext,SumGuard};
 use crate::fs::vfs::{Dentry, DCACHE};
-use crate::fs::{Stdin, Stdout, vfs::File};
+use crate::fs::{pidfd, Stdin, Stdout, vfs::File};
 use crate::mm::{copy_out_str, translate_uva_checked, UserPtr, UserPtrRaw, UserPtrRead, UserVmSpace, KVMSPACE};
 use crate::processor::processor::{current_processor, PROCESSORS};
 #[cfg(feature = "smp")]
@@ -16,17 +16,21 @@ use crate::processor::schedule::TaskLoadTracker;
 use crate::sync::mutex::spin_mutex::MutexGuard;
 use crate::sync::mutex::{MutexSupport, SpinNoIrq, SpinNoIrqLock};
 use crate::sync::UPSafeCell;
-use crate::syscall::futex::{futex_manager, FutexHashKey, RobustList, RobustListHead, FUTEX_OWNER_DIED, FUTEX_TID_MASK, FUTEX_WAITERS};
-use crate::syscall::process::CloneFlags;
-use crate::signal::{KSigAction, SigInfo, SigManager, SigSet, SIGCHLD, SIGKILL, SIGSTOP};
+use crate::ipc::futex::{futex_queue, FutexHashKey};
+use crate::syscall::futex::{RobustList, RobustListHead, FUTEX_OWNER_DIED, FUTEX_TID_MASK, FUTEX_WAITERS};
+use crate::syscall::misc::{RLimit, MLOCK_LIMIT, RLIM_INFINITY};
+use crate::syscall::process::{CloneFlags, PR_UNALIGN_SIGBUS};
+use crate::signal::{KSigAction, SigInfo, SigManager, SigSet, SIGCHLD, SIGKILL, SIGPROF, SIGSTOP, SIGVTALRM};
 use crate::syscall::SysError;
 use crate::task::{current_task, INITPROC_PID};
 use crate::task::utils::user_stack_init;
 use crate::timer::get_current_time_duration;
+use crate::executor::sched::SchedPolicy;
 use crate::timer::recoder::TimeRecorder;
-use crate::timer::timer::{ITimer, PosixTimer, TimerId};
+use crate::timer::timer::{ITimer, PosixTimer, TimerId, ITIMER_PROF, ITIMER_VIRTUAL};
 use crate::utils::{get_waker, suspend_forever, SendWrapper};
 use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::vec_deque::VecDeque;
 use alloc::sync::{Arc, Weak};
 use alloc::{fmt, format, task, vec};
 use alloc::vec::Vec;
@@ -46,7 +50,7 @@ use core::any::Any;
 use core::arch::global_asm;
 use core::ops::Deref;
 use core::ptr::{null, null_mut};
-use core::sync::atomic::{AtomicBool, AtomicU32};
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicU32};
 use core::time::Duration;
 use core::{
     ptr::slice_from_raw_parts_mut,
@@ -102,6 +106,10 @@ pub struct TaskControlBlock {
     pub exit_code: AtomicUsize,
     /// ELF file the task executes
     pub elf: Shared<Option<Arc<dyn File>>>,
+    /// argv the running program was executed with, shared by the thread group
+    pub cmdline: Shared<Vec<String>>,
+    /// initial environment of the running program, shared by the thread group
+    pub environ: Shared<Vec<String>>,
     #[allow(unused)]
     /// base address of the user stack, can be used in thread create
     pub base_size: AtomicUsize,
@@ -124,10 +132,14 @@ pub struct TaskControlBlock {
     pub sig_manager: Shared<SigManager>,
     /// pointer to user context for signal handling.
     pub sig_ucontext_ptr: AtomicUsize, 
+    /// number of the syscall the task is in, -1 while in user space
+    pub syscall_nr: AtomicIsize,
     /// the signal stack
     pub sig_stack: Shared<Option<SigStack>>,
     /// current working dentry
     pub cwd: Shared<Arc<dyn Dentry>>,
+    /// root dentry, absolute paths are resolved from it
+    pub root: Shared<Arc<dyn Dentry>>,
     /// Interval timers for the task.
     pub itimers: Shared<[ITimer; 3]>,
     /// posix timers
@@ -140,14 +152,35 @@ pub struct TaskControlBlock {
     pub cpu_allowed: AtomicUsize,
     /// the processor id of the task
     pub processor_id: AtomicUsize,
-    /// the priority of the task
-    pub priority: AtomicI32,
+    /// nice value of the task, -20 (most favoured) to 19
+    pub nice: AtomicI32,
+    /// timer ticks run since the task was last preempted
+    pub sched_ticks: AtomicU32,
+    /// scheduling policy, one of the SCHED_* values
+    pub sched_policy: AtomicU32,
+    /// static priority for SCHED_FIFO and SCHED_RR, 0 for the other policies
+    pub rt_priority: AtomicU32,
     pub ruid: AtomicI32,
     pub euid: AtomicI32,
     pub suid: AtomicI32,
     pub rgid: AtomicI32,
     pub egid: AtomicI32,
-    pub sgid: AtomicI32
+    pub sgid: AtomicI32,
+    /// how misaligned accesses are handled, shared by the thread group
+    pub unalign_ctl: Arc<AtomicU32>,
+    /// RLIMIT_CORE: max size of the core file, shared by the thread group
+    pub core_limit: Shared<RLimit>,
+    /// RLIMIT_MEMLOCK: max bytes locked in memory, shared by the thread group
+    pub memlock_limit: Shared<RLimit>,
+}
+
+/// a change of a live process its parent has not waited for yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitReport {
+    /// stopped by the signal
+    Stopped(usize),
+    /// continued by SIGCONT
+    Continued,
 }
 
 /// Hold a group of threads which belongs to the same process.
@@ -156,6 +189,12 @@ pub struct ThreadGroup {
     alive: usize,
     pub group_exiting: bool,
     pub group_exit_code: usize,
+    /// signal the parent gets when the process exits, 0 for none
+    pub exit_signal: usize,
+    /// stop or continue to report to wait4 with WUNTRACED or WCONTINUED
+    pub wait_report: Option<WaitReport>,
+    /// threads of this process waiting in wait4 for a child to change state
+    pub child_wakers: VecDeque<Waker>,
 }
 
 impl ThreadGroup {
@@ -165,7 +204,10 @@ impl ThreadGroup {
             members: BTreeMap::new(),
             alive: 0,
             group_exiting: false,
-            group_exit_code: 0
+            group_exit_code: 0,
+            exit_signal: SIGCHLD,
+            wait_report: None,
+            child_wakers: VecDeque::new(),
         }
     }
     /// Get the number of threads in the group.
@@ -238,9 +280,14 @@ impl TaskControlBlock {
         task_status: TaskStatus,
         sig_manager: SigManager,
         cwd: Arc<dyn Dentry>,
+        root: Arc<dyn Dentry>,
         vm_space: UserVmSpace,
         itimers: [ITimer;3],
-        posix_timers: BTreeMap<TimerId, PosixTimer>
+        cmdline: Vec<String>,
+        environ: Vec<String>,
+        posix_timers: BTreeMap<TimerId, PosixTimer>,
+        core_limit: RLimit,
+        memlock_limit: RLimit
     );
     #[cfg(feature = "smp")]
     generate_with_methods!(
@@ -249,6 +296,7 @@ impl TaskControlBlock {
     generate_atomic_accessors!(
         exit_code: usize,
         sig_ucontext_ptr: usize,
+        syscall_nr: isize,
         cpu_allowed: usize,
         processor_id: usize,
         euid: i32,
@@ -257,7 +305,12 @@ impl TaskControlBlock {
         rgid: i32,
         egid: i32,
         sgid: i32,
-        next_timer_id: u32
+        next_timer_id: u32,
+        unalign_ctl: u32,
+        sched_policy: u32,
+        rt_priority: u32,
+        nice: i32,
+        sched_ticks: u32
     );
     generate_state_methods!(
         Ready,
@@ -353,15 +406,6 @@ impl TaskControlBlock {
         ret
     }
     ///
-    pub fn priority(&self) -> AtomicI32 {
-        AtomicI32::new(self.priority.load(Ordering::SeqCst))
-    }
-
-    /// 
-    pub fn set_priority(&self, priority: i32) {
-        self.priority.store(priority, Ordering::SeqCst);
-    }
-    ///
     pub fn alloc_timer_id(&self) -> TimerId {
         self.next_timer_id.fetch_add(1, Ordering::Relaxed)
     }
@@ -421,9 +465,13 @@ impl TaskControlBlock {
             pgid: new_shared(pgid),
             sig_manager: new_shared(SigManager::new()),
             sig_ucontext_ptr: AtomicUsize::new(0),
+            syscall_nr: AtomicIsize::new(-1),
             sig_stack: new_shared(None),
-            cwd: new_shared(root_dentry), 
+            cwd: new_shared(root_dentry.clone()), 
+            root: new_shared(root_dentry),
             elf: new_shared(elf_file),
+            cmdline: new_shared(Vec::new()),
+            environ: new_shared(Vec::new()),
             itimers: new_shared([ITimer::ZERO; 3]),
             posix_timers: new_shared(BTreeMap::new()),
             next_timer_id: AtomicU32::new(0),
@@ -432,13 +480,19 @@ impl TaskControlBlock {
             sche_entity: new_shared(TaskLoadTracker::new()),
             cpu_allowed: AtomicUsize::new(15),
             processor_id: AtomicUsize::new(current_processor().id()),
-            priority: AtomicI32::new(20),
+            nice: AtomicI32::new(0),
+            sched_ticks: AtomicU32::new(0),
+            sched_policy: AtomicU32::new(SchedPolicy::Other as u32),
+            rt_priority: AtomicU32::new(0),
             suid: AtomicI32::new(0),
             euid: AtomicI32::new(0),
             ruid: AtomicI32::new(0),
             sgid: AtomicI32::new(0),
             rgid: AtomicI32::new(0),
             egid: AtomicI32::new(0),
+            unalign_ctl: Arc::new(AtomicU32::new(PR_UNALIGN_SIGBUS)),
+            core_limit: new_shared(RLimit { rlim_cur: 0, rlim_max: RLIM_INFINITY }),
+            memlock_limit: new_shared(RLimit { rlim_cur: MLOCK_LIMIT, rlim_max: MLOCK_LIMIT }),
         });
         // info!("in new");
         // task_control_block.get_trap_cx().set_arg_nth(0, user_sp); // set a0 to user_sp
@@ -474,6 +528,9 @@ impl TaskControlBlock {
 
         // update the executing elf file
         *self.elf.lock() = elf_file;
+        // keep a copy of what goes onto the user stack for /proc/<pid>
+        *self.cmdline.lock() = argv.clone();
+        *self.environ.lock() = envp.clone();
         // NOTE: should do termination before switching page table, so that other
         // threads will trap in by page fault and be handled by handle_zombie
         // info!("terminating all threads except main");
@@ -493,6 +550,8 @@ impl TaskControlBlock {
         let (new_user_sp, argc, argv, envp) = user_stack_init(&mut vm_space, user_sp, argv, envp, auxv);
         user_sp = new_user_sp;
 
+        // the task policy survives exec
+        vm_space.set_task_policy(self.get_vm_space().lock().task_policy());
         // substitute memory_set
         // self.with_mut_vm_space(|m| *m = vm_space);
         *self.vm_space.exclusive_access() = new_shared(vm_space);
@@ -501,6 +560,8 @@ impl TaskControlBlock {
 
         // reset the signal manager on exec
         self.with_mut_sig_manager(|sig_manager| sig_manager.reset_on_exec());
+        // the new program starts without interval timers
+        self.with_mut_itimers(|itimers| itimers.iter_mut().for_each(ITimer::cancel));
 
         // initialize trap_cx
         let mut trap_cx = TrapContext::app_init_context(
@@ -516,9 +577,12 @@ impl TaskControlBlock {
         Ok(())
     }
     /// 
-    pub fn fork(self: &Arc<TaskControlBlock>, flag: CloneFlags) -> Arc<TaskControlBlock> {
+    pub fn fork(self: &Arc<TaskControlBlock>, flag: CloneFlags) -> Result<Arc<TaskControlBlock>, SysError> {
         // alloc a pid and a kernel stack in kernel space
-        let tid_handle = tid_alloc();
+        self.fork_with_tid(flag, tid_alloc())
+    }
+    /// fork with a tid the caller has already allocated, used by clone3 set_tid
+    pub fn fork_with_tid(self: &Arc<TaskControlBlock>, flag: CloneFlags, tid_handle: TidHandle) -> Result<Arc<TaskControlBlock>, SysError> {
         // ---- hold parent PCB lock
         let status = SpinNoIrqLock::new(self.get_status());
         let leader;
@@ -528,8 +592,14 @@ impl TaskControlBlock {
         let thread_group;
         let pgid;
         let cwd;
+        let root;
         let itimers;
         let elf;
+        let cmdline;
+        let environ;
+        let unalign_ctl;
+        let core_limit;
+        let memlock_limit;
         let sig_manager = new_shared(
             match flag.contains(CloneFlags::SIGHAND) {
             true => SigManager::from_another(&self.sig_manager.lock()),
@@ -544,18 +614,34 @@ impl TaskControlBlock {
             thread_group = self.thread_group.clone();
             pgid = self.pgid.clone();
             cwd = self.cwd.clone();
+            root = self.root.clone();
             itimers = self.itimers.clone();
             elf = self.elf.clone();
+            cmdline = self.cmdline.clone();
+            environ = self.environ.clone();
+            unalign_ctl = self.unalign_ctl.clone();
+            core_limit = self.core_limit.clone();
+            memlock_limit = self.memlock_limit.clone();
         } else {
             is_leader = true;
             leader = None;
-            parent =  new_shared(Some(Arc::downgrade(self)));
+            // with CLONE_PARENT the new process is a sibling of the caller
+            parent = match flag.contains(CloneFlags::PARENT) {
+                true => new_shared(self.get_leader().parent()),
+                false => new_shared(Some(Arc::downgrade(self))),
+            };
             children = new_shared(BTreeMap::new());
             thread_group = new_shared(ThreadGroup::new());
             pgid = new_shared(*self.pgid.lock());
             cwd = new_shared(self.cwd());
+            root = new_shared(self.root());
             itimers = new_shared([ITimer::ZERO; 3]);
-            elf = new_shared(self.elf.lock().clone())
+            elf = new_shared(self.elf.lock().clone());
+            cmdline = new_shared(self.cmdline.lock().clone());
+            environ = new_shared(self.environ.lock().clone());
+            unalign_ctl = Arc::new(AtomicU32::new(self.unalign_ctl()));
+            core_limit = new_shared(*self.core_limit.lock());
+            memlock_limit = new_shared(*self.memlock_limit.lock());
         }
         let vm_space;
         if flag.contains(CloneFlags::VM){
@@ -566,7 +652,7 @@ impl TaskControlBlock {
                 self.with_mut_vm_space(
                     |vm| 
                         UserVmSpace::from_existed(vm)
-                )
+                )?
             ));
         }
         let fd_table = if flag.contains(CloneFlags::FILES) {
@@ -601,9 +687,13 @@ impl TaskControlBlock {
             pgid,
             sig_manager,
             sig_ucontext_ptr: AtomicUsize::new(0),
+            syscall_nr: AtomicIsize::new(-1),
             sig_stack: new_shared(None),
             cwd,
+            root,
             elf,
+            cmdline,
+            environ,
             itimers,
             posix_timers: new_shared(BTreeMap::new()),
             next_timer_id: AtomicU32::new(0),
@@ -612,18 +702,27 @@ impl TaskControlBlock {
             sche_entity: new_shared(TaskLoadTracker::new()),
             cpu_allowed: AtomicUsize::new(15),
             processor_id: AtomicUsize::new(self.processor_id()),
-            priority: self.priority(),
+            nice: AtomicI32::new(self.nice()),
+            sched_ticks: AtomicU32::new(0),
+            sched_policy: AtomicU32::new(self.sched_policy()),
+            rt_priority: AtomicU32::new(self.rt_priority()),
             suid: AtomicI32::new(self.suid()),
             euid: AtomicI32::new(self.euid()),
             ruid: AtomicI32::new(self.ruid()),
             sgid: AtomicI32::new(self.sgid()),
             rgid: AtomicI32::new(self.rgid()),
             egid: AtomicI32::new(self.egid()),
+            unalign_ctl,
+            core_limit,
+            memlock_limit,
         });
         // add child except when creating a thread
         if !flag.contains(CloneFlags::THREAD) {
             //info!("fork should in this ");
-            self.add_child(task_control_block.clone());
+            match task_control_block.parent().and_then(|p| p.upgrade()) {
+                Some(parent) if flag.contains(CloneFlags::PARENT) => parent.add_child(task_control_block.clone()),
+                _ => self.add_child(task_control_block.clone()),
+            }
             // println!("[fork] new process pid: {} tid: {}", task_control_block.pid(), task_control_block.tid());
         } else {
             // println!("[fork] new thread pid: {} tid: {}", task_control_block.pid(), task_control_block.tid());
@@ -635,7 +734,7 @@ impl TaskControlBlock {
             PROCESS_GROUP_MANAGER.add_task_to_group(task_control_block.pgid(), &task_control_block);
         }
         TASK_MANAGER.add_task(&task_control_block);
-        task_control_block
+        Ok(task_control_block)
     }
 
     fn futex_wake(&self, addr: usize, shared: bool, vm: &mut UserVmSpace) {
@@ -657,7 +756,7 @@ impl TaskControlBlock {
             }
         };
 
-        if futex_manager().wake(&key, 1).is_ok() {
+        if futex_queue().wake(&key, 1) > 0 {
             // println!("[handle_zombie] successfully wake: {:?}", key);
         }
     }
@@ -798,16 +897,22 @@ impl TaskControlBlock {
                 for child in children.values() {
                     if child.is_zombie() {
                         initproc.recv_sigs_process_level(
-                            SigInfo { si_signo: SIGCHLD, si_code: SigInfo::CLD_EXITED, si_pid: None }
+                            SigInfo { si_signo: SIGCHLD, si_code: SigInfo::CLD_EXITED, si_pid: None, si_value: 0 }
                         );
                     }
                     *child.parent.lock() = Some(Arc::downgrade(initproc));
+                    // init reaps whatever it adopts with a plain wait
+                    child.thread_group.lock().exit_signal = SIGCHLD;
                 }
                 initproc.children.lock().extend(children.clone()); 
                 children.clear();
             });
+            INITPROC.wake_child_waiters();
+            self.with_mut_itimers(|itimers| itimers.iter_mut().for_each(ITimer::cancel));
             log::warn!("do exit: clear fd table");
-            self.with_mut_fd_table(|table|table.fd_table.clear());
+            let pid = self.pid();
+            self.with_mut_fd_table(|table|table.close_all(pid));
+            pidfd::notify_exit(pid);
             self.notify_parent();
         }
     }
@@ -823,10 +928,11 @@ impl TaskControlBlock {
                 if task.tid() == self.tid() || task.is_zombie() {
                     continue;
                 }
-                task.recv_sigs(SigInfo { si_signo: SIGKILL, si_code: SigInfo::KERNEL, si_pid: Some(self.pid()) });
+                task.recv_sigs(SigInfo { si_signo: SIGKILL, si_code: SigInfo::KERNEL, si_pid: Some(self.pid()), si_value: 0 });
             }
         }
         drop(tg);
+        // every thread the SIGKILL reaches releases its own robust futexes in do_exit
         self.do_exit(code)
     }
 
@@ -872,7 +978,7 @@ impl TaskControlBlock {
             for child in children.values() {
                 if child.is_zombie() {
                     initproc.recv_sigs_process_level(
-                        SigInfo { si_signo: SIGCHLD, si_code: SigInfo::CLD_EXITED, si_pid: None }
+                        SigInfo { si_signo: SIGCHLD, si_code: SigInfo::CLD_EXITED, si_pid: None, si_value: 0 }
                     );
                 }
                 *child.parent.lock() = Some(Arc::downgrade(initproc));
@@ -882,7 +988,8 @@ impl TaskControlBlock {
         });
 
         // leader will be removed by parent calling sys_waitpid
-        self.with_mut_fd_table(|table|table.fd_table.clear());
+        let pid = self.pid();
+        self.with_mut_fd_table(|table|table.close_all(pid));
         if self.is_leader() {
             self.set_zombie();
         }else {
@@ -930,6 +1037,72 @@ impl TaskControlBlock {
             .unwrap()
         })
     }
+    /// get the sum of voluntary and involuntary context switches of all threads in the process
+    pub fn process_switch_pair(&self) -> (usize, usize) {
+        self.with_thread_group(|thread_group| {
+            thread_group.iter()
+            .map(|thread| thread.time_recorder().switch_pair())
+            .fold((0, 0), |(v, iv), (tv, tiv)| (v + tv, iv + tiv))
+        })
+    }
+    /// the time the interval timer `which` of the process counts in
+    pub fn itimer_clock(&self, which: usize) -> Duration {
+        match which {
+            ITIMER_VIRTUAL => self.process_user_time(),
+            ITIMER_PROF => self.process_cpu_time(),
+            _ => get_current_time_duration(),
+        }
+    }
+    /// fire the ITIMER_VIRTUAL and ITIMER_PROF timers of the process
+    /// once its cpu time reaches them, checked on every timer interrupt
+    pub fn check_cpu_itimers(self: &Arc<Self>) {
+        for (which, signo) in [(ITIMER_VIRTUAL, SIGVTALRM), (ITIMER_PROF, SIGPROF)] {
+            if self.with_itimers(|itimers| itimers[which].next_expire.is_zero()) {
+                continue;
+            }
+            let now = self.itimer_clock(which);
+            let fired = self.with_mut_itimers(|itimers| {
+                let itimer = &mut itimers[which];
+                if itimer.next_expire.is_zero() || now < itimer.next_expire {
+                    return false;
+                }
+                itimer.next_expire = if itimer.interval.is_zero() {
+                    Duration::ZERO
+                } else {
+                    now + itimer.interval
+                };
+                true
+            });
+            if fired {
+                self.recv_sigs_process_level(
+                    SigInfo { si_signo: signo, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 }
+                );
+            }
+        }
+    }
+    /// what the process used, including its own reaped children:
+    /// (user, system) time, (voluntary, involuntary) switches and peak rss in pages
+    pub fn usage_with_children(&self) -> ((Duration, Duration), (usize, usize), usize) {
+        let (utime, stime) = self.process_time_pair();
+        let (nvcsw, nivcsw) = self.process_switch_pair();
+        let maxrss = self.get_vm_space().lock().peak_rss();
+        let recorder = self.time_recorder();
+        let (c_utime, c_stime) = recorder.child_time_pair();
+        let c_csw = recorder.child_switch_pair();
+        (
+            (utime + c_utime, stime + c_stime),
+            (nvcsw + c_csw.0, nivcsw + c_csw.1),
+            maxrss.max(recorder.child_maxrss()),
+        )
+    }
+    /// add what a reaped child process used, including its own reaped children,
+    /// to the children usage of the task
+    pub fn account_reaped_child(&self, child: &Arc<Self>) {
+        let (time, csw, maxrss) = child.usage_with_children();
+        let recorder = self.time_recorder();
+        recorder.update_child_time(time);
+        recorder.update_child_usage(csw, maxrss);
+    }
 }
 
 
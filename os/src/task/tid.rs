@@ -16,6 +16,8 @@ pub type PGid = Tid;
 pub struct TidAllocator {
     current: usize,
     recycled: Vec<usize>,
+    /// the tid given out last
+    last: usize,
 }
 
 impl TidAllocator {
@@ -24,16 +26,36 @@ impl TidAllocator {
         TidAllocator {
             current: INITPROC_PID,
             recycled: Vec::new(),
+            last: 0,
         }
     }
     ///Allocate a tid
     pub fn alloc(&mut self) -> TidHandle {
-        if let Some(tid) = self.recycled.pop() {
-            TidHandle(tid)  
+        let tid = if let Some(tid) = self.recycled.pop() {
+            tid
         } else {
             self.current += 1;
-            TidHandle(self.current - 1)
+            self.current - 1
+        };
+        self.last = tid;
+        TidHandle(tid)
+    }
+    ///Allocate the given tid, fails if it is still in use
+    pub fn alloc_specific(&mut self, tid: usize) -> Option<TidHandle> {
+        if tid < INITPROC_PID {
+            return None;
+        }
+        if tid >= self.current {
+            // the tids we skip over stay available
+            self.recycled.extend(self.current..tid);
+            self.current = tid + 1;
+            self.last = tid;
+            return Some(TidHandle(tid));
         }
+        let idx = self.recycled.iter().position(|&t| t == tid)?;
+        self.recycled.swap_remove(idx);
+        self.last = tid;
+        Some(TidHandle(tid))
     }
     ///Recycle a pid
     pub fn dealloc(&mut self, pid: usize) {
@@ -64,6 +86,15 @@ impl Drop for TidHandle {
 pub fn tid_alloc() -> TidHandle {
     TID_ALLOCATOR.lock().alloc()
 }
+///Allocate the given pid from PID_ALLOCATOR, `None` if it is taken
+pub fn tid_alloc_specific(tid: usize) -> Option<TidHandle> {
+    TID_ALLOCATOR.lock().alloc_specific(tid)
+}
+
+/// the tid given out last, as the last pid of /proc/loadavg
+pub fn last_tid() -> usize {
+    TID_ALLOCATOR.lock().last
+}
 
 /// Tid address which may be set by `set_tid_address` syscall.
 pub struct TidAddress {
@@ -109,8 +109,8 @@ pub fn generate_early_auxv(
     push!(AT_GID, 0);
     push!(AT_EGID, 0);
     push!(AT_PLATFORM, 0);
-    push!(AT_HWCAP, 0);
-    push!(AT_CLKTCK, 100);
+    push!(AT_HWCAP, hal::cpu::hwcap());
+    push!(AT_CLKTCK, crate::timer::hz());
     push!(AT_SECURE, 0);
     auxv
 }
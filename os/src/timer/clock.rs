@@ -3,7 +3,13 @@
 //! or per-process if it measures time only within a single process.
 //! more info refer to linux manual
 
-use core::time::Duration;
+use core::{future::Future, pin::Pin, sync::atomic::{AtomicU64, AtomicUsize, Ordering}, task::{Context, Poll, Waker}, time::Duration};
+
+use alloc::vec::Vec;
+
+use crate::sync::mutex::SpinNoIrqLock;
+
+use super::get_current_time_duration;
 
 /// the number of global clocks we need to record (no related to task)
 pub const SUPPORT_CLOCK_NUM: usize = 2;
@@ -43,5 +49,74 @@ pub const CLOCK_MONOTONIC_COARSE: usize = 6;
 /// the system is suspended.
 pub const CLOCK_BOOTTIME: usize = 7;
 
-/// global clocks
-pub static mut CLOCK_DEVIATION: [Duration; SUPPORT_CLOCK_NUM] = [Duration::ZERO; SUPPORT_CLOCK_NUM];
\ No newline at end of file
+/// offset of CLOCK_REALTIME from the monotonic clock (in ns),
+/// the monotonic clock itself is never stepped
+static REALTIME_OFFSET: AtomicU64 = AtomicU64::new(0);
+/// bumped whenever CLOCK_REALTIME is stepped
+static REALTIME_SEQ: AtomicUsize = AtomicUsize::new(0);
+/// tasks sleeping until a CLOCK_REALTIME deadline,
+/// woken up to re-evaluate the deadline when the clock is stepped
+static REALTIME_WAITERS: SpinNoIrqLock<Vec<Waker>> = SpinNoIrqLock::new(Vec::new());
+
+/// time since boot, never goes backwards
+pub fn monotonic_time() -> Duration {
+    get_current_time_duration()
+}
+
+/// how far CLOCK_REALTIME is ahead of the monotonic clock
+pub fn realtime_offset() -> Duration {
+    Duration::from_nanos(REALTIME_OFFSET.load(Ordering::Acquire))
+}
+
+/// wall clock time
+pub fn realtime_time() -> Duration {
+    monotonic_time() + realtime_offset()
+}
+
+/// current time of a system-wide clock, None for the per-process ones
+pub fn clock_time(clock_id: usize) -> Option<Duration> {
+    match clock_id {
+        CLOCK_REALTIME | CLOCK_REALTIME_COARSE => Some(realtime_time()),
+        CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_MONOTONIC_COARSE | CLOCK_BOOTTIME => Some(monotonic_time()),
+        _ => None,
+    }
+}
+
+/// step CLOCK_REALTIME to the time,
+/// which must not be earlier than the monotonic clock
+pub fn set_realtime(time: Duration) {
+    let offset = time.saturating_sub(monotonic_time());
+    REALTIME_OFFSET.store(offset.as_nanos() as u64, Ordering::Release);
+    REALTIME_SEQ.fetch_add(1, Ordering::AcqRel);
+    let waiters = core::mem::take(&mut *REALTIME_WAITERS.lock());
+    for waker in waiters {
+        waker.wake();
+    }
+}
+
+/// number of times CLOCK_REALTIME has been stepped
+pub fn realtime_seq() -> usize {
+    REALTIME_SEQ.load(Ordering::Acquire)
+}
+
+/// a future that is ready once CLOCK_REALTIME is stepped after seq
+pub struct RealtimeStepFuture {
+    /// the value of realtime_seq() when the caller read the clock
+    pub seq: usize,
+}
+
+impl Future for RealtimeStepFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut waiters = REALTIME_WAITERS.lock();
+        // checked under the lock, so a step cannot slip in before the waker is queued
+        if realtime_seq() != self.seq {
+            return Poll::Ready(());
+        }
+        if !waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
\ No newline at end of file
@@ -1,5 +1,5 @@
 use alloc::collections::btree_map::Values;
-use crate::timer::get_current_time_ns;
+use crate::timer::{duration_to_ticks, get_current_time_ns};
 
 use super::{get_current_time_ms, NSEC_PER_SEC};
 use core::time::Duration;
@@ -153,13 +153,16 @@ impl Tms {
             cstime: 0,
         }
     }
-    /// new from a TimeRecorder
-    pub fn from_time_recorder(time_recorder: &super::recoder::TimeRecorder) -> Self {
+    /// new from the (user, system) times of the process and of its reaped children
+    pub fn from_time_pairs(
+        (utime, stime): (Duration, Duration),
+        (cutime, cstime): (Duration, Duration),
+    ) -> Self {
         Self {
-            utime: time_recorder.user_time().as_micros() as usize,
-            stime: time_recorder.kernel_time().as_micros() as usize,
-            cutime: time_recorder.child_time_pair().0.as_micros() as usize,
-            cstime: time_recorder.child_time_pair().1.as_micros() as usize,
+            utime: duration_to_ticks(utime),
+            stime: duration_to_ticks(stime),
+            cutime: duration_to_ticks(cutime),
+            cstime: duration_to_ticks(cstime),
         }
     }
 }
\ No newline at end of file
@@ -10,9 +10,12 @@ pub mod timer;
 /// time-limited task wrapper
 pub mod timed_task;
 pub mod clock;
-use core::time::Duration;
+use core::{sync::atomic::{AtomicUsize, Ordering}, time::Duration};
 
-const TICKS_PER_SEC: usize = 100;
+/// timer interrupts per second when the boot arguments set none
+const DEFAULT_HZ: usize = 100;
+/// the range `hz=` of the boot arguments is taken from
+const HZ_RANGE: core::ops::RangeInclusive<usize> = 10..=1000;
 const MSEC_PER_SEC: usize = 1_000;
 const USEC_PER_SEC: usize = 1_000_000;
 const NSEC_PER_SEC: usize = 1_000_000_000;
@@ -59,7 +62,37 @@ pub fn get_current_time_duration() -> Duration {
     Duration::new(secs, nanos)
 }
 
+static HZ: AtomicUsize = AtomicUsize::new(DEFAULT_HZ);
+
+/// set the tick rate from `hz=` of the boot arguments,
+/// before any processor sets its first trigger
+pub fn init_hz() {
+    let Some(arg) = crate::devices::boot_arg_value("hz") else {
+        return;
+    };
+    match arg.parse::<usize>() {
+        Ok(hz) if HZ_RANGE.contains(&hz) => HZ.store(hz, Ordering::Relaxed),
+        _ => log::warn!("[timer] ignore hz={}, keep {}", arg, hz()),
+    }
+    log::info!("[timer] {} ticks per second", hz());
+}
+
+/// timer interrupts per second, the clock ticks the times reported to user space count in
+pub fn hz() -> usize {
+    HZ.load(Ordering::Relaxed)
+}
+
+/// microseconds between two timer interrupts
+pub fn tick_usec() -> usize {
+    USEC_PER_SEC / hz()
+}
+
+/// the duration in clock ticks, the unit of the times reported to user space
+pub fn duration_to_ticks(duration: Duration) -> usize {
+    (duration.as_micros() * hz() as u128 / USEC_PER_SEC as u128) as usize
+}
+
 /// set the next timer interrupt
 pub fn set_next_trigger() {
-    Timer::set_timer(get_current_time() + Timer::get_timer_freq() / TICKS_PER_SEC);
+    Timer::set_timer(get_current_time() + Timer::get_timer_freq() / hz());
 }
@@ -19,6 +19,16 @@ pub struct TimeRecorder {
     child_user_time: Duration,
     /// child kernel time Duration
     child_kernel_time: Duration,
+    /// times the task gave up the cpu on its own
+    nvcsw: usize,
+    /// times the task was preempted
+    nivcsw: usize,
+    /// the next switch out is a preemption
+    preempted: bool,
+    /// context switches of reaped children: (voluntary, involuntary)
+    child_csw: (usize, usize),
+    /// largest peak resident set of reaped children (in pages)
+    child_maxrss: usize,
 }
 
 impl TimeRecorder {
@@ -31,6 +41,11 @@ impl TimeRecorder {
             user_start: Duration::ZERO,
             child_user_time: Duration::ZERO,
             child_kernel_time: Duration::ZERO,
+            nvcsw: 0,
+            nivcsw: 0,
+            preempted: false,
+            child_csw: (0, 0),
+            child_maxrss: 0,
         }
     }
     /// return a pair for user and kernel time
@@ -65,6 +80,28 @@ impl TimeRecorder {
         self.child_user_time += child_user_time;
         self.child_kernel_time += child_kernel_time;
     }
+    /// return a pair for voluntary and involuntary context switches
+    pub fn switch_pair(&self) -> (usize, usize) {
+        (self.nvcsw, self.nivcsw)
+    }
+    /// return a pair for child voluntary and involuntary context switches
+    pub fn child_switch_pair(&self) -> (usize, usize) {
+        self.child_csw
+    }
+    /// largest peak resident set of the reaped children (in pages)
+    pub fn child_maxrss(&self) -> usize {
+        self.child_maxrss
+    }
+    /// for parent task to update child task's context switches and resident set
+    pub fn update_child_usage(&mut self, (nvcsw, nivcsw): (usize, usize), maxrss: usize) {
+        self.child_csw.0 += nvcsw;
+        self.child_csw.1 += nivcsw;
+        self.child_maxrss = self.child_maxrss.max(maxrss);
+    }
+    /// the task is about to be preempted by the timer
+    pub fn record_preempt(&mut self) {
+        self.preempted = true;
+    }
     /// for switch_to_current_task recording 
     pub fn record_switch_in(&mut self) {
         let current_time = get_current_time_duration();
@@ -75,6 +112,11 @@ impl TimeRecorder {
         let current_time = get_current_time_duration();
         let kernel_time_slice = current_time - self.kernel_start;
         self.kernel_time += kernel_time_slice;
+        if core::mem::take(&mut self.preempted) {
+            self.nivcsw += 1;
+        } else {
+            self.nvcsw += 1;
+        }
     }
     /// for trap recording: from user to kernel
     pub fn record_trap(&mut self){
@@ -85,7 +127,7 @@ impl TimeRecorder {
     /// for trap_return recording: form kernel to user
     pub fn record_trap_return(&mut self){
         let current_time = get_current_time_duration();
-        self.kernel_time += current_time - self.user_start;    
+        self.kernel_time += current_time - self.kernel_start;
         self.user_start = current_time;
     }
 }
\ No newline at end of file
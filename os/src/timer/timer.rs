@@ -1,9 +1,7 @@
 use core::{
     cmp::Reverse,
-    future::Future,
-    pin::Pin,
     sync::atomic::{AtomicU64, AtomicUsize, Ordering},
-    task::{Context, Poll, Waker},
+    task::Waker,
     time::Duration,
 };
 extern crate alloc;
@@ -13,21 +11,18 @@ use alloc::{
     sync::{Arc, Weak},
     vec::Vec,
 };
-use async_trait::async_trait;
 use downcast_rs::DowncastSync;
 use log::info;
 
 use super::{ffi::TimeVal, get_current_time_duration};
 use crate::{
     devices::net::NetRxToken,
-    fs::vfs::{File, FileInner},
     processor::processor::current_processor,
     signal::{
         msg_queue::{Sigevent, SIGEV_SIGNAL},
         SigInfo, SIGALRM,
     },
     sync::mutex::SpinNoIrqLock,
-    syscall::{SysError, SysResult},
     task::task::TaskControlBlock,
     timer::{
         ffi::TimeSpec,
@@ -38,7 +33,7 @@ use hal::{
     board::MAX_PROCESSORS,
     instruction::{Instruction, InstructionHal},
 };
-use spin::{Lazy, Mutex};
+use spin::Lazy;
 /// A trait that defines the event to be triggered when a timer expires.
 /// The TimerEvent trait requires a callback method to be implemented,
 /// which will be called when the timer expires.
@@ -166,6 +161,13 @@ impl TimerManager {
 /// The global `TimerManager` instance that can be accessed from anywhere in the kernel.
 pub static TIMER_MANAGER: Lazy<TimerManager> = Lazy::new(TimerManager::new);
 
+/// real time, SIGALRM on expiration
+pub const ITIMER_REAL: usize = 0;
+/// user time of the process, SIGVTALRM on expiration
+pub const ITIMER_VIRTUAL: usize = 1;
+/// user and system time of the process, SIGPROF on expiration
+pub const ITIMER_PROF: usize = 2;
+
 /// below are timer structure in linux,ITimer is a timer struct in linux used in settimmer
 ///and in get timer, ther are three types of timer in linux
 #[derive(Debug)]
@@ -185,6 +187,10 @@ impl ITimer {
         next_expire: Duration::ZERO,
         id: 0,
     };
+    /// disarm the itimer, a pending RealITimer of it finds the id changed
+    pub fn cancel(&mut self) {
+        *self = Self::ZERO;
+    }
 }
 
 static TIMER_ID_ALLOCATOR: AtomicUsize = AtomicUsize::new(1);
@@ -243,7 +249,7 @@ impl TimerEvent for RealITimer {
     fn callback(self: Box<Self>) -> Option<Timer> {
         self.task.upgrade().and_then(|task| {
             task.with_mut_itimers(|itimers| {
-                let real_timer = &mut itimers[0];
+                let real_timer = &mut itimers[ITIMER_REAL];
                 if real_timer.id != self.id {
                     log::warn!("check failed!");
                     return None;
@@ -252,6 +258,7 @@ impl TimerEvent for RealITimer {
                     si_signo: SIGALRM,
                     si_code: SigInfo::KERNEL,
                     si_pid: None,
+                    si_value: 0,
                 });
                 let real_timer_interval = real_timer.interval;
                 if real_timer_interval == Duration::ZERO {
@@ -336,6 +343,7 @@ impl TimerEvent for PosixTimer {
                         si_signo: self.sigevent.sigev_signo as usize,
                         si_code: SigInfo::KERNEL,
                         si_pid: None,
+                        si_value: 0,
                     };
                     task.recv_sigs_process_level(sig_info);
                 }
@@ -364,138 +372,3 @@ impl TimerEvent for PosixTimer {
         None
     }
 }
-
-pub struct TimerFdFile {
-    pub file_inner: FileInner,
-    pub timer: Arc<Mutex<TimerFd>>,
-}
-
-unsafe impl Send for TimerFdFile {}
-unsafe impl Sync for TimerFdFile {}
-
-#[derive(Clone)]
-pub struct TimerFd {
-    pub task: Weak<TaskControlBlock>,
-    pub interval: Duration,
-    pub next_expire: Duration,
-    pub interval_id: TimerId,
-    pub expirations: u64,
-    // 当 poll 被调用时，waker 会被设置。
-    pub wait_future: Option<TimerFdReadFuture>,
-}
-
-#[async_trait]
-impl File for TimerFdFile {
-    fn file_inner(&self) -> &FileInner {
-        &self.file_inner
-    }
-
-    fn readable(&self) -> bool {
-        self.timer.lock().expirations > 0
-    }
-
-    fn writable(&self) -> bool {
-        false
-    }
-
-    async fn read(&self, buf: &mut [u8]) -> Result<usize, SysError> {
-        let expirations;
-        {
-            let mut inner = self.timer.lock();
-            if inner.expirations == 0 {
-                let fut = inner
-                    .wait_future
-                    .get_or_insert_with(|| TimerFdReadFuture::new(Arc::clone(&self.timer)))
-                    .clone();
-                drop(inner); 
-                fut.await;
-                inner = self.timer.lock(); 
-            }
-            expirations = inner.expirations;
-            inner.expirations = 0;
-        } 
-        if buf.len() < 8 {
-            return Err(SysError::EINVAL);
-        }
-        buf[..8].copy_from_slice(&expirations.to_ne_bytes());
-        Ok(8)
-    }
-    async fn write(&self, _buf: &[u8]) -> Result<usize, SysError> {
-        Err(SysError::EINVAL)
-    }
-}
-
-#[derive(Clone)]
-pub struct TimerFdReadFuture {
-    timer: Arc<Mutex<TimerFd>>,
-    waker: Option<Waker>,
-}
-
-impl TimerFdReadFuture {
-    pub fn new(timer: Arc<Mutex<TimerFd>>) -> Self {
-        Self { timer, waker: None }
-    }
-
-    /// Called by timer callback to wake up the future
-    pub fn wake(&self) {
-        if let Some(w) = &self.waker {
-            w.wake_by_ref();
-        }
-    }
-}
-
-impl Future for TimerFdReadFuture {
-    type Output = ();
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        let inner = self.timer.lock();
-
-        // 检查是否有到期事件
-        if inner.expirations > 0 {
-            // 如果有，就绪
-            Poll::Ready(())
-        } else {
-            // 没有到期事件，并且定时器未设置，则立即就绪
-            // 这对应于 timerfd_settime 设置 it_value 为 0 的情况。
-            if inner.next_expire.is_zero() {
-                Poll::Ready(())
-            } else {
-                // 定时器已设置但未到期，我们需要等待
-                // 更新 Waker
-                if self.waker.as_ref().map(|w| !w.will_wake(cx.waker())).unwrap_or(true) {
-                    drop(inner);
-                    self.waker = Some(cx.waker().clone());
-                }
-                // 将 Waker 注册到全局定时器管理器
-                // 确保在到期时会唤醒当前任务
-                // 避免每次 poll 都添加新的定时器。
-                TIMER_MANAGER.add_timer(Timer::new_waker_timer(
-                    self.timer.lock().next_expire,
-                    cx.waker().clone(),
-                ));
-
-                Poll::Pending
-            }
-        }
-    }
-}
-
-pub struct TimerFdEvent(pub Arc<Mutex<TimerFd>>);
-impl TimerEvent for TimerFdEvent {
-    fn callback(self: Box<Self>) -> Option<Timer> {
-        let mut inner = self.0.lock();
-        inner.expirations += 1;
-        if let Some(fut) = &inner.wait_future {
-            fut.wake();
-        }
-        if inner.interval > Duration::ZERO {
-            let next_expire = get_current_time_duration() + inner.interval;
-            inner.next_expire = next_expire;
-            drop(inner); // 释放锁
-            Some(Timer::new(next_expire, self)) // 再次将自己放回 TIMER_MANAGER
-        } else {
-            inner.next_expire = Duration::ZERO;
-            None
-        }
-    }
-}
\ No newline at end of file
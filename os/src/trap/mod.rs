@@ -22,15 +22,15 @@ use hal::trap::{set_kernel_trap_entry, set_user_trap_entry, TrapContext, TrapCon
 use hal::util::backtrace;
 use crate::fs::procfs::interrupt::IRQ_COUNTER;
 use crate::mm::vm::{KernVmSpaceHal, PageFaultAccessType, UserVmSpaceHal};
-use crate::mm::KVMSPACE;
-use crate::signal::{SigInfo, SIGILL, SIGKILL, SIGSEGV, SIGTRAP};
+use crate::mm::{UserSliceRaw, KVMSPACE};
+use crate::signal::{SigInfo, SIGBUS, SIGILL, SIGKILL, SIGSEGV, SIGTRAP};
 use crate::utils::timer::TimerGuard;
 use hal::addr::VirtAddr;
 use crate::syscall::SyscallId::SYSCALL_GETPRIORITY;
 use crate::utils::async_utils::yield_now;
 use crate::executor;
 use crate::processor::context::SumGuard;
-use crate::syscall::{syscall, SysError};
+use crate::syscall::{syscall, SysError, PR_UNALIGN_NOPRINT, PR_UNALIGN_SIGBUS};
 use crate::task::task::TaskControlBlock;
 use crate::task::{
      current_user_token, current_task,
@@ -59,13 +59,16 @@ pub async fn user_trap_handler() -> bool {
             );
             let task = current_task().unwrap().clone();
             // task.set_stopped();
-            task.recv_sigs(SigInfo { si_signo: SIGTRAP, si_code: SigInfo::KERNEL, si_pid: None });
+            task.recv_sigs(SigInfo { si_signo: SIGTRAP, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 });
         }
         TrapType::Syscall => {
             let _sum = SumGuard::new();
-            let cx = current_task().unwrap().get_trap_cx();
+            let task = current_task().unwrap().clone();
+            let cx = task.get_trap_cx();
             let syscall_id = cx.syscall_id();
             *cx.sepc() += 4;
+            // shown in /proc/<pid>/syscall while it runs
+            task.set_syscall_nr(syscall_id as isize);
             // get system call return value
             let result = syscall(
                 cx.syscall_id(), 
@@ -78,6 +81,7 @@ pub async fn user_trap_handler() -> bool {
                     cx.syscall_arg_nth(5)
                 ]
             ).await;
+            task.set_syscall_nr(-1);
             // // cx is changed during sys_exec, so we have to call it again
             // cx.save_to(0, cx.ret_nth(0));
             // report that the syscall is interrupt
@@ -112,23 +116,48 @@ pub async fn user_trap_handler() -> bool {
                         "[user_trap_handler] task pid {}, tid {}, cannot handle page fault, addr {stval:#x} access_type: {access_type:?} epc: {epc:#x}",
                         task.pid(), task.tid()
                     );
-                    task.recv_sigs(SigInfo { si_signo: SIGSEGV, si_code: SigInfo::KERNEL, si_pid: None });
+                    task.recv_sigs(SigInfo { si_signo: SIGSEGV, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 });
                 }
             }
         }
+        TrapType::MisalignedAccess(addr) => {
+            let task = current_task().unwrap().clone();
+            if emulate_misaligned(&task, addr).is_err() {
+                log::warn!(
+                    "[user_trap_handler] task {} misaligned access at {addr:#x}, epc: {epc:#x}",
+                    task.tid()
+                );
+                task.recv_sigs(SigInfo { si_signo: SIGBUS, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 });
+            }
+        }
+        TrapType::FloatUnavailable => {
+            current_task().unwrap().get_trap_cx().fx_unavailable();
+        }
         TrapType::IllegalInstruction(_) => {
-            println!("[trap_handler] IllegalInstruction in application, kernel killed it.");
-            // illegal instruction exit code
             let task = current_task().unwrap();
-            task.recv_sigs(SigInfo { si_signo: SIGILL, si_code: SigInfo::KERNEL, si_pid: None });
+            let emulated = {
+                let _sum = SumGuard::new();
+                task.get_trap_cx().emulate_counter_read()
+            };
+            if !emulated {
+                println!("[trap_handler] IllegalInstruction in application, kernel killed it.");
+                // illegal instruction exit code
+                task.recv_sigs(SigInfo { si_signo: SIGILL, si_code: SigInfo::KERNEL, si_pid: None, si_value: 0 });
+            }
         }
         TrapType::Timer => {
             IRQ_COUNTER.lock().add_timer_irq_cnt();
             crate::timer::timer::TIMER_MANAGER.check();
+            crate::task::loadavg::calc_global_load();
             #[cfg(feature = "smp")]
             crate::processor::processor::current_processor().update_load_avg();
             set_next_trigger();
-            yield_now().await;
+            let task = current_task().unwrap();
+            task.check_cpu_itimers();
+            if executor::should_preempt(task) {
+                task.time_recorder().record_preempt();
+                yield_now().await;
+            }
         }
         TrapType::ExternalInterrupt => {
             // println!("ExternalInterrupt");
@@ -146,6 +175,52 @@ pub async fn user_trap_handler() -> bool {
     // println!("before trap_return");
 }
 
+/// emulate a misaligned load/store byte by byte if the process allows it
+fn emulate_misaligned(task: &Arc<TaskControlBlock>, addr: usize) -> Result<(), ()> {
+    let ctl = task.unalign_ctl();
+    if ctl & PR_UNALIGN_SIGBUS != 0 {
+        return Err(());
+    }
+    let _sum = SumGuard::new();
+    let cx = task.get_trap_cx();
+    let access = cx.decode_misaligned().ok_or(())?;
+    let bytes = UserSliceRaw::new(addr as *mut u8, access.size);
+    let mut loaded = 0;
+    if access.store {
+        let slice = task.with_mut_vm_space(|vm| bytes.ensure_write(vm)).ok_or(())?;
+        for (i, b) in slice.to_mut().iter_mut().enumerate() {
+            *b = (access.value >> (i * 8)) as u8;
+        }
+    } else {
+        let slice = task.with_mut_vm_space(|vm| bytes.ensure_read(vm)).ok_or(())?;
+        for (i, &b) in slice.to_ref().iter().enumerate() {
+            loaded |= (b as usize) << (i * 8);
+        }
+    }
+    if ctl & PR_UNALIGN_NOPRINT == 0 {
+        log::info!("[emulate_misaligned] task {} fix up {:?} at {addr:#x}", task.tid(), access);
+    }
+    cx.complete_misaligned(&access, loaded);
+    Ok(())
+}
+
+/// print the instructions around `epc` in kernel text
+fn dump_kernel_instructions(epc: usize) {
+    unsafe extern "C" {
+        fn stext();
+        fn etext();
+    }
+    // instructions are at least 2-byte aligned, dump them as halfwords
+    let start = epc.saturating_sub(16).max(stext as usize) & !1;
+    let end = (epc + 16).min(etext as usize);
+    println!("instructions around epc:");
+    for addr in (start..end).step_by(2) {
+        let half = unsafe { (addr as *const u16).read_volatile() };
+        let mark = if addr == epc { "<-" } else { "" };
+        println!("  {addr:#x}: {half:04x} {mark}");
+    }
+}
+
 #[no_mangle]
 /// set the new addr of __restore asm function in TRAMPOLINE page,
 /// set the reg a0 = trap_cx_ptr, reg a1 = phy addr of usr page table,
@@ -223,6 +298,7 @@ fn kernel_trap_handler() {
             // println!("interrupt: supervisor timer");
             IRQ_COUNTER.lock().add_timer_irq_cnt();
             crate::timer::timer::TIMER_MANAGER.check();
+            crate::task::loadavg::calc_global_load();
             set_next_trigger();
         }
         TrapType::ExternalInterrupt => {
@@ -231,6 +307,14 @@ fn kernel_trap_handler() {
             manager.handle_irq();
         }
         TrapType::Processed => {}
+        TrapType::Breakpoint => {
+            // a kernel ebreak/break is an assertion or a debugger breakpoint,
+            // report where it is before halting
+            println!("[kernel_trap_handler] breakpoint in kernel at epc {epc:#x}");
+            dump_kernel_instructions(epc);
+            backtrace();
+            unsafe { Instruction::shutdown(true) }
+        }
         _ => {
             // error!("other exception!!");
             panic!(
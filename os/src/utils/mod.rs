@@ -8,6 +8,7 @@ pub mod ring_buffer;
 pub mod macro_utils;
 pub mod round;
 pub mod timer;
+pub mod random;
 
 pub use async_utils::*;
 pub use path::*;
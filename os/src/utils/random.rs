@@ -0,0 +1,162 @@
+//! kernel random number generator
+//! a ChaCha20 based CSPRNG, seeded from the timer,
+//! shared by /dev/urandom and getrandom
+
+use crate::{sync::mutex::SpinNoIrqLock, timer::{get_current_time, get_current_time_ms}};
+
+/// "expand 32-byte k"
+const CHACHA_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+/// size of a ChaCha20 output block in bytes
+const BLOCK_SIZE: usize = 64;
+/// reseed once this many bytes are handed out
+const RESEED_BYTES: usize = 1 << 20;
+/// reseed at least this often (in ms)
+const RESEED_INTERVAL_MS: usize = 60 * 1000;
+
+#[inline(always)]
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+/// the ChaCha20 block function with a 64 bit counter and a 64 bit nonce
+fn chacha20_block(key: &[u32; 8], counter: u64, nonce: u64) -> [u32; 16] {
+    let mut init = [0u32; 16];
+    init[..4].copy_from_slice(&CHACHA_CONSTANTS);
+    init[4..12].copy_from_slice(key);
+    init[12] = counter as u32;
+    init[13] = (counter >> 32) as u32;
+    init[14] = nonce as u32;
+    init[15] = (nonce >> 32) as u32;
+    let mut state = init;
+    for _ in 0..10 {
+        // column round
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        // diagonal round
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    for (word, init) in state.iter_mut().zip(init.iter()) {
+        *word = word.wrapping_add(*init);
+    }
+    state
+}
+
+/// ChaCha20 CSPRNG with fast key erasure:
+/// the key is replaced after every request,
+/// so the bytes handed out cannot be recovered from a later state
+pub struct ChaChaRng {
+    key: [u32; 8],
+    counter: u64,
+    /// bytes handed out since the last reseed
+    generated: usize,
+    /// time of the last reseed (in ms)
+    last_reseed: usize,
+    seeded: bool,
+}
+
+impl ChaChaRng {
+    /// an unseeded generator, it seeds itself on first use
+    pub const fn new() -> Self {
+        Self {
+            key: [0; 8],
+            counter: 0,
+            generated: 0,
+            last_reseed: 0,
+            seeded: false,
+        }
+    }
+
+    /// mix the bytes into the key
+    pub fn add_entropy(&mut self, data: &[u8]) {
+        for chunk in data.chunks(32) {
+            let mut words = [0u32; 8];
+            for (i, byte) in chunk.iter().enumerate() {
+                words[i / 4] |= (*byte as u32) << (8 * (i % 4));
+            }
+            for (k, w) in self.key.iter_mut().zip(words.iter()) {
+                *k ^= *w;
+            }
+            self.rekey();
+        }
+    }
+
+    /// collect entropy from the timer and mix it into the key
+    fn reseed(&mut self) {
+        let mut entropy = [0u32; 8];
+        for word in entropy.iter_mut() {
+            // the low bits of the cycle counter jitter between samples,
+            // a block of work in between makes the gap less regular
+            let before = get_current_time();
+            let block = chacha20_block(&self.key, self.counter, before as u64);
+            let after = get_current_time();
+            *word = (before as u32) ^ (after as u32).rotate_left(16) ^ block[0];
+            self.counter = self.counter.wrapping_add(1);
+        }
+        // the address of the stack differs between harts
+        let stack = &entropy as *const _ as usize;
+        entropy[0] ^= stack as u32;
+        entropy[1] ^= (stack >> 32) as u32;
+        for (k, e) in self.key.iter_mut().zip(entropy.iter()) {
+            *k ^= *e;
+        }
+        self.rekey();
+        self.generated = 0;
+        self.last_reseed = get_current_time_ms();
+        self.seeded = true;
+    }
+
+    /// replace the key with the next output block
+    fn rekey(&mut self) {
+        let block = chacha20_block(&self.key, self.counter, u64::MAX);
+        self.counter = self.counter.wrapping_add(1);
+        self.key.copy_from_slice(&block[..8]);
+    }
+
+    fn need_reseed(&self) -> bool {
+        !self.seeded
+            || self.generated >= RESEED_BYTES
+            || get_current_time_ms().saturating_sub(self.last_reseed) >= RESEED_INTERVAL_MS
+    }
+
+    /// next random u32
+    pub fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_buf(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    /// fill the buf with random bytes
+    pub fn fill_buf(&mut self, buf: &mut [u8]) {
+        if self.need_reseed() {
+            self.reseed();
+        }
+        for chunk in buf.chunks_mut(BLOCK_SIZE) {
+            let block = chacha20_block(&self.key, self.counter, 0);
+            self.counter = self.counter.wrapping_add(1);
+            for (i, byte) in chunk.iter_mut().enumerate() {
+                *byte = (block[i / 4] >> (8 * (i % 4))) as u8;
+            }
+        }
+        self.generated += buf.len();
+        self.rekey();
+    }
+}
+
+/// the kernel random number generator
+pub static RNG: SpinNoIrqLock<ChaChaRng> = SpinNoIrqLock::new(ChaChaRng::new());
+
+/// fill the buf from RNG, a page at a time
+/// so that a large request does not hold the lock for long
+pub fn fill_random(buf: &mut [u8]) {
+    for chunk in buf.chunks_mut(4096) {
+        RNG.lock().fill_buf(chunk);
+    }
+}
@@ -38,8 +38,24 @@ impl RingBuffer {
         self.state == RingBufferState::FULL
     }
 
-    /// Read as much as possible to fill `buf`.
-    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+    /// Bytes held in the buffer.
+    pub fn len(&self) -> usize {
+        self.arr.len() - self.free_len()
+    }
+
+    /// Bytes that can still be written before the buffer is full.
+    pub fn free_len(&self) -> usize {
+        let n = self.arr.len();
+        match self.state {
+            RingBufferState::EMPTY => n,
+            RingBufferState::FULL => 0,
+            RingBufferState::NORMAL if self.head < self.tail => n - (self.tail - self.head),
+            RingBufferState::NORMAL => self.head - self.tail,
+        }
+    }
+
+    /// Copy as much as possible to fill `buf`, leaving it in the buffer.
+    pub fn peek(&self, buf: &mut [u8]) -> usize {
         if self.state == RingBufferState::EMPTY || buf.is_empty() {
             return 0;
         }
@@ -60,7 +76,16 @@ impl RingBuffer {
                 buf[right_len..ret_len].copy_from_slice(&self.arr[..(ret_len - right_len)]);
             }
         }
-        self.head = (self.head + ret_len) % n;
+        ret_len
+    }
+
+    /// Read as much as possible to fill `buf`.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let ret_len = self.peek(buf);
+        if ret_len == 0 {
+            return 0;
+        }
+        self.head = (self.head + ret_len) % self.arr.len();
 
         if self.head == self.tail {
             self.state = RingBufferState::EMPTY;
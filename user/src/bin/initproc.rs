@@ -86,7 +86,7 @@ fn main() -> i32 {
         kill(initproc_pid, SIGTERM);
     } else {
         println!("into user mode initproc wait");
-        let term_sig_action = SignalAction { handler: term_sig_handler as *const fn(i32) as usize, mask: SignalFlags::all() };
+        let term_sig_action = SignalAction { handler: term_sig_handler as *const fn(i32) as usize, mask: SignalFlags::all(), ..Default::default() };
         sigaction(SIGTERM, Some(&term_sig_action), None);
         loop {
             let mut exit_code: i32 = 0;
@@ -0,0 +1,164 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, open, pread, pwritev2, read, unlink, waitpid, write, writev, IoVec,
+    OpenFlags, RWF_APPEND, RWF_NOAPPEND,
+};
+
+const FILE: &str = "/append_test\0";
+/// records each process appends
+const ROUNDS: usize = 200;
+const REC: usize = 64;
+/// writev sends a record as this much and then the rest
+const HEAD: usize = 8;
+
+/// a record of `owner`, the owner byte everywhere but the sequence number
+fn record(owner: u8, seq: usize) -> [u8; REC] {
+    let mut rec = [owner; REC];
+    rec[1..5].copy_from_slice(&(seq as u32).to_le_bytes());
+    rec
+}
+
+fn iov(buf: &[u8]) -> IoVec {
+    IoVec { base: buf.as_ptr(), len: buf.len() }
+}
+
+/// append the records of `owner` through its own open file,
+/// every other one with writev in two pieces
+fn append(owner: u8) {
+    let fd = open(FILE, OpenFlags::WRONLY | OpenFlags::APPEND);
+    if fd < 0 {
+        panic!("open");
+    }
+    let fd = fd as usize;
+    for seq in 0..ROUNDS {
+        let rec = record(owner, seq);
+        let ret = if seq % 2 == 0 {
+            write(fd, &rec, REC)
+        } else {
+            writev(fd, &[iov(&rec[..HEAD]), iov(&rec[HEAD..])])
+        };
+        if ret != REC as isize {
+            panic!("append");
+        }
+    }
+    close(fd);
+}
+
+fn file_size() -> usize {
+    let fd = open(FILE, OpenFlags::RDONLY);
+    let mut buf = [0u8; REC];
+    let mut size = 0;
+    loop {
+        let len = pread(fd as usize, &mut buf, size);
+        if len <= 0 {
+            break;
+        }
+        size += len as usize;
+    }
+    close(fd as usize);
+    size
+}
+
+fn check_interleaved() {
+    if file_size() != 2 * ROUNDS * REC {
+        println!("test_append: {} bytes, expected {}", file_size(), 2 * ROUNDS * REC);
+        panic!("bytes lost or overwritten");
+    }
+    let fd = open(FILE, OpenFlags::RDONLY);
+    if fd < 0 {
+        panic!("open");
+    }
+    let mut next = [0usize; 2];
+    let mut rec = [0u8; REC];
+    for i in 0..2 * ROUNDS {
+        if pread(fd as usize, &mut rec, i * REC) != REC as isize {
+            panic!("read back");
+        }
+        let owner = rec[0];
+        let slot = match owner {
+            b'A' => 0,
+            b'B' => 1,
+            _ => {
+                panic!("record torn");
+            }
+        };
+        // whole records of one owner, each owner's in the order written
+        if rec != record(owner, next[slot]) {
+            panic!("record torn or out of order");
+        }
+        next[slot] += 1;
+    }
+    close(fd as usize);
+}
+
+/// RWF_APPEND and RWF_NOAPPEND override the open flags of a single pwritev2
+fn check_per_call() {
+    let size = file_size();
+    let fd = open(FILE, OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("open");
+    }
+    let fd = fd as usize;
+    let rec = record(b'C', 0);
+    if pwritev2(fd, &[iov(&rec[..HEAD]), iov(&rec[HEAD..])], 0, RWF_APPEND) != REC as isize {
+        panic!("pwritev2 RWF_APPEND");
+    }
+    let mut buf = [0u8; REC];
+    if pread(fd, &mut buf, size) != REC as isize || buf != rec {
+        panic!("RWF_APPEND did not write at the end");
+    }
+    // the file offset is untouched by a positioned write
+    if read(fd, &mut buf) != REC as isize || buf != record(b'A', 0) && buf != record(b'B', 0) {
+        panic!("RWF_APPEND moved the file offset");
+    }
+    close(fd);
+
+    let fd = open(FILE, OpenFlags::WRONLY | OpenFlags::APPEND);
+    if fd < 0 {
+        panic!("open");
+    }
+    let rec = record(b'D', 0);
+    let ret = pwritev2(fd as usize, &[iov(&rec)], 0, RWF_NOAPPEND);
+    close(fd as usize);
+    if ret != REC as isize {
+        panic!("pwritev2 RWF_NOAPPEND");
+    }
+    let fd = open(FILE, OpenFlags::RDONLY);
+    let ok = pread(fd as usize, &mut buf, 0) == REC as isize && buf == rec;
+    close(fd as usize);
+    if !ok || file_size() != size + REC {
+        panic!("RWF_NOAPPEND did not write at the offset");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    close(fd as usize);
+
+    let pid = fork();
+    if pid == 0 {
+        append(b'B');
+        exit(0);
+    }
+    append(b'A');
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status != 0 {
+        panic!("child failed");
+    }
+    check_interleaved();
+    check_per_call();
+    unlink(FILE);
+    println!("test_append passed");
+    0
+}
@@ -0,0 +1,129 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_gettime, close, exit, fork, mmap, munmap, open, pipe, read, waitpid, write, MmapFlags,
+    MmapProt, OpenFlags, TimeSpec, CLOCK_MONOTONIC,
+};
+
+const PAGE_SIZE: usize = 4096;
+const ROUNDS: usize = 200;
+/// the child maps its own page at the parent's address after this many rounds
+const REMAP_ROUND: usize = ROUNDS / 2;
+
+fn map_at(addr: usize) -> isize {
+    let flags = if addr == 0 {
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE
+    } else {
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE | MmapFlags::MAP_FIXED
+    };
+    mmap(addr, PAGE_SIZE, MmapProt::PROT_READ | MmapProt::PROT_WRITE, flags, 0, 0)
+}
+
+fn load(addr: usize) -> usize {
+    unsafe { (addr as *const usize).read_volatile() }
+}
+
+fn store(addr: usize, value: usize) {
+    unsafe { (addr as *mut usize).write_volatile(value) }
+}
+
+fn now_ns() -> usize {
+    let mut ts = TimeSpec { tv_sec: 0, tv_nsec: 0 };
+    clock_gettime(CLOCK_MONOTONIC, &mut ts);
+    ts.tv_sec * 1_000_000_000 + ts.tv_nsec
+}
+
+fn tag(owner: usize, round: usize) -> usize {
+    owner << 32 | round
+}
+
+/// the value of a counter in /proc/vmstat
+fn vmstat(name: &str) -> Option<usize> {
+    let fd = open("/proc/vmstat\0", OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut buf = [0u8; 512];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    let text = core::str::from_utf8(&buf[..len.max(0) as usize]).ok()?;
+    let line = text.lines().find(|line| line.split(' ').next() == Some(name))?;
+    line.split(' ').nth(1)?.parse().ok()
+}
+
+/// take turns with the other process, each checking that the shared address still
+/// shows its own page
+fn ping_pong(owner: usize, addr: usize, wait_fd: usize, kick_fd: usize) {
+    let mut byte = [0u8; 1];
+    for round in 0..ROUNDS {
+        if owner == 1 || round > 0 {
+            if read(wait_fd, &mut byte) != 1 {
+                panic!("lost the other process");
+            }
+        }
+        if owner == 2 && round == REMAP_ROUND {
+            // a fresh page at the same address must not show the old one
+            munmap(addr, PAGE_SIZE);
+            if map_at(addr) != addr as isize {
+                panic!("remap");
+            }
+            if load(addr) != 0 {
+                panic!("stale translation after remap");
+            }
+        } else if round > 0 && load(addr) != tag(owner, round - 1) {
+            panic!("saw the other process's page");
+        }
+        store(addr, tag(owner, round));
+        write(kick_fd, b"x", 1);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let addr = map_at(0);
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let addr = addr as usize;
+    store(addr, 0);
+    let mut to_child = [0usize; 2];
+    let mut to_parent = [0usize; 2];
+    pipe(&mut to_child);
+    pipe(&mut to_parent);
+    let flush_all = vmstat("nr_tlb_local_flush_all").expect("no /proc/vmstat");
+    let flush_asid = vmstat("nr_tlb_local_flush_asid").expect("no asid counter");
+
+    let pid = fork();
+    if pid == 0 {
+        // give the child its own page right away instead of a copy on write one
+        munmap(addr, PAGE_SIZE);
+        map_at(addr);
+        ping_pong(2, addr, to_child[0], to_parent[1]);
+        exit(0);
+    }
+    let start = now_ns();
+    ping_pong(1, addr, to_parent[0], to_child[1]);
+    let elapsed = now_ns() - start;
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status != 0 {
+        panic!("child failed");
+    }
+
+    let flush_all = vmstat("nr_tlb_local_flush_all").expect("no /proc/vmstat") - flush_all;
+    let flush_asid = vmstat("nr_tlb_local_flush_asid").expect("no asid counter") - flush_asid;
+    println!(
+        "test_asid: {} round trips, {} ns each, {} full and {} per-asid flushes",
+        ROUNDS, elapsed / ROUNDS, flush_all, flush_asid
+    );
+    // each round trip switches address spaces at least twice
+    if flush_all >= 2 * ROUNDS {
+        panic!("switching address spaces still flushes the whole TLB");
+    }
+    println!("test_asid passed");
+    0
+}
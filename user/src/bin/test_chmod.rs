@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String};
+use user_lib::{
+    chmod, chown, close, exit, faccessat2, fork, fstatat, open, setuid, unlink, waitpid, write,
+    OpenFlags, Stat, AT_EACCESS, AT_FDCWD, F_OK, R_OK, W_OK, X_OK,
+};
+
+const EACCES: isize = -13;
+/// the user the child drops to, who owns the file
+const USER: u32 = 1000;
+
+fn path(dir: &str) -> String {
+    format!("{}/chmod_file\0", dir)
+}
+
+fn stat(path: &str) -> Stat {
+    let mut stat = Stat::default();
+    if fstatat(AT_FDCWD, Some(path), &mut stat, 0) != 0 {
+        panic!("fstatat");
+    }
+    stat
+}
+
+/// what a task that is not root may do with the file, as an exit code
+fn as_user(path: &str) -> i32 {
+    if setuid(USER) != 0 {
+        return 1;
+    }
+    if open(path, OpenFlags::WRONLY) != EACCES {
+        return 2;
+    }
+    if faccessat2(path, W_OK, 0) != EACCES || faccessat2(path, W_OK, AT_EACCESS) != EACCES {
+        return 3;
+    }
+    if faccessat2(path, R_OK, 0) != 0 || faccessat2(path, F_OK, 0) != 0 {
+        return 4;
+    }
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return 5;
+    }
+    close(fd as usize);
+    // only the owner changes the mode, and only root gives the file away
+    if chown(path, 0, 0) == 0 {
+        return 6;
+    }
+    0
+}
+
+fn test_in(dir: &str) {
+    let path = path(dir);
+    let fd = open(&path, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    write(fd as usize, b"chmod", 5);
+    close(fd as usize);
+
+    if chmod(&path, 0o400) != 0 {
+        panic!("chmod");
+    }
+    if stat(&path).st_mode & 0o7777 != 0o400 {
+        panic!("fstatat does not show the new mode");
+    }
+    if chown(&path, USER, USER) != 0 {
+        panic!("chown");
+    }
+    let st = stat(&path);
+    if st.st_uid != USER || st.st_gid != USER {
+        panic!("fstatat does not show the new owner");
+    }
+    // no one may execute the file, not even root
+    if faccessat2(&path, X_OK, 0) != EACCES {
+        panic!("X_OK on a file with no exec bit");
+    }
+    // root writes regardless of the mode
+    let fd = open(&path, OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("root could not open a 0400 file for writing");
+    }
+    close(fd as usize);
+
+    let pid = fork();
+    if pid == 0 {
+        exit(as_user(&path));
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status >> 8 != 0 {
+        println!("test_chmod: {} child failed at step {}", dir, status >> 8);
+        panic!("permissions not enforced on a task that is not root");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for dir in ["", "/tmp"] {
+        unlink(&path(dir));
+        test_in(dir);
+        unlink(&path(dir));
+    }
+    println!("test_chmod passed");
+    0
+}
@@ -0,0 +1,94 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, chroot, close, exists, exit, fork, getcwd, mkdir, open, setuid, symlink, waitpid, OpenFlags,
+};
+
+const EPERM: isize = -1;
+const ENOENT: isize = -2;
+const ENOTDIR: isize = -20;
+
+fn touch(path: &str) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd >= 0 {
+        close(fd as usize);
+    }
+}
+
+fn cwd_is(expected: &str) -> bool {
+    let mut buf = [0u8; 64];
+    let len = getcwd(&mut buf);
+    len > 0 && &buf[..len as usize - 1] == expected.as_bytes()
+}
+
+/// runs in a child, the root stays with the child
+fn confined() {
+    if chroot("/chroot_test/inside\0") != ENOTDIR || chroot("/chroot_test/nonexist\0") != ENOENT {
+        panic!("chroot to a bad path");
+    }
+    if chroot("/chroot_test\0") != 0 {
+        panic!("chroot");
+    }
+    // the cwd stays outside until changed
+    if chdir("/\0") != 0 || !cwd_is("/") {
+        panic!("chdir to the new root");
+    }
+    if !exists("/inside\0") || exists("/chroot_outside\0") {
+        panic!("absolute paths not confined");
+    }
+    // `..` at the root stays at the root
+    if exists("../chroot_outside\0") || exists("/../../chroot_outside\0") || !exists("/sub/../../inside\0") {
+        panic!("escape through ..");
+    }
+    if chdir("/sub\0") != 0 || chdir("../../..\0") != 0 || !cwd_is("/") {
+        panic!("chdir above the root");
+    }
+    // symlinks resolve inside the root too
+    if exists("/abs_link\0") || exists("/rel_link\0") {
+        panic!("escape through a symlink");
+    }
+    if !exists("/in_link\0") {
+        panic!("symlink inside the root");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    mkdir("/chroot_test\0");
+    mkdir("/chroot_test/sub\0");
+    touch("/chroot_test/inside\0");
+    touch("/chroot_outside\0");
+    symlink("/chroot_outside\0", "/chroot_test/abs_link\0");
+    symlink("../../chroot_outside\0", "/chroot_test/sub/rel_link\0");
+    symlink("sub/rel_link\0", "/chroot_test/rel_link\0");
+    symlink("/inside\0", "/chroot_test/in_link\0");
+
+    let pid = fork();
+    if pid == 0 {
+        setuid(1000);
+        exit((chroot("/chroot_test\0") != EPERM) as i32);
+    }
+    let mut status = 0;
+    if waitpid(pid as usize, &mut status) != pid || status >> 8 != 0 {
+        panic!("chroot without privilege");
+    }
+
+    let pid = fork();
+    if pid == 0 {
+        confined();
+        exit(0);
+    }
+    if waitpid(pid as usize, &mut status) != pid || status >> 8 != 0 {
+        panic!("confined child");
+    }
+    // the parent was never confined
+    if !exists("/chroot_outside\0") {
+        panic!("chroot leaked to the parent");
+    }
+    println!("test_chroot passed");
+    0
+}
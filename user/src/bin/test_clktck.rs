@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{getauxval, sleep, times, Tms, AT_CLKTCK, AT_PAGESZ};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    if getauxval(AT_PAGESZ) != 4096 {
+        panic!("AT_PAGESZ");
+    }
+    let hz = getauxval(AT_CLKTCK);
+    if !(10..=1000).contains(&hz) {
+        panic!("AT_CLKTCK");
+    }
+    // the ticks times counts in are the ones AT_CLKTCK gives
+    let mut tms = Tms::default();
+    let start = times(&mut tms);
+    sleep(1000);
+    let elapsed = (times(&mut tms) - start) as usize;
+    if elapsed < hz * 9 / 10 || elapsed > hz * 3 / 2 {
+        panic!("ticks counted over a second");
+    }
+    println!("test_clktck passed");
+    0
+}
@@ -0,0 +1,105 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_gettime, clock_nanosleep, clock_settime, exit, fork, getpid, kill, sigaction, sleep,
+    waitpid, SignalAction, TimeSpec, CLOCK_MONOTONIC, CLOCK_REALTIME, CLOCK_THREAD_CPUTIME_ID,
+    SIGUSR1, TIMER_ABSTIME,
+};
+
+const EINTR: isize = 4;
+const EINVAL: isize = 22;
+/// one timer tick
+const TICK_NS: u64 = 10_000_000;
+
+fn to_ns(ts: &TimeSpec) -> u64 {
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn from_ns(ns: u64) -> TimeSpec {
+    TimeSpec { tv_sec: (ns / 1_000_000_000) as usize, tv_nsec: (ns % 1_000_000_000) as usize }
+}
+
+fn now_ns(clockid: usize) -> u64 {
+    let mut ts = TimeSpec::default();
+    clock_gettime(clockid, &mut ts);
+    to_ns(&ts)
+}
+
+fn usr1_handler(_signo: i32) {}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // an absolute monotonic sleep wakes at the deadline, within one tick
+    let deadline = now_ns(CLOCK_MONOTONIC) + 50_000_000;
+    if clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &from_ns(deadline), None) != 0 {
+        panic!("absolute monotonic sleep");
+    }
+    let woke = now_ns(CLOCK_MONOTONIC);
+    if woke < deadline || woke - deadline > TICK_NS {
+        println!("test_clock_nanosleep: woke {} ns after the deadline", woke as i64 - deadline as i64);
+        panic!("absolute monotonic sleep missed the deadline");
+    }
+    // a deadline in the past returns at once
+    if clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &from_ns(deadline), None) != 0 {
+        panic!("past deadline");
+    }
+
+    // argument checks
+    let req = from_ns(1_000_000);
+    if clock_nanosleep(CLOCK_MONOTONIC, 2, &req, None) != -EINVAL
+        || clock_nanosleep(CLOCK_MONOTONIC, 0, &TimeSpec { tv_sec: 0, tv_nsec: 1_000_000_000 }, None) != -EINVAL
+        || clock_nanosleep(CLOCK_THREAD_CPUTIME_ID, 0, &req, None) != -EINVAL
+    {
+        panic!("bad arguments accepted");
+    }
+
+    // stepping CLOCK_REALTIME forward past the deadline wakes an absolute realtime sleep,
+    // while a monotonic sleep does not notice
+    let start = now_ns(CLOCK_MONOTONIC);
+    let pid = fork();
+    if pid == 0 {
+        let deadline = from_ns(now_ns(CLOCK_REALTIME) + 10_000_000_000);
+        exit(if clock_nanosleep(CLOCK_REALTIME, TIMER_ABSTIME, &deadline, None) == 0 { 0 } else { 1 });
+    }
+    sleep(50);
+    if clock_settime(CLOCK_REALTIME, &from_ns(now_ns(CLOCK_REALTIME) + 20_000_000_000)) != 0 {
+        panic!("clock_settime");
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status != 0 || now_ns(CLOCK_MONOTONIC) - start > 2_000_000_000 {
+        panic!("absolute realtime sleep ignored the clock step");
+    }
+    // and realtime cannot be set earlier than the monotonic clock
+    if clock_settime(CLOCK_REALTIME, &from_ns(0)) != -EINVAL || clock_settime(CLOCK_MONOTONIC, &req) != -EINVAL {
+        panic!("bad clock_settime accepted");
+    }
+
+    // an interrupted relative sleep reports the time left
+    let mut action = SignalAction::default();
+    action.handler = usr1_handler as usize;
+    sigaction(SIGUSR1, Some(&action), None);
+    let parent = getpid();
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        kill(parent, SIGUSR1);
+        exit(0);
+    }
+    let mut rem = TimeSpec::default();
+    let ret = clock_nanosleep(CLOCK_MONOTONIC, 0, &from_ns(2_000_000_000), Some(&mut rem));
+    waitpid(pid as usize, &mut status);
+    if ret != -EINTR {
+        panic!("interrupted relative sleep did not return EINTR");
+    }
+    let rem = to_ns(&rem);
+    if rem == 0 || rem >= 2_000_000_000 {
+        panic!("remaining time");
+    }
+    println!("test_clock_nanosleep passed");
+    0
+}
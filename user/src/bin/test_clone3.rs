@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicIsize, Ordering};
+
+use user_lib::{
+    clone3, clone3_entry, exit, getpid, gettid, waitpid, yield_, CloneArgs, CLONE_ARGS_SIZE_VER0,
+    CLONE_ARGS_SIZE_VER2,
+};
+
+const CLONE_VM: u64 = 0x100;
+const CLONE_FS: u64 = 0x200;
+const CLONE_FILES: u64 = 0x400;
+const CLONE_SIGHAND: u64 = 0x800;
+const CLONE_THREAD: u64 = 0x10000;
+const CLONE_PARENT_SETTID: u64 = 0x100000;
+const SIGCHLD: u64 = 17;
+const EINVAL: isize = -22;
+const EEXIST: isize = -17;
+const STACK_SIZE: usize = 16 * 1024;
+
+#[repr(align(16))]
+struct Stack([u8; STACK_SIZE]);
+
+static mut THREAD_STACK: Stack = Stack([0; STACK_SIZE]);
+static THREAD_TID: AtomicIsize = AtomicIsize::new(0);
+static THREAD_PID: AtomicIsize = AtomicIsize::new(0);
+
+extern "C" fn thread_main(arg: usize) -> ! {
+    THREAD_PID.store(getpid(), Ordering::SeqCst);
+    THREAD_TID.store(gettid() + arg as isize, Ordering::SeqCst);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let args = CloneArgs { exit_signal: SIGCHLD, ..Default::default() };
+    if clone3(&args, CLONE_ARGS_SIZE_VER0 + 8) != EINVAL || clone3(&args, 0) != EINVAL {
+        panic!("unknown struct size");
+    }
+    let bad = CloneArgs { flags: SIGCHLD, ..Default::default() };
+    if clone3(&bad, CLONE_ARGS_SIZE_VER2) != EINVAL {
+        panic!("exit signal in flags");
+    }
+    let bad = CloneArgs { stack: 0x1000, ..args };
+    if clone3(&bad, CLONE_ARGS_SIZE_VER2) != EINVAL {
+        panic!("stack without a size");
+    }
+
+    // a plain fork through the first struct version
+    let pid = clone3(&args, CLONE_ARGS_SIZE_VER0);
+    if pid == 0 {
+        exit(3);
+    }
+    let mut status = 0;
+    if pid < 0 || waitpid(pid as usize, &mut status) != pid || status >> 8 != 3 {
+        panic!("fork-like clone3");
+    }
+
+    // pick the child's pid
+    let want: i32 = 4000;
+    let with_tid = CloneArgs { set_tid: &want as *const _ as u64, set_tid_size: 1, ..args };
+    let pid = clone3(&with_tid, CLONE_ARGS_SIZE_VER2);
+    if pid == 0 {
+        exit(if getpid() == 4000 { 0 } else { 1 });
+    }
+    if pid != 4000 || waitpid(pid as usize, &mut status) != pid || status >> 8 != 0 {
+        panic!("set_tid");
+    }
+    let taken = getpid() as i32;
+    let with_tid = CloneArgs { set_tid: &taken as *const _ as u64, set_tid_size: 1, ..args };
+    if clone3(&with_tid, CLONE_ARGS_SIZE_VER2) != EEXIST {
+        panic!("set_tid of a live task");
+    }
+
+    // a thread on its own stack
+    let mut parent_tid: u32 = 0;
+    let thread = CloneArgs {
+        flags: CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD | CLONE_PARENT_SETTID,
+        parent_tid: &mut parent_tid as *mut _ as u64,
+        stack: unsafe { THREAD_STACK.0.as_ptr() as u64 },
+        stack_size: STACK_SIZE as u64,
+        ..Default::default()
+    };
+    let tid = clone3_entry(&thread, CLONE_ARGS_SIZE_VER2, thread_main, 1);
+    if tid <= 0 {
+        panic!("thread clone3");
+    }
+    while THREAD_TID.load(Ordering::SeqCst) == 0 {
+        yield_();
+    }
+    if THREAD_TID.load(Ordering::SeqCst) != tid + 1 || THREAD_PID.load(Ordering::SeqCst) != getpid() {
+        panic!("thread did not run in our thread group");
+    }
+    if parent_tid as isize != tid {
+        panic!("CLONE_PARENT_SETTID");
+    }
+    println!("test_clone3 passed");
+    0
+}
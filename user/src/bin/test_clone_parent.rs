@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{clone, exit, fork, getpid, getppid, wait, waitpid};
+
+const CLONE_PARENT: usize = 0x8000;
+const SIGCHLD: usize = 17;
+const ECHILD: isize = 10;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let top = getpid();
+    let middle = fork();
+    if middle == 0 {
+        let sibling = clone(CLONE_PARENT | SIGCHLD, 0, 0);
+        if sibling == 0 {
+            // our parent is the parent of the task that cloned us
+            exit(if getppid() == top { 7 } else { 8 });
+        }
+        let mut status = 0;
+        // the sibling is not our child, we can not reap it
+        let ret = waitpid(sibling as usize, &mut status);
+        exit(if sibling > 0 && ret == -ECHILD { 0 } else { 1 });
+    }
+
+    let mut middle_ok = false;
+    let mut sibling_ok = false;
+    for _ in 0..2 {
+        let mut status = 0;
+        let pid = wait(&mut status);
+        if pid == middle {
+            middle_ok = status >> 8 == 0;
+        } else if pid > 0 {
+            sibling_ok = status >> 8 == 7;
+        }
+    }
+    let mut status = 0;
+    if !middle_ok || !sibling_ok || wait(&mut status) != -ECHILD {
+        println!("test_clone_parent failed: middle {}, sibling {}", middle_ok, sibling_ok);
+        return -1;
+    }
+    println!("test_clone_parent passed");
+    0
+}
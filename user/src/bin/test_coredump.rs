@@ -0,0 +1,155 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::{close, exit, fork, open, read, setrlimit, waitpid, write, OpenFlags, RLimit, RLIMIT_CORE, RLIM_INFINITY};
+
+const SIGSEGV: i32 = 11;
+const PAGE_SIZE: usize = 4096;
+const CORE_PATTERN: &str = "/proc/sys/kernel/core_pattern\0";
+
+fn set_core_pattern(pattern: &str) -> bool {
+    let fd = open(CORE_PATTERN, OpenFlags::WRONLY);
+    if fd < 0 {
+        return false;
+    }
+    let ret = write(fd as usize, pattern.as_bytes(), pattern.len());
+    close(fd as usize);
+    ret == pattern.len() as isize
+}
+
+/// fork a child that crashes on a null write with the given RLIMIT_CORE,
+/// return its pid and wait status
+fn crash_with_limit(limit: usize) -> (usize, i32) {
+    let pid = fork();
+    if pid == 0 {
+        setrlimit(RLIMIT_CORE, &RLimit { rlim_cur: limit, rlim_max: RLIM_INFINITY });
+        unsafe { (0 as *mut u8).write_volatile(1) };
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    (pid as usize, status)
+}
+
+/// read the whole core file of `pid`, return its size and first page
+fn read_core(pid: usize) -> Option<(usize, [u8; PAGE_SIZE])> {
+    let path = format!("/core.{}.{}\0", pid, SIGSEGV);
+    let fd = open(path.as_str(), OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut head = [0u8; PAGE_SIZE];
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut size = 0;
+    loop {
+        let n = read(fd as usize, &mut buf);
+        if n <= 0 {
+            break;
+        }
+        let n = n as usize;
+        if size < PAGE_SIZE {
+            let len = n.min(PAGE_SIZE - size);
+            head[size..size + len].copy_from_slice(&buf[..len]);
+        }
+        size += n;
+    }
+    close(fd as usize);
+    Some((size, head))
+}
+
+fn u16_at(buf: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([buf[off], buf[off + 1]])
+}
+
+fn u32_at(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+fn u64_at(buf: &[u8], off: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[off..off + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    if !set_core_pattern("/core.%p.%s\n") {
+        println!("test_coredump failed: can not set core_pattern");
+        return -1;
+    }
+    let mut pattern = [0u8; 64];
+    let fd = open(CORE_PATTERN, OpenFlags::RDONLY);
+    let n = read(fd as usize, &mut pattern);
+    close(fd as usize);
+    if n < 0 || &pattern[..n as usize] != b"/core.%p.%s\n" {
+        println!("test_coredump failed: core_pattern not updated");
+        return -1;
+    }
+
+    // no core by default, RLIMIT_CORE is 0
+    let (pid, status) = crash_with_limit(0);
+    if status & 0x7f != SIGSEGV || status & 0x80 != 0 || read_core(pid).is_some() {
+        println!("test_coredump failed: dumped with RLIMIT_CORE 0, status {:#x}", status);
+        return -1;
+    }
+
+    let (pid, status) = crash_with_limit(RLIM_INFINITY);
+    if status & 0x7f != SIGSEGV || status & 0x80 == 0 {
+        println!("test_coredump failed: status {:#x}", status);
+        return -1;
+    }
+    let (size, head) = match read_core(pid) {
+        Some(core) => core,
+        None => {
+            println!("test_coredump failed: no core file");
+            return -1;
+        }
+    };
+    // ELF64, little endian, ET_CORE, with a PT_NOTE and some PT_LOAD
+    if &head[..6] != b"\x7fELF\x02\x01" || u16_at(&head, 16) != 4 || u16_at(&head, 56) < 2 {
+        println!("test_coredump failed: bad elf header");
+        return -1;
+    }
+    let phoff = u64_at(&head, 32) as usize;
+    if u32_at(&head, phoff) != 4 {
+        println!("test_coredump failed: first segment is not PT_NOTE");
+        return -1;
+    }
+    // the first note is NT_PRSTATUS of the crashed thread
+    let note = u64_at(&head, phoff + 8) as usize;
+    let desc = note + 12 + 8;
+    if u32_at(&head, note + 8) != 1 || &head[note + 12..note + 16] != b"CORE"
+        || u16_at(&head, desc + 12) != SIGSEGV as u16
+        || u32_at(&head, desc + 32) != pid as u32
+    {
+        println!("test_coredump failed: bad NT_PRSTATUS");
+        return -1;
+    }
+    // the last PT_LOAD ends the file
+    let phnum = u16_at(&head, 56) as usize;
+    let last = phoff + (phnum - 1) * 56;
+    let end = (u64_at(&head, last + 8) + u64_at(&head, last + 32)) as usize;
+    if size != end {
+        println!("test_coredump failed: core size {} but segments end at {}", size, end);
+        return -1;
+    }
+
+    // a small limit truncates the core
+    let (pid, status) = crash_with_limit(PAGE_SIZE);
+    match read_core(pid) {
+        Some((size, _)) if size == PAGE_SIZE && status & 0x80 != 0 => {}
+        _ => {
+            println!("test_coredump failed: core not truncated at RLIMIT_CORE");
+            return -1;
+        }
+    }
+
+    set_core_pattern("core");
+    println!("test_coredump passed");
+    0
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+use user_lib::{
+    close, execve, exit, fork, pipe, read, read_file, setuid, waitpid, yield_,
+};
+
+const ENOENT: isize = -2;
+const EACCES: isize = -13;
+
+const ENVIRON: &[&str] = &["FOO=bar", "HOME=/root", "EMPTY="];
+
+/// NUL ended strings joined
+fn nul_joined(strs: &[&str]) -> Vec<u8> {
+    strs.iter().flat_map(|s| s.bytes().chain(Some(0))).collect()
+}
+
+#[no_mangle]
+pub fn main(args: &[&str]) -> i32 {
+    if args.len() == 3 && args[1] == "wait" {
+        // the exec'ed child: hang on until the parent closes the pipe
+        let fd: usize = args[2].parse().unwrap();
+        let mut buf = [0u8; 1];
+        read(fd, &mut buf);
+        return 0;
+    }
+    let me = args[0];
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        let fd = format!("{}", pipe_fd[0]);
+        execve(me, &[me, "wait", &fd], ENVIRON);
+        exit(1);
+    }
+    close(pipe_fd[0]);
+    let cmdline = format!("/proc/{}/cmdline\0", pid);
+    let environ = format!("/proc/{}/environ\0", pid);
+    // until the child is through execve
+    while read_file(&cmdline).map_or(true, |c| !c.starts_with(me.as_bytes())) {
+        yield_();
+    }
+    let wanted_cmdline = nul_joined(&[me, "wait", &format!("{}", pipe_fd[0])]);
+    if read_file(&cmdline) != Ok(wanted_cmdline) {
+        panic!("cmdline of the child");
+    }
+    if read_file(&environ) != Ok(nul_joined(ENVIRON)) {
+        panic!("environ of the child");
+    }
+
+    // another user may not read it
+    let reader = fork();
+    if reader == 0 {
+        setuid(1000);
+        exit((read_file(&environ) != Err(EACCES)) as i32);
+    }
+    let mut status = 0;
+    if waitpid(reader as usize, &mut status) != reader || status >> 8 != 0 {
+        panic!("environ readable by another user");
+    }
+
+    close(pipe_fd[1]);
+    if waitpid(pid as usize, &mut status) != pid || status >> 8 != 0 {
+        panic!("the child did not exit cleanly");
+    }
+    if read_file(&environ) != Err(ENOENT) {
+        panic!("environ of a reaped process");
+    }
+    println!("test_environ passed");
+    0
+}
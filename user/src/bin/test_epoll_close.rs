@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, epoll_create, epoll_ctl, epoll_wait, exit, fork, pipe, read, sleep, waitpid, write,
+    EpollEvent, EPOLLIN, EPOLL_CTL_ADD, EPOLL_CTL_DEL,
+};
+
+const EPIPE: isize = -32;
+const ENOENT: isize = -2;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let epfd = epoll_create();
+    if epfd < 0 {
+        panic!("epoll_create");
+    }
+    let epfd = epfd as usize;
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) != 0 {
+        panic!("pipe");
+    }
+    let event = EpollEvent { events: EPOLLIN, data: 1 };
+    if epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &event) != 0 {
+        panic!("EPOLL_CTL_ADD");
+    }
+
+    // a blocking wait is woken by a write from another task
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        write(fds[1], b"x", 1);
+        exit(0);
+    }
+    let mut events = [EpollEvent::default(); 4];
+    let n = epoll_wait(epfd, &mut events, -1);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if n != 1 || events[0].events & EPOLLIN == 0 || events[0].data != 1 {
+        panic!("wait for a write to the pipe");
+    }
+    let mut buf = [0u8; 4];
+    read(fds[0], &mut buf);
+
+    // closing the read end drops it from the interest set, and epoll
+    // does not keep the pipe open: the writer now has no reader
+    close(fds[0]);
+    if write(fds[1], b"x", 1) != EPIPE {
+        panic!("epoll kept a closed file open");
+    }
+    close(fds[1]);
+    if epoll_wait(epfd, &mut events, 0) != 0 {
+        panic!("closed file still reported");
+    }
+
+    // the fd number is free to be registered again
+    if pipe(&mut fds) != 0 {
+        panic!("pipe");
+    }
+    if epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &event) != 0 {
+        panic!("EPOLL_CTL_ADD of a reused fd");
+    }
+    if epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], &event) != 0 || epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], &event) != ENOENT {
+        panic!("EPOLL_CTL_DEL");
+    }
+    close(fds[0]);
+    close(fds[1]);
+    close(epfd);
+    println!("test_epoll_close passed");
+    0
+}
@@ -0,0 +1,134 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, epoll_create, epoll_ctl, epoll_wait, exit, fork, pipe, read, sleep, waitpid, write,
+    EpollEvent, EPOLLET, EPOLLIN, EPOLLONESHOT, EPOLL_CTL_ADD, EPOLL_CTL_MOD,
+};
+
+/// a pipe registered in its own epoll instance
+struct Watched {
+    epfd: usize,
+    rfd: usize,
+    wfd: usize,
+}
+
+impl Watched {
+    fn new(events: u32, data: u64) -> Self {
+        let mut fds = [0usize; 2];
+        assert_eq!(pipe(&mut fds), 0);
+        let epfd = epoll_create();
+        assert!(epfd >= 0);
+        let event = EpollEvent { events, data };
+        assert_eq!(epoll_ctl(epfd as usize, EPOLL_CTL_ADD, fds[0], &event), 0);
+        Self { epfd: epfd as usize, rfd: fds[0], wfd: fds[1] }
+    }
+
+    /// number of events reported by a non blocking wait
+    fn ready(&self) -> isize {
+        let mut events = [EpollEvent::default(); 4];
+        epoll_wait(self.epfd, &mut events, 0)
+    }
+
+    fn feed(&self) {
+        write(self.wfd, b"x", 1);
+    }
+
+    fn drain(&self) {
+        let mut buf = [0u8; 64];
+        read(self.rfd, &mut buf);
+    }
+
+    fn close(self) {
+        close(self.epfd);
+        close(self.rfd);
+        close(self.wfd);
+    }
+}
+
+fn test_level() {
+    let lt = Watched::new(EPOLLIN, 7);
+    if lt.ready() != 0 {
+        panic!("empty pipe reported readable");
+    }
+    lt.feed();
+    let mut events = [EpollEvent::default(); 4];
+    if epoll_wait(lt.epfd, &mut events, 0) != 1 || events[0].events & EPOLLIN == 0 || events[0].data != 7 {
+        panic!("level triggered event is wrong");
+    }
+    // reported again and again while data stays in the pipe
+    if lt.ready() != 1 || lt.ready() != 1 {
+        panic!("level triggered fd stopped reporting");
+    }
+    lt.drain();
+    if lt.ready() != 0 {
+        panic!("drained pipe reported readable");
+    }
+    lt.close();
+}
+
+fn test_edge() {
+    let et = Watched::new(EPOLLIN | EPOLLET, 8);
+    et.feed();
+    if et.ready() != 1 || et.ready() != 0 {
+        panic!("edge triggered fd should report exactly once");
+    }
+    // new data is a new edge, even though the pipe stayed readable
+    et.feed();
+    if et.ready() != 1 || et.ready() != 0 {
+        panic!("edge triggered fd missed new data");
+    }
+    et.drain();
+    if et.ready() != 0 {
+        panic!("edge triggered fd reported after drain");
+    }
+
+    // a blocking wait is woken by the next arrival
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        et.feed();
+        exit(0);
+    }
+    let mut events = [EpollEvent::default(); 4];
+    let n = epoll_wait(et.epfd, &mut events, -1);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if n != 1 || events[0].data != 8 || et.ready() != 0 {
+        panic!("blocking edge triggered wait");
+    }
+    et.close();
+}
+
+fn test_oneshot() {
+    let os = Watched::new(EPOLLIN | EPOLLONESHOT, 9);
+    os.feed();
+    if os.ready() != 1 || os.ready() != 0 {
+        panic!("oneshot fd should report once");
+    }
+    // disarmed, new data does not bring it back
+    os.feed();
+    if os.ready() != 0 {
+        panic!("oneshot fd reported while disarmed");
+    }
+    let event = EpollEvent { events: EPOLLIN | EPOLLONESHOT, data: 9 };
+    if epoll_ctl(os.epfd, EPOLL_CTL_MOD, os.rfd, &event) != 0 {
+        panic!("EPOLL_CTL_MOD");
+    }
+    if os.ready() != 1 || os.ready() != 0 {
+        panic!("re-armed oneshot fd");
+    }
+    os.close();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_level();
+    test_edge();
+    test_oneshot();
+    println!("test_epoll_et passed");
+    0
+}
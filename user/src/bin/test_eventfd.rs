@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, eventfd, exit, fork, read, wait, write, EFD_NONBLOCK, EFD_SEMAPHORE};
+
+const EAGAIN: isize = 11;
+
+fn read_u64(fd: usize) -> Result<u64, isize> {
+    let mut buf = [0u8; 8];
+    let ret = read(fd, &mut buf);
+    if ret != 8 {
+        return Err(ret);
+    }
+    Ok(u64::from_ne_bytes(buf))
+}
+
+fn write_u64(fd: usize, val: u64) -> isize {
+    write(fd, &val.to_ne_bytes(), 8)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // nonblocking read on an empty counter
+    let fd = eventfd(0, EFD_NONBLOCK);
+    if fd < 0 {
+        println!("test_eventfd failed: eventfd returned {}", fd);
+        return -1;
+    }
+    let fd = fd as usize;
+    if read_u64(fd) != Err(-EAGAIN) {
+        println!("test_eventfd failed: empty nonblocking read did not return EAGAIN");
+        return -1;
+    }
+    write_u64(fd, 3);
+    write_u64(fd, 4);
+    if read_u64(fd) != Ok(7) {
+        println!("test_eventfd failed: counter was not summed and reset");
+        return -1;
+    }
+    close(fd);
+
+    // semaphore mode counts down by one
+    let fd = eventfd(2, EFD_SEMAPHORE | EFD_NONBLOCK) as usize;
+    if read_u64(fd) != Ok(1) || read_u64(fd) != Ok(1) || read_u64(fd) != Err(-EAGAIN) {
+        println!("test_eventfd failed: semaphore mode");
+        return -1;
+    }
+    close(fd);
+
+    // blocking read wakes up when the child writes
+    let fd = eventfd(0, 0) as usize;
+    if fork() == 0 {
+        for _ in 0..5 {
+            write_u64(fd, 2);
+        }
+        exit(0);
+    }
+    let mut total = 0;
+    while total < 10 {
+        match read_u64(fd) {
+            Ok(val) => total += val,
+            Err(err) => {
+                println!("test_eventfd failed: read returned {}", err);
+                return -1;
+            }
+        }
+    }
+    let mut exit_code: i32 = 0;
+    if wait(&mut exit_code) == -1 || exit_code != 0 {
+        println!("test_eventfd failed: child exited with {}", exit_code);
+        return -1;
+    }
+    if total != 10 {
+        println!("test_eventfd failed: read {} in total", total);
+        return -1;
+    }
+    println!("test_eventfd passed");
+    0
+}
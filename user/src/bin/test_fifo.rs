@@ -0,0 +1,137 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String};
+use user_lib::{
+    close, exit, fork, fstatat, mkfifo, mknod, open, read, unlink, waitpid, write, OpenFlags, Stat,
+    AT_FDCWD, S_IFCHR, S_IFIFO,
+};
+
+const ENXIO: isize = -6;
+const EEXIST: isize = -17;
+const S_IFMT: u32 = 0o170000;
+const MESSAGE: &[u8] = b"through the fifo";
+
+fn path(dir: &str, name: &str) -> String {
+    format!("{}/{}\0", dir, name)
+}
+
+/// the writer, run in a child: blocks in open until the reader comes
+fn writer(path: &str) -> i32 {
+    let fd = open(path, OpenFlags::WRONLY);
+    if fd < 0 {
+        return 1;
+    }
+    if write(fd as usize, MESSAGE, MESSAGE.len()) != MESSAGE.len() as isize {
+        return 2;
+    }
+    close(fd as usize);
+    0
+}
+
+fn test_fifo(dir: &str) {
+    let fifo = path(dir, "fifo");
+    if mkfifo(&fifo, 0o644) != 0 {
+        panic!("mkfifo");
+    }
+    if mkfifo(&fifo, 0o644) != EEXIST {
+        panic!("mkfifo over an existing file");
+    }
+    let mut st = Stat::default();
+    if fstatat(AT_FDCWD, Some(&fifo), &mut st, 0) != 0 || st.st_mode & S_IFMT != S_IFIFO {
+        panic!("fstatat does not show a FIFO");
+    }
+
+    // no reader: a writer that will not wait fails, a reader goes on and sees EOF
+    if open(&fifo, OpenFlags::WRONLY | OpenFlags::NONBLOCK) != ENXIO {
+        panic!("O_NONBLOCK writer without a reader");
+    }
+    let fd = open(&fifo, OpenFlags::RDONLY | OpenFlags::NONBLOCK);
+    if fd < 0 {
+        panic!("O_NONBLOCK reader");
+    }
+    let mut buf = [0u8; 64];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    if len != 0 {
+        panic!("read from a FIFO without a writer");
+    }
+
+    // one task writes, the other reads, each open waits for the other
+    let pid = fork();
+    if pid == 0 {
+        exit(writer(&fifo));
+    }
+    let fd = open(&fifo, OpenFlags::RDONLY);
+    if fd < 0 {
+        panic!("open for reading");
+    }
+    let mut got = 0;
+    loop {
+        let len = read(fd as usize, &mut buf[got..]);
+        if len <= 0 {
+            break;
+        }
+        got += len as usize;
+    }
+    close(fd as usize);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status >> 8 != 0 {
+        println!("test_fifo: {} writer failed at step {}", dir, status >> 8);
+        panic!("writer");
+    }
+    if &buf[..got] != MESSAGE {
+        panic!("data lost in the FIFO");
+    }
+}
+
+fn test_node(dir: &str) {
+    // the serial port, major 4 minor 0
+    let node = path(dir, "ttyS0");
+    let dev = 4 << 8;
+    if mknod(&node, S_IFCHR | 0o600, dev) != 0 {
+        panic!("mknod of a char device");
+    }
+    let mut st = Stat::default();
+    if fstatat(AT_FDCWD, Some(&node), &mut st, 0) != 0 || st.st_mode & S_IFMT != S_IFCHR || st.st_rdev != dev {
+        panic!("fstatat does not show the device node");
+    }
+    let fd = open(&node, OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("open of the serial port node");
+    }
+    close(fd as usize);
+
+    // no device has major 1
+    let none = path(dir, "nodev");
+    if mknod(&none, S_IFCHR | 0o600, 1 << 8 | 3) != 0 {
+        panic!("mknod of a missing device");
+    }
+    if open(&none, OpenFlags::RDONLY) != ENXIO {
+        panic!("open of a node without a device");
+    }
+}
+
+/// a failed run leaves its nodes behind
+fn cleanup(dir: &str) {
+    for name in ["fifo", "ttyS0", "nodev"] {
+        unlink(&path(dir, name));
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for dir in ["", "/tmp"] {
+        cleanup(dir);
+        test_fifo(dir);
+        test_node(dir);
+        cleanup(dir);
+    }
+    println!("test_fifo passed");
+    0
+}
@@ -0,0 +1,137 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, dup, exit, fcntl_lock, flock, fork, getpid, open, sleep, unlink, waitpid, waitpid_nb,
+    Flock, OpenFlags, F_GETLK, F_RDLCK, F_SETLK, F_SETLKW, F_UNLCK, F_WRLCK, LOCK_EX, LOCK_NB,
+    LOCK_SH, LOCK_UN,
+};
+
+const EAGAIN: isize = 11;
+const FILE: &str = "/flocktest\0";
+
+fn open_file() -> usize {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd >= 0);
+    fd as usize
+}
+
+fn record(fd: usize, cmd: usize, l_type: i16, start: isize, len: isize) -> (isize, Flock) {
+    let mut lock = Flock { l_type, l_whence: 0, l_start: start, l_len: len, l_pid: 0 };
+    let ret = fcntl_lock(fd, cmd, &mut lock);
+    (ret, lock)
+}
+
+/// flock belongs to the open file description
+fn test_flock() {
+    let fd1 = open_file();
+    let fd2 = open_file();
+    if flock(fd1, LOCK_EX) != 0 {
+        panic!("LOCK_EX");
+    }
+    if flock(fd2, LOCK_EX | LOCK_NB) != -EAGAIN || flock(fd2, LOCK_SH | LOCK_NB) != -EAGAIN {
+        panic!("second description got a conflicting lock");
+    }
+    // downgrade lets shared holders in
+    if flock(fd1, LOCK_SH) != 0 || flock(fd2, LOCK_SH | LOCK_NB) != 0 {
+        panic!("shared locks should coexist");
+    }
+    if flock(fd1, LOCK_EX | LOCK_NB) != -EAGAIN {
+        panic!("upgrade while another holder is shared");
+    }
+    if flock(fd2, LOCK_UN) != 0 || flock(fd1, LOCK_EX | LOCK_NB) != 0 {
+        panic!("upgrade after unlock");
+    }
+    // a dup shares the lock, it goes away with the last descriptor
+    let dup_fd = dup(fd1) as usize;
+    close(fd1);
+    if flock(fd2, LOCK_EX | LOCK_NB) != -EAGAIN {
+        panic!("lock dropped while a dup is still open");
+    }
+    close(dup_fd);
+    if flock(fd2, LOCK_EX | LOCK_NB) != 0 {
+        panic!("lock kept after the last close");
+    }
+
+    // a blocked LOCK_EX resumes once the holder unlocks
+    let pid = fork();
+    if pid == 0 {
+        let fd = open_file();
+        exit(if flock(fd, LOCK_EX) == 0 { 0 } else { 1 });
+    }
+    sleep(50);
+    let mut status = 0;
+    if waitpid_nb(pid, &mut status) != -2 {
+        panic!("LOCK_EX did not block");
+    }
+    flock(fd2, LOCK_UN);
+    waitpid(pid as usize, &mut status);
+    close(fd2);
+    if status >> 8 != 0 {
+        panic!("blocked LOCK_EX failed");
+    }
+}
+
+/// record locks belong to the process and cover byte ranges
+fn test_record() {
+    let fd = open_file();
+    // [0, 5) and [5, 10) merge, then [2, 3) is punched out
+    if record(fd, F_SETLK, F_RDLCK, 0, 5).0 != 0
+        || record(fd, F_SETLK, F_RDLCK, 5, 5).0 != 0
+        || record(fd, F_SETLK, F_UNLCK, 2, 1).0 != 0
+        || record(fd, F_SETLK, F_WRLCK, 20, 0).0 != 0
+    {
+        panic!("F_SETLK");
+    }
+    let parent = getpid() as i32;
+
+    let pid = fork();
+    if pid == 0 {
+        let fd = open_file();
+        let (ret, lock) = record(fd, F_GETLK, F_WRLCK, 0, 1);
+        if ret != 0 || lock.l_type != F_RDLCK || lock.l_pid != parent || lock.l_start != 0 || lock.l_len != 2 {
+            exit(1);
+        }
+        if record(fd, F_GETLK, F_WRLCK, 2, 1).1.l_type != F_UNLCK {
+            exit(2);
+        }
+        let (_, lock) = record(fd, F_GETLK, F_RDLCK, 100, 1);
+        if lock.l_type != F_WRLCK || lock.l_start != 20 || lock.l_len != 0 {
+            exit(3);
+        }
+        if record(fd, F_SETLK, F_RDLCK, 3, 2).0 != 0 || record(fd, F_SETLK, F_WRLCK, 2, 1).0 != 0 {
+            exit(4);
+        }
+        if record(fd, F_SETLK, F_WRLCK, 4, 1).0 != -EAGAIN {
+            exit(5);
+        }
+        // blocks until the parent closes its descriptor
+        exit(if record(fd, F_SETLKW, F_WRLCK, 5, 1).0 == 0 { 0 } else { 6 });
+    }
+    sleep(50);
+    let mut status = 0;
+    if waitpid_nb(pid, &mut status) != -2 {
+        waitpid(pid as usize, &mut status);
+        println!("test_flock: child status {}", status >> 8);
+        panic!("record lock checks");
+    }
+    // closing any descriptor of the file drops the process' record locks
+    close(fd);
+    waitpid(pid as usize, &mut status);
+    if status >> 8 != 0 {
+        panic!("F_SETLKW was not granted after close");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    test_flock();
+    test_record();
+    unlink(FILE);
+    println!("test_flock passed");
+    0
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::slice_from_raw_parts_mut;
+
+use user_lib::{exit, fork, mmap, wait, MmapFlags, MmapProt};
+
+const PAGES: usize = 16;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let len = PAGES * 4096;
+    let ptr = mmap(
+        0, len,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0, 0
+    ) as *mut usize;
+    let slice = unsafe {
+        &mut *slice_from_raw_parts_mut(ptr, len / 8)
+    };
+    slice.fill(132);
+
+    if fork() == 0 {
+        if slice.iter().any(|&x| x != 132) {
+            println!("child: inherited data corrupted");
+            exit(-1);
+        }
+        slice.fill(169);
+        if slice.iter().any(|&x| x != 169) {
+            println!("child: write lost");
+            exit(-1);
+        }
+        println!("child: x = {}", slice[0]);
+        exit(0);
+    }
+    // parent writes its own value to half of the pages while child runs
+    for x in slice[..len / 16].iter_mut() {
+        *x = 255;
+    }
+    let mut exit_code: i32 = 0;
+    if wait(&mut exit_code) == -1 || exit_code != 0 {
+        println!("test_fork_cow failed: child exited with {}", exit_code);
+        return -1;
+    }
+    let (front, back) = slice.split_at(len / 16);
+    if front.iter().any(|&x| x != 255) || back.iter().any(|&x| x != 132) {
+        println!("test_fork_cow failed: parent sees child's writes");
+        return -1;
+    }
+    println!("parent: x = {}, test_fork_cow passed", slice[0]);
+    0
+}
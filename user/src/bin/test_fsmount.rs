@@ -0,0 +1,125 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::read_unaligned;
+
+use user_lib::{
+    close, fsconfig, fsmount, fsopen, listmount, mkdir, move_mount, open, read, statmount, write,
+    MntIdReq, OpenFlags, Statmount, AT_FDCWD, FSCONFIG_CMD_CREATE, FSCONFIG_SET_FLAG,
+    FSCONFIG_SET_STRING, FSMOUNT_CLOEXEC, FSOPEN_CLOEXEC, LSMT_ROOT, MNT_ID_REQ_SIZE_VER1,
+    MOVE_MOUNT_F_EMPTY_PATH, STATMOUNT_FS_TYPE, STATMOUNT_MNT_POINT,
+};
+
+const ENODEV: isize = -19;
+const EBUSY: isize = -16;
+const EINVAL: isize = -22;
+const MOUNT_POINT: &str = "/fsmount_test\0";
+const FILE: &str = "/fsmount_test/file\0";
+const HEADER: usize = core::mem::size_of::<Statmount>();
+
+static mut BUF: [u8; 1024] = [0; 1024];
+
+/// the NUL-terminated string at `off` after the statmount header
+fn string(buf: &[u8], off: u32) -> &str {
+    let s = &buf[HEADER + off as usize..];
+    let len = s.iter().position(|&b| b == 0).unwrap_or(s.len());
+    core::str::from_utf8(&s[..len]).unwrap_or("")
+}
+
+/// whether the mount table lists a tmpfs on the mount point
+fn listed() -> bool {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
+    let mut ids = [0u64; 32];
+    let req = MntIdReq { size: MNT_ID_REQ_SIZE_VER1, mnt_id: LSMT_ROOT, ..Default::default() };
+    let nr = listmount(&req, &mut ids, 0);
+    ids[..nr.max(0) as usize].iter().any(|&id| {
+        let req = MntIdReq {
+            size: MNT_ID_REQ_SIZE_VER1,
+            mnt_id: id,
+            param: STATMOUNT_MNT_POINT | STATMOUNT_FS_TYPE,
+            ..Default::default()
+        };
+        if statmount(&req, buf, 0) != 0 {
+            return false;
+        }
+        let sm = unsafe { read_unaligned(buf.as_ptr() as *const Statmount) };
+        string(buf, sm.mnt_point) == "/fsmount_test" && string(buf, sm.fs_type) == "tmpfs"
+    })
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    if fsopen("nosuchfs\0", 0) != ENODEV {
+        panic!("fsopen of an unknown file system");
+    }
+    let fs_fd = fsopen("tmpfs\0", FSOPEN_CLOEXEC);
+    if fs_fd < 0 {
+        panic!("fsopen");
+    }
+    let fs_fd = fs_fd as usize;
+    // not created yet, nothing to mount
+    if fsmount(fs_fd, 0, 0) != EBUSY {
+        panic!("fsmount before FSCONFIG_CMD_CREATE");
+    }
+    if fsconfig(fs_fd, FSCONFIG_SET_STRING, Some("source\0"), Some("test\0"), 0) != 0
+        || fsconfig(fs_fd, FSCONFIG_SET_STRING, Some("mode\0"), Some("0755\0"), 0) != 0
+        || fsconfig(fs_fd, FSCONFIG_SET_FLAG, Some("rw\0"), None, 0) != 0
+    {
+        panic!("fsconfig parameters");
+    }
+    if fsconfig(fs_fd, FSCONFIG_SET_FLAG, Some("ro\0"), Some("1\0"), 0) != EINVAL {
+        panic!("fsconfig flag with a value");
+    }
+    if fsconfig(fs_fd, FSCONFIG_CMD_CREATE, None, None, 0) != 0 {
+        panic!("FSCONFIG_CMD_CREATE");
+    }
+    if fsconfig(fs_fd, FSCONFIG_SET_FLAG, Some("ro\0"), None, 0) != EBUSY {
+        panic!("fsconfig after the superblock was created");
+    }
+    let mnt_fd = fsmount(fs_fd, FSMOUNT_CLOEXEC, 0);
+    if mnt_fd < 0 {
+        panic!("fsmount");
+    }
+    let mnt_fd = mnt_fd as usize;
+    if fsmount(fs_fd, 0, 0) != EBUSY {
+        panic!("second fsmount of one context");
+    }
+    close(fs_fd);
+
+    // a detached mount is nowhere to be seen
+    mkdir(MOUNT_POINT);
+    if listed() {
+        panic!("mount listed before it is attached");
+    }
+    if move_mount(mnt_fd as isize, "\0", AT_FDCWD, MOUNT_POINT, 0) != EINVAL {
+        panic!("move_mount without MOVE_MOUNT_F_EMPTY_PATH");
+    }
+    if move_mount(mnt_fd as isize, "\0", AT_FDCWD, MOUNT_POINT, MOVE_MOUNT_F_EMPTY_PATH) != 0 {
+        panic!("move_mount");
+    }
+    if move_mount(mnt_fd as isize, "\0", AT_FDCWD, MOUNT_POINT, MOVE_MOUNT_F_EMPTY_PATH) != EINVAL {
+        panic!("move_mount of an attached mount");
+    }
+    close(mnt_fd);
+    if !listed() {
+        panic!("attached mount not in the mount table");
+    }
+
+    // files now live in the new tmpfs
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    if fd < 0 || write(fd as usize, b"mounted", 7) != 7 {
+        panic!("create a file on the new mount");
+    }
+    close(fd as usize);
+    let fd = open(FILE, OpenFlags::RDONLY);
+    let mut buf = [0u8; 7];
+    if fd < 0 || read(fd as usize, &mut buf) != 7 || &buf != b"mounted" {
+        panic!("read back from the new mount");
+    }
+    close(fd as usize);
+    println!("test_fsmount passed");
+    0
+}
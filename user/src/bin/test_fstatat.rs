@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstat, fstatat, open, pipe, symlink, unlink, write, OpenFlags, Stat, AT_EMPTY_PATH,
+    AT_FDCWD, AT_SYMLINK_NOFOLLOW,
+};
+
+const ENOENT: isize = 2;
+const EBADF: isize = 9;
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFIFO: u32 = 0o010000;
+
+const FILE: &str = "/fstatat_file\0";
+const LINK: &str = "/fstatat_link\0";
+const CONTENT: &[u8] = b"stated through its fd";
+
+fn cleanup() {
+    unlink(LINK);
+    unlink(FILE);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cleanup();
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create");
+    }
+    let fd = fd as usize;
+    write(fd, CONTENT, CONTENT.len());
+    if symlink(FILE, LINK) != 0 {
+        panic!("symlink");
+    }
+
+    // an empty path with AT_EMPTY_PATH is fstat
+    let mut by_fd = Stat::default();
+    let mut st = Stat::default();
+    fstat(fd, &mut by_fd);
+    if fstatat(fd as isize, Some("\0"), &mut st, AT_EMPTY_PATH) != 0
+        || st.st_ino != by_fd.st_ino
+        || st.st_size != CONTENT.len() as i64
+    {
+        panic!("AT_EMPTY_PATH on a file fd");
+    }
+    if fstatat(fd as isize, None, &mut st, AT_EMPTY_PATH) != 0 || st.st_ino != by_fd.st_ino {
+        panic!("AT_EMPTY_PATH with a NULL path");
+    }
+    // not only for files with a path
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    if fstatat(pipe_fd[0] as isize, Some("\0"), &mut st, AT_EMPTY_PATH) != 0
+        || st.st_mode & S_IFMT != S_IFIFO
+    {
+        panic!("AT_EMPTY_PATH on a pipe");
+    }
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // without AT_EMPTY_PATH an empty path names nothing
+    if fstatat(fd as isize, Some("\0"), &mut st, 0) != -ENOENT {
+        panic!("empty path accepted without AT_EMPTY_PATH");
+    }
+    if fstatat(-5, Some("\0"), &mut st, AT_EMPTY_PATH) != -EBADF
+        || fstatat(99, Some("\0"), &mut st, AT_EMPTY_PATH) != -EBADF
+        || fstatat(99, Some("fstatat_file\0"), &mut st, 0) != -EBADF
+    {
+        panic!("bad dirfd");
+    }
+
+    // AT_SYMLINK_NOFOLLOW stats the link itself
+    if fstatat(AT_FDCWD, Some(LINK), &mut st, 0) != 0 || st.st_ino != by_fd.st_ino {
+        panic!("symlink not followed");
+    }
+    if fstatat(AT_FDCWD, Some(LINK), &mut st, AT_SYMLINK_NOFOLLOW) != 0
+        || st.st_mode & S_IFMT != S_IFLNK
+    {
+        panic!("AT_SYMLINK_NOFOLLOW followed the link");
+    }
+
+    // an O_PATH fd, also one on the link itself
+    let path_fd = open(FILE, OpenFlags::PATH);
+    if path_fd < 0 {
+        panic!("O_PATH open");
+    }
+    if fstatat(path_fd, Some("\0"), &mut st, AT_EMPTY_PATH) != 0
+        || st.st_ino != by_fd.st_ino
+        || st.st_mode & S_IFMT != S_IFREG
+    {
+        panic!("AT_EMPTY_PATH on an O_PATH fd");
+    }
+    close(path_fd as usize);
+    let link_fd = open(LINK, OpenFlags::PATH | OpenFlags::NOFOLLOW);
+    if link_fd < 0 {
+        panic!("O_PATH | O_NOFOLLOW open");
+    }
+    if fstatat(link_fd, Some("\0"), &mut st, AT_EMPTY_PATH) != 0 || st.st_mode & S_IFMT != S_IFLNK {
+        panic!("AT_EMPTY_PATH on an O_PATH fd of a symlink");
+    }
+    close(link_fd as usize);
+    close(fd);
+    cleanup();
+    println!("test_fstatat passed");
+    0
+}
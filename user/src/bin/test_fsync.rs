@@ -0,0 +1,145 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fdatasync, fsync, get_time_ms, getpid, mmap, munmap, open, pipe, pread, sync, unlink, write,
+    MmapFlags, MmapProt, OpenFlags,
+};
+
+const PAGE_SIZE: usize = 4096;
+/// the disk the root file system lives on
+const DISK: &str = "/dev/vda\0";
+const FILE: &str = "/fsync_test\0";
+/// disk read per request while searching it
+const CHUNK: usize = 64 * PAGE_SIZE;
+/// file data starts on a sector at least
+const SECTOR: usize = 512;
+/// how much of the start of a page is compared before the rest
+const SIG_LEN: usize = 32;
+const EINVAL: isize = -22;
+
+/// a page no earlier run can have left on the disk
+fn fill(buf: &mut [u8], nonce: usize, page: usize) {
+    let mut x = (nonce as u64) ^ ((page as u64 + 1) << 48) | 1;
+    for b in buf.iter_mut() {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *b = x as u8;
+    }
+}
+
+/// whether `pages` all sit somewhere on the raw disk
+fn on_disk(pages: &[&[u8]]) -> bool {
+    let fd = open(DISK, OpenFlags::RDONLY);
+    if fd < 0 {
+        panic!("open the disk");
+    }
+    let fd = fd as usize;
+    let chunk = mmap(0, CHUNK, MmapProt::PROT_READ | MmapProt::PROT_WRITE, MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE, 0, 0);
+    if chunk < 0 {
+        panic!("mmap");
+    }
+    let chunk = unsafe { core::slice::from_raw_parts_mut(chunk as *mut u8, CHUNK) };
+    let mut found = [false; 8];
+    let mut page = [0u8; PAGE_SIZE];
+    let mut offset = 0;
+    loop {
+        let len = pread(fd, chunk, offset);
+        if len <= 0 {
+            break;
+        }
+        let len = len as usize;
+        for pos in (0..len).step_by(SECTOR) {
+            for (i, expect) in pages.iter().enumerate() {
+                if found[i] || pos + SIG_LEN > len || chunk[pos..pos + SIG_LEN] != expect[..SIG_LEN] {
+                    continue;
+                }
+                // the page may run past the chunk
+                let whole = if pos + PAGE_SIZE <= len {
+                    &chunk[pos..pos + PAGE_SIZE]
+                } else if pread(fd, &mut page, offset + pos) == PAGE_SIZE as isize {
+                    &page[..]
+                } else {
+                    continue;
+                };
+                found[i] = whole == *expect;
+            }
+        }
+        if found[..pages.len()].iter().all(|&f| f) {
+            break;
+        }
+        offset += len;
+    }
+    munmap(chunk.as_ptr() as usize, CHUNK);
+    close(fd);
+    found[..pages.len()].iter().all(|&f| f)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    let nonce = (getpid() as usize) << 32 ^ get_time_ms() as usize;
+    let mut first = [0u8; PAGE_SIZE];
+    let mut second = [0u8; PAGE_SIZE];
+    fill(&mut first, nonce, 0);
+    fill(&mut second, nonce, 1);
+
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create");
+    }
+    let fd = fd as usize;
+    if write(fd, &first, PAGE_SIZE) != PAGE_SIZE as isize {
+        panic!("write");
+    }
+    if fsync(fd) != 0 {
+        panic!("fsync");
+    }
+    // the file is still open, so its pages stay cached and only fsync got them out
+    if !on_disk(&[&first]) {
+        panic!("data not on disk after fsync");
+    }
+
+    // the data of a grown file comes back after fdatasync too
+    if write(fd, &second, PAGE_SIZE) != PAGE_SIZE as isize {
+        panic!("write");
+    }
+    if fdatasync(fd) != 0 {
+        panic!("fdatasync");
+    }
+    if !on_disk(&[&first, &second]) {
+        panic!("data not on disk after fdatasync");
+    }
+
+    // and after sync, for one more page
+    fill(&mut first, nonce, 2);
+    if write(fd, &first, PAGE_SIZE) != PAGE_SIZE as isize {
+        panic!("write");
+    }
+    if sync() != 0 {
+        panic!("sync");
+    }
+    if !on_disk(&[&first]) {
+        panic!("data not on disk after sync");
+    }
+    close(fd);
+
+    // nothing to write back for a pipe
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) != 0 {
+        panic!("pipe");
+    }
+    let ret = fsync(fds[0]);
+    close(fds[0]);
+    close(fds[1]);
+    if ret != EINVAL {
+        panic!("fsync of a pipe did not fail with EINVAL");
+    }
+    unlink(FILE);
+    println!("test_fsync passed");
+    0
+}
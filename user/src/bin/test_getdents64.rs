@@ -0,0 +1,190 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use user_lib::{
+    close, getdents64, lseek, mkdir, mkfifo, open, symlink, unlink, OpenFlags, DIRENT64_NAME_OFFSET,
+    DT_DIR, DT_FIFO, DT_LNK, DT_REG, SEEK_SET,
+};
+
+const EINVAL: isize = -22;
+const FILES: usize = 1000;
+const DIR: &str = "/getdents_test";
+
+struct Dirent {
+    off: u64,
+    reclen: usize,
+    d_type: u8,
+    name: String,
+}
+
+/// split what getdents64 returned into records
+fn parse(buf: &[u8]) -> Vec<Dirent> {
+    let mut dents = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let off = u64::from_ne_bytes(buf[pos + 8..pos + 16].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(buf[pos + 16..pos + 18].try_into().unwrap()) as usize;
+        let d_type = buf[pos + 18];
+        let name = &buf[pos + DIRENT64_NAME_OFFSET..pos + reclen];
+        let len = name.iter().position(|&b| b == 0).expect("name without a NUL");
+        if reclen % 8 != 0 || reclen < DIRENT64_NAME_OFFSET + len + 1 {
+            panic!("bad d_reclen");
+        }
+        let name = String::from(core::str::from_utf8(&name[..len]).expect("bad name"));
+        dents.push(Dirent { off, reclen, d_type, name });
+        pos += reclen;
+    }
+    dents
+}
+
+/// read the directory to its end with a small buffer, by name
+fn read_all(fd: usize, seen: &mut BTreeMap<String, u8>) {
+    let mut buf = [0u8; 512];
+    loop {
+        let n = getdents64(fd, &mut buf);
+        if n < 0 {
+            panic!("getdents64");
+        }
+        if n == 0 {
+            return;
+        }
+        for dent in parse(&buf[..n as usize]) {
+            if seen.insert(dent.name, dent.d_type).is_some() {
+                panic!("an entry returned twice");
+            }
+        }
+    }
+}
+
+fn file_name(i: usize) -> String {
+    format!("{}/f{}\0", DIR, i)
+}
+
+fn setup() -> String {
+    if mkdir(&format!("{}\0", DIR)) != 0 {
+        panic!("mkdir");
+    }
+    for i in 0..FILES {
+        let fd = open(&file_name(i), OpenFlags::CREATE | OpenFlags::WRONLY);
+        if fd < 0 {
+            panic!("create a file");
+        }
+        close(fd as usize);
+    }
+    let long: String = core::iter::repeat('n').take(255).collect();
+    if mkdir(&format!("{}/d\0", DIR)) != 0
+        || symlink("f0\0", &format!("{}/l\0", DIR)) != 0
+        || mkfifo(&format!("{}/p\0", DIR), 0o644) != 0
+    {
+        panic!("create the special entries");
+    }
+    let fd = open(&format!("{}/{}\0", DIR, long), OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create a file with a 255 byte name");
+    }
+    close(fd as usize);
+    long
+}
+
+fn open_dir() -> usize {
+    let fd = open(&format!("{}\0", DIR), OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if fd < 0 {
+        panic!("open the directory");
+    }
+    fd as usize
+}
+
+fn test_enumerate(long: &str) {
+    let fd = open_dir();
+    let mut seen = BTreeMap::new();
+    read_all(fd, &mut seen);
+    close(fd);
+    for i in 0..FILES {
+        if seen.get(&format!("f{}", i)) != Some(&DT_REG) {
+            panic!("a regular file missing or not DT_REG");
+        }
+    }
+    if seen.get("d") != Some(&DT_DIR)
+        || seen.get("l") != Some(&DT_LNK)
+        || seen.get("p") != Some(&DT_FIFO)
+        || seen.get(long) != Some(&DT_REG)
+    {
+        panic!("wrong d_type of a special entry");
+    }
+}
+
+fn test_small_buffer() {
+    let fd = open_dir();
+    let mut buf = [0u8; 8];
+    let ret = getdents64(fd, &mut buf);
+    close(fd);
+    if ret != EINVAL {
+        panic!("a buffer too small for one entry");
+    }
+}
+
+fn test_unlink_while_reading() {
+    let fd = open_dir();
+    let mut buf = [0u8; 512];
+    let n = getdents64(fd, &mut buf);
+    if n <= 0 {
+        panic!("first getdents64");
+    }
+    let mut seen = BTreeMap::new();
+    for dent in parse(&buf[..n as usize]) {
+        seen.insert(dent.name, dent.d_type);
+    }
+    // drop every other file not seen yet
+    let mut removed = Vec::new();
+    for i in (0..FILES).step_by(2) {
+        let name = format!("f{}", i);
+        if !seen.contains_key(&name) {
+            unlink(&file_name(i));
+            removed.push(name);
+        }
+    }
+    read_all(fd, &mut seen);
+    close(fd);
+    for i in 0..FILES {
+        let name = format!("f{}", i);
+        if !removed.contains(&name) && !seen.contains_key(&name) {
+            panic!("a remaining file skipped after unlinks");
+        }
+    }
+}
+
+fn test_lseek() {
+    let fd = open_dir();
+    let mut buf = [0u8; 512];
+    let n = getdents64(fd, &mut buf);
+    let dents = parse(&buf[..n.max(0) as usize]);
+    if dents.len() < 2 {
+        panic!("getdents64 before lseek");
+    }
+    // go back to right after the first entry
+    if lseek(fd, dents[0].off as i64, SEEK_SET) < 0 {
+        panic!("lseek to a d_off");
+    }
+    let n = getdents64(fd, &mut buf);
+    let again = parse(&buf[..n.max(0) as usize]);
+    close(fd);
+    if again.first().map(|d| &d.name) != Some(&dents[1].name) || again[0].reclen != dents[1].reclen {
+        panic!("lseek to a d_off did not resume after that entry");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let long = setup();
+    test_enumerate(&long);
+    test_small_buffer();
+    test_lseek();
+    test_unlink_while_reading();
+    println!("test_getdents64 passed");
+    0
+}
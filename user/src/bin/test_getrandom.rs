@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, getrandom, open, read, OpenFlags, GRND_INSECURE, GRND_NONBLOCK, GRND_RANDOM};
+
+const EINVAL: isize = 22;
+
+/// a smoke test of the distribution, far from a real statistical test:
+/// about half of the bits set, most byte values seen, none of them dominating
+fn looks_random(buf: &[u8]) -> bool {
+    let mut counts = [0usize; 256];
+    let mut ones = 0usize;
+    for &b in buf {
+        counts[b as usize] += 1;
+        ones += b.count_ones() as usize;
+    }
+    let bits = buf.len() * 8;
+    let distinct = counts.iter().filter(|&&c| c > 0).count();
+    let max = *counts.iter().max().unwrap();
+    ones * 100 > bits * 45 && ones * 100 < bits * 55 && distinct > 200 && max < buf.len() / 256 * 4
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut a = [0u8; 64];
+    let mut b = [0u8; 64];
+    if getrandom(&mut a, 0) != 64 || getrandom(&mut b, 0) != 64 {
+        panic!("getrandom");
+    }
+    if a == b || a == [0u8; 64] {
+        panic!("successive reads are the same");
+    }
+
+    let mut big = [0u8; 8192];
+    if getrandom(&mut big, GRND_NONBLOCK) != big.len() as isize || !looks_random(&big) {
+        panic!("getrandom output does not look random");
+    }
+    if getrandom(&mut a, GRND_RANDOM) != 64 {
+        panic!("GRND_RANDOM");
+    }
+    if getrandom(&mut a, GRND_RANDOM | GRND_INSECURE) != -EINVAL || getrandom(&mut a, 0x100) != -EINVAL {
+        panic!("bad flags accepted");
+    }
+
+    let fd = open("/dev/urandom\0", OpenFlags::RDONLY);
+    if fd < 0 {
+        panic!("open /dev/urandom");
+    }
+    let fd = fd as usize;
+    let ok = read(fd, &mut a) == 64 && read(fd, &mut b) == 64 && read(fd, &mut big) == big.len() as isize;
+    close(fd);
+    if !ok {
+        panic!("read /dev/urandom");
+    }
+    if a == b || !looks_random(&big) {
+        panic!("/dev/urandom output does not look random");
+    }
+    println!("test_getrandom passed");
+    0
+}
@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, get_time_ms, getrusage, waitpid, yield_, Rusage, TimeVal, RUSAGE_CHILDREN,
+    RUSAGE_SELF, RUSAGE_THREAD,
+};
+
+const EINVAL: isize = 22;
+const BIG_SIZE: usize = 4 << 20;
+
+/// left untouched in bss until the maxrss check
+static mut BIG: [u8; BIG_SIZE] = [0; BIG_SIZE];
+
+fn us(tv: &TimeVal) -> usize {
+    tv.sec * 1_000_000 + tv.usec
+}
+
+fn usage(who: i32) -> Rusage {
+    let mut ru = Rusage::default();
+    getrusage(who, &mut ru);
+    ru
+}
+
+/// burn cpu in user mode for about ms
+fn spin(ms: isize) -> usize {
+    let start = get_time_ms();
+    let mut x = 0usize;
+    while get_time_ms() - start < ms {
+        for i in 0..10000 {
+            x = x.wrapping_mul(31).wrapping_add(i);
+        }
+    }
+    x
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let before = usage(RUSAGE_SELF);
+    core::hint::black_box(spin(200));
+    let after = usage(RUSAGE_SELF);
+    if us(&after.ru_utime) <= us(&before.ru_utime) {
+        panic!("ru_utime did not increase while spinning");
+    }
+    if us(&usage(RUSAGE_THREAD).ru_utime) == 0 {
+        panic!("RUSAGE_THREAD");
+    }
+    // the spin lasts several ticks, so the task was preempted
+    if after.ru_nivcsw <= before.ru_nivcsw {
+        panic!("no involuntary context switch counted");
+    }
+    for _ in 0..10 {
+        yield_();
+    }
+    if usage(RUSAGE_SELF).ru_nvcsw < after.ru_nvcsw + 10 {
+        panic!("yields not counted as voluntary switches");
+    }
+
+    // touching memory raises the peak resident set
+    let maxrss = after.ru_maxrss;
+    for offset in (0..BIG_SIZE).step_by(4096) {
+        unsafe { core::ptr::addr_of_mut!(BIG[offset]).write_volatile(1) };
+    }
+    if usage(RUSAGE_SELF).ru_maxrss < maxrss + 2048 {
+        panic!("ru_maxrss did not grow");
+    }
+
+    // a reaped child shows up in RUSAGE_CHILDREN
+    if us(&usage(RUSAGE_CHILDREN).ru_utime) != 0 {
+        panic!("children time before any child");
+    }
+    let pid = fork();
+    if pid == 0 {
+        core::hint::black_box(spin(100));
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    let children = usage(RUSAGE_CHILDREN);
+    if us(&children.ru_utime) == 0 || children.ru_maxrss == 0 {
+        panic!("reaped child not accounted");
+    }
+
+    let mut ru = Rusage::default();
+    if getrusage(2, &mut ru) != -EINVAL {
+        panic!("bad who accepted");
+    }
+    println!("test_getrusage passed");
+    0
+}
@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, link, open, pipe, unlink, OpenFlags, Stat};
+
+/// inode number of the path, 0 if it cannot be opened
+fn ino_of(path: &str) -> u64 {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return 0;
+    }
+    let mut stat = Stat::default();
+    let ret = fstat(fd as usize, &mut stat);
+    close(fd as usize);
+    if ret != 0 {
+        return 0;
+    }
+    stat.st_ino
+}
+
+fn create(path: &str) -> bool {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        return false;
+    }
+    close(fd as usize);
+    true
+}
+
+fn cleanup() {
+    for path in ["/tmp/ino_a\0", "/tmp/ino_b\0", "/tmp/ino_c\0"] {
+        unlink(path);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cleanup();
+    if !create("/tmp/ino_a\0") || !create("/tmp/ino_b\0") || link("/tmp/ino_a\0", "/tmp/ino_c\0") != 0 {
+        panic!("setup");
+    }
+    let (a, b, c) = (ino_of("/tmp/ino_a\0"), ino_of("/tmp/ino_b\0"), ino_of("/tmp/ino_c\0"));
+    if a == 0 || b == 0 {
+        panic!("missing inode number");
+    }
+    if a != c {
+        panic!("hard links report different numbers");
+    }
+    if a == b {
+        panic!("distinct files share a number");
+    }
+    // unlinking one name leaves the number of the other alone
+    if unlink("/tmp/ino_a\0") != 0 || ino_of("/tmp/ino_c\0") != c {
+        panic!("number changed after unlink");
+    }
+
+    // pseudo files keep their numbers across stats, and do not collide
+    let paths = ["/dev/null\0", "/dev/zero\0", "/proc/meminfo\0", "/proc/mounts\0", "/tmp\0", "/tmp/ino_b\0"];
+    let mut inos = [0u64; 6];
+    for (ino, path) in inos.iter_mut().zip(paths.iter()) {
+        *ino = ino_of(path);
+        if *ino == 0 || ino_of(path) != *ino {
+            println!("test_inode_number: {} has no stable number", path.trim_end_matches('\0'));
+            panic!("unstable number");
+        }
+    }
+    for i in 0..inos.len() {
+        for j in i + 1..inos.len() {
+            if inos[i] == inos[j] {
+                panic!("pseudo files share a number");
+            }
+        }
+    }
+
+    // anonymous inodes are numbered too
+    let mut fds = [0usize; 4];
+    if pipe(&mut fds[..2]) != 0 || pipe(&mut fds[2..]) != 0 {
+        panic!("pipe");
+    }
+    let mut stats = [Stat::default(), Stat::default()];
+    fstat(fds[0], &mut stats[0]);
+    fstat(fds[2], &mut stats[1]);
+    for fd in fds {
+        close(fd);
+    }
+    if stats[0].st_ino == 0 || stats[0].st_ino == stats[1].st_ino {
+        panic!("pipes share a number");
+    }
+    cleanup();
+    println!("test_inode_number passed");
+    0
+}
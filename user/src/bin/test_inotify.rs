@@ -0,0 +1,163 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use user_lib::{
+    chmod, close, exit, fork, inotify_add_watch, inotify_init1, inotify_rm_watch, mkdir, open,
+    read, rename, unlink, wait, write, OpenFlags, INOTIFY_EVENT_SIZE, IN_ATTRIB, IN_CREATE,
+    IN_DELETE, IN_DELETE_SELF, IN_IGNORED, IN_MODIFY, IN_MOVED_FROM, IN_MOVED_TO, IN_NONBLOCK,
+    IN_ONESHOT, IN_ONLYDIR,
+};
+
+const EAGAIN: isize = 11;
+const EINVAL: isize = 22;
+const ENOTDIR: isize = 20;
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    name: String,
+}
+
+/// read what is queued and split it into events
+fn read_events(fd: usize) -> Result<Vec<Event>, isize> {
+    let mut buf = [0u8; 512];
+    let n = read(fd, &mut buf);
+    if n < 0 {
+        return Err(n);
+    }
+    let mut events = Vec::new();
+    let mut pos = 0;
+    while pos < n as usize {
+        let field = |i: usize| u32::from_ne_bytes(buf[pos + i * 4..pos + i * 4 + 4].try_into().unwrap());
+        let len = field(3) as usize;
+        let name = &buf[pos + INOTIFY_EVENT_SIZE..pos + INOTIFY_EVENT_SIZE + len];
+        let end = name.iter().position(|&b| b == 0).unwrap_or(len);
+        events.push(Event {
+            wd: field(0) as i32,
+            mask: field(1),
+            cookie: field(2),
+            name: String::from(core::str::from_utf8(&name[..end]).unwrap()),
+        });
+        pos += INOTIFY_EVENT_SIZE + len;
+    }
+    Ok(events)
+}
+
+fn event(wd: i32, mask: u32, name: &str) -> Event {
+    Event { wd, mask, cookie: 0, name: String::from(name) }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    mkdir("/tmp/inotify_test\0");
+    let fd = inotify_init1(IN_NONBLOCK);
+    if fd < 0 {
+        panic!("inotify_init1");
+    }
+    let fd = fd as usize;
+    if read_events(fd) != Err(-EAGAIN) {
+        panic!("nonblocking read of an empty queue");
+    }
+    let dir_wd = inotify_add_watch(fd, "/tmp/inotify_test\0", IN_CREATE | IN_DELETE | IN_MODIFY | IN_MOVED_FROM | IN_MOVED_TO);
+    if dir_wd <= 0 {
+        panic!("watch the directory");
+    }
+    let dir_wd = dir_wd as i32;
+
+    // a file created in the directory, then written twice: the two writes are one event
+    let file = open("/tmp/inotify_test/a\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    if file < 0 {
+        panic!("create a file");
+    }
+    write(file as usize, b"abc", 3);
+    write(file as usize, b"def", 3);
+    close(file as usize);
+    if read_events(fd) != Ok(Vec::from([event(dir_wd, IN_CREATE, "a"), event(dir_wd, IN_MODIFY, "a")])) {
+        panic!("IN_CREATE and IN_MODIFY of a new file");
+    }
+
+    // a buffer too small for the first event
+    let mut small = [0u8; 8];
+    chmod("/tmp/inotify_test/a\0", 0o600);
+    let file_wd = inotify_add_watch(fd, "/tmp/inotify_test/a\0", IN_ATTRIB | IN_DELETE_SELF);
+    if file_wd <= 0 || file_wd as i32 == dir_wd {
+        panic!("watch the file");
+    }
+    let file_wd = file_wd as i32;
+    if inotify_add_watch(fd, "/tmp/inotify_test/a\0", IN_ATTRIB | IN_ONLYDIR) != -ENOTDIR {
+        panic!("IN_ONLYDIR on a file");
+    }
+    chmod("/tmp/inotify_test/a\0", 0o644);
+    if read(fd, &mut small) != -EINVAL {
+        panic!("read into a buffer too small for an event");
+    }
+    if read_events(fd) != Ok(Vec::from([event(file_wd, IN_ATTRIB, "")])) {
+        panic!("IN_ATTRIB of the watched file");
+    }
+
+    // a rename is a pair of events sharing a cookie
+    rename("/tmp/inotify_test/a\0", "/tmp/inotify_test/b\0");
+    let events = read_events(fd).expect("read the rename");
+    if events.len() != 2 || events[0].mask != IN_MOVED_FROM || events[0].name != "a"
+        || events[1].mask != IN_MOVED_TO || events[1].name != "b"
+        || events[0].cookie == 0 || events[0].cookie != events[1].cookie
+    {
+        panic!("IN_MOVED_FROM and IN_MOVED_TO of a rename");
+    }
+
+    // the watch on a deleted file ends with an IN_IGNORED
+    unlink("/tmp/inotify_test/b\0");
+    if read_events(fd) != Ok(Vec::from([
+        event(file_wd, IN_DELETE_SELF, ""),
+        event(file_wd, IN_IGNORED, ""),
+        event(dir_wd, IN_DELETE, "b"),
+    ])) {
+        panic!("events of an unlink");
+    }
+    if inotify_rm_watch(fd, file_wd) != -EINVAL {
+        panic!("remove a watch gone with its file");
+    }
+
+    // a oneshot watch reports once
+    if inotify_add_watch(fd, "/tmp/inotify_test\0", IN_CREATE | IN_ONESHOT) as i32 != dir_wd {
+        panic!("change the mask of a watch");
+    }
+    close(open("/tmp/inotify_test/c\0", OpenFlags::CREATE | OpenFlags::WRONLY) as usize);
+    close(open("/tmp/inotify_test/d\0", OpenFlags::CREATE | OpenFlags::WRONLY) as usize);
+    if read_events(fd) != Ok(Vec::from([event(dir_wd, IN_CREATE, "c"), event(dir_wd, IN_IGNORED, "")])) {
+        panic!("IN_ONESHOT watch");
+    }
+    if inotify_rm_watch(fd, dir_wd) != -EINVAL {
+        panic!("remove a oneshot watch after its event");
+    }
+    close(fd);
+
+    // a blocking read waits for the child to create the file
+    let fd = inotify_init1(0) as usize;
+    let wd = inotify_add_watch(fd, "/tmp/inotify_test\0", IN_CREATE) as i32;
+    if fork() == 0 {
+        close(open("/tmp/inotify_test/e\0", OpenFlags::CREATE | OpenFlags::WRONLY) as usize);
+        exit(0);
+    }
+    if read_events(fd) != Ok(Vec::from([event(wd, IN_CREATE, "e")])) {
+        panic!("blocking read of IN_CREATE");
+    }
+    let mut exit_code = 0;
+    wait(&mut exit_code);
+    if inotify_rm_watch(fd, wd) != 0 || read_events(fd) != Ok(Vec::from([event(wd, IN_IGNORED, "")])) {
+        panic!("IN_IGNORED of a removed watch");
+    }
+    close(fd);
+    for name in ["/tmp/inotify_test/c\0", "/tmp/inotify_test/d\0", "/tmp/inotify_test/e\0"] {
+        unlink(name);
+    }
+    println!("test_inotify passed");
+    0
+}
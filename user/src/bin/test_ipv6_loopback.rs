@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    accept_in6, bind_in6, close, connect_in6, exit, fork, listen, read, socket, waitpid, write,
+    SockaddrIn6, AF_INET6, SOCK_STREAM,
+};
+
+const IPPROTO_TCP: i32 = 6;
+const TEST_PORT: u16 = 4446;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let server = socket(AF_INET6, SOCK_STREAM, IPPROTO_TCP);
+    if server < 0 {
+        println!("test_ipv6_loopback failed: socket returned {}", server);
+        return -1;
+    }
+    let server = server as usize;
+    let addr = SockaddrIn6::new(SockaddrIn6::LOOPBACK, TEST_PORT);
+    if bind_in6(server, &addr) != 0 || listen(server, 4) != 0 {
+        println!("test_ipv6_loopback failed: cannot listen on [::1]:{}", TEST_PORT);
+        return -1;
+    }
+
+    let pid = fork();
+    if pid == 0 {
+        let client = socket(AF_INET6, SOCK_STREAM, IPPROTO_TCP) as usize;
+        if connect_in6(client, &addr) != 0 {
+            exit(1);
+        }
+        write(client, b"ping", 4);
+        let mut buf = [0u8; 4];
+        if read(client, &mut buf) != 4 || &buf != b"pong" {
+            exit(2);
+        }
+        close(client);
+        exit(0);
+    }
+
+    let mut peer = SockaddrIn6::default();
+    let conn = accept_in6(server, &mut peer);
+    if conn < 0 {
+        println!("test_ipv6_loopback failed: accept returned {}", conn);
+        return -1;
+    }
+    if peer.sin6_family != AF_INET6 as u16 || peer.sin6_addr != SockaddrIn6::LOOPBACK {
+        println!("test_ipv6_loopback failed: peer address {:?}", peer);
+        return -1;
+    }
+    let conn = conn as usize;
+    let mut buf = [0u8; 4];
+    if read(conn, &mut buf) != 4 || &buf != b"ping" {
+        println!("test_ipv6_loopback failed: server read");
+        return -1;
+    }
+    write(conn, b"pong", 4);
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    if exit_code != 0 {
+        println!("test_ipv6_loopback failed: client exited with {}", exit_code);
+        return -1;
+    }
+    close(conn);
+    close(server);
+    println!("test_ipv6_loopback passed");
+    0
+}
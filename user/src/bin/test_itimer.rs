@@ -0,0 +1,145 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use user_lib::{
+    alarm, exit, fork, get_time_ms, getitimer, setitimer, sigaction, waitpid, ITimerVal,
+    SignalAction, TimeVal, ITIMER_PROF, ITIMER_REAL, ITIMER_VIRTUAL, SIGALRM, SIGPROF, SIGVTALRM,
+};
+
+const EINVAL: isize = -22;
+
+static ALARMS: AtomicUsize = AtomicUsize::new(0);
+static VTALARMS: AtomicUsize = AtomicUsize::new(0);
+static PROFS: AtomicUsize = AtomicUsize::new(0);
+
+fn alrm_handler(_signo: i32) {
+    ALARMS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn vtalrm_handler(_signo: i32) {
+    VTALARMS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn prof_handler(_signo: i32) {
+    PROFS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn ms(ms: usize) -> TimeVal {
+    TimeVal { sec: ms / 1000, usec: ms % 1000 * 1000 }
+}
+
+fn spin(period_ms: isize) {
+    let start = get_time_ms();
+    while get_time_ms() < start + period_ms {}
+}
+
+/// spin until `counter` moves or a second is up
+fn spin_until(counter: &AtomicUsize) -> bool {
+    let start = get_time_ms();
+    while counter.load(Ordering::SeqCst) == 0 {
+        if get_time_ms() > start + 1000 {
+            return false;
+        }
+    }
+    true
+}
+
+fn test_real() {
+    let timer = ITimerVal { it_interval: ms(50), it_value: ms(50) };
+    if setitimer(ITIMER_REAL, &timer, None) != 0 {
+        panic!("setitimer ITIMER_REAL");
+    }
+    let mut curr = ITimerVal::default();
+    if getitimer(ITIMER_REAL, &mut curr) != 0
+        || curr.it_interval.sec != 0
+        || curr.it_interval.usec != 50_000
+        || curr.it_value.usec > 50_000
+    {
+        panic!("getitimer of an armed timer");
+    }
+    // a child does not inherit it
+    let pid = fork();
+    if pid == 0 {
+        let mut curr = ITimerVal::default();
+        getitimer(ITIMER_REAL, &mut curr);
+        exit((curr.it_value.sec != 0 || curr.it_value.usec != 0) as i32);
+    }
+    let mut status = 0;
+    if waitpid(pid as usize, &mut status) != pid || status >> 8 != 0 {
+        panic!("itimer inherited over fork");
+    }
+
+    spin(330);
+    let mut old = ITimerVal::default();
+    setitimer(ITIMER_REAL, &ITimerVal::default(), Some(&mut old));
+    let alarms = ALARMS.load(Ordering::SeqCst);
+    println!("test_itimer: {} SIGALRM in 330ms", alarms);
+    if !(4..=7).contains(&alarms) {
+        panic!("wrong number of SIGALRM from a 50ms timer");
+    }
+    if old.it_interval.usec != 50_000 {
+        panic!("old value of setitimer");
+    }
+    // disarmed, no more signals
+    spin(120);
+    if ALARMS.load(Ordering::SeqCst) != alarms {
+        panic!("SIGALRM after the timer was disarmed");
+    }
+}
+
+fn test_alarm() {
+    if alarm(5) != 0 {
+        panic!("alarm without a previous one");
+    }
+    if alarm(0) != 5 {
+        panic!("seconds left of the previous alarm");
+    }
+    let mut curr = ITimerVal::default();
+    getitimer(ITIMER_REAL, &mut curr);
+    if curr.it_value.sec != 0 || curr.it_value.usec != 0 {
+        panic!("alarm(0) did not cancel");
+    }
+}
+
+fn test_cpu_timers() {
+    let timer = ITimerVal { it_interval: TimeVal::default(), it_value: ms(30) };
+    if setitimer(ITIMER_VIRTUAL, &timer, None) != 0 || setitimer(ITIMER_PROF, &timer, None) != 0 {
+        panic!("setitimer of the cpu time timers");
+    }
+    if !spin_until(&VTALARMS) {
+        panic!("no SIGVTALRM after spinning");
+    }
+    if !spin_until(&PROFS) {
+        panic!("no SIGPROF after spinning");
+    }
+    // one shot, disarmed once fired
+    let mut curr = ITimerVal::default();
+    getitimer(ITIMER_VIRTUAL, &mut curr);
+    if curr.it_value.sec != 0 || curr.it_value.usec != 0 {
+        panic!("one shot ITIMER_VIRTUAL still armed");
+    }
+    if setitimer(3, &timer, None) != EINVAL {
+        panic!("setitimer of a bad timer");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = alrm_handler as usize;
+    sigaction(SIGALRM, Some(&action), None);
+    action.handler = vtalrm_handler as usize;
+    sigaction(SIGVTALRM, Some(&action), None);
+    action.handler = prof_handler as usize;
+    sigaction(SIGPROF, Some(&action), None);
+    test_real();
+    test_alarm();
+    test_cpu_timers();
+    println!("test_itimer passed");
+    0
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpgid, kill, setpgid, sleep, waitpid, waitpid_nb};
+
+const SIGKILL: i32 = 9;
+const SIGTERM: i32 = 15;
+const ESRCH: isize = 3;
+
+fn spawn_sleeper() -> usize {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    pid as usize
+}
+
+/// reap `pid` and return the signal that killed it
+fn killed_by(pid: usize) -> i32 {
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    status & 0x7f
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a two process pipeline in its own group, and a bystander in ours
+    let first = spawn_sleeper();
+    let second = spawn_sleeper();
+    let bystander = spawn_sleeper();
+    if setpgid(first, first) != 0 || setpgid(second, first) != 0 {
+        println!("test_kill_pgroup failed: setpgid");
+        return -1;
+    }
+    if getpgid(second) != first as isize {
+        println!("test_kill_pgroup failed: second is in group {}", getpgid(second));
+        return -1;
+    }
+    if setpgid(bystander, 99999) >= 0 {
+        println!("test_kill_pgroup failed: joined a missing group");
+        return -1;
+    }
+
+    if kill(-(first as isize), SIGTERM) != 0 {
+        println!("test_kill_pgroup failed: kill of the group");
+        return -1;
+    }
+    if killed_by(first) != SIGTERM || killed_by(second) != SIGTERM {
+        println!("test_kill_pgroup failed: group members not terminated");
+        return -1;
+    }
+    let mut status = 0;
+    if waitpid_nb(bystander as isize, &mut status) != -2 {
+        println!("test_kill_pgroup failed: bystander was signalled");
+        return -1;
+    }
+    if kill(-(first as isize), SIGTERM) != -ESRCH {
+        println!("test_kill_pgroup failed: empty group did not return ESRCH");
+        return -1;
+    }
+
+    // our own group holds us and the bystander, probe it without a signal
+    if kill(0, 0) != 0 {
+        println!("test_kill_pgroup failed: kill(0, 0)");
+        return -1;
+    }
+    kill(bystander as isize, SIGKILL);
+    if killed_by(bystander) != SIGKILL {
+        println!("test_kill_pgroup failed: bystander not killed");
+        return -1;
+    }
+    println!("test_kill_pgroup passed");
+    exit(0);
+}
@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, fstat, getrlimit, pipe, setrlimit, RLimit, Stat, RLIMIT_NOFILE};
+
+const EBADF: isize = 9;
+const EMFILE: isize = 24;
+/// well past the default soft limit of 1024 fds
+const NR_PIPES: usize = 1024;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut limit = RLimit::default();
+    getrlimit(RLIMIT_NOFILE, &mut limit);
+    let max = limit.rlim_max;
+    if limit.rlim_cur < 64 || max < 2 * NR_PIPES + 64 {
+        panic!("default RLIMIT_NOFILE");
+    }
+    if setrlimit(RLIMIT_NOFILE, &RLimit { rlim_cur: max, rlim_max: max }) != 0 {
+        panic!("raise the soft limit");
+    }
+
+    // the table grows as pipes are opened, each gets the lowest free fds
+    let mut fds = [0usize; 2];
+    for i in 0..NR_PIPES {
+        if pipe(&mut fds) != 0 {
+            println!("test_many_fds: pipe {} failed", i);
+            panic!("pipe");
+        }
+        if fds[0] != 3 + 2 * i || fds[1] != 4 + 2 * i {
+            panic!("pipe fds are not the lowest free ones");
+        }
+    }
+    let top = 3 + 2 * NR_PIPES;
+
+    // holes are filled lowest first
+    close(10);
+    close(1500);
+    if dup(0) != 10 || dup(0) != 1500 || dup(0) != top as isize {
+        panic!("freed fds are not reused lowest first");
+    }
+
+    // fill the table up to the soft limit
+    let mut last = top;
+    loop {
+        let fd = dup(0);
+        if fd == -EMFILE {
+            break;
+        }
+        if fd != last as isize + 1 {
+            panic!("dup past the table end");
+        }
+        last = fd as usize;
+    }
+    if last != max - 1 {
+        panic!("EMFILE before reaching the soft limit");
+    }
+
+    // lowering the limit keeps the open fds but refuses new ones
+    let low = RLimit { rlim_cur: 100, rlim_max: max };
+    if setrlimit(RLIMIT_NOFILE, &low) != 0 {
+        panic!("lower the soft limit");
+    }
+    let mut stat = Stat::default();
+    if fstat(last, &mut stat) != 0 {
+        panic!("fd above the lowered limit was closed");
+    }
+    close(50);
+    if dup(0) != 50 {
+        panic!("free fd below the lowered limit");
+    }
+    close(200);
+    if dup(0) != -EMFILE {
+        panic!("free fd above the lowered limit was handed out");
+    }
+    // a soft limit above the hard one is refused
+    if setrlimit(RLIMIT_NOFILE, &RLimit { rlim_cur: max + 1, rlim_max: max }) == 0 {
+        panic!("soft limit above the hard limit accepted");
+    }
+    if close(max) != -EBADF {
+        panic!("close past the table");
+    }
+
+    for fd in 3..=last {
+        close(fd);
+    }
+    println!("test_many_fds passed");
+    0
+}
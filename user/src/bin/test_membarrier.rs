@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, membarrier, waitpid, MEMBARRIER_CMD_GET_REGISTRATIONS, MEMBARRIER_CMD_GLOBAL,
+    MEMBARRIER_CMD_PRIVATE_EXPEDITED, MEMBARRIER_CMD_QUERY, MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED,
+};
+
+const EPERM: isize = -1;
+const EINVAL: isize = -22;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let supported = membarrier(MEMBARRIER_CMD_QUERY, 0);
+    let wanted = MEMBARRIER_CMD_GLOBAL | MEMBARRIER_CMD_PRIVATE_EXPEDITED | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED;
+    if supported < 0 || supported as i32 & wanted != wanted {
+        panic!("MEMBARRIER_CMD_QUERY");
+    }
+    if membarrier(MEMBARRIER_CMD_GLOBAL, 0) != 0 {
+        panic!("MEMBARRIER_CMD_GLOBAL");
+    }
+    if membarrier(MEMBARRIER_CMD_GLOBAL, 1) != EINVAL || membarrier(1 << 12, 0) != EINVAL {
+        panic!("bad command or flags");
+    }
+
+    // the expedited barrier has to be registered for first
+    if membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED, 0) != EPERM {
+        panic!("private expedited barrier without registration");
+    }
+    if membarrier(MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED, 0) != 0 {
+        panic!("MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED");
+    }
+    if membarrier(MEMBARRIER_CMD_GET_REGISTRATIONS, 0) as i32 & MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED == 0 {
+        panic!("registration not reported");
+    }
+    if membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED, 0) != 0 {
+        panic!("private expedited barrier");
+    }
+
+    // a child starts with no registrations
+    let pid = fork();
+    if pid == 0 {
+        let ok = membarrier(MEMBARRIER_CMD_GET_REGISTRATIONS, 0) == 0
+            && membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED, 0) == EPERM;
+        exit(if ok { 0 } else { 1 });
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status >> 8 != 0 {
+        panic!("registration inherited by fork");
+    }
+    println!("test_membarrier passed");
+    0
+}
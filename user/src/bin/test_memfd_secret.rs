@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, ftruncate, getpid, memfd_secret, mmap, process_vm_readv, read, waitpid,
+    IoVec, MmapFlags, MmapProt,
+};
+
+const EPERM: isize = 1;
+const EFAULT: isize = 14;
+const EINVAL: isize = 22;
+const ENOSYS: isize = 38;
+const PAGE_SIZE: usize = 4096;
+const LEN: usize = 2 * PAGE_SIZE;
+
+fn map(fd: usize, flags: MmapFlags) -> isize {
+    mmap(0, LEN, MmapProt::PROT_READ | MmapProt::PROT_WRITE, flags, fd, 0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    if memfd_secret(1) != -EINVAL {
+        panic!("unknown flag accepted");
+    }
+    let fd = memfd_secret(0);
+    if fd == -ENOSYS {
+        println!("test_memfd_secret: not supported here, skipped");
+        return 0;
+    }
+    if fd < 0 {
+        panic!("memfd_secret");
+    }
+    let fd = fd as usize;
+    if ftruncate(fd, LEN as isize) != 0 || ftruncate(fd, PAGE_SIZE as isize) != -EINVAL {
+        panic!("the size should be set exactly once");
+    }
+    if map(fd, MmapFlags::MAP_PRIVATE) != -EINVAL {
+        panic!("private mapping accepted");
+    }
+    let addr = map(fd, MmapFlags::MAP_SHARED);
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let secret = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, LEN) };
+    if secret.iter().any(|&b| b != 0) {
+        panic!("fresh secret memory is not zeroed");
+    }
+    for (i, b) in secret.iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(13);
+    }
+    if secret.iter().enumerate().any(|(i, &b)| b != (i as u8).wrapping_mul(13)) {
+        panic!("secret memory lost a write");
+    }
+
+    // the kernel cannot reach the contents by any way but the mapping itself
+    let mut buf = [0u8; 16];
+    if read(fd, &mut buf) != -EINVAL {
+        panic!("read on the fd");
+    }
+    let local = [IoVec::new_mut(&mut buf)];
+    let remote = [IoVec { base: addr as *const u8, len: buf.len() }];
+    if process_vm_readv(getpid() as usize, &local, &remote, 0) != -EFAULT {
+        panic!("process_vm_readv reached secret memory");
+    }
+
+    // a child neither inherits the mapping nor may map the fd itself
+    let pid = fork();
+    if pid == 0 {
+        if map(fd, MmapFlags::MAP_SHARED) != -EPERM {
+            exit(2);
+        }
+        let byte = unsafe { core::ptr::read_volatile(addr as *const u8) };
+        exit(byte as i32 + 3);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status & 0x7f == 0 {
+        println!("test_memfd_secret: child exited with {}", (status >> 8) & 0xff);
+        panic!("child reached secret memory");
+    }
+    if secret[1] != 13 {
+        panic!("fork disturbed the owner's mapping");
+    }
+    close(fd);
+    println!("test_memfd_secret passed");
+    0
+}
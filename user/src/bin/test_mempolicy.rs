@@ -0,0 +1,123 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    get_mempolicy, madvise, mbind, meminfo, mmap, munmap, set_mempolicy, MmapFlags, MmapProt,
+    MADV_HUGEPAGE, MPOL_BIND, MPOL_DEFAULT, MPOL_F_ADDR, MPOL_F_MEMS_ALLOWED, MPOL_INTERLEAVE,
+};
+
+const PAGE_SIZE: usize = 4096;
+const HUGE_SIZE: usize = 2 * 1024 * 1024;
+const EINVAL: isize = -22;
+const EFAULT: isize = -14;
+/// frames a single fault may take without having mapped a big page
+const SMALL: usize = 256;
+
+/// a /proc/meminfo value in pages
+fn meminfo_pages(name: &str) -> Option<usize> {
+    meminfo(name).map(|kb| kb * 1024 / PAGE_SIZE)
+}
+
+/// an anonymous mapping asking for big pages, with room for an aligned one,
+/// and the start of that aligned big page
+fn map_huge() -> (usize, usize) {
+    let len = 2 * HUGE_SIZE;
+    let addr = mmap(
+        0,
+        len,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0,
+        0,
+    );
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let addr = addr as usize;
+    if madvise(addr, len, MADV_HUGEPAGE) != 0 {
+        panic!("madvise");
+    }
+    (addr, (addr + HUGE_SIZE - 1) & !(HUGE_SIZE - 1))
+}
+
+/// frames taken by the first write to `base`
+fn fault_cost(base: usize) -> usize {
+    let before = meminfo_pages("MemFree:").expect("no MemFree");
+    unsafe { (base as *mut usize).write_volatile(1) };
+    let after = meminfo_pages("MemFree:").expect("no MemFree");
+    before.saturating_sub(after)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the task policy: only node 0 exists
+    if get_mempolicy(0, 0) != Ok((MPOL_DEFAULT, 0)) {
+        panic!("initial task policy");
+    }
+    if get_mempolicy(0, MPOL_F_MEMS_ALLOWED) != Ok((MPOL_DEFAULT, 1)) {
+        panic!("MPOL_F_MEMS_ALLOWED");
+    }
+    if set_mempolicy(MPOL_INTERLEAVE, 1 << 5) != EINVAL || set_mempolicy(MPOL_BIND, 0) != EINVAL {
+        panic!("policy without an existing node");
+    }
+    if set_mempolicy(MPOL_INTERLEAVE, 1) != 0 || get_mempolicy(0, 0) != Ok((MPOL_INTERLEAVE, 1)) {
+        panic!("set_mempolicy");
+    }
+
+    // an interleaving task policy keeps MADV_HUGEPAGE memory in base pages
+    let (addr, base) = map_huge();
+    let cost = fault_cost(base);
+    munmap(addr, 2 * HUGE_SIZE);
+    if cost > SMALL {
+        println!("test_mempolicy: interleaved fault took {} frames", cost);
+        panic!("big page under an interleaving task policy");
+    }
+    if set_mempolicy(MPOL_DEFAULT, 0) != 0 {
+        panic!("set_mempolicy back to default");
+    }
+
+    // so does an interleaving VMA policy, which comes before the task policy
+    let (addr, base) = map_huge();
+    if mbind(base, HUGE_SIZE, MPOL_INTERLEAVE, 1, 0) != 0 {
+        panic!("mbind");
+    }
+    if get_mempolicy(base, MPOL_F_ADDR) != Ok((MPOL_INTERLEAVE, 1)) {
+        panic!("get_mempolicy of the bound range");
+    }
+    if get_mempolicy(base + HUGE_SIZE, MPOL_F_ADDR) != Ok((MPOL_DEFAULT, 0)) {
+        panic!("mbind reached past its range");
+    }
+    let cost = fault_cost(base);
+    munmap(addr, 2 * HUGE_SIZE);
+    if cost > SMALL {
+        println!("test_mempolicy: interleaved fault took {} frames", cost);
+        panic!("big page under an interleaving VMA policy");
+    }
+
+    // a VMA policy keeping memory on one node leaves MADV_HUGEPAGE to decide,
+    // whatever the task policy
+    let (addr, base) = map_huge();
+    if set_mempolicy(MPOL_INTERLEAVE, 1) != 0 || mbind(base, HUGE_SIZE, MPOL_BIND, 1, 0) != 0 {
+        panic!("mbind over an interleaving task policy");
+    }
+    let cost = fault_cost(base);
+    munmap(addr, 2 * HUGE_SIZE);
+    set_mempolicy(MPOL_DEFAULT, 0);
+    // only riscv64 maps big pages in user space
+    if cfg!(target_arch = "riscv64") && cost < HUGE_SIZE / PAGE_SIZE {
+        println!("test_mempolicy: bound fault took {} frames", cost);
+        panic!("MPOL_BIND kept MADV_HUGEPAGE memory from a big page");
+    }
+
+    if mbind(base + 1, PAGE_SIZE, MPOL_BIND, 1, 0) != EINVAL {
+        panic!("mbind of an unaligned address");
+    }
+    if mbind(base, PAGE_SIZE, MPOL_BIND, 1, 0) != EFAULT {
+        panic!("mbind of unmapped memory");
+    }
+    println!("test_mempolicy passed");
+    0
+}
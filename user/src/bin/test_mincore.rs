@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mincore, mmap, munmap, MmapFlags, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+const ENOMEM: isize = 12;
+const EINVAL: isize = 22;
+const EFAULT: isize = 14;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let addr = mmap(0, 4 * PAGE_SIZE, MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS, 0, 0);
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let addr = addr as usize;
+    let mut vec = [0xffu8; 4];
+    if mincore(addr, 4 * PAGE_SIZE, &mut vec) != 0 || vec.iter().any(|&b| b & 1 != 0) {
+        panic!("pages never touched");
+    }
+    unsafe { ((addr + 2 * PAGE_SIZE) as *mut u8).write_volatile(1) };
+    if mincore(addr, 4 * PAGE_SIZE, &mut vec) != 0 || vec.map(|b| b & 1) != [0, 0, 1, 0] {
+        panic!("one page touched");
+    }
+    // the length is rounded up to whole pages
+    let mut one = [0u8; 1];
+    if mincore(addr + 2 * PAGE_SIZE, 1, &mut one) != 0 || one[0] & 1 != 1 {
+        panic!("a length shorter than a page");
+    }
+    if mincore(addr + 1, PAGE_SIZE, &mut vec) != -EINVAL {
+        panic!("an unaligned start");
+    }
+    if mincore(addr, PAGE_SIZE, unsafe { core::slice::from_raw_parts_mut(8 as *mut u8, 1) }) != -EFAULT {
+        panic!("a bad vector");
+    }
+    munmap(addr + PAGE_SIZE, PAGE_SIZE);
+    if mincore(addr, 4 * PAGE_SIZE, &mut vec) != -ENOMEM {
+        panic!("a range with a hole");
+    }
+    munmap(addr, 4 * PAGE_SIZE);
+    println!("test_mincore passed");
+    0
+}
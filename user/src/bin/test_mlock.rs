@@ -0,0 +1,115 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use user_lib::{
+    exit, fork, mlock, mlock2, mlockall, mmap, munlock, munlockall, munmap, read_to_string,
+    setrlimit, setuid, waitpid, MmapFlags, MmapProt, RLimit, MCL_CURRENT, MCL_FUTURE,
+    MLOCK_ONFAULT, RLIMIT_MEMLOCK,
+};
+
+const PAGE_SIZE: usize = 4096;
+const ENOMEM: isize = 12;
+const EINVAL: isize = 22;
+
+/// the VmLck line of the own status file, in kB
+fn locked_kb() -> usize {
+    let Ok(status) = read_to_string("/proc/self/status\0") else {
+        return usize::MAX;
+    };
+    status.lines()
+        .find_map(|line| line.strip_prefix("VmLck:"))
+        .and_then(|kb| kb.trim().strip_suffix(" kB")?.trim().parse().ok())
+        .unwrap_or(usize::MAX)
+}
+
+fn anon(pages: usize) -> usize {
+    mmap(0, pages * PAGE_SIZE, MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS, 0, 0) as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let addr = anon(4);
+    if locked_kb() != 0 {
+        panic!("VmLck before any mlock");
+    }
+    // the middle two pages, the range rounded out to whole pages
+    if mlock(addr + PAGE_SIZE + 100, PAGE_SIZE) != 0 || locked_kb() != 8 {
+        panic!("mlock two pages");
+    }
+    if mlock(addr + PAGE_SIZE, 2 * PAGE_SIZE) != 0 || locked_kb() != 8 {
+        panic!("mlock the locked pages again");
+    }
+    if munlock(addr + PAGE_SIZE, PAGE_SIZE) != 0 || locked_kb() != 4 {
+        panic!("munlock one page");
+    }
+    if mlock2(addr, PAGE_SIZE, MLOCK_ONFAULT) != 0 || locked_kb() != 8 {
+        panic!("mlock2 on fault");
+    }
+    if mlock2(addr, PAGE_SIZE, 2) != -EINVAL {
+        panic!("mlock2 with unknown flags");
+    }
+    munmap(addr, 4 * PAGE_SIZE);
+    if locked_kb() != 0 {
+        panic!("VmLck after munmap");
+    }
+    let hole = anon(3);
+    munmap(hole + PAGE_SIZE, PAGE_SIZE);
+    if mlock(hole, 3 * PAGE_SIZE) != -ENOMEM {
+        panic!("mlock over a hole");
+    }
+
+    // the areas mapped after mlockall(MCL_FUTURE) are locked as well
+    if mlockall(MCL_CURRENT | MCL_FUTURE) != 0 {
+        panic!("mlockall");
+    }
+    let before = locked_kb();
+    if before == 0 {
+        panic!("VmLck after mlockall");
+    }
+    let later = anon(2);
+    // the stack counts as much as it has grown to
+    if locked_kb() < before + 8 {
+        panic!("an area mapped after MCL_FUTURE");
+    }
+    if munlockall() != 0 || locked_kb() != 0 {
+        panic!("munlockall");
+    }
+    munmap(later, 2 * PAGE_SIZE);
+    if locked_kb() != 0 {
+        panic!("an area mapped after munlockall");
+    }
+    if mlockall(0) != -EINVAL {
+        panic!("mlockall without flags");
+    }
+
+    // an unprivileged process holds no more than RLIMIT_MEMLOCK
+    let pid = fork();
+    if pid == 0 {
+        let limit = RLimit { rlim_cur: PAGE_SIZE, rlim_max: PAGE_SIZE };
+        setrlimit(RLIMIT_MEMLOCK, &limit);
+        setuid(1000);
+        let addr = anon(2);
+        if mlock(addr, 2 * PAGE_SIZE) != -ENOMEM {
+            exit(1);
+        }
+        if mlock(addr, PAGE_SIZE) != 0 {
+            exit(2);
+        }
+        if mlock(addr + PAGE_SIZE, PAGE_SIZE) != -ENOMEM {
+            exit(3);
+        }
+        exit(0);
+    }
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    if exit_code != 0 {
+        panic!("RLIMIT_MEMLOCK of an unprivileged process");
+    }
+    println!("test_mlock passed");
+    0
+}
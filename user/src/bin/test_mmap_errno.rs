@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, mmap, munmap, open, unlink, write, MmapFlags, MmapProt, OpenFlags};
+
+const PAGE_SIZE: usize = 4096;
+const EACCES: isize = -13;
+const ENODEV: isize = -19;
+const EINVAL: isize = -22;
+const FILE: &str = "/tmp/mmap_errno\0";
+
+fn map(len: usize, prot: MmapProt, flags: MmapFlags, fd: isize) -> isize {
+    mmap(0, len, prot, flags, fd as usize, 0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    let rw = MmapProt::PROT_READ | MmapProt::PROT_WRITE;
+
+    let dir = open("/tmp\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if dir < 0 {
+        panic!("open /tmp");
+    }
+    let ret = map(PAGE_SIZE, MmapProt::PROT_READ, MmapFlags::MAP_PRIVATE, dir);
+    close(dir as usize);
+    if ret != ENODEV {
+        panic!("mmap of a directory");
+    }
+
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    write(fd as usize, &[1u8; PAGE_SIZE], PAGE_SIZE);
+    // whatever the mapping, the file has to be open for reading
+    let ret = map(PAGE_SIZE, MmapProt::PROT_READ, MmapFlags::MAP_PRIVATE, fd);
+    close(fd as usize);
+    if ret != EACCES {
+        panic!("mmap of a file open only for writing");
+    }
+
+    let fd = open(FILE, OpenFlags::RDONLY);
+    if map(PAGE_SIZE, rw, MmapFlags::MAP_SHARED, fd) != EACCES {
+        panic!("shared writable mmap of a file open only for reading");
+    }
+    if map(0, MmapProt::PROT_READ, MmapFlags::MAP_SHARED, fd) != EINVAL {
+        panic!("mmap of length 0");
+    }
+    if map(PAGE_SIZE, MmapProt::PROT_READ, MmapFlags::empty(), fd) != EINVAL {
+        panic!("mmap neither shared nor private");
+    }
+    // writes that stay private, or no writes at all, are fine
+    for (prot, flags) in [(rw, MmapFlags::MAP_PRIVATE), (MmapProt::PROT_READ, MmapFlags::MAP_SHARED)] {
+        let addr = map(PAGE_SIZE, prot, flags, fd);
+        if addr < 0 {
+            panic!("mmap of a file open for reading");
+        }
+        munmap(addr as usize, PAGE_SIZE);
+    }
+    close(fd as usize);
+    unlink(FILE);
+    println!("test_mmap_errno passed");
+    0
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, mmap, munmap, open, read, unlink, waitpid, write, MmapFlags, MmapProt, OpenFlags};
+
+const PAGE_SIZE: usize = 4096;
+const FILE: &str = "mmap_writeback\0";
+
+fn fill(byte: u8) {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    write(fd as usize, &[byte; PAGE_SIZE * 2], PAGE_SIZE * 2);
+    close(fd as usize);
+}
+
+fn check(byte: u8) {
+    let fd = open(FILE, OpenFlags::RDONLY);
+    if fd < 0 {
+        panic!("open for reading");
+    }
+    let mut buf = [0u8; PAGE_SIZE * 2];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    if len != buf.len() as isize || buf.iter().any(|&b| b != byte) {
+        panic!("writes through the mapping did not reach the file");
+    }
+}
+
+/// map the file shared, read first so that the first fault does not
+/// dirty the page, then write through the mapping
+fn map_and_store(byte: u8) -> usize {
+    let fd = open(FILE, OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("open for writing");
+    }
+    let prot = MmapProt::PROT_READ | MmapProt::PROT_WRITE;
+    let addr = mmap(0, PAGE_SIZE * 2, prot, MmapFlags::MAP_SHARED, fd as usize, 0);
+    close(fd as usize);
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let data = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, PAGE_SIZE * 2) };
+    if data.iter().any(|&b| b == byte) {
+        panic!("mapping does not show the file");
+    }
+    data.fill(byte);
+    addr as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    // munmap hands the pages to the file
+    fill(1);
+    let addr = map_and_store(2);
+    munmap(addr, PAGE_SIZE * 2);
+    check(2);
+
+    // and so does exit, without any munmap
+    let pid = fork();
+    if pid == 0 {
+        map_and_store(3);
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status >> 8 != 0 {
+        panic!("child could not write through the mapping");
+    }
+    check(3);
+    unlink(FILE);
+    println!("test_mmap_writeback passed");
+    0
+}
@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, mprotect, wait, MmapFlags, MmapProt, SIGSEGV};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let ptr = mmap(
+        0, 4096,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0, 0
+    ) as *mut usize;
+    unsafe { ptr.write_volatile(132) };
+    println!("before mprotect: x = {}", unsafe { ptr.read_volatile() });
+
+    let ret = mprotect(ptr as usize, 4096, MmapProt::PROT_READ);
+    if ret < 0 {
+        println!("mprotect failed: {}", ret);
+        return ret as i32;
+    }
+    println!("after mprotect: x = {}", unsafe { ptr.read_volatile() });
+
+    if fork() == 0 {
+        // should be killed by SIGSEGV
+        unsafe { ptr.write_volatile(169) };
+        println!("child: write to read-only page succeeded, test failed");
+        exit(1);
+    }
+    let mut exit_code: i32 = 0;
+    if wait(&mut exit_code) == -1 {
+        return -1;
+    }
+    println!("parent: child exited with {:#x}", exit_code);
+    let x = unsafe { ptr.read_volatile() };
+    println!("parent: x = {}", x);
+    if exit_code & 0x7f != SIGSEGV || x != 132 {
+        println!("test_mprotect failed");
+        return -1;
+    }
+    println!("test_mprotect passed");
+    0
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    madvise, mmap, mprotect, mremap, mseal, munmap, MmapFlags, MmapProt, MremapFlags, MADV_DONTNEED,
+};
+
+const PAGE_SIZE: usize = 4096;
+const EPERM: isize = -1;
+const ENOMEM: isize = -12;
+const EINVAL: isize = -22;
+
+/// three pages, only the middle one gets sealed
+fn test_sealed(base: usize) {
+    let rw = MmapProt::PROT_READ | MmapProt::PROT_WRITE;
+    let sealed = base + PAGE_SIZE;
+    unsafe { (sealed as *mut u8).write_volatile(7) };
+    if mseal(sealed, PAGE_SIZE, 0) != 0 {
+        panic!("mseal");
+    }
+    // sealing twice is fine, there is no way back
+    if mseal(sealed, PAGE_SIZE, 0) != 0 {
+        panic!("mseal of a sealed mapping");
+    }
+
+    // nothing is unmapped when a part of the range is sealed
+    if munmap(base, 3 * PAGE_SIZE) != EPERM {
+        panic!("munmap over a sealed mapping");
+    }
+    unsafe { (base as *mut u8).write_volatile(1) };
+    if mprotect(sealed, PAGE_SIZE, MmapProt::PROT_READ | MmapProt::PROT_WRITE | MmapProt::PROT_EXEC) != EPERM {
+        panic!("mprotect of a sealed mapping");
+    }
+    if madvise(sealed, PAGE_SIZE, MADV_DONTNEED) != EPERM {
+        panic!("MADV_DONTNEED on a sealed mapping");
+    }
+    if mremap(sealed, PAGE_SIZE, 2 * PAGE_SIZE, MremapFlags::MAYMOVE, 0) != EPERM {
+        panic!("mremap of a sealed mapping");
+    }
+    let flags = MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_FIXED;
+    if mmap(sealed, PAGE_SIZE, rw, flags, usize::MAX, 0) != EPERM {
+        panic!("MAP_FIXED over a sealed mapping");
+    }
+    if unsafe { (sealed as *const u8).read_volatile() } != 7 {
+        panic!("sealed mapping lost its contents");
+    }
+
+    // the pages around it are not sealed
+    if mprotect(base, PAGE_SIZE, MmapProt::PROT_READ) != 0 {
+        panic!("mprotect next to a sealed mapping");
+    }
+    if munmap(base + 2 * PAGE_SIZE, PAGE_SIZE) != 0 {
+        panic!("munmap next to a sealed mapping");
+    }
+    if mseal(base + 2 * PAGE_SIZE, PAGE_SIZE, 0) != ENOMEM {
+        panic!("mseal of an unmapped range");
+    }
+    if mseal(sealed + 1, PAGE_SIZE, 0) != EINVAL || mseal(sealed, PAGE_SIZE, 1) != EINVAL {
+        panic!("mseal with bad arguments");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let rw = MmapProt::PROT_READ | MmapProt::PROT_WRITE;
+    let flags = MmapFlags::MAP_PRIVATE | MmapFlags::MAP_ANONYMOUS;
+    let base = mmap(0, 3 * PAGE_SIZE, rw, flags, usize::MAX, 0);
+    if base < 0 {
+        println!("test_mseal failed: mmap");
+        return -1;
+    }
+    test_sealed(base as usize);
+    println!("test_mseal passed");
+    0
+}
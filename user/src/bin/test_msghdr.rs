@@ -0,0 +1,96 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::vec;
+
+use user_lib::{
+    bind, close, recvmsg, sendmsg, socket, CmsgHdr, IoVec, MsgHdr, SockaddrIn, SOL_SOCKET,
+};
+
+const AF_INET: i32 = 2;
+const SOCK_DGRAM: i32 = 2;
+const EMSGSIZE: isize = 90;
+const LOOPBACK: u32 = 0x7f000001;
+const TEST_PORT: u16 = 4447;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let receiver = socket(AF_INET, SOCK_DGRAM, 0) as usize;
+    let addr = SockaddrIn::new(LOOPBACK.to_be(), TEST_PORT.to_be());
+    if bind(receiver, &addr, core::mem::size_of::<SockaddrIn>() as u32) != 0 {
+        println!("test_msghdr failed: bind");
+        return -1;
+    }
+    let sender = socket(AF_INET, SOCK_DGRAM, 0) as usize;
+
+    // gather three pieces into one datagram, with a control message that is ignored
+    let parts: [&[u8]; 3] = [b"scatter", b"-", b"gather"];
+    let iovs = parts.map(IoVec::new);
+    let mut control = [0usize; 3];
+    let cmsg = CmsgHdr { len: core::mem::size_of::<CmsgHdr>() + 4, level: SOL_SOCKET, ty: 0x7f };
+    unsafe { (control.as_mut_ptr() as *mut CmsgHdr).write(cmsg) };
+    let mut msg = MsgHdr::default();
+    msg.name = &addr as *const _ as *mut u8;
+    msg.namelen = core::mem::size_of::<SockaddrIn>() as u32;
+    msg.iov = iovs.as_ptr();
+    msg.iovlen = iovs.len();
+    msg.control = control.as_mut_ptr() as *mut u8;
+    msg.controllen = core::mem::size_of_val(&control);
+    if sendmsg(sender, &msg, 0) != 14 {
+        println!("test_msghdr failed: sendmsg");
+        return -1;
+    }
+
+    // scatter it across two buffers
+    let mut first = [0u8; 4];
+    let mut second = [0u8; 16];
+    let iovs = [IoVec::new_mut(&mut first), IoVec::new_mut(&mut second)];
+    let mut peer = SockaddrIn::new(0, 0);
+    let mut recv_control = [0xffu8; 32];
+    let mut msg = MsgHdr::default();
+    msg.name = &mut peer as *mut _ as *mut u8;
+    msg.namelen = core::mem::size_of::<SockaddrIn>() as u32;
+    msg.iov = iovs.as_ptr();
+    msg.iovlen = iovs.len();
+    msg.control = recv_control.as_mut_ptr();
+    msg.controllen = recv_control.len();
+    let n = recvmsg(receiver, &mut msg, 0);
+    if n != 14 || &first != b"scat" || &second[..10] != b"ter-gather" {
+        println!("test_msghdr failed: recvmsg returned {}", n);
+        return -1;
+    }
+    if msg.namelen != core::mem::size_of::<SockaddrIn>() as u32
+        || peer.sin_family != AF_INET as u16
+        || peer.sin_addr != LOOPBACK.to_be()
+    {
+        println!("test_msghdr failed: peer address");
+        return -1;
+    }
+    if msg.controllen != 0 || msg.flags != 0 {
+        println!("test_msghdr failed: control length {}, flags {}", msg.controllen, msg.flags);
+        return -1;
+    }
+
+    // a datagram larger than the socket buffer can never be sent
+    let big = vec![0u8; 128 * 1024];
+    let iovs = [IoVec::new(&big)];
+    let mut msg = MsgHdr::default();
+    msg.name = &addr as *const _ as *mut u8;
+    msg.namelen = core::mem::size_of::<SockaddrIn>() as u32;
+    msg.iov = iovs.as_ptr();
+    msg.iovlen = 1;
+    if sendmsg(sender, &msg, 0) != -EMSGSIZE {
+        println!("test_msghdr failed: oversized datagram not rejected");
+        return -1;
+    }
+
+    close(sender);
+    close(receiver);
+    println!("test_msghdr passed");
+    0
+}
@@ -0,0 +1,132 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, mprotect, munmap, waitpid, MmapFlags, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+const EINVAL: isize = 22;
+const SIGSEGV: i32 = 11;
+
+fn map(pages: usize) -> usize {
+    let addr = mmap(
+        0,
+        pages * PAGE_SIZE,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0,
+        0,
+    );
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let addr = addr as usize;
+    for i in 0..pages {
+        unsafe { ((addr + i * PAGE_SIZE) as *mut usize).write_volatile(i + 1) };
+    }
+    addr
+}
+
+fn page(addr: usize, i: usize) -> usize {
+    addr + i * PAGE_SIZE
+}
+
+/// whether page `i` still holds what `map` wrote to it
+fn intact(addr: usize, i: usize) -> bool {
+    unsafe { (page(addr, i) as *const usize).read_volatile() == i + 1 }
+}
+
+/// whether touching `addr` kills a child with SIGSEGV
+fn faults(addr: usize) -> bool {
+    let pid = fork();
+    if pid == 0 {
+        let value = unsafe { (addr as *const usize).read_volatile() };
+        exit(value as i32 & 0x7f);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    status & 0x7f == SIGSEGV
+}
+
+/// unmap the middle of one VMA, splitting it in two
+fn test_middle() {
+    let addr = map(4);
+    if munmap(page(addr, 1), 2 * PAGE_SIZE) != 0 {
+        panic!("munmap middle");
+    }
+    if !intact(addr, 0) || !intact(addr, 3) {
+        panic!("outer pages lost after unmapping the middle");
+    }
+    if !faults(page(addr, 1)) || !faults(page(addr, 2)) {
+        panic!("middle pages still accessible");
+    }
+    // a range starting in the hole and ending inside the back part
+    if munmap(page(addr, 1), 3 * PAGE_SIZE - 1) != 0 {
+        panic!("munmap from a hole");
+    }
+    if !intact(addr, 0) || !faults(page(addr, 3)) {
+        panic!("range starting in a hole");
+    }
+    munmap(addr, 4 * PAGE_SIZE);
+    if !faults(addr) {
+        panic!("front page still accessible");
+    }
+}
+
+/// unmap a range that covers several VMAs and only partially overlaps those at its ends
+fn test_spanning() {
+    let addr = map(6);
+    // split the mapping into [0, 2) [2, 4) [4, 6)
+    if mprotect(page(addr, 2), 2 * PAGE_SIZE, MmapProt::PROT_READ) != 0 {
+        panic!("mprotect");
+    }
+    if munmap(page(addr, 1), 4 * PAGE_SIZE) != 0 {
+        panic!("munmap across VMAs");
+    }
+    if !intact(addr, 0) || !intact(addr, 5) {
+        panic!("pages outside the range lost");
+    }
+    for i in 1..5 {
+        if !faults(page(addr, i)) {
+            panic!("page inside the range still accessible");
+        }
+    }
+    // the hole can be mapped again
+    let again = mmap(
+        page(addr, 1),
+        4 * PAGE_SIZE,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE | MmapFlags::MAP_FIXED,
+        0,
+        0,
+    );
+    if again != page(addr, 1) as isize {
+        panic!("remap the hole");
+    }
+    if unsafe { (page(addr, 2) as *const usize).read_volatile() } != 0 {
+        panic!("old contents visible in the new mapping");
+    }
+    munmap(addr, 6 * PAGE_SIZE);
+}
+
+fn test_args() {
+    let addr = map(1);
+    if munmap(addr + 1, PAGE_SIZE) != -EINVAL {
+        panic!("unaligned address accepted");
+    }
+    if munmap(addr, 0) != 0 || !intact(addr, 0) {
+        panic!("zero length");
+    }
+    munmap(addr, PAGE_SIZE);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_middle();
+    test_spanning();
+    test_args();
+    println!("test_munmap passed");
+    0
+}
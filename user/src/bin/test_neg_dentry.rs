@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, get_time_ms, mkdir, open, rename, unlink, OpenFlags};
+
+const ENOENT: isize = 2;
+const EEXIST: isize = 17;
+
+/// open the path read only, return the error or close the fd
+fn probe(path: &str) -> isize {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd >= 0 {
+        close(fd as usize);
+        0
+    } else {
+        fd
+    }
+}
+
+fn create(path: &str) -> bool {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        return false;
+    }
+    close(fd as usize);
+    true
+}
+
+fn cleanup() {
+    for path in ["/negtest_file\0", "/negtest_src\0", "/negtest_dst\0", "/negtest_dir/inner\0"] {
+        unlink(path);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cleanup();
+
+    // repeated misses are answered from the negative dcache
+    let start = get_time_ms();
+    for _ in 0..1000 {
+        if probe("/negtest_file\0") != -ENOENT {
+            println!("test_neg_dentry failed: missing file did not return ENOENT");
+            return -1;
+        }
+    }
+    println!("test_neg_dentry: 1000 failed lookups took {} ms", get_time_ms() - start);
+
+    // a create drops the negative entry
+    if !create("/negtest_file\0") || probe("/negtest_file\0") != 0 {
+        println!("test_neg_dentry failed: created file is still negative");
+        cleanup();
+        return -1;
+    }
+    unlink("/negtest_file\0");
+    if probe("/negtest_file\0") != -ENOENT {
+        println!("test_neg_dentry failed: unlinked file still found");
+        cleanup();
+        return -1;
+    }
+
+    // so does a rename into the name
+    if probe("/negtest_dst\0") != -ENOENT
+        || !create("/negtest_src\0")
+        || rename("/negtest_src\0", "/negtest_dst\0") != 0
+        || probe("/negtest_dst\0") != 0
+    {
+        println!("test_neg_dentry failed: renamed file is still negative");
+        cleanup();
+        return -1;
+    }
+
+    // a missing directory, created later with a file inside
+    if probe("/negtest_dir/inner\0") != -ENOENT {
+        println!("test_neg_dentry failed: missing directory did not return ENOENT");
+        cleanup();
+        return -1;
+    }
+    let ret = mkdir("/negtest_dir\0");
+    if (ret != 0 && ret != -EEXIST) || !create("/negtest_dir/inner\0") || probe("/negtest_dir/inner\0") != 0 {
+        println!("test_neg_dentry failed: file in a new directory is still negative");
+        cleanup();
+        return -1;
+    }
+
+    cleanup();
+    println!("test_neg_dentry passed");
+    0
+}
@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::hint::black_box;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use user_lib::{
+    getpid, kill, sigaction, sigaltstack, SignalAction, SignalStack, SA_ONSTACK, SIGUSR1, SIGUSR2,
+};
+
+const STACK_SIZE: usize = 16 * 1024;
+const PATTERN: u64 = 0x5a5a_1234_a5a5_4321;
+
+static mut ALT_STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+/// 1: SIGUSR1 entered, 2: SIGUSR2 ran, 3: SIGUSR1 left
+static STEP: AtomicUsize = AtomicUsize::new(0);
+static OUT_OF_ORDER: AtomicBool = AtomicBool::new(false);
+static CLOBBERED: AtomicBool = AtomicBool::new(false);
+static USR1_SP: AtomicUsize = AtomicUsize::new(0);
+static USR2_SP: AtomicUsize = AtomicUsize::new(0);
+
+fn step(expected: usize) {
+    if STEP.fetch_add(1, Ordering::SeqCst) != expected - 1 {
+        OUT_OF_ORDER.store(true, Ordering::SeqCst);
+    }
+}
+
+fn usr1_handler(_signo: i32) {
+    step(1);
+    let locals = black_box([PATTERN; 16]);
+    USR1_SP.store(&locals as *const _ as usize, Ordering::SeqCst);
+    // SIGUSR2 is not blocked, its frame goes on top of this one
+    kill(getpid(), SIGUSR2);
+    if black_box(locals).iter().any(|&v| v != PATTERN) {
+        CLOBBERED.store(true, Ordering::SeqCst);
+    }
+    step(3);
+}
+
+fn usr2_handler(_signo: i32) {
+    step(2);
+    let locals = black_box([!PATTERN; 16]);
+    USR2_SP.store(&locals as *const _ as usize, Ordering::SeqCst);
+}
+
+fn run(flags: u32) -> (usize, usize) {
+    STEP.store(0, Ordering::SeqCst);
+    let mut action = SignalAction::default();
+    action.flags = flags;
+    action.handler = usr1_handler as usize;
+    sigaction(SIGUSR1, Some(&action), None);
+    action.handler = usr2_handler as usize;
+    sigaction(SIGUSR2, Some(&action), None);
+
+    let locals = black_box([PATTERN; 8]);
+    kill(getpid(), SIGUSR1);
+    if black_box(locals).iter().any(|&v| v != PATTERN) {
+        panic!("main frame clobbered");
+    }
+    if STEP.load(Ordering::SeqCst) != 3 || OUT_OF_ORDER.load(Ordering::SeqCst) {
+        panic!("handlers ran out of order");
+    }
+    if CLOBBERED.load(Ordering::SeqCst) {
+        panic!("outer handler frame clobbered");
+    }
+    let (usr1_sp, usr2_sp) = (USR1_SP.load(Ordering::SeqCst), USR2_SP.load(Ordering::SeqCst));
+    if usr2_sp >= usr1_sp {
+        panic!("inner frame not below the outer one");
+    }
+    (usr1_sp, usr2_sp)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    run(0);
+
+    // nested delivery on the alternate stack stays on it, below the outer frame
+    let base = unsafe { core::ptr::addr_of_mut!(ALT_STACK) as usize };
+    let ss = SignalStack { sp: base, flags: 0, size: STACK_SIZE };
+    sigaltstack(Some(&ss), None);
+    let (usr1_sp, usr2_sp) = run(SA_ONSTACK);
+    let on_alt = |sp: usize| sp > base && sp <= base + STACK_SIZE;
+    if !on_alt(usr1_sp) || !on_alt(usr2_sp) {
+        panic!("handlers left the alternate stack");
+    }
+    println!("test_nested_signal passed");
+    0
+}
@@ -0,0 +1,170 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_gettime, close, exit, fork, getpid, getpriority, nice, pipe, read, setpgid, setpriority,
+    setuid, waitpid, write, TimeSpec, CLOCK_MONOTONIC, CLOCK_THREAD_CPUTIME_ID, PRIO_PGRP,
+    PRIO_PROCESS, PRIO_USER,
+};
+
+const EPERM: isize = 1;
+const ESRCH: isize = 3;
+const EACCES: isize = 13;
+const EINVAL: isize = 22;
+/// spinners per nice value, together more than there are processors
+const NR_SPINNERS: usize = 4;
+const BUSY_NS: u64 = 400_000_000;
+
+fn now_ns(clockid: usize) -> u64 {
+    let mut ts = TimeSpec::default();
+    clock_gettime(clockid, &mut ts);
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn read_exact(fd: usize, buf: &mut [u8]) -> bool {
+    let mut done = 0;
+    while done < buf.len() {
+        let ret = read(fd, &mut buf[done..]);
+        if ret <= 0 {
+            return false;
+        }
+        done += ret as usize;
+    }
+    true
+}
+
+/// wait for the start signal, spin for a while and report the cpu time it got
+fn spinner(nice_value: i32, start: usize, result: usize) -> ! {
+    if setpriority(PRIO_PROCESS, 0, nice_value) != 0 {
+        exit(1);
+    }
+    let mut byte = [0u8; 1];
+    read(start, &mut byte);
+    let wall_start = now_ns(CLOCK_MONOTONIC);
+    let cpu_start = now_ns(CLOCK_THREAD_CPUTIME_ID);
+    while now_ns(CLOCK_MONOTONIC) - wall_start < BUSY_NS {}
+    let cpu = now_ns(CLOCK_THREAD_CPUTIME_ID) - cpu_start;
+    let mut msg = [0u8; 9];
+    msg[0] = (nice_value < 0) as u8;
+    msg[1..].copy_from_slice(&cpu.to_le_bytes());
+    write(result, &msg, msg.len());
+    exit(0);
+}
+
+fn test_values() {
+    if getpriority(PRIO_PROCESS, 0) != 20 {
+        panic!("default nice is not 0");
+    }
+    if setpriority(PRIO_PROCESS, 0, 5) != 0 || getpriority(PRIO_PROCESS, 0) != 15 {
+        panic!("setpriority");
+    }
+    if nice(2) != 13 {
+        panic!("nice");
+    }
+    if setpriority(PRIO_PROCESS, 0, 100) != 0 || getpriority(PRIO_PROCESS, 0) != 1 {
+        panic!("nice not clamped to 19");
+    }
+    if setpriority(PRIO_PROCESS, 0, 0) != 0 {
+        panic!("a privileged caller could not lower its nice value");
+    }
+    if setpriority(9, 0, 0) != -EINVAL || getpriority(9, 0) != -EINVAL {
+        panic!("bad selector accepted");
+    }
+    if getpriority(PRIO_PROCESS, 999_999) != -ESRCH || getpriority(PRIO_PGRP, 999_999) != -ESRCH {
+        panic!("missing target");
+    }
+    if getpriority(PRIO_USER, 0) < 20 {
+        panic!("PRIO_USER");
+    }
+}
+
+fn test_group_and_permissions() {
+    let parent = getpid() as usize;
+    let mut go = [0usize; 2];
+    pipe(&mut go);
+    let pid = fork();
+    if pid == 0 {
+        let mut byte = [0u8; 1];
+        read(go[0], &mut byte);
+        // the parent set our nice value through our process group
+        if getpriority(PRIO_PROCESS, 0) != 10 {
+            exit(2);
+        }
+        setuid(1000);
+        if setpriority(PRIO_PROCESS, 0, -1) != -EACCES {
+            exit(3);
+        }
+        if setpriority(PRIO_PROCESS, parent, 5) != -EPERM {
+            exit(4);
+        }
+        if setpriority(PRIO_PROCESS, 0, 15) != 0 || nice(-2) != 7 {
+            exit(5);
+        }
+        exit(0);
+    }
+    let pid = pid as usize;
+    setpgid(pid, pid);
+    if setpriority(PRIO_PGRP, pid, 10) != 0 || getpriority(PRIO_PGRP, pid) != 10 {
+        panic!("PRIO_PGRP");
+    }
+    write(go[1], b"g", 1);
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    close(go[0]);
+    close(go[1]);
+    if status != 0 {
+        println!("test_nice: child exited with {:#x}", status);
+        panic!("unprivileged caller");
+    }
+}
+
+fn test_scheduling() {
+    let mut start = [0usize; 2];
+    let mut result = [0usize; 2];
+    pipe(&mut start);
+    pipe(&mut result);
+    let mut pids = [0usize; 2 * NR_SPINNERS];
+    for (i, pid) in pids.iter_mut().enumerate() {
+        let ret = fork();
+        if ret == 0 {
+            spinner(if i % 2 == 0 { -20 } else { 19 }, start[0], result[1]);
+        }
+        *pid = ret as usize;
+    }
+    write(start[1], &[0u8; 2 * NR_SPINNERS], 2 * NR_SPINNERS);
+    let (mut favoured, mut other) = (0u64, 0u64);
+    for _ in 0..pids.len() {
+        let mut msg = [0u8; 9];
+        if !read_exact(result[0], &mut msg) {
+            panic!("spinner result");
+        }
+        let mut cpu = [0u8; 8];
+        cpu.copy_from_slice(&msg[1..]);
+        let cpu = u64::from_le_bytes(cpu);
+        if msg[0] == 1 {
+            favoured += cpu;
+        } else {
+            other += cpu;
+        }
+    }
+    for &pid in pids.iter() {
+        let mut status = 0;
+        waitpid(pid, &mut status);
+    }
+    if favoured <= other {
+        println!("test_nice: nice -20 got {} ns, nice 19 got {} ns", favoured, other);
+        panic!("lower nice values did not get more cpu time");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_values();
+    test_group_and_permissions();
+    test_scheduling();
+    println!("test_nice passed");
+    0
+}
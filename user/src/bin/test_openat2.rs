@@ -0,0 +1,171 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, mkdir, open, openat2, symlink, unlink, OpenFlags, OpenHow, AT_FDCWD,
+    RESOLVE_BENEATH, RESOLVE_IN_ROOT, RESOLVE_NO_MAGICLINKS, RESOLVE_NO_SYMLINKS,
+};
+
+const ENOENT: isize = 2;
+const EXDEV: isize = 18;
+const EINVAL: isize = 22;
+const E2BIG: isize = 7;
+const ELOOP: isize = 40;
+const HOW_SIZE: usize = core::mem::size_of::<OpenHow>();
+
+/// a future, larger version of OpenHow
+#[repr(C)]
+struct OpenHowExt {
+    how: OpenHow,
+    extra: u64,
+}
+
+/// openat2 read only, return the error or close the fd
+fn probe(dirfd: isize, path: &str, resolve: u64) -> isize {
+    let how = OpenHow { flags: 0, mode: 0, resolve };
+    let fd = openat2(dirfd, path, &how, HOW_SIZE);
+    if fd >= 0 {
+        close(fd as usize);
+        0
+    } else {
+        fd
+    }
+}
+
+fn cleanup() {
+    for path in [
+        "/tmp/o2/sub/f\0",
+        "/tmp/o2/sub\0",
+        "/tmp/o2/rel\0",
+        "/tmp/o2/abs\0",
+        "/tmp/o2/up\0",
+        "/tmp/o2\0",
+    ] {
+        unlink(path);
+    }
+}
+
+fn test(dirfd: isize) {
+    // struct size checks
+    let how = OpenHow::default();
+    if openat2(dirfd, "sub/f\0", &how, 16) != -EINVAL {
+        panic!("short open_how accepted");
+    }
+    let ext = OpenHowExt { how, extra: 1 };
+    if openat2(dirfd, "sub/f\0", &ext.how, 32) != -E2BIG {
+        panic!("unknown non zero field accepted");
+    }
+    let ext = OpenHowExt { how, extra: 0 };
+    let fd = openat2(dirfd, "sub/f\0", &ext.how, 32);
+    if fd < 0 {
+        panic!("zero extended open_how refused");
+    }
+    close(fd as usize);
+
+    // argument checks
+    let bad = [
+        OpenHow { flags: 0, mode: 0, resolve: RESOLVE_BENEATH | RESOLVE_IN_ROOT },
+        OpenHow { flags: 0, mode: 0, resolve: 1 << 40 },
+        OpenHow { flags: 0, mode: 0o644, resolve: 0 },
+    ];
+    for how in bad.iter() {
+        if openat2(dirfd, "sub/f\0", how, HOW_SIZE) != -EINVAL {
+            panic!("bad open_how accepted");
+        }
+    }
+
+    // symlinks
+    if probe(dirfd, "rel/f\0", 0) != 0 || probe(dirfd, "abs/f\0", 0) != 0 {
+        panic!("plain symlink walk");
+    }
+    if probe(dirfd, "rel/f\0", RESOLVE_NO_SYMLINKS) != -ELOOP || probe(dirfd, "sub/f\0", RESOLVE_NO_SYMLINKS) != 0 {
+        panic!("RESOLVE_NO_SYMLINKS");
+    }
+
+    // RESOLVE_BENEATH: stay below dirfd, or fail with EXDEV
+    if probe(dirfd, "rel/f\0", RESOLVE_BENEATH) != 0 || probe(dirfd, "sub/../sub/f\0", RESOLVE_BENEATH) != 0 {
+        panic!("RESOLVE_BENEATH refused a path below dirfd");
+    }
+    for path in ["abs/f\0", "../o2/sub/f\0", "/tmp/o2/sub/f\0", "up/o2/sub/f\0"] {
+        if probe(dirfd, path, RESOLVE_BENEATH) != -EXDEV {
+            println!("test_openat2: {} escaped", path.trim_end_matches('\0'));
+            panic!("RESOLVE_BENEATH");
+        }
+    }
+
+    // RESOLVE_IN_ROOT: dirfd is the root, ".." and "/" stay inside
+    if probe(dirfd, "/sub/f\0", RESOLVE_IN_ROOT) != 0 || probe(dirfd, "../../sub/f\0", RESOLVE_IN_ROOT) != 0 {
+        panic!("RESOLVE_IN_ROOT clamp");
+    }
+    // "/tmp/o2/sub" inside the root does not exist
+    if probe(dirfd, "abs/f\0", RESOLVE_IN_ROOT) != -ENOENT {
+        panic!("RESOLVE_IN_ROOT absolute symlink");
+    }
+
+    // magic links
+    let fd = open("/tmp/o2/sub/f\0", OpenFlags::RDONLY);
+    if fd < 0 {
+        panic!("open");
+    }
+    let mut path = [0u8; 32];
+    let len = format_fd_path(fd as usize, &mut path);
+    let path = core::str::from_utf8(&path[..len]).unwrap();
+    let ret = (probe(AT_FDCWD, path, 0), probe(AT_FDCWD, path, RESOLVE_NO_MAGICLINKS));
+    close(fd as usize);
+    if ret != (0, -ELOOP) {
+        panic!("RESOLVE_NO_MAGICLINKS");
+    }
+}
+
+/// write "/proc/self/fd/<fd>\0" into buf
+fn format_fd_path(fd: usize, buf: &mut [u8]) -> usize {
+    let prefix = b"/proc/self/fd/";
+    buf[..prefix.len()].copy_from_slice(prefix);
+    let mut len = prefix.len();
+    let mut digits = [0u8; 20];
+    let mut n = 0;
+    let mut fd = fd;
+    loop {
+        digits[n] = b'0' + (fd % 10) as u8;
+        n += 1;
+        fd /= 10;
+        if fd == 0 {
+            break;
+        }
+    }
+    for i in (0..n).rev() {
+        buf[len] = digits[i];
+        len += 1;
+    }
+    buf[len] = 0;
+    len + 1
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cleanup();
+    if mkdir("/tmp/o2\0") != 0 || mkdir("/tmp/o2/sub\0") != 0 {
+        panic!("mkdir");
+    }
+    let fd = open("/tmp/o2/sub/f\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    close(fd as usize);
+    symlink("sub\0", "/tmp/o2/rel\0");
+    symlink("/tmp/o2/sub\0", "/tmp/o2/abs\0");
+    symlink("..\0", "/tmp/o2/up\0");
+
+    let dirfd = open("/tmp/o2\0", OpenFlags::DIRECTORY);
+    if dirfd < 0 {
+        panic!("open dirfd");
+    }
+    test(dirfd);
+    close(dirfd as usize);
+    cleanup();
+    println!("test_openat2 passed");
+    0
+}
@@ -0,0 +1,149 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, meminfo, mmap, munmap, open, read, waitpid, write, MmapFlags, MmapProt, OpenFlags};
+
+const PAGE_SIZE: usize = 4096;
+const ENOMEM: isize = 12;
+const EINVAL: isize = 22;
+const MODE: &str = "/proc/sys/vm/overcommit_memory\0";
+
+fn set_mode(mode: &[u8]) -> isize {
+    let fd = open(MODE, OpenFlags::WRONLY);
+    if fd < 0 {
+        return fd;
+    }
+    let ret = write(fd as usize, mode, mode.len());
+    close(fd as usize);
+    ret
+}
+
+fn mode() -> Option<u8> {
+    let fd = open(MODE, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    (len > 0).then(|| buf[0])
+}
+
+/// a /proc/meminfo value in pages
+fn meminfo_pages(name: &str) -> Option<usize> {
+    meminfo(name).map(|kb| kb * 1024 / PAGE_SIZE)
+}
+
+fn map(pages: usize, prot: MmapProt, extra: MmapFlags) -> isize {
+    mmap(
+        0,
+        pages * PAGE_SIZE,
+        prot,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE | extra,
+        0,
+        0,
+    )
+}
+
+fn rw() -> MmapProt {
+    MmapProt::PROT_READ | MmapProt::PROT_WRITE
+}
+
+fn test_strict() {
+    if set_mode(b"2") != 1 || mode() != Some(b'2') {
+        panic!("switch to strict mode");
+    }
+    let limit = meminfo_pages("CommitLimit:").expect("no CommitLimit");
+    let committed = meminfo_pages("Committed_AS:").expect("no Committed_AS");
+    if committed > limit {
+        panic!("already over the limit");
+    }
+    let room = limit - committed;
+
+    // a small reservation shows up and goes away again
+    let addr = map(16, rw(), MmapFlags::empty());
+    if addr < 0 {
+        panic!("small mapping refused");
+    }
+    if meminfo_pages("Committed_AS:").expect("no Committed_AS") < committed + 16 {
+        panic!("mapping not reserved");
+    }
+    munmap(addr as usize, 16 * PAGE_SIZE);
+    if meminfo_pages("Committed_AS:").expect("no Committed_AS") >= committed + 16 {
+        panic!("reservation not given back");
+    }
+
+    // more than what is left is refused up front, MAP_NORESERVE does not help
+    if map(room + 16, rw(), MmapFlags::empty()) != -ENOMEM {
+        panic!("mapping over the limit accepted");
+    }
+    if map(room + 16, rw(), MmapFlags::MAP_NORESERVE) != -ENOMEM {
+        panic!("MAP_NORESERVE mapping over the limit accepted");
+    }
+    // read-only private memory can not be dirtied and reserves nothing
+    let addr = map(room + 16, MmapProt::PROT_READ, MmapFlags::empty());
+    if addr < 0 {
+        panic!("read-only mapping refused");
+    }
+    munmap(addr as usize, (room + 16) * PAGE_SIZE);
+
+    // a fork would need the reservation twice
+    let pages = room * 2 / 3;
+    let addr = map(pages, rw(), MmapFlags::empty());
+    if addr < 0 {
+        panic!("large mapping refused");
+    }
+    let pid = fork();
+    if pid == 0 {
+        exit(0);
+    }
+    if pid > 0 {
+        let mut status = 0;
+        waitpid(pid as usize, &mut status);
+        panic!("fork over the limit succeeded");
+    }
+    if pid != -ENOMEM {
+        panic!("fork over the limit did not fail with ENOMEM");
+    }
+    munmap(addr as usize, pages * PAGE_SIZE);
+}
+
+fn test_modes() {
+    if set_mode(b"1") != 1 {
+        panic!("switch to always mode");
+    }
+    let limit = meminfo_pages("CommitLimit:").expect("no CommitLimit");
+    let addr = map(limit * 2, rw(), MmapFlags::empty());
+    if addr < 0 {
+        panic!("always mode refused a mapping");
+    }
+    munmap(addr as usize, limit * 2 * PAGE_SIZE);
+
+    if set_mode(b"0") != 1 {
+        panic!("switch to guess mode");
+    }
+    if map(limit + 16, rw(), MmapFlags::empty()) != -ENOMEM {
+        panic!("guess mode accepted a mapping larger than memory");
+    }
+    let addr = map(limit + 16, rw(), MmapFlags::MAP_NORESERVE);
+    if addr < 0 {
+        panic!("guess mode refused a MAP_NORESERVE mapping");
+    }
+    munmap(addr as usize, (limit + 16) * PAGE_SIZE);
+
+    if set_mode(b"7") != -EINVAL || mode() != Some(b'0') {
+        panic!("unknown mode accepted");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_strict();
+    test_modes();
+    set_mode(b"0");
+    println!("test_overcommit passed");
+    0
+}
@@ -0,0 +1,117 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, unlink, write, OpenFlags};
+
+const PAGE_SIZE: usize = 4096;
+/// the cache limit set for the test, in pages
+const LIMIT: usize = 256;
+const FILES: usize = 8;
+/// each file is half the limit, all together four times it
+const FILE_PAGES: usize = LIMIT / 2;
+const LIMIT_FILE: &str = "/proc/sys/vm/page_cache_limit\0";
+const NAMES: [&str; FILES] = [
+    "/pagecache_0\0",
+    "/pagecache_1\0",
+    "/pagecache_2\0",
+    "/pagecache_3\0",
+    "/pagecache_4\0",
+    "/pagecache_5\0",
+    "/pagecache_6\0",
+    "/pagecache_7\0",
+];
+
+fn set_limit(limit: &[u8]) -> isize {
+    let fd = open(LIMIT_FILE, OpenFlags::WRONLY);
+    if fd < 0 {
+        return fd;
+    }
+    let ret = write(fd as usize, limit, limit.len());
+    close(fd as usize);
+    ret
+}
+
+/// the Cached line of /proc/meminfo in pages
+fn cached() -> Option<usize> {
+    let fd = open("/proc/meminfo\0", OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut buf = [0u8; 1024];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    let text = core::str::from_utf8(&buf[..len.max(0) as usize]).ok()?;
+    let line = text.lines().find(|line| line.starts_with("Cached:"))?;
+    let kb: usize = line["Cached:".len()..].trim().trim_end_matches("KB").trim().parse().ok()?;
+    Some(kb * 1024 / PAGE_SIZE)
+}
+
+/// the content of page `page` of file `file`
+fn fill(buf: &mut [u8], file: usize, page: usize) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = (file * 31 + page * 7 + i) as u8;
+    }
+}
+
+fn bounded(before: usize) {
+    let now = cached().expect("no Cached");
+    // a little slack for whatever else runs meanwhile
+    if now > before + LIMIT + 16 {
+        println!("test_pagecache: {} pages cached, {} before", now, before);
+        panic!("page cache grew past the limit");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    set_limit(b"0");
+    for name in NAMES {
+        unlink(name);
+    }
+    if set_limit(b"256") != 3 {
+        panic!("set the limit");
+    }
+    let before = cached().expect("no Cached");
+    let mut buf = [0u8; PAGE_SIZE];
+
+    for (file, name) in NAMES.iter().enumerate() {
+        let fd = open(name, OpenFlags::CREATE | OpenFlags::RDWR);
+        if fd < 0 {
+            panic!("create");
+        }
+        for page in 0..FILE_PAGES {
+            fill(&mut buf, file, page);
+            if write(fd as usize, &buf, PAGE_SIZE) != PAGE_SIZE as isize {
+                panic!("write");
+            }
+        }
+        close(fd as usize);
+        bounded(before);
+    }
+
+    // the early files were written back and dropped, read them back from disk
+    let mut expect = [0u8; PAGE_SIZE];
+    for (file, name) in NAMES.iter().enumerate() {
+        let fd = open(name, OpenFlags::RDONLY);
+        if fd < 0 {
+            panic!("open");
+        }
+        for page in 0..FILE_PAGES {
+            fill(&mut expect, file, page);
+            if read(fd as usize, &mut buf) != PAGE_SIZE as isize || buf != expect {
+                panic!("data changed after eviction");
+            }
+        }
+        close(fd as usize);
+        bounded(before);
+    }
+    set_limit(b"0");
+    for name in NAMES {
+        unlink(name);
+    }
+    println!("test_pagecache passed");
+    0
+}
@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, get_time_ms, mkdir, open, rename, symlink, unlink, OpenFlags};
+
+const ENOENT: isize = 2;
+const FILE: &str = "/pc_a/b/c/file\0";
+
+/// open the path read only, return the error or close the fd
+fn probe(path: &str) -> isize {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd >= 0 {
+        close(fd as usize);
+        0
+    } else {
+        fd
+    }
+}
+
+fn create(path: &str) -> bool {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        return false;
+    }
+    close(fd as usize);
+    true
+}
+
+fn cleanup() {
+    for path in [
+        "/pc_link\0",
+        "/pc_a/b/c/file\0",
+        "/pc_a/b/c/file2\0",
+        "/pc_a/b/c\0",
+        "/pc_a/b\0",
+        "/pc_a\0",
+    ] {
+        unlink(path);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cleanup();
+    for dir in ["/pc_a\0", "/pc_a/b\0", "/pc_a/b/c\0"] {
+        if mkdir(dir) != 0 {
+            panic!("mkdir");
+        }
+    }
+    if !create(FILE) {
+        panic!("create");
+    }
+
+    // the first open walks the path, the following ones hit the path cache
+    let start = get_time_ms();
+    for _ in 0..1000 {
+        if probe(FILE) != 0 {
+            panic!("repeated open");
+        }
+    }
+    println!("test_path_cache: 1000 opens of {} took {} ms", FILE.trim_end_matches('\0'), get_time_ms() - start);
+
+    // a renamed file is gone from its old path
+    if rename(FILE, "/pc_a/b/c/file2\0") != 0 || probe(FILE) != -ENOENT || probe("/pc_a/b/c/file2\0") != 0 {
+        panic!("rename");
+    }
+    // so is an unlinked one, until it is created again
+    if unlink("/pc_a/b/c/file2\0") != 0 || probe("/pc_a/b/c/file2\0") != -ENOENT {
+        panic!("unlink");
+    }
+    if !create(FILE) || probe(FILE) != 0 {
+        panic!("recreate");
+    }
+
+    // a path through a symlink follows the target
+    if symlink("/pc_a/b/c\0", "/pc_link\0") != 0 || probe("/pc_link/file\0") != 0 {
+        panic!("open through symlink");
+    }
+    if unlink(FILE) != 0 || probe("/pc_link/file\0") != -ENOENT {
+        panic!("symlink path kept an unlinked file");
+    }
+
+    // removing a directory drops the paths below it
+    if !create(FILE) || probe(FILE) != 0 {
+        panic!("create again");
+    }
+    if unlink(FILE) != 0 || unlink("/pc_a/b/c\0") != 0 || probe(FILE) != -ENOENT {
+        panic!("removed directory");
+    }
+    if mkdir("/pc_a/b/c\0") != 0 || probe(FILE) != -ENOENT || !create(FILE) || probe(FILE) != 0 {
+        panic!("recreated directory");
+    }
+    cleanup();
+    println!("test_path_cache passed");
+    0
+}
@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clone3, close, epoll_create, epoll_ctl, epoll_wait, exit, fork, pidfd_open, pidfd_send_signal,
+    waitpid, yield_, CloneArgs, EpollEvent, CLONE_ARGS_SIZE_VER0, EPOLLIN, EPOLL_CTL_ADD, SIGKILL,
+};
+
+const CLONE_PIDFD: u64 = 0x1000;
+const SIGCHLD: u64 = 17;
+const ESRCH: isize = -3;
+const EINVAL: isize = -22;
+
+/// whether the pidfd reports the process has exited, waiting for it if `timeout` is -1
+fn exited(pidfd: usize, timeout: isize) -> bool {
+    let epfd = epoll_create();
+    if epfd < 0 {
+        panic!("epoll_create");
+    }
+    let event = EpollEvent { events: EPOLLIN, data: pidfd as u64 };
+    if epoll_ctl(epfd as usize, EPOLL_CTL_ADD, pidfd, &event) < 0 {
+        panic!("epoll_ctl on a pidfd");
+    }
+    let mut events = [EpollEvent::default(); 1];
+    let ret = epoll_wait(epfd as usize, &mut events, timeout);
+    close(epfd as usize);
+    ret == 1 && events[0].events & EPOLLIN != 0
+}
+
+fn test_open() {
+    if pidfd_open(0, 0) != EINVAL || pidfd_open(1, 1) != EINVAL {
+        panic!("pidfd_open with bad arguments");
+    }
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    let pidfd = pidfd_open(pid, 0);
+    if pidfd < 0 {
+        panic!("pidfd_open");
+    }
+    let pidfd = pidfd as usize;
+    if exited(pidfd, 0) {
+        panic!("pidfd readable while the child runs");
+    }
+    if pidfd_send_signal(pidfd, SIGKILL) != 0 {
+        panic!("pidfd_send_signal");
+    }
+    if !exited(pidfd, -1) {
+        panic!("pidfd not readable after the child exited");
+    }
+    let mut status = 0;
+    if waitpid(pid as usize, &mut status) != pid || status & 0x7f != SIGKILL {
+        panic!("child was not killed through its pidfd");
+    }
+    // still readable but nothing left to signal once reaped
+    if !exited(pidfd, 0) || pidfd_send_signal(pidfd, SIGKILL) != ESRCH {
+        panic!("pidfd of a reaped child");
+    }
+    close(pidfd);
+}
+
+fn test_clone() {
+    let mut pidfd: i32 = -1;
+    let args = CloneArgs {
+        flags: CLONE_PIDFD,
+        pidfd: &mut pidfd as *mut _ as u64,
+        exit_signal: SIGCHLD,
+        ..Default::default()
+    };
+    let pid = clone3(&args, CLONE_ARGS_SIZE_VER0);
+    if pid == 0 {
+        exit(5);
+    }
+    if pid < 0 || pidfd < 0 {
+        panic!("clone3 with CLONE_PIDFD");
+    }
+    if !exited(pidfd as usize, -1) {
+        panic!("pidfd of clone3 not readable after exit");
+    }
+    let mut status = 0;
+    if waitpid(pid as usize, &mut status) != pid || status >> 8 != 5 {
+        panic!("exit status");
+    }
+    close(pidfd as usize);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_open();
+    test_clone();
+    println!("test_pidfd passed");
+    0
+}
@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, exists, exit, fork, fsconfig, fsmount, fsopen, getcwd, mkdir, move_mount, open,
+    pivot_root, setuid, umount2, waitpid, OpenFlags, AT_FDCWD, FSCONFIG_CMD_CREATE,
+    MNT_DETACH, MOVE_MOUNT_F_EMPTY_PATH,
+};
+
+const EPERM: isize = -1;
+const ENOENT: isize = -2;
+const EINVAL: isize = -22;
+
+/// mount a new tmpfs on `target`
+fn mount_tmpfs(target: &str) {
+    let fs_fd = fsopen("tmpfs\0", 0);
+    if fs_fd < 0 || fsconfig(fs_fd as usize, FSCONFIG_CMD_CREATE, None, None, 0) != 0 {
+        panic!("create a tmpfs context");
+    }
+    let mnt_fd = fsmount(fs_fd as usize, 0, 0);
+    close(fs_fd as usize);
+    if mnt_fd < 0 || move_mount(mnt_fd, "\0", AT_FDCWD, target, MOVE_MOUNT_F_EMPTY_PATH) != 0 {
+        panic!("mount the tmpfs");
+    }
+    close(mnt_fd as usize);
+}
+
+fn cwd_is_root() -> bool {
+    let mut buf = [0u8; 64];
+    getcwd(&mut buf) == 2 && &buf[..2] == b"/\0"
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    mkdir("/pivot_new\0");
+    mount_tmpfs("/pivot_new\0");
+    if mkdir("/pivot_new/old\0") != 0 || mkdir("/pivot_new/sub\0") != 0 {
+        panic!("mkdir on the new root");
+    }
+    let fd = open("/pivot_new/marker\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create a file on the new root");
+    }
+    close(fd as usize);
+
+    // the unprivileged may not pivot
+    let pid = fork();
+    if pid == 0 {
+        setuid(1000);
+        exit((pivot_root("/pivot_new\0", "/pivot_new/old\0") != EPERM) as i32);
+    }
+    let mut status = 0;
+    if waitpid(pid as usize, &mut status) != pid || status >> 8 != 0 {
+        panic!("pivot_root without privilege");
+    }
+    if pivot_root("/pivot_new/sub\0", "/pivot_new/sub\0") != EINVAL {
+        panic!("new root that is not a mount point");
+    }
+    if pivot_root("/pivot_new\0", "/\0") != EINVAL {
+        panic!("put_old outside the new root");
+    }
+    if pivot_root("/pivot_new\0", "/pivot_new/nonexist\0") != ENOENT {
+        panic!("missing put_old");
+    }
+
+    chdir("/\0");
+    if pivot_root("/pivot_new\0", "/pivot_new/old\0") != 0 {
+        panic!("pivot_root");
+    }
+    if !cwd_is_root() || !exists("/marker\0") {
+        panic!("the new root is not the root");
+    }
+    // the old root hangs at put_old, this very mount point included
+    if !exists("/old/pivot_new/marker\0") {
+        panic!("the old root is not at put_old");
+    }
+
+    // and back again
+    if pivot_root("/old\0", "/old/pivot_new\0") != 0 {
+        panic!("pivot_root back to the old root");
+    }
+    if !cwd_is_root() || !exists("/pivot_new/marker\0") {
+        panic!("the old root did not come back");
+    }
+    // the first put_old still leads to the old root until unmounted
+    if !exists("/pivot_new/old/pivot_new/marker\0") {
+        panic!("put_old went away on its own");
+    }
+    if umount2("/pivot_new/old\0", MNT_DETACH) != 0 || exists("/pivot_new/old/pivot_new/marker\0") {
+        panic!("umount2 of put_old");
+    }
+    println!("test_pivot_root passed");
+    0
+}
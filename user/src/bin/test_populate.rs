@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, getrusage, mmap, munmap, open, unlink, write, MmapFlags, MmapProt, OpenFlags, Rusage,
+    RUSAGE_SELF,
+};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 1024;
+const FILE: &str = "/populate_file\0";
+const CONTENT: &[u8] = b"read before the first fault";
+
+/// resident set in kilobytes, the peak never lies below it
+fn rss_kb() -> usize {
+    let mut usage = Rusage::default();
+    getrusage(RUSAGE_SELF, &mut usage);
+    usage.ru_maxrss
+}
+
+fn test_anon() {
+    let before = rss_kb();
+    let addr = mmap(
+        0,
+        PAGES * PAGE_SIZE,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE | MmapFlags::MAP_POPULATE,
+        0,
+        0,
+    );
+    if addr < 0 {
+        panic!("mmap anonymous");
+    }
+    let addr = addr as usize;
+    // the frames are there before anything touches them
+    let grown = rss_kb() - before;
+    if grown < PAGES * PAGE_SIZE / 1024 * 3 / 4 {
+        println!("test_populate: rss grew by {} KiB", grown);
+        panic!("anonymous pages not populated");
+    }
+    for i in 0..PAGES {
+        let p = (addr + i * PAGE_SIZE) as *mut usize;
+        if unsafe { p.read_volatile() } != 0 {
+            panic!("populated page not zero");
+        }
+        unsafe { p.write_volatile(i) };
+    }
+    munmap(addr, PAGES * PAGE_SIZE);
+
+    // nothing to fill without access
+    let none = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::empty(),
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE | MmapFlags::MAP_POPULATE,
+        0,
+        0,
+    );
+    if none < 0 {
+        panic!("mmap PROT_NONE");
+    }
+    munmap(none as usize, PAGE_SIZE);
+}
+
+fn test_file() {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create");
+    }
+    let fd = fd as usize;
+    write(fd, CONTENT, CONTENT.len());
+    // the mapping reaches past the end of the file
+    let len = 4 * PAGE_SIZE;
+    for flags in [MmapFlags::MAP_PRIVATE, MmapFlags::MAP_SHARED] {
+        let addr = mmap(0, len, MmapProt::PROT_READ, flags | MmapFlags::MAP_POPULATE, fd, 0);
+        if addr < 0 {
+            panic!("mmap file");
+        }
+        let data = unsafe { core::slice::from_raw_parts(addr as *const u8, CONTENT.len()) };
+        if data != CONTENT {
+            panic!("populated file page");
+        }
+        munmap(addr as usize, len);
+    }
+    close(fd);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    test_anon();
+    test_file();
+    unlink(FILE);
+    println!("test_populate passed");
+    0
+}
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{getpid, read_to_string};
+
+/// whether `s` is a load like 0.42
+fn is_load(s: &str) -> bool {
+    s.split_once('.').map_or(false, |(int, frac)| {
+        int.parse::<usize>().is_ok() && frac.len() == 2 && frac.parse::<usize>().is_ok()
+    })
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let cpuinfo = read_to_string("/proc/cpuinfo\0").expect("read cpuinfo");
+    let processors = cpuinfo.lines().filter(|line| line.starts_with("processor")).count();
+    if processors == 0 {
+        panic!("no processor in cpuinfo");
+    }
+    let isa_lines = cpuinfo.lines()
+        .filter(|line| line.starts_with("isa") || line.starts_with("ISA"))
+        .count();
+    if isa_lines != processors {
+        panic!("the isa of each processor");
+    }
+
+    let loadavg = read_to_string("/proc/loadavg\0").expect("read loadavg");
+    let fields: Vec<&str> = loadavg.split_whitespace().collect();
+    if fields.len() != 5 || !fields[..3].iter().all(|load| is_load(load)) {
+        panic!("the load averages");
+    }
+    let (running, total) = fields[3].split_once('/').expect("the task counts");
+    let running: usize = running.parse().expect("the running tasks");
+    let total: usize = total.parse().expect("the total tasks");
+    // this task is running while it reads the file
+    if running == 0 || running > total {
+        panic!("the task counts");
+    }
+    let last_pid: isize = fields[4].parse().expect("the last pid");
+    if last_pid < getpid() {
+        panic!("the last pid");
+    }
+    println!("test_proc_loadavg passed");
+    0
+}
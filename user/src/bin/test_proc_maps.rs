@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+use user_lib::{close, getpid, mmap, munmap, open, read_to_string, unlink, write, MmapFlags, MmapProt, OpenFlags};
+
+const PAGE_SIZE: usize = 4096;
+
+/// start, end, perms, offset and pathname of each line
+fn parse(maps: &str) -> Vec<(usize, usize, &str, usize, &str)> {
+    maps.lines().map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (start, end) = fields[0].split_once('-').unwrap();
+        (
+            usize::from_str_radix(start, 16).unwrap(),
+            usize::from_str_radix(end, 16).unwrap(),
+            fields[1],
+            usize::from_str_radix(fields[2], 16).unwrap(),
+            fields.get(5).copied().unwrap_or(""),
+        )
+    }).collect()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let path = "/tmp/maps_test";
+    let fd = open(&format!("{}\0", path), OpenFlags::CREATE | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create the file");
+    }
+    let page = [7u8; PAGE_SIZE];
+    for _ in 0..4 {
+        write(fd as usize, &page, PAGE_SIZE);
+    }
+    // the middle two pages of the file
+    let addr = mmap(0, 2 * PAGE_SIZE, MmapProt::PROT_READ, MmapFlags::MAP_PRIVATE, fd as usize, PAGE_SIZE);
+    close(fd as usize);
+    if addr < 0 {
+        panic!("mmap the file");
+    }
+    let addr = addr as usize;
+
+    let maps = read_to_string("/proc/self/maps\0").expect("read /proc/self/maps");
+    let lines = parse(&maps);
+    if lines.windows(2).any(|pair| pair[0].1 > pair[1].0) {
+        panic!("the lines are not sorted");
+    }
+    let Some(&(start, end, perms, offset, _)) = lines.iter().find(|line| line.4 == path) else {
+        panic!("no line for the mapped file");
+    };
+    if start != addr || end != addr + 2 * PAGE_SIZE || perms != "r--p" || offset != PAGE_SIZE {
+        panic!("the line of the mapped file");
+    }
+    if !lines.iter().any(|line| line.4 == "[stack]" && line.2 == "rw-p") {
+        panic!("no line for the stack");
+    }
+    if lines.iter().filter(|line| line.4 == "[stack]").count() != 1 {
+        panic!("the stack is not one line");
+    }
+    let pid_maps = read_to_string(&format!("/proc/{}/maps\0", getpid())).expect("read /proc/<pid>/maps");
+    if !parse(&pid_maps).iter().any(|line| line.0 == addr && line.4 == path) {
+        panic!("the mapped file in /proc/<pid>/maps");
+    }
+
+    munmap(addr, 2 * PAGE_SIZE);
+    let maps = read_to_string("/proc/self/maps\0").expect("read the maps again");
+    if parse(&maps).iter().any(|line| line.4 == path) {
+        panic!("the line of an unmapped file");
+    }
+    unlink(&format!("{}\0", path));
+    println!("test_proc_maps passed");
+    0
+}
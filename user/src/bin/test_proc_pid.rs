@@ -0,0 +1,113 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::{
+    close, exit, fork, getpid, lseek, open, pipe, read, read_file_by_chunk, read_to_string, waitpid,
+    yield_, OpenFlags,
+};
+
+const ENOENT: isize = -2;
+
+/// the value of the `field:` line of a status file
+fn status_field<'a>(status: &'a str, field: &str) -> Option<&'a str> {
+    status.lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(":\t"))
+}
+
+/// the fields of a stat line after the comm
+fn stat_fields(stat: &str) -> Vec<&str> {
+    stat.rsplit_once(") ").map_or(Vec::new(), |(_, rest)| rest.split_whitespace().collect())
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid();
+    let status = read_to_string(&format!("/proc/{}/status\0", pid)).expect("read own status");
+    if status_field(&status, "Pid") != Some(&format!("{}", pid))
+        || status_field(&status, "Tgid") != Some(&format!("{}", pid))
+        || status_field(&status, "State") != Some("R (running)")
+        || status_field(&status, "Threads") != Some("1")
+        || status_field(&status, "Uid") != Some("0\t0\t0\t0")
+        || !status_field(&status, "VmRSS").map_or(false, |rss| rss.ends_with(" kB"))
+    {
+        panic!("status of the process");
+    }
+    let statm = read_to_string(&format!("/proc/{}/statm\0", pid)).expect("read statm");
+    let pages: Vec<usize> = statm.split_whitespace().map(|n| n.parse().unwrap()).collect();
+    if pages.len() != 7 || pages[0] == 0 || pages[1] == 0 || pages[1] > pages[0] || pages[3] == 0 {
+        panic!("statm of the process");
+    }
+
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let child = fork();
+    if child == 0 {
+        close(pipe_fd[1]);
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        exit(0);
+    }
+    close(pipe_fd[0]);
+    let child_stat = format!("/proc/{}/stat\0", child);
+    // until the child blocks on the pipe
+    while read_to_string(&child_stat).map_or(true, |s| stat_fields(&s).first() != Some(&"S")) {
+        yield_();
+    }
+    let stat = read_to_string(&child_stat).expect("read stat of the child");
+    let fields = stat_fields(&stat);
+    if !stat.starts_with(&format!("{} (", child)) || fields.len() != 50
+        || fields[1] != format!("{}", pid) || fields[17] != "1"
+    {
+        panic!("stat of the child");
+    }
+    let status = read_to_string(&format!("/proc/{}/status\0", child)).expect("read status of the child");
+    if status_field(&status, "PPid") != Some(&format!("{}", pid))
+        || status_field(&status, "State") != Some("S (sleeping)")
+    {
+        panic!("status of the child");
+    }
+    // read a few bytes at a time, the file is one snapshot
+    let status_in_pieces = read_file_by_chunk(&format!("/proc/{}/status\0", child), 7)
+        .map(|content| String::from_utf8(content).unwrap())
+        .expect("read status in pieces");
+    if status_field(&status_in_pieces, "Name") != status_field(&status, "Name")
+        || status_field(&status_in_pieces, "VmSize") != status_field(&status, "VmSize")
+    {
+        panic!("status read in pieces");
+    }
+    let fd = open(&child_stat, OpenFlags::RDONLY);
+    let mut head = [0u8; 4];
+    read(fd as usize, &mut head);
+    lseek(fd as usize, 0, 0);
+    let mut again = [0u8; 4];
+    if read(fd as usize, &mut again) != 4 || head != again {
+        panic!("stat read again from the start");
+    }
+    close(fd as usize);
+    let cmdline = read_to_string(&format!("/proc/{}/cmdline\0", child)).expect("read cmdline of the child");
+    if cmdline != read_to_string(&format!("/proc/{}/cmdline\0", pid)).expect("read own cmdline") {
+        panic!("cmdline of the forked child");
+    }
+
+    // the directory goes with the process
+    close(pipe_fd[1]);
+    let mut exit_code = 0;
+    if waitpid(child as usize, &mut exit_code) != child {
+        panic!("wait for the child");
+    }
+    for file in ["stat", "status", "statm", "cmdline"] {
+        if read_to_string(&format!("/proc/{}/{}\0", child, file)) != Err(ENOENT) {
+            panic!("a file of a reaped process");
+        }
+    }
+    if open(&format!("/proc/{}\0", child), OpenFlags::RDONLY | OpenFlags::DIRECTORY) != ENOENT {
+        panic!("the directory of a reaped process");
+    }
+    println!("test_proc_pid passed");
+    0
+}
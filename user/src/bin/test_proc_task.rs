@@ -0,0 +1,153 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use core::sync::atomic::{AtomicIsize, Ordering};
+use user_lib::{
+    clone3_entry, close, exit, get_time_ms, getdents64, getpid, gettid, open, pipe, read,
+    read_to_string, write, yield_, CloneArgs, OpenFlags, CLONE_ARGS_SIZE_VER0, DIRENT64_NAME_OFFSET,
+};
+
+const CLONE_VM: u64 = 0x100;
+const CLONE_FS: u64 = 0x200;
+const CLONE_FILES: u64 = 0x400;
+const CLONE_SIGHAND: u64 = 0x800;
+const CLONE_THREAD: u64 = 0x10000;
+const ENOENT: isize = -2;
+const STACK_SIZE: usize = 16 * 1024;
+
+#[repr(align(16))]
+struct Stack([u8; STACK_SIZE]);
+
+static mut THREAD_STACK: Stack = Stack([0; STACK_SIZE]);
+static THREAD_TID: AtomicIsize = AtomicIsize::new(0);
+
+/// block reading the pipe until the main thread writes to it
+extern "C" fn thread_main(read_fd: usize) -> ! {
+    THREAD_TID.store(gettid(), Ordering::SeqCst);
+    let mut buf = [0u8; 1];
+    read(read_fd, &mut buf);
+    exit(0)
+}
+
+/// names in the directory, without . and ..
+fn list_dir(path: &str) -> Vec<String> {
+    let fd = open(path, OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if fd < 0 {
+        panic!("open the task directory");
+    }
+    let mut names = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let n = getdents64(fd as usize, &mut buf);
+        if n <= 0 {
+            break;
+        }
+        let mut pos = 0;
+        while pos < n as usize {
+            let reclen = u16::from_ne_bytes([buf[pos + 16], buf[pos + 17]]) as usize;
+            let name = &buf[pos + DIRENT64_NAME_OFFSET..pos + reclen];
+            let len = name.iter().position(|&b| b == 0).unwrap();
+            let name = String::from(core::str::from_utf8(&name[..len]).unwrap());
+            if name != "." && name != ".." {
+                names.push(name);
+            }
+            pos += reclen;
+        }
+    }
+    close(fd as usize);
+    names.sort();
+    names
+}
+
+/// the value of the `field:` line of a status file
+fn status_field<'a>(status: &'a str, field: &str) -> Option<&'a str> {
+    status.lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(":\t"))
+}
+
+/// read the file until `pred` holds, for a second at most
+fn wait_for(path: &str, pred: impl Fn(&Result<String, isize>) -> bool) -> bool {
+    let start = get_time_ms();
+    while get_time_ms() < start + 1000 {
+        if pred(&read_to_string(path)) {
+            return true;
+        }
+        yield_();
+    }
+    false
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid();
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let args = CloneArgs {
+        flags: CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD,
+        stack: unsafe { THREAD_STACK.0.as_ptr() as u64 },
+        stack_size: STACK_SIZE as u64,
+        ..Default::default()
+    };
+    let tid = clone3_entry(&args, CLONE_ARGS_SIZE_VER0, thread_main, pipe_fd[0]);
+    if tid <= 0 {
+        panic!("clone3 of a thread");
+    }
+    while THREAD_TID.load(Ordering::SeqCst) == 0 {
+        yield_();
+    }
+
+    // one directory for each thread
+    let task_dir = format!("/proc/{}/task\0", pid);
+    let mut wanted = Vec::from([format!("{}", pid), format!("{}", tid)]);
+    wanted.sort();
+    if list_dir(&task_dir) != wanted {
+        panic!("threads listed in the task directory");
+    }
+
+    let thread_dir = format!("/proc/{}/task/{}", pid, tid);
+    let wchan = format!("{}/wchan\0", thread_dir);
+    if !wait_for(&wchan, |c| c.as_deref() == Ok("sys_read")) {
+        panic!("wchan of the thread blocked in read");
+    }
+    let status = read_to_string(&format!("{}/status\0", thread_dir)).expect("read status");
+    if status_field(&status, "Pid") != Some(&format!("{}", tid))
+        || status_field(&status, "Tgid") != Some(&format!("{}", pid))
+        || status_field(&status, "Threads") != Some("2")
+        || status_field(&status, "State") != Some("S (sleeping)")
+    {
+        panic!("status of the thread");
+    }
+    let stat = read_to_string(&format!("{}/stat\0", thread_dir)).expect("read stat");
+    let after_comm = stat.rsplit_once(") ").map(|(_, rest)| rest).expect("comm in stat");
+    let fields: Vec<&str> = after_comm.split_whitespace().collect();
+    if !stat.starts_with(&format!("{} (", tid)) || fields.len() != 50 || fields[0] != "S" || fields[17] != "2" {
+        panic!("stat of the thread");
+    }
+    // the main thread reads its own status as running
+    let main_status = read_to_string(&format!("/proc/{}/task/{}/status\0", pid, pid)).expect("read main status");
+    if status_field(&main_status, "State") != Some("R (running)") {
+        panic!("status of the main thread");
+    }
+    if read_to_string(&format!("/proc/{}/task/99999/status\0", pid)) != Err(ENOENT) {
+        panic!("a thread not in the process");
+    }
+
+    // the directory goes with the thread
+    write(pipe_fd[1], b"x");
+    let status_path = format!("{}/status\0", thread_dir);
+    if !wait_for(&status_path, |c| *c == Err(ENOENT)) {
+        panic!("status of an exited thread");
+    }
+    if list_dir(&task_dir) != Vec::from([format!("{}", pid)]) {
+        panic!("task directory after the thread exited");
+    }
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    println!("test_proc_task passed");
+    0
+}
@@ -0,0 +1,121 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::{addr_of, addr_of_mut};
+
+use user_lib::{
+    close, exit, fork, getpid, pipe, process_vm_readv, process_vm_writev, read, waitpid, write,
+    IoVec,
+};
+
+const ESRCH: isize = 3;
+const EFAULT: isize = 14;
+const EINVAL: isize = 22;
+const LEN: usize = 6000;
+
+/// lives at the same address in parent and child, crosses a page boundary
+static mut DATA: [u8; LEN] = [0; LEN];
+
+fn pattern(i: usize, seed: u8) -> u8 {
+    (i as u8).wrapping_mul(7).wrapping_add(seed)
+}
+
+fn data() -> &'static mut [u8; LEN] {
+    unsafe { &mut *addr_of_mut!(DATA) }
+}
+
+fn remote() -> IoVec {
+    IoVec { base: unsafe { addr_of!(DATA) as *const u8 }, len: LEN }
+}
+
+fn child(ready: usize, done: usize) -> ! {
+    for (i, b) in data().iter_mut().enumerate() {
+        *b = pattern(i, 1);
+    }
+    write(ready, b"r", 1);
+    // wait until the parent has written into us
+    let mut byte = [0u8; 1];
+    read(done, &mut byte);
+    let ok = data().iter().enumerate().all(|(i, &b)| b == pattern(i, 2));
+    exit(if ok { 0 } else { 1 });
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut ready = [0usize; 2];
+    let mut done = [0usize; 2];
+    pipe(&mut ready);
+    pipe(&mut done);
+    let pid = fork();
+    if pid == 0 {
+        child(ready[1], done[0]);
+    }
+    let pid = pid as usize;
+    let mut byte = [0u8; 1];
+    read(ready[0], &mut byte);
+
+    // scatter the child's buffer into two local pieces
+    static mut LOCAL: [u8; LEN] = [0; LEN];
+    let local = unsafe { &mut *addr_of_mut!(LOCAL) };
+    let (head, tail) = local.split_at_mut(1000);
+    let liov = [IoVec::new_mut(head), IoVec::new_mut(tail)];
+    if process_vm_readv(pid, &liov, &[remote()], 0) != LEN as isize {
+        panic!("readv length");
+    }
+    if local.iter().enumerate().any(|(i, &b)| b != pattern(i, 1)) {
+        panic!("readv data");
+    }
+    // our own copy was not touched by the child
+    if data().iter().any(|&b| b != 0) {
+        panic!("parent data changed");
+    }
+
+    // a fault part way stops the transfer and reports what was moved
+    let bad = IoVec { base: 0x10 as *const u8, len: 16 };
+    let first = IoVec { base: remote().base, len: 16 };
+    let liov = [IoVec::new_mut(&mut local[..64])];
+    if process_vm_readv(pid, &liov, &[first, bad], 0) != 16 {
+        panic!("partial readv");
+    }
+    if process_vm_readv(pid, &liov, &[bad], 0) != -EFAULT {
+        panic!("readv of an unmapped range");
+    }
+    if process_vm_readv(pid, &liov, &[first], 1) != -EINVAL {
+        panic!("flags accepted");
+    }
+    if process_vm_readv(1 << 20, &liov, &[first], 0) != -ESRCH {
+        panic!("missing process");
+    }
+
+    // reading ourselves does not deadlock
+    data()[..16].copy_from_slice(b"0123456789abcdef");
+    let mut own = [0u8; 16];
+    if process_vm_readv(getpid() as usize, &[IoVec::new_mut(&mut own)], &[first], 0) != 16 || &own != b"0123456789abcdef" {
+        panic!("readv of ourselves");
+    }
+
+    // write a new pattern into the child, whose pages are still shared copy on write
+    for (i, b) in local.iter_mut().enumerate() {
+        *b = pattern(i, 2);
+    }
+    if process_vm_writev(pid, &[IoVec::new(local)], &[remote()], 0) != LEN as isize {
+        panic!("writev length");
+    }
+    if data()[16..].iter().any(|&b| b != 0) {
+        panic!("writev leaked into the parent");
+    }
+    write(done[1], b"d", 1);
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    if status != 0 {
+        panic!("child did not see the written data");
+    }
+    for fd in ready.iter().chain(done.iter()) {
+        close(*fd);
+    }
+    println!("test_process_vm passed");
+    0
+}
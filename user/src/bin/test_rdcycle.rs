@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+#[cfg(target_arch = "riscv64")]
+fn read_counters() -> (usize, usize, usize) {
+    let (cycle, time, instret): (usize, usize, usize);
+    unsafe {
+        core::arch::asm!(
+            "rdcycle {0}",
+            "rdtime {1}",
+            "rdinstret {2}",
+            out(reg) cycle,
+            out(reg) time,
+            out(reg) instret,
+        );
+    }
+    (cycle, time, instret)
+}
+
+#[cfg(target_arch = "loongarch64")]
+fn read_counters() -> (usize, usize, usize) {
+    let time: usize;
+    unsafe {
+        core::arch::asm!("rdtime.d {0}, $zero", out(reg) time);
+    }
+    (time, time, time)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let (cycle0, time0, instret0) = read_counters();
+    let mut sum = 0usize;
+    for i in 0..100000 {
+        sum = sum.wrapping_add(i);
+    }
+    let (cycle1, time1, instret1) = read_counters();
+    println!(
+        "cycle {} -> {}, time {} -> {}, instret {} -> {}, sum {}",
+        cycle0, cycle1, time0, time1, instret0, instret1, sum
+    );
+    if time1 < time0 {
+        println!("test_rdcycle failed: time went backwards");
+        return -1;
+    }
+    println!("test_rdcycle passed");
+    0
+}
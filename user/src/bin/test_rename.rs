@@ -0,0 +1,156 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String};
+use user_lib::{close, mkdir, open, read, rename, renameat2, unlink, write, OpenFlags, RENAME_EXCHANGE, RENAME_NOREPLACE};
+
+const ENOENT: isize = -2;
+const EXDEV: isize = -18;
+const EEXIST: isize = -17;
+const EINVAL: isize = -22;
+
+/// the files and directories a run leaves behind if it fails, children first
+const NAMES: [&str; 12] = [
+    "a", "b", "c", "d1/x", "d1/y", "d2/x", "d2/y", "d3/x", "d3/y", "d1", "d2", "d3",
+];
+
+/// `dir/name`, NUL terminated
+fn path(dir: &str, name: &str) -> String {
+    format!("{}/{}\0", dir, name)
+}
+
+fn create(path: &str, data: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    let ret = write(fd as usize, data, data.len());
+    close(fd as usize);
+    if ret != data.len() as isize {
+        panic!("write");
+    }
+}
+
+/// the first byte of the file, or the error opening it
+fn content(path: &str) -> Result<u8, isize> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return Err(fd);
+    }
+    let mut buf = [0u8; 1];
+    let ret = read(fd as usize, &mut buf);
+    close(fd as usize);
+    if ret != 1 {
+        return Err(ret);
+    }
+    Ok(buf[0])
+}
+
+fn expect(dir: &str, name: &str, byte: u8, msg: &'static str) {
+    if content(&path(dir, name)) != Ok(byte) {
+        println!("test_rename: {}/{} is {:?}", dir, name, content(&path(dir, name)));
+        panic!("{}", msg);
+    }
+}
+
+fn expect_gone(dir: &str, name: &str, msg: &'static str) {
+    if content(&path(dir, name)) != Err(ENOENT) {
+        panic!("{}", msg);
+    }
+}
+
+fn cleanup(dir: &str) {
+    for name in NAMES {
+        unlink(&path(dir, name));
+    }
+}
+
+/// every flag behavior within the file system mounted at `dir`
+fn test_in(dir: &str) {
+    let p = |name: &str| path(dir, name);
+    create(&p("a"), b"A");
+    create(&p("b"), b"B");
+
+    // RENAME_NOREPLACE refuses an existing target and moves to a new one
+    if renameat2(&p("a"), &p("b"), RENAME_NOREPLACE) != EEXIST {
+        panic!("RENAME_NOREPLACE replaced an existing file");
+    }
+    expect(dir, "a", b'A', "RENAME_NOREPLACE touched the source");
+    expect(dir, "b", b'B', "RENAME_NOREPLACE touched the target");
+    if renameat2(&p("a"), &p("c"), RENAME_NOREPLACE) != 0 {
+        panic!("RENAME_NOREPLACE to a new name");
+    }
+    expect_gone(dir, "a", "old name still there after RENAME_NOREPLACE");
+    expect(dir, "c", b'A', "RENAME_NOREPLACE lost the file");
+
+    // RENAME_EXCHANGE swaps two files and needs both
+    if renameat2(&p("c"), &p("b"), RENAME_EXCHANGE) != 0 {
+        panic!("RENAME_EXCHANGE of two files");
+    }
+    expect(dir, "c", b'B', "RENAME_EXCHANGE did not swap the files");
+    expect(dir, "b", b'A', "RENAME_EXCHANGE did not swap the files");
+    if renameat2(&p("c"), &p("a"), RENAME_EXCHANGE) != ENOENT {
+        panic!("RENAME_EXCHANGE with a missing target");
+    }
+    if renameat2(&p("c"), &p("b"), RENAME_EXCHANGE | RENAME_NOREPLACE) != EINVAL {
+        panic!("RENAME_EXCHANGE accepted RENAME_NOREPLACE");
+    }
+
+    // whole directories, and files across them
+    if mkdir(&p("d1")) != 0 || mkdir(&p("d2")) != 0 {
+        panic!("mkdir");
+    }
+    create(&p("d1/x"), b"X");
+    create(&p("d2/y"), b"Y");
+    if renameat2(&p("d1"), &p("d2"), RENAME_EXCHANGE) != 0 {
+        panic!("RENAME_EXCHANGE of two directories");
+    }
+    expect(dir, "d1/y", b'Y', "directory exchange lost a child");
+    expect(dir, "d2/x", b'X', "directory exchange lost a child");
+    expect_gone(dir, "d1/x", "child still under the old directory");
+    if renameat2(&p("d1/y"), &p("b"), RENAME_EXCHANGE) != 0 {
+        panic!("RENAME_EXCHANGE across directories");
+    }
+    expect(dir, "d1/y", b'A', "exchange across directories");
+    expect(dir, "b", b'Y', "exchange across directories");
+
+    // the default replaces the target
+    if rename(&p("c"), &p("b")) != 0 {
+        panic!("rename over an existing file");
+    }
+    expect(dir, "b", b'B', "rename did not replace the target");
+    expect_gone(dir, "c", "old name still there after rename");
+    if rename(&p("d1"), &p("d1/y")) != EINVAL {
+        panic!("directory moved into itself");
+    }
+    // a moved directory brings its children along
+    if rename(&p("d2"), &p("d3")) != 0 {
+        panic!("rename of a directory");
+    }
+    expect(dir, "d3/x", b'X', "moved directory lost a child");
+    expect_gone(dir, "d2/x", "child still under the old directory name");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for dir in ["", "/tmp"] {
+        cleanup(dir);
+        test_in(dir);
+        cleanup(dir);
+    }
+
+    // nothing moves between file systems
+    create(&path("", "a"), b"A");
+    let ret = rename(&path("", "a"), &path("/tmp", "a"));
+    cleanup("");
+    cleanup("/tmp");
+    if ret != EXDEV {
+        panic!("rename across file systems did not fail with EXDEV");
+    }
+    println!("test_rename passed");
+    0
+}
@@ -0,0 +1,111 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, exit_group, fork, get_robust_list, gettid, mmap, munmap, set_robust_list, waitpid,
+    MmapFlags, MmapProt, RobustListHead, FUTEX_OWNER_DIED, FUTEX_TID_MASK, FUTEX_WAITERS,
+};
+
+const PAGE_SIZE: usize = 4096;
+const SIGSEGV: i32 = 11;
+/// where the single list entry lives in the shared page
+const ENTRY: usize = 64;
+/// from the entry to its futex word
+const FUTEX_OFFSET: usize = 8;
+
+#[derive(Clone, Copy)]
+enum Death {
+    Exit,
+    Crash,
+}
+
+fn futex(base: usize) -> *mut u32 {
+    (base + ENTRY + FUTEX_OFFSET) as *mut u32
+}
+
+/// fork a child that takes the lock in `base` as `owner` (its own tid if None),
+/// puts it on its robust list and dies, return the lock word and the wait status
+fn run(base: usize, owner: Option<u32>, waiters: bool, death: Death) -> (u32, i32) {
+    let pid = fork();
+    if pid < 0 {
+        panic!("fork");
+    }
+    if pid == 0 {
+        let tid = owner.unwrap_or(gettid() as u32);
+        let head = unsafe { &mut *(base as *mut RobustListHead) };
+        let entry = (base + ENTRY) as *mut usize;
+        unsafe {
+            entry.write_volatile(base);
+            futex(base).write_volatile(tid | if waiters { FUTEX_WAITERS } else { 0 });
+        }
+        head.next = base + ENTRY;
+        head.futex_offset = FUTEX_OFFSET as isize;
+        head.list_op_pending = 0;
+        if set_robust_list(head) != 0 || get_robust_list(0) != Some(base) {
+            exit(2);
+        }
+        match death {
+            Death::Exit => exit_group(0),
+            Death::Crash => {
+                let ro = mmap(0, PAGE_SIZE, MmapProt::PROT_READ, MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE, 0, 0);
+                unsafe { (ro as *mut usize).write_volatile(1) };
+                exit(3);
+            }
+        }
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status == 2 << 8 {
+        panic!("set_robust_list");
+    }
+    (unsafe { futex(base).read_volatile() }, status)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let base = mmap(
+        0,
+        PAGE_SIZE,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_SHARED,
+        0,
+        0,
+    );
+    if base < 0 {
+        panic!("mmap");
+    }
+    let base = base as usize;
+
+    // a held lock is handed over when the process exits
+    let (word, status) = run(base, None, false, Death::Exit);
+    if status != 0 {
+        panic!("child did not exit cleanly");
+    }
+    if word != FUTEX_OWNER_DIED {
+        println!("test_robust_list: lock word {:#x} after exit", word);
+        panic!("lock not released at exit_group");
+    }
+
+    // and when it crashes, the waiters bit stays for those blocked on it
+    let (word, status) = run(base, None, true, Death::Crash);
+    if status & 0x7f != SIGSEGV {
+        panic!("child did not crash");
+    }
+    if word != FUTEX_OWNER_DIED | FUTEX_WAITERS {
+        println!("test_robust_list: lock word {:#x} after crash", word);
+        panic!("lock not released after a crash");
+    }
+
+    // a lock owned by somebody else is left alone
+    let other = (gettid() as u32 + 1000) & FUTEX_TID_MASK;
+    let (word, _) = run(base, Some(other), false, Death::Exit);
+    if word != other {
+        panic!("lock of another owner changed");
+    }
+    munmap(base, PAGE_SIZE);
+    println!("test_robust_list passed");
+    0
+}
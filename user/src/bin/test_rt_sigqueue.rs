@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use user_lib::{
+    getpid, gettid, kill, sigaction, sigqueue, tgsigqueue, SigInfo, SignalAction, SignalFlags,
+    SA_SIGINFO, SIGRTMIN, SIGUSR1, SI_QUEUE,
+};
+
+const COUNT: usize = 3;
+const SIGUSR2: i32 = 12;
+const ESRCH: isize = 3;
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+static VALUES: [AtomicUsize; COUNT] = [AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)];
+static BAD_INFO: AtomicUsize = AtomicUsize::new(0);
+static USR2_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn rt_handler(signo: i32, info: *const SigInfo, _ucontext: usize) {
+    let info = unsafe { &*info };
+    if info.signo != signo || info.code != SI_QUEUE || info.pid != getpid() as i32 {
+        BAD_INFO.fetch_add(1, Ordering::SeqCst);
+    }
+    let n = CALLS.fetch_add(1, Ordering::SeqCst);
+    if n < COUNT {
+        VALUES[n].store(info.value, Ordering::SeqCst);
+    }
+}
+
+fn usr2_handler(_signo: i32) {
+    USR2_CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+/// SIGRTMIN and SIGUSR2 are in the sa_mask, so all instances are pending when this returns
+fn usr1_handler(_signo: i32) {
+    let pid = getpid() as usize;
+    for value in 1..=COUNT {
+        // alternate between the process and the thread directed variant
+        if value % 2 == 0 {
+            tgsigqueue(pid, gettid() as usize, SIGRTMIN, value * 100);
+        } else {
+            sigqueue(pid, SIGRTMIN, value * 100);
+        }
+    }
+    // a standard signal collapses into one instance
+    kill(pid as isize, SIGUSR2);
+    kill(pid as isize, SIGUSR2);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = rt_handler as usize;
+    action.flags = SA_SIGINFO;
+    sigaction(SIGRTMIN, Some(&action), None);
+
+    let mut action = SignalAction::default();
+    action.handler = usr2_handler as usize;
+    sigaction(SIGUSR2, Some(&action), None);
+
+    let mut action = SignalAction::default();
+    action.handler = usr1_handler as usize;
+    // the kernel mask uses bit signo - 1
+    action.mask = SignalFlags::from_bits_truncate((1 << (SIGRTMIN - 1)) | (1 << (SIGUSR2 - 1)));
+    sigaction(SIGUSR1, Some(&action), None);
+    kill(getpid(), SIGUSR1);
+
+    let calls = CALLS.load(Ordering::SeqCst);
+    if calls != COUNT {
+        println!("test_rt_sigqueue failed: {} handler calls for {} signals", calls, COUNT);
+        return -1;
+    }
+    for (i, value) in VALUES.iter().enumerate() {
+        if value.load(Ordering::SeqCst) != (i + 1) * 100 {
+            println!("test_rt_sigqueue failed: delivery {} carried {}", i, value.load(Ordering::SeqCst));
+            return -1;
+        }
+    }
+    if USR2_CALLS.load(Ordering::SeqCst) != 1 {
+        println!("test_rt_sigqueue failed: SIGUSR2 delivered {} times", USR2_CALLS.load(Ordering::SeqCst));
+        return -1;
+    }
+    if tgsigqueue(getpid() as usize, usize::MAX >> 1, SIGRTMIN, 0) != -ESRCH {
+        println!("test_rt_sigqueue failed: queued to a missing thread");
+        return -1;
+    }
+    if BAD_INFO.load(Ordering::SeqCst) != 0 {
+        println!("test_rt_sigqueue failed: wrong siginfo");
+        return -1;
+    }
+    println!("test_rt_sigqueue passed");
+    0
+}
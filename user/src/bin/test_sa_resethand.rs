@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use user_lib::{
+    exit, fork, getpid, kill, sigaction, waitpid, SignalAction, SA_NODEFER, SA_RESETHAND, SIGUSR1,
+};
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn handler(_signo: i32) {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+/// with SA_NODEFER the raise inside the handler is delivered at once
+fn raising_handler(_signo: i32) {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    kill(getpid(), SIGUSR1);
+}
+
+/// run in a child that installs the handler and raises SIGUSR1 twice,
+/// returns the wait status
+fn run_child(handler: usize, flags: u32) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        let mut action = SignalAction::default();
+        action.handler = handler;
+        action.flags = flags;
+        sigaction(SIGUSR1, Some(&action), None);
+        kill(getpid(), SIGUSR1);
+        if CALLS.load(Ordering::SeqCst) != 1 {
+            exit(2);
+        }
+        // the handler fired once, now the default action terminates us
+        kill(getpid(), SIGUSR1);
+        exit(3);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    status
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let status = run_child(handler as usize, SA_RESETHAND);
+    if status & 0x7f != SIGUSR1 {
+        println!("test_sa_resethand failed: second SIGUSR1 did not kill the child, status {:#x}", status);
+        return -1;
+    }
+    // the nested occurrence already takes the default action
+    let status = run_child(raising_handler as usize, SA_RESETHAND | SA_NODEFER);
+    if status & 0x7f != SIGUSR1 {
+        println!("test_sa_resethand failed: nested SIGUSR1 with SA_NODEFER, status {:#x}", status);
+        return -1;
+    }
+    println!("test_sa_resethand passed");
+    0
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use user_lib::{getpid, kill, sigaction, sigreturn, SignalAction, SA_RESTORER, SIGUSR1};
+
+static HANDLED: AtomicBool = AtomicBool::new(false);
+static RESTORED: AtomicBool = AtomicBool::new(false);
+
+fn handler(_signo: i32) {
+    HANDLED.store(true, Ordering::SeqCst);
+}
+
+/// the handler returns here instead of the kernel trampoline
+fn restorer() {
+    RESTORED.store(true, Ordering::SeqCst);
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = handler as usize;
+    action.flags = SA_RESTORER;
+    action.restorer = restorer as usize;
+    if sigaction(SIGUSR1, Some(&action), None) < 0 {
+        println!("test_sa_restorer failed: sigaction");
+        return -1;
+    }
+    kill(getpid(), SIGUSR1);
+    if !HANDLED.load(Ordering::SeqCst) || !RESTORED.load(Ordering::SeqCst) {
+        println!("test_sa_restorer failed: handled {}, restored {}",
+            HANDLED.load(Ordering::SeqCst), RESTORED.load(Ordering::SeqCst));
+        return -1;
+    }
+    println!("test_sa_restorer passed");
+    0
+}
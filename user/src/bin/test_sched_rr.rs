@@ -0,0 +1,94 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_gettime, exit, fork, kill, pipe, read, sched_get_priority_max, sched_get_priority_min,
+    sched_getparam, sched_getscheduler, sched_setscheduler, waitpid, write, SchedParam, TimeSpec,
+    CLOCK_MONOTONIC, CLOCK_THREAD_CPUTIME_ID, SCHED_FIFO, SCHED_OTHER, SCHED_RR, SIGKILL,
+};
+
+const EINVAL: isize = 22;
+/// more spinners than processors, so they could not all get a processor of their own
+const NR_SPINNERS: usize = 8;
+const BUSY_NS: u64 = 300_000_000;
+
+fn now_ns(clockid: usize) -> u64 {
+    let mut ts = TimeSpec::default();
+    clock_gettime(clockid, &mut ts);
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn spinner(ready: usize) -> ! {
+    // inherited from the parent
+    if sched_getscheduler(0) != SCHED_RR as isize {
+        exit(1);
+    }
+    sched_setscheduler(0, SCHED_OTHER, &SchedParam { sched_priority: 0 });
+    write(ready, b"s", 1);
+    let mut x: u64 = 0;
+    loop {
+        x = x.wrapping_add(1);
+        core::hint::black_box(x);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    if sched_get_priority_max(SCHED_RR) != 99 || sched_get_priority_min(SCHED_FIFO) != 1
+        || sched_get_priority_max(SCHED_OTHER) != 0 || sched_get_priority_max(42) != -EINVAL
+    {
+        panic!("priority range");
+    }
+    if sched_setscheduler(0, SCHED_RR, &SchedParam { sched_priority: 0 }) != -EINVAL
+        || sched_setscheduler(0, SCHED_RR, &SchedParam { sched_priority: 100 }) != -EINVAL
+        || sched_setscheduler(0, SCHED_OTHER, &SchedParam { sched_priority: 1 }) != -EINVAL
+    {
+        panic!("bad priority accepted");
+    }
+    if sched_setscheduler(0, SCHED_RR, &SchedParam { sched_priority: 50 }) != 0 {
+        panic!("sched_setscheduler");
+    }
+    let mut param = SchedParam::default();
+    if sched_getscheduler(0) != SCHED_RR as isize || sched_getparam(0, &mut param) != 0 || param.sched_priority != 50 {
+        panic!("policy not stored");
+    }
+
+    let mut ready = [0usize; 2];
+    pipe(&mut ready);
+    let mut pids = [0usize; NR_SPINNERS];
+    for pid in pids.iter_mut() {
+        let ret = fork();
+        if ret == 0 {
+            spinner(ready[1]);
+        }
+        *pid = ret as usize;
+    }
+    // block so the spinners get to drop to SCHED_OTHER
+    let mut byte = [0u8; 1];
+    for _ in 0..NR_SPINNERS {
+        read(ready[0], &mut byte);
+    }
+
+    // a cpu bound realtime task keeps its processor against the spinners
+    let wall_start = now_ns(CLOCK_MONOTONIC);
+    let cpu_start = now_ns(CLOCK_THREAD_CPUTIME_ID);
+    while now_ns(CLOCK_MONOTONIC) - wall_start < BUSY_NS {}
+    let wall = now_ns(CLOCK_MONOTONIC) - wall_start;
+    let cpu = now_ns(CLOCK_THREAD_CPUTIME_ID) - cpu_start;
+
+    sched_setscheduler(0, SCHED_OTHER, &SchedParam { sched_priority: 0 });
+    for &pid in pids.iter() {
+        kill(pid as isize, SIGKILL);
+        let mut status = 0;
+        waitpid(pid, &mut status);
+    }
+    if cpu * 10 < wall * 8 {
+        println!("test_sched_rr: got {} of {} ns", cpu, wall);
+        panic!("realtime task was not preferred");
+    }
+    println!("test_sched_rr passed");
+    0
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    fork, getauxval, kill, mmap, read_to_string, sleep, waitpid, yield_, MmapFlags, MmapProt,
+    AT_CLKTCK, SIGKILL,
+};
+
+/// more spinners than processors, so every processor has some waiting
+const NR_SPINNERS: usize = 8;
+const YIELDS: usize = 50;
+
+fn spinner(counter: &AtomicUsize) -> ! {
+    loop {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// how far all the spinners have got
+fn progress(counters: &[AtomicUsize]) -> usize {
+    counters.iter().map(|counter| counter.load(Ordering::Relaxed)).sum()
+}
+
+/// the user, system and idle ticks of the `cpu` line of /proc/stat
+fn cpu_ticks() -> (usize, usize, usize) {
+    let stat = read_to_string("/proc/stat\0").expect("read /proc/stat");
+    let line = stat.lines().find(|line| line.starts_with("cpu ")).expect("no cpu line");
+    let fields: Vec<usize> = line.split_whitespace().skip(1).map(|field| field.parse().unwrap_or(0)).collect();
+    if fields.len() != 10 {
+        panic!("the cpu line");
+    }
+    (fields[0], fields[2], fields[3])
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let counters = mmap(
+        0, 4096,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_SHARED,
+        0, 0
+    );
+    if counters < 0 {
+        panic!("mmap");
+    }
+    let counters = unsafe { core::slice::from_raw_parts(counters as *const AtomicUsize, NR_SPINNERS) };
+    let mut pids = [0usize; NR_SPINNERS];
+    for (i, pid) in pids.iter_mut().enumerate() {
+        let ret = fork();
+        if ret == 0 {
+            spinner(&counters[i]);
+        }
+        *pid = ret as usize;
+    }
+    // wait for every spinner to be up
+    while counters.iter().any(|counter| counter.load(Ordering::Relaxed) == 0) {
+        yield_();
+    }
+
+    // a yield lets the tasks waiting on the processor run before the caller again
+    let mut advanced = 0;
+    for _ in 0..YIELDS {
+        let before = progress(counters);
+        yield_();
+        if progress(counters) != before {
+            advanced += 1;
+        }
+    }
+    for &pid in pids.iter() {
+        kill(pid as isize, SIGKILL);
+        let mut status = 0;
+        waitpid(pid, &mut status);
+    }
+    if advanced < YIELDS / 2 {
+        println!("test_sched_yield: spinners ran over {} of {} yields", advanced, YIELDS);
+        panic!("yield went back to the caller");
+    }
+
+    // with every task asleep the processors wait for interrupts
+    let hz = getauxval(AT_CLKTCK);
+    let (user, system, idle) = cpu_ticks();
+    sleep(1000);
+    let (user_after, system_after, idle_after) = cpu_ticks();
+    let busy = (user_after - user) + (system_after - system);
+    let idle = idle_after - idle;
+    if idle < hz / 2 || busy > idle {
+        println!("test_sched_yield: {} busy and {} idle ticks while asleep", busy, idle);
+        panic!("processors kept busy while tasks slept");
+    }
+    println!("test_sched_yield passed");
+    0
+}
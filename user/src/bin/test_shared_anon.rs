@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, waitpid, MmapFlags, MmapProt};
+
+const PAGE_SIZE: usize = 4096;
+
+fn map(flags: MmapFlags) -> *mut u32 {
+    let rw = MmapProt::PROT_READ | MmapProt::PROT_WRITE;
+    let addr = mmap(0, 2 * PAGE_SIZE, rw, flags | MmapFlags::MAP_ANONYMOUS, usize::MAX, 0);
+    if addr < 0 {
+        panic!("mmap");
+    }
+    addr as *mut u32
+}
+
+/// the child writes to the page the parent touched before fork and to
+/// the page nobody touched yet, then the parent looks at both
+fn child_writes(flags: MmapFlags) -> (u32, u32) {
+    let first = map(flags);
+    let second = unsafe { first.add(PAGE_SIZE / 4) };
+    unsafe { first.write_volatile(1) };
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            first.write_volatile(2);
+            second.write_volatile(3);
+        }
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    let seen = unsafe { (first.read_volatile(), second.read_volatile()) };
+    munmap(first as usize, 2 * PAGE_SIZE);
+    seen
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    if child_writes(MmapFlags::MAP_SHARED) != (2, 3) {
+        panic!("parent does not see the writes of the child to shared memory");
+    }
+    if child_writes(MmapFlags::MAP_PRIVATE) != (1, 0) {
+        panic!("writes of the child to private memory reached the parent");
+    }
+    // and the other way round, both sides stay mapped
+    let shared = map(MmapFlags::MAP_SHARED);
+    let pid = fork();
+    if pid == 0 {
+        while unsafe { shared.read_volatile() } == 0 {
+            core::hint::spin_loop();
+        }
+        exit(unsafe { shared.read_volatile() } as i32);
+    }
+    unsafe { shared.write_volatile(5) };
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status >> 8 != 5 {
+        panic!("child does not see the writes of the parent");
+    }
+    println!("test_shared_anon passed");
+    0
+}
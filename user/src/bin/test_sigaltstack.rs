@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+use user_lib::{
+    getpid, kill, sigaction, sigaltstack, sigreturn, SignalAction, SignalStack, SA_ONSTACK,
+    SIGUSR1, SIGUSR2, SS_AUTODISARM, SS_DISABLE, SS_ONSTACK,
+};
+
+const STACK_SIZE: usize = 16 * 1024;
+
+static mut ALT_STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+static HANDLER_SP: AtomicUsize = AtomicUsize::new(0);
+static NESTED_RET: AtomicIsize = AtomicIsize::new(0);
+static REPORTED_ONSTACK: AtomicBool = AtomicBool::new(false);
+static REPORTED_DISARMED: AtomicBool = AtomicBool::new(false);
+
+fn handler(_signo: i32) {
+    let local = 0u8;
+    HANDLER_SP.store(&local as *const u8 as usize, Ordering::SeqCst);
+    // the stack cannot be changed while running on it
+    let mut old = SignalStack::default();
+    NESTED_RET.store(sigaltstack(Some(&SignalStack::default()), Some(&mut old)), Ordering::SeqCst);
+    REPORTED_ONSTACK.store(old.flags == SS_ONSTACK, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn disarm_handler(_signo: i32) {
+    let local = 0u8;
+    HANDLER_SP.store(&local as *const u8 as usize, Ordering::SeqCst);
+    // the stack is disarmed while the handler runs on it
+    let mut old = SignalStack::default();
+    sigaltstack(None, Some(&mut old));
+    REPORTED_DISARMED.store(old.flags == SS_DISABLE, Ordering::SeqCst);
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut old = SignalStack::default();
+    sigaltstack(None, Some(&mut old));
+    if old.flags != SS_DISABLE {
+        println!("test_sigaltstack failed: alternate stack enabled by default");
+        return -1;
+    }
+    let base = unsafe { core::ptr::addr_of_mut!(ALT_STACK) as usize };
+    let ss = SignalStack { sp: base, flags: 0, size: STACK_SIZE };
+    if sigaltstack(Some(&ss), None) != 0 {
+        println!("test_sigaltstack failed: cannot set the alternate stack");
+        return -1;
+    }
+    let small = SignalStack { sp: base, flags: 0, size: 16 };
+    if sigaltstack(Some(&small), None) >= 0 {
+        println!("test_sigaltstack failed: accepted a too small stack");
+        return -1;
+    }
+
+    let mut action = SignalAction::default();
+    action.handler = handler as usize;
+    action.flags = SA_ONSTACK;
+    sigaction(SIGUSR1, Some(&action), None);
+    kill(getpid(), SIGUSR1);
+
+    let sp = HANDLER_SP.load(Ordering::SeqCst);
+    if sp <= base || sp > base + STACK_SIZE {
+        println!("test_sigaltstack failed: handler ran at {:#x}, not on the alternate stack", sp);
+        return -1;
+    }
+    if NESTED_RET.load(Ordering::SeqCst) >= 0 || !REPORTED_ONSTACK.load(Ordering::SeqCst) {
+        println!("test_sigaltstack failed: stack changed while in use");
+        return -1;
+    }
+
+    let ss = SignalStack { sp: base, flags: SS_AUTODISARM, size: STACK_SIZE };
+    if sigaltstack(Some(&ss), None) != 0 {
+        println!("test_sigaltstack failed: cannot set the alternate stack with SS_AUTODISARM");
+        return -1;
+    }
+    HANDLER_SP.store(0, Ordering::SeqCst);
+    action.handler = disarm_handler as usize;
+    sigaction(SIGUSR2, Some(&action), None);
+    kill(getpid(), SIGUSR2);
+    let sp = HANDLER_SP.load(Ordering::SeqCst);
+    if sp <= base || sp > base + STACK_SIZE || !REPORTED_DISARMED.load(Ordering::SeqCst) {
+        println!("test_sigaltstack failed: SS_AUTODISARM stack not disarmed in the handler");
+        return -1;
+    }
+    sigaltstack(None, Some(&mut old));
+    if old.flags != SS_AUTODISARM || old.sp != base {
+        println!("test_sigaltstack failed: SS_AUTODISARM stack not armed again by sigreturn");
+        return -1;
+    }
+    println!("test_sigaltstack passed");
+    0
+}
@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use user_lib::{getpid, kill, sigaction, SignalAction, SignalFlags, SA_NODEFER, SIGUSR1, SIGUSR2};
+
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(0);
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+/// the depth of the SIGUSR1 handler when the SIGUSR2 handler ran
+static USR2_SEEN_DEPTH: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// re-raises its own signal once, the second run is either deferred or nested
+fn usr1_handler(_signo: i32) {
+    let depth = DEPTH.fetch_add(1, Ordering::SeqCst) + 1;
+    MAX_DEPTH.fetch_max(depth, Ordering::SeqCst);
+    if CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+        kill(getpid(), SIGUSR1);
+        // blocked by sa_mask until this handler returns
+        kill(getpid(), SIGUSR2);
+    }
+    DEPTH.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn usr2_handler(_signo: i32) {
+    USR2_SEEN_DEPTH.store(DEPTH.load(Ordering::SeqCst), Ordering::SeqCst);
+}
+
+fn run(flags: u32) -> (usize, usize) {
+    DEPTH.store(0, Ordering::SeqCst);
+    MAX_DEPTH.store(0, Ordering::SeqCst);
+    CALLS.store(0, Ordering::SeqCst);
+    USR2_SEEN_DEPTH.store(usize::MAX, Ordering::SeqCst);
+    let mut action = SignalAction::default();
+    action.handler = usr1_handler as usize;
+    action.flags = flags;
+    // the kernel mask uses bit signo - 1
+    action.mask = SignalFlags::from_bits_truncate(1 << (SIGUSR2 - 1));
+    sigaction(SIGUSR1, Some(&action), None);
+    kill(getpid(), SIGUSR1);
+    (CALLS.load(Ordering::SeqCst), MAX_DEPTH.load(Ordering::SeqCst))
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = usr2_handler as usize;
+    sigaction(SIGUSR2, Some(&action), None);
+
+    // the signal being handled is blocked, the second one runs after the first returns
+    let (calls, max_depth) = run(0);
+    if calls != 2 || max_depth != 1 {
+        println!("test_sigmask_nested failed: deferred calls {}, depth {}", calls, max_depth);
+        return -1;
+    }
+    if USR2_SEEN_DEPTH.load(Ordering::SeqCst) != 0 {
+        println!("test_sigmask_nested failed: sa_mask did not block SIGUSR2");
+        return -1;
+    }
+
+    // SA_NODEFER lets the handler interrupt itself
+    let (calls, max_depth) = run(SA_NODEFER);
+    if calls != 2 || max_depth != 2 {
+        println!("test_sigmask_nested failed: nodefer calls {}, depth {}", calls, max_depth);
+        return -1;
+    }
+
+    // the mask is restored after the nested handlers returned
+    let (calls, _) = run(0);
+    if calls != 2 {
+        println!("test_sigmask_nested failed: mask not restored after sigreturn");
+        return -1;
+    }
+    println!("test_sigmask_nested passed");
+    0
+}
@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use user_lib::{
+    close, exit, fork, getpid, kill, read, sigaction, signalfd, sigreturn, wait, SignalAction,
+    SFD_NONBLOCK, SIGUSR1,
+};
+
+const EAGAIN: isize = 11;
+const SIGUSR2: i32 = 12;
+const RECORD_SIZE: usize = 128;
+
+static HANDLED: AtomicBool = AtomicBool::new(false);
+
+fn handler() {
+    HANDLED.store(true, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn sig_mask(signo: i32) -> u64 {
+    1 << (signo - 1)
+}
+
+/// read one record and return its ssi_signo
+fn read_signo(fd: usize) -> Result<u32, isize> {
+    let mut buf = [0u8; RECORD_SIZE];
+    let ret = read(fd, &mut buf);
+    if ret != RECORD_SIZE as isize {
+        return Err(ret);
+    }
+    Ok(u32::from_ne_bytes(buf[..4].try_into().unwrap()))
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a signal in the mask is queued for the fd instead of killing us
+    let fd = signalfd(-1, sig_mask(SIGUSR1), SFD_NONBLOCK);
+    if fd < 0 {
+        println!("test_signalfd failed: signalfd returned {}", fd);
+        return -1;
+    }
+    let fd = fd as usize;
+    if read_signo(fd) != Err(-EAGAIN) {
+        println!("test_signalfd failed: empty read did not return EAGAIN");
+        return -1;
+    }
+    kill(getpid(), SIGUSR1);
+    if read_signo(fd) != Ok(SIGUSR1 as u32) {
+        println!("test_signalfd failed: SIGUSR1 was not read");
+        return -1;
+    }
+
+    // change the mask, then block until the child signals us
+    signalfd(fd as isize, sig_mask(SIGUSR2), 0);
+    let parent = getpid();
+    if fork() == 0 {
+        kill(parent, SIGUSR2);
+        exit(0);
+    }
+    let mut signo = None;
+    while signo.is_none() {
+        match read_signo(fd) {
+            Ok(n) => signo = Some(n),
+            Err(err) if err == -EAGAIN => {}
+            Err(err) => {
+                println!("test_signalfd failed: read returned {}", err);
+                return -1;
+            }
+        }
+    }
+    if signo != Some(SIGUSR2 as u32) {
+        println!("test_signalfd failed: read signal {:?}", signo);
+        return -1;
+    }
+    let mut exit_code = 0;
+    wait(&mut exit_code);
+
+    // after close the signal goes to the handler again
+    close(fd);
+    let mut action = SignalAction::default();
+    action.handler = handler as usize;
+    sigaction(SIGUSR2, Some(&action), None);
+    kill(getpid(), SIGUSR2);
+    if !HANDLED.load(Ordering::SeqCst) {
+        println!("test_signalfd failed: handler did not run after close");
+        return -1;
+    }
+    println!("test_signalfd passed");
+    0
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, getsockopt, setsockopt, socket, IPPROTO_TCP, SOCK_STREAM, SOL_SOCKET, SO_ERROR,
+    SO_KEEPALIVE, SO_RCVBUF, SO_REUSEADDR, SO_REUSEPORT, SO_SNDBUF, TCP_NODELAY,
+};
+
+const AF_INET: i32 = 2;
+const ENOPROTOOPT: isize = 92;
+
+/// set an option and read it back
+fn check_flag(fd: usize, level: i32, optname: i32, name: &str) -> bool {
+    if getsockopt(fd, level, optname) != Ok(0) {
+        println!("test_sockopt failed: {} set by default", name);
+        return false;
+    }
+    if setsockopt(fd, level, optname, 1) != 0 || getsockopt(fd, level, optname) != Ok(1) {
+        println!("test_sockopt failed: {} cannot be set", name);
+        return false;
+    }
+    if setsockopt(fd, level, optname, 0) != 0 || getsockopt(fd, level, optname) != Ok(0) {
+        println!("test_sockopt failed: {} cannot be cleared", name);
+        return false;
+    }
+    true
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = socket(AF_INET, SOCK_STREAM, IPPROTO_TCP);
+    if fd < 0 {
+        println!("test_sockopt failed: socket returned {}", fd);
+        return -1;
+    }
+    let fd = fd as usize;
+
+    if !check_flag(fd, SOL_SOCKET, SO_REUSEADDR, "SO_REUSEADDR")
+        || !check_flag(fd, SOL_SOCKET, SO_REUSEPORT, "SO_REUSEPORT")
+        || !check_flag(fd, SOL_SOCKET, SO_KEEPALIVE, "SO_KEEPALIVE")
+        || !check_flag(fd, IPPROTO_TCP, TCP_NODELAY, "TCP_NODELAY")
+    {
+        return -1;
+    }
+
+    for (optname, name) in [(SO_RCVBUF, "SO_RCVBUF"), (SO_SNDBUF, "SO_SNDBUF")] {
+        if setsockopt(fd, SOL_SOCKET, optname, 8192) != 0
+            || getsockopt(fd, SOL_SOCKET, optname) != Ok(8192)
+        {
+            println!("test_sockopt failed: {} is {:?}", name, getsockopt(fd, SOL_SOCKET, optname));
+            return -1;
+        }
+    }
+
+    if getsockopt(fd, SOL_SOCKET, SO_ERROR) != Ok(0) {
+        println!("test_sockopt failed: SO_ERROR set on a fresh socket");
+        return -1;
+    }
+
+    // unknown options are rejected instead of crashing the kernel
+    if setsockopt(fd, SOL_SOCKET, 999, 1) != -ENOPROTOOPT
+        || getsockopt(fd, SOL_SOCKET, 999) != Err(-ENOPROTOOPT)
+        || setsockopt(fd, IPPROTO_TCP, 999, 1) != -ENOPROTOOPT
+        || getsockopt(fd, IPPROTO_TCP, 999) != Err(-ENOPROTOOPT)
+    {
+        println!("test_sockopt failed: unknown option not rejected with ENOPROTOOPT");
+        return -1;
+    }
+
+    close(fd);
+    println!("test_sockopt passed");
+    0
+}
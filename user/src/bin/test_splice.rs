@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, pipe, read, splice, tee, unlink, write, OpenFlags, SPLICE_F_MOVE, SPLICE_F_NONBLOCK};
+
+const EAGAIN: isize = -11;
+const EINVAL: isize = -22;
+const FILE: &str = "/tmp/splice\0";
+const MESSAGE: &[u8] = b"duplicated by tee";
+
+fn test_tee(a: &[usize; 2], b: &[usize; 2]) {
+    write(a[1], MESSAGE, MESSAGE.len());
+    if tee(a[0], b[1], 64, 0) != MESSAGE.len() as isize {
+        panic!("tee");
+    }
+    // both pipes hold the message now
+    for fd in [b[0], a[0]] {
+        let mut buf = [0u8; 64];
+        let len = read(fd, &mut buf);
+        if len < 0 || &buf[..len as usize] != MESSAGE {
+            panic!("data after tee");
+        }
+    }
+    if tee(a[0], a[1], 64, 0) != EINVAL {
+        panic!("tee into the same pipe");
+    }
+    // nothing in `a`, a reader would have to wait
+    if tee(a[0], b[1], 64, SPLICE_F_NONBLOCK) != EAGAIN {
+        panic!("tee from an empty pipe with SPLICE_F_NONBLOCK");
+    }
+    if splice(a[0], None, b[1], None, 64, SPLICE_F_NONBLOCK) != EAGAIN {
+        panic!("splice from an empty pipe with SPLICE_F_NONBLOCK");
+    }
+}
+
+fn test_file(a: &[usize; 2]) {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create");
+    }
+    let fd = fd as usize;
+    write(fd, b"0123456789", 10);
+
+    // file to pipe, from an offset that the call moves on
+    let mut off = 2i64;
+    if splice(fd, Some(&mut off), a[1], None, 4, SPLICE_F_MOVE) != 4 || off != 6 {
+        panic!("splice from a file");
+    }
+    let mut buf = [0u8; 16];
+    if read(a[0], &mut buf) != 4 || &buf[..4] != b"2345" {
+        panic!("data spliced from a file");
+    }
+
+    // pipe to file
+    write(a[1], b"ab", 2);
+    let mut off = 0i64;
+    if splice(a[0], None, fd, Some(&mut off), 64, 0) != 2 || off != 2 {
+        panic!("splice into a file");
+    }
+    close(fd);
+    let fd = open(FILE, OpenFlags::RDONLY) as usize;
+    let len = read(fd, &mut buf);
+    close(fd);
+    if len != 10 || &buf[..10] != b"ab23456789" {
+        panic!("data spliced into a file");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink(FILE);
+    let mut a = [0usize; 2];
+    let mut b = [0usize; 2];
+    if pipe(&mut a) != 0 || pipe(&mut b) != 0 {
+        panic!("pipe");
+    }
+    test_tee(&a, &b);
+    test_file(&a);
+    for fd in a.iter().chain(b.iter()) {
+        close(*fd);
+    }
+    unlink(FILE);
+    println!("test_splice passed");
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, open, unlink, OpenFlags, Stat};
+
+/// st_dev of the path, None if it cannot be opened
+fn dev_of(path: &str) -> Option<u64> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut stat = Stat::default();
+    let ret = fstat(fd as usize, &mut stat);
+    close(fd as usize);
+    if ret != 0 {
+        return None;
+    }
+    Some(stat.st_dev)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unlink("/tmp/st_dev\0");
+    let fd = open("/tmp/st_dev\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        panic!("create");
+    }
+    close(fd as usize);
+
+    let root = dev_of("/\0").expect("stat /");
+    // a mount point reports the file system mounted on it, as do the files below it
+    let mounts = [("/tmp\0", "/tmp/st_dev\0"), ("/dev\0", "/dev/null\0"), ("/proc\0", "/proc/meminfo\0")];
+    let mut devs = [0u64; 3];
+    for (dev, (mount, file)) in devs.iter_mut().zip(mounts.iter()) {
+        *dev = dev_of(mount).expect("stat mount point");
+        if dev_of(file) != Some(*dev) {
+            println!("test_st_dev: {} is not on the device of its mount", file.trim_end_matches('\0'));
+            panic!("file and mount point differ");
+        }
+    }
+    if dev_of("/bin\0").map_or(false, |dev| dev != root) {
+        panic!("a plain directory of / reports another device");
+    }
+    for i in 0..devs.len() {
+        if devs[i] == root || devs[i] == 0 {
+            panic!("mounted file system reports the device of /");
+        }
+        for j in i + 1..devs.len() {
+            if devs[i] == devs[j] {
+                panic!("two mounts share a device");
+            }
+        }
+    }
+    unlink("/tmp/st_dev\0");
+    println!("test_st_dev passed");
+    0
+}
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstatfs, open, pipe, statfs, OpenFlags, StatFs, EXT4_SUPER_MAGIC, PROC_SUPER_MAGIC,
+    TMPFS_MAGIC,
+};
+
+const ENOENT: isize = -2;
+const EBADF: isize = -9;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut tmp = StatFs::default();
+    if statfs("/tmp\0", &mut tmp) != 0 || tmp.f_type != TMPFS_MAGIC {
+        panic!("statfs of /tmp is not a tmpfs");
+    }
+    if tmp.f_blocks == 0 || tmp.f_bfree > tmp.f_blocks || tmp.f_bsize <= 0 || tmp.f_namelen != 255 {
+        panic!("tmpfs sizes");
+    }
+    if tmp.f_files == 0 || tmp.f_ffree > tmp.f_files {
+        panic!("tmpfs inode counts");
+    }
+
+    let mut root = StatFs::default();
+    if statfs("/\0", &mut root) != 0 || root.f_type != EXT4_SUPER_MAGIC {
+        panic!("statfs of / is not ext4");
+    }
+    if root.f_blocks == 0 || root.f_bfree > root.f_blocks || root.f_files == 0 {
+        panic!("ext4 counts");
+    }
+    if root.f_fsid == tmp.f_fsid {
+        panic!("two file systems share a fsid");
+    }
+
+    let mut proc = StatFs::default();
+    if statfs("/proc/meminfo\0", &mut proc) != 0 || proc.f_type != PROC_SUPER_MAGIC {
+        panic!("statfs of a file in /proc");
+    }
+
+    // fstatfs agrees with statfs
+    let fd = open("/tmp\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if fd < 0 {
+        panic!("open /tmp");
+    }
+    let mut by_fd = StatFs::default();
+    let ret = fstatfs(fd as usize, &mut by_fd);
+    close(fd as usize);
+    if ret != 0 || by_fd.f_type != TMPFS_MAGIC || by_fd.f_fsid != tmp.f_fsid {
+        panic!("fstatfs of /tmp");
+    }
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    if fstatfs(pipe_fd[0], &mut by_fd) != 0 || by_fd.f_type == TMPFS_MAGIC {
+        panic!("fstatfs of a pipe");
+    }
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    if statfs("/no/such/path\0", &mut by_fd) != ENOENT {
+        panic!("statfs of a missing path");
+    }
+    if fstatfs(1000, &mut by_fd) != EBADF {
+        panic!("fstatfs of a bad fd");
+    }
+    println!("test_statfs passed");
+    0
+}
@@ -0,0 +1,114 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ptr::read_unaligned;
+
+use user_lib::{
+    listmount, statmount, MntIdReq, Statmount, LISTMOUNT_REVERSE, LSMT_ROOT, MNT_ID_REQ_SIZE_VER0,
+    MNT_ID_REQ_SIZE_VER1, STATMOUNT_FS_TYPE, STATMOUNT_MNT_BASIC, STATMOUNT_MNT_POINT,
+    STATMOUNT_SB_BASIC,
+};
+
+const ENOENT: isize = 2;
+const EINVAL: isize = 22;
+const EOVERFLOW: isize = 75;
+const HEADER: usize = core::mem::size_of::<Statmount>();
+
+static mut BUF: [u8; 1024] = [0; 1024];
+
+fn req(mnt_id: u64, param: u64) -> MntIdReq {
+    MntIdReq { size: MNT_ID_REQ_SIZE_VER1, mnt_id, param, ..Default::default() }
+}
+
+/// the NUL-terminated string at `off` after the header
+fn string(buf: &[u8], off: u32) -> &str {
+    let s = &buf[HEADER + off as usize..];
+    let len = s.iter().position(|&b| b == 0).unwrap_or(s.len());
+    core::str::from_utf8(&s[..len]).unwrap_or("")
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
+    let mask = STATMOUNT_SB_BASIC | STATMOUNT_MNT_BASIC | STATMOUNT_MNT_POINT | STATMOUNT_FS_TYPE;
+
+    let mut ids = [0u64; 16];
+    let nr = listmount(&req(LSMT_ROOT, 0), &mut ids, 0);
+    if nr < 3 {
+        panic!("listmount found too few mounts");
+    }
+    let ids = &ids[..nr as usize];
+    if ids.windows(2).any(|w| w[0] >= w[1]) {
+        panic!("listmount ids are not ascending");
+    }
+
+    let mut root_id = 0;
+    let mut seen = 0;
+    for &id in ids {
+        if statmount(&req(id, mask), buf, 0) != 0 {
+            panic!("statmount of a listed mount");
+        }
+        let sm = unsafe { read_unaligned(buf.as_ptr() as *const Statmount) };
+        if sm.mask & mask != mask || sm.mnt_id != id || sm.size as usize <= HEADER {
+            panic!("statmount header");
+        }
+        // every mount listed here sits on the root mount
+        if root_id == 0 {
+            root_id = sm.mnt_parent_id;
+        } else if sm.mnt_parent_id != root_id {
+            panic!("mounts disagree on the root mount");
+        }
+        let expected = match string(buf, sm.mnt_point) {
+            "/tmp" => "tmpfs",
+            "/dev" => "devtmpfs",
+            "/proc" => "proc",
+            _ => continue,
+        };
+        if string(buf, sm.fs_type) != expected {
+            println!("test_statmount: {} is {}", string(buf, sm.mnt_point), string(buf, sm.fs_type));
+            panic!("fs type");
+        }
+        seen += 1;
+    }
+    if seen != 3 {
+        panic!("/tmp, /dev or /proc was not listed");
+    }
+
+    // the root mount is its own parent and is mounted on /
+    if statmount(&req(root_id, mask), buf, 0) != 0 {
+        panic!("statmount of the root mount");
+    }
+    let sm = unsafe { read_unaligned(buf.as_ptr() as *const Statmount) };
+    if sm.mnt_parent_id != root_id || string(buf, sm.mnt_point) != "/" {
+        panic!("root mount");
+    }
+
+    // paging from the first id skips it, reverse order walks back
+    let mut rest = [0u64; 16];
+    if listmount(&req(LSMT_ROOT, ids[0]), &mut rest, 0) != nr - 1 || rest[0] != ids[1] {
+        panic!("listmount paging");
+    }
+    if listmount(&req(LSMT_ROOT, 0), &mut rest, LISTMOUNT_REVERSE) != nr || rest[0] != ids[nr as usize - 1] {
+        panic!("listmount reverse");
+    }
+    if listmount(&req(LSMT_ROOT, 0), &mut rest[..1], 0) != 1 {
+        panic!("listmount limit");
+    }
+
+    // argument checks
+    let mut short = req(root_id, mask);
+    short.size = MNT_ID_REQ_SIZE_VER0 - 8;
+    if statmount(&short, buf, 0) != -EINVAL
+        || statmount(&req(root_id, mask), buf, 1) != -EINVAL
+        || listmount(&req(LSMT_ROOT, 0), &mut rest, 2) != -EINVAL
+        || statmount(&req(12345, mask), buf, 0) != -ENOENT
+        || statmount(&req(root_id, mask), &mut buf[..HEADER], 0) != -EOVERFLOW
+    {
+        panic!("bad arguments accepted");
+    }
+    println!("test_statmount passed");
+    0
+}
@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, readlink, symlink, unlink, write, OpenFlags};
+
+const EEXIST: isize = 17;
+const ELOOP: isize = 40;
+const CONTENT: &[u8] = b"reached through a symlink";
+
+const FILE: &str = "/symtest_file\0";
+const ABS_LINK: &str = "/symtest_abs\0";
+const REL_LINK: &str = "/symtest_rel\0";
+const CHAIN_LINK: &str = "/symtest_chain\0";
+const DIR_LINK: &str = "/symtest_root\0";
+const LOOP_A: &str = "/symtest_loop_a\0";
+const LOOP_B: &str = "/symtest_loop_b\0";
+
+fn cleanup() {
+    for path in [ABS_LINK, REL_LINK, CHAIN_LINK, DIR_LINK, LOOP_A, LOOP_B, FILE] {
+        unlink(path);
+    }
+}
+
+/// open `path` and check it reads back CONTENT
+fn reads_content(path: &str) -> bool {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        println!("test_symlink: open {} returned {}", path, fd);
+        return false;
+    }
+    let mut buf = [0u8; 64];
+    let n = read(fd as usize, &mut buf);
+    close(fd as usize);
+    n == CONTENT.len() as isize && &buf[..n as usize] == CONTENT
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cleanup();
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    if fd < 0 {
+        println!("test_symlink failed: can not create the target file");
+        return -1;
+    }
+    write(fd as usize, CONTENT, CONTENT.len());
+    close(fd as usize);
+
+    if symlink("/symtest_file\0", ABS_LINK) != 0
+        || symlink("symtest_file\0", REL_LINK) != 0
+        || symlink("/symtest_abs\0", CHAIN_LINK) != 0
+        || symlink("/\0", DIR_LINK) != 0
+    {
+        println!("test_symlink failed: symlink");
+        cleanup();
+        return -1;
+    }
+    if symlink("/symtest_file\0", ABS_LINK) != -EEXIST {
+        println!("test_symlink failed: replaced an existing link");
+        cleanup();
+        return -1;
+    }
+
+    let mut buf = [0u8; 64];
+    let n = readlink(REL_LINK, &mut buf);
+    if n != 12 || &buf[..12] != b"symtest_file" {
+        println!("test_symlink failed: readlink returned {}", n);
+        cleanup();
+        return -1;
+    }
+
+    // absolute, relative, a chain of links and a link in the middle of the path
+    if !reads_content(ABS_LINK)
+        || !reads_content(REL_LINK)
+        || !reads_content(CHAIN_LINK)
+        || !reads_content("/symtest_root/symtest_file\0")
+    {
+        println!("test_symlink failed: can not read through the links");
+        cleanup();
+        return -1;
+    }
+
+    let fd = open(ABS_LINK, OpenFlags::RDONLY | OpenFlags::NOFOLLOW);
+    if fd != -ELOOP {
+        println!("test_symlink failed: O_NOFOLLOW open returned {}", fd);
+        cleanup();
+        return -1;
+    }
+
+    symlink("/symtest_loop_b\0", LOOP_A);
+    symlink("/symtest_loop_a\0", LOOP_B);
+    let fd = open(LOOP_A, OpenFlags::RDONLY);
+    if fd != -ELOOP {
+        println!("test_symlink failed: looping links returned {}", fd);
+        cleanup();
+        return -1;
+    }
+
+    cleanup();
+    println!("test_symlink passed");
+    0
+}
@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sleep, sysinfo, Sysinfo};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut before = Sysinfo::default();
+    if sysinfo(&mut before) != 0 {
+        panic!("sysinfo");
+    }
+    if before.mem_unit != 1 || before.totalram == 0 || before.freeram == 0 || before.freeram > before.totalram {
+        panic!("the memory");
+    }
+    if before.freeswap > before.totalswap {
+        panic!("the swap");
+    }
+    if before.procs == 0 {
+        panic!("the number of processes");
+    }
+    // this task keeps running over more than one period the tasks are counted in
+    sleep(6000);
+    let mut after = Sysinfo::default();
+    sysinfo(&mut after);
+    if after.uptime < before.uptime + 5 {
+        panic!("the uptime");
+    }
+    if after.loads[0] == 0 || after.loads[0] < after.loads[2] {
+        panic!("the load averages");
+    }
+    println!("test_sysinfo passed");
+    0
+}
@@ -0,0 +1,155 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, madvise, mmap, mprotect, munmap, waitpid, MmapFlags, MmapProt, MADV_HUGEPAGE};
+
+const PAGE_SIZE: usize = 4096;
+const HUGE_SIZE: usize = 2 * 1024 * 1024;
+const HUGE_PAGES: usize = HUGE_SIZE / PAGE_SIZE;
+const SIGSEGV: i32 = 11;
+
+fn page(base: usize, i: usize) -> usize {
+    base + i * PAGE_SIZE
+}
+
+fn load(addr: usize) -> usize {
+    unsafe { (addr as *const usize).read_volatile() }
+}
+
+fn store(addr: usize, value: usize) {
+    unsafe { (addr as *mut usize).write_volatile(value) }
+}
+
+/// whether page `i` still holds what `fill` wrote to it
+fn intact(base: usize, i: usize) -> bool {
+    load(page(base, i)) == i + 1
+}
+
+fn fill(base: usize, pages: usize) {
+    for i in 0..pages {
+        store(page(base, i), i + 1);
+    }
+}
+
+/// whether `f` kills a child with SIGSEGV
+fn faults(f: fn(usize), addr: usize) -> bool {
+    let pid = fork();
+    if pid == 0 {
+        f(addr);
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    status & 0x7f == SIGSEGV
+}
+
+fn read_at(addr: usize) {
+    load(addr);
+}
+
+fn write_at(addr: usize) {
+    store(addr, 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // room for two aligned big pages whatever the start
+    let len = 3 * HUGE_SIZE;
+    let addr = mmap(
+        0,
+        len,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0,
+        0,
+    );
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let addr = addr as usize;
+    if madvise(addr, len, MADV_HUGEPAGE) != 0 {
+        panic!("madvise");
+    }
+    let base = (addr + HUGE_SIZE - 1) & !(HUGE_SIZE - 1);
+    let pages = 2 * HUGE_PAGES;
+
+    // fresh memory reads as zero, also across the boundary between the big pages
+    if load(base) != 0 || load(page(base, HUGE_PAGES) - 8) != 0 || load(page(base, HUGE_PAGES)) != 0 {
+        panic!("fresh memory not zero");
+    }
+    fill(base, pages);
+    let boundary = (page(base, HUGE_PAGES) - 8) as *mut u8;
+    let bytes = *b"across the border";
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), boundary, bytes.len()) };
+    let mut back = [0u8; 17];
+    unsafe { core::ptr::copy_nonoverlapping(boundary, back.as_mut_ptr(), back.len()) };
+    if back != bytes {
+        panic!("data across the 2MiB boundary");
+    }
+    fill(base, pages);
+    for i in 0..pages {
+        if !intact(base, i) {
+            panic!("page lost its data");
+        }
+    }
+
+    // the child copies single pages out of the big page
+    let pid = fork();
+    if pid == 0 {
+        store(page(base, 7), 0);
+        let ok = load(page(base, 7)) == 0 && intact(base, 6) && intact(base, 8) && intact(base, HUGE_PAGES - 1);
+        exit(!ok as i32);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if status != 0 {
+        panic!("copy on write in the child");
+    }
+    if !intact(base, 7) {
+        panic!("the child's write reached the parent");
+    }
+    store(page(base, 9), 100);
+    if load(page(base, 9)) != 100 || !intact(base, 8) || !intact(base, 10) {
+        panic!("copy on write in the parent");
+    }
+    store(page(base, 9), 10);
+
+    // mprotect of a few pages splits the big page
+    if mprotect(page(base, 10), 2 * PAGE_SIZE, MmapProt::PROT_READ) != 0 {
+        panic!("mprotect");
+    }
+    if !intact(base, 10) || !intact(base, 11) {
+        panic!("data lost by mprotect");
+    }
+    if !faults(write_at, page(base, 10)) || !faults(write_at, page(base, 11)) {
+        panic!("read-only pages writable");
+    }
+    store(page(base, 12), 13);
+    if !intact(base, 9) || !intact(base, 12) {
+        panic!("neighbours of read-only pages");
+    }
+
+    // munmap of a few pages in the second big page
+    let hole = HUGE_PAGES + 100;
+    if munmap(page(base, hole), 2 * PAGE_SIZE) != 0 {
+        panic!("munmap");
+    }
+    if !faults(read_at, page(base, hole)) || !faults(read_at, page(base, hole + 1)) {
+        panic!("unmapped pages still accessible");
+    }
+    for i in (0..pages).filter(|&i| i != hole && i != hole + 1) {
+        if !intact(base, i) {
+            panic!("pages around the hole lost");
+        }
+    }
+    store(page(base, hole + 2), hole + 3);
+    if !intact(base, hole + 2) || !intact(base, hole - 1) {
+        panic!("pages around the hole not writable");
+    }
+    munmap(addr, len);
+    println!("test_thp passed");
+    0
+}
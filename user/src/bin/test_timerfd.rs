@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, get_time_ms, read, sleep, timerfd_create, timerfd_settime, ITimerSpec, TimeSpec,
+    CLOCK_MONOTONIC, TFD_NONBLOCK,
+};
+
+const EAGAIN: isize = 11;
+
+fn ms(ms: usize) -> TimeSpec {
+    TimeSpec { tv_sec: ms / 1000, tv_nsec: (ms % 1000) * 1_000_000 }
+}
+
+fn read_u64(fd: usize) -> Result<u64, isize> {
+    let mut buf = [0u8; 8];
+    let ret = read(fd, &mut buf);
+    if ret != 8 {
+        return Err(ret);
+    }
+    Ok(u64::from_ne_bytes(buf))
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // one-shot timer, blocking read waits for it
+    let fd = timerfd_create(CLOCK_MONOTONIC, 0);
+    if fd < 0 {
+        println!("test_timerfd failed: timerfd_create returned {}", fd);
+        return -1;
+    }
+    let fd = fd as usize;
+    let start = get_time_ms();
+    timerfd_settime(fd, 0, &ITimerSpec { it_interval: ms(0), it_value: ms(50) }, None);
+    if read_u64(fd) != Ok(1) {
+        println!("test_timerfd failed: one-shot timer did not expire once");
+        return -1;
+    }
+    if get_time_ms() - start < 50 {
+        println!("test_timerfd failed: read returned before expiration");
+        return -1;
+    }
+    close(fd);
+
+    // periodic timer, expirations accumulate between reads
+    let fd = timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK) as usize;
+    if read_u64(fd) != Err(-EAGAIN) {
+        println!("test_timerfd failed: disarmed nonblocking read did not return EAGAIN");
+        return -1;
+    }
+    timerfd_settime(fd, 0, &ITimerSpec { it_interval: ms(20), it_value: ms(20) }, None);
+    sleep(110);
+    match read_u64(fd) {
+        Ok(n) if n >= 3 => println!("periodic timer expired {} times", n),
+        other => {
+            println!("test_timerfd failed: periodic read got {:?}", other);
+            return -1;
+        }
+    }
+    // disarm, then nothing is left to read
+    let mut old = ITimerSpec::default();
+    timerfd_settime(fd, 0, &ITimerSpec::default(), Some(&mut old));
+    if old.it_interval.tv_nsec != 20_000_000 || read_u64(fd) != Err(-EAGAIN) {
+        println!("test_timerfd failed: disarm");
+        return -1;
+    }
+    close(fd);
+    println!("test_timerfd passed");
+    0
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time_ms, getauxval, times, wait, Tms, AT_CLKTCK};
+
+/// keep the cpu busy in user mode for `ms` milliseconds
+fn spin(ms: isize) {
+    let start = get_time_ms();
+    let mut x = 0usize;
+    while get_time_ms() - start < ms {
+        for i in 0..10000 {
+            x = core::hint::black_box(x.wrapping_mul(31).wrapping_add(i));
+        }
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let hz = getauxval(AT_CLKTCK);
+    if hz == 0 {
+        panic!("no AT_CLKTCK");
+    }
+    let mut before = Tms::default();
+    let start = times(&mut before);
+    if start < 0 {
+        panic!("times");
+    }
+    spin(500);
+    let mut after = Tms::default();
+    let end = times(&mut after);
+    // half a second is hz / 2 ticks, allow for the ones spent elsewhere
+    if end < start + (hz / 4) as isize || end > start + hz as isize {
+        panic!("the elapsed ticks");
+    }
+    if after.utime < before.utime + hz / 4 || after.stime < before.stime {
+        panic!("the user time of the process");
+    }
+    if after.cutime != 0 || after.cstime != 0 {
+        panic!("the times of children before any exits");
+    }
+
+    if fork() == 0 {
+        spin(300);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    wait(&mut exit_code);
+    let mut reaped = Tms::default();
+    times(&mut reaped);
+    if reaped.cutime < hz / 5 {
+        panic!("the user time of the reaped child");
+    }
+    println!("test_times passed");
+    0
+}
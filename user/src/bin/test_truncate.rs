@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fallocate, fstat, ftruncate, open, pread, truncate, unlink, write, OpenFlags, Stat,
+    FALLOC_FL_KEEP_SIZE, FALLOC_FL_PUNCH_HOLE,
+};
+
+const EOPNOTSUPP: isize = 95;
+
+fn size_of(fd: usize) -> i64 {
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    stat.st_size
+}
+
+fn blocks_of(fd: usize) -> i64 {
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    stat.st_blocks
+}
+
+/// check that [offset, offset + len) reads `byte`
+fn reads(fd: usize, offset: usize, len: usize, byte: u8) -> bool {
+    let mut buf = [0xffu8; 64];
+    assert!(len <= buf.len());
+    pread(fd, &mut buf[..len], offset) == len as isize && buf[..len].iter().all(|&b| b == byte)
+}
+
+fn test_file(path: &str) {
+    unlink(path);
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("open");
+    }
+    let fd = fd as usize;
+    let data = [b'a'; 100];
+    write(fd, &data, data.len());
+
+    // grow: the new range reads zero
+    if ftruncate(fd, 5000) != 0 || size_of(fd) != 5000 {
+        panic!("ftruncate grow");
+    }
+    if !reads(fd, 90, 10, b'a') || !reads(fd, 100, 64, 0) || !reads(fd, 4990, 10, 0) {
+        panic!("grown range does not read zero");
+    }
+
+    // shrink: EOF moves, and the cut data does not come back on regrow
+    if ftruncate(fd, 50) != 0 || size_of(fd) != 50 {
+        panic!("ftruncate shrink");
+    }
+    let mut buf = [0u8; 64];
+    if pread(fd, &mut buf, 40) != 10 || pread(fd, &mut buf, 50) != 0 {
+        panic!("EOF did not move after shrink");
+    }
+    if truncate(path, 100) != 0 || size_of(fd) != 100 || !reads(fd, 40, 10, b'a') || !reads(fd, 50, 50, 0) {
+        panic!("truncate regrow");
+    }
+
+    // fallocate extends the file by default, not with FALLOC_FL_KEEP_SIZE
+    if fallocate(fd, 0, 0, 8192) != 0 || size_of(fd) != 8192 || !reads(fd, 8000, 64, 0) {
+        panic!("fallocate extend");
+    }
+    let blocks = blocks_of(fd);
+    if blocks < 8192 / 512 {
+        panic!("st_blocks does not cover the allocation");
+    }
+    if fallocate(fd, FALLOC_FL_KEEP_SIZE, 8192, 8192) != 0 || size_of(fd) != 8192 || blocks_of(fd) <= blocks {
+        panic!("fallocate keep size");
+    }
+
+    // punch hole zeroes the range without moving EOF
+    if fallocate(fd, FALLOC_FL_PUNCH_HOLE, 10, 20) != -EOPNOTSUPP {
+        panic!("punch hole without keep size");
+    }
+    if fallocate(fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 10, 20) != 0
+        || size_of(fd) != 8192
+        || !reads(fd, 0, 10, b'a')
+        || !reads(fd, 10, 20, 0)
+        || !reads(fd, 30, 20, b'a')
+    {
+        panic!("punch hole");
+    }
+
+    if ftruncate(fd, -1) >= 0 || fallocate(fd, 0, 0, 0) >= 0 {
+        panic!("bad arguments accepted");
+    }
+    close(fd);
+    unlink(path);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for path in ["/truncate_test\0", "/tmp/truncate_test\0"] {
+        test_file(path);
+    }
+    println!("test_truncate passed");
+    0
+}
@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    accept, bind_unix, close, connect_unix, exit, fork, listen, read, socket, waitpid, write,
+    AF_UNIX, SOCK_STREAM,
+};
+
+const EADDRINUSE: isize = 98;
+const ECONNREFUSED: isize = 111;
+const ENOENT: isize = 2;
+
+/// the child connects and sends ping, the parent accepts and answers pong
+fn ping_pong(name: &[u8]) {
+    let server = socket(AF_UNIX, SOCK_STREAM, 0);
+    if server < 0 {
+        panic!("socket");
+    }
+    let server = server as usize;
+    if bind_unix(server, name) != 0 {
+        panic!("bind");
+    }
+    let other = socket(AF_UNIX, SOCK_STREAM, 0) as usize;
+    if bind_unix(other, name) != -EADDRINUSE {
+        panic!("second bind did not return EADDRINUSE");
+    }
+    close(other);
+    if listen(server, 4) != 0 {
+        panic!("listen");
+    }
+
+    let pid = fork();
+    if pid == 0 {
+        let client = socket(AF_UNIX, SOCK_STREAM, 0) as usize;
+        if connect_unix(client, name) != 0 {
+            exit(1);
+        }
+        write(client, b"ping", 4);
+        let mut buf = [0u8; 4];
+        if read(client, &mut buf) != 4 || &buf != b"pong" {
+            exit(2);
+        }
+        close(client);
+        exit(0);
+    }
+
+    // accept waits for the child to connect
+    let conn = accept(server, core::ptr::null_mut(), core::ptr::null_mut());
+    if conn < 0 {
+        panic!("accept");
+    }
+    let conn = conn as usize;
+    let mut buf = [0u8; 4];
+    if read(conn, &mut buf) != 4 || &buf != b"ping" {
+        panic!("server read");
+    }
+    write(conn, b"pong", 4);
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    if exit_code != 0 {
+        panic!("client failed");
+    }
+    // the peer closed, read returns end of file
+    if read(conn, &mut buf) != 0 {
+        panic!("read after peer close");
+    }
+    close(conn);
+    close(server);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let client = socket(AF_UNIX, SOCK_STREAM, 0) as usize;
+    if connect_unix(client, b"/test_unix_missing.sock") != -ENOENT {
+        println!("test_unix_socket failed: connect to a missing path");
+        return -1;
+    }
+    if connect_unix(client, b"\0test_unix_missing") != -ECONNREFUSED {
+        println!("test_unix_socket failed: connect to a missing abstract name");
+        return -1;
+    }
+    close(client);
+
+    ping_pong(b"/test_unix.sock");
+    ping_pong(b"\0test_unix_abstract");
+    println!("test_unix_socket passed");
+    0
+}
@@ -0,0 +1,126 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String};
+use user_lib::{
+    close, open, read, statx, symlink, unlink, utimensat, write, OpenFlags, Statx, TimeSpec, AT_FDCWD,
+    AT_SYMLINK_NOFOLLOW, STATX_ATIME, STATX_MTIME, UTIME_NOW, UTIME_OMIT,
+};
+
+const EINVAL: isize = -22;
+const ATIME: TimeSpec = TimeSpec { tv_sec: 1_000_000, tv_nsec: 123 };
+const MTIME: TimeSpec = TimeSpec { tv_sec: 2_000_000, tv_nsec: 456_000 };
+
+fn path(dir: &str, name: &str) -> String {
+    format!("{}/{}\0", dir, name)
+}
+
+fn times(path: &str, flags: u32) -> (TimeSpec, TimeSpec) {
+    let mut stx = Statx::default();
+    if statx(AT_FDCWD, path, flags, STATX_ATIME | STATX_MTIME, &mut stx) != 0 {
+        panic!("statx");
+    }
+    let ts = |t: user_lib::StatxTimestamp| TimeSpec { tv_sec: t.tv_sec as usize, tv_nsec: t.tv_nsec as usize };
+    (ts(stx.stx_atime), ts(stx.stx_mtime))
+}
+
+fn same(a: TimeSpec, b: TimeSpec) -> bool {
+    a.tv_sec == b.tv_sec && a.tv_nsec == b.tv_nsec
+}
+
+fn test_in(dir: &str) {
+    let file = path(dir, "utime_file");
+    let fd = open(&file, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create");
+    }
+    close(fd as usize);
+
+    // known times come back from statx
+    if utimensat(AT_FDCWD, Some(&file), Some(&[ATIME, MTIME]), 0) != 0 {
+        panic!("utimensat");
+    }
+    let (atime, mtime) = times(&file, 0);
+    if !same(atime, ATIME) || !same(mtime, MTIME) {
+        println!("test_utimensat: {} has atime {:?}, mtime {:?}", dir, atime, mtime);
+        panic!("statx does not show the times set");
+    }
+
+    // UTIME_OMIT keeps a time, UTIME_NOW takes the current one
+    let omit = TimeSpec { tv_sec: 0, tv_nsec: UTIME_OMIT };
+    let now = TimeSpec { tv_sec: 0, tv_nsec: UTIME_NOW };
+    if utimensat(AT_FDCWD, Some(&file), Some(&[omit, now]), 0) != 0 {
+        panic!("utimensat with UTIME_OMIT and UTIME_NOW");
+    }
+    let (atime, mtime) = times(&file, 0);
+    if !same(atime, ATIME) || same(mtime, MTIME) {
+        panic!("UTIME_OMIT or UTIME_NOW ignored");
+    }
+    let bad = TimeSpec { tv_sec: 0, tv_nsec: 1_000_000_000 };
+    if utimensat(AT_FDCWD, Some(&file), Some(&[bad, MTIME]), 0) != EINVAL {
+        panic!("nanoseconds out of range accepted");
+    }
+
+    // a write moves mtime, a read atime
+    utimensat(AT_FDCWD, Some(&file), Some(&[ATIME, MTIME]), 0);
+    let fd = open(&file, OpenFlags::RDWR);
+    write(fd as usize, b"x", 1);
+    let (atime, mtime) = times(&file, 0);
+    if !same(atime, ATIME) || same(mtime, MTIME) {
+        panic!("write did not update only mtime");
+    }
+    let mut buf = [0u8; 1];
+    let fd2 = open(&file, OpenFlags::RDONLY);
+    read(fd2 as usize, &mut buf);
+    close(fd2 as usize);
+    close(fd as usize);
+    if same(times(&file, 0).0, ATIME) {
+        panic!("read did not update atime");
+    }
+
+    // null times set both to now
+    utimensat(AT_FDCWD, Some(&file), Some(&[ATIME, MTIME]), 0);
+    if utimensat(AT_FDCWD, Some(&file), None, 0) != 0 {
+        panic!("utimensat with null times");
+    }
+    let (atime, mtime) = times(&file, 0);
+    if same(atime, ATIME) || same(mtime, MTIME) {
+        panic!("null times did not set now");
+    }
+
+    // AT_SYMLINK_NOFOLLOW changes the link, not its target
+    let link = path(dir, "utime_link");
+    if symlink(&file, &link) == 0 {
+        utimensat(AT_FDCWD, Some(&file), Some(&[ATIME, ATIME]), 0);
+        if utimensat(AT_FDCWD, Some(&link), Some(&[MTIME, MTIME]), AT_SYMLINK_NOFOLLOW) != 0 {
+            panic!("utimensat of a link");
+        }
+        if !same(times(&file, 0).1, ATIME) {
+            panic!("AT_SYMLINK_NOFOLLOW followed the link");
+        }
+        if !same(times(&link, AT_SYMLINK_NOFOLLOW).1, MTIME) {
+            panic!("link times not set");
+        }
+    }
+}
+
+/// a failed run leaves its files behind
+fn cleanup(dir: &str) {
+    unlink(&path(dir, "utime_link"));
+    unlink(&path(dir, "utime_file"));
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for dir in ["", "/tmp"] {
+        cleanup(dir);
+        test_in(dir);
+        cleanup(dir);
+    }
+    println!("test_utimensat passed");
+    0
+}
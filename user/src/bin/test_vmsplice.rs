@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, mmap, munmap, pipe, read_exact, vmsplice, write, IoVec, MmapFlags, MmapProt, SPLICE_F_GIFT,
+    SPLICE_F_NONBLOCK,
+};
+
+const PAGE_SIZE: usize = 4096;
+const EAGAIN: isize = -11;
+
+fn test_copy(fds: &[usize; 2]) {
+    let iov = [IoVec::new(b"hello "), IoVec::new(b"world")];
+    if vmsplice(fds[1], &iov, 0) != 11 {
+        panic!("vmsplice into the pipe");
+    }
+    let mut buf = [0u8; 11];
+    read_exact(fds[0], &mut buf[..6]).expect("pipe ran dry");
+    let iov = [IoVec::new_mut(&mut buf[6..])];
+    if vmsplice(fds[0], &iov, 0) != 5 || &buf != b"hello world" {
+        panic!("vmsplice out of the pipe");
+    }
+    // the pipe is empty, reading would have to wait
+    let mut byte = [0u8; 1];
+    if vmsplice(fds[0], &[IoVec::new_mut(&mut byte)], SPLICE_F_NONBLOCK) != EAGAIN {
+        panic!("vmsplice from an empty pipe with SPLICE_F_NONBLOCK");
+    }
+}
+
+fn test_gift(fds: &[usize; 2]) {
+    let addr = mmap(
+        0,
+        2 * PAGE_SIZE,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0,
+        0,
+    );
+    if addr < 0 {
+        panic!("mmap");
+    }
+    let pages = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, 2 * PAGE_SIZE) };
+    for (i, byte) in pages.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    // a part of a page cannot be given away, it is copied
+    if vmsplice(fds[1], &[IoVec::new(&pages[1..PAGE_SIZE])], SPLICE_F_GIFT) != (PAGE_SIZE - 1) as isize {
+        panic!("gift of a part of a page");
+    }
+    let mut part = [0u8; PAGE_SIZE - 1];
+    read_exact(fds[0], &mut part).expect("pipe ran dry");
+    if part[..] != pages[1..PAGE_SIZE] {
+        panic!("copy of a part of a page");
+    }
+
+    // gifted pages queue up behind what is in the pipe already
+    write(fds[1], b"head", 4);
+    if vmsplice(fds[1], &[IoVec::new(pages)], SPLICE_F_GIFT) != (2 * PAGE_SIZE) as isize {
+        panic!("vmsplice with SPLICE_F_GIFT");
+    }
+    write(fds[1], b"tail", 4);
+    // writing to a gifted page copies it, the pipe keeps what was given
+    pages.fill(0xff);
+
+    let mut head = [0u8; 4];
+    read_exact(fds[0], &mut head).expect("pipe ran dry");
+    let mut given = [0u8; PAGE_SIZE];
+    for page in 0..2 {
+        read_exact(fds[0], &mut given).expect("pipe ran dry");
+        if given.iter().enumerate().any(|(i, &b)| b != (page * PAGE_SIZE + i) as u8) {
+            panic!("data of a gifted page");
+        }
+    }
+    let mut tail = [0u8; 4];
+    read_exact(fds[0], &mut tail).expect("pipe ran dry");
+    if &head != b"head" || &tail != b"tail" {
+        panic!("order around the gifted pages");
+    }
+    munmap(addr as usize, 2 * PAGE_SIZE);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) < 0 {
+        panic!("pipe");
+    }
+    test_copy(&fds);
+    test_gift(&fds);
+    close(fds[0]);
+    close(fds[1]);
+    println!("test_vmsplice passed");
+    0
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clone, exit, fork, get_time_ms, kill, wait4, yield_, Rusage, SIGCONT, SIGKILL, SIGSTOP,
+    WCONTINUED, WNOHANG, WUNTRACED, __WALL, __WCLONE,
+};
+
+const ECHILD: isize = -10;
+
+fn test_stop_continue() {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    let mut status = 0;
+    if wait4(pid, &mut status, WNOHANG, None) != 0 {
+        panic!("WNOHANG with a running child");
+    }
+    kill(pid, SIGSTOP);
+    if wait4(pid, &mut status, WUNTRACED, None) != pid
+        || status & 0xff != 0x7f
+        || (status >> 8) & 0xff != SIGSTOP
+    {
+        panic!("stopped child with WUNTRACED");
+    }
+    // reported once only
+    if wait4(pid, &mut status, WUNTRACED | WNOHANG, None) != 0 {
+        panic!("stop reported twice");
+    }
+    kill(pid, SIGCONT);
+    if wait4(pid, &mut status, WCONTINUED, None) != pid || status != 0xffff {
+        panic!("continued child with WCONTINUED");
+    }
+    kill(pid, SIGKILL);
+    if wait4(pid, &mut status, 0, None) != pid || status & 0x7f != SIGKILL {
+        panic!("killed child");
+    }
+}
+
+fn test_rusage() {
+    let pid = fork();
+    if pid == 0 {
+        let start = get_time_ms();
+        while get_time_ms() < start + 50 {}
+        exit(7);
+    }
+    let mut status = 0;
+    let mut usage = Rusage::default();
+    if wait4(-1, &mut status, 0, Some(&mut usage)) != pid || status >> 8 != 7 {
+        panic!("exit status");
+    }
+    if usage.ru_utime.sec == 0 && usage.ru_utime.usec == 0 {
+        panic!("no user time in the child's rusage");
+    }
+    if wait4(-1, &mut status, 0, None) != ECHILD {
+        panic!("wait4 without children");
+    }
+}
+
+fn test_wclone() {
+    // no exit signal, a clone child
+    let pid = clone(0, 0, 0);
+    if pid == 0 {
+        exit(3);
+    }
+    let mut status = 0;
+    if wait4(pid, &mut status, 0, None) != ECHILD {
+        panic!("clone child waited for without __WCLONE");
+    }
+    if wait4(pid, &mut status, __WCLONE, None) != pid || status >> 8 != 3 {
+        panic!("clone child with __WCLONE");
+    }
+    let pid = fork();
+    if pid == 0 {
+        exit(4);
+    }
+    if wait4(pid, &mut status, __WCLONE | WNOHANG, None) != ECHILD {
+        panic!("fork child waited for with __WCLONE");
+    }
+    if wait4(pid, &mut status, __WALL, None) != pid || status >> 8 != 4 {
+        panic!("fork child with __WALL");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_stop_continue();
+    test_rusage();
+    test_wclone();
+    println!("test_wait4 passed");
+    0
+}
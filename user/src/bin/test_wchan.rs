@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::{
+    close, exit, fork, get_time_ms, kill, pipe, read, read_to_string, setuid, waitpid, yield_,
+    SIGKILL,
+};
+
+const EACCES: isize = -13;
+const SYSCALL_READ: usize = 63;
+
+/// read the file until `pred` holds, for a second at most
+fn wait_for(path: &str, pred: impl Fn(&str) -> bool) -> Option<String> {
+    let start = get_time_ms();
+    while get_time_ms() < start + 1000 {
+        if let Ok(content) = read_to_string(path) {
+            if pred(&content) {
+                return Some(content);
+            }
+        }
+        yield_();
+    }
+    None
+}
+
+fn test_blocked() {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let pid = fork();
+    if pid == 0 {
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        exit(0);
+    }
+    let wchan = format!("/proc/{}/wchan\0", pid);
+    let syscall = format!("/proc/{}/syscall\0", pid);
+    if wait_for(&wchan, |c| c == "sys_read").is_none() {
+        panic!("wchan of a task blocked in read");
+    }
+    let content = read_to_string(&syscall).expect("read syscall");
+    let fields: Vec<&str> = content.split_whitespace().collect();
+    if fields.len() != 9
+        || fields[0] != format!("{}", SYSCALL_READ)
+        || fields[1] != format!("{:#x}", pipe_fd[0])
+        || fields[3] != "0x1"
+    {
+        panic!("syscall of a task blocked in read");
+    }
+
+    // the arguments of a syscall are no business of another user
+    let reader = fork();
+    if reader == 0 {
+        setuid(1000);
+        let wchan_ok = read_to_string(&wchan).is_ok();
+        exit((read_to_string(&syscall) != Err(EACCES) || !wchan_ok) as i32);
+    }
+    let mut status = 0;
+    if waitpid(reader as usize, &mut status) != reader || status >> 8 != 0 {
+        panic!("permissions of wchan and syscall");
+    }
+
+    close(pipe_fd[1]);
+    close(pipe_fd[0]);
+    waitpid(pid as usize, &mut status);
+}
+
+fn test_user_space() {
+    let pid = fork();
+    if pid == 0 {
+        loop {}
+    }
+    let syscall = format!("/proc/{}/syscall\0", pid);
+    let wchan = format!("/proc/{}/wchan\0", pid);
+    let found = wait_for(&syscall, |c| c.starts_with("-1 "));
+    let wchan = read_to_string(&wchan);
+    kill(pid, SIGKILL);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    match found {
+        Some(content) if content.split_whitespace().count() == 3 => {}
+        _ => panic!("syscall of a task in user space"),
+    }
+    if wchan.as_deref() != Ok("0") {
+        panic!("wchan of a task in user space");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    test_blocked();
+    test_user_space();
+    println!("test_wchan passed");
+    0
+}
@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use user_lib::{
+    close, exit, fork, getppid, kill, pipe, sigaction, sleep, waitpid, write, SignalAction, SIGUSR1,
+};
+
+const PIPE_CAPACITY: usize = 16 * 4096;
+const EINTR: isize = -4;
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn handler(_signo: i32) {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+static BUF: [u8; 2 * PIPE_CAPACITY] = [b'w'; 2 * PIPE_CAPACITY];
+
+fn test(fds: &[usize; 2]) {
+    // the pipe takes what fits, then the signal ends the wait for room
+    let ret = write(fds[1], &BUF, BUF.len());
+    if ret != PIPE_CAPACITY as isize {
+        panic!("interrupted write did not return the bytes written");
+    }
+    // nothing fits into the full pipe before the next signal
+    if write(fds[1], &BUF[..16], 16) != EINTR {
+        panic!("interrupted write without progress did not fail with EINTR");
+    }
+    if CALLS.load(Ordering::SeqCst) != 2 {
+        panic!("handler calls");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = handler as usize;
+    sigaction(SIGUSR1, Some(&action), None);
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) < 0 {
+        panic!("pipe");
+    }
+    let pid = fork();
+    if pid == 0 {
+        // keep the read end open without reading, interrupt each blocked write
+        close(fds[1]);
+        for _ in 0..2 {
+            sleep(100);
+            kill(getppid(), SIGUSR1);
+        }
+        exit(0);
+    }
+    close(fds[0]);
+    test(&fds);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    close(fds[1]);
+    println!("test_write_intr passed");
+    0
+}
@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fgetxattr, flistxattr, fremovexattr, fsetxattr, getxattr, listxattr, lsetxattr, open,
+    removexattr, setxattr, symlink, unlink, OpenFlags, XATTR_CREATE, XATTR_REPLACE,
+};
+
+const EPERM: isize = -1;
+const EEXIST: isize = -17;
+const ERANGE: isize = -34;
+const ENODATA: isize = -61;
+const EOPNOTSUPP: isize = -95;
+
+const FILE: &str = "/tmp/xattr_test\0";
+const LINK: &str = "/tmp/xattr_link\0";
+const NAME: &str = "user.comment\0";
+const OTHER: &str = "user.other\0";
+
+fn test(fd: usize) {
+    let mut buf = [0u8; 64];
+    if getxattr(FILE, NAME, &mut buf) != ENODATA {
+        panic!("a missing attribute");
+    }
+    if setxattr(FILE, NAME, b"hello", XATTR_REPLACE) != ENODATA {
+        panic!("XATTR_REPLACE of a missing attribute");
+    }
+    if setxattr(FILE, NAME, b"hello", XATTR_CREATE) != 0 {
+        panic!("setxattr");
+    }
+    if setxattr(FILE, NAME, b"again", XATTR_CREATE) != EEXIST {
+        panic!("XATTR_CREATE of an existing attribute");
+    }
+
+    // the size query, a short buffer and the round trip
+    if getxattr(FILE, NAME, &mut []) != 5 {
+        panic!("size of the attribute");
+    }
+    if getxattr(FILE, NAME, &mut buf[..4]) != ERANGE {
+        panic!("a buffer too small for the value");
+    }
+    if getxattr(FILE, NAME, &mut buf) != 5 || &buf[..5] != b"hello" {
+        panic!("value read back");
+    }
+    if setxattr(FILE, NAME, b"bye", XATTR_REPLACE) != 0
+        || fgetxattr(fd, NAME, &mut buf) != 3
+        || &buf[..3] != b"bye"
+    {
+        panic!("value replaced and read through the fd");
+    }
+
+    // both names are listed, each ended by a NUL
+    if fsetxattr(fd, OTHER, b"", 0) != 0 || getxattr(FILE, OTHER, &mut buf) != 0 {
+        panic!("an empty value");
+    }
+    let wanted = b"user.comment\0user.other\0";
+    if listxattr(FILE, &mut []) != wanted.len() as isize {
+        panic!("size of the list");
+    }
+    if listxattr(FILE, &mut buf[..wanted.len() - 1]) != ERANGE {
+        panic!("a buffer too small for the list");
+    }
+    let n = flistxattr(fd, &mut buf);
+    if n != wanted.len() as isize || &buf[..n as usize] != wanted {
+        panic!("listxattr");
+    }
+
+    if removexattr(FILE, NAME) != 0 || getxattr(FILE, NAME, &mut buf) != ENODATA {
+        panic!("removexattr");
+    }
+    if fremovexattr(fd, NAME) != ENODATA {
+        panic!("removing a missing attribute");
+    }
+    if listxattr(FILE, &mut buf) != b"user.other\0".len() as isize {
+        panic!("list after removal");
+    }
+
+    if setxattr(FILE, "unknown.name\0", b"x", 0) != EOPNOTSUPP {
+        panic!("a name in no namespace");
+    }
+    // user attributes are for regular files and directories, not for symlinks
+    symlink(FILE, LINK);
+    let ret = lsetxattr(LINK, NAME, b"x", 0);
+    unlink(LINK);
+    if ret != EPERM {
+        panic!("a user attribute on a symlink");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    if fd < 0 {
+        panic!("create {}", FILE);
+    }
+    test(fd as usize);
+    close(fd as usize);
+    unlink(FILE);
+    println!("test_xattr passed");
+    0
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{getrusage, mmap, munmap, MmapFlags, MmapProt, Rusage, RUSAGE_SELF};
+
+const PAGE_SIZE: usize = 4096;
+const LEN: usize = 100 * 1024 * 1024;
+/// pages the reads may add to the resident set: the stack and some noise
+const SLACK: usize = 512;
+
+/// the peak resident set of the process in pages, the zero page is not counted in it
+fn maxrss() -> usize {
+    let mut usage = Rusage::default();
+    if getrusage(RUSAGE_SELF, &mut usage) != 0 {
+        panic!("getrusage");
+    }
+    usage.ru_maxrss * 1024 / PAGE_SIZE
+}
+
+fn map(len: usize) -> &'static mut [u8] {
+    let addr = mmap(
+        0,
+        len,
+        MmapProt::PROT_READ | MmapProt::PROT_WRITE,
+        MmapFlags::MAP_ANONYMOUS | MmapFlags::MAP_PRIVATE,
+        0,
+        0,
+    );
+    if addr < 0 {
+        panic!("mmap");
+    }
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) }
+}
+
+/// read one byte of every page, the sum of them
+fn touch(buf: &[u8]) -> usize {
+    (0..buf.len())
+        .step_by(PAGE_SIZE)
+        .map(|i| unsafe { core::ptr::read_volatile(&buf[i]) } as usize)
+        .sum()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let buf = map(LEN);
+    let before = maxrss();
+    if touch(buf) != 0 {
+        panic!("fresh anonymous memory is not zero");
+    }
+    let used = maxrss().saturating_sub(before);
+    println!("test_zero_page: reading {} pages added {} resident pages", LEN / PAGE_SIZE, used);
+    if used > SLACK {
+        panic!("reads allocated a frame per page");
+    }
+
+    // the first write copies up, the zero page stays zero
+    unsafe { core::ptr::write_volatile(&mut buf[PAGE_SIZE + 1], 0x5a) };
+    let written = unsafe { core::ptr::read_volatile(&buf[PAGE_SIZE + 1]) };
+    let others = touch(&buf[..PAGE_SIZE]) + touch(&buf[2 * PAGE_SIZE..]);
+    munmap(buf.as_ptr() as usize, LEN);
+    if written != 0x5a {
+        panic!("write to a zero page lost");
+    }
+    if others != 0 {
+        panic!("write to a zero page reached other pages");
+    }
+    let fresh = map(PAGE_SIZE);
+    let zero = fresh.iter().all(|&b| b == 0);
+    munmap(fresh.as_ptr() as usize, PAGE_SIZE);
+    if !zero {
+        panic!("write reached the shared zero page");
+    }
+    println!("test_zero_page passed");
+    0
+}
@@ -14,7 +14,9 @@ extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
-use alloc::{ffi::CString, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{ffi::CString, string::String, vec::Vec};
 use buddy_system_allocator::LockedHeap;
 use syscall::*;
 
@@ -25,6 +27,9 @@ static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 #[global_allocator]
 static HEAP: LockedHeap = LockedHeap::empty();
 
+/// where the aux vector is on the initial stack, after argv and envp
+static AUXV: AtomicUsize = AtomicUsize::new(0);
+
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout = {:?}", layout);
@@ -55,6 +60,9 @@ pub fn _rust_start(p: *const usize) -> ! {
 
     let argc = unsafe { p.read_volatile() };
     let argv = unsafe { p.add(1) as usize };
+    let envp = unsafe { p.add(argc + 2) };
+    let env_count = (0usize..).find(|i| unsafe { envp.add(*i).read_volatile() } == 0).unwrap();
+    AUXV.store(unsafe { envp.add(env_count + 1) } as usize, Ordering::Relaxed);
     
     unsafe {
         #[allow(static_mut_refs)]
@@ -81,6 +89,26 @@ pub fn _rust_start(p: *const usize) -> ! {
     exit(main(v.as_slice()));
 }
 
+pub const AT_NULL: usize = 0;
+pub const AT_PAGESZ: usize = 6;
+/// clock ticks per second, the unit of times
+pub const AT_CLKTCK: usize = 17;
+
+/// the value of entry `ty` of the aux vector, 0 if there is none
+pub fn getauxval(ty: usize) -> usize {
+    let mut entry = AUXV.load(Ordering::Relaxed) as *const usize;
+    loop {
+        let (key, value) = unsafe { (entry.read_volatile(), entry.add(1).read_volatile()) };
+        if key == AT_NULL {
+            return 0;
+        }
+        if key == ty {
+            return value;
+        }
+        entry = unsafe { entry.add(2) };
+    }
+}
+
 #[linkage = "weak"]
 #[no_mangle]
 fn main(_args: &[&str]) -> i32 {
@@ -92,8 +120,13 @@ bitflags! {
         const RDONLY = 0;
         const WRONLY = 1 << 0;
         const RDWR = 1 << 1;
-        const CREATE = 1 << 9;
-        const TRUNC = 1 << 10;
+        const CREATE = 0o100;
+        const TRUNC = 0o1000;
+        const APPEND = 0o2000;
+        const NONBLOCK = 0o4000;
+        const DIRECTORY = 0o200000;
+        const NOFOLLOW = 0o400000;
+        const PATH = 0o10000000;
     }
     pub struct CloneFlags: u64 {
         /// Set if VM shared between processes.
@@ -149,29 +182,641 @@ pub fn dup(fd: usize) -> isize {
     sys_dup(fd)
 }
 
+pub const LOCK_SH: usize = 1;
+pub const LOCK_EX: usize = 2;
+pub const LOCK_NB: usize = 4;
+pub const LOCK_UN: usize = 8;
+
+pub fn flock(fd: usize, operation: usize) -> isize {
+    sys_flock(fd, operation)
+}
+
+pub const EPOLLIN: u32 = 0x001;
+pub const EPOLLOUT: u32 = 0x004;
+pub const EPOLLONESHOT: u32 = 1 << 30;
+pub const EPOLLET: u32 = 1 << 31;
+pub const EPOLL_CTL_ADD: usize = 1;
+pub const EPOLL_CTL_DEL: usize = 2;
+pub const EPOLL_CTL_MOD: usize = 3;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EpollEvent {
+    pub events: u32,
+    pub data: u64,
+}
+
+pub fn epoll_create() -> isize {
+    sys_epoll_create1(0)
+}
+
+pub fn epoll_ctl(epfd: usize, op: usize, fd: usize, event: &EpollEvent) -> isize {
+    sys_epoll_ctl(epfd, op, fd, event as *const _ as usize)
+}
+
+pub fn epoll_wait(epfd: usize, events: &mut [EpollEvent], timeout: isize) -> isize {
+    sys_epoll_pwait(epfd, events.as_mut_ptr() as usize, events.len(), timeout)
+}
+
+pub const F_GETLK: usize = 5;
+pub const F_SETLK: usize = 6;
+pub const F_SETLKW: usize = 7;
+pub const F_RDLCK: i16 = 0;
+pub const F_WRLCK: i16 = 1;
+pub const F_UNLCK: i16 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Flock {
+    pub l_type: i16,
+    pub l_whence: i16,
+    pub l_start: isize,
+    pub l_len: isize,
+    pub l_pid: i32,
+}
+
+pub fn fcntl_lock(fd: usize, cmd: usize, lock: &mut Flock) -> isize {
+    sys_fcntl(fd, cmd, lock as *mut _ as usize)
+}
+
 pub fn chdir(path: &str) -> isize {
     sys_chdir(path.as_ptr() as *const u8)
 }
+pub fn chroot(path: &str) -> isize {
+    sys_chroot(path)
+}
+/// the length written including the NUL
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    sys_getcwd(buf)
+}
+
+/// lazy unmount
+pub const MNT_DETACH: i32 = 2;
+
+pub fn umount2(target: &str, flags: i32) -> isize {
+    sys_umount2(target, flags)
+}
+pub fn pivot_root(new_root: &str, put_old: &str) -> isize {
+    sys_pivot_root(new_root, put_old)
+}
 
 pub const AT_FDCWD: isize = -100;
 pub fn open(path: &str, flags: OpenFlags) -> isize {
     sys_openat(AT_FDCWD, path, flags.bits)
 }
+
+pub const RESOLVE_NO_XDEV: u64 = 0x01;
+pub const RESOLVE_NO_MAGICLINKS: u64 = 0x02;
+pub const RESOLVE_NO_SYMLINKS: u64 = 0x04;
+pub const RESOLVE_BENEATH: u64 = 0x08;
+pub const RESOLVE_IN_ROOT: u64 = 0x10;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenHow {
+    pub flags: u64,
+    pub mode: u64,
+    pub resolve: u64,
+}
+
+/// `size` is the size of the struct `how` points to, it may be a larger version of OpenHow
+pub fn openat2(dirfd: isize, path: &str, how: &OpenHow, size: usize) -> isize {
+    sys_openat2(dirfd, path, how as *const _ as usize, size)
+}
+/// argument of statmount and listmount
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MntIdReq {
+    pub size: u32,
+    pub spare: u32,
+    pub mnt_id: u64,
+    pub param: u64,
+    pub mnt_ns_id: u64,
+}
+
+pub const MNT_ID_REQ_SIZE_VER0: u32 = 24;
+pub const MNT_ID_REQ_SIZE_VER1: u32 = 32;
+
+/// statmount result header, the string fields are offsets into the bytes after it
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Statmount {
+    pub size: u32,
+    pub mnt_opts: u32,
+    pub mask: u64,
+    pub sb_dev_major: u32,
+    pub sb_dev_minor: u32,
+    pub sb_magic: u64,
+    pub sb_flags: u32,
+    pub fs_type: u32,
+    pub mnt_id: u64,
+    pub mnt_parent_id: u64,
+    pub mnt_id_old: u32,
+    pub mnt_parent_id_old: u32,
+    pub mnt_attr: u64,
+    pub mnt_propagation: u64,
+    pub mnt_peer_group: u64,
+    pub mnt_master: u64,
+    pub propagate_from: u64,
+    pub mnt_root: u32,
+    pub mnt_point: u32,
+    pub mnt_ns_id: u64,
+    pub spare: [u64; 49],
+}
+
+pub const STATMOUNT_SB_BASIC: u64 = 0x1;
+pub const STATMOUNT_MNT_BASIC: u64 = 0x2;
+pub const STATMOUNT_PROPAGATE_FROM: u64 = 0x4;
+pub const STATMOUNT_MNT_ROOT: u64 = 0x8;
+pub const STATMOUNT_MNT_POINT: u64 = 0x10;
+pub const STATMOUNT_FS_TYPE: u64 = 0x20;
+pub const STATMOUNT_MNT_OPTS: u64 = 0x80;
+pub const LISTMOUNT_REVERSE: usize = 1;
+pub const LSMT_ROOT: u64 = u64::MAX;
+
+/// `buf` receives a Statmount header followed by its strings
+pub fn statmount(req: &MntIdReq, buf: &mut [u8], flags: usize) -> isize {
+    sys_statmount(req as *const _ as usize, buf, flags)
+}
+pub fn listmount(req: &MntIdReq, mnt_ids: &mut [u64], flags: usize) -> isize {
+    sys_listmount(req as *const _ as usize, mnt_ids, flags)
+}
+
+pub const FSOPEN_CLOEXEC: u32 = 1;
+pub const FSMOUNT_CLOEXEC: u32 = 1;
+pub const FSCONFIG_SET_FLAG: u32 = 0;
+pub const FSCONFIG_SET_STRING: u32 = 1;
+pub const FSCONFIG_CMD_CREATE: u32 = 6;
+pub const MOUNT_ATTR_RDONLY: u32 = 0x1;
+pub const MOUNT_ATTR_NOSUID: u32 = 0x2;
+pub const MOUNT_ATTR_NODEV: u32 = 0x4;
+pub const MOUNT_ATTR_NOEXEC: u32 = 0x8;
+pub const MOVE_MOUNT_F_EMPTY_PATH: u32 = 0x4;
+pub const MOVE_MOUNT_T_EMPTY_PATH: u32 = 0x40;
+
+pub fn fsopen(fstype: &str, flags: u32) -> isize {
+    sys_fsopen(fstype, flags)
+}
+/// `key` and `value` are NUL-terminated, left out as the command requires
+pub fn fsconfig(fd: usize, cmd: u32, key: Option<&str>, value: Option<&str>, aux: i32) -> isize {
+    let ptr = |s: Option<&str>| s.map_or(0, |s| s.as_ptr() as usize);
+    sys_fsconfig(fd, cmd, ptr(key), ptr(value), aux)
+}
+pub fn fsmount(fs_fd: usize, flags: u32, attr_flags: u32) -> isize {
+    sys_fsmount(fs_fd, flags, attr_flags)
+}
+pub fn move_mount(from_dirfd: isize, from_path: &str, to_dirfd: isize, to_path: &str, flags: u32) -> isize {
+    sys_move_mount(from_dirfd, from_path, to_dirfd, to_path, flags)
+}
+pub fn symlink(target: &str, linkpath: &str) -> isize {
+    sys_symlinkat(target, AT_FDCWD, linkpath)
+}
+pub const DT_UNKNOWN: u8 = 0;
+pub const DT_FIFO: u8 = 1;
+pub const DT_CHR: u8 = 2;
+pub const DT_DIR: u8 = 4;
+pub const DT_BLK: u8 = 6;
+pub const DT_REG: u8 = 8;
+pub const DT_LNK: u8 = 10;
+pub const DT_SOCK: u8 = 12;
+/// size of `struct linux_dirent64` before d_name
+pub const DIRENT64_NAME_OFFSET: usize = 19;
+/// fill `buf` with `struct linux_dirent64` records, 0 at the end of the directory
+pub fn getdents64(fd: usize, buf: &mut [u8]) -> isize {
+    sys_getdents64(fd, buf)
+}
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+pub fn lseek(fd: usize, offset: i64, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+pub fn readlink(path: &str, buf: &mut [u8]) -> isize {
+    sys_readlinkat(AT_FDCWD, path, buf)
+}
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
+}
+pub fn link(old_path: &str, new_path: &str) -> isize {
+    sys_linkat(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdirat(AT_FDCWD, path, 0o755)
+}
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFBLK: u32 = 0o060000;
+pub const S_IFREG: u32 = 0o100000;
+pub fn mknod(path: &str, mode: u32, dev: u64) -> isize {
+    sys_mknodat(AT_FDCWD, path, mode, dev)
+}
+pub fn mkfifo(path: &str, mode: u32) -> isize {
+    sys_mknodat(AT_FDCWD, path, S_IFIFO | mode, 0)
+}
+pub const SPLICE_F_MOVE: u32 = 0x01;
+pub const SPLICE_F_NONBLOCK: u32 = 0x02;
+pub fn splice(fd_in: usize, off_in: Option<&mut i64>, fd_out: usize, off_out: Option<&mut i64>, len: usize, flags: u32) -> isize {
+    sys_splice(fd_in, off_in, fd_out, off_out, len, flags)
+}
+pub fn tee(fd_in: usize, fd_out: usize, len: usize, flags: u32) -> isize {
+    sys_tee(fd_in, fd_out, len, flags)
+}
+pub const SPLICE_F_GIFT: u32 = 0x08;
+/// move user memory into the pipe `fd`, or out of it when `fd` is its read end
+pub fn vmsplice(fd: usize, iov: &[IoVec], flags: u32) -> isize {
+    sys_vmsplice(fd, iov, flags)
+}
+pub fn rename(old_path: &str, new_path: &str) -> isize {
+    sys_renameat2(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+pub const RENAME_NOREPLACE: u32 = 1 << 0;
+pub const RENAME_EXCHANGE: u32 = 1 << 1;
+pub fn renameat2(old_path: &str, new_path: &str, flags: u32) -> isize {
+    sys_renameat2(AT_FDCWD, old_path, AT_FDCWD, new_path, flags)
+}
+pub fn chmod(path: &str, mode: u32) -> isize {
+    sys_fchmodat(AT_FDCWD, path, mode, 0)
+}
+pub fn chown(path: &str, uid: u32, gid: u32) -> isize {
+    sys_fchownat(AT_FDCWD, path, uid, gid, 0)
+}
+pub const F_OK: u32 = 0;
+pub const X_OK: u32 = 1;
+pub const W_OK: u32 = 2;
+pub const R_OK: u32 = 4;
+pub const AT_EACCESS: u32 = 0x200;
+pub fn faccessat2(path: &str, mode: u32, flags: u32) -> isize {
+    sys_faccessat2(AT_FDCWD, path, mode, flags)
+}
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
+pub fn truncate(path: &str, length: isize) -> isize {
+    sys_truncate(path, length)
+}
+pub fn ftruncate(fd: usize, length: isize) -> isize {
+    sys_ftruncate(fd, length)
+}
+pub fn sync() -> isize {
+    sys_sync()
+}
+pub fn fsync(fd: usize) -> isize {
+    sys_fsync(fd)
+}
+pub fn fdatasync(fd: usize) -> isize {
+    sys_fdatasync(fd)
+}
+
+pub const FALLOC_FL_KEEP_SIZE: i32 = 0x01;
+pub const FALLOC_FL_PUNCH_HOLE: i32 = 0x02;
+
+pub fn fallocate(fd: usize, mode: i32, offset: isize, len: isize) -> isize {
+    sys_fallocate(fd, mode, offset, len)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    pub st_dev: u64,
+    pub st_ino: u64,
+    pub st_mode: u32,
+    pub st_nlink: u32,
+    pub st_uid: u32,
+    pub st_gid: u32,
+    pub st_rdev: u64,
+    _pad0: u64,
+    pub st_size: i64,
+    pub st_blksize: i32,
+    _pad1: i32,
+    pub st_blocks: i64,
+    pub st_atime_sec: isize,
+    pub st_atime_nsec: isize,
+    pub st_mtime_sec: isize,
+    pub st_mtime_nsec: isize,
+    pub st_ctime_sec: isize,
+    pub st_ctime_nsec: isize,
+}
+
+pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
+    sys_fstat(fd, stat as *mut _ as usize)
+}
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatFs {
+    pub f_type: i64,
+    pub f_bsize: i64,
+    pub f_blocks: u64,
+    pub f_bfree: u64,
+    pub f_bavail: u64,
+    pub f_files: u64,
+    pub f_ffree: u64,
+    pub f_fsid: [i32; 2],
+    pub f_namelen: isize,
+    pub f_frsize: isize,
+    pub f_flags: isize,
+    pub f_spare: [isize; 4],
+}
+pub const TMPFS_MAGIC: i64 = 0x0102_1994;
+pub const EXT4_SUPER_MAGIC: i64 = 0xEF53;
+pub const PROC_SUPER_MAGIC: i64 = 0x9FA0;
+/// `path` must be NUL-terminated
+pub fn statfs(path: &str, buf: &mut StatFs) -> isize {
+    sys_statfs(path.as_ptr(), buf as *mut _ as usize)
+}
+pub fn fstatfs(fd: usize, buf: &mut StatFs) -> isize {
+    sys_fstatfs(fd, buf as *mut _ as usize)
+}
+pub const XATTR_CREATE: u32 = 0x1;
+pub const XATTR_REPLACE: u32 = 0x2;
+/// `path` and `name` must be NUL-terminated
+pub fn setxattr(path: &str, name: &str, value: &[u8], flags: u32) -> isize {
+    sys_setxattr(path, name, value, flags)
+}
+pub fn lsetxattr(path: &str, name: &str, value: &[u8], flags: u32) -> isize {
+    sys_lsetxattr(path, name, value, flags)
+}
+pub fn fsetxattr(fd: usize, name: &str, value: &[u8], flags: u32) -> isize {
+    sys_fsetxattr(fd, name, value, flags)
+}
+/// an empty `value` asks only for the size of the attribute
+pub fn getxattr(path: &str, name: &str, value: &mut [u8]) -> isize {
+    sys_getxattr(path, name, value)
+}
+pub fn lgetxattr(path: &str, name: &str, value: &mut [u8]) -> isize {
+    sys_lgetxattr(path, name, value)
+}
+pub fn fgetxattr(fd: usize, name: &str, value: &mut [u8]) -> isize {
+    sys_fgetxattr(fd, name, value)
+}
+/// the names come each ended by a NUL
+pub fn listxattr(path: &str, list: &mut [u8]) -> isize {
+    sys_listxattr(path, list)
+}
+pub fn llistxattr(path: &str, list: &mut [u8]) -> isize {
+    sys_llistxattr(path, list)
+}
+pub fn flistxattr(fd: usize, list: &mut [u8]) -> isize {
+    sys_flistxattr(fd, list)
+}
+pub fn removexattr(path: &str, name: &str) -> isize {
+    sys_removexattr(path, name)
+}
+pub fn lremovexattr(path: &str, name: &str) -> isize {
+    sys_lremovexattr(path, name)
+}
+pub fn fremovexattr(fd: usize, name: &str) -> isize {
+    sys_fremovexattr(fd, name)
+}
+pub const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+pub const AT_EMPTY_PATH: u32 = 0x1000;
+/// `path` must be NUL-terminated, None passes a NULL path
+pub fn fstatat(dirfd: isize, path: Option<&str>, stat: &mut Stat, flags: u32) -> isize {
+    let path = path.map_or(core::ptr::null(), |path| path.as_ptr());
+    sys_fstatat(dirfd, path, stat as *mut _ as usize, flags)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatxTimestamp {
+    pub tv_sec: i64,
+    pub tv_nsec: u32,
+    _reserved: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statx {
+    pub stx_mask: u32,
+    pub stx_blksize: u32,
+    pub stx_attributes: u64,
+    pub stx_nlink: u32,
+    pub stx_uid: u32,
+    pub stx_gid: u32,
+    pub stx_mode: u16,
+    _spare0: u16,
+    pub stx_ino: u64,
+    pub stx_size: u64,
+    pub stx_blocks: u64,
+    pub stx_attributes_mask: u64,
+    pub stx_atime: StatxTimestamp,
+    pub stx_btime: StatxTimestamp,
+    pub stx_ctime: StatxTimestamp,
+    pub stx_mtime: StatxTimestamp,
+    pub stx_rdev_major: u32,
+    pub stx_rdev_minor: u32,
+    pub stx_dev_major: u32,
+    pub stx_dev_minor: u32,
+    _spare: [u64; 14],
+}
+
+pub const STATX_ATIME: u32 = 1 << 5;
+pub const STATX_MTIME: u32 = 1 << 6;
+pub const STATX_CTIME: u32 = 1 << 7;
+/// `path` must be NUL-terminated
+pub fn statx(dirfd: isize, path: &str, flags: u32, mask: u32, statx: &mut Statx) -> isize {
+    sys_statx(dirfd, path.as_ptr(), flags, mask, statx as *mut _ as usize)
+}
+
+pub const UTIME_NOW: usize = 0x3fffffff;
+pub const UTIME_OMIT: usize = 0x3ffffffe;
+/// `path` must be NUL-terminated, None passes a NULL path to change `dirfd` itself;
+/// None for `times` sets both to now
+pub fn utimensat(dirfd: isize, path: Option<&str>, times: Option<&[TimeSpec; 2]>, flags: u32) -> isize {
+    let path = path.map_or(core::ptr::null(), |path| path.as_ptr());
+    let times = times.map_or(core::ptr::null(), |times| times.as_ptr());
+    sys_utimensat(dirfd, path, times, flags)
+}
+pub const GRND_NONBLOCK: u32 = 0x0001;
+pub const GRND_RANDOM: u32 = 0x0002;
+pub const GRND_INSECURE: u32 = 0x0004;
+
+pub fn getrandom(buf: &mut [u8], flags: u32) -> isize {
+    sys_getrandom(buf, flags)
+}
+pub const EFD_SEMAPHORE: i32 = 1;
+pub const EFD_NONBLOCK: i32 = 0o4000;
+pub const EFD_CLOEXEC: i32 = 0o2000000;
+
+pub fn eventfd(initval: u32, flags: i32) -> isize {
+    sys_eventfd2(initval, flags)
+}
+pub const IN_NONBLOCK: i32 = 0o4000;
+pub const IN_CLOEXEC: i32 = 0o2000000;
+pub const IN_ACCESS: u32 = 0x1;
+pub const IN_MODIFY: u32 = 0x2;
+pub const IN_ATTRIB: u32 = 0x4;
+pub const IN_MOVED_FROM: u32 = 0x40;
+pub const IN_MOVED_TO: u32 = 0x80;
+pub const IN_CREATE: u32 = 0x100;
+pub const IN_DELETE: u32 = 0x200;
+pub const IN_DELETE_SELF: u32 = 0x400;
+pub const IN_MOVE_SELF: u32 = 0x800;
+pub const IN_Q_OVERFLOW: u32 = 0x4000;
+pub const IN_IGNORED: u32 = 0x8000;
+pub const IN_ONLYDIR: u32 = 0x01000000;
+pub const IN_MASK_ADD: u32 = 0x20000000;
+pub const IN_ISDIR: u32 = 0x40000000;
+pub const IN_ONESHOT: u32 = 0x80000000;
+/// size of `struct inotify_event` before the name
+pub const INOTIFY_EVENT_SIZE: usize = 16;
+
+pub fn inotify_init1(flags: i32) -> isize {
+    sys_inotify_init1(flags)
+}
+/// `path` ends with a NUL
+pub fn inotify_add_watch(fd: usize, path: &str, mask: u32) -> isize {
+    sys_inotify_add_watch(fd, path, mask)
+}
+pub fn inotify_rm_watch(fd: usize, wd: i32) -> isize {
+    sys_inotify_rm_watch(fd, wd)
+}
+pub const SFD_NONBLOCK: i32 = 0o4000;
+pub const SFD_CLOEXEC: i32 = 0o2000000;
+
+/// `fd` is -1 to create a new signalfd, `mask` has bit `signo - 1` set for each signal
+pub fn signalfd(fd: isize, mask: u64, flags: i32) -> isize {
+    sys_signalfd4(fd, &mask, flags)
+}
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const TFD_NONBLOCK: i32 = 0o4000;
+pub const TFD_CLOEXEC: i32 = 0o2000000;
+pub const TFD_TIMER_ABSTIME: i32 = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+pub const CLOCK_THREAD_CPUTIME_ID: usize = 3;
+pub const CLOCK_BOOTTIME: usize = 7;
+/// the request of clock_nanosleep is an absolute time of the clock
+pub const TIMER_ABSTIME: i32 = 1;
+
+pub fn clock_gettime(clockid: usize, tp: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clockid, tp)
+}
+pub fn clock_settime(clockid: usize, tp: &TimeSpec) -> isize {
+    sys_clock_settime(clockid, tp)
+}
+pub fn clock_nanosleep(clockid: usize, flags: i32, req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    sys_clock_nanosleep(clockid, flags, req, rem.map_or(core::ptr::null_mut(), |r| r))
+}
+
+pub const ITIMER_REAL: usize = 0;
+pub const ITIMER_VIRTUAL: usize = 1;
+pub const ITIMER_PROF: usize = 2;
+
+pub fn getitimer(which: usize, curr: &mut ITimerVal) -> isize {
+    sys_getitimer(which, curr)
+}
+pub fn setitimer(which: usize, new: &ITimerVal, old: Option<&mut ITimerVal>) -> isize {
+    sys_setitimer(which, new, old.map_or(core::ptr::null_mut(), |o| o))
+}
+/// there is no alarm syscall on this architecture, it is ITIMER_REAL without
+/// an interval; returns the seconds left of the previous alarm, rounded
+pub fn alarm(seconds: usize) -> usize {
+    let new = ITimerVal {
+        it_interval: TimeVal::default(),
+        it_value: TimeVal { sec: seconds, usec: 0 },
+    };
+    let mut old = ITimerVal::default();
+    setitimer(ITIMER_REAL, &new, Some(&mut old));
+    if (old.it_value.sec == 0 && old.it_value.usec != 0) || old.it_value.usec >= 500_000 {
+        old.it_value.sec + 1
+    } else {
+        old.it_value.sec
+    }
+}
+
+pub fn timerfd_create(clockid: usize, flags: i32) -> isize {
+    sys_timerfd_create(clockid, flags)
+}
+pub fn timerfd_settime(fd: usize, flags: i32, new: &ITimerSpec, old: Option<&mut ITimerSpec>) -> isize {
+    sys_timerfd_settime(fd, flags, new, old)
+}
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
+    // the kernel fills two ints
+    let mut fds = [0i32; 2];
+    let ret = sys_pipe(&mut fds);
+    pipe_fd[0] = fds[0] as usize;
+    pipe_fd[1] = fds[1] as usize;
+    ret
 }
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
+/// fill `buf` from `fd`, fails with what read returned if it ends first
+pub fn read_exact(fd: usize, buf: &mut [u8]) -> Result<(), isize> {
+    let mut len = 0;
+    while len < buf.len() {
+        let n = read(fd, &mut buf[len..]);
+        if n <= 0 {
+            return Err(n);
+        }
+        len += n as usize;
+    }
+    Ok(())
+}
+/// read the whole file at `path`, at most `chunk` bytes per read
+pub fn read_file_by_chunk(path: &str, chunk: usize) -> Result<Vec<u8>, isize> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return Err(fd);
+    }
+    let mut content = Vec::new();
+    let mut buf = [0u8; 256];
+    let chunk = chunk.min(buf.len());
+    loop {
+        let n = read(fd as usize, &mut buf[..chunk]);
+        if n <= 0 {
+            break;
+        }
+        content.extend_from_slice(&buf[..n as usize]);
+    }
+    close(fd as usize);
+    Ok(content)
+}
+/// read the whole file at `path`
+pub fn read_file(path: &str) -> Result<Vec<u8>, isize> {
+    read_file_by_chunk(path, 256)
+}
+/// read the whole text file at `path`
+pub fn read_to_string(path: &str) -> Result<String, isize> {
+    read_file(path).map(|content| String::from_utf8_lossy(&content).into_owned())
+}
+/// a /proc/meminfo value in KB
+pub fn meminfo(name: &str) -> Option<usize> {
+    let text = read_to_string("/proc/meminfo\0").ok()?;
+    let line = text.lines().find(|line| line.starts_with(name))?;
+    line[name.len()..].trim().trim_end_matches("KB").trim().parse().ok()
+}
+/// whether `path` can be opened
+pub fn exists(path: &str) -> bool {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd >= 0 {
+        close(fd as usize);
+    }
+    fd >= 0
+}
+pub fn writev(fd: usize, iov: &[IoVec]) -> isize {
+    sys_writev(fd, iov)
+}
+/// per call O_APPEND for pwritev2
+pub const RWF_APPEND: i32 = 0x10;
+/// per call negation of O_APPEND for pwritev2
+pub const RWF_NOAPPEND: i32 = 0x20;
+pub fn pwritev2(fd: usize, iov: &[IoVec], offset: isize, flags: i32) -> isize {
+    sys_pwritev2(fd, iov, offset, flags)
+}
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> isize {
+    sys_pread64(fd, buf, offset)
+}
 pub fn write(fd: usize, buf: &[u8], len: usize) -> isize {
     sys_write(fd, buf, len)
 }
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
+pub fn exit_group(exit_code: i32) -> ! {
+    sys_exit_group(exit_code);
+}
 pub fn yield_() -> isize {
     sys_yield()
 }
@@ -188,12 +833,135 @@ pub fn get_time_ms() -> isize {
 pub fn getpid() -> isize {
     sys_getpid()
 }
+pub fn getppid() -> isize {
+    sys_getppid()
+}
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    sys_setpgid(pid, pgid)
+}
+pub fn getpgid(pid: usize) -> isize {
+    sys_getpgid(pid)
+}
+
+pub const RLIMIT_CORE: i32 = 4;
+pub const RLIMIT_NOFILE: i32 = 7;
+pub const RLIMIT_MEMLOCK: i32 = 8;
+pub const RLIM_INFINITY: usize = usize::MAX;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RLimit {
+    pub rlim_cur: usize,
+    pub rlim_max: usize,
+}
+
+pub fn getrlimit(resource: i32, limit: &mut RLimit) -> isize {
+    sys_prlimit64(0, resource, 0, limit as *mut _ as usize)
+}
+pub fn setrlimit(resource: i32, limit: &RLimit) -> isize {
+    sys_prlimit64(0, resource, limit as *const _ as usize, 0)
+}
+pub const SCHED_OTHER: i32 = 0;
+pub const SCHED_FIFO: i32 = 1;
+pub const SCHED_RR: i32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SchedParam {
+    pub sched_priority: i32,
+}
+
+pub fn sched_setscheduler(pid: usize, policy: i32, param: &SchedParam) -> isize {
+    sys_sched_setscheduler(pid, policy, param)
+}
+pub fn sched_getscheduler(pid: usize) -> isize {
+    sys_sched_getscheduler(pid)
+}
+pub fn sched_setparam(pid: usize, param: &SchedParam) -> isize {
+    sys_sched_setparam(pid, param)
+}
+pub fn sched_getparam(pid: usize, param: &mut SchedParam) -> isize {
+    sys_sched_getparam(pid, param)
+}
+pub fn sched_get_priority_max(policy: i32) -> isize {
+    sys_sched_get_priority_max(policy)
+}
+pub fn sched_get_priority_min(policy: i32) -> isize {
+    sys_sched_get_priority_min(policy)
+}
+pub const PRIO_PROCESS: usize = 0;
+pub const PRIO_PGRP: usize = 1;
+pub const PRIO_USER: usize = 2;
+
+pub fn setpriority(which: usize, who: usize, nice: i32) -> isize {
+    sys_setpriority(which, who, nice)
+}
+/// `20 - nice` of the most favoured task picked, or a negative errno
+pub fn getpriority(which: usize, who: usize) -> isize {
+    sys_getpriority(which, who)
+}
+/// add `inc` to the nice value of the process and return `20 - nice` like getpriority,
+/// there is no nice syscall on these architectures
+pub fn nice(inc: i32) -> isize {
+    let ret = getpriority(PRIO_PROCESS, 0);
+    if ret < 0 {
+        return ret;
+    }
+    let ret = setpriority(PRIO_PROCESS, 0, 20 - ret as i32 + inc);
+    if ret < 0 {
+        return ret;
+    }
+    getpriority(PRIO_PROCESS, 0)
+}
+pub fn setuid(uid: u32) -> isize {
+    sys_setuid(uid)
+}
 pub fn fork() -> isize {
     sys_fork()
 }
 pub fn clone(flags: usize, stack: usize, tls: usize) -> isize {
     sys_clone(flags, stack, tls)
 }
+
+/// `struct clone_args` of clone3, the stack is given by its lowest address and size
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct CloneArgs {
+    pub flags: u64,
+    pub pidfd: u64,
+    pub child_tid: u64,
+    pub parent_tid: u64,
+    pub exit_signal: u64,
+    pub stack: u64,
+    pub stack_size: u64,
+    pub tls: u64,
+    pub set_tid: u64,
+    pub set_tid_size: u64,
+    pub cgroup: u64,
+}
+
+pub const CLONE_ARGS_SIZE_VER0: usize = 64;
+pub const CLONE_ARGS_SIZE_VER2: usize = 88;
+
+pub fn clone3(args: &CloneArgs, size: usize) -> isize {
+    sys_clone3(args, size)
+}
+/// clone3 with the child running `entry(arg)`, which must not return
+pub fn clone3_entry(args: &CloneArgs, size: usize, entry: extern "C" fn(usize) -> !, arg: usize) -> isize {
+    sys_clone3_entry(args, size, entry, arg)
+}
+
+pub const PIDFD_NONBLOCK: u32 = 0o4000;
+
+pub fn pidfd_open(pid: isize, flags: u32) -> isize {
+    sys_pidfd_open(pid, flags)
+}
+pub fn pidfd_send_signal(pidfd: usize, signum: i32) -> isize {
+    sys_pidfd_send_signal(pidfd, signum, 0, 0)
+}
 pub fn exec(path: &str, args: &[*const u8]) -> isize {
     sys_exec(path, args)
 }
@@ -237,6 +1005,22 @@ pub fn waitpid_nb(pid: isize, exit_code: &mut i32) -> isize {
     sys_waitpid(pid as isize, exit_code as *mut _)
 }
 
+/// return at once if no child has changed state
+pub const WNOHANG: i32 = 1;
+/// also report children stopped by a signal
+pub const WUNTRACED: i32 = 2;
+/// also report stopped children continued by SIGCONT
+pub const WCONTINUED: i32 = 8;
+/// wait for every child, whatever its exit signal
+pub const __WALL: i32 = 0x40000000;
+/// wait only for children whose exit signal is not SIGCHLD
+pub const __WCLONE: i32 = 0x80000000u32 as i32;
+
+pub fn wait4(pid: isize, wstatus: &mut i32, options: i32, rusage: Option<&mut Rusage>) -> isize {
+    let rusage = rusage.map_or(core::ptr::null_mut(), |r| r as *mut _);
+    sys_wait4(pid, wstatus as *mut _, options, rusage)
+}
+
 pub fn sleep(period_ms: usize) {
     let start = get_time_ms();
     while get_time_ms() < start + period_ms as isize {
@@ -244,23 +1028,101 @@ pub fn sleep(period_ms: usize) {
     }
 }
 
-/// Action for a signal
+/// Action for a signal, same layout as the kernel `struct sigaction`
 #[repr(C, align(16))]
 #[derive(Debug, Clone, Copy)]
 pub struct SignalAction {
     pub handler: usize,
+    pub flags: u32,
+    pub restorer: usize,
     pub mask: SignalFlags,
+    _mask_hi: u32,
 }
 
 impl Default for SignalAction {
     fn default() -> Self {
         Self {
             handler: 0,
+            flags: 0,
+            restorer: 0,
             mask: SignalFlags::empty(),
+            _mask_hi: 0,
         }
     }
 }
 
+/// `SignalAction::restorer` is valid
+pub const SA_RESTORER: u32 = 0x04000000;
+/// run the handler on the alternate signal stack
+pub const SA_ONSTACK: u32 = 0x08000000;
+/// the handler takes `(signo, *const SigInfo, ucontext)`
+pub const SA_SIGINFO: u32 = 4;
+/// do not block the signal while its handler runs
+pub const SA_NODEFER: u32 = 0x40000000;
+/// restore the default action when the handler is entered
+pub const SA_RESETHAND: u32 = 0x80000000;
+
+/// si_code of signals sent by sigqueue
+pub const SI_QUEUE: i32 = -1;
+
+/// `siginfo_t` in libc, only the fields of `_rt`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    _pad: i32,
+    pub pid: i32,
+    pub uid: u32,
+    pub value: usize,
+    _rest: [u64; 12],
+}
+
+/// queue the signal with `value` to the process `pid`
+pub fn sigqueue(pid: usize, signum: i32, value: usize) -> isize {
+    let info = SigInfo {
+        signo: signum,
+        code: SI_QUEUE,
+        pid: getpid() as i32,
+        value,
+        ..Default::default()
+    };
+    sys_rt_sigqueueinfo(pid, signum, &info)
+}
+
+/// queue the signal with `value` to the thread `tid` of the process `tgid`
+pub fn tgsigqueue(tgid: usize, tid: usize, signum: i32, value: usize) -> isize {
+    let info = SigInfo {
+        signo: signum,
+        code: SI_QUEUE,
+        pid: getpid() as i32,
+        value,
+        ..Default::default()
+    };
+    sys_rt_tgsigqueueinfo(tgid, tid, signum, &info)
+}
+
+/// alternate signal stack, `stack_t` in libc
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalStack {
+    pub sp: usize,
+    pub flags: i32,
+    pub size: usize,
+}
+
+pub const SS_ONSTACK: i32 = 1;
+pub const SS_DISABLE: i32 = 2;
+pub const SS_AUTODISARM: i32 = 1 << 31;
+
+pub fn sigaltstack(ss: Option<&SignalStack>, old_ss: Option<&mut SignalStack>) -> isize {
+    sys_sigaltstack(
+        ss.map_or(core::ptr::null(), |s| s),
+        old_ss.map_or(core::ptr::null_mut(), |s| s),
+    )
+}
+
 pub const SIGDEF: i32 = 0; // Default signal handling
 pub const SIGHUP: i32 = 1;
 pub const SIGINT: i32 = 2;
@@ -293,6 +1155,9 @@ pub const SIGWINCH: i32 = 28;
 pub const SIGIO: i32 = 29;
 pub const SIGPWR: i32 = 30;
 pub const SIGSYS: i32 = 31;
+/// the first real-time signal, instances are queued instead of merged
+pub const SIGRTMIN: i32 = 32;
+pub const SIGRTMAX: i32 = 64;
 
 bitflags! {
     pub struct SignalFlags: i32 {
@@ -405,6 +1270,80 @@ pub fn connect(fd: usize, addr: *const SockaddrIn, addr_len: u32) -> isize {
     sys_connect(fd, addr as *const _ as *const u8, addr_len)
 }
 
+pub const AF_UNIX: i32 = 1;
+pub const AF_INET6: i32 = 10;
+pub const SOCK_STREAM: i32 = 1;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SockaddrIn6 {
+    pub sin6_family: u16,
+    pub sin6_port: u16,
+    pub sin6_flowinfo: u32,
+    pub sin6_addr: [u8; 16],
+    pub sin6_scope_id: u32,
+}
+impl SockaddrIn6 {
+    /// `::1`
+    pub const LOOPBACK: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    /// `port` is in host byte order
+    pub fn new(addr: [u8; 16], port: u16) -> Self {
+        SockaddrIn6 {
+            sin6_family: AF_INET6 as u16,
+            sin6_port: port.to_be(),
+            sin6_flowinfo: 0,
+            sin6_addr: addr,
+            sin6_scope_id: 0,
+        }
+    }
+}
+
+pub fn bind_in6(fd: usize, addr: &SockaddrIn6) -> isize {
+    sys_bind(fd, addr as *const _ as *const u8, core::mem::size_of::<SockaddrIn6>() as u32)
+}
+
+pub fn connect_in6(fd: usize, addr: &SockaddrIn6) -> isize {
+    sys_connect(fd, addr as *const _ as *const u8, core::mem::size_of::<SockaddrIn6>() as u32)
+}
+
+pub fn accept_in6(fd: usize, addr: &mut SockaddrIn6) -> isize {
+    let mut len = core::mem::size_of::<SockaddrIn6>() as u32;
+    sys_accept(fd, addr as *mut _ as *mut u8, &mut len)
+}
+
+#[repr(C)]
+pub struct SockaddrUn {
+    pub sun_family: u16,
+    pub sun_path: [u8; 108],
+}
+impl SockaddrUn {
+    /// a `name` starting with NUL is in the abstract namespace,
+    /// returns the address and its length
+    pub fn new(name: &[u8]) -> (Self, u32) {
+        let mut addr = SockaddrUn {
+            sun_family: AF_UNIX as u16,
+            sun_path: [0; 108],
+        };
+        addr.sun_path[..name.len()].copy_from_slice(name);
+        let mut len = 2 + name.len();
+        if name.first() != Some(&0) {
+            // pathname, count the terminating NUL
+            len += 1;
+        }
+        (addr, len as u32)
+    }
+}
+
+pub fn bind_unix(fd: usize, name: &[u8]) -> isize {
+    let (addr, len) = SockaddrUn::new(name);
+    sys_bind(fd, &addr as *const _ as *const u8, len)
+}
+
+pub fn connect_unix(fd: usize, name: &[u8]) -> isize {
+    let (addr, len) = SockaddrUn::new(name);
+    sys_connect(fd, &addr as *const _ as *const u8, len)
+}
+
 pub fn sendto(fd: usize, buf: &[u8], len: usize, flags: i32, addr: *const SockaddrIn, addr_len: u32) -> isize {
     sys_sendto(fd as i32, buf.as_ptr() , len, flags, addr as *const _ , addr_len)
 }
@@ -413,6 +1352,99 @@ pub fn recvfrom(fd: usize, buf: &mut [u8], len: usize, flags: i32, addr: *mut So
     sys_recvfrom(fd as i32, buf.as_ptr() as *mut u8, len, flags, addr as *mut _ , addr_len)
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoVec {
+    pub base: *const u8,
+    pub len: usize,
+}
+impl IoVec {
+    pub fn new(buf: &[u8]) -> Self {
+        IoVec { base: buf.as_ptr(), len: buf.len() }
+    }
+    /// an iovec the kernel writes to
+    pub fn new_mut(buf: &mut [u8]) -> Self {
+        IoVec { base: buf.as_mut_ptr(), len: buf.len() }
+    }
+}
+
+/// read the memory of process `pid` described by `remote` into `local`
+pub fn process_vm_readv(pid: usize, local: &[IoVec], remote: &[IoVec], flags: usize) -> isize {
+    sys_process_vm_readv(pid, local, remote, flags)
+}
+/// write `local` into the memory of process `pid` described by `remote`
+pub fn process_vm_writev(pid: usize, local: &[IoVec], remote: &[IoVec], flags: usize) -> isize {
+    sys_process_vm_writev(pid, local, remote, flags)
+}
+
+/// `struct msghdr`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MsgHdr {
+    pub name: *mut u8,
+    pub namelen: u32,
+    pub iov: *const IoVec,
+    pub iovlen: usize,
+    pub control: *mut u8,
+    pub controllen: usize,
+    pub flags: i32,
+}
+impl Default for MsgHdr {
+    fn default() -> Self {
+        MsgHdr {
+            name: core::ptr::null_mut(),
+            namelen: 0,
+            iov: core::ptr::null(),
+            iovlen: 0,
+            control: core::ptr::null_mut(),
+            controllen: 0,
+            flags: 0,
+        }
+    }
+}
+
+/// `struct cmsghdr`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CmsgHdr {
+    pub len: usize,
+    pub level: i32,
+    pub ty: i32,
+}
+
+pub fn sendmsg(fd: usize, msg: &MsgHdr, flags: i32) -> isize {
+    sys_sendmsg(fd, msg, flags)
+}
+
+pub fn recvmsg(fd: usize, msg: &mut MsgHdr, flags: i32) -> isize {
+    sys_recvmsg(fd, msg, flags)
+}
+
+pub const SOL_SOCKET: i32 = 1;
+pub const IPPROTO_TCP: i32 = 6;
+pub const SO_REUSEADDR: i32 = 2;
+pub const SO_ERROR: i32 = 4;
+pub const SO_SNDBUF: i32 = 7;
+pub const SO_RCVBUF: i32 = 8;
+pub const SO_KEEPALIVE: i32 = 9;
+pub const SO_REUSEPORT: i32 = 15;
+pub const TCP_NODELAY: i32 = 1;
+
+/// set an int socket option
+pub fn setsockopt(fd: usize, level: i32, optname: i32, value: i32) -> isize {
+    sys_setsockopt(fd, level, optname, &value as *const i32 as *const u8, 4)
+}
+
+/// get an int socket option
+pub fn getsockopt(fd: usize, level: i32, optname: i32) -> Result<i32, isize> {
+    let mut value: i32 = 0;
+    let mut len: u32 = 4;
+    match sys_getsockopt(fd, level, optname, &mut value as *mut i32 as *mut u8, &mut len) {
+        0 => Ok(value),
+        err => Err(err),
+    }
+}
+
 bitflags! {
     // Defined in <bits/mman-linux.h>
     #[derive(Default)]
@@ -431,8 +1463,12 @@ bitflags! {
         const MAP_FIXED = 0x10;
         /// Don't use a file.
         const MAP_ANONYMOUS = 0x20;
+        /// Lock the pages of the mapping.
+        const MAP_LOCKED = 0x02000;
         /// Don't check for reservations.
         const MAP_NORESERVE = 0x04000;
+        /// Populate (prefault) pagetables.
+        const MAP_POPULATE = 0x08000;
     }
 }
 
@@ -485,10 +1521,126 @@ pub fn mmap(addr: usize, len: usize, prot: MmapProt, flags: MmapFlags, fd: usize
     sys_mmap(addr, len, prot.bits, flags.bits, fd, offset)
 }
 
+pub fn munmap(addr: usize, len: usize) -> isize {
+    sys_munmap(addr, len)
+}
+
+pub fn mprotect(addr: usize, len: usize, prot: MmapProt) -> isize {
+    sys_mprotect(addr, len, prot.bits)
+}
+
+pub const MADV_DONTNEED: usize = 4;
+pub const MADV_HUGEPAGE: usize = 14;
+pub const MADV_NOHUGEPAGE: usize = 15;
+
+pub fn madvise(addr: usize, len: usize, advice: usize) -> isize {
+    sys_madvise(addr, len, advice)
+}
+
+pub const MPOL_DEFAULT: i32 = 0;
+pub const MPOL_PREFERRED: i32 = 1;
+pub const MPOL_BIND: i32 = 2;
+pub const MPOL_INTERLEAVE: i32 = 3;
+pub const MPOL_LOCAL: i32 = 4;
+pub const MPOL_F_NODE: u32 = 1 << 0;
+pub const MPOL_F_ADDR: u32 = 1 << 1;
+pub const MPOL_F_MEMS_ALLOWED: u32 = 1 << 2;
+/// bits in the node masks passed by the wrappers below, one past the last node like numactl
+const MAXNODE: usize = 65;
+
+pub fn mbind(addr: usize, len: usize, mode: i32, nodes: u64, flags: u32) -> isize {
+    sys_mbind(addr, len, mode, &nodes, MAXNODE, flags)
+}
+pub fn set_mempolicy(mode: i32, nodes: u64) -> isize {
+    sys_set_mempolicy(mode, &nodes, MAXNODE)
+}
+/// the policy mode and nodes, of the task or at `addr` with MPOL_F_ADDR
+pub fn get_mempolicy(addr: usize, flags: u32) -> Result<(i32, u64), isize> {
+    let (mut mode, mut nodes) = (0i32, 0u64);
+    let ret = sys_get_mempolicy(&mut mode, &mut nodes, MAXNODE, addr, flags);
+    if ret < 0 {
+        return Err(ret);
+    }
+    Ok((mode, nodes))
+}
+
 pub fn mremap(old_addr: usize, old_size: usize, new_size: usize, flags: MremapFlags, new_addr:usize) -> isize {
     sys_mremap(old_addr, old_size, new_size, flags.bits, new_addr)
 }
 
+/// seal the mappings in the range against any further change
+pub fn mseal(addr: usize, len: usize, flags: usize) -> isize {
+    sys_mseal(addr, len, flags)
+}
+
+pub const MCL_CURRENT: i32 = 1;
+pub const MCL_FUTURE: i32 = 2;
+pub const MCL_ONFAULT: i32 = 4;
+pub const MLOCK_ONFAULT: u32 = 1;
+
+/// lock the pages of the range in memory
+pub fn mlock(addr: usize, len: usize) -> isize {
+    sys_mlock(addr, len)
+}
+pub fn mlock2(addr: usize, len: usize, flags: u32) -> isize {
+    sys_mlock2(addr, len, flags)
+}
+pub fn munlock(addr: usize, len: usize) -> isize {
+    sys_munlock(addr, len)
+}
+pub fn mlockall(flags: i32) -> isize {
+    sys_mlockall(flags)
+}
+pub fn munlockall() -> isize {
+    sys_munlockall()
+}
+
+/// a byte for each page of the range in `vec`, the low bit set if the page is resident
+pub fn mincore(addr: usize, len: usize, vec: &mut [u8]) -> isize {
+    sys_mincore(addr, len, vec.as_mut_ptr())
+}
+
+pub const MEMBARRIER_CMD_QUERY: i32 = 0;
+pub const MEMBARRIER_CMD_GLOBAL: i32 = 1 << 0;
+pub const MEMBARRIER_CMD_PRIVATE_EXPEDITED: i32 = 1 << 3;
+pub const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: i32 = 1 << 4;
+pub const MEMBARRIER_CMD_GET_REGISTRATIONS: i32 = 1 << 9;
+
+pub fn membarrier(cmd: i32, flags: u32) -> isize {
+    sys_membarrier(cmd, flags, 0)
+}
+
+/// create a file whose mapped pages the kernel cannot read, size it with `ftruncate`
+pub fn memfd_secret(flags: u32) -> isize {
+    sys_memfd_secret(flags)
+}
+
+/// head of a thread's list of held robust futexes
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct RobustListHead {
+    /// first entry, the head itself when empty
+    pub next: usize,
+    /// from an entry to its futex word
+    pub futex_offset: isize,
+    /// entry being locked or unlocked
+    pub list_op_pending: usize,
+}
+
+pub const FUTEX_WAITERS: u32 = 0x8000_0000;
+pub const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+pub const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+
+pub fn set_robust_list(head: &RobustListHead) -> isize {
+    sys_set_robust_list(head as *const _ as usize, core::mem::size_of::<RobustListHead>())
+}
+
+/// the robust list head of thread `tid`, 0 for the caller
+pub fn get_robust_list(tid: usize) -> Option<usize> {
+    let (mut head, mut len) = (0, 0);
+    (sys_get_robust_list(tid, &mut head, &mut len) == 0).then(|| head)
+}
+
 pub fn shutdown() -> isize {
     sys_shutdown(0, 0, 0, 0)
 }
@@ -501,4 +1653,108 @@ pub struct TimeVal {
     pub sec: usize,
     /// microseconds
     pub usec: usize,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+/// resource usage, filled by getrusage
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    /// peak resident set in kilobytes
+    pub ru_maxrss: usize,
+    pub ru_ixrss: usize,
+    pub ru_idrss: usize,
+    pub ru_isrss: usize,
+    pub ru_minflt: usize,
+    pub ru_majflt: usize,
+    pub ru_nswap: usize,
+    pub ru_inblock: usize,
+    pub ru_oublock: usize,
+    pub ru_msgsnd: usize,
+    pub ru_msgrcv: usize,
+    pub ru_nsignals: usize,
+    pub ru_nvcsw: usize,
+    pub ru_nivcsw: usize,
+}
+
+pub const RUSAGE_SELF: i32 = 0;
+pub const RUSAGE_CHILDREN: i32 = -1;
+pub const RUSAGE_THREAD: i32 = 1;
+
+pub fn getrusage(who: i32, usage: &mut Rusage) -> isize {
+    sys_getrusage(who, usage)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+/// overall system statistics, filled by sysinfo
+pub struct Sysinfo {
+    /// seconds since boot
+    pub uptime: i64,
+    /// 1, 5 and 15 minute load averages, with SI_LOAD_SHIFT bits of fraction
+    pub loads: [u64; 3],
+    pub totalram: u64,
+    pub freeram: u64,
+    pub sharedram: u64,
+    pub bufferram: u64,
+    pub totalswap: u64,
+    pub freeswap: u64,
+    pub procs: u16,
+    pub pad: u16,
+    pub totalhigh: u64,
+    pub freehigh: u64,
+    /// the unit of the memory sizes in bytes
+    pub mem_unit: u32,
+}
+
+pub const SI_LOAD_SHIFT: usize = 16;
+
+pub fn sysinfo(info: &mut Sysinfo) -> isize {
+    sys_sysinfo(info)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+/// cpu times of the process and of its reaped children, in clock ticks
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+/// returns the clock ticks since an arbitrary point in the past
+pub fn times(tms: &mut Tms) -> isize {
+    sys_times(tms)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+/// TimeSpec struct for syscall, TimeSpec stands for high-precision time value
+pub struct TimeSpec {
+    /// seconds
+    pub tv_sec: usize,
+    /// nanoseconds
+    pub tv_nsec: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+/// interval and time left of an interval timer
+pub struct ITimerVal {
+    /// interval for periodic timer
+    pub it_interval: TimeVal,
+    /// time until next expiration
+    pub it_value: TimeVal,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+/// interval and initial expiration of a timer
+pub struct ITimerSpec {
+    /// interval for periodic timer
+    pub it_interval: TimeSpec,
+    /// time until next expiration
+    pub it_value: TimeSpec,
+}
@@ -1,23 +1,113 @@
 use core::arch::asm;
 
-use crate::{SignalAction, TimeVal};
-
+use crate::{CloneArgs, IoVec, ITimerSpec, ITimerVal, Rusage, SchedParam, SigInfo, SignalAction, SignalStack, Sysinfo, TimeSpec, TimeVal, Tms};
+
+const SYSCALL_SETXATTR: usize = 5;
+const SYSCALL_LSETXATTR: usize = 6;
+const SYSCALL_FSETXATTR: usize = 7;
+const SYSCALL_GETXATTR: usize = 8;
+const SYSCALL_LGETXATTR: usize = 9;
+const SYSCALL_FGETXATTR: usize = 10;
+const SYSCALL_LISTXATTR: usize = 11;
+const SYSCALL_LLISTXATTR: usize = 12;
+const SYSCALL_FLISTXATTR: usize = 13;
+const SYSCALL_REMOVEXATTR: usize = 14;
+const SYSCALL_LREMOVEXATTR: usize = 15;
+const SYSCALL_FREMOVEXATTR: usize = 16;
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_EVENTFD2: usize = 19;
+const SYSCALL_EPOLL_CREATE1: usize = 20;
+const SYSCALL_EPOLL_CTL: usize = 21;
+const SYSCALL_EPOLL_PWAIT: usize = 22;
 const SYSCALL_DUP: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_INOTIFY_INIT1: usize = 26;
+const SYSCALL_INOTIFY_ADD_WATCH: usize = 27;
+const SYSCALL_INOTIFY_RM_WATCH: usize = 28;
+const SYSCALL_FLOCK: usize = 32;
+const SYSCALL_MKNODAT: usize = 33;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_SYMLINKAT: usize = 36;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_PIVOT_ROOT: usize = 41;
+const SYSCALL_STATFS: usize = 43;
+const SYSCALL_FSTATFS: usize = 44;
+const SYSCALL_TRUNCATE: usize = 45;
+const SYSCALL_FTRUNCATE: usize = 46;
+const SYSCALL_FALLOCATE: usize = 47;
 const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_CHROOT: usize = 51;
 const SYSCALL_OPENAT: usize = 56;
+const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_LSEEK: usize = 62;
+const SYSCALL_OPENAT2: usize = 437;
+const SYSCALL_STATMOUNT: usize = 457;
+const SYSCALL_LISTMOUNT: usize = 458;
+const SYSCALL_MOVE_MOUNT: usize = 429;
+const SYSCALL_FSOPEN: usize = 430;
+const SYSCALL_FSCONFIG: usize = 431;
+const SYSCALL_FSMOUNT: usize = 432;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_WRITEV: usize = 66;
+const SYSCALL_PREAD64: usize = 67;
+const SYSCALL_PWRITEV2: usize = 287;
+const SYSCALL_VMSPLICE: usize = 75;
+const SYSCALL_SPLICE: usize = 76;
+const SYSCALL_TEE: usize = 77;
+const SYSCALL_READLINKAT: usize = 78;
+const SYSCALL_FSTATAT: usize = 79;
+const SYSCALL_UTIMENSAT: usize = 88;
+const SYSCALL_STATX: usize = 291;
+const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_SYNC: usize = 81;
+const SYSCALL_FSYNC: usize = 82;
+const SYSCALL_FDATASYNC: usize = 83;
+const SYSCALL_RENAMEAT2: usize = 276;
+const SYSCALL_FCHMODAT: usize = 53;
+const SYSCALL_FCHOWNAT: usize = 54;
+const SYSCALL_FACCESSAT2: usize = 439;
+const SYSCALL_GETRANDOM: usize = 278;
+const SYSCALL_SIGNALFD4: usize = 74;
+const SYSCALL_TIMERFD_CREATE: usize = 85;
+const SYSCALL_TIMERFD_SETTIME: usize = 86;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SCHED_SETPARAM: usize = 118;
+const SYSCALL_SCHED_SETSCHEDULER: usize = 119;
+const SYSCALL_SCHED_GETSCHEDULER: usize = 120;
+const SYSCALL_SCHED_GETPARAM: usize = 121;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SCHED_GET_PRIORITY_MAX: usize = 125;
+const SYSCALL_SCHED_GET_PRIORITY_MIN: usize = 126;
+const SYSCALL_GETRUSAGE: usize = 165;
+const SYSCALL_SYSINFO: usize = 179;
+const SYSCALL_TIMES: usize = 153;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGALTSTACK: usize = 132;
 const SYSCALL_SIGACTION: usize = 134;
 const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_RT_SIGQUEUEINFO: usize = 138;
+const SYSCALL_RT_TGSIGQUEUEINFO: usize = 240;
 const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SETPRIORITY: usize = 140;
+const SYSCALL_GETPRIORITY: usize = 141;
 const SYSCALL_REBOOT: usize = 142;
+const SYSCALL_SETUID: usize = 146;
 const SYSCALL_GETTIMEOFDAY: usize = 169;
+const SYSCALL_CLOCK_SETTIME: usize = 112;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_CLOCK_NANOSLEEP: usize = 115;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETPPID: usize = 173;
+const SYSCALL_GETTID: usize = 178;
 const SYSCALL_SOCKET: usize = 198;
 const SYSCALL_BIND: usize = 200;
 const SYSCALL_LISTEN: usize = 201;
@@ -25,13 +115,40 @@ const SYSCALL_ACCEPT: usize = 202;
 const SYSCALL_CONNECT: usize = 203;
 const SYSCALL_SENDTO: usize = 206;
 const SYSCALL_RECVFROM: usize = 207;
+const SYSCALL_SETSOCKOPT: usize = 208;
+const SYSCALL_GETSOCKOPT: usize = 209;
+const SYSCALL_SENDMSG: usize = 211;
+const SYSCALL_RECVMSG: usize = 212;
 const SYSCALL_BRK: usize = 214;
 const SYSCALL_CLONE: usize = 220;
+const SYSCALL_CLONE3: usize = 435;
+const SYSCALL_PIDFD_SEND_SIGNAL: usize = 424;
+const SYSCALL_PIDFD_OPEN: usize = 434;
 const SYSCALL_EXECVE: usize = 221;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_PRLIMIT64: usize = 261;
+const SYSCALL_PROCESS_VM_READV: usize = 270;
+const SYSCALL_PROCESS_VM_WRITEV: usize = 271;
 const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_MREMAP: usize = 216;
 const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_MADVISE: usize = 233;
+const SYSCALL_MBIND: usize = 235;
+const SYSCALL_GET_MEMPOLICY: usize = 236;
+const SYSCALL_SET_MEMPOLICY: usize = 237;
+const SYSCALL_MEMFD_SECRET: usize = 447;
+const SYSCALL_MSEAL: usize = 462;
+const SYSCALL_MLOCK: usize = 228;
+const SYSCALL_MUNLOCK: usize = 229;
+const SYSCALL_MLOCKALL: usize = 230;
+const SYSCALL_MUNLOCKALL: usize = 231;
+const SYSCALL_MLOCK2: usize = 284;
+const SYSCALL_MINCORE: usize = 232;
+const SYSCALL_MEMBARRIER: usize = 283;
+const SYSCALL_EXIT_GROUP: usize = 94;
+const SYSCALL_SET_ROBUST_LIST: usize = 99;
+const SYSCALL_GET_ROBUST_LIST: usize = 100;
 
 #[cfg(target_arch="riscv64")]
 fn syscall(id: usize, args: [usize; 6]) -> isize {
@@ -75,22 +192,262 @@ pub fn sys_dup(fd: usize) -> isize {
     syscall(SYSCALL_DUP, [fd, 0, 0,0,0,0])
 }
 
+pub fn sys_epoll_create1(flags: usize) -> isize {
+    syscall(SYSCALL_EPOLL_CREATE1, [flags, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_epoll_ctl(epfd: usize, op: usize, fd: usize, event: usize) -> isize {
+    syscall(SYSCALL_EPOLL_CTL, [epfd, op, fd, event, 0, 0])
+}
+
+pub fn sys_epoll_pwait(epfd: usize, events: usize, maxevents: usize, timeout: isize) -> isize {
+    syscall(SYSCALL_EPOLL_PWAIT, [epfd, events, maxevents, timeout as usize, 0, 0])
+}
+
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_FCNTL, [fd, cmd, arg, 0, 0, 0])
+}
+
+pub fn sys_flock(fd: usize, operation: usize) -> isize {
+    syscall(SYSCALL_FLOCK, [fd, operation, 0, 0, 0, 0])
+}
+
 pub fn sys_chdir(path: *const u8) -> isize {
     syscall(SYSCALL_CHDIR, [path as usize, 0, 0, 0, 0, 0])
 }
 
+pub fn sys_chroot(path: &str) -> isize {
+    syscall(SYSCALL_CHROOT, [path.as_ptr() as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_getcwd(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETCWD, [buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0, 0])
+}
+
+pub fn sys_umount2(target: &str, flags: i32) -> isize {
+    syscall(SYSCALL_UMOUNT2, [target.as_ptr() as usize, flags as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_pivot_root(new_root: &str, put_old: &str) -> isize {
+    syscall(SYSCALL_PIVOT_ROOT, [new_root.as_ptr() as usize, put_old.as_ptr() as usize, 0, 0, 0, 0])
+}
+
 pub fn sys_openat(dirfd: isize, path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPENAT, [dirfd as usize, path.as_ptr() as usize, flags as usize, 0, 0, 0])
 }
 
+pub fn sys_openat2(dirfd: isize, path: &str, how: usize, size: usize) -> isize {
+    syscall(SYSCALL_OPENAT2, [dirfd as usize, path.as_ptr() as usize, how, size, 0, 0])
+}
+
+pub fn sys_symlinkat(target: &str, newdirfd: isize, linkpath: &str) -> isize {
+    syscall(SYSCALL_SYMLINKAT, [target.as_ptr() as usize, newdirfd as usize, linkpath.as_ptr() as usize, 0, 0, 0])
+}
+
+pub fn sys_readlinkat(dirfd: isize, path: &str, buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_READLINKAT, [dirfd as usize, path.as_ptr() as usize, buf.as_mut_ptr() as usize, buf.len(), 0, 0])
+}
+
+pub fn sys_mknodat(dirfd: isize, path: &str, mode: u32, dev: u64) -> isize {
+    syscall(SYSCALL_MKNODAT, [dirfd as usize, path.as_ptr() as usize, mode as usize, dev as usize, 0, 0])
+}
+
+pub fn sys_mkdirat(dirfd: isize, path: &str, mode: u32) -> isize {
+    syscall(SYSCALL_MKDIRAT, [dirfd as usize, path.as_ptr() as usize, mode as usize, 0, 0, 0])
+}
+
+pub fn sys_renameat2(old_dirfd: isize, old_path: &str, new_dirfd: isize, new_path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_RENAMEAT2,
+        [old_dirfd as usize, old_path.as_ptr() as usize, new_dirfd as usize, new_path.as_ptr() as usize, flags as usize, 0],
+    )
+}
+
+pub fn sys_fchmodat(dirfd: isize, path: &str, mode: u32, flags: u32) -> isize {
+    syscall(SYSCALL_FCHMODAT, [dirfd as usize, path.as_ptr() as usize, mode as usize, flags as usize, 0, 0])
+}
+
+pub fn sys_fchownat(dirfd: isize, path: &str, uid: u32, gid: u32, flags: u32) -> isize {
+    syscall(
+        SYSCALL_FCHOWNAT,
+        [dirfd as usize, path.as_ptr() as usize, uid as usize, gid as usize, flags as usize, 0],
+    )
+}
+
+pub fn sys_faccessat2(dirfd: isize, path: &str, mode: u32, flags: u32) -> isize {
+    syscall(SYSCALL_FACCESSAT2, [dirfd as usize, path.as_ptr() as usize, mode as usize, flags as usize, 0, 0])
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(SYSCALL_UNLINKAT, [dirfd as usize, path.as_ptr() as usize, flags as usize, 0, 0, 0])
+}
+
+pub fn sys_linkat(old_dirfd: isize, old_path: &str, new_dirfd: isize, new_path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_LINKAT,
+        [old_dirfd as usize, old_path.as_ptr() as usize, new_dirfd as usize, new_path.as_ptr() as usize, flags as usize, 0],
+    )
+}
+
+pub fn sys_truncate(path: &str, length: isize) -> isize {
+    syscall(SYSCALL_TRUNCATE, [path.as_ptr() as usize, length as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_ftruncate(fd: usize, length: isize) -> isize {
+    syscall(SYSCALL_FTRUNCATE, [fd, length as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_sync() -> isize {
+    syscall(SYSCALL_SYNC, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_fsync(fd: usize) -> isize {
+    syscall(SYSCALL_FSYNC, [fd, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_fdatasync(fd: usize) -> isize {
+    syscall(SYSCALL_FDATASYNC, [fd, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_fallocate(fd: usize, mode: i32, offset: isize, len: isize) -> isize {
+    syscall(SYSCALL_FALLOCATE, [fd, mode as usize, offset as usize, len as usize, 0, 0])
+}
+
+pub fn sys_fstat(fd: usize, stat: usize) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, stat, 0, 0, 0, 0])
+}
+
+pub fn sys_statfs(path: *const u8, buf: usize) -> isize {
+    syscall(SYSCALL_STATFS, [path as usize, buf, 0, 0, 0, 0])
+}
+
+pub fn sys_fstatfs(fd: usize, buf: usize) -> isize {
+    syscall(SYSCALL_FSTATFS, [fd, buf, 0, 0, 0, 0])
+}
+
+pub fn sys_setxattr(path: &str, name: &str, value: &[u8], flags: u32) -> isize {
+    syscall(SYSCALL_SETXATTR, [path.as_ptr() as usize, name.as_ptr() as usize, value.as_ptr() as usize, value.len(), flags as usize, 0])
+}
+
+pub fn sys_lsetxattr(path: &str, name: &str, value: &[u8], flags: u32) -> isize {
+    syscall(SYSCALL_LSETXATTR, [path.as_ptr() as usize, name.as_ptr() as usize, value.as_ptr() as usize, value.len(), flags as usize, 0])
+}
+
+pub fn sys_fsetxattr(fd: usize, name: &str, value: &[u8], flags: u32) -> isize {
+    syscall(SYSCALL_FSETXATTR, [fd, name.as_ptr() as usize, value.as_ptr() as usize, value.len(), flags as usize, 0])
+}
+
+pub fn sys_getxattr(path: &str, name: &str, value: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETXATTR, [path.as_ptr() as usize, name.as_ptr() as usize, value.as_mut_ptr() as usize, value.len(), 0, 0])
+}
+
+pub fn sys_lgetxattr(path: &str, name: &str, value: &mut [u8]) -> isize {
+    syscall(SYSCALL_LGETXATTR, [path.as_ptr() as usize, name.as_ptr() as usize, value.as_mut_ptr() as usize, value.len(), 0, 0])
+}
+
+pub fn sys_fgetxattr(fd: usize, name: &str, value: &mut [u8]) -> isize {
+    syscall(SYSCALL_FGETXATTR, [fd, name.as_ptr() as usize, value.as_mut_ptr() as usize, value.len(), 0, 0])
+}
+
+pub fn sys_listxattr(path: &str, list: &mut [u8]) -> isize {
+    syscall(SYSCALL_LISTXATTR, [path.as_ptr() as usize, list.as_mut_ptr() as usize, list.len(), 0, 0, 0])
+}
+
+pub fn sys_llistxattr(path: &str, list: &mut [u8]) -> isize {
+    syscall(SYSCALL_LLISTXATTR, [path.as_ptr() as usize, list.as_mut_ptr() as usize, list.len(), 0, 0, 0])
+}
+
+pub fn sys_flistxattr(fd: usize, list: &mut [u8]) -> isize {
+    syscall(SYSCALL_FLISTXATTR, [fd, list.as_mut_ptr() as usize, list.len(), 0, 0, 0])
+}
+
+pub fn sys_removexattr(path: &str, name: &str) -> isize {
+    syscall(SYSCALL_REMOVEXATTR, [path.as_ptr() as usize, name.as_ptr() as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_lremovexattr(path: &str, name: &str) -> isize {
+    syscall(SYSCALL_LREMOVEXATTR, [path.as_ptr() as usize, name.as_ptr() as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_fremovexattr(fd: usize, name: &str) -> isize {
+    syscall(SYSCALL_FREMOVEXATTR, [fd, name.as_ptr() as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_fstatat(dirfd: isize, path: *const u8, stat: usize, flags: u32) -> isize {
+    syscall(SYSCALL_FSTATAT, [dirfd as usize, path as usize, stat, flags as usize, 0, 0])
+}
+
+pub fn sys_utimensat(dirfd: isize, path: *const u8, times: *const TimeSpec, flags: u32) -> isize {
+    syscall(SYSCALL_UTIMENSAT, [dirfd as usize, path as usize, times as usize, flags as usize, 0, 0])
+}
+
+pub fn sys_statx(dirfd: isize, path: *const u8, flags: u32, mask: u32, statx: usize) -> isize {
+    syscall(SYSCALL_STATX, [dirfd as usize, path as usize, flags as usize, mask as usize, statx, 0])
+}
+
+pub fn sys_getrandom(buf: &mut [u8], flags: u32) -> isize {
+    syscall(SYSCALL_GETRANDOM, [buf.as_mut_ptr() as usize, buf.len(), flags as usize, 0, 0, 0])
+}
+
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0,0,0,0])
 }
 
-pub fn sys_pipe(pipe: &mut [usize]) -> isize {
+pub fn sys_eventfd2(initval: u32, flags: i32) -> isize {
+    syscall(SYSCALL_EVENTFD2, [initval as usize, flags as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_inotify_init1(flags: i32) -> isize {
+    syscall(SYSCALL_INOTIFY_INIT1, [flags as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_inotify_add_watch(fd: usize, path: &str, mask: u32) -> isize {
+    syscall(SYSCALL_INOTIFY_ADD_WATCH, [fd, path.as_ptr() as usize, mask as usize, 0, 0, 0])
+}
+
+pub fn sys_inotify_rm_watch(fd: usize, wd: i32) -> isize {
+    syscall(SYSCALL_INOTIFY_RM_WATCH, [fd, wd as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_signalfd4(fd: isize, mask: &u64, flags: i32) -> isize {
+    syscall(SYSCALL_SIGNALFD4, [fd as usize, mask as *const _ as usize, 8, flags as usize, 0, 0])
+}
+
+pub fn sys_timerfd_create(clockid: usize, flags: i32) -> isize {
+    syscall(SYSCALL_TIMERFD_CREATE, [clockid, flags as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_timerfd_settime(fd: usize, flags: i32, new: &ITimerSpec, old: Option<&mut ITimerSpec>) -> isize {
+    let old = old.map_or(0, |old| old as *mut _ as usize);
+    syscall(SYSCALL_TIMERFD_SETTIME, [fd, flags as usize, new as *const _ as usize, old, 0, 0])
+}
+
+pub fn sys_pipe(pipe: &mut [i32]) -> isize {
     syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0,0,0,0])
 }
 
+pub fn sys_vmsplice(fd: usize, iov: &[IoVec], flags: u32) -> isize {
+    syscall(SYSCALL_VMSPLICE, [fd, iov.as_ptr() as usize, iov.len(), flags as usize, 0, 0])
+}
+
+pub fn sys_splice(fd_in: usize, off_in: Option<&mut i64>, fd_out: usize, off_out: Option<&mut i64>, len: usize, flags: u32) -> isize {
+    let off_in = off_in.map_or(0, |off| off as *mut i64 as usize);
+    let off_out = off_out.map_or(0, |off| off as *mut i64 as usize);
+    syscall(SYSCALL_SPLICE, [fd_in, off_in, fd_out, off_out, len, flags as usize])
+}
+
+pub fn sys_tee(fd_in: usize, fd_out: usize, len: usize, flags: u32) -> isize {
+    syscall(SYSCALL_TEE, [fd_in, fd_out, len, flags as usize, 0, 0])
+}
+
+pub fn sys_getdents64(fd: usize, buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETDENTS64, [fd, buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0])
+}
+
+pub fn sys_lseek(fd: usize, offset: i64, whence: usize) -> isize {
+    syscall(SYSCALL_LSEEK, [fd, offset as usize, whence, 0, 0, 0])
+}
+
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
         SYSCALL_READ,
@@ -98,6 +455,24 @@ pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     )
 }
 
+pub fn sys_writev(fd: usize, iov: &[IoVec]) -> isize {
+    syscall(SYSCALL_WRITEV, [fd, iov.as_ptr() as usize, iov.len(), 0, 0, 0])
+}
+
+pub fn sys_pwritev2(fd: usize, iov: &[IoVec], offset: isize, flags: i32) -> isize {
+    syscall(
+        SYSCALL_PWRITEV2,
+        [fd, iov.as_ptr() as usize, iov.len(), offset as usize, 0, flags as usize],
+    )
+}
+
+pub fn sys_pread64(fd: usize, buffer: &mut [u8], offset: usize) -> isize {
+    syscall(
+        SYSCALL_PREAD64,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len(), offset, 0, 0],
+    )
+}
+
 pub fn sys_write(fd: usize, buffer: &[u8], len: usize) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, len, 0, 0, 0])
 }
@@ -107,6 +482,11 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("sys_exit never returns!");
 }
 
+pub fn sys_exit_group(exit_code: i32) -> ! {
+    syscall(SYSCALL_EXIT_GROUP, [exit_code as usize, 0, 0, 0, 0, 0]);
+    panic!("sys_exit_group never returns!");
+}
+
 pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0,0,0,0])
 }
@@ -115,16 +495,69 @@ pub fn sys_kill(pid: usize, signal: i32) -> isize {
     syscall(SYSCALL_KILL, [pid, signal as usize, 0,0,0,0])
 }
 
+pub fn sys_getrusage(who: i32, usage: &mut Rusage) -> isize {
+    syscall(SYSCALL_GETRUSAGE, [who as usize, usage as *mut _ as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_sysinfo(info: &mut Sysinfo) -> isize {
+    syscall(SYSCALL_SYSINFO, [info as *mut _ as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_times(tms: &mut Tms) -> isize {
+    syscall(SYSCALL_TIMES, [tms as *mut _ as usize, 0, 0, 0, 0, 0])
+}
+
 pub fn sys_get_time_of_day(tv: &mut TimeVal) -> isize {
     syscall(SYSCALL_GETTIMEOFDAY, [tv as *mut _ as usize, 0, 0,0,0,0])
 }
 
+pub fn sys_clock_settime(clockid: usize, tp: &TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_SETTIME, [clockid, tp as *const _ as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_clock_gettime(clockid: usize, tp: &mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clockid, tp as *mut _ as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_clock_nanosleep(clockid: usize, flags: i32, req: &TimeSpec, rem: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_NANOSLEEP, [clockid, flags as usize, req as *const _ as usize, rem as usize, 0, 0])
+}
+
+pub fn sys_getitimer(which: usize, curr: &mut ITimerVal) -> isize {
+    syscall(SYSCALL_GETITIMER, [which, curr as *mut _ as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_setitimer(which: usize, new: &ITimerVal, old: *mut ITimerVal) -> isize {
+    syscall(SYSCALL_SETITIMER, [which, new as *const _ as usize, old as usize, 0, 0, 0])
+}
+
 pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0, 0, 0, 0])
 }
 
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall(SYSCALL_SETPGID, [pid, pgid, 0, 0, 0, 0])
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall(SYSCALL_GETPGID, [pid, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_prlimit64(pid: usize, resource: i32, new_limit: usize, old_limit: usize) -> isize {
+    syscall(SYSCALL_PRLIMIT64, [pid, resource as usize, new_limit, old_limit, 0, 0])
+}
+
+pub fn sys_getppid() -> isize {
+    syscall(SYSCALL_GETPPID, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0, 0, 0, 0])
+}
+
 pub fn sys_fork() -> isize {
-    syscall(SYSCALL_CLONE, [0, 0, 0, 0, 0, 0])
+    // SIGCHLD as the exit signal, the way fork(2) is built on clone
+    syscall(SYSCALL_CLONE, [17, 0, 0, 0, 0, 0])
 }
 pub fn sys_clone(flags: usize, stack: usize, tls: usize) -> isize {
     syscall(
@@ -133,6 +566,62 @@ pub fn sys_clone(flags: usize, stack: usize, tls: usize) -> isize {
     )
 }
 
+pub fn sys_clone3(args: &CloneArgs, size: usize) -> isize {
+    syscall(SYSCALL_CLONE3, [args as *const _ as usize, size, 0, 0, 0, 0])
+}
+
+pub fn sys_pidfd_open(pid: isize, flags: u32) -> isize {
+    syscall(SYSCALL_PIDFD_OPEN, [pid as usize, flags as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_pidfd_send_signal(pidfd: usize, sig: i32, info: usize, flags: u32) -> isize {
+    syscall(SYSCALL_PIDFD_SEND_SIGNAL, [pidfd, sig as usize, info, flags as usize, 0, 0])
+}
+
+/// clone3 where the child starts running `entry(arg)` on its own stack
+/// instead of returning, the parent gets the child's tid
+#[cfg(target_arch="riscv64")]
+pub fn sys_clone3_entry(args: &CloneArgs, size: usize, entry: extern "C" fn(usize) -> !, arg: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            "bnez a0, 1f",
+            "mv a0, t1",
+            "jr t0",
+            "1:",
+            inlateout("a0") args as *const _ as usize => ret,
+            in("a1") size,
+            in("a7") SYSCALL_CLONE3,
+            in("t0") entry as usize,
+            in("t1") arg,
+        );
+    }
+    ret
+}
+
+/// clone3 where the child starts running `entry(arg)` on its own stack
+/// instead of returning, the parent gets the child's tid
+#[cfg(target_arch="loongarch64")]
+pub fn sys_clone3_entry(args: &CloneArgs, size: usize, entry: extern "C" fn(usize) -> !, arg: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "syscall 0",
+            "bnez $a0, 1f",
+            "move $a0, $t1",
+            "jirl $zero, $t0, 0",
+            "1:",
+            inlateout("$a0") args as *const _ as usize => ret,
+            in("$a1") size,
+            in("$a7") SYSCALL_CLONE3,
+            in("$t0") entry as usize,
+            in("$t1") arg,
+        );
+    }
+    ret
+}
+
 pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
     syscall(
         SYSCALL_EXECVE,
@@ -151,6 +640,13 @@ pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
     syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0, 0, 0, 0])
 }
 
+pub fn sys_wait4(pid: isize, wstatus: *mut i32, options: i32, rusage: *mut Rusage) -> isize {
+    syscall(
+        SYSCALL_WAITPID,
+        [pid as usize, wstatus as usize, options as usize, rusage as usize, 0, 0],
+    )
+}
+
 pub fn sys_sigaction(
     signum: i32,
     action: *const SignalAction,
@@ -172,10 +668,22 @@ pub fn sys_sigaction(
     */
 }
 
+pub fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> isize {
+    syscall(SYSCALL_SIGALTSTACK, [ss as usize, old_ss as usize, 0, 0, 0, 0])
+}
+
 pub fn sys_sigprocmask(mask: u32) -> isize {
     syscall(SYSCALL_SIGPROCMASK, [mask as usize, 0, 0, 0, 0, 0])
 }
 
+pub fn sys_rt_sigqueueinfo(pid: usize, signum: i32, info: *const SigInfo) -> isize {
+    syscall(SYSCALL_RT_SIGQUEUEINFO, [pid, signum as usize, info as usize, 0, 0, 0])
+}
+
+pub fn sys_rt_tgsigqueueinfo(tgid: usize, tid: usize, signum: i32, info: *const SigInfo) -> isize {
+    syscall(SYSCALL_RT_TGSIGQUEUEINFO, [tgid, tid, signum as usize, info as usize, 0, 0])
+}
+
 pub fn sys_sigreturn() -> isize {
     syscall(SYSCALL_SIGRETURN, [0, 0, 0, 0, 0, 0])
 }
@@ -212,14 +720,168 @@ pub fn sys_recvfrom(sockfd: i32, buf: *mut u8, len: usize, flags: i32, src_addr:
     syscall(SYSCALL_RECVFROM, [sockfd as usize, buf as usize, len, flags as usize, src_addr as usize, addrlen as usize])
 }
 
+pub fn sys_setsockopt(fd: usize, level: i32, optname: i32, optval: *const u8, optlen: u32) -> isize {
+    syscall(SYSCALL_SETSOCKOPT, [fd, level as usize, optname as usize, optval as usize, optlen as usize, 0])
+}
+
+pub fn sys_getsockopt(fd: usize, level: i32, optname: i32, optval: *mut u8, optlen: *mut u32) -> isize {
+    syscall(SYSCALL_GETSOCKOPT, [fd, level as usize, optname as usize, optval as usize, optlen as usize, 0])
+}
+
+pub fn sys_sendmsg(fd: usize, msg: *const MsgHdr, flags: i32) -> isize {
+    syscall(SYSCALL_SENDMSG, [fd, msg as usize, flags as usize, 0, 0, 0])
+}
+
+pub fn sys_recvmsg(fd: usize, msg: *mut MsgHdr, flags: i32) -> isize {
+    syscall(SYSCALL_RECVMSG, [fd, msg as usize, flags as usize, 0, 0, 0])
+}
+
 pub fn sys_mmap(addr: usize, len: usize, prot: i32, flags: i32, fd: usize, offset: usize) -> isize {
     syscall(SYSCALL_MMAP, [addr, len, prot as _, flags as _, fd, offset])
 }
 
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [addr, len, 0, 0, 0, 0])
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: i32) -> isize {
+    syscall(SYSCALL_MPROTECT, [addr, len, prot as _, 0, 0, 0])
+}
+
+pub fn sys_madvise(addr: usize, len: usize, advice: usize) -> isize {
+    syscall(SYSCALL_MADVISE, [addr, len, advice, 0, 0, 0])
+}
+
+pub fn sys_membarrier(cmd: i32, flags: u32, cpu_id: i32) -> isize {
+    syscall(SYSCALL_MEMBARRIER, [cmd as usize, flags as usize, cpu_id as usize, 0, 0, 0])
+}
+
+pub fn sys_mseal(addr: usize, len: usize, flags: usize) -> isize {
+    syscall(SYSCALL_MSEAL, [addr, len, flags, 0, 0, 0])
+}
+
+pub fn sys_mlock(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MLOCK, [addr, len, 0, 0, 0, 0])
+}
+
+pub fn sys_mlock2(addr: usize, len: usize, flags: u32) -> isize {
+    syscall(SYSCALL_MLOCK2, [addr, len, flags as usize, 0, 0, 0])
+}
+
+pub fn sys_munlock(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNLOCK, [addr, len, 0, 0, 0, 0])
+}
+
+pub fn sys_mlockall(flags: i32) -> isize {
+    syscall(SYSCALL_MLOCKALL, [flags as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_munlockall() -> isize {
+    syscall(SYSCALL_MUNLOCKALL, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_mincore(addr: usize, len: usize, vec: *mut u8) -> isize {
+    syscall(SYSCALL_MINCORE, [addr, len, vec as usize, 0, 0, 0])
+}
+
+pub fn sys_mbind(addr: usize, len: usize, mode: i32, nodemask: *const u64, maxnode: usize, flags: u32) -> isize {
+    syscall(SYSCALL_MBIND, [addr, len, mode as usize, nodemask as usize, maxnode, flags as usize])
+}
+
+pub fn sys_get_mempolicy(mode: *mut i32, nodemask: *mut u64, maxnode: usize, addr: usize, flags: u32) -> isize {
+    syscall(SYSCALL_GET_MEMPOLICY, [mode as usize, nodemask as usize, maxnode, addr, flags as usize, 0])
+}
+
+pub fn sys_set_mempolicy(mode: i32, nodemask: *const u64, maxnode: usize) -> isize {
+    syscall(SYSCALL_SET_MEMPOLICY, [mode as usize, nodemask as usize, maxnode, 0, 0, 0])
+}
+
 pub fn sys_mremap(old_addr: usize, old_size: usize, new_size: usize, flags: i32, new_addr:usize) -> isize {
     syscall(SYSCALL_MREMAP, [old_addr, old_size, new_size, flags as _, new_addr, 0])
 }
 
 pub fn sys_shutdown(magic1: i32, magic2: i32, cmd: u32, args: usize) -> isize {
     syscall(SYSCALL_REBOOT, [magic1 as _, magic2 as _, cmd as _, args, 0, 0])
-}
\ No newline at end of file
+}
+pub fn sys_statmount(req: usize, buf: &mut [u8], flags: usize) -> isize {
+    syscall(SYSCALL_STATMOUNT, [req, buf.as_mut_ptr() as usize, buf.len(), flags, 0, 0])
+}
+
+pub fn sys_listmount(req: usize, mnt_ids: &mut [u64], flags: usize) -> isize {
+    syscall(SYSCALL_LISTMOUNT, [req, mnt_ids.as_mut_ptr() as usize, mnt_ids.len(), flags, 0, 0])
+}
+
+pub fn sys_fsopen(fstype: &str, flags: u32) -> isize {
+    syscall(SYSCALL_FSOPEN, [fstype.as_ptr() as usize, flags as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_fsconfig(fd: usize, cmd: u32, key: usize, value: usize, aux: i32) -> isize {
+    syscall(SYSCALL_FSCONFIG, [fd, cmd as usize, key, value, aux as usize, 0])
+}
+
+pub fn sys_fsmount(fs_fd: usize, flags: u32, attr_flags: u32) -> isize {
+    syscall(SYSCALL_FSMOUNT, [fs_fd, flags as usize, attr_flags as usize, 0, 0, 0])
+}
+
+pub fn sys_move_mount(from_dirfd: isize, from_path: &str, to_dirfd: isize, to_path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_MOVE_MOUNT,
+        [from_dirfd as usize, from_path.as_ptr() as usize, to_dirfd as usize, to_path.as_ptr() as usize, flags as usize, 0],
+    )
+}
+
+pub fn sys_process_vm_readv(pid: usize, local: &[IoVec], remote: &[IoVec], flags: usize) -> isize {
+    syscall(SYSCALL_PROCESS_VM_READV, [pid, local.as_ptr() as usize, local.len(), remote.as_ptr() as usize, remote.len(), flags])
+}
+
+pub fn sys_process_vm_writev(pid: usize, local: &[IoVec], remote: &[IoVec], flags: usize) -> isize {
+    syscall(SYSCALL_PROCESS_VM_WRITEV, [pid, local.as_ptr() as usize, local.len(), remote.as_ptr() as usize, remote.len(), flags])
+}
+
+pub fn sys_memfd_secret(flags: u32) -> isize {
+    syscall(SYSCALL_MEMFD_SECRET, [flags as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_set_robust_list(head: usize, len: usize) -> isize {
+    syscall(SYSCALL_SET_ROBUST_LIST, [head, len, 0, 0, 0, 0])
+}
+
+pub fn sys_get_robust_list(pid: usize, head: &mut usize, len: &mut usize) -> isize {
+    syscall(SYSCALL_GET_ROBUST_LIST, [pid, head as *mut _ as usize, len as *mut _ as usize, 0, 0, 0])
+}
+
+pub fn sys_sched_setscheduler(pid: usize, policy: i32, param: &SchedParam) -> isize {
+    syscall(SYSCALL_SCHED_SETSCHEDULER, [pid, policy as usize, param as *const _ as usize, 0, 0, 0])
+}
+
+pub fn sys_sched_getscheduler(pid: usize) -> isize {
+    syscall(SYSCALL_SCHED_GETSCHEDULER, [pid, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_sched_setparam(pid: usize, param: &SchedParam) -> isize {
+    syscall(SYSCALL_SCHED_SETPARAM, [pid, param as *const _ as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_sched_getparam(pid: usize, param: &mut SchedParam) -> isize {
+    syscall(SYSCALL_SCHED_GETPARAM, [pid, param as *mut _ as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_sched_get_priority_max(policy: i32) -> isize {
+    syscall(SYSCALL_SCHED_GET_PRIORITY_MAX, [policy as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_sched_get_priority_min(policy: i32) -> isize {
+    syscall(SYSCALL_SCHED_GET_PRIORITY_MIN, [policy as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_setpriority(which: usize, who: usize, prio: i32) -> isize {
+    syscall(SYSCALL_SETPRIORITY, [which, who, prio as usize, 0, 0, 0])
+}
+
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    syscall(SYSCALL_GETPRIORITY, [which, who, 0, 0, 0, 0])
+}
+
+pub fn sys_setuid(uid: u32) -> isize {
+    syscall(SYSCALL_SETUID, [uid as usize, 0, 0, 0, 0, 0])
+}